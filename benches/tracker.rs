@@ -0,0 +1,35 @@
+use std::time::SystemTime;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use window_tracker_concept::{add_or_update_window, wt_init, wt_update, ActiveWindow};
+
+fn bench_add_or_update_window(c: &mut Criterion) {
+    wt_init();
+    let titles: Vec<ActiveWindow> = (0..1000)
+        .map(|i| ActiveWindow { title: format!("Document {} - Editor", i), app: "editor".to_string(), class: "Editor".to_string(), id: i, pid: 1 })
+        .collect();
+
+    c.bench_function("add_or_update_window/existing", |b| {
+        add_or_update_window(&titles[0], SystemTime::now());
+        b.iter(|| add_or_update_window(black_box(&titles[0]), SystemTime::now()))
+    });
+
+    c.bench_function("add_or_update_window/1000_titles", |b| {
+        let mut i = 0;
+        b.iter(|| {
+            add_or_update_window(black_box(&titles[i % titles.len()]), SystemTime::now());
+            i += 1;
+        })
+    });
+}
+
+// Dominated by the platform query, so this is the per-sample cost of the tracking loop
+fn bench_wt_update(c: &mut Criterion) {
+    // Focus changes get recorded, keep them out of the real history
+    std::env::set_var("WT_DATA_DIR", std::env::temp_dir().join("window_tracker_bench"));
+    wt_init();
+    c.bench_function("wt_update", |b| b.iter(wt_update));
+}
+
+criterion_group!(benches, bench_add_or_update_window, bench_wt_update);
+criterion_main!(benches);
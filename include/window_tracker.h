@@ -0,0 +1,50 @@
+/* C interface to the window tracker library (the cdylib built from this crate). */
+#ifndef WINDOW_TRACKER_H
+#define WINDOW_TRACKER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define WT_FOCUS_CHANGED 0
+#define WT_TITLE_CHANGED 1
+
+/* The strings passed to callbacks are only valid for the duration of the call. */
+typedef void (*wt_focus_callback)(void *user_data, int event, const char *title, const char *app);
+typedef void (*wt_focus_callback_utf16)(void *user_data, int event, const uint16_t *title, const uint16_t *app);
+
+void wt_init(void);
+void wt_update(void);
+void wt_cleanup(void);
+
+size_t wt_get_window_count(void);
+double wt_get_focus_seconds(void);
+double wt_get_today_seconds(void);
+/* Changes whenever the current window or its title does; poll it instead of comparing titles. */
+uint64_t wt_get_current_id(void);
+/* Negative when the platform can't tell. */
+double wt_get_idle_seconds(void);
+
+/* Called on the thread running wt_update(); pass NULL to unregister. */
+void wt_set_focus_callback(wt_focus_callback callback, void *user_data);
+void wt_set_focus_callback_utf16(wt_focus_callback_utf16 callback, void *user_data);
+
+/* 0 on success, -1 when index is past the last window. Free the title with the matching wt_free_string*. */
+int wt_get_window_info(size_t index, char **title, double *focus_time);
+int wt_get_window_info_utf16(size_t index, uint16_t **title, double *focus_time);
+
+/* The report for the last days as JSON, NULL on failure. Free with the matching wt_free_string*. */
+char *wt_report_json(uint32_t days);
+uint16_t *wt_report_json_utf16(uint32_t days);
+
+void wt_free_string(char *text);
+void wt_free_string_utf16(uint16_t *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,137 @@
+# Deutsch
+
+weekday-mon = Mo
+weekday-tue = Di
+weekday-wed = Mi
+weekday-thu = Do
+weekday-fri = Fr
+weekday-sat = Sa
+weekday-sun = So
+day-today = Heute
+day-yesterday = Gestern
+
+duration-hours-one = { $n } Stunde
+duration-hours-other = { $n } Stunden
+duration-minutes-one = { $n } Minute
+duration-minutes-other = { $n } Minuten
+duration-seconds-one = { $n } Sekunde
+duration-seconds-other = { $n } Sekunden
+duration-join = { $text }, { $next }
+
+report-title = Fenster-Tracking-Bericht ({ $period })
+report-period = { $from } bis { $to }
+report-total = Fokuszeit gesamt: { $time }
+report-switches = Fensterwechsel: { $switches } (plus { $title_changes } Titeländerungen)
+report-daily = Fokuszeit pro Tag:
+report-top-windows = Meistgenutzte Fenster:
+report-window = Fenster: { $title }
+report-app = App: { $app }
+report-top-apps = Meistgenutzte Apps:
+report-top-classes = Meistgenutzte Fensterklassen:
+report-top-categories = Meistgenutzte Kategorien:
+report-class = Fensterklasse: { $class }
+report-category = Kategorie: { $category }
+report-top-field = Häufigste Werte von { $field }:
+report-field = { $field }: { $value }
+report-focus-time = Fokuszeit: { $time }
+report-unusual = Ungewöhnliche Nutzung:
+report-calls = Anrufe: { $time } mit eingeschalteter Kamera oder Mikrofon
+report-in-call = Im Anruf: { $call }, nur geöffnet { $open }
+report-languages = Programmierzeit nach Sprache:
+report-blocks = Fokusblöcke in Editor und Terminal:
+report-no-pushes = keine Pushes
+report-background = Im Hintergrund geöffnet:
+report-visible = Sichtbar: { $visible }, aktiv { $active }, im Hintergrund { $background }
+report-media = Gleichzeitige Medien:
+report-on-top = Über anderen Fenstern: { $time }
+report-html-written = HTML-Bericht nach { $path } geschrieben
+report-emailed = Bericht an { $address } gesendet
+
+plain-item = { $index }. { $name }: { $text }
+plain-range = { $start } bis { $end }
+
+compare-day = Vergleich: heute ({ $current }) gegenüber gestern ({ $previous })
+compare-week = Vergleich: diese Woche ({ $current }) gegenüber letzter Woche ({ $previous })
+compare-total = Fokuszeit gesamt: { $time } ({ $change } gegenüber { $before })
+compare-focus-time = Fokuszeit: { $time } ({ $difference }, { $change })
+compare-was = Fokuszeit: { $time } (vorher { $before })
+compare-new = (neu)
+compare-gone = (weggefallen)
+change-new = neu
+change-none = unverändert
+
+anomaly-unusual = { $day } { $part }: { $time } in { $app } (üblich sind { $typical })
+anomaly-missing = { $day }: keine Zeit in { $app } (üblich sind { $typical })
+part-night = nachts
+part-morning = morgens
+part-afternoon = nachmittags
+part-evening = abends
+
+notify-unusual = Ungewöhnliche Nutzung erkannt
+greeting-title = Willkommen zurück
+greeting-body = { $day }: { $hours }, meistgenutzte App: { $app }
+alert-limit-title = { $category }-Limit überschritten
+alert-limit-body = { $time } von { $goal } heute
+alert-idle-title = Aufzeichnung läuft
+alert-idle-body = Seit { $time } keine Eingabe, { $app } wird weiter aufgezeichnet
+alert-snooze = { $mins } Min. später
+alert-recategorize = Als { $category } einordnen
+alert-pause = Aufzeichnung pausieren
+limit-warn-title = Heute noch { $left } in { $app }
+limit-warn-body = { $time } vom Tageslimit { $limit } verbraucht
+limit-reached-title = Die Zeit für { $app } ist um
+limit-reached-warn = Das Tageslimit von { $limit } ist verbraucht
+limit-reached-minimize = Das Tageslimit von { $limit } ist verbraucht, das Fenster wird in { $grace } minimiert
+limit-reached-close = Das Tageslimit von { $limit } ist verbraucht, bitte speichern: das Fenster wird in { $grace } geschlossen
+limit-reached-block = Das Tageslimit von { $limit } ist verbraucht, das Fenster nimmt in { $grace } keine Eingaben mehr an
+
+html-title = Fenster-Tracking-Bericht { $period }
+html-heading = Fenster-Tracking-Bericht
+html-meta = { $period } · { $time } Fokuszeit in { $windows } Fenstern
+html-daily = Fokuszeit pro Tag
+html-top-windows = Meistgenutzte Fenster
+html-window = Fenster
+html-top-apps = Meistgenutzte Apps
+html-top-classes = Meistgenutzte Fensterklassen
+html-top-categories = Meistgenutzte Kategorien
+html-class = Fensterklasse
+html-category = Kategorie
+html-top-field = Häufigste Werte von { $field }
+html-focus-time = Fokuszeit
+html-share = Anteil
+html-empty = In diesem Zeitraum wurde keine Fokuszeit aufgezeichnet.
+html-unusual = Ungewöhnliche Nutzung
+html-calls = Anrufe
+html-calls-meta = { $time } mit eingeschalteter Kamera oder Mikrofon
+html-app = App
+html-in-call = Im Anruf
+html-just-open = Nur geöffnet
+html-languages = Programmierzeit nach Sprache
+html-language = Sprache
+html-blocks = Fokusblöcke in Editor und Terminal
+html-block = Block
+html-repos = Repositorys
+html-background = Im Hintergrund geöffnet
+html-visible = Sichtbar
+html-active = Aktiv
+html-background-time = Hintergrund
+html-media = Gleichzeitige Medien
+html-on-top = Im Vordergrund
+html-generated = Erstellt am { $time }
+
+status-title = Aktueller Tracking-Status:
+status-windows = Erfasste Fenster: { $count }
+status-shared = In { $count } Fenstern gesehen
+status-evicted = Aus dem Speicher entfernt, weiter im Verlauf: { $count } veraltete Fenster
+status-goals = Ziele heute:
+status-goal-target = { $time } vom Ziel { $goal }
+status-goal-limit = { $time } vom Limit { $goal }
+today-total = Heute: { $time }
+current-window = { $title } - { $app } ({ $time })
+current-window-call = { $title } - { $app } ({ $time }, im Anruf)
+current-paused = Pausiert
+current-stopped = Tracking läuft nicht
+tracking-paused = Tracking pausiert
+tracking-resumed = Tracking fortgesetzt
+menubar-resume = Aufzeichnung fortsetzen
+menubar-quit = Beenden
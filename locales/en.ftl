@@ -0,0 +1,137 @@
+# English, the fallback for keys missing from the other catalogs
+
+weekday-mon = Mon
+weekday-tue = Tue
+weekday-wed = Wed
+weekday-thu = Thu
+weekday-fri = Fri
+weekday-sat = Sat
+weekday-sun = Sun
+day-today = Today
+day-yesterday = Yesterday
+
+duration-hours-one = { $n } hour
+duration-hours-other = { $n } hours
+duration-minutes-one = { $n } minute
+duration-minutes-other = { $n } minutes
+duration-seconds-one = { $n } second
+duration-seconds-other = { $n } seconds
+duration-join = { $text }, { $next }
+
+report-title = Window tracking report ({ $period })
+report-period = { $from } to { $to }
+report-total = Total focus time: { $time }
+report-switches = Window switches: { $switches } (plus { $title_changes } title changes)
+report-daily = Daily focus time:
+report-top-windows = Top windows:
+report-window = Window: { $title }
+report-app = App: { $app }
+report-top-apps = Top apps:
+report-top-classes = Top window classes:
+report-top-categories = Top categories:
+report-class = Window class: { $class }
+report-category = Category: { $category }
+report-top-field = Top { $field } values:
+report-field = { $field }: { $value }
+report-focus-time = Focus time: { $time }
+report-unusual = Unusual usage:
+report-calls = Calls: { $time } with the camera or microphone on
+report-in-call = In a call: { $call }, just open { $open }
+report-languages = Coding time by language:
+report-blocks = Editor and terminal focus blocks:
+report-no-pushes = no pushes
+report-background = Open in the background:
+report-visible = Visible: { $visible }, active { $active }, background { $background }
+report-media = Concurrent media:
+report-on-top = On top of other windows: { $time }
+report-html-written = HTML report written to { $path }
+report-emailed = Report emailed to { $address }
+
+plain-item = { $index }. { $name }: { $text }
+plain-range = { $start } to { $end }
+
+compare-day = Comparison: today ({ $current }) vs yesterday ({ $previous })
+compare-week = Comparison: this week ({ $current }) vs last week ({ $previous })
+compare-total = Total focus time: { $time } ({ $change } vs { $before })
+compare-focus-time = Focus time: { $time } ({ $difference }, { $change })
+compare-was = Focus time: { $time } (was { $before })
+compare-new = (new)
+compare-gone = (gone)
+change-new = new
+change-none = no change
+
+anomaly-unusual = { $day } { $part }: { $time } in { $app } (typically { $typical })
+anomaly-missing = { $day }: no time in { $app } (typically { $typical })
+part-night = night
+part-morning = morning
+part-afternoon = afternoon
+part-evening = evening
+
+notify-unusual = Unusual usage detected
+greeting-title = Welcome back
+greeting-body = { $day }: { $hours }, top app: { $app }
+alert-limit-title = Over the { $category } limit
+alert-limit-body = { $time } of { $goal } today
+alert-idle-title = Still tracking
+alert-idle-body = No input for { $time }, still recording { $app }
+alert-snooze = Snooze { $mins }m
+alert-recategorize = Recategorize as { $category }
+alert-pause = Pause tracking
+limit-warn-title = { $left } left in { $app } today
+limit-warn-body = { $time } of the { $limit } daily limit used
+limit-reached-title = Time's up for { $app }
+limit-reached-warn = The { $limit } daily limit is used up
+limit-reached-minimize = The { $limit } daily limit is used up, the window is minimized in { $grace }
+limit-reached-close = The { $limit } daily limit is used up, save your work: the window closes in { $grace }
+limit-reached-block = The { $limit } daily limit is used up, the window stops taking input in { $grace }
+
+html-title = Window tracker report { $period }
+html-heading = Window tracker report
+html-meta = { $period } · { $time } focused across { $windows } windows
+html-daily = Daily focus time
+html-top-windows = Top windows
+html-window = Window
+html-top-apps = Top apps
+html-top-classes = Top window classes
+html-top-categories = Top categories
+html-class = Window class
+html-category = Category
+html-top-field = Top { $field } values
+html-focus-time = Focus time
+html-share = Share
+html-empty = No focus time was recorded in this period.
+html-unusual = Unusual usage
+html-calls = Calls
+html-calls-meta = { $time } with the camera or microphone on
+html-app = App
+html-in-call = In a call
+html-just-open = Just open
+html-languages = Coding time by language
+html-language = Language
+html-blocks = Editor and terminal focus blocks
+html-block = Block
+html-repos = Repos
+html-background = Open in the background
+html-visible = Visible
+html-active = Active
+html-background-time = Background
+html-media = Concurrent media
+html-on-top = On top
+html-generated = Generated { $time }
+
+status-title = Current window tracking status:
+status-windows = Number of tracked windows: { $count }
+status-shared = Seen in { $count } windows
+status-evicted = Dropped from memory, still in the history: { $count } stale windows
+status-goals = Goals today:
+status-goal-target = { $time } of the { $goal } goal
+status-goal-limit = { $time } of the { $goal } limit
+today-total = Today: { $time }
+current-window = { $title } - { $app } ({ $time })
+current-window-call = { $title } - { $app } ({ $time }, in a call)
+current-paused = Paused
+current-stopped = Not tracking
+tracking-paused = Tracking paused
+tracking-resumed = Tracking resumed
+menubar-resume = Resume tracking
+menubar-quit = Quit
@@ -0,0 +1,137 @@
+# Español
+
+weekday-mon = lun
+weekday-tue = mar
+weekday-wed = mié
+weekday-thu = jue
+weekday-fri = vie
+weekday-sat = sáb
+weekday-sun = dom
+day-today = Hoy
+day-yesterday = Ayer
+
+duration-hours-one = { $n } hora
+duration-hours-other = { $n } horas
+duration-minutes-one = { $n } minuto
+duration-minutes-other = { $n } minutos
+duration-seconds-one = { $n } segundo
+duration-seconds-other = { $n } segundos
+duration-join = { $text }, { $next }
+
+report-title = Informe de seguimiento de ventanas ({ $period })
+report-period = del { $from } al { $to }
+report-total = Tiempo de foco total: { $time }
+report-switches = Cambios de ventana: { $switches } (más { $title_changes } cambios de título)
+report-daily = Tiempo de foco por día:
+report-top-windows = Ventanas principales:
+report-window = Ventana: { $title }
+report-app = Aplicación: { $app }
+report-top-apps = Aplicaciones principales:
+report-top-classes = Clases de ventana principales:
+report-top-categories = Categorías principales:
+report-class = Clase de ventana: { $class }
+report-category = Categoría: { $category }
+report-top-field = Valores principales de { $field }:
+report-field = { $field }: { $value }
+report-focus-time = Tiempo de foco: { $time }
+report-unusual = Uso inusual:
+report-calls = Llamadas: { $time } con la cámara o el micrófono activos
+report-in-call = En llamada: { $call }, solo abierta { $open }
+report-languages = Tiempo de programación por lenguaje:
+report-blocks = Bloques de foco en editor y terminal:
+report-no-pushes = sin pushes
+report-background = Abiertas en segundo plano:
+report-visible = Visible: { $visible }, activa { $active }, en segundo plano { $background }
+report-media = Multimedia simultánea:
+report-on-top = Encima de otras ventanas: { $time }
+report-html-written = Informe HTML escrito en { $path }
+report-emailed = Informe enviado a { $address }
+
+plain-item = { $index }. { $name }: { $text }
+plain-range = de { $start } a { $end }
+
+compare-day = Comparación: hoy ({ $current }) frente a ayer ({ $previous })
+compare-week = Comparación: esta semana ({ $current }) frente a la semana pasada ({ $previous })
+compare-total = Tiempo de foco total: { $time } ({ $change } frente a { $before })
+compare-focus-time = Tiempo de foco: { $time } ({ $difference }, { $change })
+compare-was = Tiempo de foco: { $time } (antes { $before })
+compare-new = (nueva)
+compare-gone = (desaparecida)
+change-new = nueva
+change-none = sin cambios
+
+anomaly-unusual = { $day } por la { $part }: { $time } en { $app } (normalmente { $typical })
+anomaly-missing = { $day }: sin tiempo en { $app } (normalmente { $typical })
+part-night = madrugada
+part-morning = mañana
+part-afternoon = tarde
+part-evening = noche
+
+notify-unusual = Uso inusual detectado
+greeting-title = Bienvenido de nuevo
+greeting-body = { $day }: { $hours }, aplicación principal: { $app }
+alert-limit-title = Límite de { $category } superado
+alert-limit-body = { $time } de { $goal } hoy
+alert-idle-title = Seguimiento activo
+alert-idle-body = Sin actividad durante { $time }, se sigue registrando { $app }
+alert-snooze = Posponer { $mins } min
+alert-recategorize = Recategorizar como { $category }
+alert-pause = Pausar seguimiento
+limit-warn-title = Quedan { $left } de { $app } hoy
+limit-warn-body = { $time } usados del límite diario de { $limit }
+limit-reached-title = Se acabó el tiempo de { $app }
+limit-reached-warn = El límite diario de { $limit } se ha agotado
+limit-reached-minimize = El límite diario de { $limit } se ha agotado, la ventana se minimizará en { $grace }
+limit-reached-close = El límite diario de { $limit } se ha agotado, guarda tu trabajo: la ventana se cerrará en { $grace }
+limit-reached-block = El límite diario de { $limit } se ha agotado, la ventana dejará de aceptar entradas en { $grace }
+
+html-title = Informe de seguimiento de ventanas { $period }
+html-heading = Informe de seguimiento de ventanas
+html-meta = { $period } · { $time } de foco en { $windows } ventanas
+html-daily = Tiempo de foco por día
+html-top-windows = Ventanas principales
+html-window = Ventana
+html-top-apps = Aplicaciones principales
+html-top-classes = Clases de ventana principales
+html-top-categories = Categorías principales
+html-class = Clase de ventana
+html-category = Categoría
+html-top-field = Valores principales de { $field }
+html-focus-time = Tiempo de foco
+html-share = Proporción
+html-empty = No se registró tiempo de foco en este periodo.
+html-unusual = Uso inusual
+html-calls = Llamadas
+html-calls-meta = { $time } con la cámara o el micrófono activos
+html-app = Aplicación
+html-in-call = En llamada
+html-just-open = Solo abierta
+html-languages = Tiempo de programación por lenguaje
+html-language = Lenguaje
+html-blocks = Bloques de foco en editor y terminal
+html-block = Bloque
+html-repos = Repositorios
+html-background = Abiertas en segundo plano
+html-visible = Visible
+html-active = Activa
+html-background-time = Segundo plano
+html-media = Multimedia simultánea
+html-on-top = Encima
+html-generated = Generado el { $time }
+
+status-title = Estado actual del seguimiento:
+status-windows = Ventanas registradas: { $count }
+status-shared = Vista en { $count } ventanas
+status-evicted = Eliminadas de la memoria, siguen en el historial: { $count } ventanas inactivas
+status-goals = Objetivos de hoy:
+status-goal-target = { $time } del objetivo de { $goal }
+status-goal-limit = { $time } del límite de { $goal }
+today-total = Hoy: { $time }
+current-window = { $title } - { $app } ({ $time })
+current-window-call = { $title } - { $app } ({ $time }, en llamada)
+current-paused = En pausa
+current-stopped = Sin seguimiento
+tracking-paused = Seguimiento en pausa
+tracking-resumed = Seguimiento reanudado
+menubar-resume = Reanudar seguimiento
+menubar-quit = Salir
@@ -0,0 +1,137 @@
+# 日本語
+
+weekday-mon = 月
+weekday-tue = 火
+weekday-wed = 水
+weekday-thu = 木
+weekday-fri = 金
+weekday-sat = 土
+weekday-sun = 日
+day-today = 今日
+day-yesterday = 昨日
+
+duration-hours-one = { $n } 時間
+duration-hours-other = { $n } 時間
+duration-minutes-one = { $n } 分
+duration-minutes-other = { $n } 分
+duration-seconds-one = { $n } 秒
+duration-seconds-other = { $n } 秒
+duration-join = { $text } { $next }
+
+report-title = ウィンドウ記録レポート（{ $period }）
+report-period = { $from }〜{ $to }
+report-total = 合計フォーカス時間: { $time }
+report-switches = ウィンドウ切り替え: { $switches } 回（タイトル変更 { $title_changes } 回）
+report-daily = 日別フォーカス時間:
+report-top-windows = よく使うウィンドウ:
+report-window = ウィンドウ: { $title }
+report-app = アプリ: { $app }
+report-top-apps = よく使うアプリ:
+report-top-classes = よく使うウィンドウクラス:
+report-top-categories = よく使うカテゴリ:
+report-class = ウィンドウクラス: { $class }
+report-category = カテゴリ: { $category }
+report-top-field = よく使う { $field } の値:
+report-field = { $field }: { $value }
+report-focus-time = フォーカス時間: { $time }
+report-unusual = 普段と異なる利用:
+report-calls = 通話: カメラまたはマイク使用中 { $time }
+report-in-call = 通話中: { $call }、開いていただけ: { $open }
+report-languages = 言語別コーディング時間:
+report-blocks = エディターとターミナルの集中ブロック:
+report-no-pushes = プッシュなし
+report-background = バックグラウンドで開いていたアプリ:
+report-visible = 表示: { $visible }、アクティブ: { $active }、バックグラウンド: { $background }
+report-media = 同時再生メディア:
+report-on-top = 他のウィンドウの前面: { $time }
+report-html-written = HTML レポートを { $path } に書き出しました
+report-emailed = レポートを { $address } に送信しました
+
+plain-item = { $index }. { $name }: { $text }
+plain-range = { $start }〜{ $end }
+
+compare-day = 比較: 今日（{ $current }）と昨日（{ $previous }）
+compare-week = 比較: 今週（{ $current }）と先週（{ $previous }）
+compare-total = 合計フォーカス時間: { $time }（{ $change }、前回 { $before }）
+compare-focus-time = フォーカス時間: { $time }（{ $difference }、{ $change }）
+compare-was = フォーカス時間: { $time }（前回 { $before }）
+compare-new = （新規）
+compare-gone = （利用なし）
+change-new = 新規
+change-none = 変化なし
+
+anomaly-unusual = { $day } { $part }: { $app } で { $time }（通常は { $typical }）
+anomaly-missing = { $day }: { $app } の利用なし（通常は { $typical }）
+part-night = 夜間
+part-morning = 午前
+part-afternoon = 午後
+part-evening = 夕方
+
+notify-unusual = 普段と異なる利用を検出しました
+greeting-title = おかえりなさい
+greeting-body = { $day }: { $hours }、最も使ったアプリ: { $app }
+alert-limit-title = { $category } の上限を超えました
+alert-limit-body = 今日 { $goal } 中 { $time }
+alert-idle-title = 記録中です
+alert-idle-body = { $time } 操作がありませんが、{ $app } の記録を続けています
+alert-snooze = { $mins } 分後に再通知
+alert-recategorize = { $category } に分類
+alert-pause = 記録を一時停止
+limit-warn-title = 今日の { $app } は残り { $left }
+limit-warn-body = 1日の上限 { $limit } のうち { $time } を使用
+limit-reached-title = { $app } の時間は終わりです
+limit-reached-warn = 1日の上限 { $limit } を使い切りました
+limit-reached-minimize = 1日の上限 { $limit } を使い切りました。{ $grace } 後にウィンドウを最小化します
+limit-reached-close = 1日の上限 { $limit } を使い切りました。作業を保存してください。{ $grace } 後にウィンドウを閉じます
+limit-reached-block = 1日の上限 { $limit } を使い切りました。{ $grace } 後にウィンドウは入力を受け付けなくなります
+
+html-title = ウィンドウ記録レポート { $period }
+html-heading = ウィンドウ記録レポート
+html-meta = { $period } · { $windows } 個のウィンドウで合計 { $time }
+html-daily = 日別フォーカス時間
+html-top-windows = よく使うウィンドウ
+html-window = ウィンドウ
+html-top-apps = よく使うアプリ
+html-top-classes = よく使うウィンドウクラス
+html-top-categories = よく使うカテゴリ
+html-class = ウィンドウクラス
+html-category = カテゴリ
+html-top-field = よく使う { $field } の値
+html-focus-time = フォーカス時間
+html-share = 割合
+html-empty = この期間にはフォーカス時間が記録されていません。
+html-unusual = 普段と異なる利用
+html-calls = 通話
+html-calls-meta = カメラまたはマイク使用中 { $time }
+html-app = アプリ
+html-in-call = 通話中
+html-just-open = 開いていただけ
+html-languages = 言語別コーディング時間
+html-language = 言語
+html-blocks = エディターとターミナルの集中ブロック
+html-block = ブロック
+html-repos = リポジトリ
+html-background = バックグラウンドで開いていたアプリ
+html-visible = 表示
+html-active = アクティブ
+html-background-time = バックグラウンド
+html-media = 同時再生メディア
+html-on-top = 前面
+html-generated = { $time } に作成
+
+status-title = 現在の記録状況:
+status-windows = 記録中のウィンドウ数: { $count }
+status-shared = { $count } 個のウィンドウで表示
+status-evicted = メモリから削除（履歴には残っています）: 古いウィンドウ { $count } 個
+status-goals = 今日の目標:
+status-goal-target = 目標 { $goal } のうち { $time }
+status-goal-limit = 上限 { $goal } のうち { $time }
+today-total = 今日: { $time }
+current-window = { $title } - { $app }（{ $time }）
+current-window-call = { $title } - { $app }（{ $time }、通話中）
+current-paused = 一時停止中
+current-stopped = 記録していません
+tracking-paused = 記録を一時停止しました
+tracking-resumed = 記録を再開しました
+menubar-resume = 記録を再開
+menubar-quit = 終了
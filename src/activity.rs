@@ -0,0 +1,291 @@
+// `import-activity`: commits pushed and pull/merge requests opened on GitHub and GitLab, kept in
+// activity.jsonl so reports can say which repos an editor or terminal focus block went into.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::cli::Args;
+use crate::config::{self, ActivityConfig};
+use crate::storage::{self, Interval};
+
+const ACTIVITY_FILE: &str = "activity.jsonl";
+// Focus in the same editor or terminal apps with shorter breaks than this is one block
+const BLOCK_GAP_SECS: i64 = 300;
+const MIN_BLOCK_SECS: f64 = 300.0;
+// Pushes usually come right after the work, so they still count for the block this long after it
+const PUSH_SLACK_SECS: i64 = 900;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Activity {
+    pub source: String,
+    pub id: String,
+    pub repo: String,
+    // push, pull_request or merge_request
+    pub kind: String,
+    pub at: DateTime<Utc>,
+}
+
+pub struct FocusBlock {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub secs: f64,
+    pub repos: Vec<String>,
+}
+
+fn activity_path() -> PathBuf {
+    storage::data_dir().join(ACTIVITY_FILE)
+}
+
+// Everything imported so far, oldest first
+pub fn load() -> Result<Vec<Activity>, String> {
+    let text = match fs::read_to_string(activity_path()) {
+        Ok(text) => text,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut activity = Vec::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(entry) => activity.push(entry),
+            Err(e) => eprintln!("Skipping unreadable activity entry: {}", e),
+        }
+    }
+    Ok(activity)
+}
+
+fn save(activity: &[Activity]) -> Result<(), String> {
+    let path = activity_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+    }
+    let lines: String = activity.iter().map(|entry| serde_json::to_string(entry).unwrap() + "\n").collect();
+    fs::write(&path, lines).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+fn is_dev_app(apps: &[String], app: &str) -> bool {
+    let app = app.to_lowercase();
+    let app = app.strip_suffix(".exe").unwrap_or(&app);
+    apps.iter().any(|name| name.eq_ignore_ascii_case(app))
+}
+
+// Runs of focus in editors and terminals, each labeled with the repos pushed to or opened requests
+// on while it lasted
+pub fn focus_blocks(intervals: &[Interval], activity: &[Activity], apps: &[String]) -> Vec<FocusBlock> {
+    let mut dev: Vec<&Interval> = intervals.iter().filter(|interval| is_dev_app(apps, &interval.app)).collect();
+    dev.sort_by_key(|interval| interval.start);
+
+    let mut blocks: Vec<FocusBlock> = Vec::new();
+    for interval in dev {
+        match blocks.last_mut() {
+            Some(block) if interval.start - block.end <= Duration::seconds(BLOCK_GAP_SECS) => {
+                block.end = block.end.max(interval.end);
+                block.secs += interval.duration_secs();
+            }
+            _ => blocks.push(FocusBlock { start: interval.start, end: interval.end, secs: interval.duration_secs(), repos: Vec::new() }),
+        }
+    }
+    blocks.retain(|block| block.secs >= MIN_BLOCK_SECS);
+
+    for block in &mut blocks {
+        let until = block.end + Duration::seconds(PUSH_SLACK_SECS);
+        let mut repos: Vec<String> = activity.iter()
+            .filter(|entry| entry.at >= block.start && entry.at <= until)
+            .map(|entry| entry.repo.clone())
+            .collect();
+        repos.sort();
+        repos.dedup();
+        block.repos = repos;
+    }
+    blocks
+}
+
+// GitHub's public event feed: PushEvent and PullRequestEvent entries
+pub fn parse_github(events: &Value) -> Vec<Activity> {
+    events.as_array().into_iter().flatten()
+        .filter_map(|event| {
+            let kind = match event["type"].as_str()? {
+                "PushEvent" => "push",
+                "PullRequestEvent" if event["payload"]["action"] == "opened" => "pull_request",
+                _ => return None,
+            };
+            Some(Activity {
+                source: "github".to_string(),
+                id: event["id"].as_str()?.to_string(),
+                repo: event["repo"]["name"].as_str()?.to_string(),
+                kind: kind.to_string(),
+                at: event["created_at"].as_str()?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+// GitLab's user events; they only carry a project id, `repos` maps it to the project path
+pub fn parse_gitlab(events: &Value, repos: &BTreeMap<u64, String>) -> Vec<Activity> {
+    events.as_array().into_iter().flatten()
+        .filter_map(|event| {
+            let action = event["action_name"].as_str()?;
+            let kind = if action.starts_with("pushed") {
+                "push"
+            } else if action == "opened" && event["target_type"] == "MergeRequest" {
+                "merge_request"
+            } else {
+                return None;
+            };
+            let project = event["project_id"].as_u64()?;
+            Some(Activity {
+                source: "gitlab".to_string(),
+                id: event["id"].as_u64()?.to_string(),
+                repo: repos.get(&project).cloned().unwrap_or_else(|| format!("project {}", project)),
+                kind: kind.to_string(),
+                at: event["created_at"].as_str()?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let config = config::load()?;
+    let activity_config = config.activity
+        .filter(|activity| activity.github.is_some() || activity.gitlab.is_some())
+        .ok_or("import-activity needs an [activity.github] or [activity.gitlab] section in config.toml")?;
+    let days: u32 = args.parsed("--days", 7)?;
+    if days == 0 {
+        return Err("--days must be at least 1".to_string());
+    }
+    let json_output = args.json_output()?;
+    let since = Local::now() - Duration::days(i64::from(days));
+
+    let mut imported = load()?;
+    let mut counts = Vec::new();
+    for (source, fetched) in fetch(&activity_config, since.with_timezone(&Utc)) {
+        let fetched = fetched.map_err(|e| format!("failed to import from {}: {}", source, e))?;
+        let before = imported.len();
+        for entry in fetched {
+            if !imported.iter().any(|known| known.source == entry.source && known.id == entry.id) {
+                imported.push(entry);
+            }
+        }
+        counts.push((source, imported.len() - before));
+    }
+    imported.sort_by_key(|entry| entry.at);
+    save(&imported)?;
+
+    if json_output {
+        let sources: Vec<Value> = counts.iter().map(|(source, new)| json!({ "source": source, "new": new })).collect();
+        println!("{}", json!({ "sources": sources, "total": imported.len() }));
+    } else {
+        for (source, new) in &counts {
+            println!("{}: {} new pushes and requests", source, new);
+        }
+        println!("{} imported in total", imported.len());
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "activity"))]
+fn fetch(_config: &ActivityConfig, _since: DateTime<Utc>) -> Vec<(&'static str, Result<Vec<Activity>, String>)> {
+    vec![("activity", Err("importing needs --features activity".to_string()))]
+}
+
+#[cfg(feature = "activity")]
+fn fetch(config: &ActivityConfig, since: DateTime<Utc>) -> Vec<(&'static str, Result<Vec<Activity>, String>)> {
+    let mut results = Vec::new();
+    if let Some(account) = &config.github {
+        results.push(("github", client::github(account, since)));
+    }
+    if let Some(account) = &config.gitlab {
+        results.push(("gitlab", client::gitlab(account, since)));
+    }
+    results
+}
+
+#[cfg(feature = "activity")]
+mod client {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use chrono::{DateTime, Utc};
+    use serde_json::Value;
+
+    use super::{parse_github, parse_gitlab, Activity};
+    use crate::config::ForgeAccount;
+
+    // GitHub serves at most 300 events from the last 90 days
+    const MAX_PAGES: u32 = 3;
+
+    fn token(account: &ForgeAccount, default_env: &str) -> Option<String> {
+        std::env::var(account.token_env.as_deref().unwrap_or(default_env)).ok()
+    }
+
+    fn get(request: ureq::Request) -> Result<Value, String> {
+        request.timeout(Duration::from_secs(30))
+            .call()
+            .map_err(|e| match e {
+                ureq::Error::Status(code, response) => format!("{} {}", code, response.into_string().unwrap_or_default().trim()),
+                other => other.to_string(),
+            })?
+            .into_string()
+            .map_err(|e| e.to_string())
+            .and_then(|body| serde_json::from_str(&body).map_err(|e| e.to_string()))
+    }
+
+    pub fn github(account: &ForgeAccount, since: DateTime<Utc>) -> Result<Vec<Activity>, String> {
+        let base = account.url.as_deref().unwrap_or("https://api.github.com").trim_end_matches('/');
+        let token = token(account, "GITHUB_TOKEN");
+        let mut activity = Vec::new();
+        for page in 1..=MAX_PAGES {
+            let url = format!("{}/users/{}/events?per_page=100&page={}", base, account.user, page);
+            let mut request = ureq::get(&url)
+                .set("Accept", "application/vnd.github+json")
+                .set("User-Agent", "window_tracker_concept");
+            if let Some(token) = &token {
+                request = request.set("Authorization", &format!("Bearer {}", token));
+            }
+            let events = get(request)?;
+            let oldest = events.as_array().and_then(|events| events.last()).and_then(|event| event["created_at"].as_str())
+                .and_then(|at| at.parse::<DateTime<Utc>>().ok());
+            activity.extend(parse_github(&events).into_iter().filter(|entry| entry.at >= since));
+            if oldest.is_none_or(|oldest| oldest < since) {
+                break;
+            }
+        }
+        Ok(activity)
+    }
+
+    pub fn gitlab(account: &ForgeAccount, since: DateTime<Utc>) -> Result<Vec<Activity>, String> {
+        let base = account.url.as_deref().unwrap_or("https://gitlab.com").trim_end_matches('/');
+        let token = token(account, "GITLAB_TOKEN");
+        let authorized = |request: ureq::Request| match &token {
+            Some(token) => request.set("PRIVATE-TOKEN", token),
+            None => request,
+        };
+
+        let mut events = Vec::new();
+        for page in 1.. {
+            // `after` is a date and exclusive, the day before keeps events from the first day
+            let url = format!(
+                "{}/api/v4/users/{}/events?after={}&per_page=100&page={}",
+                base, account.user, (since - chrono::Duration::days(1)).format("%Y-%m-%d"), page
+            );
+            let Value::Array(page_events) = get(authorized(ureq::get(&url)))? else { break };
+            if page_events.is_empty() {
+                break;
+            }
+            events.extend(page_events);
+        }
+
+        let mut repos = BTreeMap::new();
+        for project in events.iter().filter_map(|event| event["project_id"].as_u64()) {
+            if repos.contains_key(&project) {
+                continue;
+            }
+            let details = get(authorized(ureq::get(&format!("{}/api/v4/projects/{}", base, project))))?;
+            let path = details["path_with_namespace"].as_str().map(str::to_string);
+            repos.insert(project, path.unwrap_or_else(|| format!("project {}", project)));
+        }
+        Ok(parse_gitlab(&Value::Array(events), &repos).into_iter().filter(|entry| entry.at >= since).collect())
+    }
+}
@@ -0,0 +1,123 @@
+// [alerts]: a notification when a [[goals]] limit is passed, once a day per goal, and when there was
+// no input for idle_mins while the tracker goes on recording the focused window. On Windows the
+// toasts have buttons the running tracker acts on when clicked: Snooze holds the alert back for
+// snooze_mins and shows it again after, Recategorize records the focused app under the configured
+// category until the tracker exits, and Pause tracking pauses as `pause` would.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use chrono::{Local, NaiveDate};
+
+use crate::config::{AlertsConfig, Config};
+use crate::control;
+use crate::goals;
+use crate::humanize;
+use crate::i18n::tr;
+use crate::notify::{self, Action};
+
+// Until when each snoozed alert is held back, by alert
+static SNOOZED: Mutex<Option<HashMap<String, SystemTime>>> = Mutex::new(None);
+
+const IDLE: &str = "idle";
+
+#[derive(Default)]
+pub struct AlertWatch {
+    // The day each limit was last alerted, by category
+    limits: HashMap<String, NaiveDate>,
+    // Whether the current stretch without input was alerted
+    idle: bool,
+}
+
+impl AlertWatch {
+    pub fn check(&mut self, config: &Config, idle_for: Option<Duration>) {
+        let Some(alerts) = &config.alerts else { return };
+        if crate::wt_is_paused() {
+            return;
+        }
+        let now = SystemTime::now();
+        if alerts.limits {
+            let today = Local::now().date_naive();
+            for progress in goals::progress().iter().filter(|progress| progress.over_limit()) {
+                let key = format!("limit:{}", progress.category);
+                let due = self.limits.get(&progress.category) != Some(&today) || snooze_ended(&key, now);
+                if !due || snoozed(&key, now) {
+                    continue;
+                }
+                self.limits.insert(progress.category.clone(), today);
+                let body = tr("alert-limit-body", &[("time", &humanize::duration(progress.secs)), ("goal", &humanize::duration(progress.goal_secs))]);
+                show(alerts, &key, &tr("alert-limit-title", &[("category", &progress.category)]), &body, Some(&progress.category));
+            }
+        }
+
+        let threshold = Duration::from_secs(alerts.idle_mins * 60);
+        match idle_for {
+            Some(idle) if alerts.idle_mins > 0 && idle >= threshold => {
+                if (self.idle && !snooze_ended(IDLE, now)) || snoozed(IDLE, now) {
+                    return;
+                }
+                self.idle = true;
+                let Some(window) = crate::hot::current() else { return };
+                let body = tr("alert-idle-body", &[("time", &humanize::duration(idle.as_secs_f64())), ("app", &window.app)]);
+                show(alerts, IDLE, &tr("alert-idle-title", &[]), &body, None);
+            }
+            _ => self.idle = false,
+        }
+    }
+}
+
+fn snoozed(key: &str, now: SystemTime) -> bool {
+    SNOOZED.lock().unwrap().as_ref().and_then(|snoozed| snoozed.get(key)).is_some_and(|until| *until > now)
+}
+
+// Whether the alert was snoozed and that is over, which is forgotten once asked
+fn snooze_ended(key: &str, now: SystemTime) -> bool {
+    let mut snoozed = SNOOZED.lock().unwrap();
+    let snoozed = snoozed.get_or_insert_with(HashMap::new);
+    match snoozed.get(key) {
+        Some(until) if *until <= now => {
+            snoozed.remove(key);
+            true
+        }
+        _ => false,
+    }
+}
+
+// Recategorize is offered for the focused app, only for a limit when the app is what counts towards it
+fn show(alerts: &AlertsConfig, key: &str, summary: &str, body: &str, limit: Option<&str>) {
+    let mut actions = vec![Action { id: format!("snooze:{}", key), label: tr("alert-snooze", &[("mins", &alerts.snooze_mins)]) }];
+    if let (Some(category), Some(window)) = (&alerts.recategorize, crate::hot::current()) {
+        let current = crate::wt_categorize(&window);
+        if current != *category && limit.is_none_or(|limit| current == limit) {
+            actions.push(Action { id: format!("recategorize:{}", window.app), label: tr("alert-recategorize", &[("category", category)]) });
+        }
+    }
+    actions.push(Action { id: "pause".to_string(), label: tr("alert-pause", &[]) });
+
+    let snooze = Duration::from_secs(alerts.snooze_mins.max(1) * 60);
+    let category = alerts.recategorize.clone();
+    if let Err(e) = notify::send_actions(summary, body, &actions, move |id| act(id, snooze, category)) {
+        eprintln!("Failed to show the alert: {}", e);
+    }
+}
+
+// What a clicked button does, from the thread that waited on the notification
+fn act(id: &str, snooze: Duration, category: Option<String>) {
+    match (id, id.split_once(':')) {
+        (_, Some(("snooze", key))) => {
+            SNOOZED.lock().unwrap().get_or_insert_with(HashMap::new).insert(key.to_string(), SystemTime::now() + snooze);
+        }
+        (_, Some(("recategorize", app))) => {
+            if let Some(category) = category {
+                crate::wt_recategorize(app, &category);
+                eprintln!("{} counts as {} until the tracker exits", app, category);
+            }
+        }
+        ("pause", _) => {
+            if let Err(e) = control::pause_here() {
+                eprintln!("Failed to pause tracking: {}", e);
+            }
+        }
+        (other, _) => eprintln!("Unknown alert action '{}'", other),
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Timelike, Weekday};
+
+use crate::humanize;
+use crate::i18n::{self, tr};
+use crate::report::{local_midnight, wall_clock};
+use crate::storage::Interval;
+
+// How far back the typical pattern is learned from
+pub const BASELINE_DAYS: i64 = 28;
+// Fewer comparable days than this and there is no pattern worth trusting
+const MIN_BASELINE_DAYS: usize = 5;
+// Ignore deviations smaller than this, however unusual they are statistically
+const MIN_DEVIATION_SECS: f64 = 30.0 * 60.0;
+// An app counts as a daily habit when it shows up on this share of comparable days
+const HABIT_SHARE: f64 = 0.8;
+
+// Catalog keys of the parts of the day
+const DAY_PARTS: [&str; 4] = ["part-night", "part-morning", "part-afternoon", "part-evening"];
+const HOURS_PER_PART: u32 = 6;
+
+pub enum AnomalyKind {
+    Unusual { part: usize },
+    Missing,
+}
+
+pub struct Anomaly {
+    pub day: NaiveDate,
+    pub app: String,
+    pub kind: AnomalyKind,
+    pub actual: f64,
+    pub typical: f64,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let day = i18n::day(self.day);
+        let typical = humanize::duration(self.typical);
+        let text = match self.kind {
+            AnomalyKind::Unusual { part } => tr("anomaly-unusual", &[
+                ("day", &day),
+                ("part", &tr(DAY_PARTS[part], &[])),
+                ("time", &humanize::duration(self.actual)),
+                ("app", &self.app),
+                ("typical", &typical),
+            ]),
+            AnomalyKind::Missing => tr("anomaly-missing", &[("day", &day), ("app", &self.app), ("typical", &typical)]),
+        };
+        f.write_str(&text)
+    }
+}
+
+// Per (day, part of day, app) focus seconds.
+type Buckets = HashMap<(NaiveDate, usize, String), f64>;
+
+// Flags usage on each of `days` that is far outside what the preceding BASELINE_DAYS of the same
+// day type (weekday or weekend) looked like. `intervals` must cover the baseline as well.
+pub fn detect(intervals: &[Interval], days: &[NaiveDate]) -> Vec<Anomaly> {
+    let buckets = bucket(intervals);
+    let active_days: HashSet<NaiveDate> = buckets.keys().map(|(day, _, _)| *day).collect();
+    let today = Local::now().date_naive();
+
+    let mut anomalies = Vec::new();
+    for &day in days {
+        if !active_days.contains(&day) {
+            continue;
+        }
+
+        let baseline: Vec<NaiveDate> = (1..=BASELINE_DAYS)
+            .map(|offset| day - Duration::days(offset))
+            .filter(|other| is_weekend(*other) == is_weekend(day) && active_days.contains(other))
+            .collect();
+        if baseline.len() < MIN_BASELINE_DAYS {
+            continue;
+        }
+
+        let apps: HashSet<&String> = buckets.keys()
+            .filter(|(other, _, _)| *other == day || baseline.contains(other))
+            .map(|(_, _, app)| app)
+            .collect();
+
+        for app in apps {
+            let seconds = |day: NaiveDate, part: usize| buckets.get(&(day, part, app.clone())).copied().unwrap_or(0.0);
+
+            for part in 0..DAY_PARTS.len() {
+                let samples: Vec<f64> = baseline.iter().map(|other| seconds(*other, part)).collect();
+                let (mean, std_dev) = mean_and_std_dev(&samples);
+                let actual = seconds(day, part);
+                if actual - mean >= MIN_DEVIATION_SECS && actual >= (mean + 3.0 * std_dev).max(mean * 2.0) {
+                    anomalies.push(Anomaly { day, app: app.clone(), kind: AnomalyKind::Unusual { part }, actual, typical: mean });
+                }
+            }
+
+            // Only a finished day can be missing something
+            if day >= today {
+                continue;
+            }
+            let daily: Vec<f64> = baseline.iter()
+                .map(|other| (0..DAY_PARTS.len()).map(|part| seconds(*other, part)).sum())
+                .collect();
+            let used_on = daily.iter().filter(|secs| **secs > 0.0).count();
+            let (mean, _) = mean_and_std_dev(&daily);
+            let actual: f64 = (0..DAY_PARTS.len()).map(|part| seconds(day, part)).sum();
+            if used_on as f64 >= daily.len() as f64 * HABIT_SHARE && mean >= MIN_DEVIATION_SECS && actual < 60.0 {
+                anomalies.push(Anomaly { day, app: app.clone(), kind: AnomalyKind::Missing, actual, typical: mean });
+            }
+        }
+    }
+
+    anomalies.sort_by(|a, b| a.day.cmp(&b.day).then_with(|| a.app.cmp(&b.app)));
+    anomalies
+}
+
+fn bucket(intervals: &[Interval]) -> Buckets {
+    let mut buckets = Buckets::new();
+    for interval in intervals {
+        let mut start = interval.start.with_timezone(&Local);
+        let end = interval.end.with_timezone(&Local);
+        while start < end {
+            let part = (start.hour() / HOURS_PER_PART) as usize;
+            let chunk_end = end.min(part_end(start.date_naive(), part));
+            let secs = (chunk_end - start).num_milliseconds() as f64 / 1000.0;
+            *buckets.entry((start.date_naive(), part, interval.app_name().to_string())).or_insert(0.0) += secs;
+            start = chunk_end;
+        }
+    }
+    buckets
+}
+
+fn part_end(day: NaiveDate, part: usize) -> DateTime<Local> {
+    let hour = (part as u32 + 1) * HOURS_PER_PART;
+    if hour >= 24 {
+        return local_midnight(day + Duration::days(1));
+    }
+    wall_clock(&Local, day.and_hms_opt(hour, 0, 0).unwrap())
+}
+
+fn is_weekend(day: NaiveDate) -> bool {
+    matches!(day.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+fn mean_and_std_dev(samples: &[f64]) -> (f64, f64) {
+    if samples.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+    (mean, variance.sqrt())
+}
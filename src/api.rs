@@ -0,0 +1,433 @@
+// The HTTP API on the [health] address: GET /healthz for monitoring, and for dashboards GET
+// /api/status and /api/current and POST /api/pause, /api/resume and /api/switch-profile?profile=<name>,
+// answered the way the control socket (ipc.rs) answers the CLI. With [[health.tokens]] every request
+// needs `Authorization: Bearer <token>` from a token with the endpoint's scope; without any the API
+// only listens on loopback. tls_cert and tls_key serve it over HTTPS.
+//
+// GET /api/intervals and /api/windows read the stored history of from..to (days as in `rules test
+// --since`, today when left out) a page at a time: offset and limit pick the items, next_offset in
+// the answer is where the next page starts. A request covers at most MAX_RANGE_DAYS, so a year of
+// history is never loaded or sent at once, and each client gets queries_per_minute of them.
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, Utc};
+use serde_json::{json, Value};
+
+use crate::cli::Args;
+use crate::config::{ApiScope, ApiToken, HealthConfig};
+use crate::control;
+use crate::filter::Filter;
+use crate::health;
+use crate::ipc;
+use crate::merge::Merges;
+use crate::report::{clip, local_midnight, summarize, GroupBy};
+use crate::review;
+use crate::rollup::Pivot;
+use crate::rules::parse_day;
+use crate::storage;
+
+const MAX_REQUEST_BYTES: usize = 8192;
+const MAX_RANGE_DAYS: i64 = 31;
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+const MINUTE: Duration = Duration::from_secs(60);
+
+// Swapped on reload, so added or removed tokens apply without a restart
+static TOKENS: Mutex<Vec<ApiToken>> = Mutex::new(Vec::new());
+// Where serve listens, a reload can't take the tokens away from an address off loopback
+static BOUND: Mutex<Option<SocketAddr>> = Mutex::new(None);
+static QUERIES_PER_MINUTE: AtomicU32 = AtomicU32::new(60);
+// Each client's first query of its current minute and the queries since
+static QUERIES: Mutex<Vec<(IpAddr, Instant, u32)>> = Mutex::new(Vec::new());
+
+#[cfg(feature = "tls")]
+type Tls = std::sync::Arc<rustls::ServerConfig>;
+// There is nothing to serve HTTPS with, tls_config refuses cert and key
+#[cfg(not(feature = "tls"))]
+#[derive(Clone)]
+enum Tls {}
+
+// Refuses a config that leaves the API listening off loopback without tokens, so a reload keeps
+// the ones in place
+pub fn check(config: &HealthConfig) -> Result<(), String> {
+    match *BOUND.lock().unwrap() {
+        Some(address) if !address.ip().is_loopback() && config.tokens.is_empty() => {
+            Err(format!("the API on {} would be open to anyone on the network, keep a [[health.tokens]] token", address))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn configure(config: &HealthConfig) {
+    *TOKENS.lock().unwrap() = config.tokens.clone();
+    QUERIES_PER_MINUTE.store(config.queries_per_minute, Ordering::Relaxed);
+}
+
+pub fn serve(config: &HealthConfig) -> Result<(), String> {
+    let Some(address) = &config.address else { return Ok(()) };
+    let address: SocketAddr = address.parse()
+        .map_err(|_| format!("invalid health address '{}', expected e.g. 127.0.0.1:9464", address))?;
+    if !address.ip().is_loopback() && config.tokens.is_empty() {
+        return Err(format!("the API on {} would be open to anyone on the network, run api-token to add a token first", address));
+    }
+    let tls = tls_config(config)?;
+    let listener = TcpListener::bind(address).map_err(|e| format!("failed to listen on {}: {}", address, e))?;
+    *BOUND.lock().unwrap() = Some(address);
+    thread::spawn(move || {
+        for connection in listener.incoming().filter_map(Result::ok) {
+            let tls = tls.clone();
+            thread::spawn(move || {
+                // Monitoring agents going away mid-request is nothing to report
+                let _ = connection.set_read_timeout(Some(Duration::from_secs(5)));
+                let Ok(client) = connection.peer_addr() else { return };
+                let _ = match tls {
+                    Some(tls) => answer_tls(connection, client.ip(), tls),
+                    None => answer(&mut &connection, client.ip()),
+                };
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(feature = "tls")]
+fn tls_config(config: &HealthConfig) -> Result<Option<Tls>, String> {
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+    let (cert, key) = match (&config.tls_cert, &config.tls_key) {
+        (None, None) => return Ok(None),
+        (Some(cert), Some(key)) => (cert, key),
+        _ => return Err("[health] needs both tls_cert and tls_key to serve HTTPS".to_string()),
+    };
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("failed to read certificates from {}: {}", cert.display(), e))?;
+    let key = PrivateKeyDer::from_pem_file(key).map_err(|e| format!("failed to read the private key from {}: {}", key.display(), e))?;
+    let server = rustls::ServerConfig::builder_with_provider(rustls::crypto::ring::default_provider().into())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("failed to set up TLS: {}", e))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("invalid TLS certificate or key: {}", e))?;
+    Ok(Some(Tls::new(server)))
+}
+
+#[cfg(not(feature = "tls"))]
+fn tls_config(config: &HealthConfig) -> Result<Option<Tls>, String> {
+    if config.tls_cert.is_some() || config.tls_key.is_some() {
+        return Err("[health] tls_cert and tls_key need HTTPS support, rebuild with --features tls".to_string());
+    }
+    Ok(None)
+}
+
+#[cfg(feature = "tls")]
+fn answer_tls(connection: TcpStream, client: IpAddr, tls: Tls) -> io::Result<()> {
+    let session = rustls::ServerConnection::new(tls).map_err(io::Error::other)?;
+    let mut stream = rustls::StreamOwned::new(session, connection);
+    answer(&mut stream, client)?;
+    stream.conn.send_close_notify();
+    stream.flush()
+}
+
+#[cfg(not(feature = "tls"))]
+fn answer_tls(_connection: TcpStream, _client: IpAddr, tls: Tls) -> io::Result<()> {
+    match tls {}
+}
+
+enum Endpoint {
+    Health,
+    // Answered like the same command over the control socket
+    Command(Value),
+    History,
+}
+
+// None when the request may go ahead, the status to refuse it with otherwise
+fn refusal(authorization: &str, scope: ApiScope) -> Option<&'static str> {
+    let bound = *BOUND.lock().unwrap();
+    refused(&TOKENS.lock().unwrap(), bound, authorization, scope)
+}
+
+fn refused(tokens: &[ApiToken], bound: Option<SocketAddr>, authorization: &str, scope: ApiScope) -> Option<&'static str> {
+    // Without tokens only loopback is answered, whatever the config came to hold since serve
+    if tokens.is_empty() {
+        return bound.is_some_and(|address| !address.ip().is_loopback()).then_some("401 Unauthorized");
+    }
+    let given = authorization.strip_prefix("Bearer ").unwrap_or_default().trim();
+    // Every token is compared, so timing doesn't tell which one came close
+    let found = tokens.iter().fold(None, |found, token| if ipc::tokens_match(given, &token.token) { Some(token) } else { found });
+    match found {
+        None => Some("401 Unauthorized"),
+        Some(token) if !token.scopes.contains(&scope) => Some("403 Forbidden"),
+        Some(_) => None,
+    }
+}
+
+// Seconds until the client may query again, None while it is within queries_per_minute
+fn throttle(client: IpAddr) -> Option<u64> {
+    let allowed = QUERIES_PER_MINUTE.load(Ordering::Relaxed);
+    if allowed == 0 {
+        return None;
+    }
+    let mut clients = QUERIES.lock().unwrap();
+    clients.retain(|(_, start, _)| start.elapsed() < MINUTE);
+    match clients.iter_mut().find(|(address, _, _)| *address == client) {
+        Some((_, start, count)) if *count >= allowed => Some(MINUTE.saturating_sub(start.elapsed()).as_secs() + 1),
+        Some((_, _, count)) => {
+            *count += 1;
+            None
+        }
+        None => {
+            clients.push((client, Instant::now(), 1));
+            None
+        }
+    }
+}
+
+pub fn query_value<'a>(query: &'a str, key: &str) -> &'a str {
+    query.split('&').find_map(|pair| pair.strip_prefix(key)?.strip_prefix('=')).unwrap_or_default()
+}
+
+// A query value with %XX and + decoded, for ones like filter that hold spaces and quotes
+pub fn decoded(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => match value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    out.push(byte);
+                    i += 2;
+                }
+                None => out.push(b'%'),
+            },
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// from..to as local midnights, including both days
+pub fn query_range(query: &str) -> Result<(DateTime<Local>, DateTime<Local>), String> {
+    let from = match query_value(query, "from") {
+        "" => Local::now().date_naive(),
+        day => parse_day(day, "from")?,
+    };
+    let to = match query_value(query, "to") {
+        "" => from,
+        day => parse_day(day, "to")?,
+    };
+    if to < from {
+        return Err(format!("to {} is before from {}", to, from));
+    }
+    if (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(format!("at most {} days at a time, ask for the rest with another from and to", MAX_RANGE_DAYS));
+    }
+    Ok((local_midnight(from), local_midnight(to + chrono::Duration::days(1))))
+}
+
+// The page of `items` that offset and limit ask for, under `key` with where the next page starts
+fn page(query: &str, key: &str, items: Vec<Value>) -> Result<Value, String> {
+    let number = |name: &str, default: usize| match query_value(query, name) {
+        "" => Ok(default),
+        text => text.parse::<usize>().map_err(|_| format!("invalid {} '{}', expected a number", name, text)),
+    };
+    let (offset, limit) = (number("offset", 0)?, number("limit", DEFAULT_LIMIT)?);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(format!("limit must be between 1 and {}", MAX_LIMIT));
+    }
+    let total = items.len();
+    let next_offset = (offset.saturating_add(limit) < total).then(|| offset + limit);
+    let items: Vec<Value> = items.into_iter().skip(offset).take(limit).collect();
+    Ok(json!({ "ok": true, "total": total, "offset": offset, "limit": limit, "next_offset": next_offset, key: items }))
+}
+
+// GET /api/intervals and /api/windows?by=<grouping>, both taking filter=<expression> (filter.rs);
+// /api/intervals?group_by=<keys>&rollup=<rollups> gives the rows of a pivot (rollup.rs) instead
+fn history_query(path: &str, query: &str) -> Result<Value, String> {
+    let (from, to) = query_range(query)?;
+    let filter = Filter::parse(&decoded(query_value(query, "filter")))?;
+    let history = storage::load_matching(from.with_timezone(&Utc), to.with_timezone(&Utc), &filter)
+        .map_err(|e| format!("failed to read history: {}", e))?;
+    let group_by = query_value(query, "group_by");
+    let mut answer = if path == "/api/intervals" && !group_by.is_empty() {
+        let rollup = query_value(query, "rollup");
+        let pivot = Pivot::parse(group_by, (!rollup.is_empty()).then_some(rollup))?;
+        let history = if pivot.by_title() { Merges::from_config(&crate::config::load()?)?.apply(&history) } else { history };
+        page(query, "rows", pivot.table(&clip(&history, from, to)).objects())?
+    } else if path == "/api/intervals" {
+        let intervals = clip(&history, from, to).into_iter().map(|interval| json!(interval)).collect();
+        page(query, "intervals", intervals)?
+    } else {
+        let group_by = match query_value(query, "by") {
+            "" => GroupBy::configured()?,
+            by => GroupBy::parse(by)?,
+        };
+        // Totals by title are by the names [[merge]] gives them, as in the report
+        let merges = Merges::from_config(&crate::config::load()?)?;
+        let history = if matches!(group_by, GroupBy::Title) { merges.apply(&history) } else { history };
+        let summary = summarize(&clip(&history, from, to), from, to, group_by);
+        let windows = summary.windows.iter().map(|(name, secs)| json!({ "name": name, "secs": secs })).collect();
+        let mut answer = page(query, "windows", windows)?;
+        answer["by"] = summary.group_by.column().into();
+        answer["total_secs"] = summary.total.into();
+        answer
+    };
+    answer["from"] = from.date_naive().to_string().into();
+    answer["to"] = (to - chrono::Duration::days(1)).date_naive().to_string().into();
+    Ok(answer)
+}
+
+// /healthz is 200 while healthy and 503 otherwise, so a plain HTTP check alerts without reading the
+// body; the /api endpoints are 400 when the tracker refuses the command or the query is invalid,
+// and 429 with Retry-After for a client past queries_per_minute
+fn answer(stream: &mut (impl Read + Write), client: IpAddr) -> io::Result<()> {
+    let mut reader = BufReader::new(&mut *stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let (mut authorization, mut length) = (String::new(), 0);
+    let mut total = request.len();
+    loop {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line)?;
+        total += read;
+        if read == 0 || line.trim_end().is_empty() || total > MAX_REQUEST_BYTES {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "authorization" => authorization = value.trim().to_string(),
+                "content-length" => length = value.trim().parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    // No endpoint takes a body, it is only read past so the client isn't cut off mid-send
+    io::copy(&mut reader.take(length.min(MAX_REQUEST_BYTES) as u64), &mut io::sink())?;
+
+    let mut parts = request.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let route = match (method, path) {
+        ("GET" | "HEAD", "/healthz") => Some((ApiScope::Read, Endpoint::Health)),
+        ("GET" | "HEAD", "/api/status") => Some((ApiScope::Read, Endpoint::Command(json!({ "command": "status" })))),
+        ("GET" | "HEAD", "/api/current") => Some((ApiScope::Read, Endpoint::Command(json!({ "command": "current" })))),
+        ("GET" | "HEAD", "/api/intervals" | "/api/windows") => Some((ApiScope::Read, Endpoint::History)),
+        ("POST", "/api/pause") => Some((ApiScope::Write, Endpoint::Command(json!({ "command": "pause" })))),
+        ("POST", "/api/resume") => Some((ApiScope::Write, Endpoint::Command(json!({ "command": "resume" })))),
+        ("POST", "/api/switch-profile") => Some((ApiScope::Write, Endpoint::Command(json!({ "command": "switch_profile", "profile": query_value(query, "profile") })))),
+        _ => None,
+    };
+    let mut headers = String::new();
+    let (status, body): (&str, Value) = match route {
+        None => ("404 Not Found", json!({ "ok": false, "error": "no such endpoint" })),
+        Some((scope, endpoint)) => match (refusal(&authorization, scope), endpoint) {
+            (Some(status @ "401 Unauthorized"), _) => {
+                headers.push_str("WWW-Authenticate: Bearer\r\n");
+                (status, json!({ "ok": false, "error": "wrong or missing bearer token" }))
+            }
+            (Some(status), _) => (status, json!({ "ok": false, "error": "the token doesn't allow this" })),
+            (None, Endpoint::Health) => {
+                let report = health::report();
+                (if report["ok"] == true { "200 OK" } else { "503 Service Unavailable" }, report)
+            }
+            (None, Endpoint::Command(command)) => {
+                let response = control::handle_request(&command);
+                (if response["ok"] == true { "200 OK" } else { "400 Bad Request" }, response)
+            }
+            (None, Endpoint::History) => match throttle(client) {
+                Some(wait) => {
+                    headers.push_str(&format!("Retry-After: {}\r\n", wait));
+                    ("429 Too Many Requests", json!({ "ok": false, "error": format!("too many queries, try again in {} seconds", wait) }))
+                }
+                None => match history_query(path, query) {
+                    Ok(answer) => ("200 OK", answer),
+                    Err(e) => ("400 Bad Request", json!({ "ok": false, "error": e })),
+                },
+            },
+        },
+    };
+    let body = body.to_string();
+    write!(stream, "HTTP/1.1 {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, headers, body.len())?;
+    if method != "HEAD" {
+        stream.write_all(body.as_bytes())?;
+    }
+    stream.flush()
+}
+
+// `api-token`: a new random token appended to config.toml as [[health.tokens]], printed once
+pub fn run_token(args: &Args) -> Result<(), String> {
+    let json_output = args.json_output()?;
+    let mut scopes = Vec::new();
+    for scope in args.value("--scopes").unwrap_or("read").split(',').map(str::trim) {
+        let scope = match scope {
+            "read" => ApiScope::Read,
+            "write" => ApiScope::Write,
+            other => return Err(format!("unknown scope '{}', expected 'read' or 'write'", other)),
+        };
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    let names: Vec<&str> = scopes.iter().map(|scope| if *scope == ApiScope::Read { "read" } else { "write" }).collect();
+    let token = ipc::random_hex(32)?;
+    let quoted: Vec<String> = names.iter().map(|name| format!("\"{}\"", name)).collect();
+    let snippet = format!("[[health.tokens]]\ntoken = \"{}\"\nscopes = [{}]\n", token, quoted.join(", "));
+    let path = review::append_to_config(&[snippet], |_| Ok(()))?;
+    if json_output {
+        println!("{}", json!({ "token": token, "scopes": names, "config": path }));
+        return Ok(());
+    }
+    eprintln!("Added a token to {}, a running tracker accepts it right away:", path.display());
+    println!("{}", token);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(token: &str, scopes: &[ApiScope]) -> ApiToken {
+        ApiToken { token: token.to_string(), scopes: scopes.to_vec() }
+    }
+
+    #[test]
+    fn without_tokens_only_loopback_is_answered() {
+        let loopback: SocketAddr = "127.0.0.1:9464".parse().unwrap();
+        let network: SocketAddr = "0.0.0.0:9464".parse().unwrap();
+        assert_eq!(refused(&[], None, "", ApiScope::Write), None);
+        assert_eq!(refused(&[], Some(loopback), "", ApiScope::Write), None);
+        assert_eq!(refused(&[], Some(network), "", ApiScope::Read), Some("401 Unauthorized"));
+    }
+
+    #[test]
+    fn tokens_need_the_endpoint_scope() {
+        let network: SocketAddr = "0.0.0.0:9464".parse().unwrap();
+        let tokens = [token("reader", &[ApiScope::Read]), token("writer", &[ApiScope::Read, ApiScope::Write])];
+        assert_eq!(refused(&tokens, Some(network), "", ApiScope::Read), Some("401 Unauthorized"));
+        assert_eq!(refused(&tokens, Some(network), "Bearer wrong", ApiScope::Read), Some("401 Unauthorized"));
+        assert_eq!(refused(&tokens, Some(network), "reader", ApiScope::Read), Some("401 Unauthorized"));
+        assert_eq!(refused(&tokens, Some(network), "Bearer reader", ApiScope::Read), None);
+        assert_eq!(refused(&tokens, Some(network), "Bearer reader", ApiScope::Write), Some("403 Forbidden"));
+        assert_eq!(refused(&tokens, Some(network), "Bearer writer", ApiScope::Write), None);
+    }
+
+    #[test]
+    fn a_reload_keeps_tokens_off_loopback() {
+        let config = HealthConfig::default();
+        assert!(check(&config).is_ok());
+        *BOUND.lock().unwrap() = Some("0.0.0.0:9464".parse().unwrap());
+        assert!(check(&config).is_err());
+        let config = HealthConfig { tokens: vec![token("reader", &[ApiScope::Read])], ..HealthConfig::default() };
+        assert!(check(&config).is_ok());
+        *BOUND.lock().unwrap() = None;
+    }
+}
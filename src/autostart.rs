@@ -0,0 +1,388 @@
+// `autostart enable|disable|status`: tracking from login, by what each platform has for it. On Linux
+// a systemd user unit, or an XDG autostart entry where there is no user systemd; a LaunchAgent on
+// macOS; a Run key entry on Windows, or a shortcut in the Startup folder. Enabling one method
+// removes the others, so the tracker never starts twice.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde_json::json;
+
+use crate::cli::Args;
+
+// What the service runs as, and the file it is installed in
+const SERVICE_NAME: &str = "window_tracker";
+// Carried into the service so it tracks with the config and data it was enabled with
+const ENVIRONMENT: [&str; 3] = ["WT_CONFIG", "WT_DATA_DIR", "WT_PROFILE"];
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Method {
+    Systemd,
+    Xdg,
+    LaunchAgent,
+    RunKey,
+    StartupFolder,
+}
+
+const ALL: [Method; 5] = [Method::Systemd, Method::Xdg, Method::LaunchAgent, Method::RunKey, Method::StartupFolder];
+
+// What this platform can start the tracker with, the default first
+#[cfg(all(unix, not(target_os = "macos")))]
+const METHODS: &[Method] = &[Method::Systemd, Method::Xdg];
+#[cfg(target_os = "macos")]
+const METHODS: &[Method] = &[Method::LaunchAgent];
+#[cfg(windows)]
+const METHODS: &[Method] = &[Method::RunKey, Method::StartupFolder];
+#[cfg(not(any(unix, windows)))]
+const METHODS: &[Method] = &[];
+
+// Where a method is installed, and whether it starts this executable
+struct Entry {
+    method: Method,
+    location: String,
+    this_executable: bool,
+    // Whether the service manager has it running or loaded, where it can tell
+    active: Option<bool>,
+}
+
+impl Method {
+    fn name(&self) -> &'static str {
+        match self {
+            Method::Systemd => "systemd",
+            Method::Xdg => "xdg",
+            Method::LaunchAgent => "launchagent",
+            Method::RunKey => "run-key",
+            Method::StartupFolder => "startup-folder",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Method::Systemd => "the systemd user unit",
+            Method::Xdg => "the XDG autostart entry",
+            Method::LaunchAgent => "the LaunchAgent",
+            Method::RunKey => "the Run registry key",
+            Method::StartupFolder => "the Startup folder shortcut",
+        }
+    }
+
+    fn parse(name: &str) -> Result<Self, String> {
+        let names: Vec<&str> = METHODS.iter().map(Method::name).collect();
+        match ALL.iter().find(|method| method.name() == name) {
+            Some(method) if METHODS.contains(method) => Ok(*method),
+            Some(_) => Err(format!("--method {} isn't available on this platform, expected {}", name, names.join(" or "))),
+            None => Err(format!("unknown --method '{}', expected {}", name, names.join(" or "))),
+        }
+    }
+}
+
+fn run_command(program: &str, args: &[&str]) -> Result<(), String> {
+    let status = Command::new(program).args(args).status()
+        .map_err(|e| format!("failed to run {}: {}", program, e))?;
+    if !status.success() {
+        return Err(format!("{} {} failed with {}", program, args.join(" "), status));
+    }
+    Ok(())
+}
+
+// Whether the command ran and succeeded, its output kept quiet
+fn succeeds(program: &str, args: &[&str]) -> bool {
+    Command::new(program).args(args).stdout(Stdio::null()).stderr(Stdio::null()).status().is_ok_and(|status| status.success())
+}
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).stderr(Stdio::null()).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// (name, value) of the ENVIRONMENT variables set
+fn environment() -> Vec<(&'static str, String)> {
+    ENVIRONMENT.iter()
+        .filter_map(|name| std::env::var(name).ok().map(|value| (*name, value)))
+        .collect()
+}
+
+fn home() -> Result<PathBuf, String> {
+    std::env::var_os("HOME").map(PathBuf::from).ok_or_else(|| "HOME is not set".to_string())
+}
+
+fn xdg_config_home() -> Result<PathBuf, String> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| "neither XDG_CONFIG_HOME nor HOME is set".to_string())
+}
+
+fn launch_agent_label() -> String {
+    format!("com.{}", SERVICE_NAME)
+}
+
+// The file a method is installed as, the Run key has none
+fn path(method: Method) -> Result<Option<PathBuf>, String> {
+    Ok(match method {
+        Method::Systemd => Some(xdg_config_home()?.join("systemd/user").join(format!("{}.service", SERVICE_NAME))),
+        Method::Xdg => Some(xdg_config_home()?.join("autostart").join(format!("{}.desktop", SERVICE_NAME))),
+        Method::LaunchAgent => Some(home()?.join("Library/LaunchAgents").join(format!("{}.plist", launch_agent_label()))),
+        Method::RunKey => None,
+        Method::StartupFolder => {
+            let appdata = std::env::var_os("APPDATA").map(PathBuf::from).ok_or("APPDATA is not set")?;
+            Some(appdata.join(r"Microsoft\Windows\Start Menu\Programs\Startup").join(format!("{}.lnk", SERVICE_NAME)))
+        }
+    })
+}
+
+fn write(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+fn systemd_unit(executable: &Path) -> String {
+    // systemd expands specifiers starting with %
+    let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%"));
+    let mut unit = format!("[Unit]\nDescription=Window tracker\n\n[Service]\nExecStart={} track\nRestart=on-failure\n", quote(&executable.display().to_string()));
+    for (name, value) in environment() {
+        unit.push_str(&format!("Environment={}\n", quote(&format!("{}={}", name, value))));
+    }
+    unit.push_str("\n[Install]\nWantedBy=default.target\n");
+    unit
+}
+
+fn desktop_entry(executable: &Path) -> String {
+    // Exec quotes like a shell without one, and a field code starts with %
+    let quote = |text: &str| {
+        let mut quoted = String::from("\"");
+        for c in text.chars() {
+            if matches!(c, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted.replace('%', "%%")
+    };
+    let mut exec: Vec<String> = Vec::new();
+    if !environment().is_empty() {
+        exec.push("env".to_string());
+        exec.extend(environment().iter().map(|(name, value)| quote(&format!("{}={}", name, value))));
+    }
+    exec.push(quote(&executable.display().to_string()));
+    exec.push("track".to_string());
+    format!(
+        "[Desktop Entry]\nType=Application\nName=Window tracker\nExec={}\nNoDisplay=true\nX-GNOME-Autostart-enabled=true\n",
+        exec.join(" ")
+    )
+}
+
+fn launch_agent(executable: &Path) -> String {
+    let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let mut environment_entries = String::new();
+    for (name, value) in environment() {
+        environment_entries.push_str(&format!("    <key>{}</key><string>{}</string>\n", name, escape(&value)));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n<dict>\n\
+         \x20 <key>Label</key><string>{}</string>\n\
+         \x20 <key>ProgramArguments</key><array><string>{}</string><string>track</string></array>\n\
+         \x20 <key>EnvironmentVariables</key><dict>\n{}  </dict>\n\
+         \x20 <key>RunAtLoad</key><true/>\n\
+         \x20 <key>KeepAlive</key><dict><key>SuccessfulExit</key><false/></dict>\n\
+         </dict>\n</plist>\n",
+        launch_agent_label(), escape(&executable.display().to_string()), environment_entries,
+    )
+}
+
+// A Run entry or shortcut has no environment of its own
+fn without_environment(installed: &str) -> String {
+    if environment().is_empty() {
+        installed.to_string()
+    } else {
+        format!("{}, with the default config and data since {} aren't carried over", installed, ENVIRONMENT.join(", "))
+    }
+}
+
+fn install(method: Method, executable: &Path) -> Result<String, String> {
+    let path = path(method)?;
+    let location = path.as_ref().map_or_else(|| RUN_KEY.to_string(), |path| path.display().to_string());
+    match method {
+        Method::Systemd => {
+            let path = path.as_deref().unwrap();
+            write(path, &systemd_unit(executable))?;
+            let enabled = run_command("systemctl", &["--user", "daemon-reload"])
+                .and_then(|_| run_command("systemctl", &["--user", "enable", "--now", &format!("{}.service", SERVICE_NAME)]));
+            if let Err(e) = enabled {
+                // A unit systemd never took up isn't left behind
+                let _ = fs::remove_file(path);
+                return Err(e);
+            }
+            Ok(format!("Installed {}, tracking runs now and from every login", location))
+        }
+        Method::Xdg => {
+            write(path.as_deref().unwrap(), &desktop_entry(executable))?;
+            Ok(format!("Installed {}, tracking starts at the next login (run `track` until then)", location))
+        }
+        Method::LaunchAgent => {
+            write(path.as_deref().unwrap(), &launch_agent(executable))?;
+            run_command("launchctl", &["load", "-w", &location])?;
+            Ok(format!("Installed {}, tracking runs now and from every login", location))
+        }
+        Method::RunKey => {
+            let command = format!("\"{}\" track", executable.display());
+            run_command("reg", &["add", RUN_KEY, "/v", SERVICE_NAME, "/t", "REG_SZ", "/d", &command, "/f"])?;
+            Ok(without_environment("Tracking starts at the next login"))
+        }
+        Method::StartupFolder => {
+            let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+            let script = format!(
+                "$shortcut = (New-Object -ComObject WScript.Shell).CreateShortcut({}); $shortcut.TargetPath = {}; $shortcut.Arguments = 'track'; $shortcut.WindowStyle = 7; $shortcut.Save()",
+                quote(&location),
+                quote(&executable.display().to_string())
+            );
+            if let Some(dir) = path.as_deref().and_then(Path::parent) {
+                fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+            }
+            run_command("powershell", &["-NoProfile", "-NonInteractive", "-Command", &script])?;
+            Ok(without_environment(&format!("Installed {}, tracking starts at the next login", location)))
+        }
+    }
+}
+
+fn remove_file(path: &Path) -> Result<(), String> {
+    fs::remove_file(path).map_err(|e| format!("failed to remove {}: {}", path.display(), e))
+}
+
+fn uninstall(entry: &Entry) -> Result<(), String> {
+    let path = Path::new(&entry.location);
+    match entry.method {
+        Method::Systemd => {
+            // Stopping a tracker that isn't running is fine
+            let _ = run_command("systemctl", &["--user", "disable", "--now", &format!("{}.service", SERVICE_NAME)]);
+            remove_file(path)?;
+            let _ = run_command("systemctl", &["--user", "daemon-reload"]);
+            Ok(())
+        }
+        Method::LaunchAgent => {
+            let _ = run_command("launchctl", &["unload", "-w", &entry.location]);
+            remove_file(path)
+        }
+        Method::Xdg | Method::StartupFolder => remove_file(path),
+        Method::RunKey => run_command("reg", &["delete", RUN_KEY, "/v", SERVICE_NAME, "/f"]),
+    }
+}
+
+// The methods installed now
+fn entries(executable: &Path) -> Result<Vec<Entry>, String> {
+    let executable = executable.display().to_string();
+    let mut entries = Vec::new();
+    for &method in METHODS {
+        if method == Method::RunKey {
+            if let Some(value) = output("reg", &["query", RUN_KEY, "/v", SERVICE_NAME]) {
+                entries.push(Entry { method, location: RUN_KEY.to_string(), this_executable: value.contains(&executable), active: None });
+            }
+            continue;
+        }
+        let Some(path) = path(method)? else { continue };
+        // A shortcut is binary, its target isn't read back
+        let content = match method {
+            Method::StartupFolder => path.exists().then(|| executable.clone()),
+            _ => fs::read_to_string(&path).ok(),
+        };
+        let Some(content) = content else { continue };
+        let active = match method {
+            Method::Systemd => Some(succeeds("systemctl", &["--user", "is-active", "--quiet", &format!("{}.service", SERVICE_NAME)])),
+            Method::LaunchAgent => Some(succeeds("launchctl", &["list", &launch_agent_label()])),
+            _ => None,
+        };
+        entries.push(Entry { method, location: path.display().to_string(), this_executable: content.contains(&executable), active });
+    }
+    Ok(entries)
+}
+
+// Where user systemd runs, otherwise the desktop's autostart
+fn default_method() -> Option<Method> {
+    match METHODS {
+        [Method::Systemd, Method::Xdg] if !succeeds("systemctl", &["--user", "show-environment"]) => Some(Method::Xdg),
+        methods => methods.first().copied(),
+    }
+}
+
+// Sets up tracking from login with the default method, for init
+pub fn enable(executable: &Path) -> Result<String, String> {
+    let method = default_method().ok_or("starting at login isn't supported on this platform")?;
+    enable_with(method, executable)
+}
+
+// The others go once this one is in place, a failure leaves what was there
+fn enable_with(method: Method, executable: &Path) -> Result<String, String> {
+    let others = entries(executable)?;
+    let installed = install(method, executable)?;
+    for entry in others.iter().filter(|entry| entry.method != method) {
+        uninstall(entry)?;
+        println!("Removed {} at {}", entry.method.description(), entry.location);
+    }
+    Ok(installed)
+}
+
+fn print_status(entries: &[Entry], json: bool) {
+    if json {
+        let entries: Vec<serde_json::Value> = entries.iter()
+            .map(|entry| json!({
+                "method": entry.method.name(),
+                "location": entry.location,
+                "this_executable": entry.this_executable,
+                "active": entry.active,
+            }))
+            .collect();
+        println!("{}", json!({ "enabled": !entries.is_empty(), "entries": entries }));
+        return;
+    }
+    if entries.is_empty() {
+        println!("Not set up to start at login, `autostart enable` sets it up");
+    }
+    for entry in entries {
+        let active = match entry.active {
+            Some(true) => ", running",
+            Some(false) => ", not running",
+            None => "",
+        };
+        println!("Starts at login with {} ({}){}", entry.method.description(), entry.location, active);
+        if !entry.this_executable {
+            println!("  it starts another executable than this one, `autostart enable` points it here");
+        }
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let usage = "usage: autostart enable [--method <method>] | disable | status";
+    let executable = std::env::current_exe().map_err(|e| format!("failed to locate the executable: {}", e))?;
+    match args.arg(1) {
+        Some("enable") => {
+            let method = match args.value("--method") {
+                Some(name) => Method::parse(name)?,
+                None => default_method().ok_or("starting at login isn't supported on this platform")?,
+            };
+            println!("{}", enable_with(method, &executable)?);
+            Ok(())
+        }
+        Some("disable") => {
+            let entries = entries(&executable)?;
+            if entries.is_empty() {
+                println!("Not set up to start at login");
+            }
+            for entry in &entries {
+                uninstall(entry)?;
+                println!("Removed {} at {}", entry.method.description(), entry.location);
+            }
+            Ok(())
+        }
+        Some("status") => {
+            print_status(&entries(&executable)?, args.json_output()?);
+            Ok(())
+        }
+        _ => Err(usage.to_string()),
+    }
+}
@@ -0,0 +1,331 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::cli::Args;
+use crate::config;
+use crate::journal;
+use crate::profile;
+use crate::storage;
+
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+// pending.jsonl holds finished intervals not yet moved into the history
+const DATA_FILES: [&str; 3] = ["intervals.jsonl", "intervals.db", "pending.jsonl"];
+const PENDING_FILE: &str = "pending.jsonl";
+const CONFIG_ENTRY: &str = "config/config.toml";
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: u32,
+    created: String,
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestEntry {
+    path: String,
+    size: u64,
+    sha256: String,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Where an archive entry lives on this machine; anything else in an archive is rejected.
+fn destination(entry: &str) -> Option<PathBuf> {
+    if entry == CONFIG_ENTRY {
+        return Some(config::config_path());
+    }
+    let name = entry.strip_prefix("data/")?;
+    DATA_FILES.contains(&name).then(|| storage::data_dir().join(name))
+}
+
+pub fn run_backup(args: &Args) -> Result<(), String> {
+    let path = args.arg(1).ok_or("usage: backup <archive.tar>")?;
+    let files = backup_files()?;
+    if files.is_empty() {
+        return Err("nothing to back up, no history or config found".to_string());
+    }
+
+    write_archive(path, &files)?;
+    print_written(args, path, &files, "Backup")
+}
+
+fn backup_files() -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut files = Vec::new();
+    for name in DATA_FILES {
+        if let Some(bytes) = snapshot(&storage::data_dir().join(name)).map_err(|e| format!("failed to read {}: {}", name, e))? {
+            files.push((format!("data/{}", name), bytes));
+        }
+    }
+    if let Some(bytes) = read_optional(&config::config_path()).map_err(|e| format!("failed to read config: {}", e))? {
+        files.push((CONFIG_ENTRY.to_string(), bytes));
+    }
+    Ok(files)
+}
+
+// The files with a manifest of their checksums in front
+fn write_archive(path: &str, files: &[(String, Vec<u8>)]) -> Result<(), String> {
+    let manifest = Manifest {
+        format: FORMAT_VERSION,
+        created: Utc::now().to_rfc3339(),
+        files: files.iter()
+            .map(|(path, bytes)| ManifestEntry { path: path.clone(), size: bytes.len() as u64, sha256: sha256_hex(bytes) })
+            .collect(),
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest).unwrap();
+
+    let file = File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?;
+    let mut archive = tar::Builder::new(file);
+    for (name, bytes) in std::iter::once((MANIFEST.to_string(), manifest)).chain(files.iter().cloned()) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o600);
+        header.set_mtime(Utc::now().timestamp() as u64);
+        archive.append_data(&mut header, &name, bytes.as_slice()).map_err(|e| format!("failed to write {}: {}", path, e))?;
+    }
+    archive.finish().map_err(|e| format!("failed to write {}: {}", path, e))
+}
+
+fn print_written(args: &Args, path: &str, files: &[(String, Vec<u8>)], what: &str) -> Result<(), String> {
+    if args.json_output()? {
+        let files: Vec<serde_json::Value> = files.iter()
+            .map(|(name, bytes)| serde_json::json!({ "path": name, "size": bytes.len() }))
+            .collect();
+        println!("{}", serde_json::json!({ "archive": path, "files": files }));
+        return Ok(());
+    }
+    for (name, bytes) in files {
+        println!("  {} ({} bytes)", name, bytes.len());
+    }
+    println!("{} written to {}", what, path);
+    Ok(())
+}
+
+// `export --everything`: every file the tracker keeps for any profile, plus each profile's whole
+// history as JSON lines whatever the storage backend, so it can be read without this tool.
+// Unlike a backup it is not meant for restore.
+pub fn run_export_everything(args: &Args) -> Result<(), String> {
+    let default_path = format!("window-tracker-export-{}.tar", Local::now().format("%Y-%m-%d"));
+    let path = args.value("--output").unwrap_or(&default_path);
+
+    let mut files = Vec::new();
+    collect_tree(&storage::base_data_dir(), "data", &mut files)?;
+    if let Some(config_dir) = config::base_config_path().parent() {
+        collect_tree(config_dir, "config", &mut files)?;
+    }
+
+    let active = profile::name();
+    for name in std::iter::once(profile::DEFAULT.to_string()).chain(profile::names()) {
+        profile::set_active(&name)?;
+        let history = storage::load_intervals(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC);
+        profile::set_active(&active)?;
+        let history = history.map_err(|e| format!("failed to read the history of profile '{}': {}", name, e))?;
+        if history.is_empty() {
+            continue;
+        }
+        let lines: String = history.iter().map(|interval| storage::encode(interval).unwrap() + "\n").collect();
+        files.push((format!("history/{}.jsonl", name), lines.into_bytes()));
+    }
+    if files.is_empty() {
+        return Err("nothing to export, no history or config found".to_string());
+    }
+
+    write_archive(path, &files)?;
+    print_written(args, path, &files, "Export")
+}
+
+// Every file below `directory`, named `prefix/relative/path`
+fn collect_tree(directory: &Path, prefix: &str, files: &mut Vec<(String, Vec<u8>)>) -> Result<(), String> {
+    let Ok(entries) = fs::read_dir(directory) else { return Ok(()) };
+    let mut entries: Vec<fs::DirEntry> = entries.filter_map(Result::ok).collect();
+    entries.sort_by_key(fs::DirEntry::file_name);
+    for entry in entries {
+        let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+        let path = entry.path();
+        if path.is_dir() {
+            collect_tree(&path, &name, files)?;
+        } else if let Some(bytes) = snapshot(&path).map_err(|e| format!("failed to read {}: {}", path.display(), e))? {
+            files.push((name, bytes));
+        }
+    }
+    Ok(())
+}
+
+pub fn run_restore(args: &Args) -> Result<(), String> {
+    let path = args.arg(1).ok_or("usage: restore <archive.tar> [--force]")?;
+    let json = args.json_output()?;
+    let restored = restore(path, args.flag("--force"))?;
+    if json {
+        let restored: Vec<String> = restored.iter().map(|destination| destination.display().to_string()).collect();
+        println!("{}", serde_json::json!({ "restored": restored }));
+    } else {
+        for destination in &restored {
+            println!("Restored {}", destination.display());
+        }
+    }
+    Ok(())
+}
+
+fn restore(path: &str, force: bool) -> Result<Vec<PathBuf>, String> {
+    let file = File::open(path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+
+    let mut entries = Vec::new();
+    let mut archive = tar::Archive::new(file);
+    for entry in archive.entries().map_err(|e| format!("failed to read {}: {}", path, e))? {
+        let mut entry = entry.map_err(|e| format!("failed to read {}: {}", path, e))?;
+        let name = entry.path().map_err(|e| e.to_string())?.to_string_lossy().into_owned();
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes).map_err(|e| format!("failed to read {}: {}", name, e))?;
+        entries.push((name, bytes));
+    }
+
+    let files = verify(entries)?;
+
+    // Queued intervals and the checkpoint of an open one belong to the history being replaced, left
+    // behind they would be stored into the restored one
+    let stale: Vec<PathBuf> = [storage::data_dir().join(PENDING_FILE), journal::journal_path()]
+        .into_iter()
+        .filter(|path| path.exists() && !files.iter().any(|(destination, _)| destination == path))
+        .collect();
+    let existing: Vec<&PathBuf> = files.iter().map(|(destination, _)| destination).filter(|path| path.exists()).chain(&stale).collect();
+    if !existing.is_empty() && !force {
+        let list: Vec<String> = existing.iter().map(|path| format!("  {}", path.display())).collect();
+        return Err(format!("restoring would replace existing files, pass --force to keep them as *.before-restore:\n{}", list.join("\n")));
+    }
+
+    for (destination, bytes) in &files {
+        write_replacing(destination, bytes).map_err(|e| format!("failed to restore {}: {}", destination.display(), e))?;
+    }
+    for path in &stale {
+        fs::rename(path, kept(path)).map_err(|e| format!("failed to set aside {}: {}", path.display(), e))?;
+    }
+    storage::history_replaced().map_err(|e| format!("failed to reset what was derived from the replaced history: {}", e))?;
+    Ok(files.into_iter().map(|(destination, _)| destination).collect())
+}
+
+// Where a file replaced by a restore is kept
+fn kept(path: &Path) -> PathBuf {
+    let mut kept = path.as_os_str().to_owned();
+    kept.push(".before-restore");
+    PathBuf::from(kept)
+}
+
+// Checks the archive against its manifest before anything on disk is touched.
+fn verify(entries: Vec<(String, Vec<u8>)>) -> Result<Vec<(PathBuf, Vec<u8>)>, String> {
+    let manifest = entries.iter()
+        .find(|(name, _)| name == MANIFEST)
+        .ok_or("not a window tracker backup, manifest.json is missing")?;
+    let manifest: Manifest = serde_json::from_slice(&manifest.1).map_err(|e| format!("unreadable manifest: {}", e))?;
+    if manifest.format > FORMAT_VERSION {
+        return Err(format!("backup format {} is newer than this build supports ({})", manifest.format, FORMAT_VERSION));
+    }
+
+    let mut files = Vec::new();
+    for (name, bytes) in entries.into_iter().filter(|(name, _)| name != MANIFEST) {
+        let listed = manifest.files.iter()
+            .find(|file| file.path == name)
+            .ok_or_else(|| format!("{} is not listed in the manifest", name))?;
+        if listed.size != bytes.len() as u64 || listed.sha256 != sha256_hex(&bytes) {
+            return Err(format!("{} is corrupted, its checksum does not match the manifest", name));
+        }
+        if name.ends_with(".db") && !bytes.starts_with(b"SQLite format 3\0") {
+            return Err(format!("{} is not a SQLite database", name));
+        }
+        let destination = destination(&name).ok_or_else(|| format!("unexpected file {} in backup", name))?;
+        files.push((destination, bytes));
+    }
+
+    if let Some(missing) = manifest.files.iter().find(|file| !files.iter().any(|(path, _)| destination(&file.path).as_ref() == Some(path))) {
+        return Err(format!("{} is listed in the manifest but missing from the archive", missing.path));
+    }
+    Ok(files)
+}
+
+fn write_replacing(destination: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temporary = destination.with_extension("restoring");
+    fs::write(&temporary, bytes)?;
+    if destination.exists() {
+        fs::rename(destination, kept(destination))?;
+    }
+    fs::rename(temporary, destination)
+}
+
+fn read_optional(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// A live database is copied through SQLite itself so the snapshot is consistent even while tracking.
+fn snapshot(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    #[cfg(feature = "sqlite")]
+    if path.extension().is_some_and(|extension| extension == "db") && path.exists() {
+        let copy = path.with_extension("db.backup");
+        let _ = fs::remove_file(&copy);
+        let connection = rusqlite::Connection::open(path).map_err(io::Error::other)?;
+        connection.execute("VACUUM INTO ?1", [copy.to_string_lossy()]).map_err(io::Error::other)?;
+        let bytes = fs::read(&copy);
+        let _ = fs::remove_file(&copy);
+        return bytes.map(Some);
+    }
+
+    read_optional(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::storage::Interval;
+
+    fn interval(title: &str, minute: u64) -> Interval {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + minute * 60);
+        Interval::new(title, "editor", start, start + Duration::from_secs(60))
+    }
+
+    fn titles() -> Vec<String> {
+        let mut titles: Vec<String> = storage::load_intervals(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC).unwrap()
+            .into_iter().map(|interval| interval.title).collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn a_restore_brings_back_queued_intervals_and_nothing_queued_since() {
+        let (_turn, base) = storage::testing::data_dir();
+        fs::create_dir_all(config::config_path().parent().unwrap()).unwrap();
+        fs::write(config::config_path(), "idle_secs = 120\n").unwrap();
+        storage::append_interval(&interval("stored", 0)).unwrap();
+        storage::queue_interval(&interval("queued", 1)).unwrap();
+        let archive = base.join("backup.tar").display().to_string();
+        let files = backup_files().unwrap();
+        assert!(files.iter().any(|(name, _)| name == "data/pending.jsonl"));
+        write_archive(&archive, &files).unwrap();
+
+        storage::queue_interval(&interval("queued after the backup", 2)).unwrap();
+        journal::write(&interval("open after the backup", 3)).unwrap();
+        assert!(restore(&archive, false).unwrap_err().contains("--force"));
+        restore(&archive, true).unwrap();
+        assert_eq!(titles(), ["queued", "stored"]);
+        assert_eq!(journal::read().unwrap(), None);
+        assert!(kept(&journal::journal_path()).exists());
+        // The restored queue goes into the history once
+        storage::flush().unwrap();
+        assert_eq!(titles(), ["queued", "stored"]);
+        let _ = fs::remove_dir_all(&base);
+    }
+}
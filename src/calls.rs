@@ -0,0 +1,283 @@
+// Call detection: while the camera or microphone is in use the tracker marks intervals as calls,
+// whatever has focus. Reports split the time in meeting apps into in a call and just open.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::{self, Config};
+use crate::storage::Interval;
+
+// Looking at every process's open files isn't free, calls don't need second precision
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Devices {
+    pub camera: bool,
+    pub microphone: bool,
+}
+
+impl Devices {
+    pub fn in_call(&self) -> bool {
+        self.camera || self.microphone
+    }
+}
+
+#[derive(Default)]
+pub struct CallWatch {
+    last_check: Option<Instant>,
+}
+
+impl CallWatch {
+    pub fn check(&mut self, config: &Config) {
+        if self.last_check.is_some_and(|last| last.elapsed() < CHECK_INTERVAL) {
+            return;
+        }
+        self.last_check = Some(Instant::now());
+        let detect = config.calls.as_ref().is_none_or(|calls| calls.detect);
+        crate::wt_set_in_call(detect && devices().in_call());
+    }
+}
+
+pub struct MeetingApp {
+    pub app: String,
+    pub call_secs: f64,
+    // Focused without the camera or microphone in use
+    pub open_secs: f64,
+}
+
+// Focus time in the configured meeting apps, most time in calls first
+pub fn meeting_apps(intervals: &[Interval], config: &Config) -> Vec<MeetingApp> {
+    let apps = config.calls.as_ref().map(|calls| calls.apps.clone()).unwrap_or_else(config::default_meeting_apps);
+    let mut totals: HashMap<&str, (f64, f64)> = HashMap::new();
+    for interval in intervals {
+        let app = interval.app.strip_suffix(".exe").unwrap_or(&interval.app);
+        if !apps.iter().any(|name| name.eq_ignore_ascii_case(app)) {
+            continue;
+        }
+        let (call_secs, open_secs) = totals.entry(interval.app_name()).or_insert((0.0, 0.0));
+        if interval.call {
+            *call_secs += interval.duration_secs();
+        } else {
+            *open_secs += interval.duration_secs();
+        }
+    }
+    let mut apps: Vec<MeetingApp> = totals.into_iter()
+        .map(|(app, (call_secs, open_secs))| MeetingApp { app: app.to_string(), call_secs, open_secs })
+        .collect();
+    apps.sort_by(|a, b| b.call_secs.total_cmp(&a.call_secs).then_with(|| a.app.cmp(&b.app)));
+    apps
+}
+
+// Windows records when each app last started and stopped using a privacy-sensitive device, the same
+// bookkeeping behind the microphone icon in the taskbar
+#[cfg(windows)]
+pub fn devices() -> Devices {
+    Devices { camera: consent::in_use("webcam"), microphone: consent::in_use("microphone") }
+}
+
+#[cfg(windows)]
+mod consent {
+    use windows::core::{HSTRING, PWSTR};
+    use windows::Win32::System::Registry::{
+        RegCloseKey, RegEnumKeyExW, RegOpenKeyExW, RegQueryValueExW, HKEY, HKEY_CURRENT_USER, KEY_READ,
+    };
+
+    pub fn in_use(capability: &str) -> bool {
+        let path = format!(r"Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\{}", capability);
+        unsafe {
+            let Some(key) = open(HKEY_CURRENT_USER, &path) else { return false };
+            // Store apps are right below, desktop apps below NonPackaged
+            let in_use = any_in_use(key, 2);
+            let _ = RegCloseKey(key);
+            in_use
+        }
+    }
+
+    unsafe fn open(parent: HKEY, path: &str) -> Option<HKEY> {
+        let mut key = HKEY::default();
+        RegOpenKeyExW(parent, &HSTRING::from(path), 0, KEY_READ, &mut key).is_ok().then_some(key)
+    }
+
+    unsafe fn qword(key: HKEY, name: &str) -> Option<u64> {
+        let mut value = 0u64;
+        let mut size = std::mem::size_of::<u64>() as u32;
+        let data = &mut value as *mut u64 as *mut u8;
+        RegQueryValueExW(key, &HSTRING::from(name), None, None, Some(data), Some(&mut size)).is_ok().then_some(value)
+    }
+
+    // A start without a stop is an app using the device right now
+    unsafe fn any_in_use(key: HKEY, depth: u32) -> bool {
+        if qword(key, "LastUsedTimeStart").is_some_and(|start| start != 0) && qword(key, "LastUsedTimeStop") == Some(0) {
+            return true;
+        }
+        if depth == 0 {
+            return false;
+        }
+        for index in 0.. {
+            let mut name = [0u16; 512];
+            let mut length = name.len() as u32;
+            if RegEnumKeyExW(key, index, PWSTR(name.as_mut_ptr()), &mut length, None, PWSTR::null(), None, None).is_err() {
+                break;
+            }
+            let Some(child) = open(key, &String::from_utf16_lossy(&name[..length as usize])) else { continue };
+            let in_use = any_in_use(child, depth - 1);
+            let _ = RegCloseKey(child);
+            if in_use {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// CoreAudio and CoreMediaIO tell whether any process is running a device, which is what lights up
+// the orange and green dots in the menu bar
+#[cfg(target_os = "macos")]
+pub fn devices() -> Devices {
+    Devices { camera: media_io::camera_running(), microphone: media_io::microphone_running() }
+}
+
+#[cfg(target_os = "macos")]
+mod media_io {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    struct PropertyAddress {
+        selector: u32,
+        scope: u32,
+        element: u32,
+    }
+
+    const fn code(name: &[u8; 4]) -> u32 {
+        u32::from_be_bytes(*name)
+    }
+
+    const SYSTEM_OBJECT: u32 = 1;
+    const SCOPE_GLOBAL: u32 = code(b"glob");
+    const ELEMENT_MAIN: u32 = 0;
+    const DEFAULT_INPUT_DEVICE: u32 = code(b"dIn ");
+    const DEVICES: u32 = code(b"dev#");
+    const IS_RUNNING_SOMEWHERE: u32 = code(b"gone");
+
+    #[link(name = "CoreAudio", kind = "framework")]
+    extern "C" {
+        fn AudioObjectGetPropertyData(
+            object: u32,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            data_size: *mut u32,
+            data: *mut c_void,
+        ) -> i32;
+    }
+
+    #[link(name = "CoreMediaIO", kind = "framework")]
+    extern "C" {
+        fn CMIOObjectGetPropertyDataSize(
+            object: u32,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            data_size: *mut u32,
+        ) -> i32;
+        fn CMIOObjectGetPropertyData(
+            object: u32,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            data_size: u32,
+            data_used: *mut u32,
+            data: *mut c_void,
+        ) -> i32;
+    }
+
+    fn address(selector: u32) -> PropertyAddress {
+        PropertyAddress { selector, scope: SCOPE_GLOBAL, element: ELEMENT_MAIN }
+    }
+
+    fn audio_u32(object: u32, selector: u32) -> Option<u32> {
+        let mut value = 0u32;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        let status = unsafe {
+            AudioObjectGetPropertyData(object, &address(selector), 0, std::ptr::null(), &mut size, &mut value as *mut u32 as *mut c_void)
+        };
+        (status == 0).then_some(value)
+    }
+
+    pub fn microphone_running() -> bool {
+        audio_u32(SYSTEM_OBJECT, DEFAULT_INPUT_DEVICE)
+            .filter(|device| *device != 0)
+            .and_then(|device| audio_u32(device, IS_RUNNING_SOMEWHERE))
+            .is_some_and(|running| running != 0)
+    }
+
+    pub fn camera_running() -> bool {
+        let devices_address = address(DEVICES);
+        let mut size = 0u32;
+        if unsafe { CMIOObjectGetPropertyDataSize(SYSTEM_OBJECT, &devices_address, 0, std::ptr::null(), &mut size) } != 0 {
+            return false;
+        }
+        let mut devices = vec![0u32; size as usize / std::mem::size_of::<u32>()];
+        let mut used = 0u32;
+        let status = unsafe {
+            CMIOObjectGetPropertyData(
+                SYSTEM_OBJECT, &devices_address, 0, std::ptr::null(), size, &mut used, devices.as_mut_ptr() as *mut c_void,
+            )
+        };
+        if status != 0 {
+            return false;
+        }
+        devices.truncate(used as usize / std::mem::size_of::<u32>());
+        devices.into_iter().any(|device| {
+            let mut running = 0u32;
+            let mut used = 0u32;
+            let status = unsafe {
+                CMIOObjectGetPropertyData(
+                    device,
+                    &address(IS_RUNNING_SOMEWHERE),
+                    0,
+                    std::ptr::null(),
+                    std::mem::size_of::<u32>() as u32,
+                    &mut used,
+                    &mut running as *mut u32 as *mut c_void,
+                )
+            };
+            status == 0 && running != 0
+        })
+    }
+}
+
+// A video device held open by any process of the user for the camera, a capture stream in the
+// RUNNING state for the microphone (PipeWire and PulseAudio only open the device while recording)
+#[cfg(target_os = "linux")]
+pub fn devices() -> Devices {
+    Devices { camera: camera_open(), microphone: capture_running() }
+}
+
+#[cfg(target_os = "linux")]
+fn camera_open() -> bool {
+    let Ok(processes) = std::fs::read_dir("/proc") else { return false };
+    processes.filter_map(Result::ok)
+        .filter(|process| process.file_name().to_string_lossy().bytes().all(|byte| byte.is_ascii_digit()))
+        .filter_map(|process| std::fs::read_dir(process.path().join("fd")).ok())
+        .flat_map(|files| files.filter_map(Result::ok))
+        .any(|file| std::fs::read_link(file.path()).is_ok_and(|target| target.to_string_lossy().starts_with("/dev/video")))
+}
+
+#[cfg(target_os = "linux")]
+fn capture_running() -> bool {
+    let children = |path: &std::path::Path| std::fs::read_dir(path).into_iter().flatten().filter_map(Result::ok);
+    children(std::path::Path::new("/proc/asound"))
+        .filter(|card| card.file_name().to_string_lossy().starts_with("card"))
+        .flat_map(|card| children(&card.path()))
+        .filter(|pcm| {
+            let name = pcm.file_name().to_string_lossy().into_owned();
+            name.starts_with("pcm") && name.ends_with('c')
+        })
+        .flat_map(|pcm| children(&pcm.path()))
+        .any(|stream| std::fs::read_to_string(stream.path().join("status")).is_ok_and(|status| status.contains("state: RUNNING")))
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn devices() -> Devices {
+    Devices::default()
+}
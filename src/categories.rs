@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+
+use regex::Regex;
+
+use crate::config::Config;
+
+pub const UNCATEGORIZED: &str = "Uncategorized";
+
+struct Rule {
+    name: String,
+    apps: Vec<String>,
+    titles: Vec<Regex>,
+    fields: Vec<(String, Regex)>,
+}
+
+#[derive(Default)]
+pub struct Categorizer {
+    rules: Vec<Rule>,
+}
+
+impl Categorizer {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for category in &config.categories {
+            if let Some(color) = category.color.as_deref().filter(|color| !is_color(color)) {
+                return Err(format!("invalid color '{}' for category '{}', expected #rgb or #rrggbb", color, category.name));
+            }
+            let titles = category.titles.iter()
+                .map(|pattern| Regex::new(pattern)
+                    .map_err(|e| format!("invalid title pattern for category '{}': {}", category.name, e)))
+                .collect::<Result<_, _>>()?;
+            let fields = category.fields.iter()
+                .map(|(field, pattern)| Regex::new(pattern)
+                    .map(|pattern| (field.clone(), pattern))
+                    .map_err(|e| format!("invalid pattern for field '{}' of category '{}': {}", field, category.name, e)))
+                .collect::<Result<_, _>>()?;
+            rules.push(Rule {
+                name: category.name.clone(),
+                apps: category.apps.iter().map(|app| app.to_lowercase()).collect(),
+                titles,
+                fields,
+            });
+        }
+        Ok(Categorizer { rules })
+    }
+
+    // `fields` are what the title templates extracted from the title
+    pub fn categorize(&self, title: &str, app: &str, fields: &BTreeMap<String, String>) -> &str {
+        let app = app.to_lowercase();
+        let fields_match = |rule: &Rule| {
+            !rule.fields.is_empty()
+                && rule.fields.iter().all(|(field, pattern)| fields.get(field).is_some_and(|value| pattern.is_match(value)))
+        };
+        self.rules.iter()
+            .find(|rule| rule.apps.contains(&app) || rule.titles.iter().any(|pattern| pattern.is_match(title)) || fields_match(rule))
+            .map(|rule| rule.name.as_str())
+            .unwrap_or(UNCATEGORIZED)
+    }
+}
+
+// #rgb or #rrggbb, which SVG and CSS both take as they are
+fn is_color(color: &str) -> bool {
+    color.strip_prefix('#').is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CategoryStyle {
+    pub color: Option<String>,
+    pub icon: Option<String>,
+}
+
+// The color and icon of each category that has one
+#[derive(Debug, Clone, Default)]
+pub struct CategoryStyles {
+    styles: HashMap<String, CategoryStyle>,
+}
+
+impl CategoryStyles {
+    pub fn from_config(config: &Config) -> Self {
+        let styles = config.categories.iter()
+            .filter(|category| category.color.is_some() || category.icon.is_some())
+            .map(|category| (category.name.clone(), CategoryStyle { color: category.color.clone(), icon: category.icon.clone() }))
+            .collect();
+        CategoryStyles { styles }
+    }
+
+    pub fn get(&self, category: &str) -> Option<&CategoryStyle> {
+        self.styles.get(category)
+    }
+
+    pub fn color(&self, category: &str) -> Option<&str> {
+        self.get(category).and_then(|style| style.color.as_deref())
+    }
+
+    // "💻 Coding" with an icon, the name alone without
+    pub fn label(&self, category: &str) -> String {
+        match self.get(category).and_then(|style| style.icon.as_deref()) {
+            Some(icon) => format!("{} {}", icon, category),
+            None => category.to_string(),
+        }
+    }
+}
@@ -0,0 +1,565 @@
+use std::io::IsTerminal;
+
+pub const USAGE: &str = "\
+Usage: window_tracker_concept [command] [options]
+
+Every command accepts -o json to print JSON instead of text (for track: --stream json), and
+report, today, current, query and rules test take --format <table|json|csv|tsv|markdown> to print
+their rows as an aligned table, a JSON array, CSV, TSV or a Markdown table instead;
+--profile <name> (or WT_PROFILE) to use a profile with its own history and config, and
+--lang <en|de|es|ja> for reports and messages in that language (default from LC_ALL, LC_MESSAGES or LANG).
+Colors are only used on a terminal, and never when NO_COLOR is set.
+report, purge and export --influx take --filter <expr> to look at only some intervals, e.g.
+  --filter 'app = \"firefox\" AND category != \"Work\" AND duration > 5m'
+comparing app, title, category, class, window, event and field:<name> with =, != (ignoring case),
+~ or !~ (a regex), duration with a number and s, m or h, start and end with a day, and call with
+true or false, joined with AND, OR, NOT and parentheses; GET /api/intervals takes it as filter=.
+
+Commands:
+  init                      First-run setup: where to keep the history, titles or only apps, the idle
+                            threshold and starting at login; writes config.toml and sets up
+                            starting at login as autostart enable does
+  autostart <enable|disable|status>
+                            Start tracking at login: a systemd user unit or XDG autostart entry on
+                            Linux, a LaunchAgent on macOS, a Run key entry or Startup folder shortcut
+                            on Windows; enabling one removes the others
+      --method <method>     systemd, xdg, launchagent, run-key or startup-folder (default: systemd
+                            where user systemd runs, else xdg; run-key on Windows)
+  track                     Track the focused window (default), and always-on-top windows like
+                            picture-in-picture video, reported as concurrent media; on Linux
+                            through X11, or sway's and Hyprland's IPC with --features wayland
+      --low-power           Poll less often while focus is stable and the user is idle
+      --separate-dialogs    Track dialogs on their own instead of as part of their parent window
+      --visible             Also record every window on screen, for reports on apps open in the background
+      --ephemeral           Keep everything in memory, for the live display, --stream and the APIs:
+                            nothing is written to disk and all of it is gone on exit
+      --stream json         Print focus_changed, title_changed, idle_start, idle_end,
+                            interval_finalized, config_reloaded and profile_switched events
+                            as JSON lines instead of the status display
+                            (title_debounce_ms = 1000 in config.toml coalesces flickering titles)
+      --menubar             macOS: a menu bar item with today's time instead of the status display,
+                            click it for today's top apps and a pause toggle (needs --features menubar)
+  report [options]          Summarize recorded focus time
+      --days <n>            Number of days to include (default: 7)
+      --range <period>      week, month or year for the last 7, 30 or 365 days, or <from>..<to>
+                            as in recategorize; from 31 days on it adds up daily totals kept in
+                            aggregates.json next to the history (not with --filter, or --by window
+                            or field), so long ranges stay fast
+      --by <title|window|app|class|rule|field:<name>>
+                            Group by title, native window so identical titles stay apart, app,
+                            window class, category or a title template field
+                            (default: aggregate_by in config.toml)
+      --filter <expr>       Only intervals the expression picks (see above)
+      --group-by <keys>     A row per combination of app, title, category, class, window, event,
+                            day, weekday or field:<name> instead, e.g. app,day
+      --rollup <list>       Columns of each row: sum, count, avg or max of the intervals' seconds,
+                            max_streak, the longest run with breaks under a minute (default: sum)
+      --compare <day|week>  Compare per-app time against the previous day or week
+      --plain               Numbered lines without indentation, colors or abbreviated units,
+                            for screen readers (also with --compare)
+      --games               Playtime per game found with [games]: the total, sessions and when
+                            each was last played, kept in games.json next to the history
+      --rebuild             With --games, rebuild games.json from the history, e.g. after an import
+      --dnd                 Focus time, switches per hour and mean focus between switches with
+                            Do Not Disturb on and off, as [dnd] recorded them
+      --power               Focus time on battery and on AC, its share, switches, days and top
+                            app per power source, as [power] recorded them
+      --html <path>         Write a self-contained HTML report to <path>
+      --notify              Show a desktop notification when unusual usage is found
+      --mail-to <address>   Email the HTML report to <address> (needs --features server)
+      --mail-from <address> Sender address (default: window-tracker@localhost)
+      --smtp <host:port>    SMTP relay to send through (default: localhost:25)
+  export [options]          Export recorded history
+      --heatmap             Year-view calendar heatmap of daily focus time per category
+      --format <svg|json>   Heatmap output format (default: svg)
+      --year <yyyy>         Calendar year to cover (default: the last 365 days)
+      --category <names>    Comma-separated categories to include (default: all)
+      --invoice             Per-day worklog of one category for invoicing
+      --client <category>   Category to bill
+      --rate <amount>       Hourly rate
+      --month <yyyy-mm>     Month to cover (default: this month)
+      --round <minutes>     Round each day up to this increment (default: 1)
+      --currency <code>     Shown next to amounts, e.g. EUR
+      --format <csv|html|json>
+                            Invoice output format (default: csv), print the HTML to get a PDF
+      --languages           Per-day editor time by the language of the open file
+      --days <n>            Number of days to cover (default: 30)
+      --format <csv|json>   Languages output format (default: csv)
+      --output <path>       Write to <path> instead of stdout
+      --influx              Intervals and daily switch counts as InfluxDB line protocol
+      --days <n>            Number of days to cover (default: 30)
+      --measurement <name>  Measurement of the intervals (default: measurement under [influx], or focus)
+      --filter <expr>       Only intervals the expression picks
+      --output <path>       Write to <path> instead of stdout
+      --format markdown     A Markdown note per tracked day, with front-matter and tables, for a notes
+                            vault like Obsidian's or Logseq's
+      --output <folder>     The folder the notes go into
+      --template <file>     Placeholders {date}, {day}, {total}, {total_secs}, {hours}, {switches} and
+                            the tables {windows}, {apps} and {categories} (default: a built-in note)
+      --days <n>            Number of days, a note each (default: 1, today)
+      --name <pattern>      strftime file name, e.g. %Y_%m_%d.md for Logseq (default: %Y-%m-%d.md)
+      --force               Rewrite notes already in the folder
+      --format <csv|jsonl>  Every stored interval as a CSV row or JSON line, written while the
+                            history is read so any size exports in little memory (both import back;
+                            JSON lines carry their format version, a build older than it refuses them)
+      --days <n>            Number of days to cover (default: all of the history)
+      --filter <expr>       Only intervals the expression picks
+      --since-last          Only intervals stored since the last --since-last export to the same
+                            --output in this format, all of them again after a purge or recategorize
+      --output <path>       Write to <path> instead of stdout
+      --everything          Archive all data of every profile, with each history as JSON lines
+                            (needs --features backup, --output defaults to window-tracker-export-<date>.tar)
+  backup <archive.tar>      Archive the history, with intervals not yet moved into it, and config
+                            into one file
+  restore <archive.tar>     Verify a backup and restore it
+      --force               Replace existing files, keeping them as *.before-restore, as well as
+                            intervals queued and the open interval's checkpoint since the backup
+  self-update               Install the latest GitHub release over this executable, checked against
+                            its signed SHA256SUMS, and restart the running tracker into it
+                            (needs --features self-update)
+      --check               Only say whether there is a newer release
+      --unsigned            Update on the checksum alone, for builds without a signing key
+  serve --read-only --public <folder>
+                            Share the reports in <folder> (like report --html output) over HTTP,
+                            HTML, CSS, images and PDF only: no history, no exports, no API, no
+                            control of the tracker
+                            (needs --features server, like everything that opens a port)
+      --address <addr>      Where to listen (default: 127.0.0.1:9480, 0.0.0.0:<port> for others)
+  hub serve                 Collect the history the trackers with [hub] push, for reports over all
+                            of them by user and device (needs --features hub)
+      --address <addr>      Where to listen (default: [hub_server] address, 0.0.0.0:9470)
+  hub token --user <name>   Add a member who may push to this hub and read its team report,
+                            printing the token for their [hub] config
+      --team                A team member: only their categories and times are taken, for --team
+      --admin               May also read the report by user and device over HTTP
+  hub push                  Push what was stored since the last push now, as the tracker does
+                            every push_secs
+  hub report [options]      The pushed time of everyone but team members as a pivot, as report
+                            --group-by
+      --group-by <keys>     Keys as for report, field:user and field:device among them
+                            (default: field:user,field:device)
+      --rollup, --days, --filter, --format, -o json as for report --group-by
+      --team                Time per category over the team members, without who, only categories
+                            with time from team_min_people (3) of them
+  review                    Pick recently tracked titles never to track, or to track without the title
+      --days <n>            Number of days to look back (default: 7)
+  merge                     Pick recently tracked titles to total as one, rename or never track,
+                            saved as [[merge]] and [[ignore]] rules
+      --days <n>            Number of days to look back (default: 7)
+  purge [options]           Delete history, showing what would go unless --force is given
+      --before <yyyy-mm-dd> Everything before that day
+      --app <pattern>       Windows of apps matching the pattern (combines with the others)
+      --title <pattern>     Windows with titles matching the pattern
+      --filter <expr>       Intervals the expression picks
+      --all                 All of the profile's data, the config is kept
+      --force               Really delete
+  status                    Whether a tracker is running, and whether it can store and capture
+      --check               Print JSON and exit 2 when none is running, 3 when storage fails,
+                            4 when a permission is missing or 5 when windows can't be read
+  verify                    Check that no intervals overlap, run backwards or end in the future and
+                            that no day holds more than 24 hours; fails when any do
+      --repair              Trim overlaps and future ends and delete backwards intervals
+  rules test                Re-run the config's ignore rules, title templates and categories over
+                            stored intervals and show which categories would change, storing
+                            nothing; WT_CONFIG=<draft.toml> tries out a draft
+      --since <day>         today, yesterday (default), <n>d or yyyy-mm-dd
+  recategorize              Store the categories and fields the current rules give stored intervals,
+                            keeping their titles and apps
+      --range <from>..<to>  The days to go over, either end may be left out (e.g. 30d.., 2024-01-01..)
+      --undo                Put back what the last recategorize changed
+  recall --at <time>        What was focused then (hh:mm, yesterday hh:mm or yyyy-mm-dd hh:mm), with
+                            the screen thumbnail taken at the switch to it under [thumbnails]
+  game-names                An [[enrichers]] plugin naming game executables as their Steam or Epic
+                            library does, \"ELDEN RING\" for eldenring.exe (see below)
+  current                   Show the window being tracked right now
+  today                     Show today's focus time and top windows
+  pause                     Pause the running tracker until resume
+  resume                    Resume a paused tracker
+  switch-profile <name>     Make the running tracker record into another profile
+  enforce [status]          Today's use of each [enforce] limit (-o json, --format)
+  enforce set-pin           Guard [enforce] with a PIN, kept as a hash in config.toml
+  enforce unlock            Let the running tracker take [enforce] changes and pause
+      --mins <n>            For how long (default: 15)
+  enforce lock              Lock again before the time is up
+  api-token                 Add a bearer token for the [health] HTTP API to config.toml and print it
+      --scopes <read,write> What the token may do (default: read)
+  jira-push                 Add time on windows mentioning a ticket to its Jira worklog
+      --days <n>            Number of days to push (default: 1, today)
+      --dry-run             Show the worklogs without pushing them
+  import <file.csv> [options]
+                            Backfill history from a spreadsheet or another tracker's CSV export
+      --map <fields>        Which columns make up each field, e.g. \"start=Date+From,end=Date+To,app=Tool\":
+                            start, end, duration (secs or h:mm), hours, app, title, category and
+                            device from column headers, #<n> for the nth column or 'text', joined by +
+      --device <name>       Where rows without a device column were tracked, kept as field:device
+      --overlap <mode>      Who gets time claimed twice, by the history and a row or by two rows:
+                            keep (default, both), trim (the history, or the earlier row), split
+                            (shared equally) or prefer-device with --prefer <device> (local for
+                            the history); shortened, split and dropped intervals are listed
+      --format <csv|jsonl>  Input format (default: csv); jsonl reads `export --format jsonl` from
+                            any version up to this one's, without --map
+      --delimiter <char>    Field separator, or tab (default: ,)
+      --time-format <fmt>   strftime format of the times (default: RFC 3339 or yyyy-mm-dd hh:mm[:ss])
+      --no-header           The first row is data, map columns by #<n>
+      --dry-run             Show what would be imported without saving it
+  import-activity           Fetch pushes and pull requests so reports label editor/terminal time with repos
+      --days <n>            Number of days to fetch (default: 7)
+  status-updates <on|off>   Let the running tracker set the Slack/Discord status, or clear it and stop
+  doctor                    Check the setup, run a 5 second capture test and print a report
+  powershell-module         Print a PowerShell module wrapping these commands (see --output)
+  query <sql> [options]     Run read-only SQL against the history (needs --features sqlite)
+      --format <table|json|csv|tsv|markdown>
+                            Output format (default: table)
+
+On Windows, apps running as administrator are recorded as \"(elevated window)\" unless the tracker
+runs elevated too, or with UIAccess: embed a manifest with uiAccess=\"true\", sign the executable
+and install it under Program Files, then it reads every window without running elevated.
+
+Time with the desktop or no window focused, like on the lock screen or during a UAC prompt, is
+recorded as \"(desktop / no window)\" so totals add up to the time tracked; an [[ignore]] rule with
+app = \"(desktop / no window)\" leaves it untracked instead. Likewise samples the platform couldn't
+answer are recorded as \"(sampling failed)\" or \"(not responding)\", focused windows without a
+title get \"(no title)\" and, on macOS without Screen Recording, \"(permission denied)\"; status
+shows how many samples went to each.
+
+Categories are defined in config.toml (override the location with WT_CONFIG):
+  [[categories]]
+  name = \"Coding\"
+  apps = [\"code\", \"nvim\"]
+  titles = [\"(?i)visual studio\"]
+  fields = { ticket = \"^ABC-\" }
+  color = \"#4f46e5\"  # the HTML report and heatmap draw Coding in this, #rgb or #rrggbb
+  icon = \"💻\"        # and put this before its name there and in text output
+
+The live display, reports and exports total focus time per title unless a top-level key says
+otherwise: app, class (the window class, the app where the platform has none) or rule (the category):
+  aggregate_by = \"app\"
+
+For stats without any content, a top-level key records only apps and their categories: titles
+are dropped as soon as a window is sampled, so category rules can only match apps, and totals
+are per app instead of per title:
+  privacy = \"apps\"
+
+HTML reports show each app's icon, looked up the first time it is focused and kept in icons/ in
+the data directory; a top-level key stops that:
+  app_icons = false
+
+Daily budgets per category, shown as bars in the track display: a target to reach or a limit
+to stay under, which turns red once it is used up:
+  [[goals]]
+  category = \"Coding\"
+  target_mins = 240
+  [[goals]]
+  category = \"Social\"
+  limit_mins = 30
+
+Fields pulled out of titles, kept with each interval for the fields of category rules and
+report --by field:<name> (without app a template applies to every app, the first match wins):
+  [[title_templates]]
+  app = \"firefox\"
+  template = \"{page} — Mozilla Firefox\"
+  [[title_templates]]
+  template = \"{ticket}: {summary} - Jira\"
+
+Titles totalled as one in the live display and reports by title, in order so a later rule can
+rename what an earlier one merged; the history keeps the titles (merge adds these):
+  [[merge]]
+  into = \"Project X\"
+  titles = [\"(?i)project-x\", \"^X spec\"]
+  app = \"code\"
+
+Windows that are never tracked, or with redact = true tracked as \"[redacted]\" (review adds these):
+  [[ignore]]
+  app = \"keepassxc\"
+  title = \"(?i)private browsing\"
+
+The history is kept in the per-platform data directory unless a top-level key, or WT_DATA_DIR,
+moves it (read at startup):
+  data_dir = \"/home/me/Documents/window_tracker\"
+
+Switch profiles automatically by Wi-Fi network, VPN or connected monitors (top-level config only):
+  [[profile_triggers]]
+  profile = \"office\"
+  ssid = \"Office WiFi\"
+  monitors = 2
+
+Push worklogs to Jira (needs --features jira, the API token is read from JIRA_API_TOKEN):
+  [jira]
+  url = \"https://example.atlassian.net\"
+  email = \"me@example.com\"
+  projects = [\"ABC\"]
+
+Editors whose titles name the open file, for coding time by language (extensions add to the built-in ones):
+  [languages]
+  editors = [\"code\", \"nvim\", \"idea\"]
+  extensions = { rhai = \"Rhai\" }
+
+Time with the camera or microphone in use is marked as a call; reports split these apps into
+in a call and just open (detect = false turns detection off):
+  [calls]
+  apps = [\"zoom\", \"teams\"]
+
+Back after a long gap (tracker start, unlock or wake), show a notification summing up the
+previous session, like \"Yesterday: 6.2h, top app: code\" (needs --features notify):
+  [greeting]
+  gap_mins = 120
+
+Notify when a [[goals]] limit is passed (once a day per goal) and after idle_mins without input
+while tracking goes on; on Windows the toasts have Snooze, Recategorize as <recategorize> (the
+focused app, until the tracker exits) and Pause tracking buttons (needs --features notify):
+  [alerts]
+  limits = true
+  idle_mins = 20
+  snooze_mins = 15
+  recategorize = \"Work\"
+
+Detect games and keep a playtime ledger for report --games: windows of the apps listed, of
+processes started by Steam, Battle.net, Epic, GOG Galaxy and other launchers or the ones listed,
+and on Windows of whatever holds the screen in exclusive fullscreen (fullscreen = false leaves that out); Steam games
+go by their store name and appid, and play less than session_gap_mins (15) apart is one session:
+  [games]
+  apps = [\"factorio\"]
+  launchers = [\"prismlauncher\"]
+
+Record what media players and browsers are playing with the intervals in their windows, as
+field:media and field:media_artist (artist = false leaves it out), so report --by field:media
+breaks VLC time down by what was watched; from MPRIS on Linux, the media sessions on Windows and
+Music, Spotify and TV on macOS, asked every check_secs (needs --features media):
+  [media]
+  check_secs = 5
+
+Follow the system's Do Not Disturb (Focus Assist on Windows, Focus on macOS; GNOME, KDE, Xfce,
+dunst or mako on Linux), checked every check_secs (10): the tracker's own notifications are held
+back while it is on (quiet = false shows them anyway) and intervals are stored with field:dnd on
+or off (record = false leaves it out), for report --dnd:
+  [dnd]
+  check_secs = 10
+
+Record whether the machine runs on battery or AC (sysfs on Linux, GetSystemPowerStatus on
+Windows, pmset on macOS), checked every check_secs (30): intervals are stored with field:power
+battery or ac (record = false leaves it out), for report --power, and on battery the tracker
+samples like --low-power until it is back on AC (low_power_on_battery = false doesn't):
+  [power]
+  check_secs = 30
+
+Enforce daily limits per app (needs --features enforce): warn_mins (5) before a limit a
+notification says what is left, at the limit that time is up, and grace_mins (2) later the app's
+window is minimized, closed or blocked from input (Windows; minimized elsewhere) whenever it has
+focus, until midnight; action = \"warn\" only notifies. Once `enforce set-pin` has set a PIN the
+running tracker keeps these limits through reloads and profile switches and won't pause until
+`enforce unlock`. The PIN guards the tracker's own controls only: for a child, run the tracker
+under an account they can't use and keep config.toml read-only to them:
+  [enforce]
+  [[enforce.limits]]
+  app = \"minecraft\"
+  daily_mins = 60
+  action = \"close\"
+
+Keep a small thumbnail of the screen from every switch to another window, for recall; they stay
+in thumbnails/ next to the history for retention_days, redacted windows aren't captured and
+privacy = \"apps\" excludes them (needs --features thumbnails):
+  [thumbnails]
+  width = 160
+  retention_days = 7
+
+While tracking, make reports on a cron schedule (minute hour day-of-month month day-of-week, or
+@daily and friends): written to path ({date} is the day), mailed, or the JSON summary posted to
+a webhook (needs --features webhook). A run missed while asleep happens on wake-up:
+  [[schedule]]
+  name = \"end of day\"
+  at = \"30 18 * * mon-fri\"
+  path = \"/home/me/reports/{date}.html\"
+  webhook = \"https://hooks.example.com/daily\"
+
+Record remote desktop and VM clients under the machine they show, like \"RDP: build-server\" or
+\"VirtualBox: Ubuntu\" (ignore rules see these titles); clients are rdp, vmware, virtualbox and
+parallels, all of them without the list:
+  [remote]
+  clients = [\"rdp\", \"virtualbox\"]
+
+Pass each finished interval through a plugin, like a small script, before it is stored. It reads
+the interval as a JSON line on stdin and answers with a line like {\"category\": \"Client work\",
+\"attributes\": {\"client\": \"acme\"}}, a \"title\" to redact it with, an \"app\" to rename it, or {} to
+keep it; without an answer within timeout_ms (500) it is stored unchanged:
+  [[enrichers]]
+  name = \"clients\"
+  command = [\"python3\", \"/home/me/enrich.py\"]
+Or as a rhai script run inside the tracker (needs --features scripting), next to config.toml unless
+the path says otherwise. It sees the interval as `interval` and how long it was as `secs`, and its
+value is the same answer as a map, or () to keep it; matches(text, pattern) tests a regex. Scripts
+can't reach files, the network or other programs and are stopped past timeout_ms or
+max_operations (100000); an edited script applies to the next interval:
+  [[enrichers]]
+  name = \"clients\"
+  script = \"clients.rhai\"
+with clients.rhai holding:
+  if matches(interval.title, \"(?i)acme\") { #{ category: \"Client work\", title: \"(private)\" } }
+
+The tracker ships one: game-names records games under the name in their Steam appmanifest or
+Epic manifest (legendary and Heroic's on Linux) as the app and field:game, and the executable as
+field:executable:
+  [[enrichers]]
+  name = \"game names\"
+  command = [\"window_tracker_concept\", \"game-names\"]
+
+A long track session forgets windows and titles not focused for 7 days, and beyond 10000 the least
+recently focused, from the live display; their time stays in the history:
+  [memory]
+  evict_after_days = 2
+  max_windows = 5000
+
+Finished intervals wait in pending.jsonl, where reports already see them, and move into the
+history every 60 seconds or 50 intervals; sync = \"fast\" skips the fsync after each batch:
+  [storage]
+  flush_secs = 300
+  flush_intervals = 200
+  sync = \"fast\"
+
+Durations read like 1h 10m 35s in text and HTML output (hm, hours or seconds instead), with
+the number separators of the locale (default from LC_ALL, LC_NUMERIC or LANG):
+  [format]
+  duration = \"hours\"
+  decimals = 2
+  locale = \"de\"
+
+Import GitHub/GitLab activity (needs --features activity, tokens from GITHUB_TOKEN and GITLAB_TOKEN):
+  [activity.github]
+  user = \"octocat\"
+  [activity.gitlab]
+  user = \"me\"
+  url = \"https://gitlab.example.com\"
+
+Set a Slack or Discord status while tracking (needs --features status):
+  [status]
+  slack = { token_env = \"SLACK_TOKEN\" }
+  [[status.rules]]
+  category = \"Coding\"
+  after_mins = 15
+  text = \"deep work\"
+  emoji = \":brain:\"
+
+Serve the --stream events live over a WebSocket while tracking, with a \"current\" event on connect
+(needs --features websocket; browser pages need their origin listed):
+  [websocket]
+  address = \"127.0.0.1:7878\"
+  origins = [\"http://localhost:3000\"]
+
+pause, resume, switch-profile, status and current ask the running tracker over a Unix socket (a
+named pipe on Windows) with the token it writes to control.json in the data directory, and fall
+back to control files when it doesn't listen; a top-level section picks TCP on localhost instead
+(port 0 for any free one, needs --features server), or \"off\" for only the files:
+  [ipc]
+  transport = \"tcp\"
+  port = 7879
+
+When no window is sampled for stale_secs (60) while the keyboard or mouse is in use, the tracker
+restarts its sampler and status reports it; with an address (needs --features server), GET /healthz
+answers 200 or 503 with the last sample's age, sample counts and any sampling or storage error as
+JSON:
+  [health]
+  address = \"127.0.0.1:9464\"
+  stale_secs = 120
+
+The same address serves GET /api/status and /api/current and POST /api/pause, /api/resume and
+/api/switch-profile?profile=<name>. GET /api/intervals (with group_by and rollup as in report, its
+rows) and /api/windows?by=<grouping> page through the history of from..to (days like today or
+yyyy-mm-dd, at most 31 of them) with offset and limit (100, at most 1000), each client at most
+queries_per_minute (60) times a minute. With tokens (see api-token) each request needs
+Authorization: Bearer <token> with the read or write scope, and only then may the address be
+reachable beyond localhost; tls_cert and tls_key serve HTTPS (needs --features tls):
+  [health]
+  address = \"0.0.0.0:9464\"
+  tls_cert = \"/etc/window_tracker/cert.pem\"
+  tls_key = \"/etc/window_tracker/key.pem\"
+  [[health.tokens]]
+  token = \"<from api-token>\"
+  scopes = [\"read\", \"write\"]
+
+Export intervals to an OpenTelemetry collector while tracking (needs --features otlp):
+  [otlp]
+  endpoint = \"http://localhost:4318\"
+
+Write intervals and, every minute, switch counts as InfluxDB line protocol while tracking: appended
+to path (for Telegraf's tail input), or posted to the v2 write API at url with the token from
+token_env (INFLUX_TOKEN, needs --features influx); points are <measurement> (focus) and
+<measurement>_events:
+  [influx]
+  url = \"http://localhost:8086\"
+  org = \"home\"
+  bucket = \"activity\"
+
+Send focus seconds per category (<prefix>.focus_secs.<category>) and switch counts every interval_secs
+(10) while tracking, as StatsD counters over UDP or with protocol = \"graphite\" to a carbon
+plaintext listener over TCP (prefix defaults to window_tracker):
+  [statsd]
+  address = \"127.0.0.1:8125\"
+  interval_secs = 30
+
+Push the history to a `hub serve` every push_secs (60) while tracking, under device (the host name)
+and the user whose token it is from `hub token` on the hub (needs --features hub); with team = true
+only each interval's category and times leave the machine, for the hub's team report:
+  [hub]
+  url = \"http://hub.home:9470\"
+  token = \"<from hub token>\"
+  device = \"laptop\"
+  team = true
+On the hub, `hub token` adds the members (`--admin` for those who may read the report by user and
+device, team members are never in it):
+  [hub_server]
+  address = \"0.0.0.0:9470\"
+  team_min_people = 3
+  [[hub_server.members]]
+  user = \"alice\"
+  token = \"<from hub token>\"
+  team = true";
+
+// ANSI colors only on a terminal and never when NO_COLOR is set to anything, see no-color.org
+pub fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && std::io::stdout().is_terminal()
+}
+
+pub struct Args {
+    args: Vec<String>,
+}
+
+impl Args {
+    pub fn from_env() -> Self {
+        Args { args: std::env::args().skip(1).collect() }
+    }
+
+    pub fn command(&self) -> Option<&str> {
+        self.args.first().map(String::as_str).filter(|arg| !arg.starts_with("--"))
+    }
+
+    // Positional argument `index`, counting the command as 0
+    pub fn arg(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(String::as_str).filter(|arg| !arg.starts_with("--"))
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.args.iter()
+            .position(|arg| arg == name)
+            .and_then(|i| self.args.get(i + 1))
+            .map(String::as_str)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.args.iter().any(|arg| arg == name)
+    }
+
+    // `-o json` asks any command for machine-readable output instead of text
+    pub fn json_output(&self) -> Result<bool, String> {
+        match self.value("-o") {
+            None | Some("text") => Ok(false),
+            Some("json") => Ok(true),
+            Some(other) => Err(format!("unknown output format '{}', expected 'text' or 'json'", other)),
+        }
+    }
+
+    pub fn parsed<T: std::str::FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.value(name) {
+            Some(value) => value.parse().map_err(|_| format!("invalid value '{}' for {}", value, name)),
+            None => Ok(default),
+        }
+    }
+}
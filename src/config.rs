@@ -0,0 +1,963 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::profile;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // How often the open interval is journaled to survive a crash
+    pub checkpoint_secs: u64,
+    // No keyboard or mouse input for this long counts as idle
+    pub idle_secs: u64,
+    // Title changes of one window reach listeners at most this often, 0 passes every one on
+    pub title_debounce_ms: u64,
+    // What focus time is bucketed by in the live display, reports and exports
+    pub aggregate_by: AggregateBy,
+    // Whether window titles are recorded at all, see privacy
+    pub privacy: Privacy,
+    // Whether each app's icon is kept for HTML reports, see icons
+    pub app_icons: bool,
+    pub categories: Vec<CategoryRule>,
+    pub ignore: Vec<IgnoreRule>,
+    pub merge: Vec<MergeRule>,
+    pub title_templates: Vec<TitleTemplate>,
+    pub schedule: Vec<ScheduledJob>,
+    pub enrichers: Vec<EnricherConfig>,
+    pub goals: Vec<GoalConfig>,
+    pub otlp: Option<OtlpConfig>,
+    pub influx: Option<InfluxConfig>,
+    pub statsd: Option<StatsdConfig>,
+    pub ipc: Option<IpcConfig>,
+    pub jira: Option<JiraConfig>,
+    pub activity: Option<ActivityConfig>,
+    pub status: Option<StatusConfig>,
+    pub languages: Option<LanguagesConfig>,
+    pub calls: Option<CallsConfig>,
+    pub greeting: Option<GreetingConfig>,
+    pub alerts: Option<AlertsConfig>,
+    pub memory: Option<MemoryConfig>,
+    pub remote: Option<RemoteConfig>,
+    pub storage: Option<StorageConfig>,
+    pub format: Option<FormatConfig>,
+    pub websocket: Option<WebSocketConfig>,
+    pub health: Option<HealthConfig>,
+    pub thumbnails: Option<ThumbnailConfig>,
+    pub hub: Option<HubConfig>,
+    pub hub_server: Option<HubServerConfig>,
+    pub games: Option<GamesConfig>,
+    pub media: Option<MediaConfig>,
+    pub enforce: Option<EnforceConfig>,
+    pub dnd: Option<DndConfig>,
+    pub power: Option<PowerConfig>,
+    // Only read from the top-level config.toml, they decide which profile is active
+    pub profile_triggers: Vec<ProfileTrigger>,
+    // Where the history is kept instead of the per-platform default, only read from the top-level
+    // config.toml and only at startup; WT_DATA_DIR overrides it
+    pub data_dir: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            checkpoint_secs: 10,
+            idle_secs: 300,
+            title_debounce_ms: 0,
+            aggregate_by: AggregateBy::Title,
+            privacy: Privacy::Full,
+            app_icons: true,
+            categories: Vec::new(),
+            ignore: Vec::new(),
+            merge: Vec::new(),
+            title_templates: Vec::new(),
+            schedule: Vec::new(),
+            enrichers: Vec::new(),
+            goals: Vec::new(),
+            otlp: None,
+            influx: None,
+            statsd: None,
+            ipc: None,
+            jira: None,
+            activity: None,
+            status: None,
+            languages: None,
+            calls: None,
+            greeting: None,
+            alerts: None,
+            memory: None,
+            remote: None,
+            storage: None,
+            format: None,
+            websocket: None,
+            health: None,
+            thumbnails: None,
+            hub: None,
+            hub_server: None,
+            games: None,
+            media: None,
+            enforce: None,
+            dnd: None,
+            power: None,
+            profile_triggers: Vec::new(),
+            data_dir: None,
+        }
+    }
+}
+
+impl Config {
+    // Without titles every title total would be one empty title, app-only privacy totals per app
+    pub fn grouping(&self) -> AggregateBy {
+        match (self.privacy, self.aggregate_by) {
+            (Privacy::Apps, AggregateBy::Title) => AggregateBy::App,
+            (_, aggregate_by) => aggregate_by,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Privacy {
+    #[default]
+    Full,
+    // Apps and categories only, titles are never kept
+    Apps,
+}
+
+impl Privacy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Privacy::Full => "full",
+            Privacy::Apps => "apps",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AggregateBy {
+    #[default]
+    Title,
+    App,
+    // WM_CLASS on X11, the Win32 window class on Windows; the app where there is none, like on macOS
+    Class,
+    // The category the [[categories]] rules give a window
+    Rule,
+}
+
+impl AggregateBy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AggregateBy::Title => "title",
+            AggregateBy::App => "app",
+            AggregateBy::Class => "class",
+            AggregateBy::Rule => "rule",
+        }
+    }
+}
+
+// A window belongs to the first category whose app names or title patterns match it, or whose
+// field patterns all match the fields its title template extracted.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CategoryRule {
+    pub name: String,
+    #[serde(default)]
+    pub apps: Vec<String>,
+    #[serde(default)]
+    pub titles: Vec<String>,
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    // How the category is drawn in reports, the heatmap and the live display, e.g. "#4f46e5" and "💻"
+    pub color: Option<String>,
+    pub icon: Option<String>,
+}
+
+// Windows matching every condition given are not tracked, or only with their title hidden
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IgnoreRule {
+    pub app: Option<String>,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub redact: bool,
+}
+
+// Titles matching any of titles totalled as into, e.g. into = "Project X"; with app only that app's
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MergeRule {
+    pub into: String,
+    pub titles: Vec<String>,
+    pub app: Option<String>,
+}
+
+// Fields pulled out of an app's titles, like "{page} — Mozilla Firefox"; without app for any app
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TitleTemplate {
+    pub app: Option<String>,
+    pub template: String,
+}
+
+// A daily budget for a category's focus time, shown as a bar in the live display: a target to
+// reach, e.g. category = "Coding", target_mins = 240, or a limit to stay under with limit_mins
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GoalConfig {
+    pub category: String,
+    pub target_mins: Option<u64>,
+    pub limit_mins: Option<u64>,
+}
+
+// A report the running tracker makes at cron times, e.g. at = "30 18 * * 1-5", written to path,
+// mailed and/or posted to a webhook
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduledJob {
+    // For log lines, the at expression without one
+    pub name: Option<String>,
+    pub at: String,
+    // The report covers this many days up to the run, 1 for today
+    #[serde(default = "default_schedule_days")]
+    pub days: u32,
+    #[serde(default)]
+    pub format: ReportFormat,
+    // {date} becomes the day of the run, e.g. "/home/me/reports/{date}.html"
+    pub path: Option<String>,
+    // Gets the JSON summary whatever the format
+    pub webhook: Option<String>,
+    // Gets the HTML report whatever the format
+    pub mail_to: Option<String>,
+    #[serde(default = "default_smtp")]
+    pub smtp: String,
+    #[serde(default = "default_mail_from")]
+    pub mail_from: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Html,
+    Json,
+    Text,
+}
+
+// Where `track` exports spans and metrics, e.g. endpoint = "http://localhost:4318"
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OtlpConfig {
+    pub endpoint: String,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+// Where `track` writes intervals and switch counts as InfluxDB line protocol: appended to path,
+// or posted to the v2 write API at url with the token from token_env
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InfluxConfig {
+    pub url: Option<String>,
+    pub path: Option<PathBuf>,
+    #[serde(default)]
+    pub org: String,
+    #[serde(default)]
+    pub bucket: String,
+    #[serde(default = "default_influx_token_env")]
+    pub token_env: String,
+    #[serde(default = "default_measurement")]
+    pub measurement: String,
+}
+
+// Where `track` sends focus seconds per category every interval_secs, to a StatsD daemon over UDP
+// or a Graphite (carbon) plaintext listener over TCP
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatsdConfig {
+    pub address: String,
+    #[serde(default)]
+    pub protocol: MetricsProtocol,
+    #[serde(default = "default_service_name")]
+    pub prefix: String,
+    #[serde(default = "default_statsd_interval_secs")]
+    pub interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsProtocol {
+    #[default]
+    Statsd,
+    Graphite,
+}
+
+// How client commands reach the running tracker, see ipc.rs
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IpcConfig {
+    pub transport: IpcTransport,
+    // For tcp, 0 for any free one
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpcTransport {
+    // A Unix socket, or a named pipe on Windows
+    #[default]
+    Auto,
+    Unix,
+    Pipe,
+    Tcp,
+    Off,
+}
+
+// Where jira-push adds worklogs; the API token comes from the environment, not the file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JiraConfig {
+    pub url: String,
+    pub email: String,
+    #[serde(default = "default_token_env")]
+    pub token_env: String,
+    // Find the ticket in a window title, the first capture group if there is one
+    #[serde(default = "default_ticket_patterns")]
+    pub patterns: Vec<String>,
+    // Only these project keys, all when empty
+    #[serde(default)]
+    pub projects: Vec<String>,
+}
+
+fn default_token_env() -> String {
+    "JIRA_API_TOKEN".to_string()
+}
+
+fn default_ticket_patterns() -> Vec<String> {
+    vec![r"\b([A-Z][A-Z0-9]+-[0-9]+)\b".to_string()]
+}
+
+// Accounts import-activity reads commits and pull requests from, to label report blocks with repos
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActivityConfig {
+    // Editors and terminals whose focus blocks are matched against the activity
+    #[serde(default = "default_activity_apps")]
+    pub apps: Vec<String>,
+    pub github: Option<ForgeAccount>,
+    pub gitlab: Option<ForgeAccount>,
+}
+
+// url defaults to the public instance, the token is read from token_env if it is set
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForgeAccount {
+    pub user: String,
+    pub url: Option<String>,
+    pub token_env: Option<String>,
+}
+
+pub fn default_activity_apps() -> Vec<String> {
+    [
+        "code", "codium", "nvim", "vim", "gvim", "emacs", "idea", "pycharm", "clion", "rustrover", "zed", "sublime_text",
+        "devenv", "xcode", "terminal", "iterm2", "gnome-terminal-server", "konsole", "alacritty", "kitty", "wezterm-gui",
+        "windowsterminal", "foot",
+    ]
+    .iter()
+    .map(|app| app.to_string())
+    .collect()
+}
+
+// Editors whose titles name the open file, and extensions beyond the built-in ones, e.g. { rhai = "Rhai" }
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LanguagesConfig {
+    #[serde(default = "default_editor_apps")]
+    pub editors: Vec<String>,
+    #[serde(default)]
+    pub extensions: HashMap<String, String>,
+}
+
+pub fn default_editor_apps() -> Vec<String> {
+    [
+        "code", "codium", "cursor", "nvim", "vim", "gvim", "emacs", "idea", "pycharm", "clion", "rustrover", "goland",
+        "webstorm", "zed", "sublime_text", "devenv", "xcode",
+    ]
+    .iter()
+    .map(|app| app.to_string())
+    .collect()
+}
+
+// Where `track` serves live events, e.g. address = "127.0.0.1:7878". Browsers send an Origin, only
+// pages from these origins may connect; clients without one, like scripts, always can.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebSocketConfig {
+    pub address: String,
+    #[serde(default)]
+    pub origins: Vec<String>,
+}
+
+// The tracker's own health, see health.rs; the checks run without the section too
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthConfig {
+    // Where GET /healthz and the /api endpoints answer, e.g. "127.0.0.1:9464", nowhere without it
+    pub address: Option<String>,
+    // No window sampled for this long while the user is active counts as broken
+    #[serde(default = "default_stale_secs")]
+    pub stale_secs: u64,
+    // PEM files to serve HTTPS with (needs --features tls)
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    // With any, every request needs one of them as a bearer token; `api-token` adds them
+    #[serde(default)]
+    pub tokens: Vec<ApiToken>,
+    // Requests to /api/intervals and /api/windows each client may make a minute, 0 for no limit
+    #[serde(default = "default_queries_per_minute")]
+    pub queries_per_minute: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiToken {
+    pub token: String,
+    #[serde(default = "default_api_scopes")]
+    pub scopes: Vec<ApiScope>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    // GET /healthz, /api/status, /api/current, /api/intervals and /api/windows
+    Read,
+    // POST /api/pause, /api/resume and /api/switch-profile
+    Write,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig { address: None, stale_secs: default_stale_secs(), tls_cert: None, tls_key: None, tokens: Vec::new(), queries_per_minute: default_queries_per_minute() }
+    }
+}
+
+// Intervals are marked as calls while the camera or microphone is in use, unless detect is off.
+// Reports split the time in these apps into in a call and just open.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CallsConfig {
+    #[serde(default = "default_true")]
+    pub detect: bool,
+    #[serde(default = "default_meeting_apps")]
+    pub apps: Vec<String>,
+}
+
+pub fn default_meeting_apps() -> Vec<String> {
+    ["zoom", "zoom.us", "teams", "ms-teams", "webex", "ciscowebexstart", "slack", "discord", "skype", "facetime"]
+        .iter()
+        .map(|app| app.to_string())
+        .collect()
+}
+
+// How durations and numbers read in text and HTML output, JSON and CSV keep plain numbers
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatConfig {
+    #[serde(default)]
+    pub duration: DurationStyle,
+    // For hours and seconds
+    #[serde(default = "default_decimals")]
+    pub decimals: usize,
+    // Separators of e.g. "de" or "fr_CH", taken from LC_ALL, LC_NUMERIC or LANG when not set
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DurationStyle {
+    // 1h 10m 35s
+    #[default]
+    Hms,
+    // 1h 11m
+    Hm,
+    // 1.2h
+    Hours,
+    // 4235.1 seconds
+    Seconds,
+}
+
+// A notification summing up the previous session when the user is back after a long gap
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GreetingConfig {
+    // Away, locked or suspended at least this long
+    #[serde(default = "default_greeting_gap")]
+    pub gap_mins: u64,
+}
+
+// Notifications when a limit goal is passed or the user is idle while tracking goes on, with
+// buttons to act on them on Windows; see alerts
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertsConfig {
+    // Once a day per [[goals]] limit, when it is passed
+    #[serde(default = "default_true")]
+    pub limits: bool,
+    // Without input this long while tracking, 0 for never
+    #[serde(default)]
+    pub idle_mins: u64,
+    // How long Snooze holds an alert back
+    #[serde(default = "default_snooze_mins")]
+    pub snooze_mins: u64,
+    // What Recategorize moves the focused app to, no such button without it
+    pub recategorize: Option<String>,
+}
+
+// Pushing the history to a `hub serve` while tracking, everything stored since the last push
+// every push_secs; the token is one `hub token` made on the hub
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HubConfig {
+    pub url: String,
+    pub token: String,
+    // What the hub files this machine's time under, the host name without it
+    pub device: Option<String>,
+    #[serde(default = "default_push_secs")]
+    pub push_secs: u64,
+    // Consent to team reports: only each interval's category and times leave this machine
+    #[serde(default)]
+    pub team: bool,
+}
+
+// `hub serve`: where it listens and who may push to it and read the combined reports
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HubServerConfig {
+    #[serde(default = "default_hub_address")]
+    pub address: String,
+    #[serde(default)]
+    pub members: Vec<HubMember>,
+    // A category in the team report needs time from this many team members, or it is left out
+    #[serde(default = "default_team_min_people")]
+    pub team_min_people: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HubMember {
+    pub user: String,
+    pub token: String,
+    // Only anonymous pushes are taken from them, and their time is in the team report
+    #[serde(default)]
+    pub team: bool,
+    // May read the report by user and device, everyone else only gets the team report
+    #[serde(default)]
+    pub admin: bool,
+}
+
+// Game detection and the playtime ledger of `report --games`, off without the section. Games run
+// by a known launcher and Steam games are found on their own; apps are more executables to count
+// as games, launchers more processes whose children are games
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GamesConfig {
+    #[serde(default)]
+    pub apps: Vec<String>,
+    #[serde(default)]
+    pub launchers: Vec<String>,
+    // A window holding the screen in exclusive fullscreen is a game too (Windows)
+    #[serde(default = "default_true")]
+    pub fullscreen: bool,
+    // Play less than this long after the last counts as the same session
+    #[serde(default = "default_session_gap_mins")]
+    pub session_gap_mins: u64,
+}
+
+// What media players and browsers are playing, kept as field:media on the intervals they have focus
+// in; see media
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MediaConfig {
+    // The artist too, as field:media_artist
+    #[serde(default = "default_true")]
+    pub artist: bool,
+    // How often the players are asked
+    #[serde(default = "default_media_secs")]
+    pub check_secs: u64,
+}
+
+// Whether the system's Do Not Disturb is on, for the tracker's notifications and field:dnd; see dnd
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DndConfig {
+    // The tracker's own notifications are held back while it is on
+    #[serde(default = "default_true")]
+    pub quiet: bool,
+    // Intervals are stored with field:dnd on or off
+    #[serde(default = "default_true")]
+    pub record: bool,
+    #[serde(default = "default_dnd_secs")]
+    pub check_secs: u64,
+}
+
+// Whether the machine runs on battery, for field:power and low-power sampling; see power
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PowerConfig {
+    // Intervals are stored with field:power battery or ac
+    #[serde(default = "default_true")]
+    pub record: bool,
+    // Samples like --low-power while on battery
+    #[serde(default = "default_true")]
+    pub low_power_on_battery: bool,
+    #[serde(default = "default_power_secs")]
+    pub check_secs: u64,
+}
+
+// Daily limits per app the tracker enforces, with the PIN that guards them; see enforce
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnforceConfig {
+    // Set by `enforce set-pin`, changes to [enforce] and pausing need `enforce unlock` while it is set
+    pub pin_hash: Option<String>,
+    // A notification this long before a limit is reached
+    #[serde(default = "default_enforce_warn_mins")]
+    pub warn_mins: u64,
+    // What is left to save work once a limit is reached, before the action
+    #[serde(default = "default_enforce_grace_mins")]
+    pub grace_mins: u64,
+    #[serde(default)]
+    pub limits: Vec<AppLimit>,
+}
+
+// "app" is the executable like in [[ignore]], without .exe and in any case
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppLimit {
+    pub app: String,
+    pub daily_mins: u64,
+    #[serde(default)]
+    pub action: LimitAction,
+}
+
+// What happens to the app's window while it has focus past the limit and the grace time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitAction {
+    // Notifications only
+    Warn,
+    #[default]
+    Minimize,
+    Close,
+    // The window stops taking input until the next day (Windows), minimized elsewhere
+    Block,
+}
+
+// A small screenshot at every window switch, for recall; see thumbnails
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThumbnailConfig {
+    // Pixels across, the height follows from the screen
+    #[serde(default = "default_thumbnail_width")]
+    pub width: u32,
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u64,
+}
+
+// Bounds on the per-window totals a long `track` session keeps in memory; evicted windows stay in
+// the history, they only drop out of the live display
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryConfig {
+    // Windows and titles not focused for this long are evicted
+    #[serde(default = "default_evict_after_days")]
+    pub evict_after_days: u64,
+    // Beyond this the least recently focused go first
+    #[serde(default = "default_max_windows")]
+    pub max_windows: usize,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        MemoryConfig { evict_after_days: default_evict_after_days(), max_windows: default_max_windows() }
+    }
+}
+
+// Remote desktop and VM clients recorded under the machine they show, see remote.rs
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    // rdp, vmware, virtualbox or parallels, all of them when empty
+    #[serde(default)]
+    pub clients: Vec<String>,
+}
+
+// A plugin process or a script every finished interval is passed to, see enrichers.rs and scripts.rs
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnricherConfig {
+    // In error messages, the program or script otherwise
+    pub name: Option<String>,
+    // The program and its arguments
+    #[serde(default)]
+    pub command: Vec<String>,
+    // A rhai script run in the tracker instead, relative to config.toml's directory
+    pub script: Option<PathBuf>,
+    // How long an interval waits for its answer before it is stored as it was
+    #[serde(default = "default_enricher_timeout")]
+    pub timeout_ms: u64,
+    // Where a script is stopped however quick it is
+    #[serde(default = "default_script_operations")]
+    pub max_operations: u64,
+}
+
+// Finished intervals wait in pending.jsonl and move into the history in batches
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StorageConfig {
+    // At most this long after the first one waited
+    #[serde(default = "default_flush_secs")]
+    pub flush_secs: u64,
+    // Or as soon as this many are waiting
+    #[serde(default = "default_flush_intervals")]
+    pub flush_intervals: usize,
+    #[serde(default)]
+    pub sync: SyncPolicy,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig { flush_secs: default_flush_secs(), flush_intervals: default_flush_intervals(), sync: SyncPolicy::default() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncPolicy {
+    // Each batch is on disk before it leaves pending.jsonl
+    #[default]
+    Safe,
+    // Left to the OS, a power cut can lose what it had not written yet
+    Fast,
+}
+
+// Chat status set while tracking, from the first rule whose category has held focus long enough
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusConfig {
+    // The kill switch in the file, `status-updates off` is the one for the running tracker
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // At most one update this often, except clearing when switched off
+    #[serde(default = "default_status_interval")]
+    pub min_interval_secs: u64,
+    #[serde(default)]
+    pub rules: Vec<StatusRule>,
+    pub slack: Option<StatusAccount>,
+    pub discord: Option<StatusAccount>,
+}
+
+// e.g. category = "Coding", after_mins = 15, text = "deep work", emoji = ":brain:"
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusRule {
+    pub category: String,
+    #[serde(default)]
+    pub after_mins: u64,
+    pub text: String,
+    // Slack wants a :code:, Discord the emoji itself
+    #[serde(default)]
+    pub emoji: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusAccount {
+    // Environment variable holding the user token
+    pub token_env: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_status_interval() -> u64 {
+    60
+}
+
+fn default_stale_secs() -> u64 {
+    60
+}
+
+fn default_queries_per_minute() -> u32 {
+    60
+}
+
+fn default_enricher_timeout() -> u64 {
+    500
+}
+
+fn default_script_operations() -> u64 {
+    100_000
+}
+
+fn default_greeting_gap() -> u64 {
+    120
+}
+
+fn default_snooze_mins() -> u64 {
+    15
+}
+
+fn default_evict_after_days() -> u64 {
+    7
+}
+
+fn default_max_windows() -> usize {
+    10_000
+}
+
+fn default_schedule_days() -> u32 {
+    1
+}
+
+fn default_smtp() -> String {
+    "localhost:25".to_string()
+}
+
+fn default_mail_from() -> String {
+    "window-tracker@localhost".to_string()
+}
+
+fn default_thumbnail_width() -> u32 {
+    160
+}
+
+fn default_push_secs() -> u64 {
+    60
+}
+
+fn default_session_gap_mins() -> u64 {
+    15
+}
+
+fn default_media_secs() -> u64 {
+    5
+}
+
+fn default_dnd_secs() -> u64 {
+    10
+}
+
+fn default_power_secs() -> u64 {
+    30
+}
+
+fn default_enforce_warn_mins() -> u64 {
+    5
+}
+
+fn default_enforce_grace_mins() -> u64 {
+    2
+}
+
+fn default_team_min_people() -> usize {
+    3
+}
+
+fn default_hub_address() -> String {
+    "0.0.0.0:9470".to_string()
+}
+
+fn default_retention_days() -> u64 {
+    7
+}
+
+fn default_flush_secs() -> u64 {
+    60
+}
+
+fn default_flush_intervals() -> usize {
+    50
+}
+
+fn default_decimals() -> usize {
+    1
+}
+
+// Switches to the profile while every condition given matches, e.g. ssid = "Office" or monitors = 2
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileTrigger {
+    pub profile: String,
+    pub ssid: Option<String>,
+    pub vpn: Option<bool>,
+    pub monitors: Option<usize>,
+}
+
+fn default_service_name() -> String {
+    "window_tracker".to_string()
+}
+
+fn default_api_scopes() -> Vec<ApiScope> {
+    vec![ApiScope::Read]
+}
+
+fn default_statsd_interval_secs() -> u64 {
+    10
+}
+
+fn default_influx_token_env() -> String {
+    "INFLUX_TOKEN".to_string()
+}
+
+fn default_measurement() -> String {
+    "focus".to_string()
+}
+
+// The active profile's config
+pub fn config_path() -> PathBuf {
+    profile::scoped_file(base_config_path())
+}
+
+// WT_CONFIG overrides the per-platform default location.
+pub fn base_config_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("WT_CONFIG") {
+        return PathBuf::from(path);
+    }
+
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    base.unwrap_or_else(|| PathBuf::from(".")).join("window_tracker").join("config.toml")
+}
+
+// A missing config file is not an error, everything has a default.
+pub fn load() -> Result<Config, String> {
+    load_from(config_path())
+}
+
+// The config shared by every profile
+pub fn load_base() -> Result<Config, String> {
+    load_from(base_config_path())
+}
+
+fn load_from(path: PathBuf) -> Result<Config, String> {
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+    };
+
+    toml::from_str(&text).map_err(|e| format!("invalid config {}: {}", path.display(), e))
+}
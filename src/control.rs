@@ -0,0 +1,455 @@
+// Small commands for automation like Apple Shortcuts ("Run Shell Script"): what is focused now,
+// today's totals, pausing the running tracker and switching its profile, and whether it is healthy
+// for cron jobs and monitoring. They ask it over ipc.rs when it listens, and otherwise go through
+// files in the data dir shared by all profiles, which it checks every second.
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::cli::Args;
+use crate::config;
+use crate::doctor::Status;
+use crate::enforce;
+use crate::health;
+use crate::humanize;
+use crate::i18n::tr;
+use crate::ipc;
+use crate::journal;
+use crate::profile;
+use crate::report::{self, GroupBy};
+use crate::storage::{self, Interval};
+use crate::table::{Format, Table};
+
+const PAUSE_FILE: &str = "paused";
+const SWITCH_FILE: &str = "switch-profile";
+const RUNNING_PROFILE_FILE: &str = "running-profile";
+const STATUS_FILE: &str = "status.json";
+const TODAY_TOP: usize = 5;
+
+// The checkpoint interval of the running session, for the heartbeat asked for over ipc
+static CHECKPOINT_SECS: AtomicU64 = AtomicU64::new(10);
+// A switch-profile asked for over ipc
+static SWITCH_REQUEST: Mutex<Option<String>> = Mutex::new(None);
+// The executable self-update installed, for the session to end and `track` to run it instead
+static RESTART_REQUEST: Mutex<Option<PathBuf>> = Mutex::new(None);
+// Paused (true) or resumed over ipc since the last check, applied by the tracker loop in PauseWatch
+static PAUSE_REQUEST: Mutex<Option<bool>> = Mutex::new(None);
+
+fn pause_path() -> PathBuf {
+    storage::base_data_dir().join(PAUSE_FILE)
+}
+
+fn write_control_file(name: &str, content: &str) -> Result<(), String> {
+    let directory = storage::base_data_dir();
+    fs::create_dir_all(&directory).map_err(|e| format!("failed to create {}: {}", directory.display(), e))?;
+    let path = directory.join(name);
+    fs::write(&path, content).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+// Lets commands without --profile look at whatever profile the tracker is recording into
+pub fn record_running_profile() {
+    if let Err(e) = write_control_file(RUNNING_PROFILE_FILE, &profile::name()) {
+        eprintln!("{}", e);
+    }
+}
+
+// What the running tracker last said about itself, rewritten at every checkpoint
+#[derive(Serialize, Deserialize)]
+struct Heartbeat {
+    pid: u32,
+    profile: String,
+    updated: DateTime<Utc>,
+    // Until the next heartbeat
+    checkpoint_secs: u64,
+    paused: bool,
+    storage_error: Option<String>,
+    // See health.rs
+    #[serde(default)]
+    sampling_error: Option<String>,
+    #[serde(default)]
+    last_sample: Option<DateTime<Utc>>,
+    #[serde(default)]
+    sampler_restarts: u64,
+    #[serde(default)]
+    diagnostics: health::Diagnostics,
+    // Failed or missing platform checks, like a permission doctor would warn about
+    capture_problems: Vec<String>,
+    permission_problems: Vec<String>,
+}
+
+fn status_path() -> PathBuf {
+    storage::base_data_dir().join(STATUS_FILE)
+}
+
+fn heartbeat(checkpoint_secs: u64) -> Heartbeat {
+    let mut capture_problems = Vec::new();
+    let mut permission_problems = Vec::new();
+    for check in crate::platform::checks() {
+        let problem = format!("{}: {}", check.name, check.detail);
+        if check.name.ends_with("permission") && check.status != Status::Ok {
+            permission_problems.push(problem);
+        } else if check.status == Status::Failed {
+            capture_problems.push(problem);
+        }
+    }
+    Heartbeat {
+        pid: std::process::id(),
+        profile: profile::name(),
+        updated: Utc::now(),
+        checkpoint_secs,
+        paused: crate::wt_is_paused(),
+        storage_error: storage::last_error(),
+        sampling_error: health::sampling_problem(),
+        last_sample: health::last_sample(),
+        sampler_restarts: health::sampler_restarts(),
+        diagnostics: health::diagnostics(),
+        capture_problems,
+        permission_problems,
+    }
+}
+
+pub fn record_heartbeat(checkpoint_secs: u64) {
+    CHECKPOINT_SECS.store(checkpoint_secs, Ordering::Relaxed);
+    let written = serde_json::to_string(&heartbeat(checkpoint_secs)).map_err(|e| e.to_string()).and_then(|text| write_control_file(STATUS_FILE, &text));
+    if let Err(e) = written {
+        eprintln!("{}", e);
+    }
+}
+
+// Once tracking stops, so status doesn't wait for the heartbeat to go stale
+pub fn clear_heartbeat() {
+    let _ = fs::remove_file(status_path());
+}
+
+// What the running tracker answers over ipc
+pub fn handle_request(request: &serde_json::Value) -> serde_json::Value {
+    match request["command"].as_str().unwrap_or("") {
+        "status" => json!({ "ok": true, "heartbeat": heartbeat(CHECKPOINT_SECS.load(Ordering::Relaxed)) }),
+        "current" => json!({ "ok": true, "paused": crate::wt_is_paused(), "interval": crate::wt_get_open_interval() }),
+        // Refused right away while [enforce] is locked, so the client can say so
+        "pause" => match enforce::check_pause() {
+            Ok(()) => {
+                *PAUSE_REQUEST.lock().unwrap() = Some(true);
+                json!({ "ok": true, "paused": true })
+            }
+            Err(e) => json!({ "ok": false, "error": e }),
+        },
+        "resume" => {
+            *PAUSE_REQUEST.lock().unwrap() = Some(false);
+            json!({ "ok": true, "paused": false })
+        }
+        "switch_profile" => {
+            let name = request["profile"].as_str().unwrap_or("");
+            match profile::validate(name) {
+                Ok(()) => {
+                    *SWITCH_REQUEST.lock().unwrap() = Some(name.to_string());
+                    json!({ "ok": true, "profile": name })
+                }
+                Err(e) => json!({ "ok": false, "error": e }),
+            }
+        }
+        "restart" => match request["executable"].as_str() {
+            Some(executable) => {
+                *RESTART_REQUEST.lock().unwrap() = Some(PathBuf::from(executable));
+                json!({ "ok": true, "pid": std::process::id() })
+            }
+            None => json!({ "ok": false, "error": "restart needs the executable to run" }),
+        },
+        "enforce_status" => json!({ "ok": true, "enforce": enforce::status() }),
+        "enforce_unlock" => {
+            let mins = request["mins"].as_u64().unwrap_or(enforce::UNLOCK_MINS);
+            match enforce::unlock(request["pin"].as_str().unwrap_or(""), mins) {
+                Ok(()) => json!({ "ok": true }),
+                Err(e) => json!({ "ok": false, "error": e }),
+            }
+        }
+        "enforce_lock" => {
+            enforce::lock();
+            json!({ "ok": true })
+        }
+        other => json!({ "ok": false, "error": format!("unknown command '{}'", other) }),
+    }
+}
+
+fn follow_running_profile() -> Result<(), String> {
+    if profile::is_explicit() {
+        return Ok(());
+    }
+    match fs::read_to_string(storage::base_data_dir().join(RUNNING_PROFILE_FILE)) {
+        Ok(name) => profile::set_active(name.trim()),
+        Err(_) => Ok(()),
+    }
+}
+
+pub fn run_current(args: &Args) -> Result<(), String> {
+    let json = args.json_output()?;
+    let format = Format::from_args(args)?;
+    follow_running_profile()?;
+    if pause_path().exists() {
+        if json {
+            println!("{}", json!({ "state": "paused" }));
+        } else {
+            println!("{}", tr("current-paused", &[]));
+        }
+        return Ok(());
+    }
+
+    let interval = match ipc::request("current", json!({}))? {
+        Some(response) => serde_json::from_value::<Option<Interval>>(response["interval"].clone()).map_err(|e| e.to_string())?,
+        None => {
+            let config = config::load()?;
+            let interval = journal::read().map_err(|e| format!("failed to read journal: {}", e))?;
+            // The running tracker checkpoints regularly, an old journal was left behind by a crash
+            let stale_after = chrono::Duration::seconds(config.checkpoint_secs.max(1) as i64 * 3);
+            interval.filter(|interval| Utc::now() - interval.end <= stale_after)
+        }
+    };
+    if let Some(format) = format {
+        let mut table = Table::new(&["title", "app", "category", "secs", "call"]);
+        if let Some(interval) = &interval {
+            table.push(vec![
+                json!(interval.title),
+                json!(interval.app_name()),
+                json!(interval.category_name()),
+                json!(interval.duration_secs().round() as u64),
+                json!(interval.call),
+            ]);
+        }
+        table.print(format);
+        return Ok(());
+    }
+    match interval {
+        Some(interval) if json => println!("{}", json!({
+            "state": "tracking",
+            "profile": profile::name(),
+            "title": interval.title,
+            "app": interval.app_name(),
+            "category": interval.category_name(),
+            "focused_secs": interval.duration_secs(),
+            "call": interval.call,
+        })),
+        Some(interval) => {
+            let key = if interval.call { "current-window-call" } else { "current-window" };
+            let time = humanize::duration(interval.duration_secs());
+            println!("{}", tr(key, &[("title", &interval.title), ("app", &interval.app_name()), ("time", &time)]));
+        }
+        None if json => println!("{}", json!({ "state": "stopped" })),
+        None => println!("{}", tr("current-stopped", &[])),
+    }
+    Ok(())
+}
+
+pub fn run_today(args: &Args) -> Result<(), String> {
+    follow_running_profile()?;
+    let summary = report::load(1, GroupBy::configured()?)?;
+    if let Some(format) = Format::from_args(args)? {
+        report::windows_table(&summary).print(format);
+        return Ok(());
+    }
+    if args.json_output()? {
+        print!("{}", report::render_json(&summary));
+        return Ok(());
+    }
+    println!("{}", tr("today-total", &[("time", &humanize::duration(summary.total))]));
+    for (title, secs) in summary.windows.iter().take(TODAY_TOP) {
+        println!("{} {}", humanize::duration(*secs), title);
+    }
+    Ok(())
+}
+
+// `status --check` exit codes, the first problem found decides
+const EXIT_NOT_RUNNING: i32 = 2;
+const EXIT_STORAGE: i32 = 3;
+const EXIT_PERMISSIONS: i32 = 4;
+const EXIT_CAPTURE: i32 = 5;
+
+pub fn run_status(args: &Args) -> Result<(), String> {
+    let check = args.flag("--check");
+    let heartbeat = match ipc::request("status", json!({})) {
+        Ok(Some(response)) => serde_json::from_value::<Heartbeat>(response["heartbeat"].clone()).ok(),
+        // Whoever can't ask still sees the heartbeat file
+        Ok(None) | Err(_) => match fs::read_to_string(status_path()) {
+            Ok(text) => serde_json::from_str::<Heartbeat>(&text).ok(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("failed to read {}: {}", status_path().display(), e)),
+        },
+    };
+    // A running tracker rewrites it every checkpoint, an old one was left behind by a crash
+    let heartbeat = heartbeat.filter(|heartbeat| {
+        let stale_after = chrono::Duration::seconds(heartbeat.checkpoint_secs.max(1) as i64 * 3);
+        Utc::now() - heartbeat.updated <= stale_after
+    });
+
+    let mut reasons: Vec<(&str, i32, String)> = Vec::new();
+    match &heartbeat {
+        None => reasons.push(("not_running", EXIT_NOT_RUNNING, "no tracker has checkpointed recently".to_string())),
+        Some(heartbeat) => {
+            if let Some(e) = &heartbeat.storage_error {
+                reasons.push(("storage", EXIT_STORAGE, e.clone()));
+            }
+            for problem in &heartbeat.permission_problems {
+                reasons.push(("permissions", EXIT_PERMISSIONS, problem.clone()));
+            }
+            for problem in &heartbeat.capture_problems {
+                reasons.push(("capture", EXIT_CAPTURE, problem.clone()));
+            }
+            if let Some(e) = &heartbeat.sampling_error {
+                reasons.push(("sampling", EXIT_CAPTURE, e.clone()));
+            }
+        }
+    }
+
+    if check || args.json_output()? {
+        let reasons: Vec<serde_json::Value> = reasons.iter()
+            .map(|(reason, _, detail)| json!({ "reason": reason, "detail": detail }))
+            .collect();
+        println!("{}", json!({
+            "ok": reasons.is_empty(),
+            "running": heartbeat.is_some(),
+            "pid": heartbeat.as_ref().map(|heartbeat| heartbeat.pid),
+            "profile": heartbeat.as_ref().map(|heartbeat| &heartbeat.profile),
+            "paused": heartbeat.as_ref().map(|heartbeat| heartbeat.paused),
+            "updated": heartbeat.as_ref().map(|heartbeat| heartbeat.updated.to_rfc3339()),
+            "last_sample": heartbeat.as_ref().and_then(|heartbeat| heartbeat.last_sample).map(|time| time.to_rfc3339()),
+            "sampler_restarts": heartbeat.as_ref().map(|heartbeat| heartbeat.sampler_restarts),
+            "diagnostics": heartbeat.as_ref().map(|heartbeat| &heartbeat.diagnostics),
+            "reasons": reasons,
+        }));
+    } else {
+        match &heartbeat {
+            Some(heartbeat) => println!(
+                "{} into profile '{}' (pid {}), last checkpoint {} ago",
+                if heartbeat.paused { "Paused" } else { "Tracking" },
+                heartbeat.profile,
+                heartbeat.pid,
+                humanize::duration((Utc::now() - heartbeat.updated).num_milliseconds().max(0) as f64 / 1000.0),
+            ),
+            None => println!("Not running"),
+        }
+        if let Some(diagnostics) = heartbeat.as_ref().map(|heartbeat| &heartbeat.diagnostics).filter(|diagnostics| diagnostics.total() > 0) {
+            println!(
+                "Unmeasured samples: {} failed, {} not responding, {} without a title, {} permission denied",
+                diagnostics.failed, diagnostics.not_responding, diagnostics.untitled, diagnostics.permission_denied,
+            );
+        }
+        for (reason, _, detail) in reasons.iter().filter(|(reason, ..)| *reason != "not_running") {
+            println!("[{}] {}", reason, detail);
+        }
+    }
+
+    if let Some((_, code, _)) = reasons.first().filter(|_| check) {
+        std::process::exit(*code);
+    }
+    Ok(())
+}
+
+fn print_paused(args: &Args, paused: bool) -> Result<(), String> {
+    if args.json_output()? {
+        println!("{}", json!({ "paused": paused }));
+    } else if paused {
+        println!("{}", tr("tracking-paused", &[]));
+    } else {
+        println!("{}", tr("tracking-resumed", &[]));
+    }
+    Ok(())
+}
+
+// Pausing from inside the running tracker, like an alert's button or the menu bar, the way `pause` would
+pub fn pause_here() -> Result<(), String> {
+    if !crate::wt_is_ephemeral() {
+        write_control_file(PAUSE_FILE, "")?;
+    }
+    crate::wt_pause()
+}
+
+pub fn resume_here() -> Result<(), String> {
+    match fs::remove_file(pause_path()) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("failed to resume: {}", e)),
+    }
+    crate::wt_resume();
+    Ok(())
+}
+
+// The pause file keeps a tracker started later paused too, asking the running one applies it now
+pub fn run_pause(args: &Args) -> Result<(), String> {
+    write_control_file(PAUSE_FILE, "")?;
+    // Left behind, a refused pause would apply to the next session
+    if let Err(e) = ipc::request("pause", json!({})) {
+        let _ = fs::remove_file(pause_path());
+        return Err(e);
+    }
+    print_paused(args, true)
+}
+
+pub fn run_resume(args: &Args) -> Result<(), String> {
+    match fs::remove_file(pause_path()) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("failed to resume: {}", e)),
+    }
+    ipc::request("resume", json!({}))?;
+    print_paused(args, false)
+}
+
+pub fn run_switch_profile(args: &Args) -> Result<(), String> {
+    let name = args.arg(1).ok_or("usage: switch-profile <name>")?;
+    profile::validate(name)?;
+    if ipc::request("switch_profile", json!({ "profile": name }))?.is_none() {
+        write_control_file(SWITCH_FILE, name)?;
+    }
+    if args.json_output()? {
+        println!("{}", json!({ "profile": name }));
+    } else {
+        println!("Asked the running tracker to switch to profile '{}'", name);
+    }
+    Ok(())
+}
+
+pub fn restart_request() -> Option<PathBuf> {
+    RESTART_REQUEST.lock().unwrap().clone()
+}
+
+// The profile a switch-profile command asked for since the last check
+pub fn take_switch_request() -> Option<String> {
+    if let Some(name) = SWITCH_REQUEST.lock().unwrap().take() {
+        return Some(name);
+    }
+    let path = storage::base_data_dir().join(SWITCH_FILE);
+    let name = fs::read_to_string(&path).ok()?;
+    let _ = fs::remove_file(&path);
+    Some(name.trim().to_string())
+}
+
+// Applies pause and resume requests to the running session, asked over ipc or through the file.
+// Only changes of the file count, so a pause from another source (D-Bus, the library API) isn't
+// undone on the next check.
+#[derive(Default)]
+pub struct PauseWatch {
+    requested: bool,
+}
+
+impl PauseWatch {
+    pub fn check(&mut self) {
+        let asked = PAUSE_REQUEST.lock().unwrap().take();
+        let requested = pause_path().exists();
+        if asked.is_none() && requested == self.requested {
+            return;
+        }
+        self.requested = requested;
+        if asked.unwrap_or(requested) {
+            if let Err(e) = crate::wt_pause() {
+                eprintln!("{}", e);
+            }
+        } else {
+            crate::wt_resume();
+        }
+    }
+}
@@ -0,0 +1,79 @@
+// org.windowtracker.Tracker1 on the session bus, for desktop applets and scripts, e.g.
+//   busctl --user call org.windowtracker.Tracker1 /org/windowtracker/Tracker1 org.windowtracker.Tracker1 GetToday
+use zbus::blocking::connection;
+use zbus::object_server::SignalContext;
+
+use crate::report::{self, GroupBy};
+
+const NAME: &str = "org.windowtracker.Tracker1";
+const PATH: &str = "/org/windowtracker/Tracker1";
+
+struct Tracker;
+
+#[zbus::interface(name = "org.windowtracker.Tracker1")]
+impl Tracker {
+    // Empty strings when nothing has focus
+    #[zbus(out_args("title", "app", "window_id", "focused_secs"))]
+    fn get_current(&self) -> (String, String, String, f64) {
+        match crate::wt_get_current_window() {
+            Some(window) => {
+                let window_id = window.key();
+                (window.title, window.app, window_id, crate::wt_get_focus_duration().as_secs_f64())
+            }
+            None => (String::new(), String::new(), String::new(), 0.0),
+        }
+    }
+
+    // Seconds per title, or per aggregate_by from the config, longest first
+    #[zbus(out_args("total_secs", "titles"))]
+    fn get_today(&self) -> zbus::fdo::Result<(f64, Vec<(String, f64)>)> {
+        let summary = GroupBy::configured().and_then(|group_by| report::load(1, group_by)).map_err(zbus::fdo::Error::Failed)?;
+        Ok((summary.total, summary.windows))
+    }
+
+    fn pause_tracking(&self) -> zbus::fdo::Result<()> {
+        crate::wt_pause().map_err(zbus::fdo::Error::Failed)
+    }
+
+    fn resume_tracking(&self) {
+        crate::wt_resume();
+    }
+
+    #[zbus(property)]
+    fn paused(&self) -> bool {
+        crate::wt_is_paused()
+    }
+
+    // Focus time today without reading the history like GetToday, cheap enough to poll
+    #[zbus(property)]
+    fn today_secs(&self) -> f64 {
+        crate::wt_get_today_secs()
+    }
+
+    // Changes whenever the current window or its title does
+    #[zbus(property)]
+    fn current_id(&self) -> u64 {
+        crate::wt_get_current_id()
+    }
+
+    // event is "focus_changed" or "title_changed"
+    #[zbus(signal)]
+    async fn focus_changed(context: &SignalContext<'_>, event: &str, title: &str, app: &str, window_id: &str) -> zbus::Result<()>;
+}
+
+pub fn start() -> Result<(), String> {
+    let connection = connection::Builder::session()
+        .and_then(|builder| builder.name(NAME))
+        .and_then(|builder| builder.serve_at(PATH, Tracker))
+        .and_then(|builder| builder.build())
+        .map_err(|e| format!("failed to register {} on the session bus: {}", NAME, e))?;
+
+    // The listener keeps the connection, and with it the service, alive for the whole session
+    crate::wt_add_focus_listener(Box::new(move |event, window| {
+        let body = (event.as_str(), window.title.as_str(), window.app.as_str(), window.key());
+        if let Err(e) = connection.emit_signal(None::<&str>, PATH, NAME, "FocusChanged", &body) {
+            eprintln!("Failed to emit FocusChanged: {}", e);
+        }
+    }));
+    Ok(())
+}
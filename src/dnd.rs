@@ -0,0 +1,266 @@
+// [dnd]: the system's Do Not Disturb, asked every check_secs. While it is on the tracker's own
+// notifications (alerts, the greeting, limits, report --notify) are held back unless quiet = false,
+// and every interval is stored with field:dnd on or off, so report --dnd and report --by field:dnd
+// can set focus with notifications silenced against focus without. Linux asks the notification
+// daemon there is: GNOME's show-banners, the Inhibited property KDE's has, xfce4-notifyd, dunst or
+// mako. Windows reads Focus Assist's profile the shell publishes, macOS the Focus assertions (Do Not
+// Disturb before Monterey); scheduled Focus modes without an assertion aren't seen.
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::cli::Args;
+use crate::config::{self, Config, DndConfig};
+use crate::filter::Filter;
+use crate::report;
+use crate::rollup;
+use crate::storage::{self, EventKind};
+use crate::table::{Format, Table};
+
+pub const DND: &str = "dnd";
+pub const ON: &str = "on";
+pub const OFF: &str = "off";
+
+struct State {
+    config: DndConfig,
+    // None until asked, and where the platform doesn't say
+    active: Option<bool>,
+    // Bumped by each answer that differs, for DndWatch
+    changes: u64,
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+pub fn check(config: &Config) -> Result<(), String> {
+    match &config.dnd {
+        Some(dnd) if dnd.check_secs == 0 => Err("[dnd] check_secs must be at least 1".to_string()),
+        _ => Ok(()),
+    }
+}
+
+pub fn configure(config: &Config) {
+    let mut state = STATE.lock().unwrap();
+    *state = config.dnd.clone().map(|dnd| {
+        let previous = state.take();
+        State {
+            config: dnd,
+            active: previous.as_ref().and_then(|state| state.active),
+            changes: previous.map_or(0, |state| state.changes),
+        }
+    });
+}
+
+// Asks the system from now on, every check_secs of the config at the time
+pub fn start() {
+    std::thread::spawn(|| loop {
+        let check_secs = match STATE.lock().unwrap().as_ref() {
+            Some(state) => state.config.check_secs,
+            None => return,
+        };
+        let active = platform::active();
+        if let Some(state) = STATE.lock().unwrap().as_mut() {
+            if state.active != active {
+                state.active = active;
+                state.changes += 1;
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_secs(check_secs.max(1)));
+    });
+}
+
+// What the open interval records, None without [dnd], with record = false or when it isn't known
+pub fn active() -> Option<bool> {
+    STATE.lock().unwrap().as_ref().filter(|state| state.config.record).and_then(|state| state.active)
+}
+
+// Whether the tracker's own notifications are held back now. Outside the tracker, like
+// report --notify from a cron job, the system is asked right away.
+pub fn quiet() -> bool {
+    if let Some(state) = STATE.lock().unwrap().as_ref() {
+        return state.config.quiet && state.active == Some(true);
+    }
+    config::load().ok().and_then(|config| config.dnd).is_some_and(|dnd| dnd.quiet && platform::active() == Some(true))
+}
+
+// Lets the tracker loop know when Do Not Disturb was turned on or off
+#[derive(Default)]
+pub struct DndWatch {
+    seen: u64,
+}
+
+impl DndWatch {
+    pub fn check(&mut self) {
+        let changes = STATE.lock().unwrap().as_ref().map_or(0, |state| state.changes);
+        if changes != self.seen {
+            self.seen = changes;
+            crate::wt_dnd_changed();
+        }
+    }
+}
+
+#[derive(Default)]
+struct Totals {
+    secs: f64,
+    switches: usize,
+}
+
+// report --dnd: focus time, switches to another window and how long focus lasted between them,
+// with Do Not Disturb on and off
+pub fn report(args: &Args) -> Result<(), String> {
+    let format = match Format::from_args(args)? {
+        Some(format) => format,
+        None if args.json_output()? => Format::Json,
+        None => Format::Table,
+    };
+    let (from, to) = report::period(args)?;
+    let mut totals: BTreeMap<String, Totals> = BTreeMap::new();
+    storage::scan_matching(from.with_timezone(&chrono::Utc), to.with_timezone(&chrono::Utc), &Filter::from_args(args)?, |chunk| {
+        for interval in chunk {
+            let Some(state) = interval.attributes.get(DND) else { continue };
+            let entry = totals.entry(state.clone()).or_default();
+            entry.secs += interval.duration_secs();
+            if interval.event == EventKind::FocusChanged {
+                entry.switches += 1;
+            }
+        }
+        Ok(())
+    }).map_err(|e| format!("failed to read the history: {}", e))?;
+
+    let mut table = Table::new(&["dnd", "secs", "switches", "switches_per_hour", "mean_focus_secs"]);
+    for (state, entry) in &totals {
+        let per_hour = if entry.secs > 0.0 { entry.switches as f64 * 3600.0 / entry.secs } else { 0.0 };
+        table.push(vec![
+            state.as_str().into(),
+            rollup::rounded(entry.secs),
+            entry.switches.into(),
+            rollup::rounded(per_hour),
+            rollup::rounded(entry.secs / entry.switches.max(1) as f64),
+        ]);
+    }
+    if table.is_empty() && format == Format::Table {
+        println!("No Do Not Disturb state recorded in this period, [dnd] in the config records it");
+        return Ok(());
+    }
+    table.print(format);
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::Command;
+
+    fn output(program: &str, args: &[&str]) -> Option<String> {
+        let output = Command::new(program).args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn gnome() -> Option<bool> {
+        match output("gsettings", &["get", "org.gnome.desktop.notifications", "show-banners"])?.as_str() {
+            "false" => Some(true),
+            "true" => Some(false),
+            _ => None,
+        }
+    }
+
+    // The notification spec's Inhibited property, which Plasma's server has
+    fn inhibited() -> Option<bool> {
+        let answer = output("busctl", &[
+            "--user", "get-property", "org.freedesktop.Notifications", "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications", "Inhibited",
+        ])?;
+        match answer.as_str() {
+            "b true" => Some(true),
+            "b false" => Some(false),
+            _ => None,
+        }
+    }
+
+    fn xfce() -> Option<bool> {
+        output("xfconf-query", &["-c", "xfce4-notifyd", "-p", "/do-not-disturb"]).map(|value| value == "true")
+    }
+
+    fn dunst() -> Option<bool> {
+        output("dunstctl", &["is-paused"]).map(|paused| paused == "true")
+    }
+
+    fn mako() -> Option<bool> {
+        output("makoctl", &["mode"]).map(|modes| modes.lines().any(|mode| mode.trim() == "do-not-disturb"))
+    }
+
+    pub fn active() -> Option<bool> {
+        let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_uppercase();
+        let by_desktop = if desktop.contains("GNOME") || desktop.contains("UNITY") || desktop.contains("BUDGIE") {
+            gnome()
+        } else if desktop.contains("KDE") {
+            inhibited()
+        } else if desktop.contains("XFCE") {
+            xfce()
+        } else {
+            None
+        };
+        by_desktop.or_else(dunst).or_else(mako).or_else(inhibited)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+    use windows::core::{s, w};
+    use windows::Win32::System::LibraryLoader::{GetModuleHandleW, GetProcAddress};
+    use windows::Win32::UI::Shell::{SHQueryUserNotificationState, QUNS_PRESENTATION_MODE};
+
+    // WNF_SHEL_QUIETHOURS_ACTIVE_PROFILE_CHANGED: 0 off, 1 priority only, 2 alarms only
+    const QUIET_HOURS: u64 = 0x0D83_063E_A3BF_1C75;
+
+    type QueryWnfStateData = unsafe extern "system" fn(*const u64, *const c_void, *const c_void, *mut u32, *mut c_void, *mut u32) -> i32;
+
+    // The shell publishes Focus Assist's profile through WNF, which only ntdll exports
+    fn focus_assist() -> Option<bool> {
+        let ntdll = unsafe { GetModuleHandleW(w!("ntdll.dll")) }.ok()?;
+        let query = unsafe { GetProcAddress(ntdll, s!("NtQueryWnfStateData")) }?;
+        let query: QueryWnfStateData = unsafe { std::mem::transmute(query) };
+        let (mut stamp, mut profile, mut size) = (0u32, 0u32, std::mem::size_of::<u32>() as u32);
+        let status = unsafe {
+            query(&QUIET_HOURS, std::ptr::null(), std::ptr::null(), &mut stamp, &mut profile as *mut u32 as *mut c_void, &mut size)
+        };
+        (status >= 0).then_some(profile != 0)
+    }
+
+    pub fn active() -> Option<bool> {
+        let presenting = unsafe { SHQueryUserNotificationState() }.is_ok_and(|state| state == QUNS_PRESENTATION_MODE);
+        match focus_assist() {
+            Some(on) => Some(on || presenting),
+            None => presenting.then_some(true),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    // A Focus turned on by hand is an assertion record, none when every Focus is off
+    fn focus() -> Option<bool> {
+        let home = PathBuf::from(std::env::var_os("HOME")?);
+        let text = std::fs::read(home.join("Library/DoNotDisturb/DB/Assertions.json")).ok()?;
+        let assertions: serde_json::Value = serde_json::from_slice(&text).ok()?;
+        let records = assertions["data"].as_array()?;
+        Some(records.iter().any(|record| record["storeAssertionRecords"].as_array().is_some_and(|records| !records.is_empty())))
+    }
+
+    fn do_not_disturb() -> Option<bool> {
+        let output = Command::new("defaults").args(["-currentHost", "read", "com.apple.notificationcenterui", "doNotDisturb"]).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim() == "1")
+    }
+
+    pub fn active() -> Option<bool> {
+        focus().or_else(do_not_disturb)
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+mod platform {
+    pub fn active() -> Option<bool> {
+        None
+    }
+}
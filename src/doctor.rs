@@ -0,0 +1,188 @@
+// `doctor`: checks what tracking depends on, samples the focused window for a few seconds and
+// prints a report to attach to bug reports. Window titles are left out of it.
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde_json::json;
+
+use crate::calls;
+use crate::cli::Args;
+use crate::config;
+use crate::humanize;
+use crate::journal;
+use crate::storage;
+use crate::triggers;
+
+const SELF_TEST: Duration = Duration::from_secs(5);
+const SELF_TEST_INTERVAL: Duration = Duration::from_millis(250);
+
+const FEATURES: [(&str, bool); 12] = [
+    ("x11", cfg!(feature = "x11")),
+    ("notify", cfg!(feature = "notify")),
+    ("backup", cfg!(feature = "backup")),
+    ("sqlite", cfg!(feature = "sqlite")),
+    ("dbus", cfg!(feature = "dbus")),
+    ("otlp", cfg!(feature = "otlp")),
+    ("jira", cfg!(feature = "jira")),
+    ("activity", cfg!(feature = "activity")),
+    ("status", cfg!(feature = "status")),
+    ("websocket", cfg!(feature = "websocket")),
+    ("node", cfg!(feature = "node")),
+    ("wasm", cfg!(target_arch = "wasm32")),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Status {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Status::Ok => "ok",
+            Status::Warning => "warning",
+            Status::Failed => "FAILED",
+        })
+    }
+}
+
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn new(name: &str, status: Status, detail: impl Into<String>) -> Self {
+        Check { name: name.to_string(), status, detail: detail.into() }
+    }
+}
+
+fn storage_checks() -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    checks.push(match config::load() {
+        Ok(config) => Check::new("config", Status::Ok, format!(
+            "{} ({} category rules, {} ignore rules)", config::config_path().display(), config.categories.len(), config.ignore.len()
+        )),
+        Err(e) => Check::new("config", Status::Failed, e),
+    });
+
+    if let Some(profile_triggers) = config::load_base().ok().map(|config| config.profile_triggers).filter(|list| !list.is_empty()) {
+        let surroundings = triggers::Surroundings::probe(&profile_triggers);
+        let detail = match triggers::matching(&profile_triggers, &surroundings) {
+            Some(trigger) => format!("{}, switches to '{}'", surroundings.describe(), trigger.profile),
+            None => format!("{}, no trigger matches", surroundings.describe()),
+        };
+        checks.push(Check::new("profile triggers", Status::Ok, detail));
+    }
+
+    let data_dir = storage::data_dir();
+    let probe = data_dir.join(".doctor");
+    checks.push(match fs::create_dir_all(&data_dir).and_then(|_| fs::write(&probe, b"")) {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            Check::new("data directory", Status::Ok, format!("{} is writable", data_dir.display()))
+        }
+        Err(e) => Check::new("data directory", Status::Failed, format!("{}: {}", data_dir.display(), e)),
+    });
+
+    let day_ago = Utc::now() - chrono::Duration::days(1);
+    checks.push(match storage::load_intervals(day_ago, Utc::now()) {
+        Ok(intervals) => Check::new("history", Status::Ok, format!("{} intervals in the last 24 hours", intervals.len())),
+        Err(e) => Check::new("history", Status::Failed, e.to_string()),
+    });
+
+    checks.push(match journal::read() {
+        Ok(Some(interval)) => Check::new("journal", Status::Ok, format!("open interval last checkpointed at {}", interval.end.to_rfc3339())),
+        Ok(None) => Check::new("journal", Status::Ok, "no open interval"),
+        Err(e) => Check::new("journal", Status::Warning, e.to_string()),
+    });
+    checks
+}
+
+// Samples like the tracker does and summarizes what it saw without recording anything
+fn self_test() -> Vec<Check> {
+    let started = Instant::now();
+    let (mut samples, mut hits) = (0, 0);
+    let mut windows = HashSet::new();
+    let mut apps = HashSet::new();
+    let mut idle = None;
+    while started.elapsed() < SELF_TEST {
+        samples += 1;
+        if let Some(window) = crate::platform::get_active_window(false) {
+            hits += 1;
+            windows.insert(window.key());
+            apps.insert(if window.app.is_empty() { "unknown".to_string() } else { window.app });
+        }
+        idle = crate::wt_get_idle_time().or(idle);
+        thread::sleep(SELF_TEST_INTERVAL);
+    }
+
+    let mut apps: Vec<String> = apps.into_iter().collect();
+    apps.sort();
+    let capture = match hits {
+        0 => Check::new("self-test capture", Status::Failed, format!("no focused window in {} samples", samples)),
+        _ if hits < samples => Check::new("self-test capture", Status::Warning, format!(
+            "focused window in {} of {} samples, {} windows, apps: {}", hits, samples, windows.len(), apps.join(", ")
+        )),
+        _ => Check::new("self-test capture", Status::Ok, format!(
+            "focused window in all {} samples, {} windows, apps: {}", samples, windows.len(), apps.join(", ")
+        )),
+    };
+    let idle = match idle {
+        Some(idle) => Check::new("self-test idle time", Status::Ok, format!("{} since the last input", humanize::duration(idle.as_secs_f64()))),
+        None => Check::new("self-test idle time", Status::Warning, "not available, idle detection is disabled"),
+    };
+    let devices = calls::devices();
+    let call = Check::new("self-test call detection", Status::Ok, match (devices.camera, devices.microphone) {
+        (true, true) => "camera and microphone in use",
+        (true, false) => "camera in use",
+        (false, true) => "microphone in use",
+        (false, false) => "camera and microphone not in use",
+    });
+    vec![capture, idle, call]
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let json_output = args.json_output()?;
+    if !json_output {
+        eprintln!("Running a {} second self-test, keep using your computer normally...", SELF_TEST.as_secs());
+    }
+
+    let mut checks = crate::platform::checks();
+    checks.extend(storage_checks());
+    checks.extend(self_test());
+
+    let features: Vec<&str> = FEATURES.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| *name).collect();
+    if json_output {
+        let checks: Vec<serde_json::Value> = checks.iter()
+            .map(|check| json!({ "name": check.name, "status": check.status.to_string().to_lowercase(), "detail": check.detail }))
+            .collect();
+        println!("{}", json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "features": features,
+            "checks": checks,
+        }));
+    } else {
+        println!("window_tracker_concept {} on {} {}", env!("CARGO_PKG_VERSION"), std::env::consts::OS, std::env::consts::ARCH);
+        println!("Features: {}", if features.is_empty() { "none".to_string() } else { features.join(", ") });
+        println!();
+        for check in &checks {
+            println!("[{}] {}: {}", check.status, check.name, check.detail);
+        }
+    }
+
+    if checks.iter().any(|check| check.status == Status::Failed) {
+        return Err("some checks failed, see above".to_string());
+    }
+    Ok(())
+}
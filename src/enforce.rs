@@ -0,0 +1,756 @@
+// [enforce]: daily limits per app the tracker enforces rather than only reports, for parents and for
+// anyone holding themselves to one. warn_mins before an app's limit a notification says how much is
+// left, at the limit another says time is up, and once grace_mins more have passed the app's window
+// gets the limit's action whenever it has focus until midnight: minimize, close (on macOS the app
+// quits), or block, which on Windows leaves the window up without taking input. warn only notifies.
+// Focus time is counted from the samples, before [[ignore]] rules and with or without input, so an
+// ignored app and a game played with a controller count too.
+//
+// `enforce set-pin` keeps a PIN hash in pin_hash. While it is set, the running tracker keeps the
+// [enforce] it has through reloads and profile switches and refuses to pause until `enforce unlock`
+// is given the PIN. The PIN only guards the tracker's own controls: whoever can stop the tracker,
+// or edit config.toml while it is stopped, gets around it. For a child's account the tracker has to
+// run as, and config.toml belong to, an account the child can't use.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveDate};
+use serde_json::{json, Value};
+
+use crate::config::{AppLimit, Config, EnforceConfig, LimitAction};
+use crate::game_names::EXECUTABLE;
+use crate::humanize;
+use crate::i18n::tr;
+use crate::notify;
+use crate::report::daily_chunks;
+use crate::storage::Interval;
+use crate::ActiveWindow;
+
+const PIN_SCHEME: &str = "pbkdf2-sha256";
+// How long `enforce unlock` lasts without --mins
+pub const UNLOCK_MINS: u64 = 15;
+// Wrong PINs in a row before unlocking is refused for a while
+const MAX_FAILURES: u32 = 5;
+const REFUSE_FOR: Duration = Duration::from_secs(5 * 60);
+// A window still focused past its limit gets the action again this often
+const REPEAT_ACTION: Duration = Duration::from_secs(10);
+// A longer time between checks is the machine asleep, not focus time
+const MAX_STEP: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Stage {
+    Below,
+    Warned,
+    Up,
+}
+
+struct State {
+    // The [enforce] in effect, kept while locked whatever the config says now
+    config: EnforceConfig,
+    day: NaiveDate,
+    // Focus time today by app
+    secs: HashMap<String, f64>,
+    // What was notified today by app
+    notified: HashMap<String, Stage>,
+    // The last sample, before the ignore rules
+    window: Option<ActiveWindow>,
+    last_action: Option<(u64, Instant)>,
+    // Windows blocked today, let go at midnight and when tracking ends
+    blocked: Vec<u64>,
+    unlocked_until: Option<Instant>,
+    failures: u32,
+    refused_until: Option<Instant>,
+}
+
+impl State {
+    fn new(config: EnforceConfig) -> Self {
+        State {
+            config,
+            day: Local::now().date_naive(),
+            secs: HashMap::new(),
+            notified: HashMap::new(),
+            window: None,
+            last_action: None,
+            blocked: Vec::new(),
+            unlocked_until: None,
+            failures: 0,
+            refused_until: None,
+        }
+    }
+
+    fn locked(&self) -> bool {
+        self.config.pin_hash.is_some() && self.unlocked_until.is_none_or(|until| Instant::now() >= until)
+    }
+
+    fn release(&mut self) {
+        for id in self.blocked.drain(..) {
+            platform::unblock(id);
+        }
+    }
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+// Held by the `enforce unlock` being checked, so the next one sees whether it failed
+static ATTEMPT: Mutex<()> = Mutex::new(());
+
+// "Steam.exe" and "steam" are the same app
+fn stem(app: &str) -> String {
+    let app = app.trim().to_lowercase();
+    app.strip_suffix(".exe").map(str::to_string).unwrap_or(app)
+}
+
+fn limit_for<'a>(config: &'a EnforceConfig, app: &str) -> Option<&'a AppLimit> {
+    config.limits.iter().find(|limit| stem(&limit.app) == app)
+}
+
+fn action_name(action: LimitAction) -> &'static str {
+    match action {
+        LimitAction::Warn => "warn",
+        LimitAction::Minimize => "minimize",
+        LimitAction::Close => "close",
+        LimitAction::Block => "block",
+    }
+}
+
+// pbkdf2-sha256$<iterations>$<salt>$<hash>, salt and hash in hex
+fn parse_pin_hash(text: &str) -> Result<(u32, Vec<u8>, Vec<u8>), String> {
+    let invalid = || "pin_hash in [enforce] is not one `enforce set-pin` wrote, set the PIN again".to_string();
+    let parts: Vec<&str> = text.split('$').collect();
+    let [PIN_SCHEME, iterations, salt, hash] = parts.as_slice() else { return Err(invalid()) };
+    let iterations = iterations.parse::<u32>().ok().filter(|iterations| *iterations > 0).ok_or_else(invalid)?;
+    let bytes = |hex: &str| -> Option<Vec<u8>> {
+        if hex.is_empty() || !hex.len().is_multiple_of(2) {
+            return None;
+        }
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+    };
+    Ok((iterations, bytes(salt).ok_or_else(invalid)?, bytes(hash).ok_or_else(invalid)?))
+}
+
+#[cfg(feature = "enforce")]
+fn verify_pin(pin: &str, pin_hash: &str) -> Result<bool, String> {
+    use std::num::NonZeroU32;
+    let (iterations, salt, hash) = parse_pin_hash(pin_hash)?;
+    let iterations = NonZeroU32::new(iterations).ok_or("pin_hash has no iterations")?;
+    Ok(ring::pbkdf2::verify(ring::pbkdf2::PBKDF2_HMAC_SHA256, iterations, &salt, pin.as_bytes(), &hash).is_ok())
+}
+
+#[cfg(not(feature = "enforce"))]
+fn verify_pin(_pin: &str, _pin_hash: &str) -> Result<bool, String> {
+    Err("checking the PIN needs --features enforce".to_string())
+}
+
+pub fn check(config: &Config) -> Result<(), String> {
+    let Some(enforce) = &config.enforce else { return Ok(()) };
+    for limit in &enforce.limits {
+        if stem(&limit.app).is_empty() {
+            return Err("every [[enforce.limits]] needs the app it limits".to_string());
+        }
+        if limit.daily_mins == 0 {
+            return Err(format!("the limit for '{}' needs daily_mins above 0", limit.app));
+        }
+        if enforce.limits.iter().filter(|other| stem(&other.app) == stem(&limit.app)).count() > 1 {
+            return Err(format!("'{}' has more than one limit in [enforce]", limit.app));
+        }
+    }
+    if let Some(pin_hash) = &enforce.pin_hash {
+        parse_pin_hash(pin_hash)?;
+    }
+    Ok(())
+}
+
+// Takes the config's [enforce] unless the one in effect is locked with a PIN
+pub fn configure(config: &Config) {
+    let mut state = STATE.lock().unwrap();
+    if let Some(current) = state.as_ref().filter(|state| state.locked()) {
+        if config.enforce.as_ref() != Some(&current.config) {
+            eprintln!("[enforce] is locked with a PIN, the changes to it apply after `enforce unlock`");
+        }
+        return;
+    }
+    match (state.as_mut(), config.enforce.clone()) {
+        (Some(current), Some(enforce)) => current.config = enforce,
+        (None, Some(enforce)) => *state = Some(State::new(enforce)),
+        (Some(current), None) => {
+            current.release();
+            *state = None;
+        }
+        (None, None) => {}
+    }
+}
+
+// Today's intervals already in the history, when tracking starts
+pub fn seed(intervals: &[Interval]) {
+    let mut state = STATE.lock().unwrap();
+    let Some(state) = state.as_mut() else { return };
+    let day = Local::now().date_naive();
+    let mut secs: HashMap<String, f64> = HashMap::new();
+    for interval in intervals {
+        // A game game-names renamed still counts for its executable
+        let app = interval.attributes.get(EXECUTABLE).unwrap_or(&interval.app);
+        for (_, chunk) in daily_chunks(interval).into_iter().filter(|(chunk_day, _)| *chunk_day == day) {
+            *secs.entry(stem(app)).or_default() += chunk;
+        }
+    }
+    state.day = day;
+    state.secs = secs;
+}
+
+// Every sample, ignored windows included
+pub fn sampled(window: &ActiveWindow) {
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.window = Some(window.clone());
+    }
+}
+
+pub fn check_pause() -> Result<(), String> {
+    match STATE.lock().unwrap().as_ref() {
+        Some(state) if state.locked() => Err("the [enforce] limits are locked, pausing needs `enforce unlock` with the PIN".to_string()),
+        _ => Ok(()),
+    }
+}
+
+// The tracker exits, nothing stays blocked behind it
+pub fn release() {
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.release();
+    }
+}
+
+pub fn unlock(pin: &str, mins: u64) -> Result<(), String> {
+    let _attempt = ATTEMPT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    // Counted as a wrong PIN until it is verified, whatever happens meanwhile
+    let pin_hash = {
+        let mut state = STATE.lock().unwrap();
+        let state = state.as_mut().ok_or("the tracker enforces no limits")?;
+        if state.refused_until.is_some_and(|until| Instant::now() < until) {
+            return Err("too many wrong PINs, try again in a few minutes".to_string());
+        }
+        let pin_hash = state.config.pin_hash.clone().ok_or("[enforce] has no PIN, `enforce set-pin` sets one")?;
+        state.failures += 1;
+        pin_hash
+    };
+    // PBKDF2 takes a while, the tracker loop mustn't wait on the lock meanwhile
+    let verified = verify_pin(pin, &pin_hash)?;
+    let mut guard = STATE.lock().unwrap();
+    let state = guard.as_mut().ok_or("the tracker enforces no limits")?;
+    let now = Instant::now();
+    if !verified {
+        if state.failures >= MAX_FAILURES {
+            state.failures = 0;
+            state.refused_until = Some(now + REFUSE_FOR);
+        }
+        return Err("wrong PIN".to_string());
+    }
+    state.failures = 0;
+    state.unlocked_until = Some(now + Duration::from_secs(mins.max(1) * 60));
+    drop(guard);
+    // What was changed while locked applies now
+    match crate::config::load().and_then(|config| check(&config).map(|_| config)) {
+        Ok(config) => configure(&config),
+        Err(e) => eprintln!("Keeping the [enforce] in effect: {}", e),
+    }
+    Ok(())
+}
+
+pub fn lock() {
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.unlocked_until = None;
+    }
+}
+
+// Each limit with today's use, for `enforce status`; None without [enforce]
+pub fn status() -> Option<Value> {
+    let state = STATE.lock().unwrap();
+    let state = state.as_ref()?;
+    let today = Local::now().date_naive();
+    let used = |app: &str| if state.day == today { state.secs.get(&stem(app)).copied().unwrap_or(0.0) } else { 0.0 };
+    Some(status_json(&state.config, state.locked(), used))
+}
+
+fn status_json(config: &EnforceConfig, locked: bool, used: impl Fn(&str) -> f64) -> Value {
+    let limits: Vec<Value> = config.limits.iter()
+        .map(|limit| json!({
+            "app": limit.app,
+            "daily_mins": limit.daily_mins,
+            "used_secs": used(&limit.app),
+            "action": action_name(limit.action),
+        }))
+        .collect();
+    json!({ "locked": locked, "pin": config.pin_hash.is_some(), "limits": limits })
+}
+
+// Counts the focused app's time and escalates once it nears and passes its limit
+#[derive(Default)]
+pub struct EnforceWatch {
+    last: Option<Instant>,
+}
+
+impl EnforceWatch {
+    pub fn check(&mut self) {
+        let now = Instant::now();
+        let step = self.last.replace(now).map_or(Duration::ZERO, |last| (now - last).min(MAX_STEP));
+        let mut guard = STATE.lock().unwrap();
+        let Some(state) = guard.as_mut() else { return };
+        let today = Local::now().date_naive();
+        if state.day != today {
+            state.release();
+            state.day = today;
+            state.secs.clear();
+            state.notified.clear();
+            state.last_action = None;
+        }
+        if crate::wt_is_paused() {
+            return;
+        }
+        let Some(window) = state.window.clone() else { return };
+        let app = stem(&window.app);
+        let Some(limit) = limit_for(&state.config, &app).cloned() else { return };
+        let used = *state.secs.entry(app.clone()).and_modify(|secs| *secs += step.as_secs_f64()).or_insert(step.as_secs_f64());
+
+        let limit_secs = limit.daily_mins as f64 * 60.0;
+        let warn_secs = limit_secs - state.config.warn_mins as f64 * 60.0;
+        let grace_secs = state.config.grace_mins as f64 * 60.0;
+        let stage = if used >= limit_secs {
+            Stage::Up
+        } else if state.config.warn_mins > 0 && used >= warn_secs {
+            Stage::Warned
+        } else {
+            Stage::Below
+        };
+        let notified = state.notified.insert(app.clone(), stage).unwrap_or(Stage::Below);
+        if stage > notified {
+            notify_stage(&limit, stage, used, limit_secs, grace_secs);
+        }
+        if stage != Stage::Up || used < limit_secs + grace_secs || limit.action == LimitAction::Warn {
+            return;
+        }
+        if limit.action == LimitAction::Block && state.blocked.contains(&window.id) {
+            return;
+        }
+        if state.last_action.is_some_and(|(id, at)| id == window.id && now - at < REPEAT_ACTION) {
+            return;
+        }
+        state.last_action = Some((window.id, now));
+        let result = match limit.action {
+            LimitAction::Block => platform::block(&window).map(|blocked| {
+                if blocked {
+                    state.blocked.push(window.id);
+                }
+            }),
+            LimitAction::Close => platform::close(&window),
+            _ => platform::minimize(&window),
+        };
+        match result {
+            Ok(()) => eprintln!("The {} limit for {} is used up, applied {}", humanize::duration(limit_secs), limit.app, action_name(limit.action)),
+            Err(e) => eprintln!("Failed to {} {}: {}", action_name(limit.action), limit.app, e),
+        }
+    }
+}
+
+fn notify_stage(limit: &AppLimit, stage: Stage, used: f64, limit_secs: f64, grace_secs: f64) {
+    let (limit_text, grace) = (humanize::duration(limit_secs), humanize::duration(grace_secs));
+    let (summary, body) = match stage {
+        Stage::Below => return,
+        Stage::Warned => (
+            tr("limit-warn-title", &[("left", &humanize::duration(limit_secs - used)), ("app", &limit.app)]),
+            tr("limit-warn-body", &[("time", &humanize::duration(used)), ("limit", &limit_text)]),
+        ),
+        Stage::Up => {
+            let key = format!("limit-reached-{}", action_name(limit.action));
+            (tr("limit-reached-title", &[("app", &limit.app)]), tr(&key, &[("limit", &limit_text), ("grace", &grace)]))
+        }
+    };
+    if let Err(e) = notify::send(&summary, &body) {
+        eprintln!("{}: {} ({})", summary, body, e);
+    }
+}
+
+#[cfg(feature = "enforce")]
+mod command {
+    use std::fs;
+    use std::io::{self, IsTerminal};
+    use std::num::NonZeroU32;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    use serde_json::{json, Value};
+
+    use super::{check, parse_pin_hash, status_json, stem, verify_pin, PIN_SCHEME, UNLOCK_MINS};
+    use crate::cli::Args;
+    use crate::config::{self, Config};
+    use crate::ipc;
+    use crate::report::{daily_chunks, local_midnight};
+    use crate::review::prompt;
+    use crate::rollup;
+    use crate::storage;
+    use crate::table::{Format, Table};
+
+    const MIN_PIN_CHARS: usize = 4;
+    const ITERATIONS: u32 = 100_000;
+
+    // Without echo in a terminal on Unix; Windows consoles show what is typed
+    fn read_pin(text: &str) -> Result<String, String> {
+        let hidden = cfg!(unix) && io::stdin().is_terminal() && Command::new("stty").arg("-echo").status().is_ok_and(|status| status.success());
+        let pin = prompt(text);
+        if hidden {
+            let _ = Command::new("stty").arg("echo").status();
+            eprintln!();
+        }
+        pin?.filter(|pin| !pin.is_empty()).ok_or_else(|| "no PIN given".to_string())
+    }
+
+    fn hash_pin(pin: &str) -> Result<String, String> {
+        let salt = ipc::random_hex(16)?.into_bytes();
+        let mut hash = [0u8; 32];
+        let iterations = NonZeroU32::new(ITERATIONS).unwrap();
+        ring::pbkdf2::derive(ring::pbkdf2::PBKDF2_HMAC_SHA256, iterations, &salt, pin.as_bytes(), &mut hash);
+        let hex = |bytes: &[u8]| bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+        Ok(format!("{}${}${}${}", PIN_SCHEME, ITERATIONS, hex(&salt), hex(&hash)))
+    }
+
+    // config.toml with pin_hash set under [enforce], the rest of the file as it is
+    fn with_pin_hash(text: &str, pin_hash: &str) -> String {
+        let mut lines = Vec::new();
+        let (mut in_enforce, mut written) = (false, false);
+        for line in text.lines() {
+            let header = line.split('#').next().unwrap_or_default().trim();
+            if header.starts_with('[') {
+                in_enforce = header == "[enforce]";
+            }
+            if in_enforce && header.split('=').next().is_some_and(|key| key.trim() == "pin_hash") {
+                continue;
+            }
+            lines.push(line.to_string());
+            if in_enforce && header == "[enforce]" {
+                lines.push(format!("pin_hash = \"{}\"", pin_hash));
+                written = true;
+            }
+        }
+        // Only [[enforce.limits]] so far
+        if !written {
+            lines.push(String::new());
+            lines.push("[enforce]".to_string());
+            lines.push(format!("pin_hash = \"{}\"", pin_hash));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    fn write_pin_hash(pin_hash: &str) -> Result<PathBuf, String> {
+        let path = config::config_path();
+        let text = fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let updated = with_pin_hash(&text, pin_hash);
+        // Nothing is written that the tracker would refuse to load
+        let config: Config = toml::from_str(&updated).map_err(|e| format!("failed to put the PIN in {}: {}", path.display(), e))?;
+        check(&config)?;
+        if config.enforce.and_then(|enforce| enforce.pin_hash).as_deref() != Some(pin_hash) {
+            return Err(format!("failed to put the PIN in {}, add pin_hash = \"{}\" under [enforce]", path.display(), pin_hash));
+        }
+        fs::write(&path, updated).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    fn set_pin() -> Result<(), String> {
+        let enforce = config::load()?.enforce.ok_or("there are no limits to guard, add [enforce] with them to config.toml first")?;
+        let current = match &enforce.pin_hash {
+            Some(pin_hash) => {
+                parse_pin_hash(pin_hash)?;
+                let pin = read_pin("Current PIN: ")?;
+                if !verify_pin(&pin, pin_hash)? {
+                    return Err("wrong PIN".to_string());
+                }
+                Some(pin)
+            }
+            None => None,
+        };
+        let pin = read_pin("New PIN: ")?;
+        if pin.chars().count() < MIN_PIN_CHARS {
+            return Err(format!("the PIN needs at least {} characters", MIN_PIN_CHARS));
+        }
+        if read_pin("New PIN again: ")? != pin {
+            return Err("the PINs don't match".to_string());
+        }
+        // A running tracker only takes a changed [enforce] while unlocked
+        if let Some(current) = current {
+            ipc::request("enforce_unlock", json!({ "pin": current, "mins": 1 }))?;
+        }
+        let path = write_pin_hash(&hash_pin(&pin)?)?;
+        println!("Set the PIN in {}, changing [enforce] or pausing now needs `enforce unlock`", path.display());
+        Ok(())
+    }
+
+    fn unlock(args: &Args) -> Result<(), String> {
+        let mins: u64 = args.parsed("--mins", UNLOCK_MINS)?;
+        let pin = read_pin("PIN: ")?;
+        match ipc::request("enforce_unlock", json!({ "pin": pin, "mins": mins }))? {
+            Some(_) => println!("Unlocked for {} minutes: changes to [enforce] apply and tracking can be paused", mins.max(1)),
+            None => println!("No tracker is running, config.toml can be edited as it is"),
+        }
+        Ok(())
+    }
+
+    fn lock() -> Result<(), String> {
+        match ipc::request("enforce_lock", json!({}))? {
+            Some(_) => println!("Locked again"),
+            None => println!("No tracker is running"),
+        }
+        Ok(())
+    }
+
+    // From the running tracker, or from today's history when none runs
+    fn load_status() -> Result<Option<Value>, String> {
+        if let Some(response) = ipc::request("enforce_status", json!({}))? {
+            return Ok(Some(response["enforce"].clone()).filter(|status| !status.is_null()));
+        }
+        let Some(enforce) = config::load()?.enforce else { return Ok(None) };
+        let now = chrono::Local::now();
+        let start = local_midnight(now.date_naive()).with_timezone(&chrono::Utc);
+        let intervals = storage::load_intervals(start, now.with_timezone(&chrono::Utc)).map_err(|e| format!("failed to read the history: {}", e))?;
+        let used = |app: &str| -> f64 {
+            intervals.iter()
+                .filter(|interval| stem(interval.attributes.get(super::EXECUTABLE).unwrap_or(&interval.app)) == stem(app))
+                .flat_map(daily_chunks)
+                .filter(|(day, _)| *day == now.date_naive())
+                .map(|(_, secs)| secs)
+                .sum()
+        };
+        Ok(Some(status_json(&enforce, enforce.pin_hash.is_some(), used)))
+    }
+
+    fn status(args: &Args) -> Result<(), String> {
+        let format = match Format::from_args(args)? {
+            Some(format) => format,
+            None if args.json_output()? => Format::Json,
+            None => Format::Table,
+        };
+        let Some(status) = load_status()? else {
+            println!("No limits are enforced, [enforce] in the config sets them");
+            return Ok(());
+        };
+        let mut table = Table::new(&["app", "daily_mins", "used_secs", "left_secs", "action"]);
+        for limit in status["limits"].as_array().into_iter().flatten() {
+            let used = limit["used_secs"].as_f64().unwrap_or(0.0);
+            let left = (limit["daily_mins"].as_f64().unwrap_or(0.0) * 60.0 - used).max(0.0);
+            table.push(vec![limit["app"].clone(), limit["daily_mins"].clone(), rollup::rounded(used), rollup::rounded(left), limit["action"].clone()]);
+        }
+        if table.is_empty() && format == Format::Table {
+            println!("[enforce] has no limits yet, add [[enforce.limits]] with app and daily_mins");
+            return Ok(());
+        }
+        table.print(format);
+        if format == Format::Table {
+            match (status["pin"].as_bool(), status["locked"].as_bool()) {
+                (Some(true), Some(true)) => println!("\nLocked with a PIN"),
+                (Some(true), _) => println!("\nUnlocked for now"),
+                _ => println!("\nNo PIN set, `enforce set-pin` guards the limits"),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn run(args: &Args) -> Result<(), String> {
+        match args.arg(1) {
+            Some("status") | None => status(args),
+            Some(option) if option.starts_with('-') => status(args),
+            Some("set-pin") => set_pin(),
+            Some("unlock") => unlock(args),
+            Some("lock") => lock(),
+            Some(_) => Err("usage: enforce <status|set-pin|unlock|lock>".to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "enforce")]
+pub use command::run;
+
+#[cfg(windows)]
+mod platform {
+    use crate::ActiveWindow;
+    use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+    use windows::Win32::UI::Input::KeyboardAndMouse::EnableWindow;
+    use windows::Win32::UI::WindowsAndMessaging::{IsWindow, PostMessageW, ShowWindow, SW_MINIMIZE, WM_CLOSE};
+
+    fn hwnd(id: u64) -> HWND {
+        HWND(id as usize as *mut std::ffi::c_void)
+    }
+
+    pub fn minimize(window: &ActiveWindow) -> Result<(), String> {
+        let _ = unsafe { ShowWindow(hwnd(window.id), SW_MINIMIZE) };
+        Ok(())
+    }
+
+    // As the close button would, the app may still ask to save
+    pub fn close(window: &ActiveWindow) -> Result<(), String> {
+        unsafe { PostMessageW(hwnd(window.id), WM_CLOSE, WPARAM(0), LPARAM(0)) }.map_err(|e| e.to_string())
+    }
+
+    pub fn block(window: &ActiveWindow) -> Result<bool, String> {
+        let _ = unsafe { EnableWindow(hwnd(window.id), false) };
+        Ok(true)
+    }
+
+    pub fn unblock(id: u64) {
+        if unsafe { IsWindow(hwnd(id)) }.as_bool() {
+            let _ = unsafe { EnableWindow(hwnd(id), true) };
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "x11"))]
+mod platform {
+    use crate::ActiveWindow;
+    use std::os::raw::c_long;
+    use x11::xlib::{
+        AnyPropertyType, ClientMessage, Display, SubstructureNotifyMask, SubstructureRedirectMask, Window, XClientMessageEvent,
+        XCloseDisplay, XDefaultRootWindow, XDefaultScreen, XEvent, XFlush, XFree, XGetWindowProperty, XIconifyWindow, XInternAtom,
+        XOpenDisplay, XSendEvent,
+    };
+
+    fn property(display: *mut Display, window: Window, name: &std::ffi::CStr) -> Option<c_long> {
+        let atom = unsafe { XInternAtom(display, name.as_ptr(), 1) };
+        if atom == 0 {
+            return None;
+        }
+        let (mut actual_type, mut format, mut count, mut after) = (0, 0, 0, 0);
+        let mut data = std::ptr::null_mut();
+        let status = unsafe {
+            XGetWindowProperty(display, window, atom, 0, 1, 0, AnyPropertyType as u64, &mut actual_type, &mut format, &mut count, &mut after, &mut data)
+        };
+        if status != 0 || data.is_null() {
+            return None;
+        }
+        let value = (count > 0 && format == 32).then(|| unsafe { *(data as *const c_long) });
+        unsafe { XFree(data.cast()) };
+        value
+    }
+
+    // The sampled window may be a child holding the input focus, the window manager acts on the top
+    // level one it made active. It is only used while it is still the sampled app's.
+    fn with_window(window: &ActiveWindow, act: impl FnOnce(*mut Display, Window) -> bool) -> Result<(), String> {
+        let display = unsafe { XOpenDisplay(std::ptr::null()) };
+        if display.is_null() {
+            return Err("cannot open the X display".to_string());
+        }
+        let root = unsafe { XDefaultRootWindow(display) };
+        let target = property(display, root, c"_NET_ACTIVE_WINDOW")
+            .map(|active| active as Window)
+            .filter(|active| *active != 0 && property(display, *active, c"_NET_WM_PID").is_some_and(|pid| pid as u32 == window.pid))
+            .unwrap_or(window.id as Window);
+        let done = act(display, target);
+        unsafe {
+            XFlush(display);
+            XCloseDisplay(display);
+        }
+        if done { Ok(()) } else { Err("the window manager refused".to_string()) }
+    }
+
+    pub fn minimize(window: &ActiveWindow) -> Result<(), String> {
+        with_window(window, |display, target| unsafe { XIconifyWindow(display, target, XDefaultScreen(display)) } != 0)
+    }
+
+    // _NET_CLOSE_WINDOW, what the title bar's close button asks for
+    pub fn close(window: &ActiveWindow) -> Result<(), String> {
+        with_window(window, |display, target| {
+            let atom = unsafe { XInternAtom(display, c"_NET_CLOSE_WINDOW".as_ptr(), 0) };
+            let mut message = XClientMessageEvent {
+                type_: ClientMessage,
+                serial: 0,
+                send_event: 1,
+                display,
+                window: target,
+                message_type: atom,
+                format: 32,
+                data: Default::default(),
+            };
+            // No timestamp, from a pager
+            message.data.set_long(1, 2);
+            let mut event = XEvent { client_message: message };
+            let root = unsafe { XDefaultRootWindow(display) };
+            unsafe { XSendEvent(display, root, 0, SubstructureRedirectMask | SubstructureNotifyMask, &mut event) != 0 }
+        })
+    }
+
+    // X11 has no input lock for another client's window
+    pub fn block(window: &ActiveWindow) -> Result<bool, String> {
+        minimize(window).map(|_| false)
+    }
+
+    pub fn unblock(_id: u64) {}
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use crate::ActiveWindow;
+    use std::process::Command;
+
+    // Through System Events, which needs the Automation permission the first time
+    fn run_script(script: String) -> Result<(), String> {
+        std::thread::spawn(move || {
+            if let Err(e) = Command::new("osascript").args(["-e", &script]).output() {
+                eprintln!("Failed to run osascript: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    pub fn minimize(window: &ActiveWindow) -> Result<(), String> {
+        run_script(format!("tell application \"System Events\" to set visible of (first process whose unix id is {}) to false", window.pid))
+    }
+
+    // Windows can't be told apart from outside without the Accessibility permission, the app quits
+    pub fn close(window: &ActiveWindow) -> Result<(), String> {
+        run_script(format!(
+            "tell application \"System Events\" to set appName to name of (first process whose unix id is {})\ntell application appName to quit",
+            window.pid
+        ))
+    }
+
+    pub fn block(window: &ActiveWindow) -> Result<bool, String> {
+        minimize(window).map(|_| false)
+    }
+
+    pub fn unblock(_id: u64) {}
+}
+
+#[cfg(not(any(windows, target_os = "macos", all(target_os = "linux", feature = "x11"))))]
+mod platform {
+    use crate::ActiveWindow;
+
+    pub fn minimize(_window: &ActiveWindow) -> Result<(), String> {
+        Err("windows can't be minimized on this platform".to_string())
+    }
+
+    pub fn close(_window: &ActiveWindow) -> Result<(), String> {
+        Err("windows can't be closed on this platform".to_string())
+    }
+
+    pub fn block(_window: &ActiveWindow) -> Result<bool, String> {
+        Err("windows can't be blocked on this platform".to_string())
+    }
+
+    pub fn unblock(_id: u64) {}
+}
+
+#[cfg(all(test, feature = "enforce"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrong_pins_at_once_are_refused_after_max_failures() {
+        let (salt, iterations) = (b"0123456789abcdef", 1_000);
+        let mut hash = [0u8; 32];
+        ring::pbkdf2::derive(ring::pbkdf2::PBKDF2_HMAC_SHA256, std::num::NonZeroU32::new(iterations).unwrap(), salt, b"4711", &mut hash);
+        let hex = |bytes: &[u8]| bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+        let pin_hash = format!("{}${}${}${}", PIN_SCHEME, iterations, hex(salt), hex(&hash));
+        *STATE.lock().unwrap() = Some(State::new(EnforceConfig { pin_hash: Some(pin_hash), warn_mins: 5, grace_mins: 1, limits: Vec::new() }));
+
+        let attempts: Vec<_> = (0..20).map(|i| std::thread::spawn(move || unlock(&format!("{:04}", i), 1).unwrap_err())).collect();
+        let errors: Vec<String> = attempts.into_iter().map(|attempt| attempt.join().unwrap()).collect();
+        assert_eq!(errors.iter().filter(|e| *e == "wrong PIN").count(), MAX_FAILURES as usize, "{:?}", errors);
+        assert!(errors.iter().filter(|e| *e != "wrong PIN").all(|e| e.starts_with("too many wrong PINs")));
+        // The right PIN waits out the lockout too
+        assert!(unlock("4711", 1).unwrap_err().starts_with("too many wrong PINs"));
+        assert!(STATE.lock().unwrap().as_ref().unwrap().unlocked_until.is_none());
+    }
+}
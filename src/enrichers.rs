@@ -0,0 +1,216 @@
+// [[enrichers]]: every finished interval passes through these before it is stored, so attribution
+// rules this crate doesn't know about can add attributes, change the category or redact the title
+// without a fork. Programs embedding the library implement Enricher; anything else, like a small
+// script, runs as a plugin process that reads one interval as a JSON line on stdin and answers each
+// with one JSON line on stdout:
+// {"category": "Client work", "attributes": {"client": "acme"}, "title": "(private)"}, all
+// optional, {} to keep it as is, and "app" renames the app like game-names does with game
+// executables. The live display and the journal still see the title the window had.
+// Plugins start with the first interval and keep running; one that exits, hangs past timeout_ms or
+// answers garbage is restarted for the next interval and the current one is stored unchanged.
+// With script instead of command the answer comes from a sandboxed rhai script, see scripts.rs.
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::config::{Config, EnricherConfig};
+use crate::storage::Interval;
+
+pub trait Enricher: Send {
+    // In error messages
+    fn name(&self) -> &str;
+    // An error leaves the interval as the enrichers before this one made it
+    fn enrich(&mut self, interval: &mut Interval) -> Result<(), String>;
+}
+
+// What a plugin answers with, and a script gives back
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Changes {
+    category: Option<String>,
+    title: Option<String>,
+    app: Option<String>,
+    attributes: BTreeMap<String, String>,
+}
+
+impl Changes {
+    pub(crate) fn apply(self, interval: &mut Interval) {
+        if let Some(category) = self.category {
+            interval.category = category;
+        }
+        if let Some(title) = self.title {
+            interval.title = title;
+        }
+        if let Some(app) = self.app {
+            interval.app = app;
+        }
+        interval.attributes.extend(self.attributes);
+    }
+}
+
+struct Running {
+    child: Child,
+    stdin: ChildStdin,
+    // Lines from stdout, read on their own thread so a hung plugin can time out
+    lines: Receiver<String>,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct Plugin {
+    config: EnricherConfig,
+    running: Option<Running>,
+}
+
+impl Plugin {
+    fn new(config: &EnricherConfig) -> Self {
+        Plugin { config: config.clone(), running: None }
+    }
+
+    fn start(&self) -> Result<Running, String> {
+        let (program, args) = self.config.command.split_first().ok_or("the command is empty")?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("failed to start {}: {}", program, e))?;
+        let stdin = child.stdin.take().ok_or("no stdin")?;
+        let stdout = child.stdout.take().ok_or("no stdout")?;
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Running { child, stdin, lines })
+    }
+
+    fn exchange(&mut self, interval: &Interval) -> Result<Changes, String> {
+        if self.running.is_none() {
+            self.running = Some(self.start()?);
+        }
+        let running = self.running.as_mut().unwrap();
+        let mut line = serde_json::to_string(interval).map_err(|e| e.to_string())?;
+        line.push('\n');
+        running.stdin.write_all(line.as_bytes())
+            .and_then(|_| running.stdin.flush())
+            .map_err(|e| format!("failed to write to it: {}", e))?;
+        let answer = match running.lines.recv_timeout(Duration::from_millis(self.config.timeout_ms)) {
+            Ok(answer) => answer,
+            Err(RecvTimeoutError::Timeout) => return Err(format!("no answer within {}ms", self.config.timeout_ms)),
+            Err(RecvTimeoutError::Disconnected) => return Err("it exited".to_string()),
+        };
+        serde_json::from_str(&answer).map_err(|e| format!("unreadable answer '{}': {}", answer, e))
+    }
+}
+
+impl Enricher for Plugin {
+    fn name(&self) -> &str {
+        self.config.name.as_deref().or(self.config.command.first().map(String::as_str)).unwrap_or_default()
+    }
+
+    fn enrich(&mut self, interval: &mut Interval) -> Result<(), String> {
+        match self.exchange(interval) {
+            Ok(changes) => {
+                changes.apply(interval);
+                Ok(())
+            }
+            Err(e) => {
+                // Started afresh next time rather than answering this interval late
+                self.running = None;
+                Err(e)
+            }
+        }
+    }
+}
+
+// A plugin or script from [[enrichers]], with the settings it was made from
+struct Configured {
+    config: EnricherConfig,
+    enricher: Box<dyn Enricher>,
+}
+
+fn configured(config: &EnricherConfig) -> Result<Configured, String> {
+    let enricher: Box<dyn Enricher> = match &config.script {
+        #[cfg(feature = "scripting")]
+        Some(_) => Box::new(crate::scripts::Script::load(config)?),
+        #[cfg(not(feature = "scripting"))]
+        Some(script) => return Err(format!("the script {} needs --features scripting", script.display())),
+        None => Box::new(Plugin::new(config)),
+    };
+    Ok(Configured { config: config.clone(), enricher })
+}
+
+#[derive(Default)]
+pub struct Enrichers {
+    // Added through wt_add_enricher, they run first and stay across config changes
+    custom: Vec<Box<dyn Enricher>>,
+    plugins: Vec<Configured>,
+}
+
+impl Enrichers {
+    pub fn check(config: &Config) -> Result<(), String> {
+        for enricher in &config.enrichers {
+            let script = enricher.script.as_ref().map(|script| script.display().to_string());
+            let name = enricher.name.clone().or(enricher.command.first().cloned()).or(script).unwrap_or_default();
+            match (&enricher.script, enricher.command.first()) {
+                (Some(_), Some(_)) => return Err(format!("enricher '{}' has a command and a script, give it one of them", name)),
+                (None, program) if program.is_none_or(|program| program.is_empty()) => {
+                    return Err(format!("enricher '{}' needs a command or a script", name));
+                }
+                // Compiled, so a broken script is refused with the rest of the config
+                (Some(_), None) => {
+                    configured(enricher).map_err(|e| format!("enricher '{}': {}", name, e))?;
+                }
+                _ => {}
+            }
+            if enricher.timeout_ms == 0 {
+                return Err(format!("enricher '{}': timeout_ms must be at least 1", name));
+            }
+            if enricher.max_operations == 0 {
+                return Err(format!("enricher '{}': max_operations must be at least 1", name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, enricher: Box<dyn Enricher>) {
+        self.custom.push(enricher);
+    }
+
+    // Plugins whose settings didn't change keep their process, scripts their compiled form
+    pub fn configure(&mut self, config: &Config) {
+        let mut previous = std::mem::take(&mut self.plugins);
+        self.plugins = config.enrichers.iter()
+            .filter_map(|enricher| match previous.iter().position(|plugin| plugin.config == *enricher) {
+                Some(index) => Some(previous.remove(index)),
+                None => configured(enricher).map_err(|e| eprintln!("Enricher left out: {}", e)).ok(),
+            })
+            .collect();
+    }
+
+    pub fn enrich(&mut self, interval: &mut Interval) {
+        let enrichers = self.custom.iter_mut().map(|enricher| enricher.as_mut() as &mut dyn Enricher)
+            .chain(self.plugins.iter_mut().map(|plugin| plugin.enricher.as_mut() as &mut dyn Enricher));
+        for enricher in enrichers {
+            let before = interval.clone();
+            if let Err(e) = enricher.enrich(interval) {
+                eprintln!("Enricher '{}' failed: {}", enricher.name(), e);
+                *interval = before;
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// The typed event model behind --stream json and the WebSocket: each event serializes to one of
+// the stream's JSON lines and reads back from it, so crates building on the tracker don't have to
+// pick fields out of JSON values.
+use std::fmt;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::humanize;
+use crate::storage::{EventKind, Interval};
+use crate::ActiveWindow;
+
+// Focus moved to another window, or the focused one changed its title or call state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusEvent {
+    pub event: EventKind,
+    pub time: DateTime<Utc>,
+    pub title: String,
+    pub app: String,
+    // See ActiveWindow::key
+    pub window_id: String,
+}
+
+impl FocusEvent {
+    pub fn new(event: EventKind, window: &ActiveWindow) -> Self {
+        FocusEvent { event, time: Utc::now(), title: window.title.clone(), app: window.app.clone(), window_id: window.key() }
+    }
+}
+
+impl fmt::Display for FocusEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}: {} ({})", timestamp(&self.time), self.event.as_str(), self.title, self.app)
+    }
+}
+
+// Crossing the idle threshold, in either direction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum IdleEvent {
+    IdleStart { time: DateTime<Utc>, idle_secs: f64 },
+    IdleEnd { time: DateTime<Utc> },
+}
+
+impl fmt::Display for IdleEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdleEvent::IdleStart { time, idle_secs } => write!(f, "{} idle_start: no input for {}", timestamp(time), humanize::duration(*idle_secs)),
+            IdleEvent::IdleEnd { time } => write!(f, "{} idle_end", timestamp(time)),
+        }
+    }
+}
+
+// What happens to the tracking session itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SessionEvent {
+    IntervalFinalized { time: DateTime<Utc>, interval: Interval, duration_secs: f64 },
+    ProfileSwitched { time: DateTime<Utc>, profile: String },
+    ConfigReloaded { time: DateTime<Utc>, changes: Vec<String> },
+}
+
+impl fmt::Display for SessionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionEvent::IntervalFinalized { time, interval, duration_secs } => {
+                write!(f, "{} interval_finalized: {} of {} ({})", timestamp(time), humanize::duration(*duration_secs), interval.title, interval.app_name())
+            }
+            SessionEvent::ProfileSwitched { time, profile } => write!(f, "{} profile_switched: {}", timestamp(time), profile),
+            SessionEvent::ConfigReloaded { time, changes } if changes.is_empty() => write!(f, "{} config_reloaded", timestamp(time)),
+            SessionEvent::ConfigReloaded { time, changes } => write!(f, "{} config_reloaded: {}", timestamp(time), changes.join(", ")),
+        }
+    }
+}
+
+// Focus time of one title in the current session, or of an app, class or category with aggregate_by,
+// see wt_get_all_windows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowTime {
+    pub title: String,
+    pub focus_secs: f64,
+}
+
+impl fmt::Display for WindowTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.title, humanize::duration(self.focus_secs))
+    }
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
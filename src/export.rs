@@ -0,0 +1,83 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+use crate::cli::{self, Args};
+use crate::heatmap;
+use crate::history;
+use crate::influx;
+use crate::invoice;
+use crate::languages;
+use crate::notes;
+
+pub fn run(args: &Args) -> Result<(), String> {
+    if args.flag("--heatmap") {
+        return heatmap::run(args);
+    }
+    if args.flag("--invoice") {
+        return invoice::run(args);
+    }
+    if args.flag("--languages") {
+        return languages::run(args);
+    }
+    if args.flag("--influx") {
+        return influx::run_export(args);
+    }
+    match args.value("--format") {
+        Some("markdown") => return notes::run(args),
+        Some(format @ ("csv" | "jsonl")) => return history::run(args, format),
+        _ => {}
+    }
+    if args.flag("--everything") {
+        #[cfg(feature = "backup")]
+        return crate::backup::run_export_everything(args);
+        #[cfg(not(feature = "backup"))]
+        return Err("export --everything is not available in this build, rebuild with --features backup".to_string());
+    }
+
+    Err(format!("nothing to export, pass --heatmap, --invoice, --languages, --influx, --format <markdown|csv|jsonl> or --everything\n\n{}", cli::USAGE))
+}
+
+pub fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// Writes to --output when given, stdout otherwise.
+pub fn write_output(args: &Args, content: &str) -> Result<(), String> {
+    match args.value("--output") {
+        Some(path) => {
+            fs::write(path, content).map_err(|e| format!("failed to write {}: {}", path, e))?;
+            eprintln!("Exported to {}", path);
+            Ok(())
+        }
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+// write_output for exports made as they go: `write` gets --output or stdout behind a buffer, so
+// nothing has to be held in memory first
+pub fn stream_output(args: &Args, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> Result<(), String> {
+    match args.value("--output") {
+        Some(path) => {
+            let file = File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?;
+            let mut out = BufWriter::new(file);
+            write(&mut out).and_then(|()| out.flush()).map_err(|e| format!("failed to export to {}: {}", path, e))?;
+            eprintln!("Exported to {}", path);
+            Ok(())
+        }
+        None => {
+            let mut out = BufWriter::new(io::stdout().lock());
+            match write(&mut out).and_then(|()| out.flush()) {
+                // Whatever reads it, like head, has all it wants
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                result => result.map_err(|e| format!("failed to export: {}", e)),
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+// C ABI over the wt_* functions for hosts like C# (P/Invoke); include/window_tracker.h declares it.
+// Strings handed out by the library are owned by the caller and must be released with the matching
+// wt_free_* function. The *_utf16 variants return NUL-terminated UTF-16 for Windows hosts.
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{c_char, c_int, c_void, CString};
+use std::sync::{Mutex, Once};
+
+use crate::report::{self, GroupBy};
+use crate::storage::EventKind;
+
+pub type FocusCallback = extern "C" fn(user_data: *mut c_void, event: c_int, title: *const c_char, app: *const c_char);
+pub type FocusCallbackUtf16 = extern "C" fn(user_data: *mut c_void, event: c_int, title: *const u16, app: *const u16);
+
+#[derive(Clone, Copy)]
+enum Callback {
+    Utf8(FocusCallback),
+    Utf16(FocusCallbackUtf16),
+}
+
+// The user data pointer is only ever passed back to the host, never dereferenced
+struct Registration {
+    callback: Callback,
+    user_data: usize,
+}
+
+static CALLBACK: Mutex<Option<Registration>> = Mutex::new(None);
+static LISTENER: Once = Once::new();
+
+fn event_code(event: EventKind) -> c_int {
+    match event {
+        EventKind::FocusChanged => 0,
+        EventKind::TitleChanged => 1,
+        // Calls only split stored intervals, focus listeners never see this
+        EventKind::CallChanged => 1,
+    }
+}
+
+fn set_callback(registration: Option<Registration>) {
+    *CALLBACK.lock().unwrap() = registration;
+    LISTENER.call_once(|| {
+        crate::wt_add_focus_listener(Box::new(|event, window| {
+            let Some(registration) = CALLBACK.lock().unwrap().as_ref().map(|r| (r.callback, r.user_data)) else { return };
+            let (callback, user_data) = registration;
+            match callback {
+                Callback::Utf8(callback) => {
+                    let (title, app) = (to_c_string(&window.title), to_c_string(&window.app));
+                    callback(user_data as *mut c_void, event_code(event), title.as_ptr(), app.as_ptr());
+                }
+                Callback::Utf16(callback) => {
+                    let (title, app) = (to_utf16(&window.title), to_utf16(&window.app));
+                    callback(user_data as *mut c_void, event_code(event), title.as_ptr(), app.as_ptr());
+                }
+            }
+        }));
+    });
+}
+
+// Interior NULs can't cross the C boundary, they are dropped
+fn to_c_string(text: &str) -> CString {
+    CString::new(text.replace('\0', "")).unwrap()
+}
+
+fn to_utf16(text: &str) -> Vec<u16> {
+    text.encode_utf16().filter(|unit| *unit != 0).chain(std::iter::once(0)).collect()
+}
+
+fn into_raw_utf16(text: &str) -> *mut u16 {
+    Box::into_raw(to_utf16(text).into_boxed_slice()).cast()
+}
+
+#[no_mangle]
+pub extern "C" fn wt_init() {
+    crate::wt_init();
+}
+
+#[no_mangle]
+pub extern "C" fn wt_update() {
+    crate::wt_update();
+}
+
+#[no_mangle]
+pub extern "C" fn wt_cleanup() {
+    crate::wt_cleanup();
+}
+
+#[no_mangle]
+pub extern "C" fn wt_get_window_count() -> usize {
+    crate::wt_get_window_count()
+}
+
+#[no_mangle]
+pub extern "C" fn wt_get_focus_seconds() -> f64 {
+    crate::wt_get_focus_duration().as_secs_f64()
+}
+
+#[no_mangle]
+pub extern "C" fn wt_get_today_seconds() -> f64 {
+    crate::wt_get_today_secs()
+}
+
+// Changes whenever the current window or its title does
+#[no_mangle]
+pub extern "C" fn wt_get_current_id() -> u64 {
+    crate::wt_get_current_id()
+}
+
+// Negative when the platform can't tell
+#[no_mangle]
+pub extern "C" fn wt_get_idle_seconds() -> f64 {
+    crate::wt_get_idle_time().map_or(-1.0, |idle| idle.as_secs_f64())
+}
+
+// Called on the thread running wt_update() for every focus (event 0) or title (event 1) change.
+// The strings are only valid during the call. Pass NULL to unregister.
+#[no_mangle]
+pub extern "C" fn wt_set_focus_callback(callback: Option<FocusCallback>, user_data: *mut c_void) {
+    set_callback(callback.map(|callback| Registration { callback: Callback::Utf8(callback), user_data: user_data as usize }));
+}
+
+#[no_mangle]
+pub extern "C" fn wt_set_focus_callback_utf16(callback: Option<FocusCallbackUtf16>, user_data: *mut c_void) {
+    set_callback(callback.map(|callback| Registration { callback: Callback::Utf16(callback), user_data: user_data as usize }));
+}
+
+// Returns 0 and fills in the title (free with wt_free_string) and focus time, or -1 past the last window
+#[no_mangle]
+pub unsafe extern "C" fn wt_get_window_info(index: usize, title: *mut *mut c_char, focus_time: *mut f64) -> c_int {
+    let Some(window) = crate::wt_get_window_info(index) else { return -1 };
+    if !title.is_null() {
+        *title = to_c_string(&window.title).into_raw();
+    }
+    if !focus_time.is_null() {
+        *focus_time = window.focus_secs;
+    }
+    0
+}
+
+// As wt_get_window_info, the title is released with wt_free_string_utf16
+#[no_mangle]
+pub unsafe extern "C" fn wt_get_window_info_utf16(index: usize, title: *mut *mut u16, focus_time: *mut f64) -> c_int {
+    let Some(window) = crate::wt_get_window_info(index) else { return -1 };
+    if !title.is_null() {
+        *title = into_raw_utf16(&window.title);
+    }
+    if !focus_time.is_null() {
+        *focus_time = window.focus_secs;
+    }
+    0
+}
+
+fn report_json(days: u32) -> Option<String> {
+    if days == 0 {
+        return None;
+    }
+    match GroupBy::configured().and_then(|group_by| report::load(days, group_by)) {
+        Ok(summary) => Some(report::render_json(&summary)),
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    }
+}
+
+// The report for the last `days` days as JSON, NULL when the history can't be read
+#[no_mangle]
+pub extern "C" fn wt_report_json(days: u32) -> *mut c_char {
+    report_json(days).map_or(std::ptr::null_mut(), |json| to_c_string(&json).into_raw())
+}
+
+#[no_mangle]
+pub extern "C" fn wt_report_json_utf16(days: u32) -> *mut u16 {
+    report_json(days).map_or(std::ptr::null_mut(), |json| into_raw_utf16(&json))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wt_free_string(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wt_free_string_utf16(text: *mut u16) {
+    if text.is_null() {
+        return;
+    }
+    let mut length = 0;
+    while *text.add(length) != 0 {
+        length += 1;
+    }
+    drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(text, length + 1)));
+}
@@ -0,0 +1,459 @@
+// --filter expressions picking intervals, the same in every command and in the HTTP API:
+//   app = "firefox" AND category != "Work" AND duration > 5m
+//   (title ~ "jira|confluence" OR field:ticket != "") AND NOT call = true
+// Text fields (app, title, category, class, window, event and field:<name> for what the title
+// templates extracted) compare with = and != ignoring case, ~ and !~ with a case-insensitive
+// regex. duration takes 90, 90s, 5m or 1h30m; start and end a day like in --since or an RFC 3339
+// time with <, <=, > and >=; call true or false. AND binds tighter than OR, parentheses group.
+use std::iter::Peekable;
+use std::str::Chars;
+
+use chrono::{DateTime, Utc};
+use regex::{Regex, RegexBuilder};
+
+use crate::report::local_midnight;
+use crate::rules::parse_day;
+use crate::storage::Interval;
+
+// Matches everything when empty
+#[derive(Debug, Clone, Default)]
+pub struct Filter(Option<Expr>);
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Text(TextField, TextTest),
+    Duration(Compare, f64),
+    Time(TimeField, Compare, DateTime<Utc>),
+    Call(bool),
+}
+
+#[derive(Debug, Clone)]
+enum TextField {
+    App,
+    Title,
+    Category,
+    Class,
+    Window,
+    Event,
+    Attribute(String),
+}
+
+#[derive(Debug, Clone)]
+enum TextTest {
+    // Lowercased
+    Equal(String),
+    NotEqual(String),
+    Matches(Regex),
+    NotMatches(Regex),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TimeField {
+    Start,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compare {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Compare {
+    fn holds<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Compare::Equal => left == right,
+            Compare::NotEqual => left != right,
+            Compare::Less => left < right,
+            Compare::LessOrEqual => left <= right,
+            Compare::Greater => left > right,
+            Compare::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    // A field name, keyword or unquoted value
+    Word(String),
+    Quoted(String),
+    Operator(&'static str),
+    Open,
+    Close,
+}
+
+const OPERATORS: [&str; 9] = ["==", "!=", "!~", "<=", ">=", "=", "~", "<", ">"];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<Chars> = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => value.push(chars.next().ok_or("unfinished escape at the end of the filter")?),
+                        Some(c) => value.push(c),
+                        None => return Err("unclosed quote in the filter".to_string()),
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            '=' | '!' | '~' | '<' | '>' => {
+                let rest: String = chars.clone().take(2).collect();
+                let Some(operator) = OPERATORS.iter().find(|operator| rest.starts_with(*operator)) else {
+                    return Err(format!("unknown operator '{}' in the filter", rest));
+                };
+                for _ in 0..operator.len() {
+                    chars.next();
+                }
+                tokens.push(Token::Operator(operator));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()\"=!~<>".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// 90, 90s, 5m, 1h30m and the like, in seconds
+fn parse_duration(text: &str) -> Option<f64> {
+    if let Ok(secs) = text.parse::<f64>() {
+        return Some(secs);
+    }
+    let (mut secs, mut number) = (0.0, String::new());
+    for c in text.chars() {
+        match c {
+            '0'..='9' | '.' => number.push(c),
+            'h' | 'm' | 's' if !number.is_empty() => {
+                let unit = match c { 'h' => 3600.0, 'm' => 60.0, _ => 1.0 };
+                secs += number.parse::<f64>().ok()? * unit;
+                number.clear();
+            }
+            _ => return None,
+        }
+    }
+    number.is_empty().then_some(secs)
+}
+
+fn parse_time(text: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    Ok(local_midnight(parse_day(text, "the filter")?).with_timezone(&Utc))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    // The next token when it is the keyword, in any case
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let expr = self.or()?;
+            if self.next() != Some(Token::Close) {
+                return Err("missing ) in the filter".to_string());
+            }
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let field = match self.next() {
+            Some(Token::Word(word)) => word,
+            Some(other) => return Err(format!("expected a field name in the filter, found {}", describe(&other))),
+            None => return Err("the filter ends where a field name should be".to_string()),
+        };
+        let operator = match self.next() {
+            Some(Token::Operator(operator)) => operator,
+            _ => return Err(format!("expected =, !=, ~, !~, <, <=, > or >= after {} in the filter", field)),
+        };
+        let value = match self.next() {
+            Some(Token::Word(value) | Token::Quoted(value)) => value,
+            _ => return Err(format!("expected a value after {} {} in the filter", field, operator)),
+        };
+        let compare = match operator {
+            "=" | "==" => Compare::Equal,
+            "!=" => Compare::NotEqual,
+            "<" => Compare::Less,
+            "<=" => Compare::LessOrEqual,
+            ">" => Compare::Greater,
+            ">=" => Compare::GreaterOrEqual,
+            _ => Compare::Equal,
+        };
+
+        let text = match field.to_ascii_lowercase().as_str() {
+            "duration" => {
+                let secs = parse_duration(&value).ok_or_else(|| format!("invalid duration '{}' in the filter, expected e.g. 90s, 5m or 1h30m", value))?;
+                return match operator {
+                    "~" | "!~" => Err("duration compares with =, !=, <, <=, > or >=".to_string()),
+                    _ => Ok(Expr::Duration(compare, secs)),
+                };
+            }
+            name @ ("start" | "end") => {
+                if matches!(operator, "=" | "==" | "!=" | "~" | "!~") {
+                    return Err(format!("{} compares with <, <=, > or >=", name));
+                }
+                let field = if name == "start" { TimeField::Start } else { TimeField::End };
+                return Ok(Expr::Time(field, compare, parse_time(&value)?));
+            }
+            "call" => {
+                let call = match value.to_ascii_lowercase().as_str() {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(format!("call is true or false, not '{}'", value)),
+                };
+                return match compare {
+                    Compare::Equal => Ok(Expr::Call(call)),
+                    Compare::NotEqual => Ok(Expr::Call(!call)),
+                    _ => Err("call compares with = or !=".to_string()),
+                };
+            }
+            "app" => TextField::App,
+            "title" => TextField::Title,
+            "category" => TextField::Category,
+            "class" => TextField::Class,
+            "window" => TextField::Window,
+            "event" => TextField::Event,
+            _ => match field.strip_prefix("field:") {
+                Some(name) if !name.is_empty() => TextField::Attribute(name.to_string()),
+                _ => return Err(format!("unknown field '{}' in the filter, expected app, title, category, class, window, event, duration, start, end, call or field:<name>", field)),
+            },
+        };
+        let regex = || RegexBuilder::new(&value).case_insensitive(true).build()
+            .map_err(|e| format!("invalid pattern '{}' in the filter: {}", value, e));
+        let test = match operator {
+            "=" | "==" => TextTest::Equal(value.to_lowercase()),
+            "!=" => TextTest::NotEqual(value.to_lowercase()),
+            "~" => TextTest::Matches(regex()?),
+            "!~" => TextTest::NotMatches(regex()?),
+            _ => return Err(format!("{} compares with =, !=, ~ or !~", field)),
+        };
+        Ok(Expr::Text(text, test))
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => format!("'{}'", word),
+        Token::Quoted(value) => format!("\"{}\"", value),
+        Token::Operator(operator) => format!("'{}'", operator),
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+    }
+}
+
+impl Filter {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let tokens = tokenize(text)?;
+        if tokens.is_empty() {
+            return Ok(Filter::default());
+        }
+        let mut parser = Parser { tokens, position: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {} in the filter, join conditions with AND or OR", describe(token)));
+        }
+        Ok(Filter(Some(expr)))
+    }
+
+    // --filter when given, everything otherwise
+    pub fn from_args(args: &crate::cli::Args) -> Result<Self, String> {
+        args.value("--filter").map(Filter::parse).transpose().map(Option::unwrap_or_default)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    pub fn matches(&self, interval: &Interval) -> bool {
+        self.0.as_ref().is_none_or(|expr| expr.matches(interval))
+    }
+}
+
+impl Expr {
+    fn matches(&self, interval: &Interval) -> bool {
+        match self {
+            Expr::And(left, right) => left.matches(interval) && right.matches(interval),
+            Expr::Or(left, right) => left.matches(interval) || right.matches(interval),
+            Expr::Not(expr) => !expr.matches(interval),
+            Expr::Text(field, test) => {
+                let value = match field {
+                    TextField::App => interval.app_name(),
+                    TextField::Title => &interval.title,
+                    TextField::Category => interval.category_name(),
+                    TextField::Class => interval.class_name(),
+                    TextField::Window => &interval.window_id,
+                    TextField::Event => interval.event.as_str(),
+                    TextField::Attribute(name) => interval.attributes.get(name).map(String::as_str).unwrap_or_default(),
+                };
+                match test {
+                    TextTest::Equal(expected) => value.to_lowercase() == *expected,
+                    TextTest::NotEqual(expected) => value.to_lowercase() != *expected,
+                    TextTest::Matches(pattern) => pattern.is_match(value),
+                    TextTest::NotMatches(pattern) => !pattern.is_match(value),
+                }
+            }
+            Expr::Duration(compare, secs) => compare.holds(interval.duration_secs(), *secs),
+            Expr::Time(TimeField::Start, compare, time) => compare.holds(interval.start, *time),
+            Expr::Time(TimeField::End, compare, time) => compare.holds(interval.end, *time),
+            Expr::Call(call) => interval.call == *call,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    fn interval(app: &str, title: &str, category: &str, mins: u64) -> Interval {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut interval = Interval::new(title, app, start, start + Duration::from_secs(mins * 60));
+        interval.category = category.to_string();
+        interval
+    }
+
+    // Which of the intervals the filter picks, by title
+    fn picked<'a>(filter: &str, intervals: &'a [Interval]) -> Vec<&'a str> {
+        let filter = Filter::parse(filter).unwrap();
+        intervals.iter().filter(|interval| filter.matches(interval)).map(|interval| interval.title.as_str()).collect()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_and_parentheses_group() {
+        let intervals = [
+            interval("firefox", "Docs", "Work", 10),
+            interval("firefox", "News", "Leisure", 10),
+            interval("code", "main.rs", "Work", 60),
+        ];
+        // app = code OR (app = firefox AND category = Leisure)
+        assert_eq!(picked("app = code OR app = firefox AND category = Leisure", &intervals), ["News", "main.rs"]);
+        assert_eq!(picked("(app = code OR app = firefox) AND category = Leisure", &intervals), ["News"]);
+        assert_eq!(picked("app = firefox and category = work or duration >= 1h", &intervals), ["Docs", "main.rs"]);
+    }
+
+    #[test]
+    fn not_applies_to_the_next_condition_or_group() {
+        let intervals = [
+            interval("firefox", "Docs", "Work", 10),
+            interval("firefox", "News", "Leisure", 10),
+            interval("code", "main.rs", "Work", 60),
+        ];
+        assert_eq!(picked("NOT app = firefox AND category = Work", &intervals), ["main.rs"]);
+        assert_eq!(picked("NOT (app = firefox AND category = Work)", &intervals), ["News", "main.rs"]);
+        assert_eq!(picked("NOT NOT app = code", &intervals), ["main.rs"]);
+        assert_eq!(picked("title !~ \"^n\" AND NOT duration > 30m", &intervals), ["Docs"]);
+    }
+
+    #[test]
+    fn quoted_values_keep_spaces_operators_and_keywords() {
+        let intervals = [
+            interval("excel", "Q1 = plan (draft)", "Work", 10),
+            interval("excel", "and", "Work", 10),
+            interval("excel", "Say \"hi\"", "Work", 10),
+        ];
+        assert_eq!(picked("title = \"Q1 = plan (draft)\"", &intervals), ["Q1 = plan (draft)"]);
+        assert_eq!(picked("title = \"AND\"", &intervals), ["and"]);
+        assert_eq!(picked("title = \"say \\\"hi\\\"\"", &intervals), ["Say \"hi\""]);
+        assert_eq!(picked("title ~ \"\\\\(draft\\\\)$\"", &intervals), ["Q1 = plan (draft)"]);
+        // An empty filter picks everything
+        assert_eq!(picked("  ", &intervals).len(), 3);
+    }
+
+    #[test]
+    fn malformed_filters_are_errors() {
+        for (filter, error) in [
+            ("title = \"open", "unclosed quote"),
+            ("title = \"end\\", "unfinished escape"),
+            ("app =! firefox", "unknown operator"),
+            ("(app = code", "missing )"),
+            ("app = code)", "unexpected ')'"),
+            ("app = code firefox", "unexpected 'firefox'"),
+            ("app = code AND", "ends where a field name should be"),
+            ("= code", "expected a field name"),
+            ("app code", "expected =, !="),
+            ("app =", "expected a value after app ="),
+            ("colour = red", "unknown field 'colour'"),
+            ("field: = x", "unknown field"),
+            ("duration > soon", "invalid duration"),
+            ("duration ~ 5m", "duration compares with"),
+            ("start = 2024-01-01", "start compares with <"),
+            ("call = maybe", "call is true or false"),
+            ("call > true", "call compares with = or !="),
+            ("app < code", "app compares with =, !=, ~ or !~"),
+            ("title ~ \"(\"", "invalid pattern"),
+        ] {
+            let e = Filter::parse(filter).unwrap_err();
+            assert!(e.contains(error), "{}: {}", filter, e);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// `game-names`: an [[enrichers]] plugin shipped with the tracker that records games under the name
+// their library gives them, "ELDEN RING" rather than eldenring.exe. The interval's process (from
+// its window_id) is looked up in the Steam appmanifests, then its executable, or the app where
+// there is no process, in the Epic launcher's manifests (legendary's and Heroic's installed.json on
+// Linux). A game found gets the name as its app and game attribute, and keeps the executable in the
+// executable attribute so rules can still tell it apart:
+//   [[enrichers]]
+//   name = "game names"
+//   command = ["window_tracker_concept", "game-names"]
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+
+use crate::cli::Args;
+use crate::games::{self, GAME, STEAM_APPID};
+use crate::storage::Interval;
+
+pub const EXECUTABLE: &str = "executable";
+pub const EPIC_APP: &str = "epic_app";
+
+#[derive(Debug, Clone)]
+struct Found {
+    name: String,
+    // The attribute naming it in its store
+    store_id: Option<(&'static str, String)>,
+}
+
+struct EpicGame {
+    name: String,
+    app: String,
+    install: PathBuf,
+    executable: String,
+}
+
+fn home() -> PathBuf {
+    std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default()
+}
+
+// Backslashes as slashes and lowercase, Windows paths compare like that
+fn comparable(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").to_lowercase()
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit(['/', '\\']).next().unwrap_or(path).to_lowercase()
+}
+
+// The launcher's own .item manifests on Windows and macOS
+fn launcher_manifests() -> Vec<EpicGame> {
+    let dirs = [
+        std::env::var_os("PROGRAMDATA").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+            .join(r"Epic\EpicGamesLauncher\Data\Manifests"),
+        home().join("Library/Application Support/Epic/EpicGamesLauncher/Data/Manifests"),
+    ];
+    dirs.iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "item"))
+        .filter_map(|entry| serde_json::from_slice::<Value>(&fs::read(entry.path()).ok()?).ok())
+        .filter_map(|item| Some(EpicGame {
+            name: item["DisplayName"].as_str()?.to_string(),
+            app: item["AppName"].as_str().unwrap_or_default().to_string(),
+            install: PathBuf::from(item["InstallLocation"].as_str()?),
+            executable: item["LaunchExecutable"].as_str().unwrap_or_default().to_string(),
+        }))
+        .collect()
+}
+
+// legendary's installed.json, Heroic keeps its own copy
+fn legendary_manifests() -> Vec<EpicGame> {
+    let config = std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from).unwrap_or_else(|| home().join(".config"));
+    let files = [
+        config.join("legendary/installed.json"),
+        home().join(".config/heroic/legendaryConfig/legendary/installed.json"),
+    ];
+    files.iter()
+        .filter_map(|file| serde_json::from_slice::<Map<String, Value>>(&fs::read(file).ok()?).ok())
+        .flat_map(|installed| installed.into_iter())
+        .filter_map(|(app, game)| Some(EpicGame {
+            name: game["title"].as_str()?.to_string(),
+            app,
+            install: PathBuf::from(game["install_path"].as_str()?),
+            executable: game["executable"].as_str().unwrap_or_default().to_string(),
+        }))
+        .collect()
+}
+
+fn epic_game(paths: &[PathBuf], app: &str) -> Option<Found> {
+    let app = file_name(app);
+    let games: Vec<EpicGame> = launcher_manifests().into_iter().chain(legendary_manifests()).collect();
+    let installed_in = |game: &EpicGame| {
+        let install = comparable(&game.install);
+        !install.is_empty() && paths.iter().any(|path| comparable(path).starts_with(&format!("{}/", install.trim_end_matches('/'))))
+    };
+    games.iter()
+        .find(|game| installed_in(game))
+        .or_else(|| games.iter().find(|game| !game.executable.is_empty() && file_name(&game.executable) == app))
+        .map(|game| Found {
+            name: game.name.clone(),
+            store_id: (!game.app.is_empty()).then(|| (EPIC_APP, game.app.clone())),
+        })
+}
+
+fn resolve(interval: &Interval) -> Option<Found> {
+    let pid = interval.window_id.split(':').next().and_then(|pid| pid.parse::<u32>().ok()).filter(|pid| *pid != 0);
+    let process = pid.and_then(games::process);
+    if let Some(game) = process.as_ref().zip(pid).and_then(|(process, pid)| games::steam_game(process, pid)) {
+        return Some(Found { name: game.name, store_id: game.steam_appid.map(|appid| (STEAM_APPID, appid)) });
+    }
+    let paths = process.map(|process| process.paths).unwrap_or_default();
+    epic_game(&paths, &interval.app)
+}
+
+fn answer(interval: &Interval, found: Option<&Found>) -> Value {
+    let Some(found) = found.filter(|found| found.name != interval.app) else { return json!({}) };
+    let mut attributes = Map::new();
+    attributes.insert(GAME.to_string(), found.name.clone().into());
+    attributes.insert(EXECUTABLE.to_string(), interval.app.clone().into());
+    if let Some((attribute, id)) = &found.store_id {
+        attributes.insert(attribute.to_string(), id.clone().into());
+    }
+    json!({ "app": found.name, "attributes": attributes })
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    // Each answer is a JSON object whatever -o says, that's what the tracker reads back; an unknown
+    // -o is still refused like everywhere else
+    args.json_output()?;
+    // A process keeps its executable, so each is looked up once
+    let mut known: HashMap<(String, String), Option<Found>> = HashMap::new();
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|e| format!("failed to read an interval: {}", e))?;
+        let reply = match serde_json::from_str::<Interval>(&line) {
+            Ok(interval) => {
+                let key = (interval.window_id.clone(), interval.app.clone());
+                let found = known.entry(key).or_insert_with(|| resolve(&interval));
+                answer(&interval, found.as_ref())
+            }
+            Err(_) => json!({}),
+        };
+        writeln!(stdout, "{}", reply).and_then(|_| stdout.flush()).map_err(|e| format!("failed to answer: {}", e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,462 @@
+// [games]: game detection and the playtime ledger. A window is a game when its app is one of the
+// configured apps, when a game launcher (Steam, Battle.net, Epic, GOG Galaxy, ...) is among the
+// ancestors of its process, or when it was seen holding the screen in exclusive fullscreen
+// (Windows). Steam games are named by their appmanifest and carry their appid. Intervals in a game
+// are stored with the game and steam_appid attributes, and games.json in the data folder keeps the
+// time, sessions and last play of each for `report --games`, rebuilt from the history when it is
+// missing or the history was rewritten since.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::Args;
+use crate::config::{self, Config, GamesConfig};
+use crate::filter::Filter;
+use crate::rollup;
+use crate::storage::{self, Interval};
+use crate::table::{Format, Table};
+use crate::ActiveWindow;
+
+pub const GAME: &str = "game";
+pub const STEAM_APPID: &str = "steam_appid";
+const LEDGER_FILE: &str = "games.json";
+// Process names, lowercase and without .exe
+const LAUNCHERS: &[&str] = &[
+    "steam", "battle.net", "epicgameslauncher", "galaxyclient", "eadesktop", "origin", "upc", "ubisoftconnect",
+    "heroic", "lutris", "legendary", "itch", "playnite.desktopapp", "playnite.fullscreenapp", "rockstarlauncher",
+];
+// The launchers' own windows run as their children too: the store, overlays, crash reporters
+const NOT_GAMES: &[&str] = &["helper", "overlay", "crash", "update", "error"];
+// Parents looked through before giving up, against cycles of reused pids
+const MAX_ANCESTORS: usize = 32;
+// Processes remembered before the cache starts over
+const MAX_KNOWN: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    pub name: String,
+    pub steam_appid: Option<String>,
+}
+
+struct Detector {
+    config: GamesConfig,
+    // Per pid, the app it was detected for (pids are reused) and the answer
+    known: HashMap<u32, (String, Option<Game>)>,
+    // Pids seen in exclusive fullscreen
+    fullscreen: HashSet<u32>,
+}
+
+static DETECTOR: Mutex<Option<Detector>> = Mutex::new(None);
+
+pub fn configure(config: &Config) {
+    *DETECTOR.lock().unwrap() = config.games.clone().map(|config| Detector { config, known: HashMap::new(), fullscreen: HashSet::new() });
+}
+
+fn process_name(name: &str) -> String {
+    let name = name.to_lowercase();
+    name.strip_suffix(".exe").map(str::to_string).unwrap_or(name)
+}
+
+// Linux cuts process names to 15 bytes
+fn same_process(name: &str, known: &str) -> bool {
+    name == known || (name.len() == 15 && known.starts_with(name))
+}
+
+fn is_launcher(name: &str, config: &GamesConfig) -> bool {
+    LAUNCHERS.iter().any(|launcher| same_process(name, launcher))
+        || config.launchers.iter().any(|launcher| same_process(name, &process_name(launcher)))
+}
+
+// Called with every sampled window; exclusive fullscreen is only known while the game has focus
+pub fn sampled(window: &ActiveWindow) {
+    let mut detector = DETECTOR.lock().unwrap();
+    let Some(detector) = detector.as_mut() else { return };
+    if detector.config.fullscreen && window.pid != 0 && !detector.fullscreen.contains(&window.pid) && platform::exclusive_fullscreen() {
+        detector.fullscreen.insert(window.pid);
+        detector.known.remove(&window.pid);
+    }
+}
+
+// The game the window is, None when it isn't one or detection is off
+pub fn detect(window: &ActiveWindow) -> Option<Game> {
+    let mut detector = DETECTOR.lock().unwrap();
+    let detector = detector.as_mut()?;
+    if window.pid == 0 {
+        return None;
+    }
+    if let Some((app, game)) = detector.known.get(&window.pid) {
+        if *app == window.app {
+            return game.clone();
+        }
+    }
+    if detector.known.len() >= MAX_KNOWN {
+        detector.known.clear();
+        detector.fullscreen.clear();
+    }
+    let game = find(window, &detector.config, detector.fullscreen.contains(&window.pid));
+    detector.known.insert(window.pid, (window.app.clone(), game.clone()));
+    game
+}
+
+fn find(window: &ActiveWindow, config: &GamesConfig, fullscreen: bool) -> Option<Game> {
+    let name = process_name(&window.app);
+    let configured = config.apps.iter().any(|app| process_name(app) == name);
+    if !configured && (is_launcher(&name, config) || NOT_GAMES.iter().any(|part| name.contains(part))) {
+        return None;
+    }
+    let process = process(window.pid);
+    let launched = process.as_ref().is_some_and(|process| launched(process, config));
+    let steam = process.as_ref().and_then(|process| steam_game(process, window.pid));
+    if !configured && !launched && !fullscreen && steam.is_none() {
+        return None;
+    }
+    Some(steam.unwrap_or(Game { name: game_name(&window.app), steam_appid: None }))
+}
+
+fn launched(process: &Process, config: &GamesConfig) -> bool {
+    let mut parent = process.parent;
+    for _ in 0..MAX_ANCESTORS {
+        let Some(ancestor) = (parent > 1).then(|| platform::process(parent)).flatten() else { return false };
+        if is_launcher(&process_name(&ancestor.name), config) {
+            return true;
+        }
+        parent = ancestor.parent;
+    }
+    false
+}
+
+// The app without .exe, as it was written
+fn game_name(app: &str) -> String {
+    app.strip_suffix(".exe").or_else(|| app.strip_suffix(".EXE")).unwrap_or(app).to_string()
+}
+
+pub struct Process {
+    pub name: String,
+    pub parent: u32,
+    // The executable and the command line, where a Proton game's own path is
+    pub paths: Vec<PathBuf>,
+}
+
+pub fn process(pid: u32) -> Option<Process> {
+    platform::process(pid)
+}
+
+// A process under steamapps/common/<folder>, or one Steam started with SteamAppId in its
+// environment, named by the appmanifest of its library
+pub fn steam_game(process: &Process, pid: u32) -> Option<Game> {
+    let appid = platform::steam_appid(pid);
+    if let Some((steamapps, folder)) = process.paths.iter().find_map(|path| steam_folder(path)) {
+        return Some(manifest(&steamapps, |fields| fields.get("installdir").is_some_and(|dir| dir.eq_ignore_ascii_case(&folder)))
+            .unwrap_or(Game { name: folder, steam_appid: appid }));
+    }
+    let appid = appid?;
+    steam_libraries().iter()
+        .find_map(|steamapps| manifest(steamapps, |fields| fields.get("appid") == Some(&appid)))
+        .or(Some(Game { name: game_name(&process.name), steam_appid: Some(appid) }))
+}
+
+// The steamapps folder and the game's folder in it
+fn steam_folder(path: &Path) -> Option<(PathBuf, String)> {
+    path.ancestors().find_map(|folder| {
+        let common = folder.parent()?;
+        let steamapps = common.parent()?;
+        let named = |path: &Path, name: &str| path.file_name().is_some_and(|file| file.to_string_lossy().eq_ignore_ascii_case(name));
+        if !named(common, "common") || !named(steamapps, "steamapps") {
+            return None;
+        }
+        Some((steamapps.to_path_buf(), folder.file_name()?.to_string_lossy().into_owned()))
+    })
+}
+
+// The "key" "value" pairs of a Steam .acf or .vdf file, nesting ignored
+fn fields(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split('"').collect();
+            (parts.len() >= 5).then(|| (parts[1].to_lowercase(), parts[3].replace("\\\\", "\\")))
+        })
+        .collect()
+}
+
+fn manifest(steamapps: &Path, matches: impl Fn(&HashMap<String, String>) -> bool) -> Option<Game> {
+    fs::read_dir(steamapps).ok()?.filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("appmanifest_"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .map(|text| fields(&text).into_iter().collect::<HashMap<_, _>>())
+        .find(|fields| matches(fields))
+        .and_then(|mut fields| Some(Game { name: fields.remove("name")?, steam_appid: fields.remove("appid") }))
+}
+
+// The steamapps folders of the default Steam install and the libraries it lists
+fn steam_libraries() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    let roots = [
+        home.join(".steam/steam"),
+        home.join(".local/share/Steam"),
+        home.join("Library/Application Support/Steam"),
+        PathBuf::from(r"C:\Program Files (x86)\Steam"),
+    ];
+    let mut libraries: Vec<PathBuf> = roots.iter().map(|root| root.join("steamapps")).filter(|path| path.is_dir()).collect();
+    let listed: Vec<PathBuf> = libraries.iter()
+        .filter_map(|steamapps| fs::read_to_string(steamapps.join("libraryfolders.vdf")).ok())
+        .flat_map(|text| fields(&text))
+        .filter(|(key, _)| key == "path")
+        .map(|(_, path)| PathBuf::from(path).join("steamapps"))
+        .collect();
+    for library in listed {
+        if !libraries.contains(&library) && library.is_dir() {
+            libraries.push(library);
+        }
+    }
+    libraries
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Entry {
+    secs: f64,
+    sessions: u64,
+    last_played: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    steam_appid: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Ledger {
+    // The history generation it was built from, see storage::history_replaced
+    generation: u64,
+    games: BTreeMap<String, Entry>,
+}
+
+fn ledger_path() -> PathBuf {
+    storage::data_dir().join(LEDGER_FILE)
+}
+
+fn session_gap(config: Option<&GamesConfig>) -> Duration {
+    Duration::minutes(config.map_or(15, |config| config.session_gap_mins) as i64)
+}
+
+fn add(ledger: &mut Ledger, interval: &Interval, gap: Duration) {
+    let Some(game) = interval.attributes.get(GAME) else { return };
+    let entry = ledger.games.entry(game.clone()).or_default();
+    if entry.last_played.is_none_or(|last| interval.start - last > gap) {
+        entry.sessions += 1;
+    }
+    entry.secs += interval.duration_secs();
+    entry.last_played = Some(entry.last_played.map_or(interval.end, |last| last.max(interval.end)));
+    if let Some(appid) = interval.attributes.get(STEAM_APPID) {
+        entry.steam_appid = Some(appid.clone());
+    }
+}
+
+fn rebuild(gap: Duration) -> io::Result<Ledger> {
+    let generation = storage::generation();
+    let mut played = Vec::new();
+    storage::scan_matching(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC, &Filter::default(), |chunk| {
+        played.extend(chunk.iter().filter(|interval| interval.attributes.contains_key(GAME)).cloned());
+        Ok(())
+    })?;
+    played.sort_by_key(|interval| interval.start);
+    let mut ledger = Ledger { generation, games: BTreeMap::new() };
+    for interval in &played {
+        add(&mut ledger, interval, gap);
+    }
+    Ok(ledger)
+}
+
+fn write(ledger: &Ledger) -> io::Result<()> {
+    let path = ledger_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, serde_json::to_vec_pretty(ledger)?)?;
+    fs::rename(temporary, path)
+}
+
+// The ledger on disk, or rebuilt and written when it is missing, unreadable or out of date
+fn load(gap: Duration, rebuilt: bool) -> io::Result<Ledger> {
+    let stored = fs::read(ledger_path()).ok()
+        .and_then(|bytes| serde_json::from_slice::<Ledger>(&bytes).ok())
+        .filter(|ledger| !rebuilt && ledger.generation == storage::generation());
+    match stored {
+        Some(ledger) => Ok(ledger),
+        None => {
+            let ledger = rebuild(gap)?;
+            write(&ledger)?;
+            Ok(ledger)
+        }
+    }
+}
+
+// Adds a stored game interval to the ledger; a rebuilt one already has it, the interval having been
+// queued before this
+pub fn interval_stored(interval: &Interval) {
+    if !interval.attributes.contains_key(GAME) || crate::wt_is_ephemeral() {
+        return;
+    }
+    let gap = match DETECTOR.lock().unwrap().as_ref() {
+        Some(detector) => session_gap(Some(&detector.config)),
+        None => return,
+    };
+    let stored = fs::read(ledger_path()).ok()
+        .and_then(|bytes| serde_json::from_slice::<Ledger>(&bytes).ok())
+        .filter(|ledger| ledger.generation == storage::generation());
+    let result = match stored {
+        Some(mut ledger) => {
+            add(&mut ledger, interval, gap);
+            write(&ledger)
+        }
+        None => rebuild(gap).and_then(|ledger| write(&ledger)),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to update {}: {}", LEDGER_FILE, e);
+    }
+}
+
+// `report --games`
+pub fn report(args: &Args) -> Result<(), String> {
+    let format = match Format::from_args(args)? {
+        Some(format) => format,
+        None if args.json_output()? => Format::Json,
+        None => Format::Table,
+    };
+    let config = config::load()?;
+    let ledger = load(session_gap(config.games.as_ref()), args.flag("--rebuild"))
+        .map_err(|e| format!("failed to read the playtime ledger: {}", e))?;
+    let mut games: Vec<(&String, &Entry)> = ledger.games.iter().collect();
+    games.sort_by(|a, b| b.1.last_played.cmp(&a.1.last_played).then_with(|| a.0.cmp(b.0)));
+
+    let mut table = Table::new(&["game", "steam_appid", "secs", "sessions", "last_played"]);
+    for (game, entry) in games {
+        let last_played = entry.last_played.map(|last| last.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string());
+        table.push(vec![
+            game.as_str().into(),
+            entry.steam_appid.clone().into(),
+            rollup::rounded(entry.secs),
+            entry.sessions.into(),
+            last_played.into(),
+        ]);
+    }
+    if table.is_empty() && format == Format::Table {
+        println!("{}", if config.games.is_some() { "No games played yet" } else { "No games recorded, [games] in the config turns detection on" });
+        return Ok(());
+    }
+    table.print(format);
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::Process;
+    use std::fs;
+    use std::path::PathBuf;
+
+    pub fn process(pid: u32) -> Option<Process> {
+        // pid (name) state ppid ..., the name may itself hold spaces and parentheses
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        let (name, rest) = stat.split_once(" (").and_then(|(_, rest)| rest.rsplit_once(") "))?;
+        let parent = rest.split_whitespace().nth(1)?.parse().ok()?;
+        let mut paths: Vec<PathBuf> = fs::read_link(format!("/proc/{}/exe", pid)).into_iter().collect();
+        let command = fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+        paths.extend(command.split(|byte| *byte == 0)
+            .map(|argument| String::from_utf8_lossy(argument).replace('\\', "/"))
+            // Wine's Z: drive is /
+            .map(|argument| argument.strip_prefix("Z:").or_else(|| argument.strip_prefix("z:")).map(str::to_string).unwrap_or(argument))
+            .filter(|argument| argument.starts_with('/'))
+            .map(PathBuf::from));
+        Some(Process { name: name.to_string(), parent, paths })
+    }
+
+    pub fn steam_appid(pid: u32) -> Option<String> {
+        let environment = fs::read(format!("/proc/{}/environ", pid)).ok()?;
+        let variables: Vec<String> = environment.split(|byte| *byte == 0).map(|variable| String::from_utf8_lossy(variable).into_owned()).collect();
+        ["SteamAppId=", "SteamGameId="].iter()
+            .find_map(|name| variables.iter().find_map(|variable| variable.strip_prefix(name)))
+            .filter(|appid| !appid.is_empty() && appid.bytes().all(|byte| byte.is_ascii_digit()) && *appid != "0")
+            .map(str::to_string)
+    }
+
+    pub fn exclusive_fullscreen() -> bool {
+        false
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::Process;
+    use std::path::PathBuf;
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::{CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS};
+    use windows::Win32::System::Threading::{OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION};
+    use windows::Win32::UI::Shell::{SHQueryUserNotificationState, QUNS_RUNNING_D3D_FULL_SCREEN};
+
+    pub fn process(pid: u32) -> Option<Process> {
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0).ok()?;
+            let mut entry = PROCESSENTRY32W { dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32, ..Default::default() };
+            let mut found = None;
+            let mut more = Process32FirstW(snapshot, &mut entry).is_ok();
+            while more {
+                if entry.th32ProcessID == pid {
+                    let length = entry.szExeFile.iter().position(|c| *c == 0).unwrap_or(entry.szExeFile.len());
+                    found = Some((String::from_utf16_lossy(&entry.szExeFile[..length]), entry.th32ParentProcessID));
+                    break;
+                }
+                more = Process32NextW(snapshot, &mut entry).is_ok();
+            }
+            let _ = CloseHandle(snapshot);
+            let (name, parent) = found?;
+            Some(Process { name, parent, paths: image_path(pid).into_iter().collect() })
+        }
+    }
+
+    unsafe fn image_path(pid: u32) -> Option<PathBuf> {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut length = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut length);
+        let _ = CloseHandle(process);
+        result.ok()?;
+        Some(PathBuf::from(String::from_utf16_lossy(&buffer[..length as usize])))
+    }
+
+    // Steam doesn't leave anything readable in a Windows game's process, the path finds it
+    pub fn steam_appid(_pid: u32) -> Option<String> {
+        None
+    }
+
+    pub fn exclusive_fullscreen() -> bool {
+        unsafe { SHQueryUserNotificationState() }.is_ok_and(|state| state == QUNS_RUNNING_D3D_FULL_SCREEN)
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+mod platform {
+    use super::Process;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    // ps gives the parent and the executable path, macOS has neither in a file
+    pub fn process(pid: u32) -> Option<Process> {
+        let output = Command::new("ps").args(["-o", "ppid=,comm=", "-p", &pid.to_string()]).output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let (parent, command) = text.trim().split_once(char::is_whitespace)?;
+        let path = PathBuf::from(command.trim());
+        let name = path.file_name()?.to_string_lossy().into_owned();
+        Some(Process { name, parent: parent.trim().parse().ok()?, paths: vec![path] })
+    }
+
+    pub fn steam_appid(_pid: u32) -> Option<String> {
+        None
+    }
+
+    pub fn exclusive_fullscreen() -> bool {
+        false
+    }
+}
@@ -0,0 +1,134 @@
+// [[goals]]: daily budgets for a category's focus time, either a target to reach ("Coding 4h") or
+// a limit to stay under ("Social 30m"). The live display draws a bar per goal from today's stored
+// intervals, what is stored as the session goes on, and the open interval.
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::{Local, NaiveDate};
+
+use crate::categories::UNCATEGORIZED;
+use crate::config::{Config, GoalConfig};
+use crate::humanize;
+use crate::i18n::tr;
+use crate::report::{self, daily_chunks};
+use crate::storage::Interval;
+
+const BAR_WIDTH: usize = 20;
+
+struct Today {
+    day: NaiveDate,
+    // Focus time of stored intervals by category
+    secs: HashMap<String, f64>,
+}
+
+static GOALS: Mutex<Vec<GoalConfig>> = Mutex::new(Vec::new());
+static TODAY: Mutex<Option<Today>> = Mutex::new(None);
+
+pub struct Progress {
+    pub category: String,
+    pub secs: f64,
+    pub goal_secs: f64,
+    // A limit to stay under rather than a target to reach
+    pub limit: bool,
+}
+
+impl Progress {
+    pub fn over_limit(&self) -> bool {
+        self.limit && self.secs > self.goal_secs
+    }
+
+    pub fn reached(&self) -> bool {
+        !self.limit && self.secs >= self.goal_secs
+    }
+}
+
+pub fn check(config: &Config) -> Result<(), String> {
+    for goal in &config.goals {
+        match (goal.target_mins, goal.limit_mins) {
+            (Some(mins), None) | (None, Some(mins)) if mins > 0 => {}
+            (Some(_), Some(_)) => return Err(format!("the goal for '{}' has both target_mins and limit_mins, make it two goals", goal.category)),
+            _ => return Err(format!("the goal for '{}' needs target_mins or limit_mins above 0", goal.category)),
+        }
+    }
+    Ok(())
+}
+
+pub fn configure(config: &Config) {
+    *GOALS.lock().unwrap() = config.goals.clone();
+}
+
+fn add(today: &mut Today, interval: &Interval) {
+    for (day, secs) in daily_chunks(interval) {
+        if day == today.day {
+            *today.secs.entry(interval.category_name().to_string()).or_default() += secs;
+        }
+    }
+}
+
+// Today's intervals already in the history, when tracking starts
+pub fn seed(intervals: &[Interval]) {
+    let mut today = Today { day: Local::now().date_naive(), secs: HashMap::new() };
+    for interval in intervals {
+        add(&mut today, interval);
+    }
+    *TODAY.lock().unwrap() = Some(today);
+}
+
+pub fn interval_stored(interval: &Interval) {
+    let day = Local::now().date_naive();
+    let mut today = TODAY.lock().unwrap();
+    let today = today.get_or_insert_with(|| Today { day, secs: HashMap::new() });
+    if today.day != day {
+        *today = Today { day, secs: HashMap::new() };
+    }
+    add(today, interval);
+}
+
+// Each goal with today's time, the open interval counting for the category of the focused window
+pub fn progress() -> Vec<Progress> {
+    let goals = GOALS.lock().unwrap().clone();
+    if goals.is_empty() {
+        return Vec::new();
+    }
+    let day = Local::now().date_naive();
+    let stored: HashMap<String, f64> = TODAY.lock().unwrap().as_ref()
+        .filter(|today| today.day == day)
+        .map(|today| today.secs.clone())
+        .unwrap_or_default();
+    let open = crate::hot::current().map(|window| {
+        let category = crate::wt_categorize(&window);
+        (if category.is_empty() { UNCATEGORIZED.to_string() } else { category }, crate::hot::open_today_secs())
+    });
+    goals.iter()
+        .map(|goal| {
+            let open_secs = open.as_ref().filter(|(category, _)| *category == goal.category).map_or(0.0, |(_, secs)| *secs);
+            Progress {
+                category: goal.category.clone(),
+                secs: stored.get(&goal.category).copied().unwrap_or(0.0) + open_secs,
+                goal_secs: goal.target_mins.or(goal.limit_mins).unwrap_or(0) as f64 * 60.0,
+                limit: goal.limit_mins.is_some(),
+            }
+        })
+        .collect()
+}
+
+// [████████░░░░] filled by the share of the goal, green once a target is reached, red over a limit
+pub fn bar(progress: &Progress) -> String {
+    let share = if progress.goal_secs > 0.0 { (progress.secs / progress.goal_secs).min(1.0) } else { 1.0 };
+    let filled = (share * BAR_WIDTH as f64).round() as usize;
+    let bar = format!("[{}{}]", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+    if progress.over_limit() {
+        report::highlight(&bar, report::RED)
+    } else if progress.reached() {
+        report::highlight(&bar, report::GREEN)
+    } else {
+        bar
+    }
+}
+
+// The bar with how much of the goal is used up, for the live display
+pub fn line(progress: &Progress, label: &str) -> String {
+    let key = if progress.limit { "status-goal-limit" } else { "status-goal-target" };
+    let text = tr(key, &[("time", &humanize::duration(progress.secs)), ("goal", &humanize::duration(progress.goal_secs))]);
+    format!("{} {} {}", bar(progress), label, text)
+}
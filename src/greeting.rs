@@ -0,0 +1,106 @@
+// [greeting]: back at the computer after a long gap (the tracker started, the screen unlocked or
+// the machine woke up), a notification sums up the previous session from the stored history,
+// like "Yesterday: 6.2h, top app: code".
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local, Utc};
+
+use crate::config::Config;
+use crate::humanize;
+use crate::i18n::{self, tr};
+use crate::notify;
+use crate::storage::{self, Interval};
+
+// Sessions going back further than this are cut off
+const LOOKBACK: Duration = Duration::from_secs(24 * 3600);
+// Less than this isn't worth a summary
+const MIN_SESSION_SECS: f64 = 60.0;
+
+#[derive(Default)]
+pub struct GreetingWatch {
+    last_check: Option<SystemTime>,
+    // Since the user came back, when this tracker saw it
+    session_start: Option<SystemTime>,
+    away_since: Option<SystemTime>,
+}
+
+impl GreetingWatch {
+    pub fn check(&mut self, config: &Config, idle_for: Option<Duration>) {
+        let now = SystemTime::now();
+        let Some(greeting) = &config.greeting else {
+            self.last_check = Some(now);
+            return;
+        };
+        let gap = Duration::from_secs(greeting.gap_mins.max(1) * 60);
+
+        match self.last_check {
+            // The tracker wasn't running since the last stored interval
+            None => self.away_since = last_recorded(now).filter(|end| elapsed(*end, now) >= gap),
+            // Nothing runs while the machine sleeps
+            Some(last) if elapsed(last, now) >= gap => {
+                self.away_since.get_or_insert(last);
+            }
+            Some(_) => {}
+        }
+        self.last_check = Some(now);
+        if let Some(idle) = idle_for.filter(|idle| *idle >= gap) {
+            self.away_since.get_or_insert(now - idle);
+            return;
+        }
+
+        if let Some(away_since) = self.away_since.take() {
+            let from = self.session_start.unwrap_or(away_since - LOOKBACK);
+            if let Some(body) = summary(from, away_since, gap, now) {
+                if let Err(e) = notify::send(&tr("greeting-title", &[]), &body) {
+                    eprintln!("Failed to show the session summary: {}", e);
+                }
+            }
+            self.session_start = Some(now);
+        }
+    }
+}
+
+fn elapsed(since: SystemTime, now: SystemTime) -> Duration {
+    now.duration_since(since).unwrap_or_default()
+}
+
+fn last_recorded(now: SystemTime) -> Option<SystemTime> {
+    let intervals = storage::load_intervals((now - LOOKBACK).into(), now.into()).ok()?;
+    intervals.iter().map(|interval| interval.end).max().map(SystemTime::from)
+}
+
+// The run of stored intervals ending at `to` without a gap this long, e.g. "Yesterday: 6.2h, top app: code"
+fn summary(from: SystemTime, to: SystemTime, gap: Duration, now: SystemTime) -> Option<String> {
+    let mut intervals = storage::load_intervals(from.into(), to.into()).ok()?;
+    intervals.sort_by_key(|interval| interval.start);
+    let session = last_session(&intervals, chrono::Duration::from_std(gap).ok()?);
+
+    let mut apps: HashMap<&str, f64> = HashMap::new();
+    for interval in session {
+        *apps.entry(interval.app_name()).or_insert(0.0) += interval.duration_secs();
+    }
+    let total: f64 = apps.values().sum();
+    if total < MIN_SESSION_SECS {
+        return None;
+    }
+    let (top_app, _) = apps.into_iter().max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let end: DateTime<Local> = session.last()?.end.with_timezone(&Local);
+    let days_ago = (DateTime::<Local>::from(now).date_naive() - end.date_naive()).num_days();
+    let day = match days_ago {
+        0 => tr("day-today", &[]),
+        1 => tr("day-yesterday", &[]),
+        _ => i18n::day(end.date_naive()),
+    };
+    Some(tr("greeting-body", &[("day", &day), ("hours", &humanize::hours(total)), ("app", &top_app)]))
+}
+
+fn last_session(intervals: &[Interval], gap: chrono::Duration) -> &[Interval] {
+    let mut start = intervals.len();
+    let mut session_start: Option<DateTime<Utc>> = None;
+    while start > 0 && session_start.is_none_or(|next| next - intervals[start - 1].end < gap) {
+        start -= 1;
+        session_start = Some(session_start.map_or(intervals[start].start, |next| next.min(intervals[start].start)));
+    }
+    &intervals[start..]
+}
@@ -0,0 +1,179 @@
+// The tracker watching itself, so it doesn't silently stop recording: when no window has been
+// sampled for [health] stale_secs while the user is at the keyboard, say after the X server
+// restarted, the sampler is started afresh (each query opens its own display or handle, so that
+// picks the platform back up once it is there again) and the problem shows in `status` and on
+// GET /healthz (see api.rs) until samples come back. Samples that couldn't tell what was focused
+// are tracked as their own buckets (see measured in lib.rs) and counted here, so gaps in measuring
+// show rather than vanish.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::HealthConfig;
+use crate::sampler;
+use crate::storage;
+
+static STARTED_MS: AtomicU64 = AtomicU64::new(0);
+// The last sample that found a window, 0 before the first
+static LAST_SAMPLE_MS: AtomicU64 = AtomicU64::new(0);
+static SAMPLES: AtomicU64 = AtomicU64::new(0);
+static MISSED: AtomicU64 = AtomicU64::new(0);
+static RESTARTS: AtomicU64 = AtomicU64::new(0);
+static LAST_RESTART_MS: AtomicU64 = AtomicU64::new(0);
+static STALE_SECS: AtomicU64 = AtomicU64::new(60);
+static FAILED: AtomicU64 = AtomicU64::new(0);
+static NOT_RESPONDING: AtomicU64 = AtomicU64::new(0);
+static UNTITLED: AtomicU64 = AtomicU64::new(0);
+static DENIED: AtomicU64 = AtomicU64::new(0);
+// What check found wrong with sampling the last time it ran
+static PROBLEM: Mutex<Option<String>> = Mutex::new(None);
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as u64)
+}
+
+fn time(ms: u64) -> Option<DateTime<Utc>> {
+    (ms > 0).then(|| DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_millis(ms)))
+}
+
+pub fn reset() {
+    STARTED_MS.store(now_ms(), Ordering::Relaxed);
+    LAST_SAMPLE_MS.store(0, Ordering::Relaxed);
+    SAMPLES.store(0, Ordering::Relaxed);
+    MISSED.store(0, Ordering::Relaxed);
+    RESTARTS.store(0, Ordering::Relaxed);
+    LAST_RESTART_MS.store(0, Ordering::Relaxed);
+    for counter in [&FAILED, &NOT_RESPONDING, &UNTITLED, &DENIED] {
+        counter.store(0, Ordering::Relaxed);
+    }
+    *PROBLEM.lock().unwrap() = None;
+}
+
+pub fn configure(config: &HealthConfig) {
+    STALE_SECS.store(config.stale_secs.max(1), Ordering::Relaxed);
+}
+
+// Whether a sample found the focused window
+pub fn sampled(found: bool) {
+    if found {
+        SAMPLES.fetch_add(1, Ordering::Relaxed);
+        LAST_SAMPLE_MS.store(now_ms(), Ordering::Relaxed);
+    } else {
+        MISSED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub enum Unmeasured {
+    // The platform call failed
+    Failed,
+    // It didn't return within the sampler's timeout
+    NotResponding,
+    // The focused window has no title
+    Untitled,
+    // The tracker may not read it
+    Denied,
+}
+
+pub fn unmeasured(kind: Unmeasured) {
+    let counter = match kind {
+        Unmeasured::Failed => &FAILED,
+        Unmeasured::NotResponding => &NOT_RESPONDING,
+        Unmeasured::Untitled => &UNTITLED,
+        Unmeasured::Denied => &DENIED,
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+// Samples of each bucket since tracking started
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Diagnostics {
+    pub failed: u64,
+    pub not_responding: u64,
+    pub untitled: u64,
+    pub permission_denied: u64,
+}
+
+impl Diagnostics {
+    pub fn total(&self) -> u64 {
+        self.failed + self.not_responding + self.untitled + self.permission_denied
+    }
+}
+
+pub fn diagnostics() -> Diagnostics {
+    Diagnostics {
+        failed: FAILED.load(Ordering::Relaxed),
+        not_responding: NOT_RESPONDING.load(Ordering::Relaxed),
+        untitled: UNTITLED.load(Ordering::Relaxed),
+        permission_denied: DENIED.load(Ordering::Relaxed),
+    }
+}
+
+// How long ago a window was last sampled, or tracking started if none has been yet
+fn sample_age() -> Duration {
+    let last = match LAST_SAMPLE_MS.load(Ordering::Relaxed) {
+        0 => STARTED_MS.load(Ordering::Relaxed),
+        ms => ms,
+    };
+    Duration::from_millis(now_ms().saturating_sub(last))
+}
+
+// About once a second from the tracking loop, not while paused since nothing is sampled then
+pub fn check(idle: Option<Duration>) {
+    let stale_after = Duration::from_secs(STALE_SECS.load(Ordering::Relaxed));
+    let age = sample_age();
+    // The lock screen and a user away leave nothing to sample, that's not a fault
+    let broken = age >= stale_after && idle.is_none_or(|idle| idle < age);
+    let mut problem = PROBLEM.lock().unwrap();
+    if !broken {
+        if problem.take().is_some() {
+            eprintln!("Sampling windows works again");
+        }
+        return;
+    }
+    if problem.is_none() {
+        eprintln!("No window sampled for {}s while in use, restarting the sampler", age.as_secs());
+    }
+    *problem = Some(format!("no window sampled for {}s while in use", age.as_secs()));
+    // Once per stale period, a platform that stays away isn't hammered
+    let now = now_ms();
+    if now.saturating_sub(LAST_RESTART_MS.load(Ordering::Relaxed)) >= stale_after.as_millis() as u64 {
+        LAST_RESTART_MS.store(now, Ordering::Relaxed);
+        RESTARTS.fetch_add(1, Ordering::Relaxed);
+        sampler::restart();
+    }
+}
+
+pub fn sampling_problem() -> Option<String> {
+    PROBLEM.lock().unwrap().clone()
+}
+
+pub fn last_sample() -> Option<DateTime<Utc>> {
+    time(LAST_SAMPLE_MS.load(Ordering::Relaxed))
+}
+
+pub fn sampler_restarts() -> u64 {
+    RESTARTS.load(Ordering::Relaxed)
+}
+
+// What GET /healthz answers with
+pub fn report() -> serde_json::Value {
+    let sampling = sampling_problem();
+    let storage = storage::last_error();
+    json!({
+        "ok": sampling.is_none() && storage.is_none(),
+        "started": time(STARTED_MS.load(Ordering::Relaxed)).map(|time| time.to_rfc3339()),
+        "last_sample": last_sample().map(|time| time.to_rfc3339()),
+        "last_sample_age_secs": sample_age().as_secs_f64(),
+        "samples": SAMPLES.load(Ordering::Relaxed),
+        "missed_samples": MISSED.load(Ordering::Relaxed),
+        "sampler_restarts": sampler_restarts(),
+        "diagnostics": diagnostics(),
+        "paused": crate::wt_is_paused(),
+        "sampling_error": sampling,
+        "storage_error": storage,
+    })
+}
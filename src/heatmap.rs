@@ -0,0 +1,200 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use serde_json::json;
+
+use crate::categories::CategoryStyles;
+use crate::cli::Args;
+use crate::config;
+use crate::export;
+use crate::humanize;
+use crate::report::{daily_chunks, escape_html};
+use crate::storage::{self, Interval};
+
+const CELL: u32 = 11;
+const GAP: u32 = 2;
+const COLORS: [&str; 5] = ["#ebedf0", "#9be9a8", "#40c463", "#30a14e", "#216e39"];
+// The same levels for a category with its own color
+const OPACITY: [f64; 5] = [0.0, 0.3, 0.55, 0.8, 1.0];
+
+// Daily focus seconds per category across a year, laid out as Monday-first week columns.
+pub struct Heatmap {
+    pub first_day: NaiveDate,
+    pub last_day: NaiveDate,
+    pub categories: BTreeMap<String, HashMap<NaiveDate, f64>>,
+    pub styles: CategoryStyles,
+}
+
+impl Heatmap {
+    // Monday on or before the first day, so every column is a full week
+    fn grid_start(&self) -> NaiveDate {
+        self.first_day - Duration::days(i64::from(self.first_day.weekday().num_days_from_monday()))
+    }
+
+    fn weeks(&self) -> usize {
+        ((self.last_day - self.grid_start()).num_days() / 7 + 1) as usize
+    }
+
+    fn cell(&self, days: &HashMap<NaiveDate, f64>, week: usize, weekday: usize) -> Option<(NaiveDate, f64)> {
+        let day = self.grid_start() + Duration::days((week * 7 + weekday) as i64);
+        if day < self.first_day || day > self.last_day {
+            return None;
+        }
+        Some((day, days.get(&day).copied().unwrap_or(0.0)))
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let today = Local::now().date_naive();
+    let (first_day, last_day) = match args.value("--year") {
+        Some(year) => {
+            let year: i32 = year.parse().map_err(|_| format!("invalid value '{}' for --year", year))?;
+            let first = NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(|| format!("invalid year {}", year))?;
+            let last = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+            (first, last.min(today))
+        }
+        None => (today - Duration::days(364), today),
+    };
+    if first_day > last_day {
+        return Err("the requested year has not started yet".to_string());
+    }
+
+    let filter: Option<Vec<&str>> = args.value("--category").map(|names| names.split(',').map(str::trim).collect());
+    let heatmap = build(first_day, last_day, filter.as_deref())?;
+
+    let default_format = if args.json_output()? { "json" } else { "svg" };
+    let content = match args.value("--format").unwrap_or(default_format) {
+        "svg" => render_svg(&heatmap),
+        "json" => render_json(&heatmap),
+        other => return Err(format!("unknown heatmap format '{}', expected 'svg' or 'json'", other)),
+    };
+    export::write_output(args, &content)
+}
+
+pub fn build(first_day: NaiveDate, last_day: NaiveDate, filter: Option<&[&str]>) -> Result<Heatmap, String> {
+    // A year of intervals adds up to the daily totals, which are kept for the next time
+    let days = storage::load_days(first_day, last_day).map_err(|e| format!("failed to read history: {}", e))?;
+    let mut heatmap = from_intervals(&days.intervals, first_day, last_day, filter);
+    heatmap.styles = CategoryStyles::from_config(&config::load()?);
+    Ok(heatmap)
+}
+
+// Intervals outside the days are ignored
+pub fn from_intervals(intervals: &[Interval], first_day: NaiveDate, last_day: NaiveDate, filter: Option<&[&str]>) -> Heatmap {
+    let mut categories: BTreeMap<String, HashMap<NaiveDate, f64>> = BTreeMap::new();
+    for interval in intervals {
+        let category = interval.category_name();
+        if filter.is_some_and(|names| !names.iter().any(|name| name.eq_ignore_ascii_case(category))) {
+            continue;
+        }
+
+        let days = categories.entry(category.to_string()).or_default();
+        for (day, secs) in daily_chunks(interval) {
+            if day >= first_day && day <= last_day {
+                *days.entry(day).or_insert(0.0) += secs;
+            }
+        }
+    }
+
+    Heatmap { first_day, last_day, categories, styles: CategoryStyles::default() }
+}
+
+pub fn render_json(heatmap: &Heatmap) -> String {
+    let categories: serde_json::Map<String, serde_json::Value> = heatmap.categories.iter()
+        .map(|(name, days)| {
+            let weeks: Vec<Vec<Option<f64>>> = (0..heatmap.weeks())
+                .map(|week| (0..7).map(|weekday| heatmap.cell(days, week, weekday).map(|(_, secs)| secs)).collect())
+                .collect();
+            (name.clone(), json!(weeks))
+        })
+        .collect();
+    let styles: serde_json::Map<String, serde_json::Value> = heatmap.categories.keys()
+        .filter_map(|name| heatmap.styles.get(name).map(|style| (name.clone(), json!({ "color": style.color, "icon": style.icon }))))
+        .collect();
+
+    let document = json!({
+        "first_day": heatmap.first_day.to_string(),
+        "last_day": heatmap.last_day.to_string(),
+        "grid_start": heatmap.grid_start().to_string(),
+        "rows": ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+        "unit": "seconds",
+        "categories": categories,
+        "styles": styles,
+    });
+    format!("{}\n", serde_json::to_string(&document).unwrap())
+}
+
+pub fn render_svg(heatmap: &Heatmap) -> String {
+    const LEFT: u32 = 30;
+    const HEADER: u32 = 34;
+    let block_height = HEADER + 7 * (CELL + GAP) + 12;
+    let width = LEFT + heatmap.weeks() as u32 * (CELL + GAP) + 10;
+    let height = block_height * heatmap.categories.len().max(1) as u32;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"10\">",
+        width, height
+    ).unwrap();
+
+    if heatmap.categories.is_empty() {
+        writeln!(svg, "<text x=\"{}\" y=\"20\" fill=\"#6b7280\">No focus time recorded</text>", LEFT).unwrap();
+    }
+
+    for (index, (name, days)) in heatmap.categories.iter().enumerate() {
+        let top = index as u32 * block_height;
+        let total: f64 = days.values().sum();
+        let max = days.values().copied().fold(0.0, f64::max);
+
+        writeln!(
+            svg,
+            "<text x=\"0\" y=\"{}\" font-size=\"13\" font-weight=\"bold\" fill=\"#1f2328\">{} &#183; {}</text>",
+            top + 14,
+            escape_html(&heatmap.styles.label(name)),
+            humanize::duration(total)
+        ).unwrap();
+
+        for (weekday, label) in [(0, "Mon"), (2, "Wed"), (4, "Fri")] {
+            writeln!(
+                svg,
+                "<text x=\"0\" y=\"{}\" fill=\"#6b7280\">{}</text>",
+                top + HEADER + weekday * (CELL + GAP) + CELL - 2,
+                label
+            ).unwrap();
+        }
+
+        for week in 0..heatmap.weeks() {
+            let x = LEFT + week as u32 * (CELL + GAP);
+            let week_start = heatmap.grid_start() + Duration::days(week as i64 * 7);
+            if week_start.day() <= 7 {
+                writeln!(svg, "<text x=\"{}\" y=\"{}\" fill=\"#6b7280\">{}</text>", x, top + HEADER - 4, week_start.format("%b")).unwrap();
+            }
+
+            for weekday in 0..7 {
+                let Some((day, secs)) = heatmap.cell(days, week, weekday) else { continue };
+                let level = if secs <= 0.0 || max <= 0.0 { 0 } else { ((secs / max * 4.0).ceil() as usize).clamp(1, 4) };
+                let (fill, opacity) = match heatmap.styles.color(name) {
+                    Some(color) if level > 0 => (color, format!(" fill-opacity=\"{}\"", OPACITY[level])),
+                    _ => (COLORS[level], String::new()),
+                };
+                writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"2\" fill=\"{}\"{}><title>{}: {}</title></rect>",
+                    x,
+                    top + HEADER + weekday as u32 * (CELL + GAP),
+                    CELL,
+                    CELL,
+                    fill,
+                    opacity,
+                    day.format("%a %Y-%m-%d"),
+                    humanize::duration(secs)
+                ).unwrap();
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
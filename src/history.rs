@@ -0,0 +1,126 @@
+// `export --format csv` or `--format jsonl`: the stored intervals themselves, a row or a JSON line
+// each, written while they are read so a history of any size exports in little memory. Values are
+// as stored, empty ones stay empty, and the CSV imports back with
+// --map "start=start,end=end,app=app,title=title,category=category".
+//
+// With --since-last only what was stored since the last export with --since-last to the same
+// --output (or stdout) in the same format: its mark in export_marks.json moves on once everything
+// is written, so a failed run is simply repeated. After a rewrite (purge, recategorize, verify
+// --repair, restore) the next one is all of it again.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{self, PathBuf};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::cli::Args;
+use crate::export::{self, csv_field};
+use crate::filter::Filter;
+use crate::report::last_days;
+use crate::storage::{self, Interval, Mark};
+
+const MARKS_FILE: &str = "export_marks.json";
+const CSV_HEADER: &str = "start,end,secs,app,title,category,class,window,event,call\n";
+
+fn csv_row(interval: &Interval) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{},{},{}\n",
+        interval.start.to_rfc3339_opts(SecondsFormat::Millis, true),
+        interval.end.to_rfc3339_opts(SecondsFormat::Millis, true),
+        interval.duration_secs(),
+        csv_field(&interval.app),
+        csv_field(&interval.title),
+        csv_field(&interval.category),
+        csv_field(&interval.class),
+        csv_field(&interval.window_id),
+        interval.event.as_str(),
+        interval.call,
+    )
+}
+
+fn marks_path() -> PathBuf {
+    storage::data_dir().join(MARKS_FILE)
+}
+
+// Destination to mark
+fn read_marks() -> BTreeMap<String, Mark> {
+    fs::read(marks_path()).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok()).unwrap_or_default()
+}
+
+fn write_marks(marks: &BTreeMap<String, Mark>) -> io::Result<()> {
+    let path = marks_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, serde_json::to_vec_pretty(marks)?)?;
+    fs::rename(temporary, path)
+}
+
+// The same file however it is named on the command line
+fn destination(args: &Args, format: &str) -> String {
+    let output = match args.value("--output") {
+        Some(output) => path::absolute(output).map(|path| path.display().to_string()).unwrap_or_else(|_| output.to_string()),
+        None => "stdout".to_string(),
+    };
+    format!("{}:{}", format, output)
+}
+
+pub fn run(args: &Args, format: &str) -> Result<(), String> {
+    // All of it unless --days is given
+    let (from, to) = match args.value("--days") {
+        Some(_) => {
+            let days: u32 = args.parsed("--days", 1)?;
+            if days == 0 {
+                return Err("--days must be at least 1".to_string());
+            }
+            let (from, to) = last_days(days);
+            (from.with_timezone(&Utc), to.with_timezone(&Utc))
+        }
+        None => (DateTime::<Utc>::MIN_UTC, Utc::now()),
+    };
+    let filter = Filter::from_args(args)?;
+    let csv = format == "csv";
+    let since_last = args.flag("--since-last");
+    let destination = destination(args, format);
+    let mut marks = if since_last { read_marks() } else { BTreeMap::new() };
+
+    // Set once everything up to it is written
+    let mut reached = None;
+    export::stream_output(args, |out| {
+        if csv {
+            out.write_all(CSV_HEADER.as_bytes())?;
+        }
+        let mut write = |chunk: &[Interval]| {
+            for interval in chunk {
+                if csv {
+                    out.write_all(csv_row(interval).as_bytes())?;
+                } else {
+                    out.write_all(storage::encode(interval)?.as_bytes())?;
+                    out.write_all(b"\n")?;
+                }
+            }
+            Ok(())
+        };
+        if !since_last {
+            return storage::scan_matching(from, to, &filter, write);
+        }
+        let mark = match storage::scan_since(marks.get(&destination), from, to, &filter, &mut write)? {
+            Some(mark) => mark,
+            None => {
+                eprintln!("The history was rewritten since the last export to {}, exporting all of it again", destination);
+                storage::scan_since(None, from, to, &filter, &mut write)?
+                    .ok_or_else(|| io::Error::other("the history was replaced while exporting"))?
+            }
+        };
+        reached = Some(mark);
+        Ok(())
+    })?;
+
+    if let Some(mark) = reached {
+        marks.insert(destination, mark);
+        write_marks(&marks).map_err(|e| format!("failed to save where the export got to in {}: {}", marks_path().display(), e))?;
+    }
+    Ok(())
+}
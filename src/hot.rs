@@ -0,0 +1,126 @@
+// What status bars and the D-Bus, C and Node APIs poll several times a second, kept out of the
+// sampler's locks so readers never wait on a sample or a history write: numbers are atomics, the
+// current window an Arc swapped in on focus changes behind a lock only ever held to copy it.
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+
+use crate::report;
+use crate::storage::{EventKind, Interval};
+use crate::ActiveWindow;
+
+// Today's total packs the day in the high bits and the milliseconds (at most 2^40) below them
+const MS_BITS: u32 = 40;
+
+static CURRENT: Mutex<Option<Arc<ActiveWindow>>> = Mutex::new(None);
+// Bumped whenever the current window or its title changes, so pollers can tell without comparing strings
+static CURRENT_ID: AtomicU64 = AtomicU64::new(0);
+// When the open interval started, in milliseconds since the Unix epoch, 0 without one
+static FOCUS_START_MS: AtomicU64 = AtomicU64::new(0);
+static SWITCHES: AtomicU64 = AtomicU64::new(0);
+static TITLE_CHANGES: AtomicU64 = AtomicU64::new(0);
+static WINDOW_COUNT: AtomicUsize = AtomicUsize::new(0);
+// Focus time of today's stored intervals, see pack
+static TODAY: AtomicU64 = AtomicU64::new(0);
+
+pub fn reset() {
+    *CURRENT.lock().unwrap() = None;
+    FOCUS_START_MS.store(0, Ordering::Relaxed);
+    SWITCHES.store(0, Ordering::Relaxed);
+    TITLE_CHANGES.store(0, Ordering::Relaxed);
+    WINDOW_COUNT.store(0, Ordering::Relaxed);
+}
+
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as u64)
+}
+
+// A new open interval in `window`, or none
+pub fn set_current(window: Option<&ActiveWindow>, start: SystemTime) {
+    *CURRENT.lock().unwrap() = window.cloned().map(Arc::new);
+    CURRENT_ID.fetch_add(1, Ordering::Relaxed);
+    FOCUS_START_MS.store(if window.is_some() { millis(start) } else { 0 }, Ordering::Relaxed);
+}
+
+// The same window carries on in a new interval, after a call started or ended
+pub fn restart_focus(start: SystemTime) {
+    FOCUS_START_MS.store(millis(start), Ordering::Relaxed);
+}
+
+pub fn current() -> Option<Arc<ActiveWindow>> {
+    CURRENT.lock().unwrap().clone()
+}
+
+pub fn current_id() -> u64 {
+    CURRENT_ID.load(Ordering::Relaxed)
+}
+
+fn focus_start() -> Option<SystemTime> {
+    match FOCUS_START_MS.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(UNIX_EPOCH + Duration::from_millis(ms)),
+    }
+}
+
+pub fn focus_duration() -> Duration {
+    focus_start().and_then(|start| start.elapsed().ok()).unwrap_or(Duration::ZERO)
+}
+
+pub fn count_event(event: EventKind) {
+    match event {
+        EventKind::FocusChanged => SWITCHES.fetch_add(1, Ordering::Relaxed),
+        EventKind::TitleChanged => TITLE_CHANGES.fetch_add(1, Ordering::Relaxed),
+        EventKind::CallChanged => 0,
+    };
+}
+
+// Window switches and title changes
+pub fn event_counts() -> (u64, u64) {
+    (SWITCHES.load(Ordering::Relaxed), TITLE_CHANGES.load(Ordering::Relaxed))
+}
+
+pub fn set_window_count(count: usize) {
+    WINDOW_COUNT.store(count, Ordering::Relaxed);
+}
+
+pub fn window_count() -> usize {
+    WINDOW_COUNT.load(Ordering::Relaxed)
+}
+
+fn pack(day: NaiveDate, ms: u64) -> u64 {
+    (day.num_days_from_ce() as u64) << MS_BITS | ms.min((1 << MS_BITS) - 1)
+}
+
+// The stored milliseconds when they are for `day`, 0 after midnight
+fn unpack(packed: u64, day: NaiveDate) -> u64 {
+    if packed >> MS_BITS == day.num_days_from_ce() as u64 { packed & ((1 << MS_BITS) - 1) } else { 0 }
+}
+
+// Today's focus time already in the history, read once when tracking starts
+pub fn seed_today(secs: f64) {
+    TODAY.store(pack(Local::now().date_naive(), (secs * 1000.0) as u64), Ordering::Relaxed);
+}
+
+pub fn interval_stored(interval: &Interval) {
+    let today = Local::now().date_naive();
+    let Some((_, secs)) = report::daily_chunks(interval).into_iter().find(|(day, _)| *day == today) else { return };
+    let added = (secs * 1000.0) as u64;
+    let _ = TODAY.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |packed| Some(pack(today, unpack(packed, today) + added)));
+}
+
+// Stored focus time today plus the open interval's share of it
+pub fn today_secs() -> f64 {
+    let stored = unpack(TODAY.load(Ordering::Relaxed), Local::now().date_naive()) as f64 / 1000.0;
+    stored + open_today_secs()
+}
+
+// The open interval's focus time since midnight
+pub fn open_today_secs() -> f64 {
+    let now = Local::now();
+    focus_start().map_or(0.0, |start| {
+        let start = DateTime::<Local>::from(start).max(report::local_midnight(now.date_naive()));
+        (now - start).num_milliseconds().max(0) as f64 / 1000.0
+    })
+}
@@ -0,0 +1,640 @@
+// `hub serve`: a small self-hosted server the trackers of a household or team push their history
+// to, kept apart by who pushed it and from which machine, for reports over all of them. Each member
+// has a token (`hub token --user <name>`), a tracker with [hub] in its config.toml pushes what it
+// stored since its last push every push_secs:
+//   POST /hub/intervals?device=<name>   JSON lines as `export --format jsonl` writes them
+//   GET /hub/report?from=&to=&group_by=&rollup=&filter=
+// both with `Authorization: Bearer <token>`; the report by user and device needs an admin's token
+// (`hub token --admin`), other members only get the team report. The hub keeps a JSON lines file per user and device
+// under hub/ in its data directory, every interval with field:user and field:device set, so the
+// report groups and filters by them as by anything else (default: field:user,field:device). A
+// push that failed is repeated from the same place and what the hub already has is skipped, so
+// nothing is lost or counted twice while it is unreachable. After the history was rewritten
+// (purge, recategorize, verify --repair) the tracker pushes all of it with ?replace=1 and the hub
+// replaces what it had of that device, so removed and changed intervals don't stay behind. It
+// speaks plain HTTP, put it behind a TLS proxy to push over the internet.
+//
+// Team mode is opt-in on both ends: with team = true under [hub] the tracker strips every interval
+// to its category and times before it is pushed, and a member added with `hub token --team` may
+// only push such intervals, anything naming an app or title is refused. `hub report --team` (and
+// ?team=1) totals the categories over the team members without saying whose time it is, leaving
+// out categories fewer than team_min_people of them spent time in. Their time is never in the
+// report by user and device, an admin's included.
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::api::{decoded, query_range, query_value};
+use crate::cli::Args;
+use crate::config::{self, HubConfig, HubMember, HubServerConfig};
+use crate::filter::Filter;
+use crate::ipc;
+use crate::overlap::DEVICE;
+use crate::report::{clip, last_days};
+use crate::review;
+use crate::rollup::{self, Pivot};
+use crate::storage::{self, EventKind, Interval, Mark};
+use crate::table::{Format, Table};
+
+// The attribute naming whose time an interval on the hub is
+pub const USER: &str = "user";
+const HUB_DIR: &str = "hub";
+const MARK_FILE: &str = "hub_mark.json";
+const DEFAULT_GROUP_BY: &str = "field:user,field:device";
+const MAX_HEADER_BYTES: usize = 8192;
+const MAX_PUSH_BYTES: usize = 64 * 1024 * 1024;
+
+// One push is stored at a time, so two can't both miss what the other is adding
+static STORING: Mutex<()> = Mutex::new(());
+
+// User and device names are directory and file names on the hub
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 64 && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn host_name() -> String {
+    let name = std::env::var("COMPUTERNAME").ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| Command::new("hostname").output().ok().map(|output| String::from_utf8_lossy(&output.stdout).into_owned()))
+        .unwrap_or_default();
+    let name: String = name.trim().chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' }).collect();
+    if name.is_empty() { "device".to_string() } else { name }
+}
+
+fn device(config: &HubConfig) -> Result<String, String> {
+    let device = config.device.clone().unwrap_or_else(host_name);
+    if !valid_name(&device) {
+        return Err(format!("invalid [hub] device '{}', use letters, digits, - _ and .", device));
+    }
+    Ok(device)
+}
+
+fn hub_dir() -> PathBuf {
+    storage::base_data_dir().join(HUB_DIR)
+}
+
+// What leaves a team member's machine: the category and when, nothing naming what was worked on
+fn anonymized(interval: &Interval) -> Interval {
+    Interval {
+        title: String::new(),
+        app: String::new(),
+        category: interval.category_name().to_string(),
+        window_id: String::new(),
+        class: String::new(),
+        attributes: BTreeMap::new(),
+        event: EventKind::default(),
+        call: false,
+        ..interval.clone()
+    }
+}
+
+fn is_anonymous(interval: &Interval) -> bool {
+    interval.title.is_empty() && interval.app.is_empty() && interval.window_id.is_empty() && interval.class.is_empty()
+        && interval.attributes.is_empty() && !interval.call
+}
+
+// Every interval on the hub overlapping [from, to) that the filter picks, unclipped
+fn load_all(from: DateTime<Utc>, to: DateTime<Utc>, filter: &Filter) -> Result<Vec<Interval>, String> {
+    let mut files = Vec::new();
+    if let Ok(users) = fs::read_dir(hub_dir()) {
+        for user in users.filter_map(Result::ok).filter(|entry| entry.path().is_dir()) {
+            let Ok(devices) = fs::read_dir(user.path()) else { continue };
+            files.extend(devices.filter_map(Result::ok).map(|entry| entry.path()).filter(|path| path.extension().is_some_and(|extension| extension == "jsonl")));
+        }
+    }
+    files.sort();
+    let mut intervals = Vec::new();
+    for path in files {
+        let stored = storage::load_interval_file(&path, from, to).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        intervals.extend(stored.into_iter().filter(|interval| filter.matches(interval)));
+    }
+    intervals.sort_by_key(|interval| interval.start);
+    Ok(intervals)
+}
+
+// What a member pushed, without what the hub already has of that device; with `replace` instead
+// of what it has, the start of a device's whole history
+fn store(member: &HubMember, device: &str, body: &str, replace: bool) -> Result<Value, String> {
+    let user = &member.user;
+    let mut intervals = body.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line, text)| storage::decode(text).map_err(|e| format!("line {}: {}", line + 1, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if member.team && !intervals.iter().all(is_anonymous) {
+        return Err(format!("{} is a team member, this hub only takes categories and times from them; set team = true under [hub] on their machine", user));
+    }
+    for interval in &mut intervals {
+        interval.attributes.insert(USER.to_string(), user.to_string());
+        // Imported with --device on the member's machine, it stays where it was tracked
+        interval.attributes.entry(DEVICE.to_string()).or_insert_with(|| device.to_string());
+    }
+
+    let _storing = STORING.lock().unwrap();
+    let path = hub_dir().join(user).join(format!("{}.jsonl", device));
+    let mut known = HashSet::new();
+    if let (false, Some(from), Some(to)) = (replace, intervals.iter().map(|i| i.start).min(), intervals.iter().map(|i| i.end).max()) {
+        let stored = storage::load_interval_file(&path, from, to).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        known.extend(stored.into_iter().map(|i| (i.start, i.end, i.app, i.title)));
+    }
+    let total = intervals.len();
+    intervals.retain(|i| known.insert((i.start, i.end, i.app.clone(), i.title.clone())));
+    if replace {
+        // Written next to it and renamed over it, a report never sees the device half replaced
+        let temporary = path.with_extension("jsonl.tmp");
+        let _ = fs::remove_file(&temporary);
+        storage::append_interval_file(&temporary, &intervals)
+            .and_then(|()| fs::rename(&temporary, &path))
+            .map_err(|e| format!("failed to store the push: {}", e))?;
+    } else {
+        storage::append_interval_file(&path, &intervals).map_err(|e| format!("failed to store the push: {}", e))?;
+    }
+    Ok(json!({ "ok": true, "stored": intervals.len(), "skipped": total - intervals.len() }))
+}
+
+// The intervals that aren't a team member's, the only ones a report by user and device covers
+fn personal(intervals: Vec<Interval>, server: &HubServerConfig) -> Vec<Interval> {
+    let team: HashSet<&str> = server.members.iter().filter(|member| member.team).map(|member| member.user.as_str()).collect();
+    intervals.into_iter().filter(|interval| interval.attributes.get(USER).is_none_or(|user| !team.contains(user.as_str()))).collect()
+}
+
+// Totals by category over the team members, the number of them with time in it and the average per
+// person, without saying who; also how many categories were left out for too few people
+fn team_table(intervals: &[Interval], server: &HubServerConfig) -> (Table, usize) {
+    let team: HashSet<&str> = server.members.iter().filter(|member| member.team).map(|member| member.user.as_str()).collect();
+    let mut categories: BTreeMap<&str, (HashSet<&str>, f64)> = BTreeMap::new();
+    for interval in intervals {
+        let Some(user) = interval.attributes.get(USER).map(String::as_str).filter(|user| team.contains(user)) else { continue };
+        let (people, secs) = categories.entry(interval.category_name()).or_default();
+        people.insert(user);
+        *secs += interval.duration_secs();
+    }
+    let total = categories.len();
+    let mut rows: Vec<(&str, usize, f64)> = categories.into_iter()
+        .filter(|(_, (people, _))| people.len() >= server.team_min_people.max(1))
+        .map(|(category, (people, secs))| (category, people.len(), secs))
+        .collect();
+    rows.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.cmp(b.0)));
+
+    let mut table = Table::new(&["category", "people", "sum_secs", "per_person_secs"]);
+    let left_out = total - rows.len();
+    for (category, people, secs) in rows {
+        table.push(vec![category.into(), people.into(), rollup::rounded(secs), rollup::rounded(secs / people as f64)]);
+    }
+    (table, left_out)
+}
+
+fn server_config() -> Result<HubServerConfig, String> {
+    config::load()?.hub_server.filter(|server| !server.members.is_empty())
+        .ok_or_else(|| "the hub has no members to push to it, `hub token --user <name>` adds one".to_string())
+}
+
+// Whether the member may read what they asked for: the report by user and device needs an admin,
+// the team report is everyone's
+fn may_read(member: &HubMember, query: &str) -> bool {
+    query_value(query, "team") == "1" || (member.admin && !member.team)
+}
+
+// GET /hub/report, from..to as for /api/intervals
+fn report(query: &str) -> Result<Value, String> {
+    let (from, to) = query_range(query)?;
+    let server = server_config()?;
+    if query_value(query, "team") == "1" {
+        let intervals = load_all(from.with_timezone(&Utc), to.with_timezone(&Utc), &Filter::default())?;
+        let (table, left_out) = team_table(&clip(&intervals, from, to), &server);
+        return Ok(json!({
+            "ok": true,
+            "from": from.date_naive().to_string(),
+            "to": (to - chrono::Duration::days(1)).date_naive().to_string(),
+            "rows": table.objects(),
+            "left_out": left_out,
+        }));
+    }
+    let filter = Filter::parse(&decoded(query_value(query, "filter")))?;
+    let group_by = match decoded(query_value(query, "group_by")) {
+        group_by if group_by.is_empty() => DEFAULT_GROUP_BY.to_string(),
+        group_by => group_by,
+    };
+    let rollup = decoded(query_value(query, "rollup"));
+    let pivot = Pivot::parse(&group_by, (!rollup.is_empty()).then_some(rollup.as_str()))?;
+    let intervals = personal(load_all(from.with_timezone(&Utc), to.with_timezone(&Utc), &filter)?, &server);
+    Ok(json!({
+        "ok": true,
+        "from": from.date_naive().to_string(),
+        "to": (to - chrono::Duration::days(1)).date_naive().to_string(),
+        "rows": pivot.table(&clip(&intervals, from, to)).objects(),
+    }))
+}
+
+// The members as config.toml has them now, so `hub token` applies without a restart
+fn members(started_with: &[HubMember]) -> Vec<HubMember> {
+    config::load().ok().and_then(|config| config.hub_server).map(|server| server.members).unwrap_or_else(|| started_with.to_vec())
+}
+
+fn member(authorization: &str, members: &[HubMember]) -> Option<HubMember> {
+    let given = authorization.strip_prefix("Bearer ")?.trim();
+    // Every token is compared, so timing doesn't tell which one came close
+    members.iter()
+        .fold(None, |found, member| if ipc::tokens_match(given, &member.token) { Some(member) } else { found })
+        .filter(|member| valid_name(&member.user))
+        .cloned()
+}
+
+fn answer(connection: &TcpStream, started_with: &[HubMember]) -> io::Result<()> {
+    let mut reader = BufReader::new(connection);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let (mut authorization, mut length) = (String::new(), 0);
+    let mut total = request.len();
+    loop {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line)?;
+        total += read;
+        if read == 0 || line.trim_end().is_empty() || total > MAX_HEADER_BYTES {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "authorization" => authorization = value.trim().to_string(),
+                "content-length" => length = value.trim().parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+
+    let mut parts = request.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let endpoint = matches!((method, path), ("POST", "/hub/intervals") | ("GET", "/hub/report"));
+    let mut headers = String::new();
+    let (status, body) = match (method, path, member(&authorization, &members(started_with))) {
+        (_, _, None) if endpoint => {
+            headers.push_str("WWW-Authenticate: Bearer\r\n");
+            ("401 Unauthorized", json!({ "ok": false, "error": "wrong or missing bearer token" }))
+        }
+        ("POST", "/hub/intervals", Some(_)) if length > MAX_PUSH_BYTES => {
+            ("413 Payload Too Large", json!({ "ok": false, "error": format!("at most {} bytes at a time", MAX_PUSH_BYTES) }))
+        }
+        ("POST", "/hub/intervals", Some(member)) => {
+            let mut body = String::new();
+            (&mut reader).take(length as u64).read_to_string(&mut body)?;
+            let device = decoded(query_value(query, "device"));
+            if !valid_name(&device) {
+                ("400 Bad Request", json!({ "ok": false, "error": format!("invalid device '{}', use letters, digits, - _ and .", device) }))
+            } else {
+                match store(&member, &device, &body, query_value(query, "replace") == "1") {
+                    Ok(answer) => ("200 OK", answer),
+                    Err(e) => ("400 Bad Request", json!({ "ok": false, "error": e })),
+                }
+            }
+        }
+        ("GET", "/hub/report", Some(member)) if !may_read(&member, query) => {
+            ("403 Forbidden", json!({ "ok": false, "error": "the report by user and device needs an admin's token, ask for ?team=1" }))
+        }
+        ("GET", "/hub/report", Some(_)) => match report(query) {
+            Ok(answer) => ("200 OK", answer),
+            Err(e) => ("400 Bad Request", json!({ "ok": false, "error": e })),
+        },
+        _ => ("404 Not Found", json!({ "ok": false, "error": "no such endpoint" })),
+    };
+    let body = body.to_string();
+    let mut stream = connection;
+    write!(stream, "HTTP/1.1 {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, headers, body.len())?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()
+}
+
+fn serve(args: &Args) -> Result<(), String> {
+    let server = server_config()?;
+    let address = args.value("--address").unwrap_or(&server.address);
+    let listener = TcpListener::bind(address).map_err(|e| format!("failed to listen on {}: {}", address, e))?;
+    println!("Hub listening on {}, storing in {}", address, hub_dir().display());
+    for connection in listener.incoming().filter_map(Result::ok) {
+        let members = server.members.clone();
+        thread::spawn(move || {
+            // Members going away mid-push is nothing to report, they push it again
+            let _ = connection.set_read_timeout(Some(Duration::from_secs(30)));
+            let _ = answer(&connection, &members);
+        });
+    }
+    Ok(())
+}
+
+fn mark_path() -> PathBuf {
+    storage::data_dir().join(MARK_FILE)
+}
+
+fn write_mark(mark: &Mark) -> io::Result<()> {
+    let temporary = mark_path().with_extension("json.tmp");
+    fs::write(&temporary, serde_json::to_vec(mark)?)?;
+    fs::rename(temporary, mark_path())
+}
+
+fn send(config: &HubConfig, device: &str, intervals: &[Interval], replace: bool) -> io::Result<()> {
+    let mut body = String::new();
+    for interval in intervals {
+        let interval = if config.team { &anonymized(interval) } else { interval };
+        body.push_str(&storage::encode(interval)?);
+        body.push('\n');
+    }
+    let mut url = format!("{}/hub/intervals?device={}", config.url.trim_end_matches('/'), device);
+    if replace {
+        url.push_str("&replace=1");
+    }
+    ureq::post(&url)
+        .timeout(Duration::from_secs(60))
+        .set("Authorization", &format!("Bearer {}", config.token))
+        .set("Content-Type", "application/x-ndjson")
+        .send_string(&body)
+        .map_err(|e| match e {
+            ureq::Error::Status(code, response) => {
+                let answer: Value = response.into_string().ok().and_then(|text| serde_json::from_str(&text).ok()).unwrap_or_default();
+                io::Error::other(format!("{} from the hub: {}", code, answer["error"].as_str().unwrap_or("no reason given")))
+            }
+            other => io::Error::other(other),
+        })?;
+    Ok(())
+}
+
+// Sends what was stored after the mark; without one all of it, replacing what the hub has of this
+// device. None when the history was replaced since the mark
+fn push_from(config: &HubConfig, device: &str, mark: Option<&Mark>) -> io::Result<Option<(Mark, usize)>> {
+    let (mut replace, mut sent) = (mark.is_none(), 0);
+    let reached = storage::scan_since(mark, DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC, &Filter::default(), |chunk| {
+        send(config, device, chunk, replace)?;
+        replace = false;
+        sent += chunk.len();
+        Ok(())
+    })?;
+    if reached.is_some() && replace {
+        // Nothing is left, what the hub has of this device goes all the same
+        send(config, device, &[], true)?;
+    }
+    Ok(reached.map(|reached| (reached, sent)))
+}
+
+// Pushes everything stored since the last push, all of it after a rewrite of the history; the
+// number of intervals sent
+pub fn push(config: &HubConfig) -> Result<usize, String> {
+    let device = device(config)?;
+    let mark: Option<Mark> = fs::read(mark_path()).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok());
+    let pushed = match push_from(config, &device, mark.as_ref()) {
+        Ok(Some(pushed)) => pushed,
+        Ok(None) => push_from(config, &device, None)
+            .and_then(|pushed| pushed.ok_or_else(|| io::Error::other("the history was replaced while pushing")))
+            .map_err(|e| format!("failed to push to {}: {}", config.url, e))?,
+        Err(e) => return Err(format!("failed to push to {}: {}", config.url, e)),
+    };
+    let (reached, sent) = pushed;
+    write_mark(&reached).map_err(|e| format!("failed to save where the push got to in {}: {}", mark_path().display(), e))?;
+    Ok(sent)
+}
+
+// Pushes from a background thread while tracking, reporting when the hub stops and starts answering
+pub fn start(config: &HubConfig) -> Result<(), String> {
+    device(config)?;
+    if config.push_secs == 0 {
+        return Err("[hub] push_secs must be at least 1".to_string());
+    }
+    let config = config.clone();
+    thread::spawn(move || {
+        let mut failing = false;
+        loop {
+            match push(&config) {
+                Ok(_) if failing => {
+                    eprintln!("Pushing to the hub at {} works again", config.url);
+                    failing = false;
+                }
+                Ok(_) => {}
+                Err(e) if !failing => {
+                    eprintln!("{}, retrying every {}s", e, config.push_secs);
+                    failing = true;
+                }
+                Err(_) => {}
+            }
+            thread::sleep(Duration::from_secs(config.push_secs));
+        }
+    });
+    Ok(())
+}
+
+// `hub token --user <name>`: a new member appended to config.toml, the token printed once
+fn add_member(args: &Args) -> Result<(), String> {
+    let user = args.value("--user").ok_or("usage: hub token --user <name>")?;
+    if !valid_name(user) {
+        return Err(format!("invalid user '{}', use letters, digits, - _ and .", user));
+    }
+    let (team, admin) = (args.flag("--team"), args.flag("--admin"));
+    if team && admin {
+        return Err("a team member can't be an admin, their time is only in the team report".to_string());
+    }
+    let token = ipc::random_hex(32)?;
+    let mut snippet = format!("[[hub_server.members]]\nuser = \"{}\"\ntoken = \"{}\"\n", user, token);
+    if team {
+        snippet.push_str("team = true\n");
+    }
+    if admin {
+        snippet.push_str("admin = true\n");
+    }
+    let path = review::append_to_config(&[snippet], |_| Ok(()))?;
+    eprintln!("Added {} to the hub members in {}, a running hub accepts the token right away.", user, path.display());
+    eprintln!("On their machines, in config.toml:\n[hub]\nurl = \"http://<this machine>:9470\"\ntoken = \"{}\"{}", token, if team { "\nteam = true" } else { "" });
+    println!("{}", token);
+    Ok(())
+}
+
+// `hub report --team`
+fn team_report(args: &Args) -> Result<(), String> {
+    let server = server_config()?;
+    let days: u32 = args.parsed("--days", 7)?;
+    if days == 0 {
+        return Err("--days must be at least 1".to_string());
+    }
+    let format = match Format::from_args(args)? {
+        Some(format) => format,
+        None if args.json_output()? => Format::Json,
+        None => Format::Table,
+    };
+    let (from, to) = last_days(days);
+    let intervals = load_all(from.with_timezone(&Utc), to.with_timezone(&Utc), &Filter::default())?;
+    let (table, left_out) = team_table(&clip(&intervals, from, to), &server);
+    table.print(format);
+    if left_out > 0 {
+        eprintln!("{} categories with time from fewer than {} team members left out", left_out, server.team_min_people);
+    }
+    Ok(())
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    match args.arg(1) {
+        Some("serve") => serve(args),
+        Some("push") => {
+            let config = config::load()?.hub.ok_or("nothing to push to, add [hub] with the url and token of a hub")?;
+            let sent = push(&config)?;
+            println!("Pushed {} intervals to {}", sent, config.url);
+            Ok(())
+        }
+        Some("report") if args.flag("--team") => team_report(args),
+        Some("report") => {
+            let server = server_config()?;
+            rollup::print(args, args.value("--group-by").unwrap_or(DEFAULT_GROUP_BY), |from, to, filter| {
+                load_all(from, to, filter).map(|intervals| personal(intervals, &server))
+            })
+        }
+        Some("token") => add_member(args),
+        _ => Err("usage: hub <serve|push|report|token>".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn member(user: &str, team: bool, admin: bool) -> HubMember {
+        HubMember { user: user.to_string(), token: format!("{}-token", user), team, admin }
+    }
+
+    fn server(members: Vec<HubMember>, team_min_people: usize) -> HubServerConfig {
+        HubServerConfig { address: "127.0.0.1:9470".to_string(), members, team_min_people }
+    }
+
+    fn pushed(user: &str, category: &str, mins: u64) -> Interval {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut interval = anonymized(&Interval::new("", "", start, start + Duration::from_secs(mins * 60)));
+        interval.category = category.to_string();
+        interval.attributes.insert(USER.to_string(), user.to_string());
+        interval
+    }
+
+    #[test]
+    fn the_team_report_leaves_out_categories_of_too_few_people() {
+        let server = server(vec![member("alice", true, false), member("bob", true, false), member("carol", false, false)], 2);
+        let intervals = vec![
+            pushed("alice", "Dev", 60),
+            pushed("bob", "Dev", 30),
+            pushed("alice", "Chat", 10),
+            pushed("carol", "Chat", 50),
+        ];
+        let (table, left_out) = team_table(&intervals, &server);
+        let rows = table.objects();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["category"], "Dev");
+        assert_eq!(rows[0]["people"], 2);
+        assert_eq!(rows[0]["sum_secs"], 5400);
+        assert!(rows.iter().all(|row| row.get(USER).is_none()));
+        // Chat: carol isn't a team member, and alice alone is too few
+        assert_eq!(left_out, 1);
+    }
+
+    #[test]
+    fn team_members_are_never_in_the_report_by_user() {
+        let server = server(vec![member("alice", true, false), member("carol", false, true)], 1);
+        let kept = personal(vec![pushed("alice", "Dev", 60), pushed("carol", "Dev", 60)], &server);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].attributes[USER], "carol");
+    }
+
+    #[test]
+    fn only_admins_read_the_report_by_user() {
+        assert!(!may_read(&member("bob", false, false), "group_by=field:user,title"));
+        assert!(!may_read(&member("alice", true, true), "group_by=field:user,title"));
+        assert!(may_read(&member("carol", false, true), "group_by=field:user,title"));
+        assert!(may_read(&member("bob", false, false), "team=1"));
+        assert!(may_read(&member("alice", true, false), "team=1"));
+    }
+
+    // A hub on a port of its own answering the members, and a tracker's [hub] pushing to it
+    fn hub_for(member: HubMember) -> HubConfig {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let config = HubConfig { url, token: member.token.clone(), device: Some("laptop".to_string()), push_secs: 60, team: member.team };
+        thread::spawn(move || {
+            for connection in listener.incoming().filter_map(Result::ok) {
+                let _ = answer(&connection, std::slice::from_ref(&member));
+            }
+        });
+        config
+    }
+
+    fn stored(title: &str, category: &str, from_secs: u64) -> Interval {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + from_secs);
+        let mut interval = Interval::new(title, "editor", start, start + Duration::from_secs(600));
+        interval.category = category.to_string();
+        interval
+    }
+
+    fn on_the_hub() -> Vec<Interval> {
+        load_all(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC, &Filter::default()).unwrap()
+    }
+
+    // Purging one interval and recategorizing another, as purge and rules do
+    fn purge_and_recategorize() {
+        storage::rewrite_intervals(|interval| match interval.title.as_str() {
+            "Secret" => None,
+            "Notes" => Some(Interval { category: "Writing".to_string(), ..interval.clone() }),
+            _ => Some(interval.clone()),
+        }).unwrap();
+    }
+
+    #[test]
+    fn a_purge_and_recategorize_reach_the_hub_with_the_next_push() {
+        let (_turn, _base) = storage::testing::data_dir();
+        let config = hub_for(member("carol", false, true));
+        storage::append_intervals(&[stored("Notes", "Dev", 0), stored("Secret", "Dev", 600), stored("Plan", "Dev", 1200)]).unwrap();
+        assert_eq!(push(&config), Ok(3));
+        assert_eq!(on_the_hub().len(), 3);
+
+        purge_and_recategorize();
+        assert_eq!(push(&config), Ok(2));
+        let hub: Vec<(String, String)> = on_the_hub().into_iter().map(|i| (i.title, i.category)).collect();
+        assert_eq!(hub, [("Notes".to_string(), "Writing".to_string()), ("Plan".to_string(), "Dev".to_string())]);
+
+        // Appended after that, pushed as before: only what is new, nothing twice
+        storage::append_interval(&stored("Review", "Dev", 1800)).unwrap();
+        assert_eq!(push(&config), Ok(1));
+        assert_eq!(on_the_hub().len(), 3);
+
+        // Down to nothing, the hub is emptied too
+        storage::rewrite_intervals(|_| None).unwrap();
+        assert_eq!(push(&config), Ok(0));
+        assert!(on_the_hub().is_empty());
+    }
+
+    #[test]
+    fn a_purge_and_recategorize_reach_the_team_report() {
+        let (_turn, _base) = storage::testing::data_dir();
+        let alice = member("alice", true, false);
+        let server = server(vec![alice.clone()], 1);
+        let config = hub_for(alice);
+        storage::append_intervals(&[stored("Notes", "Dev", 0), stored("Secret", "Dev", 600), stored("Plan", "Chat", 1200)]).unwrap();
+        push(&config).unwrap();
+        let (table, _) = team_table(&on_the_hub(), &server);
+        let rows: Vec<(String, f64)> = table.objects().iter().map(|row| (row["category"].as_str().unwrap().to_string(), row["sum_secs"].as_f64().unwrap())).collect();
+        assert_eq!(rows, [("Dev".to_string(), 1200.0), ("Chat".to_string(), 600.0)]);
+
+        purge_and_recategorize();
+        push(&config).unwrap();
+        let (table, _) = team_table(&on_the_hub(), &server);
+        let rows: Vec<(String, f64)> = table.objects().iter().map(|row| (row["category"].as_str().unwrap().to_string(), row["sum_secs"].as_f64().unwrap())).collect();
+        assert_eq!(rows, [("Chat".to_string(), 600.0), ("Writing".to_string(), 600.0)]);
+    }
+
+    #[test]
+    fn team_pushes_must_be_anonymous() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let named = Interval::new("Quarterly plan", "excel", start, start + Duration::from_secs(60));
+        assert!(!is_anonymous(&named));
+        assert!(is_anonymous(&anonymized(&named)));
+    }
+}
@@ -0,0 +1,156 @@
+// Durations and numbers as people read them, "1h 10m 35s" and "1.234,5" in German, configured with
+// [format]. Text and HTML output go through here, JSON and CSV keep plain numbers.
+use std::sync::Mutex;
+
+use crate::config::{self, Config, DurationStyle};
+use crate::i18n::tr;
+
+static CURRENT: Mutex<Option<Humanizer>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Humanizer {
+    style: DurationStyle,
+    decimals: usize,
+    decimal_separator: char,
+    group_separator: Option<char>,
+}
+
+impl Default for Humanizer {
+    fn default() -> Self {
+        Humanizer { style: DurationStyle::Hms, decimals: 1, decimal_separator: '.', group_separator: Some(',') }
+    }
+}
+
+impl Humanizer {
+    pub fn from_config(config: &Config) -> Self {
+        let format = config.format.as_ref();
+        let locale = format.and_then(|format| format.locale.clone())
+            .or_else(|| ["LC_ALL", "LC_NUMERIC", "LANG"].iter().find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty())));
+        let (decimal_separator, group_separator) = locale.as_deref().map_or(('.', Some(',')), separators);
+        Humanizer {
+            style: format.map(|format| format.duration).unwrap_or_default(),
+            decimals: format.map_or(1, |format| format.decimals),
+            decimal_separator,
+            group_separator,
+        }
+    }
+
+    pub fn duration(&self, secs: f64) -> String {
+        let secs = secs.max(0.0);
+        match self.style {
+            DurationStyle::Hms => {
+                let total = secs.round() as u64;
+                let (hours, minutes, secs) = (total / 3600, total / 60 % 60, total % 60);
+                match (hours, minutes) {
+                    (0, 0) => format!("{}s", secs),
+                    (0, _) => format!("{}m {}s", minutes, secs),
+                    _ => format!("{}h {}m {}s", hours, minutes, secs),
+                }
+            }
+            DurationStyle::Hm => {
+                let minutes = (secs / 60.0).round() as u64;
+                if minutes < 60 { format!("{}m", minutes) } else { format!("{}h {}m", minutes / 60, minutes % 60) }
+            }
+            DurationStyle::Hours => self.hours(secs),
+            DurationStyle::Seconds => format!("{} seconds", self.number(secs, self.decimals)),
+        }
+    }
+
+    // Units spelled out, "1 hour, 10 minutes, 35 seconds", for screen readers
+    pub fn spoken(&self, secs: f64) -> String {
+        let secs = secs.max(0.0);
+        let unit = |name: &str, value: u64| {
+            let key = format!("duration-{}-{}", name, if value == 1 { "one" } else { "other" });
+            tr(&key, &[("n", &value)])
+        };
+        let parts = match self.style {
+            DurationStyle::Hms => {
+                let total = secs.round() as u64;
+                [("hours", total / 3600), ("minutes", total / 60 % 60), ("seconds", total % 60)].to_vec()
+            }
+            DurationStyle::Hm => {
+                let minutes = (secs / 60.0).round() as u64;
+                [("hours", minutes / 60), ("minutes", minutes % 60)].to_vec()
+            }
+            DurationStyle::Hours => return tr("duration-hours-other", &[("n", &self.number(secs / 3600.0, self.decimals))]),
+            DurationStyle::Seconds => return tr("duration-seconds-other", &[("n", &self.number(secs, self.decimals))]),
+        };
+        // Zero units are left out unless everything is zero, which reads "0 seconds"
+        let (last, _) = parts[parts.len() - 1];
+        parts.iter()
+            .filter(|(_, value)| *value > 0)
+            .map(|(name, value)| unit(name, *value))
+            .reduce(|text, next| tr("duration-join", &[("text", &text), ("next", &next)]))
+            .unwrap_or_else(|| unit(last, 0))
+    }
+
+    pub fn hours(&self, secs: f64) -> String {
+        format!("{}h", self.number(secs / 3600.0, self.decimals))
+    }
+
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        let text = format!("{:.*}", decimals, value.abs());
+        let (whole, fraction) = text.split_once('.').map_or((text.as_str(), None), |(whole, fraction)| (whole, Some(fraction)));
+        let mut out = String::new();
+        if value < 0.0 && text.bytes().any(|byte| byte.is_ascii_digit() && byte != b'0') {
+            out.push('-');
+        }
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                out.extend(self.group_separator);
+            }
+            out.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            out.push(self.decimal_separator);
+            out.push_str(fraction);
+        }
+        out
+    }
+}
+
+// Decimal and thousands separators of a locale like "de_DE.UTF-8"
+fn separators(locale: &str) -> (char, Option<char>) {
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    let (language, region) = locale.split_once(['_', '-']).unwrap_or((locale, ""));
+    match (language.to_lowercase().as_str(), region.to_uppercase().as_str()) {
+        ("c" | "posix", _) => ('.', None),
+        (_, "CH" | "LI") => ('.', Some('\'')),
+        ("de" | "nl" | "es" | "it" | "pt" | "da" | "id" | "tr" | "el" | "ro" | "hr" | "sl" | "sr", _) => (',', Some('.')),
+        ("fr" | "ru" | "pl" | "sv" | "fi" | "nb" | "nn" | "no" | "cs" | "sk" | "uk" | "hu" | "bg" | "lt" | "lv" | "et", _) => {
+            (',', Some('\u{a0}'))
+        }
+        _ => ('.', Some(',')),
+    }
+}
+
+// Used from then on instead of the config loaded on first use, the tracker calls this on reload
+pub fn configure(config: &Config) {
+    *CURRENT.lock().unwrap() = Some(Humanizer::from_config(config));
+}
+
+// A config that fails to load is reported by whatever needs it, the defaults do here
+pub fn current() -> Humanizer {
+    *CURRENT.lock().unwrap().get_or_insert_with(|| config::load().map(|config| Humanizer::from_config(&config)).unwrap_or_default())
+}
+
+pub fn duration(secs: f64) -> String {
+    current().duration(secs)
+}
+
+// With a sign, for differences
+pub fn signed_duration(secs: f64) -> String {
+    format!("{}{}", if secs < 0.0 { '-' } else { '+' }, duration(secs.abs()))
+}
+
+pub fn spoken(secs: f64) -> String {
+    current().spoken(secs)
+}
+
+pub fn hours(secs: f64) -> String {
+    current().hours(secs)
+}
+
+pub fn number(value: f64, decimals: usize) -> String {
+    current().number(value, decimals)
+}
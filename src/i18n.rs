@@ -0,0 +1,90 @@
+// User-facing text in the language from --lang, else LC_ALL, LC_MESSAGES or LANG. The catalogs in
+// locales/ are built in, Fluent-style `key = text` lines with { $name } placeholders; keys missing
+// from a translation fall back to English. JSON output and error messages stay English.
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+
+use crate::cli::Args;
+
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+    ("ja", include_str!("../locales/ja.ftl")),
+];
+
+lazy_static::lazy_static! {
+    static ref MESSAGES: HashMap<&'static str, HashMap<&'static str, &'static str>> = CATALOGS.iter()
+        .map(|(language, catalog)| (*language, parse(catalog)))
+        .collect();
+}
+
+static LANGUAGE: Mutex<Option<&'static str>> = Mutex::new(None);
+
+fn parse(catalog: &'static str) -> HashMap<&'static str, &'static str> {
+    catalog.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, text)| (key.trim(), text.trim()))
+        .collect()
+}
+
+// The supported language of a locale like "de_DE.UTF-8" or "ja"
+fn supported(locale: &str) -> Option<&'static str> {
+    let language = locale.split(['_', '-', '.', '@']).next().unwrap_or_default().to_lowercase();
+    CATALOGS.iter().map(|(code, _)| *code).find(|code| *code == language)
+}
+
+pub fn set_language(locale: &str) -> Result<(), String> {
+    let language = supported(locale).ok_or_else(|| {
+        let codes: Vec<&str> = CATALOGS.iter().map(|(code, _)| *code).collect();
+        format!("unsupported language '{}', expected one of {}", locale, codes.join(", "))
+    })?;
+    *LANGUAGE.lock().unwrap() = Some(language);
+    Ok(())
+}
+
+// --lang, which has to be supported, else the environment, which falls back to English
+pub fn from_args(args: &Args) -> Result<(), String> {
+    match args.value("--lang") {
+        Some(lang) => set_language(lang),
+        None => Ok(()),
+    }
+}
+
+pub fn language() -> &'static str {
+    LANGUAGE.lock().unwrap().get_or_insert_with(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+            .and_then(|locale| supported(&locale))
+            .unwrap_or("en")
+    })
+}
+
+// The text for `key` with each { $name } replaced
+pub fn tr(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let text = MESSAGES[language()].get(key).or_else(|| MESSAGES["en"].get(key)).copied().unwrap_or(key);
+    let mut text = text.to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{ ${} }}", name), &value.to_string());
+    }
+    text
+}
+
+// "Mon 2024-03-04" with the weekday in the current language
+pub fn day(date: NaiveDate) -> String {
+    let weekday = match date.weekday() {
+        Weekday::Mon => "weekday-mon",
+        Weekday::Tue => "weekday-tue",
+        Weekday::Wed => "weekday-wed",
+        Weekday::Thu => "weekday-thu",
+        Weekday::Fri => "weekday-fri",
+        Weekday::Sat => "weekday-sat",
+        Weekday::Sun => "weekday-sun",
+    };
+    format!("{} {}", tr(weekday, &[]), date.format("%Y-%m-%d"))
+}
@@ -0,0 +1,344 @@
+// App icons for reports: the first time an app is focused its icon is looked up once, from the
+// .desktop entry and icon theme on Linux, the app bundle on macOS and the executable's resources on
+// Windows, and kept in icons/ in the data directory shared by all profiles. HTML reports put it
+// before the app's rows as a data URI, so the report stays one self-contained file.
+// app_icons = false turns it off.
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::config::Config;
+use crate::report::{base64, escape_html};
+use crate::{storage, ActiveWindow, NO_WINDOW};
+
+// By preference, what browsers show as an image
+const FORMATS: [(&str, &str); 3] = [("png", "image/png"), ("svg", "image/svg+xml"), ("ico", "image/x-icon")];
+// Larger files are left out of reports
+const MAX_BYTES: u64 = 256 * 1024;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+// Apps looked up by this process, found or not
+static LOOKED_UP: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+pub fn configure(config: &Config) {
+    ENABLED.store(config.app_icons, Ordering::Relaxed);
+}
+
+fn directory() -> PathBuf {
+    storage::base_data_dir().join("icons")
+}
+
+// App names are used as file names with anything but letters, digits, - and . replaced
+fn file_stem(app: &str) -> String {
+    app.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '.' { c } else { '_' }).collect()
+}
+
+fn cached(app: &str) -> Option<(PathBuf, &'static str)> {
+    let stem = file_stem(app);
+    FORMATS.iter()
+        .map(|(extension, mime)| (directory().join(format!("{}.{}", stem, extension)), *mime))
+        .find(|(path, _)| path.is_file())
+}
+
+// After focus moved to the window, looks its app's icon up in the background unless already done
+pub fn focus_changed(window: &ActiveWindow) {
+    if !ENABLED.load(Ordering::Relaxed) || crate::wt_is_ephemeral() || window.app.is_empty() || window.app == NO_WINDOW || window.pid == 0 {
+        return;
+    }
+    if !LOOKED_UP.lock().unwrap().get_or_insert_with(HashSet::new).insert(window.app.clone()) || cached(&window.app).is_some() {
+        return;
+    }
+    let window = window.clone();
+    thread::spawn(move || {
+        if let Err(e) = store(&window) {
+            eprintln!("Failed to store the icon of {}: {}", window.app, e);
+        }
+    });
+}
+
+fn store(window: &ActiveWindow) -> Result<(), String> {
+    let directory = directory();
+    fs::create_dir_all(&directory).map_err(|e| format!("failed to create {}: {}", directory.display(), e))?;
+    // Apps without one are looked up again by the next session
+    platform::save_icon(window, &directory.join(file_stem(&window.app)))
+}
+
+// The app's icon as a data: URI, None before it was stored or when it has none
+pub fn data_uri(app: &str) -> Option<String> {
+    let (path, mime) = cached(app)?;
+    if fs::metadata(&path).ok()?.len() > MAX_BYTES {
+        return None;
+    }
+    Some(format!("data:{};base64,{}", mime, base64(&fs::read(path).ok()?)))
+}
+
+// An <img> for the start of a report row, empty without an icon
+pub fn html(app: &str) -> String {
+    data_uri(app).map_or(String::new(), |uri| format!("<img class=\"icon\" src=\"{}\" alt=\"\">", escape_html(&uri)))
+}
+
+// Copies the first of the files that exists, keeping its extension
+#[cfg(unix)]
+fn copy_first(candidates: impl IntoIterator<Item = PathBuf>, stem: &Path) -> Result<(), String> {
+    for candidate in candidates {
+        let Some(extension) = candidate.extension().and_then(|extension| extension.to_str()) else { continue };
+        if !candidate.is_file() || !FORMATS.iter().any(|(known, _)| *known == extension) {
+            continue;
+        }
+        let target = stem.with_extension(extension);
+        fs::copy(&candidate, &target).map_err(|e| format!("failed to copy {} to {}: {}", candidate.display(), target.display(), e))?;
+        break;
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use crate::ActiveWindow;
+
+    // Theme sizes tried, a little larger than shown so it stays sharp on high-DPI screens
+    const SIZES: [&str; 6] = ["48x48", "64x64", "128x128", "32x32", "256x256", "scalable"];
+
+    // Where .desktop entries and icon themes live, by the XDG base directory spec, plus Flatpak's
+    fn data_dirs() -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        match std::env::var_os("XDG_DATA_HOME") {
+            Some(home) => dirs.push(PathBuf::from(home)),
+            None => dirs.extend(std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))),
+        }
+        let system = std::env::var("XDG_DATA_DIRS").unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+        dirs.extend(system.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from));
+        dirs.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+        dirs
+    }
+
+    // The value of a key in the [Desktop Entry] group
+    fn entry_value<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+        let mut in_entry = false;
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+            } else if in_entry {
+                if let Some((name, value)) = line.split_once('=') {
+                    if name.trim() == key {
+                        return Some(value.trim());
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // The Icon= of the entry for the window: by StartupWMClass, the file name, or what Exec runs
+    fn icon_name(window: &ActiveWindow, dirs: &[PathBuf]) -> Option<String> {
+        let executable = fs::read_link(format!("/proc/{}/exe", window.pid)).ok()
+            .and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()));
+        let names: Vec<String> = [Some(window.app.clone()), Some(window.class.clone()), executable]
+            .into_iter()
+            .flatten()
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_lowercase())
+            .collect();
+        for dir in dirs {
+            let Ok(entries) = fs::read_dir(dir.join("applications")) else { continue };
+            for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+                if path.extension().is_none_or(|extension| extension != "desktop") {
+                    continue;
+                }
+                let Ok(text) = fs::read_to_string(&path) else { continue };
+                let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_lowercase()).unwrap_or_default();
+                let class = entry_value(&text, "StartupWMClass").map(str::to_lowercase);
+                let command = entry_value(&text, "Exec")
+                    .and_then(|exec| exec.split_whitespace().next())
+                    .and_then(|program| Path::new(program.trim_matches('"')).file_name().map(|name| name.to_string_lossy().to_lowercase()));
+                let matches = names.iter().any(|name| {
+                    class.as_deref() == Some(name.as_str()) || stem == *name || stem.ends_with(&format!(".{}", name)) || command.as_deref() == Some(name.as_str())
+                });
+                if let (true, Some(icon)) = (matches, entry_value(&text, "Icon")) {
+                    return Some(icon.to_string());
+                }
+            }
+        }
+        None
+    }
+
+    pub fn save_icon(window: &ActiveWindow, stem: &Path) -> Result<(), String> {
+        let dirs = data_dirs();
+        let Some(icon) = icon_name(window, &dirs) else { return Ok(()) };
+        if icon.starts_with('/') {
+            return super::copy_first([PathBuf::from(&icon)], stem);
+        }
+        let mut candidates = Vec::new();
+        for dir in &dirs {
+            for size in SIZES {
+                for extension in ["png", "svg"] {
+                    candidates.push(dir.join(format!("icons/hicolor/{}/apps/{}.{}", size, icon, extension)));
+                }
+            }
+            for extension in ["png", "svg"] {
+                candidates.push(dir.join(format!("pixmaps/{}.{}", icon, extension)));
+            }
+        }
+        super::copy_first(candidates, stem)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use crate::ActiveWindow;
+
+    fn output(program: &str, args: &[&str]) -> Option<String> {
+        let output = Command::new(program).args(args).output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !text.is_empty()).then_some(text)
+    }
+
+    // The .app the process runs from, from its executable path
+    fn bundle(pid: u32) -> Option<PathBuf> {
+        let executable = PathBuf::from(output("ps", &["-o", "comm=", "-p", &pid.to_string()])?);
+        executable.ancestors().find(|path| path.extension().is_some_and(|extension| extension == "app")).map(Path::to_path_buf)
+    }
+
+    // The bundle's .icns converted to a PNG by sips, which ships with macOS
+    pub fn save_icon(window: &ActiveWindow, stem: &Path) -> Result<(), String> {
+        let Some(bundle) = bundle(window.pid) else { return Ok(()) };
+        let info = bundle.join("Contents/Info.plist");
+        let Some(name) = output("plutil", &["-extract", "CFBundleIconFile", "raw", "-o", "-", &info.display().to_string()]) else { return Ok(()) };
+        let mut icns = bundle.join("Contents/Resources").join(&name);
+        if icns.extension().is_none() {
+            icns.set_extension("icns");
+        }
+        if !icns.is_file() {
+            return Ok(());
+        }
+        let target = stem.with_extension("png");
+        output("sips", &["-s", "format", "png", "-Z", "64", &icns.display().to_string(), "--out", &target.display().to_string()])
+            .ok_or_else(|| format!("sips couldn't convert {}", icns.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::fs;
+    use std::path::Path;
+
+    use windows::core::{PCWSTR, PWSTR};
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::Graphics::Gdi::{
+        DeleteObject, GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+    };
+    use windows::Win32::System::Threading::{OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION};
+    use windows::Win32::UI::Shell::ExtractIconExW;
+    use windows::Win32::UI::WindowsAndMessaging::{DestroyIcon, GetIconInfo, HICON, ICONINFO};
+
+    use crate::ActiveWindow;
+
+    unsafe fn executable(pid: u32) -> Option<Vec<u16>> {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut length = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut length);
+        let _ = CloseHandle(process);
+        result.ok()?;
+        let mut path = buffer[..length as usize].to_vec();
+        path.push(0);
+        Some(path)
+    }
+
+    // The icon's 32-bit pixels, top row first, None for icons without color
+    unsafe fn pixels(icon: HICON) -> Option<(u32, u32, Vec<u8>)> {
+        let mut info = ICONINFO::default();
+        GetIconInfo(icon, &mut info).ok()?;
+        let mut bitmap = BITMAP::default();
+        let described = !info.hbmColor.is_invalid()
+            && GetObjectW(info.hbmColor, std::mem::size_of::<BITMAP>() as i32, Some(std::ptr::addr_of_mut!(bitmap).cast())) != 0;
+        let mut result = None;
+        if described && bitmap.bmWidth > 0 && bitmap.bmHeight > 0 {
+            let (width, height) = (bitmap.bmWidth as u32, bitmap.bmHeight as u32);
+            let mut bgra = vec![0u8; width as usize * height as usize * 4];
+            let mut header = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: width as i32,
+                    biHeight: -(height as i32),
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let screen = GetDC(None);
+            if GetDIBits(screen, info.hbmColor, 0, height, Some(bgra.as_mut_ptr().cast()), &mut header, DIB_RGB_COLORS) == height as i32 {
+                result = Some((width, height, bgra));
+            }
+            ReleaseDC(None, screen);
+        }
+        let _ = DeleteObject(info.hbmColor);
+        let _ = DeleteObject(info.hbmMask);
+        result
+    }
+
+    // One 32-bit image in an .ico, which browsers show with its transparency
+    fn ico(width: u32, height: u32, bgra: &[u8]) -> Vec<u8> {
+        let row_mask = (width as usize).div_ceil(32) * 4;
+        let image_size = 40 + bgra.len() + row_mask * height as usize;
+        let mut out = Vec::with_capacity(22 + image_size);
+        out.extend([0, 0, 1, 0, 1, 0]);
+        // 0 stands for 256
+        out.extend([width.min(256) as u8, height.min(256) as u8, 0, 0]);
+        out.extend(1u16.to_le_bytes());
+        out.extend(32u16.to_le_bytes());
+        out.extend((image_size as u32).to_le_bytes());
+        out.extend(22u32.to_le_bytes());
+        // The header counts the color rows and the mask rows, both stored bottom row first
+        out.extend(40u32.to_le_bytes());
+        out.extend((width as i32).to_le_bytes());
+        out.extend((height as i32 * 2).to_le_bytes());
+        out.extend(1u16.to_le_bytes());
+        out.extend(32u16.to_le_bytes());
+        out.extend([0u8; 24]);
+        for row in bgra.chunks(width as usize * 4).rev() {
+            out.extend(row);
+        }
+        // Transparency comes from the alpha channel, the mask is left clear
+        out.extend(vec![0u8; row_mask * height as usize]);
+        out
+    }
+
+    pub fn save_icon(window: &ActiveWindow, stem: &Path) -> Result<(), String> {
+        unsafe {
+            let Some(path) = executable(window.pid) else { return Ok(()) };
+            let mut icon = HICON::default();
+            if ExtractIconExW(PCWSTR(path.as_ptr()), 0, Some(&mut icon), None, 1) == 0 || icon.is_invalid() {
+                return Ok(());
+            }
+            let pixels = pixels(icon);
+            let _ = DestroyIcon(icon);
+            let Some((width, height, bgra)) = pixels else { return Ok(()) };
+            let target = stem.with_extension("ico");
+            fs::write(&target, ico(width, height, &bgra)).map_err(|e| format!("failed to write {}: {}", target.display(), e))
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use std::path::Path;
+
+    use crate::ActiveWindow;
+
+    pub fn save_icon(_window: &ActiveWindow, _stem: &Path) -> Result<(), String> {
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+// Windows that are never tracked, or tracked with the title hidden, from [[ignore]] in config.toml.
+// `review` writes these rules for titles picked from the history.
+use regex::Regex;
+
+use crate::config::Config;
+use crate::ActiveWindow;
+
+pub const REDACTED: &str = "[redacted]";
+
+struct Rule {
+    app: Option<String>,
+    title: Option<Regex>,
+    redact: bool,
+}
+
+impl Rule {
+    fn matches(&self, title: &str, app: &str) -> bool {
+        self.app.as_ref().is_none_or(|name| name.eq_ignore_ascii_case(app))
+            && self.title.as_ref().is_none_or(|pattern| pattern.is_match(title))
+    }
+}
+
+#[derive(Default)]
+pub struct WindowFilter {
+    rules: Vec<Rule>,
+}
+
+pub enum Decision {
+    Track,
+    Redact,
+    Skip,
+}
+
+impl WindowFilter {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in &config.ignore {
+            if rule.app.is_none() && rule.title.is_none() {
+                return Err("an [[ignore]] rule needs app, title or both".to_string());
+            }
+            let title = rule.title.as_deref()
+                .map(|pattern| Regex::new(pattern).map_err(|e| format!("invalid ignore pattern '{}': {}", pattern, e)))
+                .transpose()?;
+            rules.push(Rule { app: rule.app.clone(), title, redact: rule.redact });
+        }
+        Ok(WindowFilter { rules })
+    }
+
+    // The first matching rule decides
+    pub fn decide(&self, title: &str, app: &str) -> Decision {
+        match self.rules.iter().find(|rule| rule.matches(title, app)) {
+            Some(rule) if rule.redact => Decision::Redact,
+            Some(_) => Decision::Skip,
+            None => Decision::Track,
+        }
+    }
+
+    // The window as it should be recorded, None when it isn't recorded at all
+    pub fn apply(&self, mut window: ActiveWindow) -> Option<ActiveWindow> {
+        match self.decide(&window.title, &window.app) {
+            Decision::Track => Some(window),
+            Decision::Redact => {
+                window.title = REDACTED.to_string();
+                Some(window)
+            }
+            Decision::Skip => None,
+        }
+    }
+}
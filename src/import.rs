@@ -0,0 +1,338 @@
+// `import <file.csv> --map "start=Date+From,end=Date+To,app=Tool"`: backfills the history from
+// spreadsheets and other trackers' exports. The map names the interval fields after the column
+// headers to take them from, see USAGE. `--format jsonl` takes the JSON lines this tool exports
+// instead, kept as they were recorded. Overlaps with the history are left or resolved with
+// --overlap, see overlap.rs.
+use std::collections::HashSet;
+use std::fs;
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone, Utc};
+use serde_json::json;
+
+use crate::categories::Categorizer;
+use crate::cli::Args;
+use crate::config;
+use crate::humanize;
+use crate::overlap::{self, Resolution};
+use crate::storage::{self, Interval};
+use crate::templates::TitleParser;
+
+const FIELDS: &[&str] = &["start", "end", "duration", "hours", "app", "title", "category", "device"];
+// Tried in order when there is no --time-format, without an offset the time is local
+const TIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%d.%m.%Y %H:%M"];
+
+#[derive(Debug, Clone)]
+enum Source {
+    Column(usize),
+    Literal(String),
+}
+
+// Each field's value is its sources joined with spaces, so "Date+Time" reads "2024-03-01 09:30"
+struct Mapping {
+    fields: Vec<(&'static str, Vec<Source>)>,
+}
+
+impl Mapping {
+    fn parse(map: &str, header: Option<&[String]>) -> Result<Self, String> {
+        let mut fields = Vec::new();
+        for entry in split_outside_quotes(map, ',') {
+            let (field, sources) = entry.split_once('=')
+                .ok_or_else(|| format!("invalid --map entry '{}', expected field=column", entry))?;
+            let field = FIELDS.iter().copied().find(|known| known.eq_ignore_ascii_case(field.trim()))
+                .ok_or_else(|| format!("unknown field '{}' in --map, expected one of {}", field.trim(), FIELDS.join(", ")))?;
+            let sources = split_outside_quotes(sources, '+').iter()
+                .map(|source| Self::source(source, header))
+                .collect::<Result<Vec<_>, _>>()?;
+            fields.push((field, sources));
+        }
+        let has = |name: &str| fields.iter().any(|(field, _)| *field == name);
+        if !has("start") || !(has("end") || has("duration") || has("hours")) || !(has("app") || has("title")) {
+            return Err("--map needs start, end (or duration or hours) and app or title".to_string());
+        }
+        Ok(Mapping { fields })
+    }
+
+    // 'text' is taken as is, #3 is the third column, anything else a column header
+    fn source(source: &str, header: Option<&[String]>) -> Result<Source, String> {
+        let source = source.trim();
+        if let Some(text) = source.strip_prefix('\'').and_then(|rest| rest.strip_suffix('\'')) {
+            return Ok(Source::Literal(text.to_string()));
+        }
+        if let Some(number) = source.strip_prefix('#') {
+            return match number.parse::<usize>() {
+                Ok(number) if number > 0 => Ok(Source::Column(number - 1)),
+                _ => Err(format!("invalid column number '{}' in --map, columns count from #1", source)),
+            };
+        }
+        let header = header.ok_or_else(|| format!("column '{}' needs a header row, use #<n> with --no-header", source))?;
+        header.iter().position(|name| name.trim().eq_ignore_ascii_case(source))
+            .map(Source::Column)
+            .ok_or_else(|| format!("no column '{}', the header has {}", source, header.join(", ")))
+    }
+
+    fn value(&self, field: &str, record: &[String]) -> Option<String> {
+        let (_, sources) = self.fields.iter().find(|(name, _)| *name == field)?;
+        let parts: Vec<&str> = sources.iter()
+            .map(|source| match source {
+                Source::Column(index) => record.get(*index).map_or("", |value| value.trim()),
+                Source::Literal(text) => text.as_str(),
+            })
+            .filter(|part| !part.is_empty())
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    fn interval(&self, record: &[String], time_format: Option<&str>, categorizer: &Categorizer, parser: &TitleParser) -> Result<Interval, String> {
+        let time = |field: &str| self.value(field, record)
+            .map(|text| parse_time(&text, time_format).ok_or_else(|| format!("unreadable {} time '{}'", field, text)))
+            .transpose();
+        let start = time("start")?.ok_or("no start time")?;
+        let end = match (time("end")?, self.value("duration", record), self.value("hours", record)) {
+            (Some(end), _, _) => end,
+            (None, Some(duration), _) => start + parse_duration(&duration).ok_or_else(|| format!("unreadable duration '{}'", duration))?,
+            (None, None, Some(hours)) => {
+                let hours: f64 = hours.replace(',', ".").parse().map_err(|_| format!("unreadable hours '{}'", hours))?;
+                start + Duration::milliseconds((hours * 3_600_000.0) as i64)
+            }
+            (None, None, None) => return Err("no end time or duration".to_string()),
+        };
+        if end <= start {
+            return Err("ends before it starts".to_string());
+        }
+        let app = self.value("app", record).unwrap_or_default();
+        let title = self.value("title", record).unwrap_or_default();
+        let attributes = parser.parse(&title, &app);
+        let category = self.value("category", record)
+            .unwrap_or_else(|| categorizer.categorize(&title, &app, &attributes).to_string());
+        let mut interval = Interval { category, attributes, ..Interval::new(&title, &app, start.into(), end.into()) };
+        if let Some(device) = self.value("device", record) {
+            interval.attributes.insert(overlap::DEVICE.to_string(), device);
+        }
+        Ok(interval)
+    }
+}
+
+// Splits on `separator` except inside 'quotes'
+fn split_outside_quotes(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+    for c in text.chars() {
+        if c == '\'' {
+            quoted = !quoted;
+        }
+        if c == separator && !quoted {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+    parts.into_iter().filter(|part| !part.trim().is_empty()).collect()
+}
+
+fn parse_time(text: &str, format: Option<&str>) -> Option<DateTime<Utc>> {
+    let local = |naive: NaiveDateTime| Local.from_local_datetime(&naive).earliest().map(|time| time.with_timezone(&Utc));
+    if let Some(format) = format {
+        return DateTime::parse_from_str(text, format).ok().map(|time| time.with_timezone(&Utc))
+            .or_else(|| NaiveDateTime::parse_from_str(text, format).ok().and_then(local));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(seconds) = text.parse::<i64>() {
+        return DateTime::from_timestamp(seconds, 0);
+    }
+    TIME_FORMATS.iter().find_map(|format| NaiveDateTime::parse_from_str(text, format).ok()).and_then(local)
+}
+
+// Seconds, or h:mm and h:mm:ss
+fn parse_duration(text: &str) -> Option<Duration> {
+    let parts: Vec<i64> = text.split(':').map(|part| part.trim().parse().ok()).collect::<Option<_>>()?;
+    let secs = match parts[..] {
+        [secs] => secs,
+        [hours, minutes] => hours * 3600 + minutes * 60,
+        [hours, minutes, secs] => hours * 3600 + minutes * 60 + secs,
+        _ => return None,
+    };
+    Some(Duration::seconds(secs))
+}
+
+// RFC 4180: quoted fields may hold the delimiter, newlines and doubled quotes
+fn parse_csv(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+// The rows of a CSV file as intervals, as --map says
+fn read_csv(args: &Args, text: &str) -> Result<Vec<Interval>, String> {
+    let map = args.value("--map").ok_or("import needs --map, e.g. --map \"start=Start,end=End,app=Application\"")?;
+    let delimiter = match args.value("--delimiter") {
+        None => ',',
+        Some("tab" | "\\t") => '\t',
+        Some(text) if text.chars().count() == 1 => text.chars().next().unwrap(),
+        Some(text) => return Err(format!("invalid --delimiter '{}', expected a single character or 'tab'", text)),
+    };
+    let config = config::load()?;
+    let categorizer = Categorizer::from_config(&config)?;
+    let parser = TitleParser::from_config(&config)?;
+
+    let mut records = parse_csv(text, delimiter).into_iter();
+    let header = if args.flag("--no-header") { None } else { records.next() };
+    let mapping = Mapping::parse(map, header.as_deref())?;
+    // Spreadsheet row numbers, counting the header
+    let first_row = if header.is_some() { 2 } else { 1 };
+    let mut intervals = Vec::new();
+    for (row, record) in records.enumerate() {
+        if record.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+        let interval = mapping.interval(&record, args.value("--time-format"), &categorizer, &parser)
+            .map_err(|e| format!("row {}: {}", row + first_row, e))?;
+        intervals.push(interval);
+    }
+    Ok(intervals)
+}
+
+// The lines of `export --format jsonl`, or a history file of `export --everything`, from this
+// machine or another; older versions are upgraded, a newer one is refused
+fn read_jsonl(text: &str) -> Result<Vec<Interval>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line, text)| storage::decode(text).map_err(|e| format!("line {}: {}", line + 1, e)))
+        .collect()
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let path = args.arg(1).ok_or("usage: import <file.csv> --map \"start=<column>,end=<column>,app=<column>\" [--dry-run]")?;
+    let jsonl = match args.value("--format").unwrap_or("csv") {
+        "csv" => false,
+        "jsonl" => true,
+        other => return Err(format!("unknown import format '{}', expected 'csv' or 'jsonl'", other)),
+    };
+    let dry_run = args.flag("--dry-run");
+    let resolution = Resolution::parse(args.value("--overlap").unwrap_or("keep"), args.value("--prefer"))?;
+    let json_output = args.json_output()?;
+
+    let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let mut intervals = if jsonl { read_jsonl(&text)? } else { read_csv(args, &text)? };
+    if let Some(device) = args.value("--device") {
+        for interval in &mut intervals {
+            interval.attributes.entry(overlap::DEVICE.to_string()).or_insert_with(|| device.to_string());
+        }
+    }
+
+    // Importing the same file twice adds nothing the second time
+    let mut known = HashSet::new();
+    let mut stored = Vec::new();
+    if let (Some(from), Some(to)) = (intervals.iter().map(|i| i.start).min(), intervals.iter().map(|i| i.end).max()) {
+        // A day either side, so what overlaps the ends comes unclipped
+        stored = storage::load_intervals(from - Duration::days(1), to + Duration::days(1)).map_err(|e| format!("failed to read history: {}", e))?;
+        known.extend(stored.iter().map(|i| (i.start, i.end, i.app.clone(), i.title.clone())));
+    }
+    let total = intervals.len();
+    intervals.retain(|i| known.insert((i.start, i.end, i.app.clone(), i.title.clone())));
+    let skipped = total - intervals.len();
+
+    let overlap::Outcome { imported: intervals, replaced, conflicts, overlapping } = overlap::resolve(&stored, &intervals, &resolution);
+    if !dry_run {
+        if !replaced.is_empty() {
+            let index = overlap::index(&replaced);
+            storage::rewrite_intervals(|interval| {
+                let replacement = index.get(&(interval.start, interval.end))
+                    .and_then(|candidates| candidates.iter().find(|&&i| replaced[i].0 == *interval));
+                match replacement {
+                    Some(&i) => replaced[i].1.first().cloned(),
+                    None => Some(interval.clone()),
+                }
+            }).map_err(|e| format!("failed to trim stored intervals: {}", e))?;
+        }
+        // The rest of stored intervals split in several
+        let mut added: Vec<Interval> = intervals.iter().cloned()
+            .chain(replaced.iter().flat_map(|(_, pieces)| pieces.iter().skip(1).cloned()))
+            .collect();
+        added.sort_by_key(|interval| interval.start);
+        storage::append_intervals(&added).map_err(|e| format!("failed to save history: {}", e))?;
+    }
+
+    let secs = intervals.iter().map(Interval::duration_secs).fold(0.0, |total, secs| total + secs);
+    if json_output {
+        let conflicts: Vec<serde_json::Value> = conflicts.iter()
+            .map(|conflict| json!({
+                "start": conflict.interval.start.to_rfc3339(),
+                "end": conflict.interval.end.to_rfc3339(),
+                "app": conflict.interval.app,
+                "title": conflict.interval.title,
+                "device": overlap::device(&conflict.interval),
+                "stored": conflict.stored,
+                "lost_secs": conflict.lost_secs,
+                "pieces": conflict.pieces,
+            }))
+            .collect();
+        println!("{}", json!({
+            "imported": intervals.len(),
+            "skipped": skipped,
+            "hours": secs / 3600.0,
+            "dry_run": dry_run,
+            "overlap": resolution.name(),
+            "overlapping": overlapping,
+            "conflicts": conflicts,
+        }));
+        return Ok(());
+    }
+
+    let verb = if dry_run { "Would import" } else { "Imported" };
+    println!("{} {} intervals ({}), skipped {} already in the history", verb, intervals.len(), humanize::duration(secs), skipped);
+    if resolution == Resolution::Keep {
+        if overlapping > 0 {
+            println!("{} of them overlap other intervals, --overlap trim, prefer-device or split resolves that", overlapping);
+        }
+        return Ok(());
+    }
+    if !conflicts.is_empty() {
+        println!("Resolved overlaps of {} intervals by {}:", conflicts.len(), resolution.name());
+    }
+    for conflict in &conflicts {
+        let interval = &conflict.interval;
+        let left = match conflict.pieces {
+            0 => "dropped".to_string(),
+            1 => "shortened".to_string(),
+            pieces => format!("split in {}", pieces),
+        };
+        println!(
+            "  {} {} {} \"{}\" ({}, {}): {} by {}",
+            interval.start.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            if conflict.stored { "stored" } else { "imported" },
+            interval.app_name(),
+            interval.title,
+            overlap::device(interval),
+            humanize::duration(interval.duration_secs()),
+            left,
+            humanize::duration(conflict.lost_secs),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,206 @@
+// Focus intervals and switch counts as InfluxDB line protocol, for Influx/Telegraf stacks. While
+// tracking, [influx] appends them to a file (which Telegraf can tail) or posts them to the v2 write
+// API; `export --influx` writes the stored history the same way. A point per interval:
+//   focus,app=code,category=Coding,event=focus_changed title="main.rs",secs=42.5,call=false <start>
+// and every minute while tracking the running totals:
+//   focus_events switches=12i,title_changes=30i,evicted_windows=0i <now>
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+use crate::cli::Args;
+use crate::config::{self, InfluxConfig};
+use crate::export;
+use crate::filter::Filter;
+use crate::report::{clip, last_days, local_midnight};
+use crate::storage::{self, EventKind, Interval};
+
+const COUNTS_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn check(config: &InfluxConfig) -> Result<(), String> {
+    match (&config.url, &config.path) {
+        (None, None) => return Err("[influx] needs url or path".to_string()),
+        (Some(_), Some(_)) => return Err("[influx] takes url or path, not both".to_string()),
+        (Some(_), None) if config.bucket.is_empty() => return Err("[influx] with url needs the bucket to write to".to_string()),
+        _ => {}
+    }
+    if config.measurement.is_empty() {
+        return Err("the [influx] measurement can't be empty".to_string());
+    }
+    Ok(())
+}
+
+// Commas and spaces end a measurement, tags also end at =
+fn escape_key(text: &str, equals: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            ',' | ' ' | '\\' => escaped.push('\\'),
+            '=' if equals => escaped.push('\\'),
+            // A line break would end the point
+            '\n' | '\r' => {
+                escaped.push(' ');
+                continue;
+            }
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn string_field(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace(['\n', '\r'], " "))
+}
+
+fn nanos(time: DateTime<Utc>) -> i64 {
+    time.timestamp_nanos_opt().unwrap_or(0)
+}
+
+// Tags without a value are left out, line protocol doesn't allow them empty
+fn tags(measurement: &str, tags: &[(&str, &str)]) -> String {
+    let mut line = escape_key(measurement, false);
+    for (key, value) in tags.iter().filter(|(_, value)| !value.is_empty()) {
+        write!(line, ",{}={}", escape_key(key, true), escape_key(value, true)).unwrap();
+    }
+    line
+}
+
+pub fn interval_line(measurement: &str, interval: &Interval) -> String {
+    format!(
+        "{} title={},secs={},call={} {}\n",
+        tags(measurement, &[("app", interval.app_name()), ("category", interval.category_name()), ("event", interval.event.as_str())]),
+        string_field(&interval.title),
+        interval.duration_secs(),
+        interval.call,
+        nanos(interval.start),
+    )
+}
+
+fn counts_line(measurement: &str, fields: &[(&str, u64)], time: DateTime<Utc>) -> String {
+    let fields: Vec<String> = fields.iter().map(|(key, value)| format!("{}={}i", escape_key(key, true), value)).collect();
+    format!("{} {} {}\n", tags(&format!("{}_events", measurement), &[]), fields.join(","), nanos(time))
+}
+
+fn write(config: &InfluxConfig, lines: &str) -> Result<(), String> {
+    if let Some(path) = &config.path {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+        return file.write_all(lines.as_bytes()).map_err(|e| format!("failed to write {}: {}", path.display(), e));
+    }
+    post(config, lines)
+}
+
+#[cfg(feature = "influx")]
+fn post(config: &InfluxConfig, lines: &str) -> Result<(), String> {
+    let Some(url) = &config.url else { return Ok(()) };
+    let endpoint = format!("{}/api/v2/write", url.trim_end_matches('/'));
+    let mut request = ureq::post(&endpoint)
+        .timeout(Duration::from_secs(10))
+        .query("bucket", &config.bucket)
+        .query("precision", "ns")
+        .set("Content-Type", "text/plain; charset=utf-8");
+    if !config.org.is_empty() {
+        request = request.query("org", &config.org);
+    }
+    if let Ok(token) = std::env::var(&config.token_env) {
+        request = request.set("Authorization", &format!("Token {}", token));
+    }
+    request.send_string(lines).map(|_| ()).map_err(|e| format!("failed to write to {}: {}", endpoint, e))
+}
+
+#[cfg(not(feature = "influx"))]
+fn post(config: &InfluxConfig, _lines: &str) -> Result<(), String> {
+    Err(format!("writing to {} needs InfluxDB support, rebuild with --features influx", config.url.as_deref().unwrap_or("")))
+}
+
+// Writes from a background thread so a slow server never delays sampling
+pub fn start(config: &InfluxConfig) -> Result<(), String> {
+    check(config)?;
+    if let (Some(url), false) = (&config.url, cfg!(feature = "influx")) {
+        return Err(format!("writing to {} needs InfluxDB support, rebuild with --features influx, or use path", url));
+    }
+    if config.path.is_some() && crate::wt_is_ephemeral() {
+        return Err("[influx] path writes every interval to disk, it can't be combined with --ephemeral".to_string());
+    }
+    let (sender, receiver) = mpsc::channel::<Interval>();
+    crate::wt_add_interval_listener(Box::new(move |interval| {
+        let _ = sender.send(interval.clone());
+    }));
+
+    let config = config.clone();
+    thread::spawn(move || {
+        let mut last_counts = Instant::now();
+        loop {
+            let mut lines = String::new();
+            match receiver.recv_timeout(COUNTS_INTERVAL.saturating_sub(last_counts.elapsed())) {
+                Ok(interval) => {
+                    lines.push_str(&interval_line(&config.measurement, &interval));
+                    for interval in receiver.try_iter() {
+                        lines.push_str(&interval_line(&config.measurement, &interval));
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            if last_counts.elapsed() >= COUNTS_INTERVAL {
+                let counts = crate::wt_get_event_counts();
+                lines.push_str(&counts_line(&config.measurement, &[
+                    ("switches", counts.switches),
+                    ("title_changes", counts.title_changes),
+                    ("evicted_windows", crate::wt_get_evicted_windows()),
+                ], Utc::now()));
+                last_counts = Instant::now();
+            }
+            if !lines.is_empty() {
+                if let Err(e) = write(&config, &lines) {
+                    eprintln!("{}", e);
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+// `export --influx`: the stored intervals of the last days, and each day's switches as a point at
+// its midnight
+pub fn run_export(args: &Args) -> Result<(), String> {
+    let days: u32 = args.parsed("--days", 30)?;
+    if days == 0 {
+        return Err("--days must be at least 1".to_string());
+    }
+    let measurement = match args.value("--measurement") {
+        Some(measurement) => measurement.to_string(),
+        None => config::load()?.influx.map(|influx| influx.measurement).unwrap_or_else(|| "focus".to_string()),
+    };
+    let (from, to) = last_days(days);
+    let filter = Filter::from_args(args)?;
+
+    // Written a chunk at a time, only the counts per day are kept
+    export::stream_output(args, |out| {
+        let mut days: BTreeMap<NaiveDate, (u64, u64)> = BTreeMap::new();
+        storage::scan_matching(from.with_timezone(&Utc), to.with_timezone(&Utc), &filter, |chunk| {
+            for interval in &clip(chunk, from, to) {
+                out.write_all(interval_line(&measurement, interval).as_bytes())?;
+                let counts = days.entry(interval.start.with_timezone(&Local).date_naive()).or_default();
+                match interval.event {
+                    EventKind::FocusChanged => counts.0 += 1,
+                    EventKind::TitleChanged => counts.1 += 1,
+                    _ => {}
+                }
+            }
+            Ok(())
+        })?;
+        for (day, (switches, title_changes)) in days {
+            let midnight = local_midnight(day).with_timezone(&Utc);
+            out.write_all(counts_line(&measurement, &[("switches", switches), ("title_changes", title_changes)], midnight).as_bytes())?;
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,115 @@
+// `init`: first-run setup. It asks where to keep the history, whether to record window titles or
+// only apps, how long without input counts as idle and whether to track from login, then writes
+// config.toml and sets up starting at login as `autostart enable` does.
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::autostart;
+use crate::cli::Args;
+use crate::config::{self, Config};
+use crate::storage;
+
+// The answer to the question with the hint in brackets, empty for just enter
+fn prompt(question: &str, hint: &str) -> Result<String, String> {
+    eprint!("{} [{}] ", question, hint);
+    io::stderr().flush().map_err(|e| e.to_string())?;
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) => Err("setup cancelled, nothing was written".to_string()),
+        Ok(_) => Ok(line.trim().to_string()),
+        Err(e) => Err(format!("failed to read input: {}", e)),
+    }
+}
+
+// The default for an empty answer
+fn ask(question: &str, default: &str) -> Result<String, String> {
+    let answer = prompt(question, default)?;
+    Ok(if answer.is_empty() { default.to_string() } else { answer })
+}
+
+fn ask_yes(question: &str, default: bool) -> Result<bool, String> {
+    loop {
+        match prompt(question, if default { "Y/n" } else { "y/N" })?.to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => eprintln!("Answer y or n"),
+        }
+    }
+}
+
+fn toml_string(text: &str) -> String {
+    toml::Value::String(text.to_string()).to_string()
+}
+
+fn config_text(data_dir: Option<&Path>, titles: bool, idle_mins: u64) -> String {
+    let mut text = String::from("# Written by init, see --help for everything else that can go here\n");
+    text.push_str(&format!("idle_secs = {}\n", idle_mins * 60));
+    if let Some(dir) = data_dir {
+        text.push_str(&format!("data_dir = {}\n", toml_string(&dir.display().to_string())));
+    }
+    if !titles {
+        text.push_str("# Only which app is focused, window titles are never kept\nprivacy = \"apps\"\n");
+    }
+    text
+}
+
+pub fn run(_args: &Args) -> Result<(), String> {
+    if !io::stdin().is_terminal() {
+        return Err("init is interactive, run it in a terminal".to_string());
+    }
+    let path = config::base_config_path();
+    let kept = path.with_extension("toml.before-init");
+    if path.exists() && !ask_yes(&format!("{} already exists, replace it? The old one is kept as {}", path.display(), kept.display()), false)? {
+        println!("Nothing changed");
+        return Ok(());
+    }
+
+    let current_dir = storage::base_data_dir();
+    let data_dir = PathBuf::from(ask("Where should the history be kept?", &current_dir.display().to_string())?);
+    let titles = loop {
+        match ask("Record window titles, or only which app is focused? (titles/apps)", "titles")?.as_str() {
+            "titles" | "t" => break true,
+            "apps" | "a" => break false,
+            _ => eprintln!("Answer titles or apps"),
+        }
+    };
+    let idle_mins = loop {
+        match ask("Minutes without keyboard or mouse input before you count as away?", "5")?.parse::<u64>() {
+            Ok(minutes) if minutes > 0 => break minutes,
+            _ => eprintln!("Answer a whole number of minutes, at least 1"),
+        }
+    };
+    let autostart = ask_yes("Start tracking at login?", true)?;
+
+    let text = config_text((data_dir != storage::default_data_dir()).then_some(data_dir.as_path()), titles, idle_mins);
+    // Nothing is written that the tracker would refuse to load
+    toml::from_str::<Config>(&text).map_err(|e| format!("the answers would make an invalid config: {}", e))?;
+    fs::create_dir_all(&data_dir).map_err(|e| format!("failed to create {}: {}", data_dir.display(), e))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+    }
+    if path.exists() {
+        fs::rename(&path, &kept).map_err(|e| format!("failed to keep {} as {}: {}", path.display(), kept.display(), e))?;
+    }
+    fs::write(&path, text).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    println!("Wrote {}", path.display());
+    if data_dir != current_dir && fs::read_dir(&current_dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        println!("The history recorded so far stays in {}, move it over before tracking", current_dir.display());
+    }
+    if std::env::var_os("WT_DATA_DIR").is_some() && data_dir != current_dir {
+        println!("WT_DATA_DIR is set and takes precedence over data_dir");
+    }
+
+    if autostart {
+        let executable = std::env::current_exe().map_err(|e| format!("failed to locate the executable: {}", e))?;
+        match autostart::enable(&executable) {
+            Ok(installed) => println!("{}", installed),
+            Err(e) => println!("Starting at login couldn't be set up ({}), run `track` yourself instead", e),
+        }
+    } else {
+        println!("Run `track` to start tracking");
+    }
+    Ok(())
+}
@@ -0,0 +1,192 @@
+// `export --invoice`: one worklog line per day for a billable category over a month, with hours
+// and amounts, as CSV for spreadsheets and accounting tools or as HTML to print to PDF.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use chrono::{Datelike, Local, Months, NaiveDate, Utc};
+use serde_json::json;
+
+use crate::cli::Args;
+use crate::export::{self, csv_field};
+use crate::humanize;
+use crate::report::{daily_chunks, escape_html, local_midnight};
+use crate::storage::{self, Interval};
+
+// Titles listed in a line's description
+const DESCRIPTION_TITLES: usize = 3;
+
+pub struct Line {
+    pub date: NaiveDate,
+    pub hours: f64,
+    pub amount: f64,
+    pub description: String,
+}
+
+pub struct Invoice {
+    pub client: String,
+    pub month: NaiveDate,
+    pub rate: f64,
+    pub currency: String,
+    pub lines: Vec<Line>,
+}
+
+impl Invoice {
+    pub fn total_hours(&self) -> f64 {
+        self.lines.iter().map(|line| line.hours).fold(0.0, |total, hours| total + hours)
+    }
+
+    pub fn total_amount(&self) -> f64 {
+        self.lines.iter().map(|line| line.amount).fold(0.0, |total, amount| total + amount)
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let client = args.value("--client").ok_or("usage: export --invoice --client <category> --rate <per hour> [--month yyyy-mm]")?;
+    let rate = args.value("--rate").ok_or("export --invoice needs --rate <per hour>")?;
+    let rate: f64 = rate.parse().ok().filter(|rate: &f64| *rate >= 0.0).ok_or_else(|| format!("invalid value '{}' for --rate", rate))?;
+    let round_minutes = match args.value("--round") {
+        Some(minutes) => minutes.parse::<u32>().ok().filter(|minutes| *minutes > 0).ok_or_else(|| format!("invalid value '{}' for --round", minutes))?,
+        None => 1,
+    };
+    let month = match args.value("--month") {
+        Some(month) => NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+            .map_err(|_| format!("invalid value '{}' for --month, expected yyyy-mm", month))?,
+        None => Local::now().date_naive().with_day(1).unwrap(),
+    };
+
+    let next_month = month + Months::new(1);
+    let from = local_midnight(month).with_timezone(&Utc);
+    let to = local_midnight(next_month).with_timezone(&Utc);
+    let intervals = storage::load_intervals(from, to).map_err(|e| format!("failed to read history: {}", e))?;
+
+    let mut invoice = build(&intervals, client, month, rate, round_minutes);
+    invoice.currency = args.value("--currency").unwrap_or("").to_string();
+
+    let default_format = if args.json_output()? { "json" } else { "csv" };
+    let content = match args.value("--format").unwrap_or(default_format) {
+        "csv" => render_csv(&invoice),
+        "html" => render_html(&invoice),
+        "json" => render_json(&invoice),
+        other => return Err(format!("unknown invoice format '{}', expected 'csv', 'html' or 'json'", other)),
+    };
+    export::write_output(args, &content)
+}
+
+// Each day's hours are rounded up to `round_minutes`, the usual billing increment
+pub fn build(intervals: &[Interval], client: &str, month: NaiveDate, rate: f64, round_minutes: u32) -> Invoice {
+    let next_month = month + Months::new(1);
+    let mut days: BTreeMap<NaiveDate, HashMap<&str, f64>> = BTreeMap::new();
+    for interval in intervals.iter().filter(|interval| interval.category_name().eq_ignore_ascii_case(client)) {
+        for (day, secs) in daily_chunks(interval) {
+            if day >= month && day < next_month {
+                *days.entry(day).or_default().entry(&interval.title).or_insert(0.0) += secs;
+            }
+        }
+    }
+
+    let increment = f64::from(round_minutes) * 60.0;
+    let lines = days.into_iter()
+        .map(|(date, titles)| {
+            let secs: f64 = titles.values().sum();
+            // Hours as printed, so every amount is exactly hours times rate
+            let hours = ((secs / increment).ceil() * increment / 36.0).round() / 100.0;
+            let mut titles: Vec<(&str, f64)> = titles.into_iter().collect();
+            titles.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            let description = titles.iter().take(DESCRIPTION_TITLES).map(|(title, _)| *title).collect::<Vec<_>>().join("; ");
+            Line { date, hours, amount: (hours * rate * 100.0).round() / 100.0, description }
+        })
+        .collect();
+
+    Invoice { client: client.to_string(), month, rate, currency: String::new(), lines }
+}
+
+pub fn render_csv(invoice: &Invoice) -> String {
+    let mut out = String::from("date,client,hours,rate,amount,description\n");
+    for line in &invoice.lines {
+        writeln!(
+            out,
+            "{},{},{:.2},{:.2},{:.2},{}",
+            line.date,
+            csv_field(&invoice.client),
+            line.hours,
+            invoice.rate,
+            line.amount,
+            csv_field(&line.description)
+        ).unwrap();
+    }
+    writeln!(out, "total,{},{:.2},,{:.2},", csv_field(&invoice.client), invoice.total_hours(), invoice.total_amount()).unwrap();
+    out
+}
+
+pub fn render_json(invoice: &Invoice) -> String {
+    let document = json!({
+        "client": invoice.client,
+        "month": invoice.month.format("%Y-%m").to_string(),
+        "rate": invoice.rate,
+        "currency": invoice.currency,
+        "lines": invoice.lines.iter()
+            .map(|line| json!({ "date": line.date.to_string(), "hours": line.hours, "amount": line.amount, "description": line.description }))
+            .collect::<Vec<_>>(),
+        "total_hours": invoice.total_hours(),
+        "total_amount": invoice.total_amount(),
+    });
+    format!("{}\n", serde_json::to_string(&document).unwrap())
+}
+
+const STYLE: &str = "<style>
+@page { size: A4; margin: 20mm; }
+body { font-family: -apple-system, \"Segoe UI\", sans-serif; color: #1f2328; margin: 0; }
+main { max-width: 800px; margin: 2rem auto; padding: 0 1rem; }
+h1 { font-size: 1.5rem; margin-bottom: 0.25rem; }
+.meta { color: #6b7280; font-size: 0.9rem; }
+table { border-collapse: collapse; width: 100%; font-size: 0.9rem; margin-top: 1.5rem; }
+th, td { text-align: left; padding: 0.4rem 0.5rem; border-bottom: 1px solid #e5e7eb; vertical-align: top; }
+th { color: #6b7280; font-weight: 600; }
+.num { text-align: right; white-space: nowrap; font-variant-numeric: tabular-nums; }
+tr.total td { font-weight: 600; border-top: 2px solid #1f2328; border-bottom: none; }
+@media print { main { margin: 0; max-width: none; } }
+</style>
+";
+
+pub fn render_html(invoice: &Invoice) -> String {
+    let money = |amount: f64| {
+        let amount = humanize::number(amount, 2);
+        if invoice.currency.is_empty() { amount } else { format!("{} {}", amount, escape_html(&invoice.currency)) }
+    };
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    writeln!(out, "<title>Worklog {} {}</title>", escape_html(&invoice.client), invoice.month.format("%Y-%m")).unwrap();
+    out.push_str(STYLE);
+    out.push_str("</head>\n<body>\n<main>\n");
+
+    writeln!(out, "<h1>Worklog: {}</h1>", escape_html(&invoice.client)).unwrap();
+    writeln!(out, "<p class=\"meta\">{} &middot; {}/hour</p>", invoice.month.format("%B %Y"), money(invoice.rate)).unwrap();
+
+    out.push_str("<table>\n<tr><th>Date</th><th>Description</th><th class=\"num\">Hours</th><th class=\"num\">Amount</th></tr>\n");
+    for line in &invoice.lines {
+        writeln!(
+            out,
+            "<tr><td class=\"num\">{}</td><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+            line.date.format("%a %Y-%m-%d"),
+            escape_html(&line.description),
+            humanize::number(line.hours, 2),
+            money(line.amount)
+        ).unwrap();
+    }
+    writeln!(
+        out,
+        "<tr class=\"total\"><td>Total</td><td></td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+        humanize::number(invoice.total_hours(), 2),
+        money(invoice.total_amount())
+    ).unwrap();
+    out.push_str("</table>\n");
+
+    if invoice.lines.is_empty() {
+        writeln!(out, "<p class=\"meta\">No time was recorded for {} this month.</p>", escape_html(&invoice.client)).unwrap();
+    }
+
+    writeln!(out, "<p class=\"meta\">Generated {}</p>", Local::now().format("%Y-%m-%d %H:%M")).unwrap();
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
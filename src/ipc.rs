@@ -0,0 +1,344 @@
+// How client commands like pause, status and current reach the running tracker: one JSON line
+// request and one JSON line answer over a transport, a Unix socket, a named pipe on Windows or TCP
+// on localhost. The tracker writes which one it listens on, with a random token every request has
+// to carry, to control.json in the data dir shared by all profiles, readable only by the user.
+// [ipc] picks the transport (auto: a Unix socket, a named pipe on Windows), transport = "off"
+// leaves only the control files.
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::{IpcConfig, IpcTransport};
+use crate::storage;
+
+const CONTROL_FILE: &str = "control.json";
+const SOCKET_FILE: &str = "control.sock";
+// How long a client waits for the tracker, which answers from its own thread right away
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST: u64 = 64 * 1024;
+
+pub trait Connection: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Connection for T {}
+
+pub trait Listener: Send {
+    fn accept(&mut self) -> io::Result<Box<dyn Connection>>;
+}
+
+pub trait Transport {
+    // Starts listening, and what clients need to find it again
+    fn listen(&self) -> Result<(Box<dyn Listener>, Endpoint), String>;
+}
+
+// What control.json holds
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Endpoint {
+    pub transport: String,
+    // A socket path, a pipe name or a localhost port
+    pub address: String,
+    #[serde(default)]
+    pub token: String,
+}
+
+impl Endpoint {
+    fn connect(&self) -> io::Result<Box<dyn Connection>> {
+        match self.transport.as_str() {
+            #[cfg(unix)]
+            "unix" => {
+                let stream = std::os::unix::net::UnixStream::connect(&self.address)?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(windows)]
+            "pipe" => pipe::connect(&self.address),
+            "tcp" => {
+                let port: u16 = self.address.parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid port"))?;
+                let stream = TcpStream::connect_timeout(&SocketAddr::from((Ipv4Addr::LOCALHOST, port)), TIMEOUT)?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                Ok(Box::new(stream))
+            }
+            other => Err(io::Error::new(io::ErrorKind::Unsupported, format!("the {} transport isn't available here", other))),
+        }
+    }
+}
+
+#[cfg(unix)]
+pub struct UnixSocket;
+
+#[cfg(unix)]
+impl Transport for UnixSocket {
+    fn listen(&self) -> Result<(Box<dyn Listener>, Endpoint), String> {
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::net::UnixListener;
+
+        struct Socket(UnixListener);
+        impl Listener for Socket {
+            fn accept(&mut self) -> io::Result<Box<dyn Connection>> {
+                Ok(Box::new(self.0.accept()?.0))
+            }
+        }
+
+        let path = storage::base_data_dir().join(SOCKET_FILE);
+        // Left behind by a tracker that didn't get to clean up
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).map_err(|e| format!("failed to listen on {}: {}", path.display(), e))?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("failed to restrict {}: {}", path.display(), e))?;
+        Ok((Box::new(Socket(listener)), Endpoint { transport: "unix".to_string(), address: path.display().to_string(), token: String::new() }))
+    }
+}
+
+#[cfg(feature = "server")]
+pub struct TcpLocalhost {
+    // 0 for any free port
+    pub port: u16,
+}
+
+#[cfg(feature = "server")]
+impl Transport for TcpLocalhost {
+    fn listen(&self) -> Result<(Box<dyn Listener>, Endpoint), String> {
+        struct Tcp(std::net::TcpListener);
+        impl Listener for Tcp {
+            fn accept(&mut self) -> io::Result<Box<dyn Connection>> {
+                let (stream, _) = self.0.accept()?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                Ok(Box::new(stream))
+            }
+        }
+
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, self.port))
+            .map_err(|e| format!("failed to listen on localhost:{}: {}", self.port, e))?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        Ok((Box::new(Tcp(listener)), Endpoint { transport: "tcp".to_string(), address: port.to_string(), token: String::new() }))
+    }
+}
+
+#[cfg(windows)]
+pub struct NamedPipe;
+
+#[cfg(windows)]
+impl Transport for NamedPipe {
+    fn listen(&self) -> Result<(Box<dyn Listener>, Endpoint), String> {
+        struct Pipe(String);
+        impl Listener for Pipe {
+            fn accept(&mut self) -> io::Result<Box<dyn Connection>> {
+                Ok(Box::new(pipe::accept(&self.0)?))
+            }
+        }
+
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        let name = format!(r"\\.\pipe\window_tracker-{}", user.replace(['\\', '/'], "_"));
+        Ok((Box::new(Pipe(name.clone())), Endpoint { transport: "pipe".to_string(), address: name, token: String::new() }))
+    }
+}
+
+#[cfg(windows)]
+mod pipe {
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::windows::io::FromRawHandle;
+
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{CloseHandle, ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED};
+    use windows::Win32::Storage::FileSystem::PIPE_ACCESS_DUPLEX;
+    use windows::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+        PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    // A new instance of the pipe, once a client has connected to it
+    pub fn accept(name: &str) -> io::Result<File> {
+        let wide: Vec<u16> = name.encode_utf16().chain(Some(0)).collect();
+        unsafe {
+            let handle = CreateNamedPipeW(
+                PCWSTR(wide.as_ptr()),
+                PIPE_ACCESS_DUPLEX,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                4096,
+                4096,
+                0,
+                None,
+            );
+            if handle.is_invalid() {
+                return Err(io::Error::last_os_error());
+            }
+            // A client that came between creating and waiting is already connected
+            if let Err(e) = ConnectNamedPipe(handle, None) {
+                if e.code() != ERROR_PIPE_CONNECTED.to_hresult() {
+                    let _ = CloseHandle(handle);
+                    return Err(io::Error::other(e));
+                }
+            }
+            Ok(File::from_raw_handle(handle.0 as _))
+        }
+    }
+
+    pub fn connect(name: &str) -> io::Result<Box<dyn super::Connection>> {
+        // Every instance busy means another client is just being answered
+        for _ in 0..50 {
+            match OpenOptions::new().read(true).write(true).open(name) {
+                Ok(file) => return Ok(Box::new(file)),
+                Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY.0 as i32) => std::thread::sleep(std::time::Duration::from_millis(100)),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::TimedOut, "the tracker's pipe stayed busy"))
+    }
+}
+
+fn control_path() -> PathBuf {
+    storage::base_data_dir().join(CONTROL_FILE)
+}
+
+// `bytes` random bytes as hex from the OS's CSPRNG (getrandom, BCryptGenRandom, SecRandomCopyBytes);
+// the tokens and the PIN salt are made from it, so there is nothing to fall back on
+pub fn random_hex(bytes: usize) -> Result<String, String> {
+    let mut random = vec![0u8; bytes];
+    getrandom::getrandom(&mut random).map_err(|e| format!("the system's random number generator failed: {}", e))?;
+    Ok(random.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn write_control(endpoint: &Endpoint) -> Result<(), String> {
+    let directory = storage::base_data_dir();
+    fs::create_dir_all(&directory).map_err(|e| format!("failed to create {}: {}", directory.display(), e))?;
+    let path = control_path();
+    let text = serde_json::to_string(endpoint).map_err(|e| e.to_string())?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    // The mode only applies to a new file, one left from before may be readable by others
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600)).map_err(|e| format!("failed to restrict {}: {}", path.display(), e))?;
+    }
+    file.write_all(text.as_bytes()).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+fn transport(config: &IpcConfig) -> Option<Box<dyn Transport>> {
+    match config.transport {
+        IpcTransport::Off => None,
+        #[cfg(feature = "server")]
+        IpcTransport::Tcp => Some(Box::new(TcpLocalhost { port: config.port })),
+        #[cfg(unix)]
+        IpcTransport::Auto | IpcTransport::Unix => Some(Box::new(UnixSocket)),
+        #[cfg(windows)]
+        IpcTransport::Auto | IpcTransport::Pipe => Some(Box::new(NamedPipe)),
+        // What the platform doesn't have, check turned down already
+        #[cfg(feature = "server")]
+        _ => Some(Box::new(TcpLocalhost { port: config.port })),
+        // Only the control files then
+        #[cfg(not(feature = "server"))]
+        _ => None,
+    }
+}
+
+pub fn check(config: &IpcConfig) -> Result<(), String> {
+    match config.transport {
+        IpcTransport::Unix if !cfg!(unix) => Err("the unix [ipc] transport needs Unix sockets, use pipe or tcp".to_string()),
+        IpcTransport::Pipe if !cfg!(windows) => Err("the pipe [ipc] transport is only on Windows, use unix or tcp".to_string()),
+        IpcTransport::Tcp if !cfg!(feature = "server") => Err("the tcp [ipc] transport needs --features server".to_string()),
+        _ => Ok(()),
+    }
+}
+
+// Compared in full whatever the first difference, so timing tells nothing
+pub fn tokens_match(given: &str, token: &str) -> bool {
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn answer(mut connection: Box<dyn Connection>, token: &str, handle: fn(&Value) -> Value) {
+    let mut line = String::new();
+    if BufReader::new((&mut connection).take(MAX_REQUEST)).read_line(&mut line).is_err() {
+        return;
+    }
+    let response = match serde_json::from_str::<Value>(&line) {
+        Ok(request) => {
+            if tokens_match(request["token"].as_str().unwrap_or(""), token) { handle(&request) } else { json!({ "ok": false, "error": "wrong or missing token" }) }
+        }
+        Err(e) => json!({ "ok": false, "error": format!("invalid request: {}", e) }),
+    };
+    let _ = connection.write_all(format!("{}\n", response).as_bytes()).and_then(|_| connection.flush());
+}
+
+// Answers requests from a background thread, each connection on its own so a client that never
+// finishes its request holds up nobody
+pub fn serve(config: &IpcConfig, handle: fn(&Value) -> Value) -> Result<(), String> {
+    check(config)?;
+    let Some(transport) = transport(config) else { return Ok(()) };
+    let (mut listener, mut endpoint) = transport.listen()?;
+    endpoint.token = random_hex(32)?;
+    write_control(&endpoint)?;
+    let token = endpoint.token;
+    thread::spawn(move || loop {
+        match listener.accept() {
+            Ok(connection) => {
+                let token = token.clone();
+                thread::spawn(move || answer(connection, &token, handle));
+            }
+            Err(e) => {
+                eprintln!("Failed to accept a control connection: {}", e);
+                thread::sleep(Duration::from_secs(1));
+            }
+        }
+    });
+    Ok(())
+}
+
+// Once tracking stops, so clients go back to the control files
+pub fn stop() {
+    let Ok(text) = fs::read_to_string(control_path()) else { return };
+    if let Ok(endpoint) = serde_json::from_str::<Endpoint>(&text) {
+        if endpoint.transport == "unix" {
+            let _ = fs::remove_file(&endpoint.address);
+        }
+    }
+    let _ = fs::remove_file(control_path());
+}
+
+// The running tracker's answer to `command`, None when none is listening
+pub fn request(command: &str, mut request: Value) -> Result<Option<Value>, String> {
+    let Ok(text) = fs::read_to_string(control_path()) else { return Ok(None) };
+    let endpoint: Endpoint = serde_json::from_str(&text).map_err(|e| format!("unreadable {}: {}", control_path().display(), e))?;
+    let mut connection = match endpoint.connect() {
+        Ok(connection) => connection,
+        // Left behind by a tracker that crashed
+        Err(e) if matches!(e.kind(), io::ErrorKind::ConnectionRefused | io::ErrorKind::NotFound) => return Ok(None),
+        Err(e) => return Err(format!("failed to reach the tracker over {}: {}", endpoint.transport, e)),
+    };
+    request["command"] = command.into();
+    request["token"] = endpoint.token.into();
+    connection.write_all(format!("{}\n", request).as_bytes()).and_then(|_| connection.flush())
+        .map_err(|e| format!("failed to send to the tracker: {}", e))?;
+    let mut line = String::new();
+    BufReader::new(connection).read_line(&mut line).map_err(|e| format!("no answer from the tracker: {}", e))?;
+    let response: Value = serde_json::from_str(&line).map_err(|e| format!("invalid answer from the tracker: {}", e))?;
+    if response["ok"] == false {
+        return Err(response["error"].as_str().unwrap_or("the tracker refused the request").to_string());
+    }
+    Ok(Some(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_hex_is_hex_and_never_repeats() {
+        let (first, second) = (random_hex(32).unwrap(), random_hex(32).unwrap());
+        assert_eq!(first.len(), 64);
+        assert!(first.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,196 @@
+// `jira-push`: time on windows whose titles mention a ticket (ABC-123 in a branch name, browser tab
+// or terminal) rolled into one worklog per ticket and day and added to the issues in Jira.
+// What was pushed is kept in jira-worklogs.json, so running it again only adds time tracked since.
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::Regex;
+use serde_json::json;
+
+use crate::cli::Args;
+use crate::config::{self, JiraConfig};
+use crate::humanize;
+use crate::report::{clip, daily_chunks, last_days, local_midnight};
+use crate::storage::{self, Interval};
+
+const LEDGER_FILE: &str = "jira-worklogs.json";
+// Jira rejects worklogs under a minute
+const MIN_WORKLOG_SECS: f64 = 60.0;
+
+pub struct Worklog {
+    pub day: NaiveDate,
+    pub ticket: String,
+    pub started: DateTime<Utc>,
+    pub secs: f64,
+    pub titles: Vec<String>,
+}
+
+impl Worklog {
+    fn ledger_key(&self) -> String {
+        format!("{} {}", self.day, self.ticket)
+    }
+}
+
+pub struct TicketMatcher {
+    patterns: Vec<Regex>,
+    projects: Vec<String>,
+}
+
+impl TicketMatcher {
+    pub fn from_config(config: &JiraConfig) -> Result<Self, String> {
+        let patterns = config.patterns.iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| format!("invalid jira pattern '{}': {}", pattern, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(TicketMatcher { patterns, projects: config.projects.clone() })
+    }
+
+    // The first capture group of the first matching pattern, or the whole match without one
+    pub fn ticket(&self, title: &str) -> Option<String> {
+        self.patterns.iter().find_map(|pattern| {
+            let captures = pattern.captures(title)?;
+            let ticket = captures.get(1).or_else(|| captures.get(0))?.as_str().to_uppercase();
+            let project = ticket.split('-').next().unwrap_or_default();
+            (self.projects.is_empty() || self.projects.iter().any(|name| name.eq_ignore_ascii_case(project))).then_some(ticket)
+        })
+    }
+}
+
+pub fn worklogs(intervals: &[Interval], matcher: &TicketMatcher) -> Vec<Worklog> {
+    let mut worklogs: BTreeMap<(NaiveDate, String), Worklog> = BTreeMap::new();
+    let mut titles: HashMap<(NaiveDate, String), HashMap<&str, f64>> = HashMap::new();
+    for interval in intervals {
+        let Some(ticket) = matcher.ticket(&interval.title) else { continue };
+        for (day, secs) in daily_chunks(interval) {
+            let worklog = worklogs.entry((day, ticket.clone())).or_insert_with(|| Worklog {
+                day,
+                ticket: ticket.clone(),
+                started: interval.start.max(local_midnight(day).with_timezone(&Utc)),
+                secs: 0.0,
+                titles: Vec::new(),
+            });
+            worklog.secs += secs;
+            *titles.entry((day, ticket.clone())).or_default().entry(&interval.title).or_insert(0.0) += secs;
+        }
+    }
+
+    worklogs.into_iter()
+        .map(|(key, mut worklog)| {
+            let mut by_time: Vec<(&str, f64)> = titles.remove(&key).unwrap_or_default().into_iter().collect();
+            by_time.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            worklog.titles = by_time.into_iter().take(3).map(|(title, _)| title.to_string()).collect();
+            worklog
+        })
+        .collect()
+}
+
+fn ledger_path() -> PathBuf {
+    storage::data_dir().join(LEDGER_FILE)
+}
+
+// Seconds already pushed per "day ticket"
+fn read_ledger() -> Result<BTreeMap<String, f64>, String> {
+    match fs::read_to_string(ledger_path()) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| format!("unreadable {}: {}", ledger_path().display(), e)),
+        Err(_) => Ok(BTreeMap::new()),
+    }
+}
+
+fn write_ledger(ledger: &BTreeMap<String, f64>) -> Result<(), String> {
+    let path = ledger_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(ledger).unwrap()).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let config = config::load()?;
+    let jira = config.jira.ok_or("jira-push needs a [jira] section in config.toml")?;
+    let matcher = TicketMatcher::from_config(&jira)?;
+    let days: u32 = match args.value("--days") {
+        Some(days) => days.parse().ok().filter(|days| *days > 0).ok_or_else(|| format!("invalid value '{}' for --days", days))?,
+        None => 1,
+    };
+    let dry_run = args.flag("--dry-run");
+    let json_output = args.json_output()?;
+
+    let (from, to) = last_days(days);
+    let intervals = storage::load_intervals(from.with_timezone(&Utc), to.with_timezone(&Utc))
+        .map_err(|e| format!("failed to read history: {}", e))?;
+    let intervals = clip(&intervals, from, to);
+
+    let mut ledger = read_ledger()?;
+    let mut results = Vec::new();
+    for worklog in worklogs(&intervals, &matcher) {
+        let pushed = ledger.get(&worklog.ledger_key()).copied().unwrap_or(0.0);
+        let secs = ((worklog.secs - pushed) / 60.0).floor() * 60.0;
+        let status = if secs < MIN_WORKLOG_SECS {
+            "up to date".to_string()
+        } else if dry_run {
+            "would push".to_string()
+        } else {
+            match push(&jira, &worklog, secs) {
+                Ok(()) => {
+                    ledger.insert(worklog.ledger_key(), pushed + secs);
+                    write_ledger(&ledger)?;
+                    "pushed".to_string()
+                }
+                Err(e) => format!("failed: {}", e),
+            }
+        };
+        results.push((worklog, secs.max(0.0), status));
+    }
+
+    if json_output {
+        let worklogs: Vec<serde_json::Value> = results.iter()
+            .map(|(worklog, secs, status)| json!({
+                "date": worklog.day.to_string(),
+                "ticket": worklog.ticket,
+                "tracked_secs": worklog.secs,
+                "push_secs": secs,
+                "status": status,
+            }))
+            .collect();
+        println!("{}", json!({ "dry_run": dry_run, "worklogs": worklogs }));
+    } else if results.is_empty() {
+        println!("No tracked time mentions a ticket");
+    } else {
+        for (worklog, secs, status) in &results {
+            println!("{}  {:<12} {:>8} tracked, {:>8} {}", worklog.day, worklog.ticket, humanize::duration(worklog.secs), humanize::duration(*secs), status);
+        }
+    }
+
+    if results.iter().any(|(_, _, status)| status.starts_with("failed")) {
+        return Err("some worklogs could not be pushed".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "jira"))]
+fn push(_jira: &JiraConfig, _worklog: &Worklog, _secs: f64) -> Result<(), String> {
+    Err("pushing to Jira needs --features jira, --dry-run works without it".to_string())
+}
+
+#[cfg(feature = "jira")]
+fn push(jira: &JiraConfig, worklog: &Worklog, secs: f64) -> Result<(), String> {
+    let token = std::env::var(&jira.token_env).map_err(|_| format!("set {} to a Jira API token", jira.token_env))?;
+    let url = format!("{}/rest/api/2/issue/{}/worklog", jira.url.trim_end_matches('/'), worklog.ticket);
+    let body = json!({
+        "started": worklog.started.with_timezone(&chrono::Local).format("%Y-%m-%dT%H:%M:%S%.3f%z").to_string(),
+        "timeSpentSeconds": secs as u64,
+        "comment": format!("Tracked by window_tracker: {}", worklog.titles.join("; ")),
+    });
+
+    ureq::post(&url)
+        .timeout(std::time::Duration::from_secs(30))
+        .set("Authorization", &format!("Basic {}", crate::report::base64(format!("{}:{}", jira.email, token).as_bytes())))
+        .set("Content-Type", "application/json")
+        .send_string(&body.to_string())
+        .map_err(|e| match e {
+            ureq::Error::Status(code, response) => format!("{} {}", code, response.into_string().unwrap_or_default().trim()),
+            other => other.to_string(),
+        })?;
+    Ok(())
+}
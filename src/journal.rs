@@ -0,0 +1,63 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::storage::{self, Interval};
+
+const JOURNAL_FILE: &str = "journal.json";
+
+// Where the open interval is checkpointed, left behind by a crash until the next start
+pub fn journal_path() -> PathBuf {
+    storage::data_dir().join(JOURNAL_FILE)
+}
+
+// Checkpoints the still-open interval, replacing the previous checkpoint atomically.
+pub fn write(interval: &Interval) -> io::Result<()> {
+    let path = journal_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, storage::encode(interval)?)?;
+    fs::rename(temporary, path)
+}
+
+// The last checkpoint of the running session, if any
+pub fn read() -> io::Result<Option<Interval>> {
+    match fs::read(journal_path()) {
+        Ok(bytes) => Ok(storage::decode(&String::from_utf8_lossy(&bytes)).ok()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// Moves an interval left behind by a crash into the history, up to its last checkpoint.
+pub fn recover() -> io::Result<Option<Interval>> {
+    let path = journal_path();
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let interval = match storage::decode(&String::from_utf8_lossy(&bytes)) {
+        Ok(mut interval) if interval.end > interval.start => {
+            // The checkpoints are written before enrichment, which only finished intervals get
+            crate::wt_enrich(&mut interval);
+            storage::append_interval(&interval)?;
+            Some(interval)
+        }
+        Ok(_) => None,
+        // Left for the newer build that wrote it
+        Err(e) if e.kind() == io::ErrorKind::Unsupported => {
+            return Err(io::Error::new(e.kind(), format!("journal {}: {}", path.display(), e)));
+        }
+        Err(e) => {
+            eprintln!("Discarding unreadable journal {}: {}", path.display(), e);
+            None
+        }
+    };
+    fs::remove_file(path)?;
+    Ok(interval)
+}
@@ -0,0 +1,178 @@
+// Coding time by programming language, from the file extension in editor and IDE titles like
+// "main.rs - crate - Visual Studio Code". Reports show the totals, `export --languages` the days.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use chrono::{NaiveDate, Utc};
+use serde_json::json;
+
+use crate::cli::Args;
+use crate::config::{self, Config};
+use crate::export::{self, csv_field};
+use crate::report::{clip, daily_chunks, last_days};
+use crate::storage::{self, Interval};
+
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "Rust"),
+    ("py", "Python"),
+    ("pyi", "Python"),
+    ("ipynb", "Python"),
+    ("js", "JavaScript"),
+    ("mjs", "JavaScript"),
+    ("cjs", "JavaScript"),
+    ("jsx", "JavaScript"),
+    ("ts", "TypeScript"),
+    ("tsx", "TypeScript"),
+    ("go", "Go"),
+    ("c", "C"),
+    ("h", "C"),
+    ("cc", "C++"),
+    ("cpp", "C++"),
+    ("cxx", "C++"),
+    ("hpp", "C++"),
+    ("cs", "C#"),
+    ("java", "Java"),
+    ("kt", "Kotlin"),
+    ("kts", "Kotlin"),
+    ("swift", "Swift"),
+    ("m", "Objective-C"),
+    ("mm", "Objective-C"),
+    ("rb", "Ruby"),
+    ("php", "PHP"),
+    ("lua", "Lua"),
+    ("zig", "Zig"),
+    ("hs", "Haskell"),
+    ("ex", "Elixir"),
+    ("exs", "Elixir"),
+    ("erl", "Erlang"),
+    ("scala", "Scala"),
+    ("clj", "Clojure"),
+    ("dart", "Dart"),
+    ("r", "R"),
+    ("jl", "Julia"),
+    ("sh", "Shell"),
+    ("bash", "Shell"),
+    ("zsh", "Shell"),
+    ("ps1", "PowerShell"),
+    ("sql", "SQL"),
+    ("html", "HTML"),
+    ("css", "CSS"),
+    ("scss", "CSS"),
+    ("vue", "Vue"),
+    ("svelte", "Svelte"),
+    ("md", "Markdown"),
+    ("toml", "TOML"),
+    ("yaml", "YAML"),
+    ("yml", "YAML"),
+    ("json", "JSON"),
+];
+
+pub struct Languages {
+    editors: Vec<String>,
+    // Lowercase extension to language, the built-in table overridden by the config
+    extensions: HashMap<String, String>,
+}
+
+impl Languages {
+    pub fn from_config(config: &Config) -> Self {
+        let mut extensions: HashMap<String, String> = EXTENSIONS.iter()
+            .map(|(extension, language)| (extension.to_string(), language.to_string()))
+            .collect();
+        let editors = match &config.languages {
+            Some(languages) => {
+                for (extension, language) in &languages.extensions {
+                    extensions.insert(extension.trim_start_matches('.').to_lowercase(), language.clone());
+                }
+                languages.editors.clone()
+            }
+            None => config::default_editor_apps(),
+        };
+        Languages { editors, extensions }
+    }
+
+    fn is_editor(&self, app: &str) -> bool {
+        let app = app.to_lowercase();
+        let app = app.strip_suffix(".exe").unwrap_or(&app);
+        self.editors.iter().any(|name| name.eq_ignore_ascii_case(app))
+    }
+
+    // The language of the first file name in an editor's title with a known extension
+    pub fn language(&self, interval: &Interval) -> Option<&str> {
+        if !self.is_editor(&interval.app) {
+            return None;
+        }
+        interval.title
+            .split(|c: char| c.is_whitespace() || "/\\()[]:,;\"'`".contains(c))
+            .filter_map(|word| word.rsplit_once('.'))
+            .filter(|(name, _)| !name.is_empty())
+            .find_map(|(_, extension)| self.extensions.get(&extension.to_lowercase()))
+            .map(String::as_str)
+    }
+}
+
+// Seconds per language, most first
+pub fn by_language(intervals: &[Interval], languages: &Languages) -> Vec<(String, f64)> {
+    let mut totals: HashMap<&str, f64> = HashMap::new();
+    for interval in intervals {
+        if let Some(language) = languages.language(interval) {
+            *totals.entry(language).or_insert(0.0) += interval.duration_secs();
+        }
+    }
+    let mut totals: Vec<(String, f64)> = totals.into_iter().map(|(language, secs)| (language.to_string(), secs)).collect();
+    totals.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+// Days in order, languages by name
+fn by_day(intervals: &[Interval], languages: &Languages) -> BTreeMap<NaiveDate, BTreeMap<String, f64>> {
+    let mut days: BTreeMap<NaiveDate, BTreeMap<String, f64>> = BTreeMap::new();
+    for interval in intervals {
+        let Some(language) = languages.language(interval) else { continue };
+        for (day, secs) in daily_chunks(interval) {
+            *days.entry(day).or_default().entry(language.to_string()).or_insert(0.0) += secs;
+        }
+    }
+    days
+}
+
+// `export --languages`
+pub fn run(args: &Args) -> Result<(), String> {
+    let days: u32 = args.parsed("--days", 30)?;
+    if days == 0 {
+        return Err("--days must be at least 1".to_string());
+    }
+    let languages = Languages::from_config(&config::load()?);
+    let (from, to) = last_days(days);
+    let intervals = storage::load_intervals(from.with_timezone(&Utc), to.with_timezone(&Utc))
+        .map_err(|e| format!("failed to read history: {}", e))?;
+    let intervals = clip(&intervals, from, to);
+    let days = by_day(&intervals, &languages);
+
+    let default_format = if args.json_output()? { "json" } else { "csv" };
+    let content = match args.value("--format").unwrap_or(default_format) {
+        "csv" => render_csv(&days),
+        "json" => render_json(&days),
+        other => return Err(format!("unknown languages format '{}', expected 'csv' or 'json'", other)),
+    };
+    export::write_output(args, &content)
+}
+
+fn render_csv(days: &BTreeMap<NaiveDate, BTreeMap<String, f64>>) -> String {
+    let mut out = String::from("date,language,hours\n");
+    for (day, languages) in days {
+        for (language, secs) in languages {
+            writeln!(out, "{},{},{:.2}", day, csv_field(language), secs / 3600.0).unwrap();
+        }
+    }
+    out
+}
+
+fn render_json(days: &BTreeMap<NaiveDate, BTreeMap<String, f64>>) -> String {
+    let document = json!({
+        "days": days.iter().map(|(day, languages)| json!({
+            "date": day.to_string(),
+            "languages": languages.iter().map(|(language, secs)| json!({ "language": language, "secs": secs })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+    });
+    format!("{}\n", serde_json::to_string(&document).unwrap())
+}
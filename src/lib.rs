@@ -0,0 +1,2146 @@
+pub mod activity;
+pub mod alerts;
+pub mod anomaly;
+#[cfg(feature = "server")]
+pub mod api;
+pub mod autostart;
+#[cfg(feature = "backup")]
+pub mod backup;
+pub mod calls;
+pub mod categories;
+pub mod cli;
+pub mod config;
+pub mod control;
+#[cfg(all(feature = "dbus", target_os = "linux"))]
+pub mod dbus;
+pub mod dnd;
+pub mod doctor;
+pub mod enforce;
+pub mod enrichers;
+pub mod events;
+pub mod export;
+pub mod ffi;
+pub mod filter;
+pub mod game_names;
+pub mod games;
+pub mod goals;
+pub mod greeting;
+pub mod health;
+pub mod hot;
+pub mod heatmap;
+pub mod history;
+#[cfg(feature = "hub")]
+pub mod hub;
+pub mod humanize;
+pub mod i18n;
+pub mod icons;
+pub mod ignore;
+pub mod import;
+pub mod influx;
+pub mod ipc;
+pub mod init;
+pub mod invoice;
+pub mod jira;
+pub mod journal;
+pub mod languages;
+#[cfg(feature = "server")]
+pub mod mail;
+pub mod media;
+pub mod notes;
+pub mod merge;
+#[cfg(all(feature = "menubar", target_os = "macos"))]
+pub mod menubar;
+#[cfg(feature = "node")]
+pub mod node;
+pub mod notify;
+pub mod overlap;
+#[cfg(feature = "otlp")]
+pub mod otlp;
+pub mod polling;
+pub mod power;
+pub mod powershell;
+pub mod presence;
+pub mod privacy;
+pub mod profile;
+pub mod purge;
+pub mod query;
+pub mod reload;
+pub mod remote;
+pub mod report;
+pub mod review;
+pub mod rollup;
+pub mod rules;
+pub mod sampler;
+#[cfg(feature = "scripting")]
+pub mod scripts;
+pub mod schedule;
+pub mod storage;
+pub mod table;
+pub mod triggers;
+#[cfg(feature = "self-update")]
+pub mod update;
+pub mod verify;
+#[cfg(feature = "server")]
+pub mod viewer;
+pub mod visibility;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+pub mod wayland;
+pub mod statsd;
+pub mod stream;
+pub mod templates;
+pub mod throttle;
+pub mod thumbnails;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use std::thread;
+use std::time::Duration as StdDuration;
+
+use categories::{Categorizer, CategoryStyles};
+use config::AggregateBy;
+use enrichers::{Enricher, Enrichers};
+use events::WindowTime;
+use ignore::WindowFilter;
+use merge::Merges;
+use remote::RemoteLabels;
+use storage::{EventKind, Interval};
+use templates::TitleParser;
+use throttle::TitleThrottle;
+
+static EVICTED_WINDOWS: AtomicU64 = AtomicU64::new(0);
+static EPHEMERAL: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    // Keyed by (window identity, title) so both per-window and per-title totals can be derived
+    static ref WINDOWS: Mutex<HashMap<(String, String), WindowRecord>> = Mutex::new(HashMap::new());
+    static ref LAST_FOCUS_CHANGE: Mutex<SystemTime> = Mutex::new(SystemTime::now());
+    static ref CURRENT_INTERVAL: Mutex<Option<OpenInterval>> = Mutex::new(None);
+    static ref CATEGORIZER: Mutex<Categorizer> = Mutex::new(Categorizer::default());
+    static ref WINDOW_FILTER: Mutex<WindowFilter> = Mutex::new(WindowFilter::default());
+    static ref MERGES: Mutex<Merges> = Mutex::new(Merges::default());
+    static ref REMOTE_LABELS: Mutex<RemoteLabels> = Mutex::new(RemoteLabels::default());
+    static ref TITLE_PARSER: Mutex<TitleParser> = Mutex::new(TitleParser::default());
+    static ref SEPARATE_DIALOGS: Mutex<bool> = Mutex::new(false);
+    static ref PAUSED: Mutex<bool> = Mutex::new(false);
+    static ref IN_CALL: Mutex<bool> = Mutex::new(false);
+    static ref FOCUS_LISTENERS: Mutex<Vec<FocusListener>> = Mutex::new(Vec::new());
+    static ref AGGREGATE_BY: Mutex<AggregateBy> = Mutex::new(AggregateBy::Title);
+    static ref TITLE_THROTTLE: Mutex<TitleThrottle> = Mutex::new(TitleThrottle::default());
+    static ref INTERVAL_LISTENERS: Mutex<Vec<IntervalListener>> = Mutex::new(Vec::new());
+    static ref ENRICHERS: Mutex<Enrichers> = Mutex::new(Enrichers::default());
+    // Categories given to apps for the rest of the session by lowercase app, see alerts
+    static ref RECATEGORIZED: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+// Called from the sampling thread whenever focus moves to another window or the title changes
+pub type FocusListener = Box<dyn Fn(EventKind, &ActiveWindow) + Send>;
+// Called from the sampling thread with each interval as it is added to the history
+pub type IntervalListener = Box<dyn Fn(&Interval) + Send>;
+
+#[derive(Debug, Clone)]
+pub struct ActiveWindow {
+    pub title: String,
+    pub app: String,
+    // WM_CLASS res_class on X11 or the Win32 window class, empty on macOS which has none
+    pub class: String,
+    // Native handle: HWND, CGWindowID or X window id
+    pub id: u64,
+    pub pid: u32,
+}
+
+// Title and app of the time with the desktop or nothing focused
+pub const NO_WINDOW: &str = "(desktop / no window)";
+// Title and app of the time the platform couldn't say what was focused: the query failed, or didn't
+// return within the sampler's timeout. Like NO_WINDOW these keep the time from going to the window
+// before, a gap in measuring shows in reports; health.rs counts them for status.
+pub const SAMPLING_FAILED: &str = "(sampling failed)";
+pub const NOT_RESPONDING: &str = "(not responding)";
+// The title of a focused window that has none, and of one the tracker may not read (macOS without
+// the Screen Recording permission; on Windows elevated windows are ELEVATED_WINDOW)
+pub const NO_TITLE: &str = "(no title)";
+pub const NO_PERMISSION: &str = "(permission denied)";
+
+impl ActiveWindow {
+    // Stable identity of the window itself, so two windows with the same title stay apart
+    pub fn key(&self) -> String {
+        format!("{}:{:x}", self.pid, self.id)
+    }
+
+    // What the platforms return when they know nothing has focus (the desktop, a lock screen or a
+    // UAC prompt), so that time adds up as its own rather than going to the window before
+    pub fn none() -> Self {
+        ActiveWindow::placeholder(NO_WINDOW)
+    }
+
+    fn placeholder(name: &str) -> Self {
+        ActiveWindow { title: name.to_string(), app: name.to_string(), class: String::new(), id: 0, pid: 0 }
+    }
+
+    // NO_WINDOW and the buckets for failed samples, which tell nothing about content
+    pub fn is_placeholder(&self) -> bool {
+        self.pid == 0 && self.title == self.app && [NO_WINDOW, SAMPLING_FAILED, NOT_RESPONDING].contains(&self.app.as_str())
+    }
+}
+
+impl WindowRecord {
+    // The bucket for this record's time, falling back like Interval::app_name, class_name and category_name
+    fn aggregate_key(&self, aggregate_by: AggregateBy) -> &str {
+        match aggregate_by {
+            AggregateBy::Title => &self.title,
+            AggregateBy::Class if !self.class.is_empty() => &self.class,
+            AggregateBy::App | AggregateBy::Class => if self.app.is_empty() { "unknown" } else { &self.app },
+            AggregateBy::Rule => if self.category.is_empty() { categories::UNCATEGORIZED } else { &self.category },
+        }
+    }
+}
+
+struct OpenInterval {
+    window: ActiveWindow,
+    start: SystemTime,
+    event: EventKind,
+    call: bool,
+    // What played in the window when it opened, see media
+    media: Option<media::Media>,
+    // Whether Do Not Disturb was on, see dnd
+    dnd: Option<bool>,
+    // Whether the machine was on battery, see power
+    power: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EventCounts {
+    pub switches: u64,
+    pub title_changes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct WindowRecord {
+    pub key: String,
+    pub title: String,
+    pub app: String,
+    pub class: String,
+    pub category: String,
+    pub focus_time: f64,
+    pub first_seen: SystemTime,
+    pub last_focused: SystemTime,
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::ActiveWindow;
+    use crate::doctor::{Check, Status};
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, BOOL, E_ACCESSDENIED, ERROR_SUCCESS, FALSE, HWND, LPARAM, TRUE};
+    use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED};
+    use windows::Win32::Storage::Packaging::Appx::GetApplicationUserModelId;
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        EnumChildWindows, EnumWindows, GetClassNameW, GetForegroundWindow, GetWindow, GetWindowLongW, GetWindowTextW, GetWindowThreadProcessId, IsIconic,
+        IsWindowVisible, GWL_EXSTYLE, GW_OWNER, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+    };
+    use std::time::Duration;
+
+    // Draws the frames of UWP apps, the app itself runs in another process
+    const FRAME_HOST: &str = "ApplicationFrameHost.exe";
+    // The desktop's windows, behind the icons and with them
+    const DESKTOP_CLASSES: [&str; 2] = ["Progman", "WorkerW"];
+    // The app of windows whose process runs elevated while the tracker doesn't, and their title
+    // when it can't be read either
+    pub const ELEVATED_WINDOW: &str = "(elevated window)";
+
+    pub fn get_active_window(separate_dialogs: bool) -> Option<ActiveWindow> {
+        unsafe {
+            let mut hwnd = GetForegroundWindow();
+            // The secure desktop of UAC prompts and the lock screen have no foreground window
+            if hwnd.is_invalid() {
+                return Some(ActiveWindow::none());
+            }
+            if !separate_dialogs {
+                hwnd = root_owner(hwnd);
+            }
+            let mut class = [0u16; 256];
+            let class_length = GetClassNameW(hwnd, &mut class).max(0);
+            if DESKTOP_CLASSES.contains(&String::from_utf16_lossy(&class[..class_length as usize]).as_str()) {
+                return Some(ActiveWindow::none());
+            }
+            describe(hwnd, true)
+        }
+    }
+
+    // Without `untitled` windows without a title are left out, like the hidden helper windows
+    // enumerating them turns up
+    unsafe fn describe(hwnd: HWND, untitled: bool) -> Option<ActiveWindow> {
+        let mut pid = 0;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        let mut buffer = [0u16; 512];
+        let length = GetWindowTextW(hwnd, &mut buffer);
+        let mut app = match get_process_name(pid) {
+            Ok(app) => app,
+            // Elevated and protected processes turn a non-elevated tracker away. Recording them
+            // as such keeps their time from going to the window focused before.
+            Err(e) if e.code() == E_ACCESSDENIED => {
+                let title = if length == 0 { ELEVATED_WINDOW.to_string() } else { String::from_utf16_lossy(&buffer[..length as usize]) };
+                return Some(ActiveWindow { title, app: ELEVATED_WINDOW.to_string(), class: String::new(), id: hwnd.0 as usize as u64, pid });
+            }
+            Err(_) => String::new(),
+        };
+        if length == 0 && !untitled {
+            return None;
+        }
+        if app.eq_ignore_ascii_case(FRAME_HOST) {
+            // Still the frame host while the app is starting up and has no window yet
+            if let Some((hosted_pid, package)) = hosted_app(hwnd, pid) {
+                pid = hosted_pid;
+                app = package;
+            }
+        }
+        let mut class = [0u16; 256];
+        let class_length = GetClassNameW(hwnd, &mut class).max(0);
+        Some(ActiveWindow {
+            title: String::from_utf16_lossy(&buffer[..length as usize]),
+            app,
+            class: String::from_utf16_lossy(&class[..class_length as usize]),
+            id: hwnd.0 as usize as u64,
+            pid,
+        })
+    }
+
+    // Top-level windows in z-order, without minimized ones, tool windows and the windows UWP apps
+    // and other virtual desktops keep "visible" but cloaked
+    pub fn get_visible_windows() -> Vec<ActiveWindow> {
+        unsafe extern "system" fn collect(hwnd: HWND, handles: LPARAM) -> BOOL {
+            (*(handles.0 as *mut Vec<HWND>)).push(hwnd);
+            TRUE
+        }
+
+        let mut handles: Vec<HWND> = Vec::new();
+        unsafe {
+            if EnumWindows(Some(collect), LPARAM(&mut handles as *mut Vec<HWND> as isize)).is_err() {
+                return Vec::new();
+            }
+            handles.into_iter().filter(|hwnd| is_on_screen(*hwnd)).filter_map(|hwnd| describe(hwnd, false)).collect()
+        }
+    }
+
+    // Visible windows kept above all others, which is how picture-in-picture players float
+    pub fn get_floating_windows() -> Vec<ActiveWindow> {
+        get_visible_windows().into_iter()
+            .filter(|window| unsafe {
+                GetWindowLongW(HWND(window.id as usize as *mut std::ffi::c_void), GWL_EXSTYLE) as u32 & WS_EX_TOPMOST.0 != 0
+            })
+            .collect()
+    }
+
+    unsafe fn is_on_screen(hwnd: HWND) -> bool {
+        if !IsWindowVisible(hwnd).as_bool() || IsIconic(hwnd).as_bool() {
+            return false;
+        }
+        if GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 & WS_EX_TOOLWINDOW.0 != 0 {
+            return false;
+        }
+        let mut cloaked = 0u32;
+        let queried = DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_CLOAKED,
+            &mut cloaked as *mut u32 as *mut std::ffi::c_void,
+            std::mem::size_of::<u32>() as u32,
+        );
+        !(queried.is_ok() && cloaked != 0)
+    }
+
+    // Dialogs are owned by the window they were opened from; follow the chain up to the last
+    // visible owner, skipping the hidden owner windows some frameworks create.
+    unsafe fn root_owner(mut hwnd: HWND) -> HWND {
+        while let Ok(owner) = GetWindow(hwnd, GW_OWNER) {
+            if owner.is_invalid() || !IsWindowVisible(owner).as_bool() {
+                break;
+            }
+            hwnd = owner;
+        }
+        hwnd
+    }
+
+    // The process owning the app's window inside a UWP frame, and its package name like
+    // "Microsoft.WindowsCalculator", or the executable's for an app without package identity
+    unsafe fn hosted_app(frame: HWND, host_pid: u32) -> Option<(u32, String)> {
+        struct Search {
+            host_pid: u32,
+            found: Option<u32>,
+        }
+
+        unsafe extern "system" fn visit(hwnd: HWND, search: LPARAM) -> BOOL {
+            let search = &mut *(search.0 as *mut Search);
+            let mut pid = 0;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            if pid != 0 && pid != search.host_pid {
+                search.found = Some(pid);
+                return FALSE;
+            }
+            TRUE
+        }
+
+        let mut search = Search { host_pid, found: None };
+        let _ = EnumChildWindows(frame, Some(visit), LPARAM(&mut search as *mut Search as isize));
+        let pid = search.found?;
+        let app = package_name(pid).or_else(|| get_process_name(pid).ok())?;
+        Some((pid, app))
+    }
+
+    // The package part of the app user model id "Microsoft.WindowsCalculator_8wekyb3d8bbwe!App"
+    unsafe fn package_name(pid: u32) -> Option<String> {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+
+        // Ids are at most 130 characters
+        let mut buffer = [0u16; 256];
+        let mut length = buffer.len() as u32;
+        let result = GetApplicationUserModelId(process, &mut length, PWSTR(buffer.as_mut_ptr()));
+        let _ = CloseHandle(process);
+        if result != ERROR_SUCCESS {
+            return None;
+        }
+
+        // The length includes the terminating NUL
+        let id = String::from_utf16_lossy(&buffer[..length.saturating_sub(1) as usize]);
+        id.split(['_', '!']).next().filter(|name| !name.is_empty()).map(str::to_string)
+    }
+
+    unsafe fn get_process_name(pid: u32) -> windows::core::Result<String> {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid)?;
+
+        let mut buffer = [0u16; 260];
+        let mut length = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut length);
+        let _ = CloseHandle(process);
+        result?;
+
+        let path = String::from_utf16_lossy(&buffer[..length as usize]);
+        Ok(path.rsplit('\\').next().unwrap_or_default().to_string())
+    }
+
+    pub fn checks() -> Vec<Check> {
+        let foreground = unsafe { GetForegroundWindow() };
+        if foreground.is_invalid() {
+            return vec![Check::new("foreground window", Status::Warning, "none right now, e.g. the lock screen or a UAC prompt is up")];
+        }
+        let elevated = unsafe { describe(foreground, true) }.is_some_and(|window| window.app == ELEVATED_WINDOW);
+        vec![if elevated {
+            Check::new(
+                "foreground window",
+                Status::Warning,
+                "runs elevated, its time goes to \"(elevated window)\"; run the tracker elevated or as a UIAccess build to see it",
+            )
+        } else {
+            Check::new("foreground window", Status::Ok, "GetForegroundWindow works")
+        }]
+    }
+
+    pub fn get_idle_time() -> Option<Duration> {
+        let mut info = LASTINPUTINFO { cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32, dwTime: 0 };
+        unsafe {
+            if !GetLastInputInfo(&mut info).as_bool() {
+                return None;
+            }
+            Some(Duration::from_millis(u64::from(GetTickCount().wrapping_sub(info.dwTime))))
+        }
+    }
+
+    // GDI shrinks the screen itself, with HALFTONE averaging rather than dropping pixels
+    #[cfg(feature = "thumbnails")]
+    pub fn capture_screen(max_width: u32) -> Option<crate::thumbnails::Thumbnail> {
+        use windows::Win32::Graphics::Gdi::{
+            CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC, GetDIBits, ReleaseDC, SelectObject,
+            SetStretchBltMode, StretchBlt, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, HALFTONE, SRCCOPY,
+        };
+        use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN};
+
+        let (width, height) = unsafe { (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN)) };
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        let (scaled_width, scaled_height) = crate::thumbnails::scaled_size(width as u32, height as u32, max_width);
+        let mut bgra = vec![0u8; scaled_width as usize * scaled_height as usize * 4];
+        let copied = unsafe {
+            let screen = GetDC(None);
+            let memory = CreateCompatibleDC(screen);
+            let bitmap = CreateCompatibleBitmap(screen, scaled_width as i32, scaled_height as i32);
+            let previous = SelectObject(memory, bitmap);
+            SetStretchBltMode(memory, HALFTONE);
+            let mut copied = StretchBlt(memory, 0, 0, scaled_width as i32, scaled_height as i32, screen, 0, 0, width, height, SRCCOPY).as_bool();
+            SelectObject(memory, previous);
+            if copied {
+                let mut info = BITMAPINFO {
+                    bmiHeader: BITMAPINFOHEADER {
+                        biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                        biWidth: scaled_width as i32,
+                        // Negative for rows from the top down
+                        biHeight: -(scaled_height as i32),
+                        biPlanes: 1,
+                        biBitCount: 32,
+                        biCompression: BI_RGB.0,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                copied = GetDIBits(memory, bitmap, 0, scaled_height, Some(bgra.as_mut_ptr().cast()), &mut info, DIB_RGB_COLORS) == scaled_height as i32;
+            }
+            let _ = DeleteObject(bitmap);
+            let _ = DeleteDC(memory);
+            ReleaseDC(None, screen);
+            copied
+        };
+        copied.then(|| crate::thumbnails::Thumbnail {
+            width: scaled_width,
+            height: scaled_height,
+            rgb: bgra.chunks(4).flat_map(|pixel| [pixel[2], pixel[1], pixel[0]]).collect(),
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::ActiveWindow;
+    use crate::doctor::{Check, Status};
+    use core_foundation::base::{CFType, TCFType};
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::number::CFNumber;
+    use core_foundation::string::CFString;
+    use core_graphics::display::{kCGNullWindowID, kCGWindowListExcludeDesktopElements, kCGWindowListOptionOnScreenOnly};
+    use core_graphics::window::copy_window_info;
+    use std::time::Duration;
+
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT_TYPE: u32 = !0;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state_id: i32, event_type: u32) -> f64;
+        fn CGPreflightScreenCaptureAccess() -> bool;
+    }
+
+    pub fn checks() -> Vec<Check> {
+        let window_list = match copy_window_info(kCGWindowListOptionOnScreenOnly, kCGNullWindowID) {
+            Some(_) => Check::new("window list", Status::Ok, "the window server lists on-screen windows"),
+            None => Check::new("window list", Status::Failed, "CGWindowListCopyWindowInfo returned nothing"),
+        };
+        let screen_recording = if unsafe { CGPreflightScreenCaptureAccess() } {
+            Check::new("screen recording permission", Status::Ok, "granted, window titles are visible")
+        } else {
+            Check::new(
+                "screen recording permission",
+                Status::Warning,
+                "not granted, titles are recorded as (permission denied) (System Settings > Privacy & Security > Screen Recording)",
+            )
+        };
+        vec![window_list, screen_recording]
+    }
+
+    pub fn get_idle_time() -> Option<Duration> {
+        let seconds = unsafe { CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT_TYPE) };
+        (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+    }
+
+    // Needs the same Screen Recording permission window titles do, without it only the desktop shows
+    #[cfg(feature = "thumbnails")]
+    pub fn capture_screen(max_width: u32) -> Option<crate::thumbnails::Thumbnail> {
+        use core_graphics::display::CGDisplay;
+
+        let image = CGDisplay::main().image()?;
+        if image.bits_per_pixel() != 32 {
+            return None;
+        }
+        let (width, height, row) = (image.width() as u32, image.height() as u32, image.bytes_per_row());
+        let data = image.data();
+        let bytes = data.bytes();
+        // Little-endian BGRA, as displays are captured
+        crate::thumbnails::downscale(width, height, max_width, |x, y| {
+            let at = y as usize * row + x as usize * 4;
+            bytes.get(at..at + 3).map_or([0; 3], |pixel| [pixel[2], pixel[1], pixel[0]])
+        })
+    }
+
+    type WindowInfo = CFDictionary<CFString, CFType>;
+
+    fn get_string(info: &WindowInfo, key: &'static str) -> Option<String> {
+        info.find(CFString::from_static_string(key))
+            .and_then(|value| value.downcast::<CFString>())
+            .map(|value| value.to_string())
+    }
+
+    fn get_number(info: &WindowInfo, key: &'static str) -> Option<f64> {
+        info.find(CFString::from_static_string(key))
+            .and_then(|value| value.downcast::<CFNumber>())
+            .and_then(|value| value.to_f64())
+    }
+
+    // (x, y, width, height) in screen points
+    fn get_bounds(info: &WindowInfo) -> Option<(f64, f64, f64, f64)> {
+        let bounds = info.find(CFString::from_static_string("kCGWindowBounds"))?.downcast::<CFDictionary>()?;
+        let bounds: WindowInfo = unsafe { CFDictionary::wrap_under_get_rule(bounds.as_concrete_TypeRef()) };
+        Some((
+            get_number(&bounds, "X")?,
+            get_number(&bounds, "Y")?,
+            get_number(&bounds, "Width")?,
+            get_number(&bounds, "Height")?,
+        ))
+    }
+
+    // The window server doesn't expose window ownership, so a front window counts as a dialog when the
+    // next window of the same app lies directly behind it and fully contains it, like sheets and panels do.
+    fn is_dialog_of(front: &WindowInfo, behind: &WindowInfo) -> bool {
+        let (Some((x, y, width, height)), Some((parent_x, parent_y, parent_width, parent_height))) =
+            (get_bounds(front), get_bounds(behind))
+        else {
+            return false;
+        };
+        width * height < parent_width * parent_height
+            && x >= parent_x
+            && y >= parent_y
+            && x + width <= parent_x + parent_width
+            && y + height <= parent_y + parent_height
+    }
+
+    pub fn get_active_window(separate_dialogs: bool) -> Option<ActiveWindow> {
+        let window_list = copy_window_info(kCGWindowListOptionOnScreenOnly, kCGNullWindowID)?;
+        let windows: Vec<WindowInfo> = window_list.iter()
+            .map(|info| unsafe { CFDictionary::wrap_under_get_rule(*info as CFDictionaryRef) })
+            .collect();
+        let Some(mut window_info) = windows.first() else { return Some(ActiveWindow::none()) };
+
+        if !separate_dialogs {
+            let pid = get_number(window_info, "kCGWindowOwnerPID");
+            let behind = windows.iter().skip(1).find(|info| get_number(info, "kCGWindowOwnerPID") == pid);
+            if let Some(parent) = behind.filter(|parent| is_dialog_of(window_info, parent)) {
+                window_info = parent;
+            }
+        }
+
+        describe(window_info)
+    }
+
+    // Window names need the screen recording permission, without it the title is NO_PERMISSION
+    fn describe(window_info: &WindowInfo) -> Option<ActiveWindow> {
+        let app = get_string(window_info, "kCGWindowOwnerName")?;
+        let title = match get_string(window_info, "kCGWindowName").filter(|name| !name.is_empty()) {
+            Some(name) => name,
+            None if !unsafe { CGPreflightScreenCaptureAccess() } => crate::NO_PERMISSION.to_string(),
+            None => String::new(),
+        };
+        Some(ActiveWindow {
+            title,
+            app,
+            class: String::new(),
+            id: get_number(window_info, "kCGWindowNumber").unwrap_or(0.0) as u64,
+            pid: get_number(window_info, "kCGWindowOwnerPID").unwrap_or(0.0) as u32,
+        })
+    }
+
+    // Ordinary app windows on screen, front to back; layer 0 leaves out the menu bar, Dock and overlays
+    pub fn get_visible_windows() -> Vec<ActiveWindow> {
+        let options = kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements;
+        let Some(window_list) = copy_window_info(options, kCGNullWindowID) else { return Vec::new() };
+        window_list.iter()
+            .map(|info| unsafe { CFDictionary::wrap_under_get_rule(*info as CFDictionaryRef) })
+            .filter(|info: &WindowInfo| get_number(info, "kCGWindowLayer") == Some(0.0))
+            .filter_map(|info| describe(&info))
+            .collect()
+    }
+
+    // Windows at the floating level and above but below the Dock (layer 20), which is where
+    // picture-in-picture players and "keep on top" windows sit
+    pub fn get_floating_windows() -> Vec<ActiveWindow> {
+        let options = kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements;
+        let Some(window_list) = copy_window_info(options, kCGNullWindowID) else { return Vec::new() };
+        window_list.iter()
+            .map(|info| unsafe { CFDictionary::wrap_under_get_rule(*info as CFDictionaryRef) })
+            .filter(|info: &WindowInfo| get_number(info, "kCGWindowLayer").is_some_and(|layer| (3.0..20.0).contains(&layer)))
+            .filter_map(|info| describe(&info))
+            .collect()
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "x11"))]
+mod platform {
+    use super::ActiveWindow;
+    use crate::doctor::{Check, Status};
+    use crate::sampler;
+    use std::ffi::{c_void, CStr, CString};
+    use std::os::raw::{c_char, c_int, c_long, c_uchar, c_ulong};
+    use std::process::Command;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Mutex, Once, OnceLock};
+    use std::time::{Duration, Instant};
+    use x11::xss::{XScreenSaverAllocInfo, XScreenSaverQueryExtension, XScreenSaverQueryInfo};
+    use x11::xlib::{
+        AnyPropertyType, Display, Window, XClassHint, XCloseDisplay, XErrorEvent, XFetchName, XFree, XGetClassHint, XGetInputFocus,
+        XGetTransientForHint,
+        XGetWindowProperty, XInternAtom, XDefaultRootWindow, XOpenDisplay, XSetErrorHandler, XSetIOErrorHandler,
+    };
+
+    // Waits between attempts while no X server can be reached double up to this
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    // Every query opens its own connection, so once the X server restarts (with the session, or
+    // with the compositor running XWayland) the next one reaches the new server. While none can
+    // be reached the server is looked for again with backoff instead of on every sample.
+    struct Reconnect {
+        // The display in use, None for $DISPLAY
+        name: Option<CString>,
+        failures: u32,
+        retry_at: Option<Instant>,
+    }
+
+    static RECONNECT: Mutex<Reconnect> = Mutex::new(Reconnect { name: None, failures: 0, retry_at: None });
+    // The server went away in the middle of a query
+    static CONNECTION_LOST: AtomicBool = AtomicBool::new(false);
+    static HANDLERS: Once = Once::new();
+
+    type IoErrorExitHandler = unsafe extern "C" fn(*mut Display, *mut c_void);
+    type SetIoErrorExitHandler = unsafe extern "C" fn(*mut Display, Option<IoErrorExitHandler>, *mut c_void);
+
+    extern "C" {
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    }
+
+    // XSetIOErrorExitHandler is new in libX11 1.7, looked up rather than linked so older ones still
+    // start the tracker
+    fn set_io_error_exit_handler() -> Option<SetIoErrorExitHandler> {
+        static FOUND: OnceLock<Option<SetIoErrorExitHandler>> = OnceLock::new();
+        *FOUND.get_or_init(|| {
+            // A null handle is RTLD_DEFAULT, the libraries already loaded
+            let symbol = unsafe { dlsym(std::ptr::null_mut(), c"XSetIOErrorExitHandler".as_ptr()) };
+            (!symbol.is_null()).then(|| unsafe { std::mem::transmute::<*mut c_void, SetIoErrorExitHandler>(symbol) })
+        })
+    }
+
+    // Xlib exits the process once this returns, unless the display has on_io_error_exit. Where
+    // libX11 can't be told so, the sampler thread never returns: it stays parked with the dead
+    // connection and the sampler carries on with a fresh one.
+    unsafe extern "C" fn on_io_error(_display: *mut Display) -> c_int {
+        CONNECTION_LOST.store(true, Ordering::SeqCst);
+        if set_io_error_exit_handler().is_none() && std::thread::current().name() == Some(sampler::THREAD_NAME) {
+            sampler::park();
+        }
+        0
+    }
+
+    // Called instead of exit(): the calls on the dead connection return and the query ends on the
+    // sampler thread like any other, which goes on with a fresh connection
+    unsafe extern "C" fn on_io_error_exit(_display: *mut Display, _data: *mut c_void) {}
+
+    // The default handler exits too, over as little as a window closing between two requests
+    unsafe extern "C" fn on_error(_display: *mut Display, _event: *mut XErrorEvent) -> c_int {
+        0
+    }
+
+    fn try_open(name: Option<&CString>) -> Option<*mut Display> {
+        let display = unsafe { XOpenDisplay(name.map_or(std::ptr::null(), |name| name.as_ptr())) };
+        if display.is_null() {
+            return None;
+        }
+        if let Some(set_io_error_exit_handler) = set_io_error_exit_handler() {
+            unsafe { set_io_error_exit_handler(display, Some(on_io_error_exit), std::ptr::null_mut()) };
+        }
+        Some(display)
+    }
+
+    // Closes a query's display; false when the connection died during the query, what it got is
+    // then made up
+    fn close_display(display: *mut Display) -> bool {
+        unsafe { XCloseDisplay(display) };
+        !CONNECTION_LOST.load(Ordering::SeqCst)
+    }
+
+    // Where the X server may be now: the session's DISPLAY as systemd has it, then every local socket
+    fn probe_displays() -> Vec<CString> {
+        let mut names = Vec::new();
+        if let Ok(output) = Command::new("systemctl").args(["--user", "show-environment"]).output() {
+            let environment = String::from_utf8_lossy(&output.stdout);
+            names.extend(environment.lines().filter_map(|line| line.strip_prefix("DISPLAY=")).map(str::to_string));
+        }
+        if let Ok(entries) = std::fs::read_dir("/tmp/.X11-unix") {
+            let mut sockets: Vec<String> = entries.filter_map(Result::ok)
+                .filter_map(|entry| entry.file_name().to_str()?.strip_prefix('X').map(|number| format!(":{}", number)))
+                .collect();
+            sockets.sort();
+            names.extend(sockets);
+        }
+        let mut unique = Vec::new();
+        for name in names.into_iter().filter_map(|name| CString::new(name).ok()) {
+            if !unique.contains(&name) {
+                unique.push(name);
+            }
+        }
+        unique
+    }
+
+    fn open_display() -> Option<*mut Display> {
+        HANDLERS.call_once(|| unsafe {
+            XSetErrorHandler(Some(on_error));
+            XSetIOErrorHandler(Some(on_io_error));
+        });
+        let mut reconnect = RECONNECT.lock().unwrap();
+        if CONNECTION_LOST.swap(false, Ordering::SeqCst) {
+            eprintln!("Lost the connection to the X server");
+        }
+        if reconnect.retry_at.is_some_and(|at| Instant::now() < at) {
+            return None;
+        }
+        let (display, moved_to) = match try_open(reconnect.name.as_ref()) {
+            Some(display) => (Some(display), None),
+            None => probe_displays().into_iter()
+                .filter(|name| Some(name) != reconnect.name.as_ref())
+                .find_map(|name| try_open(Some(&name)).map(|display| (Some(display), Some(name))))
+                .unwrap_or((None, None)),
+        };
+        match display {
+            Some(display) => {
+                if let Some(name) = moved_to {
+                    eprintln!("Reconnected to the X server on {}", name.to_string_lossy());
+                    reconnect.name = Some(name);
+                } else if reconnect.failures > 0 {
+                    eprintln!("Reconnected to the X server");
+                }
+                reconnect.failures = 0;
+                reconnect.retry_at = None;
+                Some(display)
+            }
+            None => {
+                if reconnect.failures == 0 {
+                    eprintln!("Cannot reach the X server, retrying with backoff");
+                }
+                reconnect.failures += 1;
+                let backoff = Duration::from_secs(1 << reconnect.failures.min(6)).min(MAX_BACKOFF);
+                reconnect.retry_at = Some(Instant::now() + backoff);
+                None
+            }
+        }
+    }
+
+    pub fn checks() -> Vec<Check> {
+        let mut checks = Vec::new();
+        let display_name = std::env::var("DISPLAY").unwrap_or_default();
+        #[cfg(feature = "wayland")]
+        checks.extend(crate::wayland::checks());
+        #[cfg(not(feature = "wayland"))]
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            checks.push(Check::new("wayland", Status::Warning, "Wayland session, only XWayland windows can be seen through X11 (--features wayland asks sway or Hyprland)"));
+        }
+
+        let Some(display) = try_open(None) else {
+            checks.push(Check::new("x11 display", Status::Failed, format!("cannot open display '{}', is DISPLAY set?", display_name)));
+            return checks;
+        };
+        checks.push(Check::new("x11 display", Status::Ok, format!("connected to '{}'", display_name)));
+
+        let (mut event_base, mut error_base) = (0, 0);
+        checks.push(if unsafe { XScreenSaverQueryExtension(display, &mut event_base, &mut error_base) } != 0 {
+            Check::new("screensaver extension", Status::Ok, "available for idle time")
+        } else {
+            Check::new("screensaver extension", Status::Warning, "missing, idle time is unavailable")
+        });
+        unsafe { XCloseDisplay(display) };
+        checks
+    }
+
+    pub fn get_idle_time() -> Option<Duration> {
+        let display = open_display()?;
+
+        let mut idle = None;
+        unsafe {
+            let info = XScreenSaverAllocInfo();
+            if !info.is_null() {
+                if XScreenSaverQueryInfo(display, XDefaultRootWindow(display), info) != 0 {
+                    idle = Some(Duration::from_millis(u64::from((*info).idle as u32)));
+                }
+                XFree(info.cast());
+            }
+        }
+        if close_display(display) { idle } else { None }
+    }
+
+    // One channel of an X pixel as 0 to 255, by the visual's mask for it
+    #[cfg(feature = "thumbnails")]
+    fn channel(pixel: c_ulong, mask: c_ulong) -> u8 {
+        if mask == 0 {
+            return 0;
+        }
+        let value = (pixel & mask) >> mask.trailing_zeros();
+        let max = mask >> mask.trailing_zeros();
+        (value * 255 / max) as u8
+    }
+
+    #[cfg(feature = "thumbnails")]
+    pub fn capture_screen(max_width: u32) -> Option<crate::thumbnails::Thumbnail> {
+        use x11::xlib::{XAllPlanes, XDestroyImage, XGetImage, XGetPixel, XGetWindowAttributes, XWindowAttributes, ZPixmap};
+
+        let display = open_display()?;
+        let thumbnail = unsafe {
+            let root = XDefaultRootWindow(display);
+            let mut attributes: XWindowAttributes = std::mem::zeroed();
+            let mut thumbnail = None;
+            if XGetWindowAttributes(display, root, &mut attributes) != 0 && attributes.width > 0 && attributes.height > 0 {
+                let (width, height) = (attributes.width as u32, attributes.height as u32);
+                let image = XGetImage(display, root, 0, 0, width, height, XAllPlanes(), ZPixmap);
+                if !image.is_null() {
+                    let (red, green, blue) = ((*image).red_mask, (*image).green_mask, (*image).blue_mask);
+                    thumbnail = crate::thumbnails::downscale(width, height, max_width, |x, y| {
+                        let pixel = XGetPixel(image, x as c_int, y as c_int);
+                        [channel(pixel, red), channel(pixel, green), channel(pixel, blue)]
+                    });
+                    XDestroyImage(image);
+                }
+            }
+            thumbnail
+        };
+        if close_display(display) { thumbnail } else { None }
+    }
+
+    pub fn get_active_window(separate_dialogs: bool) -> Option<ActiveWindow> {
+        // The compositor sees native Wayland windows as well as the XWayland ones
+        #[cfg(feature = "wayland")]
+        if let Some(window) = crate::wayland::get_active_window() {
+            return Some(window);
+        }
+        let display = open_display()?;
+
+        const POINTER_ROOT: Window = 1;
+        let mut window = 0;
+        let mut revert_to = 0;
+        unsafe { XGetInputFocus(display, &mut window, &mut revert_to) };
+        // Focus on none of the windows, or on the desktop behind them
+        let active_window = if window == 0 || window == POINTER_ROOT || window == unsafe { XDefaultRootWindow(display) } {
+            Some(ActiveWindow::none())
+        } else {
+            if !separate_dialogs {
+                window = transient_root(display, window);
+            }
+            describe(display, window, true)
+        };
+        if close_display(display) { active_window } else { None }
+    }
+
+    // Without `untitled` windows without WM_NAME are left out
+    fn describe(display: *mut Display, window: Window, untitled: bool) -> Option<ActiveWindow> {
+        let mut window_name = std::ptr::null_mut();
+        let title = if unsafe { XFetchName(display, window, &mut window_name) } <= 0 || window_name.is_null() {
+            if !untitled {
+                return None;
+            }
+            String::new()
+        } else {
+            let title = unsafe { CStr::from_ptr(window_name) }.to_string_lossy().into_owned();
+            unsafe { XFree(window_name.cast()) };
+            title
+        };
+        let pid = get_longs(display, window, c"_NET_WM_PID", 1).first().map(|pid| *pid as u32);
+        Some(ActiveWindow {
+            title,
+            app: pid.and_then(get_process_name).unwrap_or_default(),
+            class: get_class(display, window).unwrap_or_default(),
+            id: window,
+            pid: pid.unwrap_or(0),
+        })
+    }
+
+    // The res_class half of WM_CLASS, like "firefox" or "Gnome-terminal"
+    fn get_class(display: *mut Display, window: Window) -> Option<String> {
+        let mut hint = XClassHint { res_name: std::ptr::null_mut(), res_class: std::ptr::null_mut() };
+        if unsafe { XGetClassHint(display, window, &mut hint) } == 0 {
+            return None;
+        }
+        let class = (!hint.res_class.is_null()).then(|| unsafe { CStr::from_ptr(hint.res_class) }.to_string_lossy().into_owned());
+        for name in [hint.res_name, hint.res_class] {
+            if !name.is_null() {
+                unsafe { XFree(name.cast()) };
+            }
+        }
+        class
+    }
+
+    // The window manager's client list (EWMH), without windows on other desktops and minimized ones
+    pub fn get_visible_windows() -> Vec<ActiveWindow> {
+        #[cfg(feature = "wayland")]
+        if let Some(windows) = crate::wayland::get_visible_windows() {
+            return windows;
+        }
+        client_windows(None)
+    }
+
+    // Visible windows the window manager keeps above the others, like picture-in-picture players
+    pub fn get_floating_windows() -> Vec<ActiveWindow> {
+        #[cfg(feature = "wayland")]
+        if let Some(windows) = crate::wayland::get_floating_windows() {
+            return windows;
+        }
+        client_windows(Some(c"_NET_WM_STATE_ABOVE"))
+    }
+
+    // The visible client windows, only those with `state` in _NET_WM_STATE if given
+    fn client_windows(state: Option<&CStr>) -> Vec<ActiveWindow> {
+        const ALL_DESKTOPS: c_ulong = 0xFFFF_FFFF;
+        let Some(display) = open_display() else { return Vec::new() };
+
+        let root = unsafe { XDefaultRootWindow(display) };
+        let current_desktop = get_longs(display, root, c"_NET_CURRENT_DESKTOP", 1).first().copied();
+        let hidden = unsafe { XInternAtom(display, c"_NET_WM_STATE_HIDDEN".as_ptr(), 1) };
+        let wanted = state.map(|state| unsafe { XInternAtom(display, state.as_ptr(), 1) });
+        let windows = get_longs(display, root, c"_NET_CLIENT_LIST", 4096).into_iter()
+            .filter(|window| {
+                let desktop = get_longs(display, *window, c"_NET_WM_DESKTOP", 1).first().copied();
+                current_desktop.is_none() || desktop.is_none() || desktop == current_desktop || desktop == Some(ALL_DESKTOPS)
+            })
+            .filter(|window| {
+                let states = get_longs(display, *window, c"_NET_WM_STATE", 64);
+                (hidden == 0 || !states.contains(&hidden)) && wanted.is_none_or(|wanted| wanted != 0 && states.contains(&wanted))
+            })
+            .filter_map(|window| describe(display, window, false))
+            .collect();
+        if close_display(display) { windows } else { Vec::new() }
+    }
+
+    // Dialogs set WM_TRANSIENT_FOR to the window they belong to, follow it to the top-level window.
+    // The depth limit guards against clients that create a cycle.
+    fn transient_root(display: *mut Display, mut window: Window) -> Window {
+        for _ in 0..8 {
+            let mut parent = 0;
+            let found = unsafe { XGetTransientForHint(display, window, &mut parent) } != 0;
+            if !found || parent == 0 || parent == unsafe { XDefaultRootWindow(display) } {
+                break;
+            }
+            window = parent;
+        }
+        window
+    }
+
+    // A 32-bit property (CARDINAL, WINDOW or ATOM), which Xlib hands out as longs
+    fn get_longs(display: *mut Display, window: Window, name: &CStr, max_items: c_long) -> Vec<c_ulong> {
+        let mut actual_type = 0;
+        let mut actual_format: c_int = 0;
+        let mut item_count: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut property: *mut c_uchar = std::ptr::null_mut();
+
+        unsafe {
+            let atom = XInternAtom(display, name.as_ptr(), 1);
+            if atom == 0 {
+                return Vec::new();
+            }
+            XGetWindowProperty(
+                display, window, atom, 0, max_items, 0, AnyPropertyType as c_ulong,
+                &mut actual_type, &mut actual_format, &mut item_count, &mut bytes_after, &mut property,
+            );
+            if property.is_null() {
+                return Vec::new();
+            }
+            let values = if actual_format == 32 {
+                std::slice::from_raw_parts(property as *const c_ulong, item_count as usize).to_vec()
+            } else {
+                Vec::new()
+            };
+            XFree(property.cast());
+            values
+        }
+    }
+
+    fn get_process_name(pid: u32) -> Option<String> {
+        std::fs::read_to_string(format!("/proc/{}/comm", pid))
+            .ok()
+            .map(|name| name.trim_end().to_string())
+    }
+}
+
+// Wayland without X11: what the compositor's IPC tells, with no idle time
+#[cfg(all(target_os = "linux", feature = "wayland", not(feature = "x11")))]
+mod platform {
+    use super::ActiveWindow;
+    use crate::doctor::{Check, Status};
+    use std::time::Duration;
+
+    pub fn checks() -> Vec<Check> {
+        let checks = crate::wayland::checks();
+        if checks.is_empty() {
+            return vec![Check::new("backend", Status::Failed, "not a Wayland session, and this build has no X11 backend")];
+        }
+        checks
+    }
+
+    pub fn get_active_window(_separate_dialogs: bool) -> Option<ActiveWindow> {
+        crate::wayland::get_active_window()
+    }
+
+    pub fn get_visible_windows() -> Vec<ActiveWindow> {
+        crate::wayland::get_visible_windows().unwrap_or_default()
+    }
+
+    pub fn get_floating_windows() -> Vec<ActiveWindow> {
+        crate::wayland::get_floating_windows().unwrap_or_default()
+    }
+
+    pub fn get_idle_time() -> Option<Duration> {
+        None
+    }
+
+    #[cfg(feature = "thumbnails")]
+    pub fn capture_screen(_max_width: u32) -> Option<crate::thumbnails::Thumbnail> {
+        None
+    }
+}
+
+// Builds without a desktop backend still record nothing but can read and report history
+#[cfg(not(any(windows, target_os = "macos", all(target_os = "linux", any(feature = "x11", feature = "wayland")))))]
+mod platform {
+    use super::ActiveWindow;
+    use crate::doctor::{Check, Status};
+    use std::time::Duration;
+
+    pub fn checks() -> Vec<Check> {
+        vec![Check::new("backend", Status::Failed, "this build has no window backend for this platform")]
+    }
+
+    pub fn get_active_window(_separate_dialogs: bool) -> Option<ActiveWindow> {
+        None
+    }
+
+    pub fn get_visible_windows() -> Vec<ActiveWindow> {
+        Vec::new()
+    }
+
+    pub fn get_floating_windows() -> Vec<ActiveWindow> {
+        Vec::new()
+    }
+
+    pub fn get_idle_time() -> Option<Duration> {
+        None
+    }
+
+    #[cfg(feature = "thumbnails")]
+    pub fn capture_screen(_max_width: u32) -> Option<crate::thumbnails::Thumbnail> {
+        None
+    }
+}
+
+use platform::{get_active_window, get_idle_time};
+
+pub fn wt_init() {
+    let mut windows = WINDOWS.lock().unwrap();
+    windows.clear();
+    let mut last_focus_change = LAST_FOCUS_CHANGE.lock().unwrap();
+    *last_focus_change = SystemTime::now();
+    let mut current_interval = CURRENT_INTERVAL.lock().unwrap();
+    *current_interval = None;
+    hot::reset();
+    health::reset();
+    let today = if wt_is_ephemeral() { Vec::new() } else { stored_today() };
+    hot::seed_today(today.iter()
+        .flat_map(report::daily_chunks)
+        .filter(|(day, _)| *day == chrono::Local::now().date_naive())
+        .fold(0.0, |total, (_, secs)| total + secs));
+    goals::seed(&today);
+    enforce::seed(&today);
+    let mut paused = PAUSED.lock().unwrap();
+    *paused = false;
+    let mut in_call = IN_CALL.lock().unwrap();
+    *in_call = false;
+    EVICTED_WINDOWS.store(0, Ordering::Relaxed);
+}
+
+// Today's history, none when it can't be read; reports say why
+fn stored_today() -> Vec<Interval> {
+    let now = chrono::Local::now();
+    let midnight = report::local_midnight(now.date_naive());
+    storage::load_intervals(midnight.with_timezone(&chrono::Utc), now.with_timezone(&chrono::Utc)).unwrap_or_default()
+}
+
+// Nothing of the session is written to disk while this is set, not the history, the journal or
+// the control files; its intervals only reach the interval listeners, the live API and the
+// display, and are gone once the process exits. Set it before wt_init.
+pub fn wt_set_ephemeral(ephemeral: bool) {
+    EPHEMERAL.store(ephemeral, Ordering::Relaxed);
+}
+
+pub fn wt_is_ephemeral() -> bool {
+    EPHEMERAL.load(Ordering::Relaxed)
+}
+
+// Dialogs count towards the window they were opened from unless this is set
+pub fn wt_set_separate_dialogs(separate: bool) {
+    *SEPARATE_DIALOGS.lock().unwrap() = separate;
+}
+
+pub fn wt_update() {
+    if !wt_is_ephemeral() {
+        if let Err(e) = storage::flush_if_due() {
+            eprintln!("Failed to store pending intervals: {}", e);
+        }
+    }
+    if *PAUSED.lock().unwrap() {
+        return;
+    }
+    let current_time = SystemTime::now();
+
+    let separate_dialogs = *SEPARATE_DIALOGS.lock().unwrap();
+    // Measured and stripped on the sampler thread, so a dropped title never leaves it
+    let window = sampler::run("The active window query", move || {
+        get_active_window(separate_dialogs).map(|mut window| {
+            let unmeasured = unmeasured(&mut window);
+            (privacy::strip(window), unmeasured)
+        })
+    });
+    health::sampled(matches!(window, Some(Some(_))));
+    let window = measured(window);
+    enforce::sampled(&window);
+    let Some(window) = wt_filter_window(window) else {
+        // An ignored window ends the open interval, and its time is credited to no one
+        if CURRENT_INTERVAL.lock().unwrap().is_some() {
+            if let Err(e) = close_open_interval() {
+                eprintln!("{}", e);
+            }
+        }
+        *LAST_FOCUS_CHANGE.lock().unwrap() = current_time;
+        return;
+    };
+
+    add_or_update_window(&window, current_time);
+    games::sampled(&window);
+    let mut throttle = TITLE_THROTTLE.lock().unwrap();
+    let mut events = Vec::new();
+    let mut switched = false;
+    if let Some((event, finished)) = record_interval(&window, current_time) {
+        if let Some(interval) = finished {
+            notify_interval_listeners(&interval);
+        }
+        switched = event == EventKind::FocusChanged;
+        events = throttle.event(event, &window, Instant::now());
+    }
+    events.extend(throttle.due(Instant::now()));
+    drop(throttle);
+    for (event, window) in events {
+        for listener in FOCUS_LISTENERS.lock().unwrap().iter() {
+            listener(event, &window);
+        }
+    }
+    if switched {
+        icons::focus_changed(&window);
+        #[cfg(feature = "thumbnails")]
+        thumbnails::focus_changed(window.title == ignore::REDACTED, current_time);
+    }
+}
+
+// The sample as it is tracked, with what couldn't be measured as its own bucket and counted
+fn measured(sample: Option<Option<(ActiveWindow, Option<health::Unmeasured>)>>) -> ActiveWindow {
+    match sample {
+        Some(Some((window, unmeasured))) => {
+            if let Some(kind) = unmeasured {
+                health::unmeasured(kind);
+            }
+            window
+        }
+        Some(None) => {
+            health::unmeasured(health::Unmeasured::Failed);
+            ActiveWindow::placeholder(SAMPLING_FAILED)
+        }
+        None => {
+            health::unmeasured(health::Unmeasured::NotResponding);
+            ActiveWindow::placeholder(NOT_RESPONDING)
+        }
+    }
+}
+
+// What couldn't be measured of a window the platform returned, told before privacy::strip drops
+// its title; an untitled one gets the placeholder title
+fn unmeasured(window: &mut ActiveWindow) -> Option<health::Unmeasured> {
+    #[cfg(windows)]
+    let denied = window.app == platform::ELEVATED_WINDOW;
+    #[cfg(not(windows))]
+    let denied = window.title == NO_PERMISSION;
+    if denied {
+        Some(health::Unmeasured::Denied)
+    } else if window.title.trim().is_empty() {
+        window.title = NO_TITLE.to_string();
+        Some(health::Unmeasured::Untitled)
+    } else {
+        None
+    }
+}
+
+pub fn wt_add_focus_listener(listener: FocusListener) {
+    FOCUS_LISTENERS.lock().unwrap().push(listener);
+}
+
+pub fn wt_add_interval_listener(listener: IntervalListener) {
+    INTERVAL_LISTENERS.lock().unwrap().push(listener);
+}
+
+// Runs before the config's plugins on every interval stored from then on
+pub fn wt_add_enricher(enricher: Box<dyn Enricher>) {
+    ENRICHERS.lock().unwrap().add(enricher);
+}
+
+// The interval as the enrichers leave it, just before it is stored
+pub fn wt_enrich(interval: &mut Interval) {
+    ENRICHERS.lock().unwrap().enrich(interval);
+}
+
+fn notify_interval_listeners(interval: &Interval) {
+    hot::interval_stored(interval);
+    goals::interval_stored(interval);
+    games::interval_stored(interval);
+    for listener in INTERVAL_LISTENERS.lock().unwrap().iter() {
+        listener(interval);
+    }
+}
+
+// Stops recording until wt_resume; the open interval is stored up to now
+pub fn wt_pause() -> Result<(), String> {
+    enforce::check_pause()?;
+    let mut paused = PAUSED.lock().unwrap();
+    if *paused {
+        return Ok(());
+    }
+    *paused = true;
+    drop(paused);
+    close_open_interval()
+}
+
+pub fn wt_resume() {
+    *PAUSED.lock().unwrap() = false;
+    // The paused time must not be credited to whatever window has focus on resume
+    *LAST_FOCUS_CHANGE.lock().unwrap() = SystemTime::now();
+}
+
+pub fn wt_is_paused() -> bool {
+    *PAUSED.lock().unwrap()
+}
+
+// Whether the config's categories and ignore rules can be used
+fn check_rules(config: &config::Config) -> Result<(), String> {
+    Categorizer::from_config(config)?;
+    WindowFilter::from_config(config)?;
+    TitleParser::from_config(config)?;
+    RemoteLabels::from_config(config)?;
+    Enrichers::check(config)?;
+    schedule::Schedule::from_config(config)?;
+    goals::check(config)?;
+    thumbnails::check(config)?;
+    media::check(config)?;
+    dnd::check(config)?;
+    power::check(config)?;
+    enforce::check(config)?;
+    #[cfg(feature = "server")]
+    api::check(&config.health.clone().unwrap_or_default())?;
+    Merges::from_config(config)?;
+    Ok(())
+}
+
+fn apply_rules(config: &config::Config) -> Result<(), String> {
+    let categorizer = Categorizer::from_config(config)?;
+    let title_parser = TitleParser::from_config(config)?;
+    schedule::Schedule::from_config(config)?;
+    Enrichers::check(config)?;
+    goals::check(config)?;
+    thumbnails::check(config)?;
+    media::check(config)?;
+    dnd::check(config)?;
+    power::check(config)?;
+    enforce::check(config)?;
+    #[cfg(feature = "server")]
+    api::check(&config.health.clone().unwrap_or_default())?;
+    let merges = Merges::from_config(config)?;
+    *WINDOW_FILTER.lock().unwrap() = WindowFilter::from_config(config)?;
+    *MERGES.lock().unwrap() = merges;
+    *REMOTE_LABELS.lock().unwrap() = RemoteLabels::from_config(config)?;
+    *CATEGORIZER.lock().unwrap() = categorizer;
+    *TITLE_PARSER.lock().unwrap() = title_parser;
+    ENRICHERS.lock().unwrap().configure(config);
+    TITLE_THROTTLE.lock().unwrap().set_period(Duration::from_millis(config.title_debounce_ms));
+    *AGGREGATE_BY.lock().unwrap() = config.grouping();
+    privacy::configure(config);
+    icons::configure(config);
+    goals::configure(config);
+    games::configure(config);
+    thumbnails::configure(config);
+    media::configure(config);
+    dnd::configure(config);
+    power::configure(config);
+    enforce::configure(config);
+    storage::configure_writes(&config.storage.clone().unwrap_or_default());
+    publish_window_count(&WINDOWS.lock().unwrap());
+    humanize::configure(config);
+    health::configure(&config.health.clone().unwrap_or_default());
+    #[cfg(feature = "server")]
+    api::configure(&config.health.clone().unwrap_or_default());
+    Ok(())
+}
+
+// Ends the open interval in the current profile, then moves to the other one. The previous profile
+// stays active when the new one's config can't be used.
+fn switch_profile(name: &str) -> Result<config::Config, String> {
+    profile::validate(name)?;
+    close_open_interval()?;
+
+    let previous = profile::name();
+    profile::set_active(name)?;
+    let new_config = config::load().and_then(|config| check_rules(&config).map(|_| config));
+    match new_config {
+        Ok(config) => {
+            if !wt_is_ephemeral() {
+                control::record_running_profile();
+            }
+            Ok(config)
+        }
+        Err(e) => {
+            profile::set_active(&previous)?;
+            Err(e)
+        }
+    }
+}
+
+fn close_open_interval() -> Result<(), String> {
+    if wt_is_ephemeral() {
+        let now = SystemTime::now();
+        let open = CURRENT_INTERVAL.lock().unwrap().take();
+        hot::set_current(None, now);
+        if let Some(interval) = open.and_then(|open| store_interval(build_interval(&open, now))) {
+            notify_interval_listeners(&interval);
+        }
+        return Ok(());
+    }
+    checkpoint_open_interval(SystemTime::now());
+    *CURRENT_INTERVAL.lock().unwrap() = None;
+    hot::set_current(None, SystemTime::now());
+    // Going through the journal stores the interval exactly as a crash recovery would
+    if let Some(interval) = journal::recover().map_err(|e| format!("failed to store the open interval: {}", e))? {
+        notify_interval_listeners(&interval);
+    }
+    storage::flush().map_err(|e| format!("failed to store pending intervals: {}", e))
+}
+
+pub fn add_or_update_window(window: &ActiveWindow, current_time: SystemTime) {
+    let category = wt_categorize(window);
+    let mut windows = WINDOWS.lock().unwrap();
+    let mut last_focus_change = LAST_FOCUS_CHANGE.lock().unwrap();
+    let elapsed_time = last_focus_change.elapsed().unwrap_or(Duration::from_secs(0)).as_secs_f64();
+
+    let key = (window.key(), window.title.clone());
+    if let Some(record) = windows.get_mut(&key) {
+        record.focus_time += elapsed_time;
+        record.last_focused = current_time;
+    } else {
+        windows.insert(key, WindowRecord {
+            key: window.key(),
+            title: window.title.clone(),
+            app: window.app.clone(),
+            class: window.class.clone(),
+            category,
+            focus_time: elapsed_time,
+            first_seen: current_time,
+            last_focused: current_time,
+        });
+        publish_window_count(&windows);
+    }
+
+    *last_focus_change = current_time;
+}
+
+fn build_interval(open: &OpenInterval, end: SystemTime) -> Interval {
+    let window = &open.window;
+    let mut interval = Interval::new(&window.title, &window.app, open.start, end);
+    interval.window_id = window.key();
+    interval.class = window.class.clone();
+    interval.attributes = wt_title_fields(window);
+    if let Some(game) = games::detect(window) {
+        interval.attributes.insert(games::GAME.to_string(), game.name);
+        interval.attributes.extend(game.steam_appid.map(|appid| (games::STEAM_APPID.to_string(), appid)));
+    }
+    if let Some(media) = &open.media {
+        interval.attributes.insert(media::MEDIA.to_string(), media.title.clone());
+        interval.attributes.extend(media.artist.clone().map(|artist| (media::MEDIA_ARTIST.to_string(), artist)));
+    }
+    if let Some(dnd) = open.dnd {
+        interval.attributes.insert(dnd::DND.to_string(), if dnd { dnd::ON } else { dnd::OFF }.to_string());
+    }
+    if let Some(on_battery) = open.power {
+        interval.attributes.insert(power::POWER.to_string(), if on_battery { power::BATTERY } else { power::AC }.to_string());
+    }
+    interval.event = open.event;
+    interval.category = wt_categorize(window);
+    interval.call = open.call;
+    interval
+}
+
+fn store_interval(mut interval: Interval) -> Option<Interval> {
+    wt_enrich(&mut interval);
+    if wt_is_ephemeral() {
+        return Some(interval);
+    }
+    match storage::queue_interval(&interval) {
+        Ok(()) => Some(interval),
+        Err(e) => {
+            eprintln!("Failed to record interval: {}", e);
+            None
+        }
+    }
+}
+
+// The window as the ignore rules let it be recorded, None when it isn't recorded at all. Remote
+// clients are labeled first, so the rules can match "RDP: host".
+pub fn wt_filter_window(window: ActiveWindow) -> Option<ActiveWindow> {
+    let window = REMOTE_LABELS.lock().unwrap().apply(window);
+    WINDOW_FILTER.lock().unwrap().apply(window)
+}
+
+// The fields the title templates extract from this window's title
+pub fn wt_title_fields(window: &ActiveWindow) -> BTreeMap<String, String> {
+    TITLE_PARSER.lock().unwrap().parse(&window.title, &window.app)
+}
+
+// The category an interval in this window would be recorded under
+pub fn wt_categorize(window: &ActiveWindow) -> String {
+    if let Some(category) = RECATEGORIZED.lock().unwrap().get(&window.app.to_lowercase()) {
+        return category.clone();
+    }
+    let fields = wt_title_fields(window);
+    CATEGORIZER.lock().unwrap().categorize(&window.title, &window.app, &fields).to_string()
+}
+
+// Until the tracker exits the app's windows are recorded under `category` whatever the rules say,
+// the open interval included
+pub fn wt_recategorize(app: &str, category: &str) {
+    RECATEGORIZED.lock().unwrap().insert(app.to_lowercase(), category.to_string());
+}
+
+// Closes the open interval when focus moves to a different window or title and appends it to the history.
+// Returns what opened the new interval, if one was opened, and the interval that was stored.
+fn record_interval(window: &ActiveWindow, current_time: SystemTime) -> Option<(EventKind, Option<Interval>)> {
+    let mut current_interval = CURRENT_INTERVAL.lock().unwrap();
+    let (event, finished) = match current_interval.as_ref() {
+        Some(open) if open.window.key() == window.key() && open.window.title == window.title => return None,
+        Some(open) => {
+            let stored = store_interval(build_interval(open, current_time));
+            // Same window, new title: a new page or document rather than a switch
+            let event = if open.window.key() == window.key() { EventKind::TitleChanged } else { EventKind::FocusChanged };
+            (event, stored)
+        }
+        None => (EventKind::FocusChanged, None),
+    };
+
+    hot::count_event(event);
+
+    let open = OpenInterval { window: window.clone(), start: current_time, event, call: *IN_CALL.lock().unwrap(), media: media::playing_in(window), dnd: dnd::active(), power: power::on_battery() };
+    // Replace the journal right away so a crash can't replay the interval that was just stored
+    write_journal(&build_interval(&open, current_time));
+    *current_interval = Some(open);
+    hot::set_current(Some(window), current_time);
+    Some((event, finished))
+}
+
+// A call starting or ending closes the open interval and continues the window in a new one, so
+// each interval is either all in a call or not at all
+pub fn wt_set_in_call(in_call: bool) {
+    {
+        let mut call = IN_CALL.lock().unwrap();
+        if *call == in_call {
+            return;
+        }
+        *call = in_call;
+    }
+
+    continue_open_interval(|open, start| Some(OpenInterval {
+        window: open.window.clone(),
+        start,
+        event: EventKind::CallChanged,
+        call: in_call,
+        media: open.media.clone(),
+        dnd: open.dnd,
+        power: open.power,
+    }));
+}
+
+// Stores the open interval up to now and goes on with the one `next` makes from it, starting now;
+// nothing changes when it makes none
+fn continue_open_interval(next: impl FnOnce(&OpenInterval, SystemTime) -> Option<OpenInterval>) {
+    let current_time = SystemTime::now();
+    let mut current_interval = CURRENT_INTERVAL.lock().unwrap();
+    let Some(open) = current_interval.as_ref() else { return };
+    let Some(next) = next(open, current_time) else { return };
+    let stored = store_interval(build_interval(open, current_time));
+    write_journal(&build_interval(&next, current_time));
+    *current_interval = Some(next);
+    hot::restart_focus(current_time);
+    drop(current_interval);
+    if let Some(interval) = stored {
+        notify_interval_listeners(&interval);
+    }
+}
+
+// What plays in the open interval's window changed: it is stored up to now and the window goes on
+// in a new interval, as on a title change
+pub fn wt_media_changed() {
+    continue_open_interval(|open, start| {
+        let media = media::playing_in(&open.window);
+        (media != open.media).then(|| OpenInterval {
+            window: open.window.clone(),
+            start,
+            event: EventKind::TitleChanged,
+            call: open.call,
+            media,
+            dnd: open.dnd,
+            power: open.power,
+        })
+    });
+}
+
+// Do Not Disturb was turned on or off: the open interval is split like on a title change, so
+// each is all with it on or all with it off
+pub fn wt_dnd_changed() {
+    continue_open_interval(|open, start| {
+        let dnd = dnd::active();
+        (dnd != open.dnd).then(|| OpenInterval {
+            window: open.window.clone(),
+            start,
+            event: EventKind::TitleChanged,
+            call: open.call,
+            media: open.media.clone(),
+            dnd,
+            power: open.power,
+        })
+    });
+}
+
+// The machine went on battery or back on AC: the open interval is split like on a title change,
+// so each is all on one power source
+pub fn wt_power_changed() {
+    continue_open_interval(|open, start| {
+        let power = power::on_battery();
+        (power != open.power).then(|| OpenInterval {
+            window: open.window.clone(),
+            start,
+            event: EventKind::TitleChanged,
+            call: open.call,
+            media: open.media.clone(),
+            dnd: open.dnd,
+            power,
+        })
+    });
+}
+
+fn checkpoint_open_interval(current_time: SystemTime) {
+    let current_interval = CURRENT_INTERVAL.lock().unwrap();
+    if let Some(open) = current_interval.as_ref() {
+        write_journal(&build_interval(open, current_time));
+    }
+}
+
+fn write_journal(interval: &Interval) {
+    if wt_is_ephemeral() {
+        return;
+    }
+    if let Err(e) = journal::write(interval) {
+        eprintln!("Failed to write journal: {}", e);
+    }
+}
+
+// How long the current window has held focus
+pub fn wt_get_focus_duration() -> Duration {
+    hot::focus_duration()
+}
+
+// The window the open interval belongs to
+pub fn wt_get_current_window() -> Option<ActiveWindow> {
+    hot::current().map(|window| (*window).clone())
+}
+
+// The open interval as it would be stored now
+pub fn wt_get_open_interval() -> Option<Interval> {
+    CURRENT_INTERVAL.lock().unwrap().as_ref().map(|open| build_interval(open, SystemTime::now()))
+}
+
+// Changes whenever the current window or its title does, cheaper to poll than the window itself
+pub fn wt_get_current_id() -> u64 {
+    hot::current_id()
+}
+
+// Focus time today, from the history when tracking started plus what was recorded since
+pub fn wt_get_today_secs() -> f64 {
+    hot::today_secs()
+}
+
+// Window switches and same-window title changes since wt_init
+pub fn wt_get_event_counts() -> EventCounts {
+    let (switches, title_changes) = hot::event_counts();
+    EventCounts { switches, title_changes }
+}
+
+// Time since the last keyboard or mouse input, when the platform can tell
+pub fn wt_get_idle_time() -> Option<Duration> {
+    sampler::run("The idle time query", get_idle_time).flatten()
+}
+
+// The number of entries wt_get_all_windows has
+pub fn wt_get_window_count() -> usize {
+    hot::window_count()
+}
+
+fn publish_window_count(windows: &HashMap<(String, String), WindowRecord>) {
+    let aggregate_by = *AGGREGATE_BY.lock().unwrap();
+    let keys: HashSet<&str> = windows.values().map(|record| record.aggregate_key(aggregate_by)).collect();
+    hot::set_window_count(keys.len());
+}
+
+pub fn wt_get_window_info(index: usize) -> Option<WindowTime> {
+    wt_get_all_windows().into_iter().nth(index)
+}
+
+// Focus time per title summed across every window that showed it, or per app, class or category
+// with aggregate_by in the config
+pub fn wt_get_all_windows() -> Vec<WindowTime> {
+    let aggregate_by = *AGGREGATE_BY.lock().unwrap();
+    let merges = MERGES.lock().unwrap();
+    let windows = WINDOWS.lock().unwrap();
+    let mut titles: HashMap<&str, f64> = HashMap::new();
+    for record in windows.values() {
+        let key = match aggregate_by {
+            AggregateBy::Title => merges.bucket(&record.title, &record.app),
+            _ => record.aggregate_key(aggregate_by),
+        };
+        *titles.entry(key).or_insert(0.0) += record.focus_time;
+    }
+    titles.into_iter()
+        .map(|(title, focus_secs)| WindowTime { title: title.to_string(), focus_secs })
+        .collect()
+}
+
+// Focus time per window, labelled with the title it showed the longest
+pub fn wt_get_window_instances() -> Vec<WindowRecord> {
+    let windows = WINDOWS.lock().unwrap();
+    let mut instances: HashMap<&str, (WindowRecord, f64)> = HashMap::new();
+    for record in windows.values() {
+        match instances.get_mut(record.key.as_str()) {
+            Some((instance, longest)) => {
+                instance.focus_time += record.focus_time;
+                instance.first_seen = instance.first_seen.min(record.first_seen);
+                instance.last_focused = instance.last_focused.max(record.last_focused);
+                if record.focus_time > *longest {
+                    instance.title = record.title.clone();
+                    *longest = record.focus_time;
+                }
+            }
+            None => {
+                instances.insert(&record.key, (record.clone(), record.focus_time));
+            }
+        }
+    }
+    instances.into_values().map(|(instance, _)| instance).collect()
+}
+
+// Drops windows and titles not focused within `evict_after_days`, then the least recently focused
+// beyond `max_windows`. Their time is in the history already; the open interval's window stays.
+pub fn wt_evict_stale_windows(memory: &config::MemoryConfig, now: SystemTime) -> usize {
+    let current = wt_get_current_window().map(|window| (window.key(), window.title));
+    let mut windows = WINDOWS.lock().unwrap();
+    let before = windows.len();
+    let stale_after = Duration::from_secs(memory.evict_after_days * 24 * 60 * 60);
+    windows.retain(|key, record| {
+        Some(key) == current.as_ref() || now.duration_since(record.last_focused).unwrap_or_default() < stale_after
+    });
+    if windows.len() > memory.max_windows {
+        let mut by_age: Vec<(SystemTime, (String, String))> = windows.iter()
+            .filter(|(key, _)| Some(*key) != current.as_ref())
+            .map(|(key, record)| (record.last_focused, key.clone()))
+            .collect();
+        by_age.sort();
+        for (_, key) in by_age.into_iter().take(windows.len() - memory.max_windows) {
+            windows.remove(&key);
+        }
+    }
+    let evicted = before - windows.len();
+    publish_window_count(&windows);
+    EVICTED_WINDOWS.fetch_add(evicted as u64, Ordering::Relaxed);
+    evicted
+}
+
+// Windows and titles wt_evict_stale_windows has dropped since wt_init
+pub fn wt_get_evicted_windows() -> u64 {
+    EVICTED_WINDOWS.load(Ordering::Relaxed)
+}
+
+pub fn wt_cleanup() {
+    let mut windows = WINDOWS.lock().unwrap();
+    windows.clear();
+    hot::set_window_count(0);
+    if wt_is_ephemeral() {
+        return;
+    }
+    if let Err(e) = storage::flush() {
+        eprintln!("Failed to store pending intervals: {}", e);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrackOptions {
+    pub low_power: bool,
+    pub separate_dialogs: bool,
+    // Newline-delimited JSON events on stdout instead of the status display
+    pub stream: bool,
+    // Also record every window on screen, see visibility
+    pub visible: bool,
+    // Keep the session in memory only, see wt_set_ephemeral
+    pub ephemeral: bool,
+    // A status item in the macOS menu bar instead of the status display, see menubar
+    pub menubar: bool,
+}
+
+impl TrackOptions {
+    pub fn from_args(args: &cli::Args) -> Result<Self, String> {
+        let stream = match args.value("--stream") {
+            None => args.json_output()?,
+            Some("json") => true,
+            Some(other) => return Err(format!("unknown stream format '{}', expected 'json'", other)),
+        };
+        Ok(TrackOptions {
+            low_power: args.flag("--low-power"),
+            separate_dialogs: args.flag("--separate-dialogs"),
+            stream,
+            visible: args.flag("--visible"),
+            ephemeral: args.flag("--ephemeral"),
+            menubar: args.flag("--menubar"),
+        })
+    }
+}
+
+pub fn track(options: TrackOptions) -> Result<(), String> {
+    if options.menubar {
+        #[cfg(all(feature = "menubar", target_os = "macos"))]
+        return menubar::run(options);
+        #[cfg(not(all(feature = "menubar", target_os = "macos")))]
+        return Err("--menubar needs macOS and a build with --features menubar".to_string());
+    }
+    if !options.stream {
+        run_session(options, &AtomicBool::new(false), print_status)?;
+    } else {
+        stream::install();
+        run_session(options, &AtomicBool::new(false), |_| {})?;
+    }
+    match control::restart_request() {
+        Some(executable) => relaunch(&executable),
+        None => Ok(()),
+    }
+}
+
+// Runs the executable self-update installed with this process's arguments: in place of this process
+// on Unix, keeping the pid a service manager watches, and as a new one taking over the console on
+// Windows
+fn relaunch(executable: &std::path::Path) -> Result<(), String> {
+    let mut command = std::process::Command::new(executable);
+    command.args(std::env::args_os().skip(1));
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        Err(format!("failed to restart into {}: {}", executable.display(), command.exec()))
+    }
+    #[cfg(not(unix))]
+    {
+        command.spawn().map_err(|e| format!("failed to restart into {}: {}", executable.display(), e))?;
+        Ok(())
+    }
+}
+
+fn print_status(config: &config::Config) {
+    println!("\n{}", i18n::tr("status-title", &[]));
+    println!("{}", i18n::tr("status-windows", &[("count", &wt_get_window_count())]));
+    let event_counts = wt_get_event_counts();
+    println!("{}", report::switches_line(event_counts.switches as usize, event_counts.title_changes as usize));
+    let evicted = wt_get_evicted_windows();
+    if evicted > 0 {
+        println!("{}", i18n::tr("status-evicted", &[("count", &evicted)]));
+    }
+
+    // Display all windows and their times, noting titles shared by several windows
+    let aggregate_by = *AGGREGATE_BY.lock().unwrap();
+    let styles = CategoryStyles::from_config(config);
+    for window in wt_get_all_windows() {
+        let name = if aggregate_by == AggregateBy::Rule { styles.label(&window.title) } else { window.title.clone() };
+        println!("{}", report::GroupBy::from(aggregate_by).item_line(&name));
+        println!("  {}", i18n::tr("report-focus-time", &[("time", &humanize::duration(window.focus_secs))]));
+        let shared_by = WINDOWS.lock().unwrap().keys().filter(|(_, other)| *other == window.title).count();
+        if aggregate_by == AggregateBy::Title && shared_by > 1 {
+            println!("  {}", i18n::tr("status-shared", &[("count", &shared_by)]));
+        }
+    }
+
+    let goals = goals::progress();
+    if !goals.is_empty() {
+        println!("{}", i18n::tr("status-goals", &[]));
+        for progress in &goals {
+            println!("  {}", goals::line(progress, &styles.label(&progress.category)));
+        }
+    }
+}
+
+// Tracks until `stop` is set, calling `status` with the current config about once a second, then
+// stores the open interval. The tracking state is global, so only one session can run in a process at a time.
+pub fn run_session(options: TrackOptions, stop: &AtomicBool, mut status: impl FnMut(&config::Config)) -> Result<(), String> {
+    if options.ephemeral && options.visible {
+        return Err("--visible records every window on screen to disk, it can't be combined with --ephemeral".to_string());
+    }
+    wt_set_ephemeral(options.ephemeral);
+    // A profile given on the command line wins over the triggers until they match something else
+    let mut profile_triggers = triggers::ProfileTriggers::start();
+    if let Some(name) = profile_triggers.current().filter(|_| !profile::is_explicit()) {
+        profile::set_active(name)?;
+        if options.stream {
+            stream::profile_switched(name);
+        } else {
+            eprintln!("Using profile '{}' from a profile trigger", name);
+        }
+    }
+
+    let mut config = config::load()?;
+    apply_rules(&config)?;
+    if let Some(otlp) = &config.otlp {
+        #[cfg(feature = "otlp")]
+        otlp::start(otlp);
+        #[cfg(not(feature = "otlp"))]
+        return Err(format!("exporting to {} needs OTLP support, rebuild with --features otlp", otlp.endpoint));
+    }
+    if let Some(influx) = &config.influx {
+        influx::start(influx)?;
+    }
+    // Pushing would send the stored history off the machine and keep where it got to on disk
+    if let Some(hub) = config.hub.as_ref().filter(|_| !options.ephemeral) {
+        #[cfg(feature = "hub")]
+        hub::start(hub)?;
+        #[cfg(not(feature = "hub"))]
+        return Err(format!("pushing to the hub at {} needs hub support, rebuild with --features hub", hub.url));
+    }
+    if let Some(statsd) = &config.statsd {
+        statsd::start(statsd)?;
+    }
+    #[cfg(feature = "status")]
+    if let Some(status) = &config.status {
+        presence::start(status, config.idle_secs);
+    }
+    #[cfg(not(feature = "status"))]
+    if config.status.is_some() {
+        return Err("setting the chat status needs --features status, or remove [status] from config.toml".to_string());
+    }
+    #[cfg(feature = "websocket")]
+    if let Some(websocket) = &config.websocket {
+        websocket::start(websocket)?;
+    }
+    #[cfg(not(feature = "websocket"))]
+    if let Some(websocket) = &config.websocket {
+        return Err(format!("serving events on {} needs --features websocket", websocket.address));
+    }
+
+    #[cfg(feature = "server")]
+    api::serve(&config.health.clone().unwrap_or_default())?;
+    #[cfg(not(feature = "server"))]
+    if let Some(address) = config.health.as_ref().and_then(|health| health.address.as_ref()) {
+        return Err(format!("serving the API on {} needs --features server", address));
+    }
+
+    #[cfg(not(feature = "thumbnails"))]
+    if config.thumbnails.is_some() {
+        return Err("[thumbnails] screenshots need --features thumbnails, or remove [thumbnails] from config.toml".to_string());
+    }
+
+    #[cfg(feature = "media")]
+    if config.media.is_some() {
+        media::start();
+    }
+    #[cfg(not(feature = "media"))]
+    if config.media.is_some() {
+        return Err("[media] titles need --features media, or remove [media] from config.toml".to_string());
+    }
+    if config.dnd.is_some() {
+        dnd::start();
+    }
+    if config.power.is_some() {
+        power::start();
+    }
+    #[cfg(not(feature = "enforce"))]
+    if config.enforce.is_some() {
+        return Err("[enforce] limits need --features enforce, or remove [enforce] from config.toml".to_string());
+    }
+
+    #[cfg(not(feature = "notify"))]
+    if config.greeting.is_some() {
+        return Err("the [greeting] notification needs --features notify, or remove [greeting] from config.toml".to_string());
+    }
+    #[cfg(not(feature = "notify"))]
+    if config.alerts.is_some() {
+        return Err("[alerts] notifications need --features notify, or remove [alerts] from config.toml".to_string());
+    }
+
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    if let Err(e) = dbus::start() {
+        eprintln!("{}", e);
+    }
+
+    // An earlier session's unfinished interval waits for the next one that stores anything
+    match if options.ephemeral { Ok(None) } else { journal::recover() } {
+        Ok(Some(interval)) if options.stream => stream::interval_finalized(&interval),
+        Ok(Some(interval)) => println!("Recovered {} of '{}' from an unfinished session", humanize::duration(interval.duration_secs()), interval.title),
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to recover journal: {}", e),
+    }
+
+    wt_init();
+    wt_set_separate_dialogs(options.separate_dialogs);
+    let mut update_interval = polling::MIN_INTERVAL;  // Adapts between 50ms and 2s (5s in low-power mode)
+    let display_interval = StdDuration::from_secs(1);     // Update display every second
+    let mut checkpoint_interval = StdDuration::from_secs(config.checkpoint_secs.max(1));
+    let mut last_display = Instant::now();
+    let mut last_checkpoint = Instant::now();
+    let mut pause_watch = control::PauseWatch::default();
+    let mut visibility_watch = options.visible.then(|| visibility::VisibilityWatch::new(visibility::Layer::Visible));
+    let mut floating_watch = (!options.ephemeral).then(|| visibility::VisibilityWatch::new(visibility::Layer::Floating));
+    let mut call_watch = calls::CallWatch::default();
+    let mut media_watch = media::MediaWatch::default();
+    let mut dnd_watch = dnd::DndWatch::default();
+    let mut power_watch = power::PowerWatch::default();
+    let mut enforce_watch = enforce::EnforceWatch::default();
+    let mut idle_watch = stream::IdleWatch::default();
+    let mut greeting_watch = greeting::GreetingWatch::default();
+    let mut alert_watch = alerts::AlertWatch::default();
+    let mut schedule_watch = schedule::ScheduleWatch::default();
+    if options.ephemeral {
+        eprintln!("Ephemeral session: nothing is written to disk and everything tracked is gone on exit");
+        if !config.schedule.is_empty() {
+            eprintln!("Scheduled reports don't run in an ephemeral session");
+        }
+        if config.hub.is_some() {
+            eprintln!("Nothing is pushed to the hub in an ephemeral session");
+        }
+    } else {
+        control::record_running_profile();
+        control::record_heartbeat(config.checkpoint_secs);
+    }
+    // A request left over from before this session doesn't apply to it
+    let _ = control::take_switch_request();
+    if !options.ephemeral {
+        if let Err(e) = ipc::serve(&config.ipc.clone().unwrap_or_default(), control::handle_request) {
+            eprintln!("{}, client commands go through the control files", e);
+        }
+    }
+    let mut config_watch = reload::ConfigWatch::start()
+        .map_err(|e| eprintln!("Config changes need a restart: {}", e))
+        .ok();
+
+    while !stop.load(Ordering::Relaxed) {
+        wt_update();  // Update window tracking
+
+        // Journal the open interval so a crash loses at most one checkpoint interval
+        if last_checkpoint.elapsed() >= checkpoint_interval {
+            checkpoint_open_interval(SystemTime::now());
+            wt_evict_stale_windows(&config.memory.clone().unwrap_or_default(), SystemTime::now());
+            if !options.ephemeral {
+                control::record_heartbeat(config.checkpoint_secs);
+            }
+            last_checkpoint = Instant::now();
+        }
+
+        // Only display updates every second
+        if last_display.elapsed() >= display_interval {
+            pause_watch.check();
+            if let Some(watch) = &mut visibility_watch {
+                watch.check(SystemTime::now());
+            }
+            if let Some(watch) = &mut floating_watch {
+                watch.check(SystemTime::now());
+            }
+            call_watch.check(&config);
+            media_watch.check();
+            dnd_watch.check();
+            power_watch.check();
+            enforce_watch.check();
+            greeting_watch.check(&config, wt_get_idle_time());
+            alert_watch.check(&config, wt_get_idle_time());
+            if !options.ephemeral {
+                schedule_watch.check(&config);
+            }
+            if !wt_is_paused() {
+                health::check(wt_get_idle_time());
+            }
+            if stream::is_active() {
+                idle_watch.check(wt_get_idle_time(), StdDuration::from_secs(config.idle_secs));
+            }
+            if control::restart_request().is_some() {
+                break;
+            }
+            if let Some(name) = control::take_switch_request().or_else(|| profile_triggers.check()) {
+                // What was on screen so far belongs to the profile being left
+                if let Some(watch) = &mut visibility_watch {
+                    watch.finish(SystemTime::now());
+                }
+                if let Some(watch) = &mut floating_watch {
+                    watch.finish(SystemTime::now());
+                }
+                match switch_profile(&name) {
+                    Ok(new_config) => {
+                        stream::profile_switched(&name);
+                        if !options.stream {
+                            eprintln!("Switched to profile '{}'", name);
+                        }
+                        apply_rules(&new_config)?;
+                        checkpoint_interval = StdDuration::from_secs(new_config.checkpoint_secs.max(1));
+                        config = new_config;
+                        config_watch = reload::ConfigWatch::start().ok();
+                    }
+                    Err(e) => eprintln!("Failed to switch to profile '{}': {}", name, e),
+                }
+            }
+            if let Some(new_config) = config_watch.as_ref().and_then(reload::ConfigWatch::check) {
+                match check_rules(&new_config) {
+                    Ok(()) => {
+                        let changes = reload::describe_changes(&config, &new_config);
+                        stream::config_reloaded(&changes);
+                        if !options.stream && !changes.is_empty() {
+                            eprintln!("Config reloaded: {}", changes.join(", "));
+                        }
+                        apply_rules(&new_config)?;
+                        checkpoint_interval = StdDuration::from_secs(new_config.checkpoint_secs.max(1));
+                        config = new_config;
+                    }
+                    Err(e) => eprintln!("Ignoring config change: {}", e),
+                }
+            }
+            status(&config);
+            last_display = Instant::now();
+        }
+
+        update_interval = if options.low_power || power::low_power() {
+            polling::low_power_interval(update_interval, wt_get_focus_duration(), wt_get_idle_time())
+        } else {
+            polling::adaptive_interval(wt_get_focus_duration())
+        };
+
+        thread::sleep(update_interval);
+    }
+
+    if let Some(watch) = &mut visibility_watch {
+        watch.finish(SystemTime::now());
+    }
+    if let Some(watch) = &mut floating_watch {
+        watch.finish(SystemTime::now());
+    }
+    let closed = close_open_interval();
+    enforce::release();
+    if !options.ephemeral {
+        control::clear_heartbeat();
+        ipc::stop();
+    }
+    wt_set_ephemeral(false);
+    closed
+}
@@ -0,0 +1,62 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+// Minimal SMTP client for handing reports to a relay (a local MTA, msmtp, a LAN smarthost).
+// It speaks plain SMTP without STARTTLS or AUTH, so point it at something that relays for you.
+pub fn send_html(relay: &str, from: &str, to: &str, subject: &str, html: &str) -> Result<(), String> {
+    let stream = TcpStream::connect(relay).map_err(|e| format!("failed to connect to {}: {}", relay, e))?;
+    stream.set_read_timeout(Some(Duration::from_secs(30))).ok();
+
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let mut writer = stream;
+
+    expect(&mut reader, 220)?;
+    command(&mut writer, &mut reader, "EHLO localhost", 250)?;
+    command(&mut writer, &mut reader, &format!("MAIL FROM:<{}>", from), 250)?;
+    command(&mut writer, &mut reader, &format!("RCPT TO:<{}>", to), 250)?;
+    command(&mut writer, &mut reader, "DATA", 354)?;
+
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/html; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        from, to, subject
+    );
+    for line in html.lines() {
+        // Dot-stuffing so a line starting with '.' can't end the message early
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push_str(".\r\n");
+    writer.write_all(message.as_bytes()).map_err(|e| e.to_string())?;
+    expect(&mut reader, 250)?;
+
+    command(&mut writer, &mut reader, "QUIT", 221)
+}
+
+fn command(writer: &mut TcpStream, reader: &mut impl BufRead, line: &str, code: u16) -> Result<(), String> {
+    writer.write_all(format!("{}\r\n", line).as_bytes()).map_err(|e| e.to_string())?;
+    expect(reader, code)
+}
+
+// Reads a (possibly multi-line) SMTP reply and checks its status code.
+fn expect(reader: &mut impl BufRead, code: u16) -> Result<(), String> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Err("SMTP server closed the connection".to_string());
+        }
+
+        let status: u16 = line.get(..3).and_then(|s| s.parse().ok())
+            .ok_or_else(|| format!("unexpected SMTP reply: {}", line.trim_end()))?;
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        if status != code {
+            return Err(format!("SMTP server replied: {}", line.trim_end()));
+        }
+        return Ok(());
+    }
+}
@@ -1,150 +1,80 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
-use std::time::{Duration, Instant, SystemTime};
-use std::thread;
-use std::time::Duration as StdDuration;
+#[cfg(feature = "backup")]
+use window_tracker_concept::backup;
+#[cfg(feature = "enforce")]
+use window_tracker_concept::enforce;
+#[cfg(feature = "hub")]
+use window_tracker_concept::hub;
+#[cfg(feature = "self-update")]
+use window_tracker_concept::update;
+#[cfg(feature = "server")]
+use window_tracker_concept::{api, viewer};
+use window_tracker_concept::{activity, autostart, cli, control, doctor, export, game_names, i18n, import, init, jira, merge, powershell, presence, profile, purge, query, report, review, rules, thumbnails, track, verify, TrackOptions};
 
-lazy_static::lazy_static! {
-    static ref WINDOWS: Mutex<HashMap<String, f64>> = Mutex::new(HashMap::new());
-    static ref LAST_FOCUS_CHANGE: Mutex<SystemTime> = Mutex::new(SystemTime::now());
-}
-
-#[cfg(windows)]
-mod platform {
-    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowTextW};
-
-    pub fn get_active_window_title() -> Option<String> {
-        unsafe {
-            let hwnd = GetForegroundWindow();
-            if hwnd.is_invalid() {
-                return None;
-            }
-
-            let mut buffer = [0u16; 512];
-            let length = GetWindowTextW(hwnd, &mut buffer);
-            if length > 0 {
-                Some(String::from_utf16_lossy(&buffer[..length as usize]))
-            } else {
-                None
-            }
-        }
-    }
-}
-
-#[cfg(target_os = "macos")]
-mod platform {
-    pub fn get_active_window_title() -> Option<String> {
-        use core_foundation::base::TCFType;
-        let window_list = unsafe { CGWindowListCopyWindowInfo(kCGWindowListOptionOnScreenOnly, 0) };
-        if let Some(window_list) = window_list {
-            if let Some(window_info) = window_list.get(0) {
-                if let Some(window_owner) = window_info.get("kCGWindowOwnerName") {
-                    let owner_name: CFString = window_owner.downcast::<CFString>().unwrap();
-                    let window_title_str = owner_name.to_string();
-                    add_or_update_window(&window_title_str, current_time);
-                }
-            }
-        }
-    }
-}
-
-#[cfg(target_os = "linux")]
-mod platform {
-    pub fn get_active_window_title() -> Option<String> {
-        let display = unsafe { XOpenDisplay(std::ptr::null()) };
-        if !display.is_null() {
-            let mut window: u64 = 0;
-            let mut revert_to: i32 = 0;
-            unsafe { XGetInputFocus(display, &mut window, &mut revert_to) };
-            if window != 0 {
-                let mut window_name: *mut i8 = std::ptr::null_mut();
-                if unsafe { XFetchName(display, window, &mut window_name) } > 0 && !window_name.is_null() {
-                    let title = unsafe { CStr::from_ptr(window_name) };
-                    let window_title_str = title.to_string_lossy().into_owned();
-                    add_or_update_window(&window_title_str, current_time);
-                }
-                unsafe { XCloseDisplay(display) };
-            }
-        }
+fn main() {
+    let args = cli::Args::from_env();
+    if args.flag("--help") || args.flag("-h") || args.command() == Some("help") {
+        println!("{}", cli::USAGE);
+        return;
     }
-}
-
-use platform::get_active_window_title;
 
-pub fn wt_init() {
-    let mut windows = WINDOWS.lock().unwrap();
-    windows.clear();
-    let mut last_focus_change = LAST_FOCUS_CHANGE.lock().unwrap();
-    *last_focus_change = SystemTime::now();
-}
-
-pub fn wt_update() {
-    let current_time = SystemTime::now();
-
-    if let Some(window_title) = get_active_window_title() {
-        add_or_update_window(&window_title, current_time);
+    if let Err(e) = profile::from_args(&args).and_then(|()| i18n::from_args(&args)).and_then(|()| run(&args)) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
     }
 }
 
-fn add_or_update_window(title: &str, current_time: SystemTime) {
-    let mut windows = WINDOWS.lock().unwrap();
-    let mut last_focus_change = LAST_FOCUS_CHANGE.lock().unwrap();
-    let elapsed_time = last_focus_change.elapsed().unwrap_or(Duration::from_secs(0)).as_secs_f64();
-
-    if let Some(total_focus_time) = windows.get_mut(title) {
-        *total_focus_time += elapsed_time;
-    } else {
-        windows.insert(title.to_string(), elapsed_time);
+fn run(args: &cli::Args) -> Result<(), String> {
+    match args.command() {
+        None | Some("track") => TrackOptions::from_args(args).and_then(track),
+        Some("report") => report::run(args),
+        Some("export") => export::run(args),
+        Some("query") => query::run(args),
+        Some("doctor") => doctor::run(args),
+        Some("powershell-module") => powershell::run(args),
+        Some("status") => control::run_status(args),
+        Some("current") => control::run_current(args),
+        Some("today") => control::run_today(args),
+        Some("pause") => control::run_pause(args),
+        Some("resume") => control::run_resume(args),
+        Some("switch-profile") => control::run_switch_profile(args),
+        #[cfg(feature = "server")]
+        Some("api-token") => api::run_token(args),
+        Some("jira-push") => jira::run(args),
+        Some("init") => init::run(args),
+        Some("autostart") => autostart::run(args),
+        Some("import") => import::run(args),
+        Some("import-activity") => activity::run(args),
+        Some("status-updates") => presence::run(args),
+        Some("review") => review::run(args),
+        Some("merge") => merge::run(args),
+        Some("purge") => purge::run(args),
+        Some("verify") => verify::run(args),
+        Some("rules") => rules::run(args),
+        Some("game-names") => game_names::run(args),
+        Some("recategorize") => rules::run_recategorize(args),
+        Some("recall") => thumbnails::run_recall(args),
+        #[cfg(feature = "server")]
+        Some("serve") => viewer::run(args),
+        #[cfg(not(feature = "server"))]
+        Some("api-token" | "serve") => Err("the API and serve are not available in this build, rebuild with --features server".to_string()),
+        #[cfg(feature = "backup")]
+        Some("backup") => backup::run_backup(args),
+        #[cfg(feature = "backup")]
+        Some("restore") => backup::run_restore(args),
+        #[cfg(not(feature = "backup"))]
+        Some("backup" | "restore") => Err("backup and restore are not available in this build, rebuild with --features backup".to_string()),
+        #[cfg(feature = "self-update")]
+        Some("self-update") => update::run(args),
+        #[cfg(not(feature = "self-update"))]
+        Some("self-update") => Err("self-update is not available in this build, rebuild with --features self-update".to_string()),
+        #[cfg(feature = "enforce")]
+        Some("enforce") => enforce::run(args),
+        #[cfg(not(feature = "enforce"))]
+        Some("enforce") => Err("enforce is not available in this build, rebuild with --features enforce".to_string()),
+        #[cfg(feature = "hub")]
+        Some("hub") => hub::run(args),
+        #[cfg(not(feature = "hub"))]
+        Some("hub") => Err("hub is not available in this build, rebuild with --features hub".to_string()),
+        Some(other) => Err(format!("unknown command '{}'\n\n{}", other, cli::USAGE)),
     }
-
-    *last_focus_change = current_time;
-}
-
-pub fn wt_get_window_count() -> usize {
-    let windows = WINDOWS.lock().unwrap();
-    windows.len()
-}
-
-pub fn wt_get_window_info(index: usize) -> Option<(String, f64)> {
-    let windows = WINDOWS.lock().unwrap();
-    windows.iter().nth(index).map(|(k, &v)| (k.clone(), v))
-}
-
-pub fn wt_get_all_windows() -> Vec<(String, f64)> {
-    let windows = WINDOWS.lock().unwrap();
-    windows.iter()
-        .map(|(k, &v)| (k.clone(), v))
-        .collect()
-}
-
-pub fn wt_cleanup() {
-    let mut windows = WINDOWS.lock().unwrap();
-    windows.clear();
 }
-
-fn main() {
-    wt_init();
-    let update_interval = StdDuration::from_millis(100);  // Check active window every 100ms
-    let display_interval = StdDuration::from_secs(1);     // Update display every second
-    let mut last_display = Instant::now();
-
-    loop {
-        wt_update();  // Update window tracking
-
-        // Only display updates every second
-        if last_display.elapsed() >= display_interval {
-            println!("\nCurrent window tracking status:");
-            println!("Number of tracked windows: {}", wt_get_window_count());
-
-            // Display all windows and their times
-            for (title, focus_time) in wt_get_all_windows() {
-                println!("Window: {}", title);
-                println!("  Focus time: {:.1} seconds", focus_time);
-            }
-
-            last_display = Instant::now();
-        }
-
-        thread::sleep(update_interval);
-    }
-}
\ No newline at end of file
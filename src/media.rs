@@ -0,0 +1,269 @@
+// [media]: what media players and browsers are playing, so time in VLC or a browser breaks down by
+// what was watched or listened to (report --by field:media). A thread asks the players every
+// check_secs: MPRIS on the session bus on Linux, the system media sessions (SMTC) on Windows, and on
+// macOS the players that can be scripted, Music, Spotify and TV, as Now Playing is private to
+// Apple's own apps. An interval in a playing player's window is stored with field:media and
+// field:media_artist; when what plays changes the interval is split like on a title change.
+use std::sync::Mutex;
+
+use crate::config::{Config, MediaConfig, Privacy};
+use crate::ActiveWindow;
+
+pub const MEDIA: &str = "media";
+pub const MEDIA_ARTIST: &str = "media_artist";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Playing {
+    // What the player goes by: the MPRIS bus name, desktop entry and identity, or the session's
+    // app id, lowercase
+    pub names: Vec<String>,
+    pub pid: Option<u32>,
+    pub title: String,
+    pub artist: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Media {
+    pub title: String,
+    pub artist: Option<String>,
+}
+
+struct State {
+    config: MediaConfig,
+    playing: Vec<Playing>,
+    // Bumped by each answer that differs, for MediaWatch
+    changes: u64,
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+pub fn check(config: &Config) -> Result<(), String> {
+    let Some(media) = &config.media else { return Ok(()) };
+    if config.privacy == Privacy::Apps {
+        return Err("[media] titles are what windows show, they can't be combined with privacy = \"apps\"".to_string());
+    }
+    if media.check_secs == 0 {
+        return Err("[media] check_secs must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+pub fn configure(config: &Config) {
+    let mut state = STATE.lock().unwrap();
+    *state = config.media.clone().map(|media| {
+        let previous = state.take();
+        State {
+            config: media,
+            playing: previous.as_ref().map(|state| state.playing.clone()).unwrap_or_default(),
+            changes: previous.map_or(0, |state| state.changes),
+        }
+    });
+}
+
+// Asks the players from now on, every check_secs of the config at the time
+#[cfg(feature = "media")]
+pub fn start() {
+    std::thread::spawn(|| loop {
+        let check_secs = match STATE.lock().unwrap().as_ref() {
+            Some(state) => state.config.check_secs,
+            None => return,
+        };
+        let playing = platform::playing();
+        if let Some(state) = STATE.lock().unwrap().as_mut() {
+            if state.playing != playing {
+                state.playing = playing;
+                state.changes += 1;
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_secs(check_secs.max(1)));
+    });
+}
+
+fn stem(app: &str) -> String {
+    let app = app.to_lowercase();
+    app.strip_suffix(".exe").map(str::to_string).unwrap_or(app)
+}
+
+// Whether the window is the player's: the same process, or the app under one of its names
+fn plays_in(playing: &Playing, window: &ActiveWindow) -> bool {
+    let app = stem(&window.app);
+    playing.pid.is_some_and(|pid| pid == window.pid)
+        || (app.len() >= 3 && playing.names.iter().any(|name| *name == app || name.split(['.', '!', ' ']).any(|part| part == app)))
+}
+
+// What plays in the window, None when nothing does or [media] is off
+pub fn playing_in(window: &ActiveWindow) -> Option<Media> {
+    let state = STATE.lock().unwrap();
+    let state = state.as_ref()?;
+    if window.pid == 0 && window.app.is_empty() {
+        return None;
+    }
+    state.playing.iter().find(|playing| plays_in(playing, window)).map(|playing| Media {
+        title: playing.title.clone(),
+        artist: playing.artist.clone().filter(|_| state.config.artist),
+    })
+}
+
+// Lets the tracker loop know when what plays changed
+#[derive(Default)]
+pub struct MediaWatch {
+    seen: u64,
+}
+
+impl MediaWatch {
+    pub fn check(&mut self) {
+        let changes = STATE.lock().unwrap().as_ref().map_or(0, |state| state.changes);
+        if changes != self.seen {
+            self.seen = changes;
+            crate::wt_media_changed();
+        }
+    }
+}
+
+// The text before a NUL or the whole of it, D-Bus and WinRT strings can hold either
+#[cfg(feature = "media")]
+fn cleaned(text: String) -> Option<String> {
+    let text = text.split('\0').next().unwrap_or_default().trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(all(feature = "media", target_os = "linux"))]
+mod platform {
+    use super::{cleaned, Playing};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use zbus::blocking::fdo::DBusProxy;
+    use zbus::blocking::{proxy, Connection};
+    use zbus::names::BusName;
+    use zbus::proxy::CacheProperties;
+    use zbus::zvariant::OwnedValue;
+
+    const PREFIX: &str = "org.mpris.MediaPlayer2.";
+    const PATH: &str = "/org/mpris/MediaPlayer2";
+
+    fn property<T: TryFrom<OwnedValue>>(connection: &Connection, name: &str, interface: &str, property: &str) -> Option<T>
+    where
+        T::Error: Into<zbus::Error>,
+    {
+        proxy::Builder::<zbus::blocking::Proxy>::new(connection)
+            .destination(name.to_string()).ok()?
+            .path(PATH).ok()?
+            .interface(interface.to_string()).ok()?
+            .cache_properties(CacheProperties::No)
+            .build().ok()?
+            .get_property(property).ok()
+    }
+
+    fn player(connection: &Connection, bus: &DBusProxy, name: &str) -> Option<Playing> {
+        let status: String = property(connection, name, "org.mpris.MediaPlayer2.Player", "PlaybackStatus")?;
+        if status != "Playing" {
+            return None;
+        }
+        let metadata: HashMap<String, OwnedValue> = property(connection, name, "org.mpris.MediaPlayer2.Player", "Metadata")?;
+        let title = metadata.get("xesam:title").and_then(|title| String::try_from(title.try_clone().ok()?).ok()).and_then(cleaned)?;
+        let artist = metadata.get("xesam:artist")
+            .and_then(|artist| Vec::<String>::try_from(artist.try_clone().ok()?).ok())
+            .and_then(|artists| cleaned(artists.join(", ")));
+        // firefox.instance_1234 and the like
+        let mut names = vec![name.trim_start_matches(PREFIX).split(".instance").next().unwrap_or_default().to_lowercase()];
+        for root in ["DesktopEntry", "Identity"] {
+            names.extend(property::<String>(connection, name, "org.mpris.MediaPlayer2", root).map(|value| value.to_lowercase()));
+        }
+        let pid = BusName::try_from(name).ok().and_then(|bus_name| bus.get_connection_unix_process_id(bus_name).ok());
+        Some(Playing { names, pid, title, artist })
+    }
+
+    // Kept between checks, opened again after the bus went away
+    static CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+
+    pub fn playing() -> Vec<Playing> {
+        let mut kept = CONNECTION.lock().unwrap();
+        let connection = match kept.as_ref() {
+            Some(connection) => connection.clone(),
+            None => match Connection::session() {
+                Ok(connection) => kept.insert(connection).clone(),
+                Err(_) => return Vec::new(),
+            },
+        };
+        let Ok(bus) = DBusProxy::new(&connection) else { return Vec::new() };
+        let Ok(names) = bus.list_names() else {
+            *kept = None;
+            return Vec::new();
+        };
+        drop(kept);
+        names.iter()
+            .map(|name| name.as_str())
+            .filter(|name| name.starts_with(PREFIX))
+            .filter_map(|name| player(&connection, &bus, name))
+            .collect()
+    }
+}
+
+#[cfg(all(feature = "media", windows))]
+mod platform {
+    use super::{cleaned, Playing};
+    use windows::Media::Control::{
+        GlobalSystemMediaTransportControlsSession as Session, GlobalSystemMediaTransportControlsSessionManager as Manager,
+        GlobalSystemMediaTransportControlsSessionPlaybackStatus as Status,
+    };
+
+    fn session(session: &Session) -> Option<Playing> {
+        if session.GetPlaybackInfo().ok()?.PlaybackStatus().ok()? != Status::Playing {
+            return None;
+        }
+        let properties = session.TryGetMediaPropertiesAsync().ok()?.get().ok()?;
+        let title = cleaned(properties.Title().ok()?.to_string())?;
+        let artist = properties.Artist().ok().and_then(|artist| cleaned(artist.to_string()));
+        // Like Chrome, MSEdge, Spotify.exe or a packaged app's Publisher.App_hash!App
+        let app = session.SourceAppUserModelId().ok()?.to_string().to_lowercase();
+        let names = vec![app.strip_suffix(".exe").unwrap_or(&app).to_string()];
+        Some(Playing { names, pid: None, title, artist })
+    }
+
+    pub fn playing() -> Vec<Playing> {
+        let Ok(manager) = Manager::RequestAsync().and_then(|request| request.get()) else { return Vec::new() };
+        let Ok(sessions) = manager.GetSessions() else { return Vec::new() };
+        sessions.into_iter().filter_map(|found| session(&found)).collect()
+    }
+}
+
+#[cfg(all(feature = "media", target_os = "macos"))]
+mod platform {
+    use super::{cleaned, Playing};
+    use std::process::Command;
+
+    // Application() of a player that isn't installed throws, running() doesn't launch one that is
+    const SCRIPT: &str = "var out = [];
+['Music', 'Spotify', 'TV'].forEach(function (name) {
+  try {
+    var app = Application(name);
+    if (app.running() && app.playerState() === 'playing') {
+      var track = app.currentTrack();
+      out.push([name, track.name(), track.artist() || ''].join('\\t'));
+    }
+  } catch (e) {}
+});
+out.join('\\n');";
+
+    pub fn playing() -> Vec<Playing> {
+        let Ok(output) = Command::new("osascript").args(["-l", "JavaScript", "-e", SCRIPT]).output() else { return Vec::new() };
+        String::from_utf8_lossy(&output.stdout).lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let names = vec![fields.next()?.to_lowercase()];
+                let title = cleaned(fields.next()?.to_string())?;
+                let artist = fields.next().and_then(|artist| cleaned(artist.to_string()));
+                Some(Playing { names, pid: None, title, artist })
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(feature = "media", not(any(windows, target_os = "linux", target_os = "macos"))))]
+mod platform {
+    use super::Playing;
+
+    pub fn playing() -> Vec<Playing> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,274 @@
+// `track --menubar` on macOS: a status item in the menu bar showing today's focus time, and a
+// popover on click with today's top apps, a pause toggle and quit. AppKit wants the main thread,
+// so tracking runs on another one and the status item reads its state on a timer. It goes straight
+// to the Objective-C runtime, as the rest of the macOS code goes straight to CoreGraphics.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CString};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use chrono::Utc;
+
+use crate::control;
+use crate::humanize;
+use crate::i18n::tr;
+use crate::report::{clip, last_days};
+use crate::storage;
+use crate::{stream, TrackOptions};
+
+type Id = *mut c_void;
+type Sel = *const c_void;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct EdgeInsets {
+    top: f64,
+    left: f64,
+    bottom: f64,
+    right: f64,
+}
+
+#[link(name = "objc")]
+extern "C" {
+    fn objc_getClass(name: *const c_char) -> Id;
+    fn sel_registerName(name: *const c_char) -> Sel;
+    fn objc_msgSend();
+    fn objc_allocateClassPair(superclass: Id, name: *const c_char, extra_bytes: usize) -> Id;
+    fn class_addMethod(class: Id, name: Sel, imp: *const c_void, types: *const c_char) -> bool;
+    fn objc_registerClassPair(class: Id);
+}
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {}
+
+// How often the status item's time is brought up to date
+const REFRESH_SECS: f64 = 5.0;
+// Apps listed in the popover
+const TOP_APPS: usize = 5;
+const VARIABLE_LENGTH: f64 = -1.0;
+const ACTIVATION_POLICY_ACCESSORY: i64 = 1;
+const POPOVER_BEHAVIOR_TRANSIENT: i64 = 1;
+const USER_INTERFACE_LAYOUT_VERTICAL: i64 = 1;
+const LAYOUT_ATTRIBUTE_LEADING: i64 = 5;
+const RECT_EDGE_MIN_Y: u64 = 1;
+const UTF8_ENCODING: u64 = 4;
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+// What the callbacks change, only touched on the main thread
+struct Ui {
+    button: Id,
+    popover: Id,
+    label: Id,
+    pause: Id,
+}
+
+thread_local! {
+    static UI: RefCell<Option<Ui>> = const { RefCell::new(None) };
+}
+
+fn class(name: &str) -> Id {
+    let name = CString::new(name).unwrap();
+    unsafe { objc_getClass(name.as_ptr()) }
+}
+
+fn sel(name: &str) -> Sel {
+    let name = CString::new(name).unwrap();
+    unsafe { sel_registerName(name.as_ptr()) }
+}
+
+// objc_msgSend called as the method's own signature, one per shape used here
+unsafe fn send(receiver: Id, selector: &str) -> Id {
+    let send: unsafe extern "C" fn(Id, Sel) -> Id = std::mem::transmute(objc_msgSend as *const c_void);
+    send(receiver, sel(selector))
+}
+
+unsafe fn send_id(receiver: Id, selector: &str, arg: Id) -> Id {
+    let send: unsafe extern "C" fn(Id, Sel, Id) -> Id = std::mem::transmute(objc_msgSend as *const c_void);
+    send(receiver, sel(selector), arg)
+}
+
+unsafe fn send_i64(receiver: Id, selector: &str, arg: i64) -> Id {
+    let send: unsafe extern "C" fn(Id, Sel, i64) -> Id = std::mem::transmute(objc_msgSend as *const c_void);
+    send(receiver, sel(selector), arg)
+}
+
+unsafe fn send_f64(receiver: Id, selector: &str, arg: f64) -> Id {
+    let send: unsafe extern "C" fn(Id, Sel, f64) -> Id = std::mem::transmute(objc_msgSend as *const c_void);
+    send(receiver, sel(selector), arg)
+}
+
+unsafe fn send_bool(receiver: Id, selector: &str) -> bool {
+    let send: unsafe extern "C" fn(Id, Sel) -> bool = std::mem::transmute(objc_msgSend as *const c_void);
+    send(receiver, sel(selector))
+}
+
+unsafe fn ns_string(text: &str) -> Id {
+    let init: unsafe extern "C" fn(Id, Sel, *const c_void, usize, u64) -> Id = std::mem::transmute(objc_msgSend as *const c_void);
+    let string = init(send_alloc("NSString"), sel("initWithBytes:length:encoding:"), text.as_ptr().cast(), text.len(), UTF8_ENCODING);
+    send(string, "autorelease")
+}
+
+unsafe fn send_alloc(name: &str) -> Id {
+    send(class(name), "alloc")
+}
+
+unsafe fn button(title: &str, target: Id, action: &str) -> Id {
+    let send: unsafe extern "C" fn(Id, Sel, Id, Id, Sel) -> Id = std::mem::transmute(objc_msgSend as *const c_void);
+    send(class("NSButton"), sel("buttonWithTitle:target:action:"), ns_string(title), target, sel(action))
+}
+
+// A subclass of NSObject answering the actions of the status item, the popover's buttons and the timer
+unsafe fn target() -> Id {
+    let name = CString::new("WTMenuBarTarget").unwrap();
+    let target_class = objc_allocateClassPair(class("NSObject"), name.as_ptr(), 0);
+    let types = CString::new("v@:@").unwrap();
+    let methods: [(&str, extern "C" fn(Id, Sel, Id)); 4] = [("toggle:", toggle), ("pause:", pause), ("quit:", quit), ("refresh:", refresh)];
+    for (selector, imp) in methods {
+        class_addMethod(target_class, sel(selector), imp as *const c_void, types.as_ptr());
+    }
+    objc_registerClassPair(target_class);
+    send(send(target_class, "alloc"), "init")
+}
+
+fn pause_label() -> String {
+    if crate::wt_is_paused() { tr("menubar-resume", &[]) } else { tr("alert-pause", &[]) }
+}
+
+fn status_title() -> String {
+    if crate::wt_is_paused() {
+        format!("⏸ {}", tr("current-paused", &[]))
+    } else {
+        format!("⏱ {}", humanize::duration(crate::wt_get_today_secs()))
+    }
+}
+
+// Today's total and top apps, the open interval counting
+fn today_text() -> String {
+    let (from, to) = last_days(1);
+    let mut intervals = storage::load_intervals(from.with_timezone(&Utc), to.with_timezone(&Utc)).unwrap_or_default();
+    intervals.extend(crate::wt_get_open_interval());
+    let mut apps: HashMap<String, f64> = HashMap::new();
+    for interval in clip(&intervals, from, to) {
+        *apps.entry(interval.app_name().to_string()).or_default() += interval.duration_secs();
+    }
+    let total: f64 = apps.values().sum();
+    let mut apps: Vec<(String, f64)> = apps.into_iter().collect();
+    apps.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut lines = vec![tr("today-total", &[("time", &humanize::duration(total))])];
+    for (app, secs) in apps.iter().take(TOP_APPS) {
+        lines.push(format!("{}  {}", humanize::duration(*secs), app));
+    }
+    lines.join("\n")
+}
+
+fn with_ui(update: impl FnOnce(&Ui)) {
+    UI.with(|ui| {
+        if let Some(ui) = ui.borrow().as_ref() {
+            update(ui);
+        }
+    });
+}
+
+extern "C" fn refresh(_this: Id, _selector: Sel, _sender: Id) {
+    with_ui(|ui| unsafe {
+        send_id(ui.button, "setTitle:", ns_string(&status_title()));
+        if send_bool(ui.popover, "isShown") {
+            send_id(ui.label, "setStringValue:", ns_string(&today_text()));
+            send_id(ui.pause, "setTitle:", ns_string(&pause_label()));
+        }
+    });
+}
+
+extern "C" fn toggle(_this: Id, _selector: Sel, sender: Id) {
+    with_ui(|ui| unsafe {
+        if send_bool(ui.popover, "isShown") {
+            send_id(ui.popover, "performClose:", sender);
+            return;
+        }
+        send_id(ui.label, "setStringValue:", ns_string(&today_text()));
+        send_id(ui.pause, "setTitle:", ns_string(&pause_label()));
+        // An empty rect places it by the whole button
+        let show: unsafe extern "C" fn(Id, Sel, Rect, Id, u64) = std::mem::transmute(objc_msgSend as *const c_void);
+        let empty = Rect { x: 0.0, y: 0.0, width: 0.0, height: 0.0 };
+        show(ui.popover, sel("showRelativeToRect:ofView:preferredEdge:"), empty, ui.button, RECT_EDGE_MIN_Y);
+    });
+}
+
+extern "C" fn pause(this: Id, selector: Sel, sender: Id) {
+    let result = if crate::wt_is_paused() { control::resume_here() } else { control::pause_here() };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+    refresh(this, selector, sender);
+}
+
+// The session ends with the open interval stored, then the process exits
+extern "C" fn quit(_this: Id, _selector: Sel, _sender: Id) {
+    STOP.store(true, Ordering::Relaxed);
+}
+
+pub fn run(options: TrackOptions) -> Result<(), String> {
+    if options.stream {
+        stream::install();
+    }
+    thread::spawn(move || {
+        let result = crate::run_session(options, &STOP, |_| {});
+        if let Err(e) = &result {
+            eprintln!("{}", e);
+        }
+        std::process::exit(if result.is_ok() { 0 } else { 1 });
+    });
+
+    unsafe {
+        let app = send(class("NSApplication"), "sharedApplication");
+        send_i64(app, "setActivationPolicy:", ACTIVATION_POLICY_ACCESSORY);
+        let target = target();
+
+        let status_bar = send(class("NSStatusBar"), "systemStatusBar");
+        let item = send(send_f64(status_bar, "statusItemWithLength:", VARIABLE_LENGTH), "retain");
+        let status_button = send(item, "button");
+        send_id(status_button, "setTitle:", ns_string(&status_title()));
+        send_id(status_button, "setTarget:", target);
+        let set_action: unsafe extern "C" fn(Id, Sel, Sel) = std::mem::transmute(objc_msgSend as *const c_void);
+        set_action(status_button, sel("setAction:"), sel("toggle:"));
+
+        let label = send_id(class("NSTextField"), "labelWithString:", ns_string(""));
+        let pause_button = button(&pause_label(), target, "pause:");
+        let quit_button = button(&tr("menubar-quit", &[]), target, "quit:");
+        let views = [label, pause_button, quit_button];
+        let array: unsafe extern "C" fn(Id, Sel, *const Id, u64) -> Id = std::mem::transmute(objc_msgSend as *const c_void);
+        let views = array(class("NSArray"), sel("arrayWithObjects:count:"), views.as_ptr(), views.len() as u64);
+        let stack = send_id(class("NSStackView"), "stackViewWithViews:", views);
+        send_i64(stack, "setOrientation:", USER_INTERFACE_LAYOUT_VERTICAL);
+        send_i64(stack, "setAlignment:", LAYOUT_ATTRIBUTE_LEADING);
+        send_f64(stack, "setSpacing:", 8.0);
+        let insets: unsafe extern "C" fn(Id, Sel, EdgeInsets) = std::mem::transmute(objc_msgSend as *const c_void);
+        insets(stack, sel("setEdgeInsets:"), EdgeInsets { top: 12.0, left: 12.0, bottom: 12.0, right: 12.0 });
+
+        let controller = send(send_alloc("NSViewController"), "init");
+        send_id(controller, "setView:", stack);
+        let popover = send(send_alloc("NSPopover"), "init");
+        send_i64(popover, "setBehavior:", POPOVER_BEHAVIOR_TRANSIENT);
+        send_id(popover, "setContentViewController:", controller);
+
+        UI.with(|ui| *ui.borrow_mut() = Some(Ui { button: status_button, popover, label, pause: pause_button }));
+
+        let timer: unsafe extern "C" fn(Id, Sel, f64, Id, Sel, Id, bool) -> Id = std::mem::transmute(objc_msgSend as *const c_void);
+        timer(class("NSTimer"), sel("scheduledTimerWithTimeInterval:target:selector:userInfo:repeats:"), REFRESH_SECS, target, sel("refresh:"), std::ptr::null_mut(), true);
+
+        send(app, "run");
+    }
+    Ok(())
+}
@@ -0,0 +1,241 @@
+// [[merge]]: titles totalled under one name in the live display and in reports by title, like
+// several windows that are all the same project. Rules apply in order, each renaming what it
+// matches by then, so a later rule can rename what an earlier one merged. The history keeps the
+// titles as they were recorded.
+// `merge` picks recently tracked titles interactively to merge, rename or never track again, and
+// appends the rules for them to config.toml.
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, IsTerminal};
+
+use chrono::Utc;
+use regex::Regex;
+
+use crate::cli::Args;
+use crate::config::{self, Config};
+use crate::humanize;
+use crate::ignore::{Decision, WindowFilter, REDACTED};
+use crate::report::last_days;
+use crate::review;
+use crate::storage::{self, Interval};
+
+const MERGE_LIMIT: usize = 30;
+
+struct Rule {
+    app: Option<String>,
+    titles: Vec<Regex>,
+    into: String,
+}
+
+#[derive(Default)]
+pub struct Merges {
+    rules: Vec<Rule>,
+}
+
+impl Merges {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in &config.merge {
+            if rule.into.trim().is_empty() || rule.titles.is_empty() {
+                return Err("a [[merge]] rule needs into and at least one of titles".to_string());
+            }
+            let titles = rule.titles.iter()
+                .map(|pattern| Regex::new(pattern).map_err(|e| format!("invalid merge pattern '{}': {}", pattern, e)))
+                .collect::<Result<Vec<_>, _>>()?;
+            rules.push(Rule { app: rule.app.clone(), titles, into: rule.into.clone() });
+        }
+        Ok(Merges { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    // The name a title is totalled under
+    pub fn bucket<'a>(&'a self, title: &'a str, app: &str) -> &'a str {
+        self.rules.iter().fold(title, |name, rule| {
+            let matches = rule.app.as_ref().is_none_or(|rule_app| rule_app.eq_ignore_ascii_case(app))
+                && rule.titles.iter().any(|pattern| pattern.is_match(name));
+            if matches { &rule.into } else { name }
+        })
+    }
+
+    // The intervals with their titles merged, for totals by title
+    pub fn apply(&self, intervals: &[Interval]) -> Vec<Interval> {
+        intervals.iter()
+            .map(|interval| Interval { title: self.bucket(&interval.title, &interval.app).to_string(), ..interval.clone() })
+            .collect()
+    }
+}
+
+// One line of the list: a title, or what rules merged already, with the titles recorded under it
+struct Entry {
+    name: String,
+    apps: BTreeSet<String>,
+    titles: BTreeSet<(String, String)>,
+    secs: f64,
+}
+
+impl Entry {
+    fn apps_label(&self) -> String {
+        match self.apps.iter().next() {
+            Some(_) if self.apps.len() > 1 => format!("{} apps", self.apps.len()),
+            Some(app) if !app.is_empty() => app.clone(),
+            _ => "unknown".to_string(),
+        }
+    }
+}
+
+enum Action {
+    Merge(Vec<usize>, String),
+    Rename(usize, String),
+    Delete(Vec<usize>),
+}
+
+impl Action {
+    // "m1,2,3 Project X", "r4 New name" or "d5,6", numbers from 1 to count
+    fn parse(line: &str, count: usize) -> Result<Self, String> {
+        let usage = || format!("expected m<numbers> <name>, r<number> <name> or d<numbers>, numbers from 1 to {} separated by commas", count);
+        let mut chars = line.chars();
+        let letter = chars.next().ok_or_else(usage)?;
+        let (numbers, name) = chars.as_str().split_once(' ').unwrap_or((chars.as_str(), ""));
+        let indexes = numbers.split(',')
+            .map(|number| number.trim().parse::<usize>().ok().filter(|index| (1..=count).contains(index)).map(|index| index - 1))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(usage)?;
+        let name = name.trim().to_string();
+        match letter {
+            'm' if indexes.len() >= 2 && !name.is_empty() => Ok(Action::Merge(indexes, name)),
+            'r' if indexes.len() == 1 && !name.is_empty() => Ok(Action::Rename(indexes[0], name)),
+            'd' if name.is_empty() => Ok(Action::Delete(indexes)),
+            _ => Err(usage()),
+        }
+    }
+
+    fn describe(&self, entries: &[Entry]) -> String {
+        let names = |indexes: &[usize]| indexes.iter().map(|index| format!("'{}'", entries[*index].name)).collect::<Vec<_>>().join(", ");
+        match self {
+            Action::Merge(indexes, name) => format!("total {} as '{}'", names(indexes), name),
+            Action::Rename(index, name) => format!("rename '{}' to '{}'", entries[*index].name, name),
+            Action::Delete(indexes) => format!("never track {} again", names(indexes)),
+        }
+    }
+
+    fn rules(&self, entries: &[Entry]) -> Vec<String> {
+        match self {
+            Action::Merge(indexes, name) => vec![merge_rule(indexes, name, entries)],
+            Action::Rename(index, name) => vec![merge_rule(&[*index], name, entries)],
+            Action::Delete(indexes) => indexes.iter()
+                .flat_map(|index| &entries[*index].titles)
+                .map(|(app, title)| match app.as_str() {
+                    "" => format!("[[ignore]]\ntitle = {}\n", exact(title)),
+                    app => format!("[[ignore]]\napp = {}\ntitle = {}\n", string(app), exact(title)),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn string(text: &str) -> String {
+    toml::Value::String(text.to_string()).to_string()
+}
+
+fn exact(text: &str) -> String {
+    string(&format!("^{}$", regex::escape(text)))
+}
+
+// Matching the entries by the names they are listed under, so it also renames what rules merged
+// before it; only for their app when they all come from one
+fn merge_rule(indexes: &[usize], name: &str, entries: &[Entry]) -> String {
+    let merged: Vec<&Entry> = indexes.iter().map(|index| &entries[*index]).collect();
+    let titles: Vec<String> = merged.iter().map(|entry| exact(&entry.name)).collect();
+    let mut rule = format!("[[merge]]\ninto = {}\ntitles = [{}]\n", string(name), titles.join(", "));
+    let apps: BTreeSet<&String> = merged.iter().flat_map(|entry| &entry.apps).collect();
+    if let (1, Some(app)) = (apps.len(), apps.iter().next().filter(|app| !app.is_empty())) {
+        rule.push_str(&format!("app = {}\n", string(app)));
+    }
+    rule
+}
+
+// The most tracked titles the current rules still record, merged as the rules have them
+fn entries(days: u32, config: &Config) -> Result<Vec<Entry>, String> {
+    let filter = WindowFilter::from_config(config)?;
+    let merges = Merges::from_config(config)?;
+    let (from, to) = last_days(days);
+    let intervals = storage::load_intervals(from.with_timezone(&Utc), to.with_timezone(&Utc))
+        .map_err(|e| format!("failed to read history: {}", e))?;
+
+    let mut entries: HashMap<&str, Entry> = HashMap::new();
+    for interval in &intervals {
+        if interval.title == REDACTED || !matches!(filter.decide(&interval.title, &interval.app), Decision::Track) {
+            continue;
+        }
+        let name = merges.bucket(&interval.title, &interval.app);
+        let entry = entries.entry(name).or_insert_with(|| Entry {
+            name: name.to_string(),
+            apps: BTreeSet::new(),
+            titles: BTreeSet::new(),
+            secs: 0.0,
+        });
+        entry.apps.insert(interval.app.clone());
+        entry.titles.insert((interval.app.clone(), interval.title.clone()));
+        entry.secs += interval.duration_secs();
+    }
+    let mut entries: Vec<Entry> = entries.into_values().collect();
+    entries.sort_by(|a, b| b.secs.total_cmp(&a.secs).then_with(|| a.name.cmp(&b.name)));
+    entries.truncate(MERGE_LIMIT);
+    Ok(entries)
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    if !io::stdin().is_terminal() {
+        return Err("merge is interactive, run it in a terminal".to_string());
+    }
+    let days: u32 = args.parsed("--days", 7)?;
+    if days == 0 {
+        return Err("--days must be at least 1".to_string());
+    }
+
+    let config = config::load()?;
+    let entries = entries(days, &config)?;
+    if entries.is_empty() {
+        println!("Nothing tracked in the last {} days that isn't already ignored", days);
+        return Ok(());
+    }
+
+    println!("Most tracked titles in the last {} days:", days);
+    for (i, entry) in entries.iter().enumerate() {
+        let merged = match entry.titles.len() {
+            1 => String::new(),
+            count => format!(" ({} titles)", count),
+        };
+        println!("{:>3}  {:>8}  {:<16} {}{}", i + 1, humanize::duration(entry.secs), entry.apps_label(), entry.name, merged);
+    }
+    println!("\nm1,2,3 <name> totals entries 1, 2 and 3 as <name>, r4 <name> renames entry 4 and d5 never");
+    println!("tracks entry 5 again. One per line, an empty line finishes.");
+
+    let mut rules: Vec<String> = Vec::new();
+    while let Some(line) = review::prompt("> ")? {
+        if line.is_empty() {
+            break;
+        }
+        match Action::parse(&line, entries.len()) {
+            Ok(action) => {
+                println!("  {}", action.describe(&entries));
+                for rule in action.rules(&entries) {
+                    if !rules.contains(&rule) {
+                        rules.push(rule);
+                    }
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    if rules.is_empty() {
+        println!("No rules added");
+        return Ok(());
+    }
+    review::confirm_and_append(&rules, |updated| {
+        WindowFilter::from_config(updated)?;
+        Merges::from_config(updated).map(|_| ())
+    })
+}
@@ -0,0 +1,145 @@
+// Node.js addon for embedding the tracker in Electron apps, e.g.
+//   const tracker = new Tracker(); tracker.onFocusChange(change => ...); tracker.start();
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{Env, Error, JsFunction, Result};
+use napi_derive::napi;
+
+use crate::report::{self, GroupBy};
+use crate::TrackOptions;
+
+// The tracking state is per process, so at most one tracker samples at a time
+static SESSION_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+#[napi(object)]
+pub struct TrackerOptions {
+    pub low_power: Option<bool>,
+    pub separate_dialogs: Option<bool>,
+    pub visible: Option<bool>,
+    pub ephemeral: Option<bool>,
+}
+
+#[napi(object)]
+pub struct WindowTime {
+    pub title: String,
+    pub seconds: f64,
+}
+
+#[napi(object)]
+pub struct FocusChange {
+    // "focus_changed" or "title_changed"
+    pub event: String,
+    pub title: String,
+    pub app: String,
+    pub window_id: String,
+}
+
+#[napi]
+#[derive(Default)]
+pub struct Tracker {
+    stop: Arc<AtomicBool>,
+    session: Option<JoinHandle<std::result::Result<(), String>>>,
+}
+
+#[napi]
+impl Tracker {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Tracker::default()
+    }
+
+    // Samples on a background thread until stop() is called
+    #[napi]
+    pub fn start(&mut self, options: Option<TrackerOptions>) -> Result<()> {
+        if SESSION_ACTIVE.swap(true, Ordering::SeqCst) {
+            return Err(Error::from_reason("a tracker is already running in this process"));
+        }
+
+        let options = TrackOptions {
+            low_power: options.as_ref().and_then(|options| options.low_power).unwrap_or(false),
+            separate_dialogs: options.as_ref().and_then(|options| options.separate_dialogs).unwrap_or(false),
+            stream: false,
+            visible: options.as_ref().and_then(|options| options.visible).unwrap_or(false),
+            ephemeral: options.as_ref().and_then(|options| options.ephemeral).unwrap_or(false),
+            menubar: false,
+        };
+        self.stop.store(false, Ordering::Relaxed);
+        let stop = Arc::clone(&self.stop);
+        self.session = Some(thread::spawn(move || {
+            let result = crate::run_session(options, &stop, |_| {});
+            SESSION_ACTIVE.store(false, Ordering::SeqCst);
+            result
+        }));
+        Ok(())
+    }
+
+    // Stores the open interval and waits for the sampling thread to finish
+    #[napi]
+    pub fn stop(&mut self) -> Result<()> {
+        let Some(session) = self.session.take() else { return Ok(()) };
+        self.stop.store(true, Ordering::Relaxed);
+        match session.join() {
+            Ok(result) => result.map_err(Error::from_reason),
+            Err(_) => Err(Error::from_reason("the tracking thread panicked")),
+        }
+    }
+
+    // Focus time per title in the current session, or per aggregate_by from the config
+    #[napi]
+    pub fn windows(&self) -> Vec<WindowTime> {
+        crate::wt_get_all_windows().into_iter().map(|window| WindowTime { title: window.title, seconds: window.focus_secs }).collect()
+    }
+
+    #[napi]
+    pub fn focus_seconds(&self) -> f64 {
+        crate::wt_get_focus_duration().as_secs_f64()
+    }
+
+    #[napi]
+    pub fn today_seconds(&self) -> f64 {
+        crate::wt_get_today_secs()
+    }
+
+    #[napi]
+    pub fn idle_seconds(&self) -> Option<f64> {
+        crate::wt_get_idle_time().map(|idle| idle.as_secs_f64())
+    }
+
+    // The callback runs on the JavaScript thread; it doesn't keep the process alive on its own
+    #[napi(ts_args_type = "callback: (change: FocusChange) => void")]
+    pub fn on_focus_change(&self, env: Env, callback: JsFunction) -> Result<()> {
+        let mut callback: ThreadsafeFunction<FocusChange, ErrorStrategy::Fatal> =
+            callback.create_threadsafe_function(0, |context: ThreadSafeCallContext<FocusChange>| Ok(vec![context.value]))?;
+        callback.unref(&env)?;
+
+        crate::wt_add_focus_listener(Box::new(move |event, window| {
+            let change = FocusChange {
+                event: event.as_str().to_string(),
+                title: window.title.clone(),
+                app: window.app.clone(),
+                window_id: window.key(),
+            };
+            callback.call(change, ThreadsafeFunctionCallMode::NonBlocking);
+        }));
+        Ok(())
+    }
+}
+
+// The report for the last `days` days (default 7) as a JSON string, grouped by "title", "window",
+// "app", "class" or "rule" (default: aggregate_by from the config)
+#[napi]
+pub fn report(days: Option<u32>, group_by: Option<String>) -> Result<String> {
+    let days = days.unwrap_or(7);
+    if days == 0 {
+        return Err(Error::from_reason("days must be at least 1"));
+    }
+    let group_by = match group_by {
+        Some(group_by) => GroupBy::parse(&group_by),
+        None => GroupBy::configured(),
+    }.map_err(Error::from_reason)?;
+    let summary = report::load(days, group_by).map_err(Error::from_reason)?;
+    Ok(report::render_json(&summary))
+}
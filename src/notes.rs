@@ -0,0 +1,155 @@
+// `export --format markdown`: one Markdown note per tracked day, for a notes vault like Obsidian's
+// or Logseq's. A template (--template, the built-in one otherwise) has placeholders like {date},
+// {total} and {windows} filled in, the tables are Markdown tables. Notes already in the folder
+// are left alone unless --force is given, which rewrites them.
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use chrono::{Duration, NaiveDate, Utc};
+
+use crate::cli::Args;
+use crate::config;
+use crate::humanize;
+use crate::i18n;
+use crate::merge::Merges;
+use crate::report::{clip, last_days, local_midnight, summarize, GroupBy, Summary};
+use crate::storage;
+use crate::table::{Format, Table};
+
+// Rows in each table of a note
+const TOP_ROWS: usize = 15;
+
+const DEFAULT_TEMPLATE: &str = "\
+---
+date: {date}
+focus_hours: {hours}
+switches: {switches}
+tags: [focus]
+---
+
+# Focus on {day}
+
+{total} of focus time, {switches} window switches.
+
+## Categories
+
+{categories}
+
+## Apps
+
+{apps}
+
+## Top windows
+
+{windows}
+";
+
+// The summary's rows with their time as a Markdown table
+fn markdown_table(summary: &Summary) -> String {
+    let column = summary.group_by.column();
+    let mut table = Table::new(&[&column, "time"]);
+    for (name, secs) in summary.windows.iter().take(TOP_ROWS) {
+        table.push(vec![name.clone().into(), humanize::duration(*secs).into()]);
+    }
+    table.render(Format::Markdown).trim_end().to_string()
+}
+
+// The template with each placeholder filled in, others are left as they are
+pub fn render(template: &str, date: NaiveDate, windows: &Summary, apps: &Summary, categories: &Summary) -> String {
+    let values = [
+        ("{date}", date.to_string()),
+        // The weekday in the report language and the date
+        ("{day}", i18n::day(date)),
+        ("{total}", humanize::duration(windows.total)),
+        // Plain numbers for the front-matter, whatever the locale
+        ("{total_secs}", format!("{:.0}", windows.total)),
+        ("{hours}", format!("{:.2}", windows.total / 3600.0)),
+        ("{switches}", windows.switches.to_string()),
+        ("{windows}", markdown_table(windows)),
+        ("{apps}", markdown_table(apps)),
+        ("{categories}", markdown_table(categories)),
+    ];
+    // One pass, so a title like "{apps}" in a table stays as it is
+    let mut note = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        note.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match values.iter().find(|(placeholder, _)| rest.starts_with(placeholder)) {
+            Some((placeholder, value)) => {
+                note.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                note.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    note.push_str(rest);
+    note
+}
+
+// The file name for a day from a strftime pattern
+fn file_name(pattern: &str, date: NaiveDate) -> Result<String, String> {
+    let mut name = String::new();
+    write!(name, "{}", date.format(pattern)).map_err(|_| format!("invalid --name pattern '{}'", pattern))?;
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(format!("--name '{}' must give a file name without directories", pattern));
+    }
+    Ok(name)
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let folder = args.value("--output").ok_or("usage: export --format markdown --output <notes folder> [--template <file>] [--days <n>] [--name <pattern>] [--force]")?;
+    let days: u32 = args.parsed("--days", 1)?;
+    if days == 0 {
+        return Err("--days must be at least 1".to_string());
+    }
+    let template = match args.value("--template") {
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?,
+        None => DEFAULT_TEMPLATE.to_string(),
+    };
+    let pattern = args.value("--name").unwrap_or("%Y-%m-%d.md");
+    let force = args.flag("--force");
+
+    let config = config::load()?;
+    let merges = Merges::from_config(&config)?;
+    let group_by = match args.value("--by") {
+        Some(group_by) => GroupBy::parse(group_by)?,
+        None => config.grouping().into(),
+    };
+    let (from, to) = last_days(days);
+    let history = storage::load_intervals(from.with_timezone(&Utc), to.with_timezone(&Utc))
+        .map_err(|e| format!("failed to read history: {}", e))?;
+    let merged = merges.apply(&history);
+
+    let folder = Path::new(folder);
+    fs::create_dir_all(folder).map_err(|e| format!("failed to create {}: {}", folder.display(), e))?;
+    let (mut written, mut kept) = (0, 0);
+    for date in from.date_naive().iter_days().take_while(|date| *date <= to.date_naive()) {
+        let (start, end) = (local_midnight(date).max(from), local_midnight(date + Duration::days(1)).min(to));
+        let by = |group_by: GroupBy| {
+            let history = if matches!(group_by, GroupBy::Title) { &merged } else { &history };
+            summarize(&clip(history, start, end), start, end, group_by)
+        };
+        let windows = by(group_by.clone());
+        if windows.total <= 0.0 {
+            continue;
+        }
+        let path = folder.join(file_name(pattern, date)?);
+        if path.exists() && !force {
+            kept += 1;
+            continue;
+        }
+        let note = render(&template, date, &windows, &by(GroupBy::App), &by(GroupBy::Rule));
+        fs::write(&path, note).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        written += 1;
+    }
+    eprintln!("Wrote {} daily notes to {}", written, folder.display());
+    if kept > 0 {
+        eprintln!("{} notes were already there and kept, --force rewrites them", kept);
+    }
+    Ok(())
+}
@@ -0,0 +1,141 @@
+#[cfg(feature = "notify")]
+use std::process::Command;
+
+// Desktop notifications through the tool each platform already ships with.
+#[cfg(feature = "notify")]
+pub fn send(summary: &str, body: &str) -> Result<(), String> {
+    if held_back(summary) {
+        return Ok(());
+    }
+    let status = notification_command(summary, body)
+        .status()
+        .map_err(|e| format!("failed to show notification: {}", e))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("notification command exited with {}", status))
+    }
+}
+
+// While Do Not Disturb is on, with [dnd] and unless quiet = false
+#[cfg(feature = "notify")]
+fn held_back(summary: &str) -> bool {
+    let quiet = crate::dnd::quiet();
+    if quiet {
+        eprintln!("Do Not Disturb is on, not showing \"{}\"", summary);
+    }
+    quiet
+}
+
+#[cfg(not(feature = "notify"))]
+pub fn send(_summary: &str, _body: &str) -> Result<(), String> {
+    Err("desktop notifications are not available in this build, rebuild with --features notify".to_string())
+}
+
+// A button on a notification, `id` is what comes back when it is clicked
+pub struct Action {
+    pub id: String,
+    pub label: String,
+}
+
+// On Windows the toast gets a button per action, and `chosen` is called from a thread waiting on the
+// toast's activation with the id of the one clicked. The other platforms show the notification
+// without them.
+#[cfg(all(feature = "notify", windows))]
+pub fn send_actions(summary: &str, body: &str, actions: &[Action], chosen: impl FnOnce(&str) + Send + 'static) -> Result<(), String> {
+    use std::io::Read;
+    use std::process::Stdio;
+
+    if held_back(summary) {
+        return Ok(());
+    }
+    let mut child = toast_command(summary, body, actions)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to show notification: {}", e))?;
+    let mut stdout = child.stdout.take();
+    std::thread::spawn(move || {
+        let mut clicked = String::new();
+        if let Some(stdout) = &mut stdout {
+            let _ = stdout.read_to_string(&mut clicked);
+        }
+        let _ = child.wait();
+        let clicked = clicked.trim();
+        if !clicked.is_empty() {
+            chosen(clicked);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(all(feature = "notify", not(windows)))]
+pub fn send_actions(summary: &str, body: &str, _actions: &[Action], _chosen: impl FnOnce(&str) + Send + 'static) -> Result<(), String> {
+    send(summary, body)
+}
+
+#[cfg(not(feature = "notify"))]
+pub fn send_actions(summary: &str, body: &str, _actions: &[Action], _chosen: impl FnOnce(&str) + Send + 'static) -> Result<(), String> {
+    send(summary, body)
+}
+
+#[cfg(all(feature = "notify", target_os = "linux"))]
+fn notification_command(summary: &str, body: &str) -> Command {
+    let mut command = Command::new("notify-send");
+    command.args(["--app-name=Window Tracker", summary, body]);
+    command
+}
+
+#[cfg(all(feature = "notify", target_os = "macos"))]
+fn notification_command(summary: &str, body: &str) -> Command {
+    let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut command = Command::new("osascript");
+    command.args(["-e", &format!("display notification {} with title {}", quote(body), quote(summary))]);
+    command
+}
+
+#[cfg(all(feature = "notify", windows))]
+fn notification_command(summary: &str, body: &str) -> Command {
+    toast_command(summary, body, &[])
+}
+
+// How long a toast with buttons is waited on, it stays in the Action Center after it is gone
+#[cfg(all(feature = "notify", windows))]
+const ACTION_WAIT_SECS: u64 = 600;
+
+// A toast shown from PowerShell; with actions it stays until one is clicked, the toast is dismissed
+// or ACTION_WAIT_SECS pass, and prints the clicked one's id
+#[cfg(all(feature = "notify", windows))]
+fn toast_command(summary: &str, body: &str, actions: &[Action]) -> Command {
+    let escape = |text: &str| {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "''")
+    };
+    let (buttons, wait) = if actions.is_empty() {
+        (String::new(), String::new())
+    } else {
+        let buttons: String = actions.iter()
+            .map(|action| format!("<action content=\"{}\" arguments=\"{}\" activationType=\"foreground\"/>", escape(&action.label), escape(&action.id)))
+            .collect();
+        (format!("<actions>{}</actions>", buttons), format!(
+            "; Register-ObjectEvent -InputObject $toast -EventName Activated -SourceIdentifier clicked > $null; \
+             Register-ObjectEvent -InputObject $toast -EventName Dismissed -SourceIdentifier dismissed > $null; \
+             $answer = Wait-Event -Timeout {}; \
+             if ($answer -and $answer.SourceIdentifier -eq 'clicked') {{ Write-Output $answer.SourceArgs[1].Arguments }}",
+            ACTION_WAIT_SECS
+        ))
+    };
+    let script = format!(
+        "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+         $xml = [Windows.Data.Xml.Dom.XmlDocument, Windows.Data.Xml.Dom.XmlDocument, ContentType = WindowsRuntime]::new(); \
+         $xml.LoadXml('<toast><visual><binding template=\"ToastGeneric\"><text>{}</text><text>{}</text></binding></visual>{}</toast>'); \
+         $toast = [Windows.UI.Notifications.ToastNotification]::new($xml); \
+         [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('Window Tracker').Show($toast){}",
+        escape(summary),
+        escape(body),
+        buttons,
+        wait
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    command
+}
@@ -0,0 +1,116 @@
+// Sends focus intervals to an OpenTelemetry collector over OTLP/HTTP with JSON encoding:
+// one span per interval, named after the app, plus cumulative counters for switches.
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::config::OtlpConfig;
+use crate::ipc::random_hex;
+use crate::storage::Interval;
+
+const METRICS_INTERVAL: Duration = Duration::from_secs(60);
+
+fn unix_nanos(time: DateTime<Utc>) -> String {
+    time.timestamp_nanos_opt().unwrap_or(0).to_string()
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+fn resource(config: &OtlpConfig) -> Value {
+    json!({ "attributes": [attribute("service.name", &config.service_name)] })
+}
+
+// None when the system can't give the random ids
+fn span(interval: &Interval) -> Option<Value> {
+    Some(json!({
+        "traceId": random_hex(16).ok()?,
+        "spanId": random_hex(8).ok()?,
+        "name": interval.app_name(),
+        "kind": 1,
+        "startTimeUnixNano": unix_nanos(interval.start),
+        "endTimeUnixNano": unix_nanos(interval.end),
+        "attributes": [
+            attribute("window.title", &interval.title),
+            attribute("window.id", &interval.window_id),
+            attribute("category", interval.category_name()),
+            attribute("event", interval.event.as_str()),
+        ],
+    }))
+}
+
+fn counter(name: &str, description: &str, value: u64, start: DateTime<Utc>) -> Value {
+    json!({
+        "name": name,
+        "description": description,
+        "sum": {
+            "aggregationTemporality": 2,
+            "isMonotonic": true,
+            "dataPoints": [{
+                "asInt": value.to_string(),
+                "startTimeUnixNano": unix_nanos(start),
+                "timeUnixNano": unix_nanos(Utc::now()),
+            }],
+        },
+    })
+}
+
+fn post(config: &OtlpConfig, path: &str, body: &Value) {
+    let url = format!("{}{}", config.endpoint.trim_end_matches('/'), path);
+    let result = ureq::post(&url)
+        .timeout(Duration::from_secs(10))
+        .set("Content-Type", "application/json")
+        .send_string(&body.to_string());
+    if let Err(e) = result {
+        eprintln!("Failed to export to {}: {}", url, e);
+    }
+}
+
+// Exports from a background thread so a slow collector never delays sampling
+pub fn start(config: &OtlpConfig) {
+    let (sender, receiver) = mpsc::channel::<Interval>();
+    crate::wt_add_interval_listener(Box::new(move |interval| {
+        let _ = sender.send(interval.clone());
+    }));
+
+    let config = config.clone();
+    let started = Utc::now();
+    thread::spawn(move || {
+        let scope = json!({ "name": "window_tracker", "version": env!("CARGO_PKG_VERSION") });
+        let mut last_metrics = Instant::now();
+        loop {
+            match receiver.recv_timeout(METRICS_INTERVAL.saturating_sub(last_metrics.elapsed())) {
+                Ok(interval) => {
+                    let spans: Vec<Value> = std::iter::once(interval).chain(receiver.try_iter()).filter_map(|interval| span(&interval)).collect();
+                    post(&config, "/v1/traces", &json!({
+                        "resourceSpans": [{ "resource": resource(&config), "scopeSpans": [{ "scope": scope, "spans": spans }] }],
+                    }));
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            if last_metrics.elapsed() >= METRICS_INTERVAL {
+                let counts = crate::wt_get_event_counts();
+                let metrics = [
+                    counter("window_tracker.switches", "Focus moves to another window", counts.switches, started),
+                    counter("window_tracker.title_changes", "Title changes within the focused window", counts.title_changes, started),
+                    counter(
+                        "window_tracker.evicted_windows",
+                        "Windows and titles dropped from memory after going unfocused",
+                        crate::wt_get_evicted_windows(),
+                        started,
+                    ),
+                ];
+                post(&config, "/v1/metrics", &json!({
+                    "resourceMetrics": [{ "resource": resource(&config), "scopeMetrics": [{ "scope": scope, "metrics": metrics }] }],
+                }));
+                last_metrics = Instant::now();
+            }
+        }
+    });
+}
@@ -0,0 +1,311 @@
+// Overlaps between imported intervals and the history, or between imported ones, as merging
+// exports from devices with clocks a little apart leaves them. Every stretch of time claimed by
+// more than one interval, an imported one among them, goes to:
+//   trim           the stored intervals, or the imported one that started first
+//   prefer-device  the intervals of the --prefer device, trim where it has none (stored
+//                  intervals have no device unless imported with one, --prefer local means them)
+//   split          all of them in equal parts, in the order they started
+// and keep stores them overlapping as they are. An interval left with time in several places is
+// split into one per stretch.
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::storage::Interval;
+
+// The attribute naming where an imported interval was tracked
+pub const DEVICE: &str = "device";
+// What intervals tracked here count as for --prefer
+pub const LOCAL: &str = "local";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    Keep,
+    Trim,
+    Prefer(String),
+    Split,
+}
+
+impl Resolution {
+    pub fn parse(name: &str, prefer: Option<&str>) -> Result<Self, String> {
+        match (name, prefer) {
+            ("keep", _) => Ok(Resolution::Keep),
+            ("trim", _) => Ok(Resolution::Trim),
+            ("split", _) => Ok(Resolution::Split),
+            ("prefer-device", Some(device)) => Ok(Resolution::Prefer(device.to_string())),
+            ("prefer-device", None) => Err("--overlap prefer-device needs --prefer <device>".to_string()),
+            (other, _) => Err(format!("unknown --overlap '{}', expected keep, trim, prefer-device or split", other)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Resolution::Keep => "keep",
+            Resolution::Trim => "trim",
+            Resolution::Prefer(_) => "prefer-device",
+            Resolution::Split => "split",
+        }
+    }
+}
+
+pub fn device(interval: &Interval) -> &str {
+    interval.attributes.get(DEVICE).map_or(LOCAL, String::as_str)
+}
+
+// An interval that lost time to another
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub interval: Interval,
+    pub stored: bool,
+    pub lost_secs: f64,
+    // What is left of it, none when it is gone
+    pub pieces: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Outcome {
+    // The imported intervals as they should be stored
+    pub imported: Vec<Interval>,
+    // Stored intervals to replace with what is left of them
+    pub replaced: Vec<(Interval, Vec<Interval>)>,
+    pub conflicts: Vec<Conflict>,
+    // Imported intervals sharing time with another, whatever became of it
+    pub overlapping: usize,
+}
+
+struct Claim<'a> {
+    interval: &'a Interval,
+    stored: bool,
+    overlapped: bool,
+    owned: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+impl Claim<'_> {
+    fn own(&mut self, from: DateTime<Utc>, to: DateTime<Utc>) {
+        match self.owned.last_mut() {
+            Some(last) if last.1 == from => last.1 = to,
+            _ => self.owned.push((from, to)),
+        }
+    }
+}
+
+// Who gets a stretch claimed by `active` (indices into claims, in the order they started)
+fn owners(resolution: &Resolution, claims: &[Claim], active: &[usize]) -> Vec<usize> {
+    let trim = || {
+        let stored: Vec<usize> = active.iter().copied().filter(|&i| claims[i].stored).collect();
+        if stored.is_empty() { vec![active[0]] } else { stored }
+    };
+    match resolution {
+        Resolution::Prefer(preferred) => {
+            let preferred: Vec<usize> = active.iter().copied().filter(|&i| device(claims[i].interval) == preferred).collect();
+            match preferred.first() {
+                Some(&first) if !claims[first].stored && preferred.iter().all(|&i| !claims[i].stored) => vec![first],
+                _ => trim(),
+            }
+        }
+        _ => trim(),
+    }
+}
+
+pub fn resolve(stored: &[Interval], imported: &[Interval], resolution: &Resolution) -> Outcome {
+    let mut claims: Vec<Claim> = stored.iter().map(|interval| (interval, true))
+        .chain(imported.iter().map(|interval| (interval, false)))
+        .map(|(interval, stored)| Claim { interval, stored, overlapped: false, owned: Vec::new() })
+        .collect();
+    // Stored first among those starting together, so they count as the earlier
+    claims.sort_by_key(|claim| (claim.interval.start, !claim.stored));
+
+    let mut bounds: Vec<DateTime<Utc>> = claims.iter().flat_map(|claim| [claim.interval.start, claim.interval.end]).collect();
+    bounds.sort();
+    bounds.dedup();
+
+    let mut active: Vec<usize> = Vec::new();
+    let mut next = 0;
+    for window in bounds.windows(2) {
+        let (from, to) = (window[0], window[1]);
+        active.retain(|&i| claims[i].interval.end > from);
+        while next < claims.len() && claims[next].interval.start <= from {
+            if claims[next].interval.end > from {
+                active.push(next);
+            }
+            next += 1;
+        }
+        if active.is_empty() {
+            continue;
+        }
+        if active.len() > 1 {
+            for &i in &active {
+                claims[i].overlapped = true;
+            }
+        }
+        if *resolution == Resolution::Keep || active.len() == 1 || active.iter().all(|&i| claims[i].stored) {
+            for &i in &active {
+                claims[i].own(from, to);
+            }
+            continue;
+        }
+        if *resolution == Resolution::Split {
+            let part = (to - from) / active.len() as i32;
+            for (n, &i) in active.iter().enumerate() {
+                let end = if n + 1 == active.len() { to } else { from + part * (n as i32 + 1) };
+                claims[i].own(from + part * n as i32, end);
+            }
+            continue;
+        }
+        for i in owners(resolution, &claims, &active) {
+            claims[i].own(from, to);
+        }
+    }
+
+    let mut outcome = Outcome::default();
+    for claim in claims {
+        if claim.overlapped && !claim.stored {
+            outcome.overlapping += 1;
+        }
+        let pieces: Vec<Interval> = claim.owned.iter()
+            .filter(|(from, to)| from < to)
+            .map(|&(start, end)| Interval { start, end, ..claim.interval.clone() })
+            .collect();
+        let whole = pieces.len() == 1 && pieces[0].start == claim.interval.start && pieces[0].end == claim.interval.end;
+        if !whole {
+            let kept: f64 = pieces.iter().map(Interval::duration_secs).sum();
+            outcome.conflicts.push(Conflict {
+                interval: claim.interval.clone(),
+                stored: claim.stored,
+                lost_secs: claim.interval.duration_secs() - kept,
+                pieces: pieces.len(),
+            });
+        }
+        if claim.stored {
+            if !whole {
+                outcome.replaced.push((claim.interval.clone(), pieces));
+            }
+        } else {
+            outcome.imported.extend(pieces);
+        }
+    }
+    outcome.conflicts.sort_by_key(|conflict| conflict.interval.start);
+    outcome.imported.sort_by_key(|interval| interval.start);
+    outcome
+}
+
+// Stored intervals by (start, end), for rewrite_intervals to find the replaced ones in
+pub fn index(replaced: &[(Interval, Vec<Interval>)]) -> HashMap<(DateTime<Utc>, DateTime<Utc>), Vec<usize>> {
+    let mut index: HashMap<_, Vec<usize>> = HashMap::new();
+    for (i, (interval, _)) in replaced.iter().enumerate() {
+        index.entry((interval.start, interval.end)).or_default().push(i);
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    fn at(secs: u64) -> DateTime<Utc> {
+        DateTime::from(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs))
+    }
+
+    fn span(title: &str, from: u64, to: u64) -> Interval {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        Interval::new(title, "app", start + Duration::from_secs(from), start + Duration::from_secs(to))
+    }
+
+    fn on(device: &str, mut interval: Interval) -> Interval {
+        interval.attributes.insert(DEVICE.to_string(), device.to_string());
+        interval
+    }
+
+    type Span<'a> = (&'a str, DateTime<Utc>, DateTime<Utc>);
+
+    fn spans(intervals: &[Interval]) -> Vec<Span<'_>> {
+        intervals.iter().map(|interval| (interval.title.as_str(), interval.start, interval.end)).collect()
+    }
+
+    // Each stored interval replaced, with what is left of it
+    fn replaced(outcome: &Outcome) -> Vec<(&str, Vec<Span<'_>>)> {
+        outcome.replaced.iter().map(|(interval, pieces)| (interval.title.as_str(), spans(pieces))).collect()
+    }
+
+    const ALL: [Resolution; 3] = [Resolution::Keep, Resolution::Trim, Resolution::Split];
+
+    #[test]
+    fn adjacent_intervals_are_no_overlap() {
+        let stored = [span("Stored", 0, 600)];
+        let imported = [span("After", 600, 1200), span("Next", 1200, 1800)];
+        for resolution in ALL.into_iter().chain([Resolution::Prefer("phone".to_string())]) {
+            let outcome = resolve(&stored, &imported, &resolution);
+            assert_eq!(spans(&outcome.imported), spans(&imported), "{}", resolution.name());
+            assert!(outcome.replaced.is_empty() && outcome.conflicts.is_empty());
+            assert_eq!(outcome.overlapping, 0);
+        }
+    }
+
+    #[test]
+    fn an_interval_touching_both_neighbours_fits_between_them() {
+        let stored = [span("Before", 0, 600), span("After", 1200, 1800)];
+        let outcome = resolve(&stored, &[span("Gap", 600, 1200)], &Resolution::Trim);
+        assert_eq!(spans(&outcome.imported), [("Gap", at(600), at(1200))]);
+        assert!(outcome.conflicts.is_empty());
+
+        // Reaching into both, it is cut back to their boundaries
+        let outcome = resolve(&stored, &[span("Wider", 300, 1500)], &Resolution::Trim);
+        assert_eq!(spans(&outcome.imported), [("Wider", at(600), at(1200))]);
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!((outcome.conflicts[0].lost_secs, outcome.conflicts[0].pieces), (600.0, 1));
+        assert!(outcome.replaced.is_empty());
+    }
+
+    #[test]
+    fn nested_intervals() {
+        // Imported inside a stored one: trimmed away, or sharing the stretch on split
+        let stored = [span("Outer", 0, 3600)];
+        let inner = [span("Inner", 600, 1200)];
+        let outcome = resolve(&stored, &inner, &Resolution::Trim);
+        assert!(outcome.imported.is_empty() && outcome.replaced.is_empty());
+        assert_eq!((outcome.conflicts[0].lost_secs, outcome.conflicts[0].pieces, outcome.overlapping), (600.0, 0, 1));
+
+        let outcome = resolve(&stored, &inner, &Resolution::Split);
+        assert_eq!(spans(&outcome.imported), [("Inner", at(900), at(1200))]);
+        assert_eq!(replaced(&outcome), [("Outer", vec![("Outer", at(0), at(900)), ("Outer", at(1200), at(3600))])]);
+
+        // A stored one inside an imported one leaves it in two pieces
+        let outcome = resolve(&inner, &[span("Outer", 0, 3600)], &Resolution::Trim);
+        assert_eq!(spans(&outcome.imported), [("Outer", at(0), at(600)), ("Outer", at(1200), at(3600))]);
+        assert_eq!((outcome.conflicts[0].lost_secs, outcome.conflicts[0].pieces), (600.0, 2));
+
+        // Between imported ones, the one that started first keeps it
+        let outcome = resolve(&[], &[span("Outer", 0, 3600), span("Inner", 600, 1200)], &Resolution::Trim);
+        assert_eq!(spans(&outcome.imported), [("Outer", at(0), at(3600))]);
+        assert_eq!(outcome.overlapping, 2);
+    }
+
+    #[test]
+    fn identical_intervals() {
+        let stored = [span("Stored", 0, 600)];
+        let imported = [on("phone", span("Imported", 0, 600))];
+
+        let outcome = resolve(&stored, &imported, &Resolution::Keep);
+        assert_eq!(spans(&outcome.imported), [("Imported", at(0), at(600))]);
+        assert!(outcome.replaced.is_empty() && outcome.conflicts.is_empty());
+        assert_eq!(outcome.overlapping, 1);
+
+        // The stored one counts as the earlier
+        let outcome = resolve(&stored, &imported, &Resolution::Trim);
+        assert!(outcome.imported.is_empty() && outcome.replaced.is_empty());
+
+        let outcome = resolve(&stored, &imported, &Resolution::Split);
+        assert_eq!(spans(&outcome.imported), [("Imported", at(300), at(600))]);
+        assert_eq!(replaced(&outcome), [("Stored", vec![("Stored", at(0), at(300))])]);
+
+        let outcome = resolve(&stored, &imported, &Resolution::Prefer("phone".to_string()));
+        assert_eq!(spans(&outcome.imported), [("Imported", at(0), at(600))]);
+        assert_eq!(replaced(&outcome), [("Stored", vec![])]);
+
+        // Preferring the local one is trimming
+        let outcome = resolve(&stored, &imported, &Resolution::Prefer(LOCAL.to_string()));
+        assert!(outcome.imported.is_empty() && outcome.replaced.is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+// Right after a focus change, to catch rapid switching
+pub const MIN_INTERVAL: Duration = Duration::from_millis(50);
+// Ceiling while the user is active but focus has been stable for a while
+pub const ADAPTIVE_MAX_INTERVAL: Duration = Duration::from_secs(2);
+pub const LOW_POWER_MAX_INTERVAL: Duration = Duration::from_secs(5);
+
+// Focus has to stay put and the user has to be away this long before low-power polling kicks in
+const LOW_POWER_AFTER: Duration = Duration::from_secs(30);
+
+// Polls at a tenth of how long the current window has held focus, so a switch shortly after a
+// change is caught within ~50ms while a window focused for 20s+ is only checked every 2s.
+pub fn adaptive_interval(focused_for: Duration) -> Duration {
+    (focused_for / 10).clamp(MIN_INTERVAL, ADAPTIVE_MAX_INTERVAL)
+}
+
+// On top of adaptive polling, keeps doubling the interval while nothing is happening and snaps
+// back on any focus or input activity. Without idle information the user is assumed present.
+pub fn low_power_interval(current: Duration, focused_for: Duration, idle_for: Option<Duration>) -> Duration {
+    let settled = focused_for >= LOW_POWER_AFTER && idle_for.is_some_and(|idle| idle >= LOW_POWER_AFTER);
+    if settled {
+        (current * 2).clamp(ADAPTIVE_MAX_INTERVAL, LOW_POWER_MAX_INTERVAL)
+    } else {
+        adaptive_interval(focused_for)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn adaptive_interval_clamps_between_50ms_and_2s() {
+        assert_eq!(adaptive_interval(Duration::ZERO), MIN_INTERVAL);
+        assert_eq!(adaptive_interval(ms(499)), MIN_INTERVAL);
+        assert_eq!(adaptive_interval(ms(500)), MIN_INTERVAL);
+        assert_eq!(adaptive_interval(ms(510)), ms(51));
+        assert_eq!(adaptive_interval(ms(19_990)), ms(1_999));
+        assert_eq!(adaptive_interval(ms(20_000)), ADAPTIVE_MAX_INTERVAL);
+        assert_eq!(adaptive_interval(Duration::from_secs(3600)), ADAPTIVE_MAX_INTERVAL);
+    }
+
+    #[test]
+    fn low_power_interval_backs_off_only_while_settled() {
+        let away = Some(LOW_POWER_AFTER);
+        assert_eq!(low_power_interval(ADAPTIVE_MAX_INTERVAL, LOW_POWER_AFTER, away), ms(4_000));
+        assert_eq!(low_power_interval(ms(4_000), LOW_POWER_AFTER, away), LOW_POWER_MAX_INTERVAL);
+        assert_eq!(low_power_interval(LOW_POWER_MAX_INTERVAL, Duration::from_secs(600), away), LOW_POWER_MAX_INTERVAL);
+        // Right after a switch it catches up like adaptive polling
+        assert_eq!(low_power_interval(MIN_INTERVAL, LOW_POWER_AFTER, away), ADAPTIVE_MAX_INTERVAL);
+        // Input, a recent switch or no idle information snap it back
+        assert_eq!(low_power_interval(LOW_POWER_MAX_INTERVAL, LOW_POWER_AFTER, Some(ms(200))), ADAPTIVE_MAX_INTERVAL);
+        assert_eq!(low_power_interval(LOW_POWER_MAX_INTERVAL, ms(1_000), away), ms(100));
+        assert_eq!(low_power_interval(LOW_POWER_MAX_INTERVAL, LOW_POWER_AFTER, None), ADAPTIVE_MAX_INTERVAL);
+    }
+
+    // Runs the tracker loop's polling over recorded focus changes (ms since the start), the way
+    // run_session sleeps between samples; each switch seen gives when and how late it was seen
+    fn replay(switches: &[u64], until: u64, low_power: bool, idle: impl Fn(u64) -> Option<Duration>) -> Vec<(u64, u64)> {
+        let (mut now, mut focused_since, mut interval) = (0, 0, MIN_INTERVAL);
+        let mut seen = Vec::new();
+        let mut pending = switches.iter().peekable();
+        while now < until {
+            let focused_for = ms(now - focused_since);
+            interval = if low_power {
+                low_power_interval(interval, focused_for, idle(now))
+            } else {
+                adaptive_interval(focused_for)
+            };
+            now += interval.as_millis() as u64;
+            // Switches between two samples are one change, to whichever window has focus last
+            let mut latest = None;
+            while let Some(switch) = pending.next_if(|switch| **switch <= now) {
+                latest = Some(*switch);
+            }
+            if let Some(switch) = latest {
+                seen.push((now, now - switch));
+                focused_since = now;
+            }
+        }
+        seen
+    }
+
+    // A burst of alt-tabs, some reading, and a window left alone
+    const RECORDED: [u64; 7] = [300, 420, 2_000, 2_030, 25_000, 26_000, 90_000];
+
+    #[test]
+    fn replay_catches_switches_within_the_interval_in_effect() {
+        let seen = replay(&RECORDED, 120_000, false, |_| None);
+        // The second alt-tab is seen on the next 50ms sample; after 23s on one window the visit at
+        // 25s is shorter than the 2s between samples and only the switch at 26s is seen
+        assert_eq!(seen, vec![(300, 0), (450, 30), (2_013, 13), (2_063, 33), (26_495, 495), (90_927, 927)]);
+        assert!(seen.iter().all(|(_, late)| ms(*late) <= ADAPTIVE_MAX_INTERVAL));
+    }
+
+    #[test]
+    fn replay_in_low_power_mode_waits_longer_once_settled() {
+        // The user went away at 30s and came back at 100s
+        let idle = |now: u64| Some(if (30_000..100_000).contains(&now) { ms(now - 30_000) } else { Duration::ZERO });
+        let seen = replay(&RECORDED, 120_000, true, idle);
+        assert_eq!(seen[..5], replay(&RECORDED, 120_000, false, |_| None)[..5]);
+        let (_, late) = seen[5];
+        assert!(ms(late) > ADAPTIVE_MAX_INTERVAL && ms(late) <= LOW_POWER_MAX_INTERVAL, "{:?}", seen);
+    }
+}
@@ -0,0 +1,238 @@
+// [power]: whether the machine runs on battery or AC, asked every check_secs. Every interval is
+// stored with field:power battery or ac (record = false leaves it out), for report --power and
+// report --by field:power, and while on battery the tracker samples like --low-power does unless
+// low_power_on_battery = false. Linux reads the power supplies in sysfs, leaving out the batteries
+// of mice and other devices, Windows asks GetSystemPowerStatus and macOS pmset. A machine without
+// a battery is on AC; where nothing is found no field:power is stored.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use crate::cli::Args;
+use crate::config::{Config, PowerConfig};
+use crate::filter::Filter;
+use crate::report;
+use crate::rollup;
+use crate::storage::{self, EventKind};
+use crate::table::{Format, Table};
+
+pub const POWER: &str = "power";
+pub const BATTERY: &str = "battery";
+pub const AC: &str = "ac";
+
+struct State {
+    config: PowerConfig,
+    // None until asked, and where the platform doesn't say
+    on_battery: Option<bool>,
+    // Bumped by each answer that differs, for PowerWatch
+    changes: u64,
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+pub fn check(config: &Config) -> Result<(), String> {
+    match &config.power {
+        Some(power) if power.check_secs == 0 => Err("[power] check_secs must be at least 1".to_string()),
+        _ => Ok(()),
+    }
+}
+
+pub fn configure(config: &Config) {
+    let mut state = STATE.lock().unwrap();
+    *state = config.power.clone().map(|power| {
+        let previous = state.take();
+        State {
+            config: power,
+            on_battery: previous.as_ref().and_then(|state| state.on_battery),
+            changes: previous.map_or(0, |state| state.changes),
+        }
+    });
+}
+
+// Asks the system from now on, every check_secs of the config at the time
+pub fn start() {
+    std::thread::spawn(|| loop {
+        let check_secs = match STATE.lock().unwrap().as_ref() {
+            Some(state) => state.config.check_secs,
+            None => return,
+        };
+        let on_battery = platform::on_battery();
+        if let Some(state) = STATE.lock().unwrap().as_mut() {
+            if state.on_battery != on_battery {
+                state.on_battery = on_battery;
+                state.changes += 1;
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_secs(check_secs.max(1)));
+    });
+}
+
+// What the open interval records, None without [power], with record = false or when it isn't known
+pub fn on_battery() -> Option<bool> {
+    STATE.lock().unwrap().as_ref().filter(|state| state.config.record).and_then(|state| state.on_battery)
+}
+
+// Whether the tracker loop samples in low-power mode for the battery
+pub fn low_power() -> bool {
+    STATE.lock().unwrap().as_ref().is_some_and(|state| state.config.low_power_on_battery && state.on_battery == Some(true))
+}
+
+// Lets the tracker loop know when the machine went on battery or back on AC
+#[derive(Default)]
+pub struct PowerWatch {
+    seen: u64,
+    low_power: bool,
+}
+
+impl PowerWatch {
+    pub fn check(&mut self) {
+        let changes = STATE.lock().unwrap().as_ref().map_or(0, |state| state.changes);
+        if changes == self.seen {
+            return;
+        }
+        self.seen = changes;
+        crate::wt_power_changed();
+        let low_power = low_power();
+        if low_power != self.low_power {
+            self.low_power = low_power;
+            if low_power {
+                eprintln!("On battery, sampling in low-power mode");
+            } else {
+                eprintln!("Off battery, sampling as before");
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Totals {
+    secs: f64,
+    switches: usize,
+    days: BTreeSet<chrono::NaiveDate>,
+    apps: HashMap<String, f64>,
+}
+
+// report --power: focus time on battery and on AC, its share of the time either was recorded,
+// switches to another window, on how many days and the app most of it went to
+pub fn report(args: &Args) -> Result<(), String> {
+    let format = match Format::from_args(args)? {
+        Some(format) => format,
+        None if args.json_output()? => Format::Json,
+        None => Format::Table,
+    };
+    let (from, to) = report::period(args)?;
+    let mut totals: BTreeMap<String, Totals> = BTreeMap::new();
+    storage::scan_matching(from.with_timezone(&chrono::Utc), to.with_timezone(&chrono::Utc), &Filter::from_args(args)?, |chunk| {
+        for interval in chunk {
+            let Some(source) = interval.attributes.get(POWER) else { continue };
+            let entry = totals.entry(source.clone()).or_default();
+            let secs = interval.duration_secs();
+            entry.secs += secs;
+            *entry.apps.entry(interval.app.clone()).or_default() += secs;
+            entry.days.insert(interval.start.with_timezone(&chrono::Local).date_naive());
+            if interval.event == EventKind::FocusChanged {
+                entry.switches += 1;
+            }
+        }
+        Ok(())
+    }).map_err(|e| format!("failed to read the history: {}", e))?;
+
+    let total: f64 = totals.values().map(|entry| entry.secs).sum();
+    let mut table = Table::new(&["power", "secs", "share_pct", "switches", "days", "top_app"]);
+    for (source, entry) in &totals {
+        let top_app = entry.apps.iter().max_by(|a, b| a.1.total_cmp(b.1)).map(|(app, _)| app.as_str()).unwrap_or_default();
+        table.push(vec![
+            source.as_str().into(),
+            rollup::rounded(entry.secs),
+            rollup::rounded(if total > 0.0 { entry.secs * 100.0 / total } else { 0.0 }),
+            entry.switches.into(),
+            entry.days.len().into(),
+            top_app.into(),
+        ]);
+    }
+    if table.is_empty() && format == Format::Table {
+        println!("No power source recorded in this period, [power] in the config records it");
+        return Ok(());
+    }
+    table.print(format);
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::path::Path;
+
+    fn read(supply: &Path, name: &str) -> String {
+        fs::read_to_string(supply.join(name)).map(|value| value.trim().to_string()).unwrap_or_default()
+    }
+
+    pub fn on_battery() -> Option<bool> {
+        let supplies: Vec<_> = fs::read_dir("/sys/class/power_supply").ok()?.filter_map(Result::ok).map(|entry| entry.path()).collect();
+        let (mut adapters, mut adapter_online, mut batteries, mut discharging) = (0, false, 0, false);
+        for supply in &supplies {
+            match read(supply, "type").as_str() {
+                "Mains" | "USB" => {
+                    adapters += 1;
+                    adapter_online |= read(supply, "online") == "1";
+                }
+                // A wireless mouse's battery has scope Device, the machine's has none or System
+                "Battery" if read(supply, "scope") != "Device" => {
+                    batteries += 1;
+                    discharging |= read(supply, "status") == "Discharging";
+                }
+                _ => {}
+            }
+        }
+        if adapter_online {
+            Some(false)
+        } else {
+            Some(batteries > 0 && (discharging || adapters > 0))
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    // BatteryFlag when there is no system battery
+    const NO_BATTERY: u8 = 128;
+
+    pub fn on_battery() -> Option<bool> {
+        let mut status = SYSTEM_POWER_STATUS::default();
+        unsafe { GetSystemPowerStatus(&mut status) }.ok()?;
+        if status.BatteryFlag == NO_BATTERY {
+            return Some(false);
+        }
+        // 0 offline, 1 online, 255 unknown
+        match status.ACLineStatus {
+            0 => Some(true),
+            1 => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+
+    // The first line says "Now drawing from 'AC Power'", 'Battery Power' or 'UPS Power'
+    pub fn on_battery() -> Option<bool> {
+        let output = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let source = text.lines().next()?.split('\'').nth(1)?;
+        match source {
+            "AC Power" => Some(false),
+            "Battery Power" => Some(true),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+mod platform {
+    pub fn on_battery() -> Option<bool> {
+        None
+    }
+}
@@ -0,0 +1,77 @@
+// Generates a PowerShell module around this binary, every function calling a command with -o json:
+//   window_tracker_concept powershell-module --output WindowTracker.psm1; Import-Module .\WindowTracker.psm1
+use crate::cli::Args;
+use crate::export;
+
+const MODULE: &str = r#"# Generated by `window_tracker_concept powershell-module`
+$script:WindowTracker = '@EXECUTABLE@'
+
+function Invoke-WindowTracker {
+    param([Parameter(Mandatory)][string[]] $Arguments)
+    $output = & $script:WindowTracker @Arguments -o json
+    if ($LASTEXITCODE -ne 0) {
+        throw "window tracker exited with code $LASTEXITCODE"
+    }
+    $output | ConvertFrom-Json
+}
+
+function Get-WindowTrackerReport {
+    [CmdletBinding()]
+    param(
+        [int] $Days = 7,
+        [ValidateSet('title', 'window')][string] $By = 'title',
+        [ValidateSet('day', 'week')][string] $Compare
+    )
+    if ($Compare) {
+        Invoke-WindowTracker -Arguments @('report', '--compare', $Compare)
+    } else {
+        Invoke-WindowTracker -Arguments @('report', '--days', $Days, '--by', $By)
+    }
+}
+
+function Get-WindowTrackerCurrent {
+    [CmdletBinding()]
+    param()
+    Invoke-WindowTracker -Arguments @('current')
+}
+
+function Get-WindowTrackerToday {
+    [CmdletBinding()]
+    param()
+    Invoke-WindowTracker -Arguments @('today')
+}
+
+function Suspend-WindowTracker {
+    [CmdletBinding()]
+    param()
+    Invoke-WindowTracker -Arguments @('pause')
+}
+
+function Resume-WindowTracker {
+    [CmdletBinding()]
+    param()
+    Invoke-WindowTracker -Arguments @('resume')
+}
+
+function Invoke-WindowTrackerQuery {
+    [CmdletBinding()]
+    param([Parameter(Mandatory, Position = 0)][string] $Sql)
+    Invoke-WindowTracker -Arguments @('query', $Sql)
+}
+
+function Backup-WindowTracker {
+    [CmdletBinding()]
+    param([Parameter(Mandatory, Position = 0)][string] $Path)
+    Invoke-WindowTracker -Arguments @('backup', $Path)
+}
+
+Export-ModuleMember -Function Get-WindowTrackerReport, Get-WindowTrackerCurrent, Get-WindowTrackerToday,
+    Suspend-WindowTracker, Resume-WindowTracker, Invoke-WindowTrackerQuery, Backup-WindowTracker
+"#;
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let executable = std::env::current_exe().map_err(|e| format!("failed to locate the executable: {}", e))?;
+    // Single-quoted PowerShell strings only need their quotes doubled
+    let executable = executable.display().to_string().replace('\'', "''");
+    export::write_output(args, &MODULE.replace("@EXECUTABLE@", &executable))
+}
@@ -0,0 +1,187 @@
+// Sets a Slack or Discord status from what the tracker sees, e.g. "deep work" after 15 minutes
+// in the editor, cleared on idle. `status-updates off` is the kill switch for the running tracker.
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde_json::json;
+
+use crate::cli::Args;
+use crate::config::StatusRule;
+use crate::storage;
+
+const OFF_FILE: &str = "status-off";
+
+fn off_path() -> PathBuf {
+    storage::base_data_dir().join(OFF_FILE)
+}
+
+pub fn is_switched_off() -> bool {
+    off_path().exists()
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let on = match args.arg(1) {
+        Some("on") => true,
+        Some("off") => false,
+        _ => return Err("usage: status-updates <on|off>".to_string()),
+    };
+    if on {
+        match fs::remove_file(off_path()) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("failed to switch status updates on: {}", e)),
+        }
+    } else {
+        let directory = storage::base_data_dir();
+        fs::create_dir_all(&directory).map_err(|e| format!("failed to create {}: {}", directory.display(), e))?;
+        fs::write(off_path(), "").map_err(|e| format!("failed to switch status updates off: {}", e))?;
+    }
+
+    if args.json_output()? {
+        println!("{}", json!({ "status_updates": on }));
+    } else if on {
+        println!("Status updates on");
+    } else {
+        println!("Status updates off, the running tracker clears the status");
+    }
+    Ok(())
+}
+
+// The status that should show: the first rule for the focused category once it held focus long
+// enough, nothing while idle
+pub fn wanted(rules: &[StatusRule], category: &str, focused_secs: u64, idle: bool) -> Option<usize> {
+    if idle {
+        return None;
+    }
+    rules.iter().position(|rule| rule.category.eq_ignore_ascii_case(category) && focused_secs >= rule.after_mins * 60)
+}
+
+#[cfg(feature = "status")]
+pub use updater::start;
+
+#[cfg(feature = "status")]
+mod updater {
+    use std::sync::mpsc::{self, RecvTimeoutError};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use serde_json::{json, Value};
+
+    use super::{is_switched_off, wanted};
+    use crate::config::{StatusAccount, StatusConfig, StatusRule};
+
+    const TICK: Duration = Duration::from_secs(5);
+
+    fn token(account: &StatusAccount) -> Result<String, String> {
+        std::env::var(&account.token_env).map_err(|_| format!("set {} to a user token", account.token_env))
+    }
+
+    fn error_text(e: ureq::Error) -> String {
+        match e {
+            ureq::Error::Status(code, response) => format!("{} {}", code, response.into_string().unwrap_or_default().trim()),
+            other => other.to_string(),
+        }
+    }
+
+    fn set_slack(account: &StatusAccount, rule: Option<&StatusRule>) -> Result<(), String> {
+        let profile = json!({
+            "status_text": rule.map_or("", |rule| rule.text.as_str()),
+            "status_emoji": rule.map_or("", |rule| rule.emoji.as_str()),
+            "status_expiration": 0,
+        });
+        let response: Value = ureq::post("https://slack.com/api/users.profile.set")
+            .timeout(Duration::from_secs(10))
+            .set("Authorization", &format!("Bearer {}", token(account)?))
+            .set("Content-Type", "application/json; charset=utf-8")
+            .send_string(&json!({ "profile": profile }).to_string())
+            .map_err(error_text)?
+            .into_string()
+            .ok()
+            .and_then(|body| serde_json::from_str(&body).ok())
+            .unwrap_or_default();
+        // Slack answers 200 with ok: false for bad tokens and missing scopes
+        match response["ok"].as_bool() {
+            Some(true) => Ok(()),
+            _ => Err(response["error"].as_str().unwrap_or("unexpected response").to_string()),
+        }
+    }
+
+    fn set_discord(account: &StatusAccount, rule: Option<&StatusRule>) -> Result<(), String> {
+        let custom_status = match rule {
+            Some(rule) if rule.emoji.is_empty() => json!({ "text": rule.text }),
+            Some(rule) => json!({ "text": rule.text, "emoji_name": rule.emoji }),
+            None => Value::Null,
+        };
+        ureq::request("PATCH", "https://discord.com/api/v9/users/@me/settings")
+            .timeout(Duration::from_secs(10))
+            .set("Authorization", &token(account)?)
+            .set("Content-Type", "application/json")
+            .send_string(&json!({ "custom_status": custom_status }).to_string())
+            .map_err(error_text)?;
+        Ok(())
+    }
+
+    fn apply(config: &StatusConfig, rule: Option<&StatusRule>) {
+        let text = rule.map_or("cleared".to_string(), |rule| format!("'{}'", rule.text));
+        if let Some(slack) = &config.slack {
+            if let Err(e) = set_slack(slack, rule) {
+                eprintln!("Failed to set the Slack status to {}: {}", text, e);
+            }
+        }
+        if let Some(discord) = &config.discord {
+            if let Err(e) = set_discord(discord, rule) {
+                eprintln!("Failed to set the Discord status to {}: {}", text, e);
+            }
+        }
+    }
+
+    // Follows the focus events from a background thread, so the chat APIs never delay sampling
+    pub fn start(config: &StatusConfig, idle_secs: u64) {
+        let (sender, receiver) = mpsc::channel::<String>();
+        crate::wt_add_focus_listener(Box::new(move |_, window| {
+            let _ = sender.send(crate::wt_categorize(window));
+        }));
+
+        let config = config.clone();
+        let min_interval = Duration::from_secs(config.min_interval_secs);
+        thread::spawn(move || {
+            let mut category: Option<(String, Instant)> = None;
+            // Nothing is touched until a rule first applies, so a status set by hand stays
+            let mut shown: Option<usize> = None;
+            let mut last_update: Option<Instant> = None;
+            loop {
+                match receiver.recv_timeout(TICK) {
+                    Ok(focused) => {
+                        for focused in std::iter::once(focused).chain(receiver.try_iter()) {
+                            if category.as_ref().is_none_or(|(current, _)| *current != focused) {
+                                category = Some((focused, Instant::now()));
+                            }
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+
+                let idle = crate::wt_is_paused() || crate::wt_get_idle_time().is_some_and(|idle| idle.as_secs() >= idle_secs);
+                if idle {
+                    // Coming back starts the streak over
+                    if let Some((_, since)) = &mut category {
+                        *since = Instant::now();
+                    }
+                }
+                let off = !config.enabled || is_switched_off();
+                let next = match &category {
+                    Some((name, since)) if !off => wanted(&config.rules, name, since.elapsed().as_secs(), idle),
+                    _ => None,
+                };
+                if next == shown || (!off && last_update.is_some_and(|last| last.elapsed() < min_interval)) {
+                    continue;
+                }
+                apply(&config, next.map(|index| &config.rules[index]));
+                shown = next;
+                last_update = Some(Instant::now());
+            }
+        });
+    }
+}
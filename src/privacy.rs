@@ -0,0 +1,27 @@
+// privacy = "apps" in config.toml: only which app is focused and the category it gets are recorded.
+// Titles are dropped on the sampler thread as each platform query returns, so they never reach the
+// live display, listeners, title templates or the history. [[categories]] titles and fields match
+// nothing then, apps still do.
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::{Config, Privacy};
+use crate::ActiveWindow;
+
+static APPS_ONLY: AtomicBool = AtomicBool::new(false);
+
+pub fn configure(config: &Config) {
+    APPS_ONLY.store(config.privacy == Privacy::Apps, Ordering::Relaxed);
+}
+
+pub fn apps_only() -> bool {
+    APPS_ONLY.load(Ordering::Relaxed)
+}
+
+// The window as the tracker may see it
+pub fn strip(mut window: ActiveWindow) -> ActiveWindow {
+    // The placeholders for nothing focused or measured tell nothing about content
+    if apps_only() && !window.is_placeholder() {
+        window.title.clear();
+    }
+    window
+}
@@ -0,0 +1,66 @@
+// Named profiles ("work", "personal") keep their own history and config under profiles/<name>.
+// Without a profile the top-level files are used, so an existing setup is the default profile.
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::cli::Args;
+
+pub const DEFAULT: &str = "default";
+
+static ACTIVE: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn validate(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("invalid profile name '{}', use letters, digits, '-' and '_'", name));
+    }
+    Ok(())
+}
+
+pub fn set_active(name: &str) -> Result<(), String> {
+    validate(name)?;
+    *ACTIVE.lock().unwrap() = (name != DEFAULT).then(|| name.to_string());
+    Ok(())
+}
+
+// --profile, else WT_PROFILE
+pub fn from_args(args: &Args) -> Result<(), String> {
+    let name = args.value("--profile").map(str::to_string).or_else(|| std::env::var("WT_PROFILE").ok());
+    match name {
+        Some(name) => set_active(&name),
+        None => Ok(()),
+    }
+}
+
+pub fn is_explicit() -> bool {
+    ACTIVE.lock().unwrap().is_some()
+}
+
+pub fn name() -> String {
+    ACTIVE.lock().unwrap().clone().unwrap_or_else(|| DEFAULT.to_string())
+}
+
+// Profiles that have stored data, sorted
+pub fn names() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(crate::storage::base_data_dir().join("profiles")) else { return Vec::new() };
+    let mut names: Vec<String> = entries.filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| validate(name).is_ok())
+        .collect();
+    names.sort();
+    names
+}
+
+// The profile's copy of a top-level directory
+pub fn scoped_dir(base: PathBuf) -> PathBuf {
+    match ACTIVE.lock().unwrap().as_deref() {
+        Some(name) => base.join("profiles").join(name),
+        None => base,
+    }
+}
+
+// The profile's copy of a top-level file, e.g. profiles/work/config.toml next to config.toml
+pub fn scoped_file(path: PathBuf) -> PathBuf {
+    let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else { return path };
+    scoped_dir(parent.to_path_buf()).join(file_name)
+}
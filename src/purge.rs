@@ -0,0 +1,154 @@
+// `purge`: deletes history precisely, by date, app or title, or all of a profile's data with --all.
+// Thumbnails taken during the deleted time go with it. Without --force it only tells what would go.
+use std::cell::RefCell;
+use std::fs;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::{Regex, RegexBuilder};
+use serde_json::json;
+
+use crate::cli::Args;
+use crate::filter::Filter;
+use crate::profile;
+use crate::report::local_midnight;
+use crate::storage::{self, Interval, Rewritten};
+use crate::thumbnails;
+use crate::visibility::{self, Layer};
+
+struct Selection {
+    before: Option<DateTime<Utc>>,
+    app: Option<Regex>,
+    title: Option<Regex>,
+    filter: Filter,
+}
+
+impl Selection {
+    fn from_args(args: &Args) -> Result<Self, String> {
+        let before = args.value("--before")
+            .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map(|date| local_midnight(date).with_timezone(&Utc))
+                .map_err(|_| format!("invalid date '{}' for --before, expected yyyy-mm-dd", date)))
+            .transpose()?;
+        let pattern = |name: &str| args.value(name)
+            .map(|pattern| RegexBuilder::new(pattern).case_insensitive(true).build()
+                .map_err(|e| format!("invalid pattern '{}' for {}: {}", pattern, name, e)))
+            .transpose();
+        let selection = Selection { before, app: pattern("--app")?, title: pattern("--title")?, filter: Filter::from_args(args)? };
+        if selection.before.is_none() && selection.app.is_none() && selection.title.is_none() && selection.filter.is_empty() {
+            return Err("usage: purge --before <yyyy-mm-dd> | --app <pattern> | --title <pattern> | --filter <expr> | --all [--force]".to_string());
+        }
+        Ok(selection)
+    }
+
+    // The interval without the selected part: an interval running past --before keeps the rest
+    fn keep(&self, interval: &Interval) -> Option<Interval> {
+        let selected = self.app.as_ref().is_none_or(|pattern| pattern.is_match(interval.app_name()))
+            && self.title.as_ref().is_none_or(|pattern| pattern.is_match(&interval.title))
+            && self.filter.matches(interval);
+        match self.before {
+            _ if !selected => Some(interval.clone()),
+            None => None,
+            Some(before) if interval.end <= before => None,
+            Some(before) if interval.start < before => Some(Interval { start: before, ..interval.clone() }),
+            Some(_) => Some(interval.clone()),
+        }
+    }
+
+    // keep() with the time it deletes from the interval
+    fn keep_noting(&self, interval: &Interval, deleted: &RefCell<Vec<(DateTime<Utc>, DateTime<Utc>)>>) -> Option<Interval> {
+        let kept = self.keep(interval);
+        match &kept {
+            None => deleted.borrow_mut().push((interval.start, interval.end)),
+            Some(kept) if kept.start > interval.start => deleted.borrow_mut().push((interval.start, kept.start)),
+            Some(_) => {}
+        }
+        kept
+    }
+}
+
+// What the tracker stores is JSON, JSON lines and SQLite, plus what restore set aside; the
+// extension-less control files belong to a running tracker
+fn is_profile_data(name: &str) -> bool {
+    [".json", ".jsonl", ".db", ".tmp", ".before-restore"].iter().any(|extension| name.ends_with(extension))
+}
+
+fn purge_all(force: bool) -> Result<Vec<String>, String> {
+    let directory = storage::data_dir();
+    let entries = match fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut files: Vec<String> = thumbnails::delete(force, |_| true)?.iter().map(|path| path.display().to_string()).collect();
+    for entry in entries.filter_map(Result::ok) {
+        let is_file = entry.file_type().is_ok_and(|kind| kind.is_file());
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !is_file || !is_profile_data(&name) {
+            continue;
+        }
+        if force {
+            fs::remove_file(entry.path()).map_err(|e| format!("failed to delete {}: {}", entry.path().display(), e))?;
+        }
+        files.push(entry.path().display().to_string());
+    }
+    files.sort();
+    Ok(files)
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let force = args.flag("--force");
+    let json_output = args.json_output()?;
+
+    if args.flag("--all") {
+        let files = purge_all(force)?;
+        if json_output {
+            println!("{}", json!({ "profile": profile::name(), "deleted": force, "files": files }));
+        } else if files.is_empty() {
+            println!("Profile '{}' has no stored data", profile::name());
+        } else {
+            let verb = if force { "Deleted" } else { "Would delete" };
+            for file in &files {
+                println!("{} {}", verb, file);
+            }
+            if force {
+                println!("The config is kept, delete {} yourself to remove it too", crate::config::config_path().display());
+            } else {
+                println!("Pass --force to delete all of profile '{}' data", profile::name());
+            }
+        }
+        return Ok(());
+    }
+
+    let selection = Selection::from_args(args)?;
+    // The focused time taken out, by the main history only
+    let deleted = RefCell::new(Vec::new());
+    let rewritten = if force {
+        // Visible and floating windows have titles too
+        for layer in [Layer::Visible, Layer::Floating] {
+            visibility::rewrite(layer, |interval| selection.keep(interval)).map_err(|e| format!("failed to purge visible windows: {}", e))?;
+        }
+        storage::rewrite_intervals(|interval| selection.keep_noting(interval, &deleted)).map_err(|e| format!("failed to purge history: {}", e))?
+    } else {
+        let intervals = storage::load_intervals(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC)
+            .map_err(|e| format!("failed to read history: {}", e))?;
+        let mut rewritten = Rewritten::default();
+        for interval in &intervals {
+            rewritten.count(interval, selection.keep_noting(interval, &deleted).as_ref());
+        }
+        rewritten
+    };
+    let deleted = deleted.into_inner();
+    let thumbnails = thumbnails::delete(force, |taken| deleted.iter().any(|(start, end)| *start <= taken && taken < *end))?.len();
+
+    let thumbnails_note = match thumbnails {
+        0 => String::new(),
+        count => format!(" with {} thumbnails", count),
+    };
+    if json_output {
+        println!("{}", json!({ "deleted": force, "removed": rewritten.removed, "shortened": rewritten.changed, "thumbnails": thumbnails }));
+    } else if force {
+        println!("Deleted {} intervals and shortened {}{}", rewritten.removed, rewritten.changed, thumbnails_note);
+    } else {
+        println!("Would delete {} intervals and shorten {}{}, pass --force to do it", rewritten.removed, rewritten.changed, thumbnails_note);
+    }
+    Ok(())
+}
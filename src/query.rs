@@ -0,0 +1,52 @@
+use crate::cli::Args;
+use crate::table::Format;
+
+// Runs read-only SQL against the interval table, e.g.
+// query "SELECT app, COUNT(*) FROM intervals GROUP BY app" --format csv
+pub fn run(args: &Args) -> Result<(), String> {
+    let sql = args.arg(1).ok_or("usage: query \"SELECT ...\" [--format table|json|csv|tsv|markdown]")?;
+    let default_format = if args.json_output()? { Format::Json } else { Format::Table };
+    run_sql(sql, Format::from_args(args)?.unwrap_or(default_format))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn run_sql(_sql: &str, _format: Format) -> Result<(), String> {
+    Err("the query command needs the SQLite backend, rebuild with --features sqlite".to_string())
+}
+
+#[cfg(feature = "sqlite")]
+fn run_sql(sql: &str, format: Format) -> Result<(), String> {
+    use rusqlite::types::ValueRef;
+
+    use crate::storage::sqlite;
+    use crate::table::Table;
+
+    let connection = sqlite::open_read_only().map_err(|e| format!("failed to open database: {}", e))?;
+    let mut statement = connection.prepare(sql).map_err(|e| e.to_string())?;
+    if !statement.readonly() {
+        return Err("only read-only statements can be run through query".to_string());
+    }
+
+    let columns: Vec<String> = statement.column_names().iter().map(|name| name.to_string()).collect();
+    let mut rows = statement.query([]).map_err(|e| e.to_string())?;
+    let mut table = Table::with_columns(columns.clone());
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let mut record = Vec::with_capacity(columns.len());
+        for i in 0..columns.len() {
+            record.push(match row.get_ref(i).map_err(|e| e.to_string())? {
+                ValueRef::Null => serde_json::Value::Null,
+                ValueRef::Integer(n) => n.into(),
+                ValueRef::Real(n) => n.into(),
+                ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned().into(),
+                ValueRef::Blob(blob) => format!("<{} byte blob>", blob.len()).into(),
+            });
+        }
+        table.push(record);
+    }
+
+    table.print(format);
+    if format == Format::Table {
+        println!("({} rows)", table.len());
+    }
+    Ok(())
+}
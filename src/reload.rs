@@ -0,0 +1,139 @@
+// Picks up edits to config.toml while tracking. The directory is watched rather than the file,
+// since editors often save by replacing the file.
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+
+use ::notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::config::{self, Config};
+
+pub struct ConfigWatch {
+    path: PathBuf,
+    events: Receiver<::notify::Result<::notify::Event>>,
+    // Dropping the watcher stops the events
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatch {
+    pub fn start() -> Result<Self, String> {
+        let path = config::config_path();
+        let directory = path.parent().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+        std::fs::create_dir_all(&directory).map_err(|e| format!("failed to create {}: {}", directory.display(), e))?;
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = ::notify::recommended_watcher(sender).map_err(|e| format!("failed to watch the config: {}", e))?;
+        watcher.watch(&directory, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("failed to watch {}: {}", directory.display(), e))?;
+        Ok(ConfigWatch { path, events, _watcher: watcher })
+    }
+
+    // The new config when the file changed and still parses; a broken edit keeps the old one
+    pub fn check(&self) -> Option<Config> {
+        let changed = self.events.try_iter()
+            .filter_map(Result::ok)
+            .any(|event| event.paths.iter().any(|path| path.file_name() == self.path.file_name()));
+        if !changed {
+            return None;
+        }
+
+        match config::load() {
+            Ok(config) => Some(config),
+            Err(e) => {
+                eprintln!("Ignoring config change: {}", e);
+                None
+            }
+        }
+    }
+}
+
+// Human-readable differences, for the reload log line
+pub fn describe_changes(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+    if old.checkpoint_secs != new.checkpoint_secs {
+        changes.push(format!("checkpoint_secs {} -> {}", old.checkpoint_secs, new.checkpoint_secs));
+    }
+    if old.idle_secs != new.idle_secs {
+        changes.push(format!("idle_secs {} -> {}", old.idle_secs, new.idle_secs));
+    }
+    if old.title_debounce_ms != new.title_debounce_ms {
+        changes.push(format!("title_debounce_ms {} -> {}", old.title_debounce_ms, new.title_debounce_ms));
+    }
+    if old.aggregate_by != new.aggregate_by {
+        changes.push(format!("aggregate_by {} -> {}", old.aggregate_by.as_str(), new.aggregate_by.as_str()));
+    }
+    if old.privacy != new.privacy {
+        changes.push(format!("privacy {} -> {}", old.privacy.as_str(), new.privacy.as_str()));
+    }
+    if old.app_icons != new.app_icons {
+        changes.push(format!("app_icons {} -> {}", old.app_icons, new.app_icons));
+    }
+    if old.categories != new.categories {
+        let names = |config: &Config| config.categories.iter().map(|rule| rule.name.as_str()).collect::<Vec<_>>().join(", ");
+        changes.push(format!("categories [{}] -> [{}]", names(old), names(new)));
+    }
+    if old.ignore != new.ignore {
+        changes.push(format!("ignore rules {} -> {}", old.ignore.len(), new.ignore.len()));
+    }
+    if old.merge != new.merge {
+        changes.push(format!("merge rules {} -> {}", old.merge.len(), new.merge.len()));
+    }
+    if old.title_templates != new.title_templates {
+        changes.push(format!("title templates {} -> {}", old.title_templates.len(), new.title_templates.len()));
+    }
+    if old.schedule != new.schedule {
+        changes.push(format!("schedule {} -> {} jobs", old.schedule.len(), new.schedule.len()));
+    }
+    if old.goals != new.goals {
+        changes.push(format!("goals {} -> {}", old.goals.len(), new.goals.len()));
+    }
+    if old.enrichers != new.enrichers {
+        changes.push(format!("enrichers {} -> {}", old.enrichers.len(), new.enrichers.len()));
+    }
+    if old.remote != new.remote {
+        changes.push("remote clients changed".to_string());
+    }
+    if old.memory != new.memory {
+        changes.push("memory limits changed".to_string());
+    }
+    if old.thumbnails != new.thumbnails {
+        changes.push("thumbnails changed".to_string());
+    }
+    if old.storage != new.storage {
+        changes.push("storage flush policy changed".to_string());
+    }
+    if old.data_dir != new.data_dir {
+        changes.push("data_dir changed, restart tracking to apply it".to_string());
+    }
+    if old.otlp != new.otlp {
+        changes.push("otlp changed, restart tracking to apply it".to_string());
+    }
+    if old.influx != new.influx {
+        changes.push("influx changed, restart tracking to apply it".to_string());
+    }
+    if old.ipc != new.ipc {
+        changes.push("ipc changed, restart tracking to apply it".to_string());
+    }
+    if old.statsd != new.statsd {
+        changes.push("statsd changed, restart tracking to apply it".to_string());
+    }
+    if old.status != new.status {
+        changes.push("status changed, restart tracking to apply it".to_string());
+    }
+    let (old_health, new_health) = (old.health.clone().unwrap_or_default(), new.health.clone().unwrap_or_default());
+    if old_health.stale_secs != new_health.stale_secs {
+        changes.push(format!("health stale_secs {} -> {}", old_health.stale_secs, new_health.stale_secs));
+    }
+    if old_health.queries_per_minute != new_health.queries_per_minute {
+        changes.push(format!("api queries_per_minute {} -> {}", old_health.queries_per_minute, new_health.queries_per_minute));
+    }
+    if old_health.address != new_health.address || (old_health.tls_cert, old_health.tls_key) != (new_health.tls_cert, new_health.tls_key) {
+        changes.push("health address or TLS changed, restart tracking to apply it".to_string());
+    }
+    if old_health.tokens != new_health.tokens {
+        changes.push(format!("api tokens {} -> {}", old_health.tokens.len(), new_health.tokens.len()));
+    }
+    if old.websocket != new.websocket {
+        changes.push("websocket changed, restart tracking to apply it".to_string());
+    }
+    changes
+}
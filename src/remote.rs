@@ -0,0 +1,165 @@
+// [remote]: time in a remote desktop or VM client is recorded under the machine it shows, like
+// "RDP: build-server" or "VirtualBox: Ubuntu 24.04", instead of the client's own window title.
+// Each client is a plugin that knows its apps and where the connection name sits in their titles.
+use crate::config::Config;
+use crate::ActiveWindow;
+
+pub trait RemoteClient: Send + Sync {
+    // As listed in [remote] clients
+    fn name(&self) -> &'static str;
+    // Before the connection name in the recorded title
+    fn label(&self) -> &'static str;
+    fn handles(&self, app: &str) -> bool;
+    // The host or VM name from a title of one of its windows, None for its other windows
+    fn connection(&self, title: &str) -> Option<String>;
+}
+
+// The process name on Windows and X11, the app name on macOS
+fn app_is(app: &str, names: &[&str]) -> bool {
+    let app = app.to_lowercase();
+    let app = app.strip_suffix(".exe").unwrap_or(&app);
+    names.iter().any(|name| name.eq_ignore_ascii_case(app))
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+// "build-server - Remote Desktop Connection" from mstsc, "FreeRDP: build-server" from xfreerdp,
+// the Windows and macOS Remote Desktop apps title session windows with the PC name alone
+struct Rdp;
+
+impl RemoteClient for Rdp {
+    fn name(&self) -> &'static str {
+        "rdp"
+    }
+
+    fn label(&self) -> &'static str {
+        "RDP"
+    }
+
+    fn handles(&self, app: &str) -> bool {
+        app_is(app, &["mstsc", "msrdc", "xfreerdp", "wlfreerdp", "Microsoft Remote Desktop", "Windows App"])
+    }
+
+    fn connection(&self, title: &str) -> Option<String> {
+        if let Some(host) = title.strip_suffix(" - Remote Desktop Connection") {
+            return non_empty(host);
+        }
+        if let Some(host) = title.strip_prefix("FreeRDP:") {
+            return non_empty(host);
+        }
+        // The connection list and settings windows
+        let clients = ["Remote Desktop Connection", "Remote Desktop", "Microsoft Remote Desktop", "Windows App"];
+        if clients.iter().any(|client| title.eq_ignore_ascii_case(client)) {
+            return None;
+        }
+        non_empty(title)
+    }
+}
+
+// "Ubuntu - VMware Workstation", also the Player and Fusion editions
+struct Vmware;
+
+impl RemoteClient for Vmware {
+    fn name(&self) -> &'static str {
+        "vmware"
+    }
+
+    fn label(&self) -> &'static str {
+        "VMware"
+    }
+
+    fn handles(&self, app: &str) -> bool {
+        app_is(app, &["vmware", "vmplayer", "VMware Fusion"])
+    }
+
+    fn connection(&self, title: &str) -> Option<String> {
+        title.rsplit_once(" - VMware").and_then(|(vm, _)| non_empty(vm))
+    }
+}
+
+// "Ubuntu 24.04 [Running] - Oracle VM VirtualBox"
+struct VirtualBox;
+
+impl RemoteClient for VirtualBox {
+    fn name(&self) -> &'static str {
+        "virtualbox"
+    }
+
+    fn label(&self) -> &'static str {
+        "VirtualBox"
+    }
+
+    fn handles(&self, app: &str) -> bool {
+        app_is(app, &["VirtualBoxVM", "VirtualBox"])
+    }
+
+    fn connection(&self, title: &str) -> Option<String> {
+        let (vm, _) = title.rsplit_once(" - Oracle")?;
+        // Without the machine state
+        non_empty(vm.rsplit_once(" [").map_or(vm, |(vm, _)| vm))
+    }
+}
+
+// VM windows are titled with the VM name alone, the Control Center is the app's other window
+struct Parallels;
+
+impl RemoteClient for Parallels {
+    fn name(&self) -> &'static str {
+        "parallels"
+    }
+
+    fn label(&self) -> &'static str {
+        "Parallels"
+    }
+
+    fn handles(&self, app: &str) -> bool {
+        app_is(app, &["Parallels Desktop", "prl_client_app"])
+    }
+
+    fn connection(&self, title: &str) -> Option<String> {
+        if title.eq_ignore_ascii_case("Control Center") || title.eq_ignore_ascii_case("Parallels Desktop") {
+            return None;
+        }
+        non_empty(title)
+    }
+}
+
+const BUILT_IN: &[&dyn RemoteClient] = &[&Rdp, &Vmware, &VirtualBox, &Parallels];
+
+#[derive(Default)]
+pub struct RemoteLabels {
+    clients: Vec<&'static dyn RemoteClient>,
+}
+
+impl RemoteLabels {
+    // Every built-in client for a [remote] section without clients, none without the section
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let Some(remote) = &config.remote else { return Ok(RemoteLabels::default()) };
+        if remote.clients.is_empty() {
+            return Ok(RemoteLabels { clients: BUILT_IN.to_vec() });
+        }
+        let clients = remote.clients.iter()
+            .map(|name| {
+                BUILT_IN.iter().copied().find(|client| client.name().eq_ignore_ascii_case(name)).ok_or_else(|| {
+                    let names: Vec<&str> = BUILT_IN.iter().map(|client| client.name()).collect();
+                    format!("unknown remote client '{}', expected one of {}", name, names.join(", "))
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(RemoteLabels { clients })
+    }
+
+    // The window titled "RDP: host" and so on when a client shows a connection in it
+    pub fn apply(&self, mut window: ActiveWindow) -> ActiveWindow {
+        let connection = self.clients.iter()
+            .filter(|client| client.handles(&window.app))
+            .find_map(|client| client.connection(&window.title).map(|connection| (client.label(), connection)));
+        if let Some((label, connection)) = connection {
+            window.title = format!("{}: {}", label, connection);
+        }
+        window
+    }
+}
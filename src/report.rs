@@ -0,0 +1,1222 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use serde_json::json;
+
+use crate::activity::{self, FocusBlock};
+use crate::anomaly::{self, Anomaly};
+use crate::calls::{self, MeetingApp};
+use crate::categories::CategoryStyles;
+use crate::cli::{self, Args};
+use crate::config::{self, AggregateBy};
+use crate::dnd;
+use crate::filter::Filter;
+use crate::games;
+use crate::humanize;
+use crate::i18n::{self, tr};
+use crate::icons;
+use crate::languages::{self, Languages};
+#[cfg(feature = "server")]
+use crate::mail;
+use crate::merge::Merges;
+use crate::notify;
+use crate::power;
+use crate::rollup;
+use crate::rules::parse_day;
+use crate::storage::{self, EventKind, Interval};
+use crate::table::{Format, Table};
+use crate::visibility::{self, BackgroundApp, ConcurrentMedia, Layer};
+
+const TOP_WINDOWS: usize = 15;
+// From this long a report adds up daily totals rather than intervals
+const DAILY_TOTALS_DAYS: i64 = 31;
+
+pub type Range = (DateTime<Local>, DateTime<Local>);
+
+pub struct Summary {
+    pub from: DateTime<Local>,
+    pub to: DateTime<Local>,
+    pub total: f64,
+    pub switches: usize,
+    pub title_changes: usize,
+    // Focus time per title, or whatever group_by says
+    pub windows: Vec<(String, f64)>,
+    pub group_by: GroupBy,
+    pub days: Vec<(NaiveDate, f64)>,
+    pub anomalies: Vec<Anomaly>,
+    // Editor and terminal focus with the repos pushed to, only once import-activity has run
+    pub blocks: Vec<FocusBlock>,
+    // Apps on screen without focus, only when tracking with --visible
+    pub background: Vec<BackgroundApp>,
+    // Always-on-top windows, e.g. picture-in-picture video, while something else had focus
+    pub media: Vec<ConcurrentMedia>,
+    // Editor time by the language of the open file
+    pub languages: Vec<(String, f64)>,
+    // Focus time with the camera or microphone in use
+    pub call_secs: f64,
+    pub meetings: Vec<MeetingApp>,
+    // Colors and icons for a report grouped by category
+    pub styles: CategoryStyles,
+    // The app with the most time in each row of windows, for its icon in the HTML report; none by category or field
+    pub apps: HashMap<String, String>,
+}
+
+impl Summary {
+    // A category with its icon when grouped by them, otherwise the name as it is
+    fn item_name(&self, name: &str) -> String {
+        match self.group_by {
+            GroupBy::Rule => self.styles.label(name),
+            _ => name.to_string(),
+        }
+    }
+
+    fn item_color(&self, name: &str) -> Option<&str> {
+        match self.group_by {
+            GroupBy::Rule => self.styles.color(name),
+            _ => None,
+        }
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let json = args.json_output()?;
+    let format = Format::from_args(args)?;
+    if args.value("--group-by").is_some() {
+        return rollup::run(args);
+    }
+    if args.flag("--games") {
+        return games::report(args);
+    }
+    if args.flag("--dnd") {
+        return dnd::report(args);
+    }
+    if args.flag("--power") {
+        return power::report(args);
+    }
+    if let Some(period) = args.value("--compare") {
+        return run_compare(period, json, args.flag("--plain"), format);
+    }
+
+    let (from, to) = period(args)?;
+
+    let group_by = match args.value("--by") {
+        Some(group_by) => GroupBy::parse(group_by)?,
+        None => GroupBy::configured()?,
+    };
+    let summary = load_period(from, to, group_by, &Filter::from_args(args)?)?;
+
+    if let Some(format) = format {
+        windows_table(&summary).print(format);
+    } else if json {
+        print!("{}", render_json(&summary));
+    } else if args.flag("--plain") {
+        print!("{}", render_plain(&summary));
+    } else {
+        print!("{}", render_text(&summary));
+    }
+
+    if args.flag("--notify") && !summary.anomalies.is_empty() {
+        let body: Vec<String> = summary.anomalies.iter().map(Anomaly::to_string).collect();
+        if let Err(e) = notify::send(&tr("notify-unusual", &[]), &body.join("\n")) {
+            eprintln!("{}", e);
+        }
+    }
+
+    let mail_to = args.value("--mail-to");
+    if args.value("--html").is_none() && mail_to.is_none() {
+        return Ok(());
+    }
+    #[cfg(not(feature = "server"))]
+    if mail_to.is_some() {
+        return Err("--mail-to needs --features server".to_string());
+    }
+
+    let html = render_html(&summary);
+    if let Some(path) = args.value("--html") {
+        fs::write(path, &html).map_err(|e| format!("failed to write {}: {}", path, e))?;
+        eprintln!("\n{}", tr("report-html-written", &[("path", &path)]));
+    }
+    #[cfg(feature = "server")]
+    if let Some(to) = mail_to {
+        let relay = args.value("--smtp").unwrap_or("localhost:25");
+        let from = args.value("--mail-from").unwrap_or("window-tracker@localhost");
+        let subject = tr("html-title", &[("period", &period_label(&summary))]);
+        mail::send_html(relay, from, to, &subject, &html)?;
+        eprintln!("\n{}", tr("report-emailed", &[("address", &to)]));
+    }
+
+    Ok(())
+}
+
+// The last `days` calendar days, starting at local midnight and ending now.
+pub fn last_days(days: u32) -> Range {
+    let now = Local::now();
+    let first_day = now.date_naive() - Duration::days(i64::from(days) - 1);
+    (local_midnight(first_day), now)
+}
+
+// --range, or --days up to today (default 7)
+pub fn period(args: &Args) -> Result<Range, String> {
+    match args.value("--range") {
+        Some(range) => parse_period(range),
+        None => {
+            let days: u32 = args.parsed("--days", 7)?;
+            if days == 0 {
+                return Err("--days must be at least 1".to_string());
+            }
+            Ok(last_days(days))
+        }
+    }
+}
+
+// --range: week, month or year for the last 7, 30 or 365 days, or <from>..<to> with days as in
+// recategorize --range, to today when the end is left out, or a single day
+fn parse_period(text: &str) -> Result<Range, String> {
+    match text {
+        "week" => return Ok(last_days(7)),
+        "month" => return Ok(last_days(30)),
+        "year" => return Ok(last_days(365)),
+        _ => {}
+    }
+    let (first, last) = text.split_once("..").unwrap_or((text, text));
+    let today = Local::now().date_naive();
+    let first = parse_day(first, "--range")?;
+    let last = if last.is_empty() { today } else { parse_day(last, "--range")? };
+    if last < first {
+        return Err(format!("the range '{}' ends before it starts", text));
+    }
+    let to = if last >= today { Local::now() } else { local_midnight(last + Duration::days(1)) };
+    Ok((local_midnight(first), to))
+}
+
+// The current day/week so far and the same stretch of the previous one, so partial periods compare fairly.
+fn compare_ranges(period: &str) -> Result<[Range; 2], String> {
+    let now = Local::now();
+    let today = now.date_naive();
+    let (start, length) = match period {
+        "day" => (today, Duration::days(1)),
+        "week" => (today - Duration::days(i64::from(today.weekday().num_days_from_monday())), Duration::weeks(1)),
+        other => return Err(format!("unknown comparison period '{}', expected 'day' or 'week'", other)),
+    };
+
+    // Stepping back in calendar days keeps the wall-clock time when a DST change falls in between
+    let earlier = |time: DateTime<Local>| wall_clock(&Local, time.naive_local() - length);
+    Ok([
+        (local_midnight(start), now),
+        (local_midnight(start - length), earlier(now)),
+    ])
+}
+
+fn run_compare(period: &str, json: bool, plain: bool, format: Option<Format>) -> Result<(), String> {
+    let [current, previous] = compare_ranges(period)?;
+    let current_apps = app_totals(current)?;
+    let previous_apps = app_totals(previous)?;
+
+    let mut apps: Vec<&String> = current_apps.keys().chain(previous_apps.keys()).collect();
+    apps.sort();
+    apps.dedup();
+    apps.sort_by(|a, b| {
+        let key = |app: &String| (current_apps.get(app).copied().unwrap_or(0.0), previous_apps.get(app).copied().unwrap_or(0.0));
+        let (a_current, a_previous) = key(a);
+        let (b_current, b_previous) = key(b);
+        b_current.total_cmp(&a_current).then(b_previous.total_cmp(&a_previous))
+    });
+
+    // Folding from 0.0 because an empty f64 sum is -0.0
+    let current_total = current_apps.values().fold(0.0, |total, secs| total + secs);
+    let previous_total = previous_apps.values().fold(0.0, |total, secs| total + secs);
+
+    if let Some(format) = format {
+        let mut table = Table::new(&["app", "secs", "previous_secs", "change"]);
+        for app in apps {
+            let now = current_apps.get(app).copied().unwrap_or(0.0);
+            let before = previous_apps.get(app).copied().unwrap_or(0.0);
+            table.push(vec![json!(app), json!(now.round() as u64), json!(before.round() as u64), json!(format_change(now, before))]);
+        }
+        table.print(format);
+        return Ok(());
+    }
+
+    if json {
+        let range = |(from, to): Range, total: f64| json!({ "from": from.to_rfc3339(), "to": to.to_rfc3339(), "total_secs": total });
+        let apps: Vec<serde_json::Value> = apps.iter()
+            .map(|app| json!({ "app": app, "secs": current_apps.get(*app), "previous_secs": previous_apps.get(*app) }))
+            .collect();
+        let document = json!({
+            "period": period,
+            "current": range(current, current_total),
+            "previous": range(previous, previous_total),
+            "apps": apps,
+        });
+        println!("{}", document);
+        return Ok(());
+    }
+
+    let format_range = |(from, to): Range| {
+        tr("report-period", &[("from", &from.format("%Y-%m-%d %H:%M")), ("to", &to.format("%Y-%m-%d %H:%M"))])
+    };
+    // --plain puts each app on one numbered line with the units spelled out and no colors
+    let duration = if plain { humanize::spoken } else { humanize::duration };
+    let marker = |key: &str, color: &str| if plain { tr(key, &[]) } else { highlight(&tr(key, &[]), color) };
+    let label = |app: &str| if plain { app.to_string() } else { tr("report-app", &[("app", &app)]) };
+    let heading = if period == "day" { "compare-day" } else { "compare-week" };
+    println!("{}", tr(heading, &[("current", &format_range(current)), ("previous", &format_range(previous))]));
+    println!("{}", tr("compare-total", &[
+        ("time", &duration(current_total)),
+        ("change", &format_change(current_total, previous_total)),
+        ("before", &duration(previous_total)),
+    ]));
+
+    for (index, app) in apps.into_iter().enumerate() {
+        let now = current_apps.get(app).copied();
+        let before = previous_apps.get(app).copied();
+        let (name, detail) = match (now, before) {
+            (Some(now), Some(before)) => {
+                let difference = format!("{}{}", if now < before { '-' } else { '+' }, duration((now - before).abs()));
+                let detail = tr("compare-focus-time", &[
+                    ("time", &duration(now)),
+                    ("difference", &difference),
+                    ("change", &format_change(now, before)),
+                ]);
+                (label(app), detail)
+            }
+            (Some(now), None) => (
+                format!("{} {}", label(app), marker("compare-new", GREEN)),
+                tr("report-focus-time", &[("time", &duration(now))]),
+            ),
+            (None, Some(before)) => (
+                format!("{} {}", label(app), marker("compare-gone", RED)),
+                tr("compare-was", &[("time", &duration(0.0)), ("before", &duration(before))]),
+            ),
+            (None, None) => continue,
+        };
+        if plain {
+            println!("{}", plain_item(index, &name, &detail));
+        } else {
+            println!("\n{}\n  {}", name, detail);
+        }
+    }
+
+    Ok(())
+}
+
+fn app_totals((from, to): Range) -> Result<HashMap<String, f64>, String> {
+    let intervals = storage::load_intervals(from.with_timezone(&Utc), to.with_timezone(&Utc))
+        .map_err(|e| format!("failed to read history: {}", e))?;
+
+    let mut totals = HashMap::new();
+    for interval in &intervals {
+        *totals.entry(interval.app_name().to_string()).or_insert(0.0) += interval.duration_secs();
+    }
+    Ok(totals)
+}
+
+fn format_change(now: f64, before: f64) -> String {
+    if before > 0.0 {
+        let change = (now - before) / before * 100.0;
+        format!("{}{}%", if change < 0.0 { "" } else { "+" }, humanize::number(change, 1))
+    } else if now > 0.0 {
+        tr("change-new", &[])
+    } else {
+        tr("change-none", &[])
+    }
+}
+
+pub const GREEN: &str = "32";
+pub const RED: &str = "31";
+
+pub fn highlight(text: &str, color: &str) -> String {
+    if cli::use_color() {
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn local_midnight(date: NaiveDate) -> DateTime<Local> {
+    wall_clock(&Local, date.and_hms_opt(0, 0, 0).unwrap())
+}
+
+// A wall-clock time in the zone. Skipped by a DST change (some zones skip midnight itself), it is
+// the moment the clocks jumped; repeated, it is the first occurrence.
+pub fn wall_clock<Tz: TimeZone>(zone: &Tz, time: NaiveDateTime) -> DateTime<Tz> {
+    let mut nudged = time;
+    loop {
+        if let Some(time) = zone.from_local_datetime(&nudged).earliest() {
+            return time;
+        }
+        nudged += Duration::minutes(15);
+        if nudged - time > Duration::days(1) {
+            return zone.from_utc_datetime(&time);
+        }
+    }
+}
+
+// Splits an interval that crosses midnight between the days it covers, on the clock it was recorded
+// on. Following the zone's own rules, a day with a DST change is 23 or 25 hours long.
+pub fn daily_chunks(interval: &Interval) -> Vec<(NaiveDate, f64)> {
+    match interval.recorded_offset() {
+        Some(offset) if offset != interval.start.with_timezone(&Local).offset().fix() => chunks_in(&offset, interval),
+        _ => chunks_in(&Local, interval),
+    }
+}
+
+fn chunks_in<Tz: TimeZone>(zone: &Tz, interval: &Interval) -> Vec<(NaiveDate, f64)> {
+    let mut chunks = Vec::new();
+    let mut start = interval.start.with_timezone(zone);
+    let end = interval.end.with_timezone(zone);
+    while start < end {
+        let next_midnight = wall_clock(zone, (start.date_naive() + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap());
+        let chunk_end = end.clone().min(next_midnight);
+        chunks.push((start.date_naive(), (chunk_end.clone() - start).num_milliseconds() as f64 / 1000.0));
+        start = chunk_end;
+    }
+    chunks
+}
+
+#[derive(Clone, PartialEq)]
+pub enum GroupBy {
+    Title,
+    // Each native window separately, so identical titles in different windows don't merge
+    Window,
+    App,
+    Class,
+    // The category from the [[categories]] rules
+    Rule,
+    // A field from the title templates, intervals without it are left out of the grouping
+    Field(String),
+}
+
+// Catalog keys for what a report is grouped by: the section heading, one entry with the
+// placeholder for its name, and the HTML heading and column
+struct GroupLabels {
+    heading: &'static str,
+    item: &'static str,
+    placeholder: &'static str,
+    html_heading: &'static str,
+    html_column: &'static str,
+}
+
+impl GroupBy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "title" => Ok(GroupBy::Title),
+            "window" => Ok(GroupBy::Window),
+            "app" => Ok(GroupBy::App),
+            "class" => Ok(GroupBy::Class),
+            "rule" => Ok(GroupBy::Rule),
+            other => match other.strip_prefix("field:") {
+                Some(field) if !field.is_empty() => Ok(GroupBy::Field(field.to_string())),
+                _ => Err(format!("unknown grouping '{}', expected 'title', 'window', 'app', 'class', 'rule' or 'field:<name>'", other)),
+            },
+        }
+    }
+
+    // aggregate_by from the config
+    pub fn configured() -> Result<Self, String> {
+        Ok(config::load()?.grouping().into())
+    }
+
+    fn key<'a>(&self, interval: &'a Interval) -> Option<&'a str> {
+        match self {
+            GroupBy::Window if !interval.window_id.is_empty() => Some(&interval.window_id),
+            GroupBy::Title | GroupBy::Window => Some(&interval.title),
+            GroupBy::App => Some(interval.app_name()),
+            GroupBy::Class => Some(interval.class_name()),
+            GroupBy::Rule => Some(interval.category_name()),
+            GroupBy::Field(field) => interval.attributes.get(field).map(String::as_str),
+        }
+    }
+
+    // None for a field, which names itself
+    fn labels(&self) -> Option<GroupLabels> {
+        let (heading, item, placeholder, html_heading, html_column) = match self {
+            GroupBy::Title | GroupBy::Window => ("report-top-windows", "report-window", "title", "html-top-windows", "html-window"),
+            GroupBy::App => ("report-top-apps", "report-app", "app", "html-top-apps", "html-app"),
+            GroupBy::Class => ("report-top-classes", "report-class", "class", "html-top-classes", "html-class"),
+            GroupBy::Rule => ("report-top-categories", "report-category", "category", "html-top-categories", "html-category"),
+            GroupBy::Field(_) => return None,
+        };
+        Some(GroupLabels { heading, item, placeholder, html_heading, html_column })
+    }
+
+    fn heading(&self, html: bool) -> String {
+        let Some(labels) = self.labels() else {
+            return tr(if html { "html-top-field" } else { "report-top-field" }, &[("field", &self.field_name())]);
+        };
+        tr(if html { labels.html_heading } else { labels.heading }, &[])
+    }
+
+    fn html_column(&self) -> String {
+        match self.labels() {
+            Some(labels) => tr(labels.html_column, &[]),
+            None => self.field_name(),
+        }
+    }
+
+    // The header of the name column in --format output
+    pub fn column(&self) -> String {
+        match self.labels() {
+            Some(labels) => labels.placeholder.to_string(),
+            None => self.field_name(),
+        }
+    }
+
+    // The field a field grouping is by, empty for the others
+    fn field_name(&self) -> String {
+        if let GroupBy::Field(field) = self { field.clone() } else { String::new() }
+    }
+
+    // "Window: name", "App: name" and so on
+    pub fn item_line(&self, name: &str) -> String {
+        match self.labels() {
+            Some(labels) => tr(labels.item, &[(labels.placeholder, &name)]),
+            None => tr("report-field", &[("field", &self.field_name()), ("value", &name)]),
+        }
+    }
+}
+
+impl From<AggregateBy> for GroupBy {
+    fn from(aggregate_by: AggregateBy) -> Self {
+        match aggregate_by {
+            AggregateBy::Title => GroupBy::Title,
+            AggregateBy::App => GroupBy::App,
+            AggregateBy::Class => GroupBy::Class,
+            AggregateBy::Rule => GroupBy::Rule,
+        }
+    }
+}
+
+// The report for the last `days` days from the stored history
+pub fn load(days: u32, group_by: GroupBy) -> Result<Summary, String> {
+    load_matching(days, group_by, &Filter::default())
+}
+
+// The report over only the intervals the filter picks, the anomaly baseline included
+pub fn load_matching(days: u32, group_by: GroupBy, filter: &Filter) -> Result<Summary, String> {
+    let (from, to) = last_days(days);
+    load_period(from, to, group_by, filter)
+}
+
+// Periods of DAILY_TOTALS_DAYS or more come from the daily totals (storage/aggregates.rs) unless
+// filtered or grouped by native window or field, which those don't keep, and have no focus blocks
+pub fn load_period(from: DateTime<Local>, to: DateTime<Local>, group_by: GroupBy, filter: &Filter) -> Result<Summary, String> {
+    let baseline_from = from - Duration::days(anomaly::BASELINE_DAYS);
+    let daily = filter.is_empty() && !matches!(group_by, GroupBy::Window | GroupBy::Field(_)) && (to - from).num_days() >= DAILY_TOTALS_DAYS;
+    let (history, counts) = if daily {
+        let days = storage::load_days(baseline_from.date_naive(), to.date_naive()).map_err(|e| format!("failed to read history: {}", e))?;
+        (days.intervals, Some(days.counts))
+    } else {
+        let history = storage::load_matching(baseline_from.with_timezone(&Utc), to.with_timezone(&Utc), filter)
+            .map_err(|e| format!("failed to read history: {}", e))?;
+        (history, None)
+    };
+    let config = config::load()?;
+    // Totals by title are by the names [[merge]] gives them, the rest looks at titles as recorded
+    let merges = Merges::from_config(&config)?;
+    let mut summary = match group_by {
+        GroupBy::Title if !merges.is_empty() => build(&merges.apply(&history), from, to, group_by),
+        _ => build(&history, from, to, group_by),
+    };
+    let intervals = clip(&history, from, to);
+
+    summary.styles = CategoryStyles::from_config(&config);
+    summary.languages = languages::by_language(&intervals, &Languages::from_config(&config));
+    summary.call_secs = intervals.iter().filter(|interval| interval.call).map(Interval::duration_secs).sum();
+    summary.meetings = calls::meeting_apps(&intervals, &config);
+    if let Some(counts) = &counts {
+        (summary.switches, summary.title_changes) = counts.range(from.date_naive()..=to.date_naive())
+            .fold((0, 0), |(switches, title_changes), (_, counts)| (switches + counts.0, title_changes + counts.1));
+    }
+    let imported = if counts.is_none() { activity::load()? } else { Vec::new() };
+    if !imported.is_empty() {
+        let apps = config.activity.map(|activity| activity.apps).unwrap_or_else(config::default_activity_apps);
+        summary.blocks = activity::focus_blocks(&intervals, &imported, &apps);
+    }
+    let visible = visibility::load(Layer::Visible, from.with_timezone(&Utc), to.with_timezone(&Utc))?;
+    if !visible.is_empty() {
+        summary.background = visibility::background_apps(&visible, &intervals);
+    }
+    let floating = visibility::load(Layer::Floating, from.with_timezone(&Utc), to.with_timezone(&Utc))?;
+    summary.media = visibility::concurrent_media(&floating, &intervals);
+    Ok(summary)
+}
+
+// The full report for a period: `history` may reach back further, the extra days are the anomaly baseline.
+pub fn build(history: &[Interval], from: DateTime<Local>, to: DateTime<Local>, group_by: GroupBy) -> Summary {
+    let intervals = clip(history, from, to);
+    let mut summary = summarize(&intervals, from, to, group_by);
+    let report_days: Vec<NaiveDate> = summary.days.iter().map(|(day, _)| *day).collect();
+    summary.anomalies = anomaly::detect(history, &report_days);
+    summary
+}
+
+// The parts of the intervals that fall within the range
+pub fn clip(intervals: &[Interval], from: DateTime<Local>, to: DateTime<Local>) -> Vec<Interval> {
+    let (from, to) = (from.with_timezone(&Utc), to.with_timezone(&Utc));
+    intervals.iter()
+        .filter(|interval| interval.end > from && interval.start < to)
+        .map(|interval| Interval { start: interval.start.max(from), end: interval.end.min(to), ..interval.clone() })
+        .collect()
+}
+
+pub fn summarize(intervals: &[Interval], from: DateTime<Local>, to: DateTime<Local>, group_by: GroupBy) -> Summary {
+    let mut groups: HashMap<&str, HashMap<&str, f64>> = HashMap::new();
+    let mut group_apps: HashMap<&str, HashMap<&str, f64>> = HashMap::new();
+    let mut days: HashMap<NaiveDate, f64> = HashMap::new();
+    let mut total = 0.0;
+    let by_app = !matches!(group_by, GroupBy::Rule | GroupBy::Field(_));
+
+    for interval in intervals {
+        if let Some(key) = group_by.key(interval) {
+            *groups.entry(key).or_default().entry(&interval.title).or_insert(0.0) += interval.duration_secs();
+            if by_app {
+                *group_apps.entry(key).or_default().entry(interval.app_name()).or_insert(0.0) += interval.duration_secs();
+            }
+        }
+        total += interval.duration_secs();
+
+        for (day, secs) in daily_chunks(interval) {
+            *days.entry(day).or_insert(0.0) += secs;
+        }
+    }
+
+    let mut apps = HashMap::new();
+    let mut windows: Vec<(String, f64)> = groups.into_iter()
+        .map(|(key, titles)| {
+            let secs = titles.values().sum();
+            let label = match group_by {
+                GroupBy::Title | GroupBy::App | GroupBy::Class | GroupBy::Rule | GroupBy::Field(_) => key.to_string(),
+                GroupBy::Window => {
+                    let (title, _) = titles.iter().max_by(|a, b| a.1.total_cmp(b.1)).unwrap();
+                    if key == *title { key.to_string() } else { format!("{} (window {})", title, key) }
+                }
+            };
+            if let Some((app, _)) = group_apps.get(key).and_then(|apps| apps.iter().max_by(|a, b| a.1.total_cmp(b.1))) {
+                apps.insert(label.clone(), app.to_string());
+            }
+            (label, secs)
+        })
+        .collect();
+    windows.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let days = from.date_naive()
+        .iter_days()
+        .take_while(|day| *day <= to.date_naive())
+        .map(|day| (day, days.get(&day).copied().unwrap_or(0.0)))
+        .collect();
+
+    let title_changes = intervals.iter().filter(|interval| interval.event == EventKind::TitleChanged).count();
+    let switches = intervals.iter().filter(|interval| interval.event == EventKind::FocusChanged).count();
+
+    Summary {
+        from,
+        to,
+        total,
+        switches,
+        title_changes,
+        windows,
+        group_by,
+        days,
+        anomalies: Vec::new(),
+        blocks: Vec::new(),
+        background: Vec::new(),
+        media: Vec::new(),
+        languages: Vec::new(),
+        call_secs: 0.0,
+        meetings: Vec::new(),
+        styles: CategoryStyles::default(),
+        apps,
+    }
+}
+
+// Each row of windows with its focus time, for --format
+pub fn windows_table(summary: &Summary) -> Table {
+    let column = summary.group_by.column();
+    let mut table = Table::new(&[&column, "time", "secs"]);
+    for (name, secs) in &summary.windows {
+        table.push(vec![json!(summary.item_name(name)), json!(humanize::duration(*secs)), json!(secs.round() as u64)]);
+    }
+    table
+}
+
+pub fn period_label(summary: &Summary) -> String {
+    tr("report-period", &[("from", &summary.from.format("%Y-%m-%d")), ("to", &summary.to.format("%Y-%m-%d"))])
+}
+
+pub fn render_json(summary: &Summary) -> String {
+    let document = json!({
+        "from": summary.from.to_rfc3339(),
+        "to": summary.to.to_rfc3339(),
+        "total_secs": summary.total,
+        "switches": summary.switches,
+        "title_changes": summary.title_changes,
+        "windows": summary.windows.iter().map(|(name, secs)| {
+            let mut window = json!({ "name": name, "secs": secs });
+            if let (GroupBy::Rule, Some(style)) = (&summary.group_by, summary.styles.get(name)) {
+                window["color"] = json!(style.color);
+                window["icon"] = json!(style.icon);
+            }
+            window
+        }).collect::<Vec<_>>(),
+        "days": summary.days.iter().map(|(day, secs)| json!({ "date": day.to_string(), "secs": secs })).collect::<Vec<_>>(),
+        "anomalies": summary.anomalies.iter().map(Anomaly::to_string).collect::<Vec<_>>(),
+        "call_secs": summary.call_secs,
+        "meeting_apps": summary.meetings.iter().map(|app| json!({
+            "app": app.app,
+            "call_secs": app.call_secs,
+            "open_secs": app.open_secs,
+        })).collect::<Vec<_>>(),
+        "languages": summary.languages.iter().map(|(language, secs)| json!({ "language": language, "secs": secs })).collect::<Vec<_>>(),
+        "focus_blocks": summary.blocks.iter().map(|block| json!({
+            "start": block.start.to_rfc3339(),
+            "end": block.end.to_rfc3339(),
+            "secs": block.secs,
+            "repos": block.repos,
+        })).collect::<Vec<_>>(),
+        "background_apps": summary.background.iter().map(|app| json!({
+            "app": app.app,
+            "visible_secs": app.visible_secs,
+            "active_secs": app.active_secs,
+            "background_secs": app.background_secs(),
+        })).collect::<Vec<_>>(),
+        "concurrent_media": summary.media.iter().map(|media| json!({
+            "app": media.app,
+            "title": media.title,
+            "secs": media.secs,
+        })).collect::<Vec<_>>(),
+    });
+    format!("{}\n", serde_json::to_string(&document).unwrap())
+}
+
+pub fn switches_line(switches: usize, title_changes: usize) -> String {
+    tr("report-switches", &[("switches", &switches), ("title_changes", &title_changes)])
+}
+
+pub fn render_text(summary: &Summary) -> String {
+    let mut out = String::new();
+    writeln!(out, "{}", tr("report-title", &[("period", &period_label(summary))])).unwrap();
+    writeln!(out, "{}", tr("report-total", &[("time", &humanize::duration(summary.total))])).unwrap();
+    writeln!(out, "{}", switches_line(summary.switches, summary.title_changes)).unwrap();
+
+    writeln!(out, "\n{}", tr("report-daily", &[])).unwrap();
+    for (day, secs) in &summary.days {
+        writeln!(out, "  {}: {}", i18n::day(*day), humanize::duration(*secs)).unwrap();
+    }
+
+    writeln!(out, "\n{}", summary.group_by.heading(false)).unwrap();
+    for (name, secs) in summary.windows.iter().take(TOP_WINDOWS) {
+        writeln!(out, "{}", summary.group_by.item_line(&summary.item_name(name))).unwrap();
+        writeln!(out, "  {}", tr("report-focus-time", &[("time", &humanize::duration(*secs))])).unwrap();
+    }
+
+    if !summary.anomalies.is_empty() {
+        writeln!(out, "\n{}", tr("report-unusual", &[])).unwrap();
+        for anomaly in &summary.anomalies {
+            writeln!(out, "  {}", anomaly).unwrap();
+        }
+    }
+
+    if summary.call_secs > 0.0 || !summary.meetings.is_empty() {
+        writeln!(out, "\n{}", tr("report-calls", &[("time", &humanize::duration(summary.call_secs))])).unwrap();
+        for app in &summary.meetings {
+            writeln!(out, "{}", tr("report-app", &[("app", &app.app)])).unwrap();
+            let (call, open) = (humanize::duration(app.call_secs), humanize::duration(app.open_secs));
+            writeln!(out, "  {}", tr("report-in-call", &[("call", &call), ("open", &open)])).unwrap();
+        }
+    }
+
+    if !summary.languages.is_empty() {
+        writeln!(out, "\n{}", tr("report-languages", &[])).unwrap();
+        for (language, secs) in &summary.languages {
+            writeln!(out, "  {}: {}", language, humanize::duration(*secs)).unwrap();
+        }
+    }
+
+    if !summary.blocks.is_empty() {
+        writeln!(out, "\n{}", tr("report-blocks", &[])).unwrap();
+        for block in &summary.blocks {
+            writeln!(out, "  {}  {}", block_label(block), block_repos(block)).unwrap();
+        }
+    }
+
+    if !summary.background.is_empty() {
+        writeln!(out, "\n{}", tr("report-background", &[])).unwrap();
+        for app in summary.background.iter().take(TOP_WINDOWS) {
+            writeln!(out, "{}", tr("report-app", &[("app", &app.app)])).unwrap();
+            let visible = tr("report-visible", &[
+                ("visible", &humanize::duration(app.visible_secs)),
+                ("active", &humanize::duration(app.active_secs)),
+                ("background", &humanize::duration(app.background_secs())),
+            ]);
+            writeln!(out, "  {}", visible).unwrap();
+        }
+    }
+
+    if !summary.media.is_empty() {
+        writeln!(out, "\n{}", tr("report-media", &[])).unwrap();
+        for media in summary.media.iter().take(TOP_WINDOWS) {
+            writeln!(out, "{} ({})", tr("report-window", &[("title", &media.title)]), media.app).unwrap();
+            writeln!(out, "  {}", tr("report-on-top", &[("time", &humanize::duration(media.secs))])).unwrap();
+        }
+    }
+    out
+}
+
+// The text report as numbered lines without indentation or abbreviated units, for screen readers
+pub fn render_plain(summary: &Summary) -> String {
+    let mut out = String::new();
+    writeln!(out, "{}", tr("report-title", &[("period", &period_label(summary))])).unwrap();
+    writeln!(out, "{}", tr("report-total", &[("time", &humanize::spoken(summary.total))])).unwrap();
+    writeln!(out, "{}", switches_line(summary.switches, summary.title_changes)).unwrap();
+
+    plain_section(
+        &mut out,
+        tr("report-daily", &[]),
+        summary.days.iter().map(|(day, secs)| (i18n::day(*day), humanize::spoken(*secs))).collect(),
+    );
+    plain_section(
+        &mut out,
+        summary.group_by.heading(false),
+        summary.windows.iter().take(TOP_WINDOWS).map(|(title, secs)| (summary.item_name(title), humanize::spoken(*secs))).collect(),
+    );
+    if !summary.anomalies.is_empty() {
+        writeln!(out, "\n{}", tr("report-unusual", &[])).unwrap();
+        for anomaly in &summary.anomalies {
+            writeln!(out, "{}", anomaly).unwrap();
+        }
+    }
+    if summary.call_secs > 0.0 || !summary.meetings.is_empty() {
+        plain_section(
+            &mut out,
+            tr("report-calls", &[("time", &humanize::spoken(summary.call_secs))]),
+            summary.meetings.iter().map(|app| {
+                let (call, open) = (humanize::spoken(app.call_secs), humanize::spoken(app.open_secs));
+                (app.app.clone(), tr("report-in-call", &[("call", &call), ("open", &open)]))
+            }).collect(),
+        );
+    }
+    plain_section(
+        &mut out,
+        tr("report-languages", &[]),
+        summary.languages.iter().map(|(language, secs)| (language.clone(), humanize::spoken(*secs))).collect(),
+    );
+    plain_section(
+        &mut out,
+        tr("report-blocks", &[]),
+        summary.blocks.iter().map(|block| {
+            let (start, end) = (block.start.with_timezone(&Local), block.end.with_timezone(&Local));
+            let range = tr("plain-range", &[("start", &start.format("%H:%M")), ("end", &end.format("%H:%M"))]);
+            let name = format!("{}, {}", i18n::day(start.date_naive()), range);
+            (name, format!("{}, {}", humanize::spoken(block.secs), block_repos(block)))
+        }).collect(),
+    );
+    plain_section(
+        &mut out,
+        tr("report-background", &[]),
+        summary.background.iter().take(TOP_WINDOWS).map(|app| {
+            let visible = tr("report-visible", &[
+                ("visible", &humanize::spoken(app.visible_secs)),
+                ("active", &humanize::spoken(app.active_secs)),
+                ("background", &humanize::spoken(app.background_secs())),
+            ]);
+            (app.app.clone(), visible)
+        }).collect(),
+    );
+    plain_section(
+        &mut out,
+        tr("report-media", &[]),
+        summary.media.iter().take(TOP_WINDOWS).map(|media| {
+            (format!("{} ({})", media.title, media.app), tr("report-on-top", &[("time", &humanize::spoken(media.secs))]))
+        }).collect(),
+    );
+    out
+}
+
+fn plain_section(out: &mut String, heading: String, items: Vec<(String, String)>) {
+    if items.is_empty() {
+        return;
+    }
+    writeln!(out, "\n{}", heading).unwrap();
+    for (index, (name, text)) in items.iter().enumerate() {
+        writeln!(out, "{}", plain_item(index, name, text)).unwrap();
+    }
+}
+
+fn plain_item(index: usize, name: &str, text: &str) -> String {
+    tr("plain-item", &[("index", &(index + 1)), ("name", &name), ("text", &text)])
+}
+
+fn block_label(block: &FocusBlock) -> String {
+    let (start, end) = (block.start.with_timezone(&Local), block.end.with_timezone(&Local));
+    format!("{} {}-{} ({})", i18n::day(start.date_naive()), start.format("%H:%M"), end.format("%H:%M"), humanize::duration(block.secs))
+}
+
+fn block_repos(block: &FocusBlock) -> String {
+    if block.repos.is_empty() { tr("report-no-pushes", &[]) } else { block.repos.join(", ") }
+}
+
+pub fn render_html(summary: &Summary) -> String {
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">", i18n::language()).unwrap();
+    writeln!(out, "<title>{}</title>", escape_html(&tr("html-title", &[("period", &period_label(summary))]))).unwrap();
+    out.push_str(STYLE);
+    out.push_str("</head>\n<body>\n<main>\n");
+
+    writeln!(out, "<h1>{}</h1>", escape_html(&tr("html-heading", &[]))).unwrap();
+    let meta = tr("html-meta", &[
+        ("period", &period_label(summary)),
+        ("time", &humanize::duration(summary.total)),
+        ("windows", &summary.windows.len().to_string()),
+    ]);
+    writeln!(out, "<p class=\"meta\">{}</p>", escape_html(&meta)).unwrap();
+
+    writeln!(out, "<h2>{}</h2>", escape_html(&tr("html-daily", &[]))).unwrap();
+    out.push_str(&daily_chart_svg(&summary.days));
+
+    writeln!(out, "<h2>{}</h2>\n<table>", escape_html(&summary.group_by.heading(true))).unwrap();
+    let columns = header_cells(&[("html-focus-time", "num"), ("html-share", "share")]);
+    writeln!(out, "<tr><th>{}</th>{}</tr>", escape_html(&summary.group_by.html_column()), columns).unwrap();
+    let top = summary.windows.first().map(|(_, secs)| *secs).unwrap_or(0.0);
+    for (title, secs) in summary.windows.iter().take(TOP_WINDOWS) {
+        let width = if top > 0.0 { secs / top * 100.0 } else { 0.0 };
+        // Colors were checked to be #rgb or #rrggbb, safe inside the attribute
+        let (swatch, bar_color) = match summary.item_color(title) {
+            Some(color) => (format!("<span class=\"swatch\" style=\"background:{}\"></span>", color), format!(";background:{}", color)),
+            None => (String::new(), String::new()),
+        };
+        writeln!(
+            out,
+            "<tr><td>{}{}{}</td><td class=\"num\">{}</td><td class=\"share\"><div class=\"bar\" style=\"width:{:.1}%{}\"></div></td></tr>",
+            summary.apps.get(title).map_or(String::new(), |app| icons::html(app)),
+            swatch,
+            escape_html(&summary.item_name(title)),
+            humanize::duration(*secs),
+            width,
+            bar_color
+        ).unwrap();
+    }
+    out.push_str("</table>\n");
+
+    if summary.windows.is_empty() {
+        writeln!(out, "<p class=\"meta\">{}</p>", escape_html(&tr("html-empty", &[]))).unwrap();
+    }
+
+    if !summary.anomalies.is_empty() {
+        writeln!(out, "<h2>{}</h2>\n<ul class=\"anomalies\">", escape_html(&tr("html-unusual", &[]))).unwrap();
+        for anomaly in &summary.anomalies {
+            writeln!(out, "<li>{}</li>", escape_html(&anomaly.to_string())).unwrap();
+        }
+        out.push_str("</ul>\n");
+    }
+
+    if summary.call_secs > 0.0 || !summary.meetings.is_empty() {
+        writeln!(out, "<h2>{}</h2>", escape_html(&tr("html-calls", &[]))).unwrap();
+        let meta = tr("html-calls-meta", &[("time", &humanize::duration(summary.call_secs))]);
+        writeln!(out, "<p class=\"meta\">{}</p>", escape_html(&meta)).unwrap();
+        if !summary.meetings.is_empty() {
+            writeln!(out, "<table>\n<tr>{}</tr>", header_cells(&[("html-app", ""), ("html-in-call", "num"), ("html-just-open", "num")])).unwrap();
+            for app in &summary.meetings {
+                writeln!(
+                    out,
+                    "<tr><td>{}{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                    icons::html(&app.app),
+                    escape_html(&app.app),
+                    humanize::duration(app.call_secs),
+                    humanize::duration(app.open_secs)
+                ).unwrap();
+            }
+            out.push_str("</table>\n");
+        }
+    }
+
+    if !summary.languages.is_empty() {
+        writeln!(out, "<h2>{}</h2>\n<table>", escape_html(&tr("html-languages", &[]))).unwrap();
+        writeln!(out, "<tr>{}</tr>", header_cells(&[("html-language", ""), ("html-focus-time", "num")])).unwrap();
+        for (language, secs) in &summary.languages {
+            writeln!(out, "<tr><td>{}</td><td class=\"num\">{}</td></tr>", escape_html(language), humanize::duration(*secs)).unwrap();
+        }
+        out.push_str("</table>\n");
+    }
+
+    if !summary.blocks.is_empty() {
+        writeln!(out, "<h2>{}</h2>\n<table>", escape_html(&tr("html-blocks", &[]))).unwrap();
+        writeln!(out, "<tr>{}</tr>", header_cells(&[("html-block", ""), ("html-repos", "")])).unwrap();
+        for block in &summary.blocks {
+            writeln!(out, "<tr><td>{}</td><td>{}</td></tr>", escape_html(&block_label(block)), escape_html(&block_repos(block))).unwrap();
+        }
+        out.push_str("</table>\n");
+    }
+
+    if !summary.background.is_empty() {
+        writeln!(out, "<h2>{}</h2>\n<table>", escape_html(&tr("html-background", &[]))).unwrap();
+        let header = header_cells(&[("html-app", ""), ("html-visible", "num"), ("html-active", "num"), ("html-background-time", "num")]);
+        writeln!(out, "<tr>{}</tr>", header).unwrap();
+        for app in summary.background.iter().take(TOP_WINDOWS) {
+            writeln!(
+                out,
+                "<tr><td>{}{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                icons::html(&app.app),
+                escape_html(&app.app),
+                humanize::duration(app.visible_secs),
+                humanize::duration(app.active_secs),
+                humanize::duration(app.background_secs())
+            ).unwrap();
+        }
+        out.push_str("</table>\n");
+    }
+
+    if !summary.media.is_empty() {
+        writeln!(out, "<h2>{}</h2>\n<table>", escape_html(&tr("html-media", &[]))).unwrap();
+        writeln!(out, "<tr>{}</tr>", header_cells(&[("html-window", ""), ("html-app", ""), ("html-on-top", "num")])).unwrap();
+        for media in summary.media.iter().take(TOP_WINDOWS) {
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td class=\"num\">{}</td></tr>",
+                escape_html(&media.title),
+                escape_html(&media.app),
+                humanize::duration(media.secs)
+            ).unwrap();
+        }
+        out.push_str("</table>\n");
+    }
+
+    let generated = tr("html-generated", &[("time", &Local::now().format("%Y-%m-%d %H:%M"))]);
+    writeln!(out, "<p class=\"meta\">{}</p>", escape_html(&generated)).unwrap();
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
+
+// Table header cells from catalog keys, each with its class
+fn header_cells(columns: &[(&str, &str)]) -> String {
+    columns.iter()
+        .map(|(key, class)| {
+            let class = if class.is_empty() { String::new() } else { format!(" class=\"{}\"", class) };
+            format!("<th{}>{}</th>", class, escape_html(&tr(key, &[])))
+        })
+        .collect()
+}
+
+fn daily_chart_svg(days: &[(NaiveDate, f64)]) -> String {
+    const WIDTH: f64 = 640.0;
+    const HEIGHT: f64 = 180.0;
+    const LABEL_HEIGHT: f64 = 20.0;
+
+    let max = days.iter().map(|(_, secs)| *secs).fold(0.0, f64::max);
+    let slot = WIDTH / days.len().max(1) as f64;
+    let bar_width = (slot * 0.7).max(1.0);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg class=\"chart\" viewBox=\"0 0 {} {}\" role=\"img\" aria-label=\"Daily focus time\">",
+        WIDTH,
+        HEIGHT + LABEL_HEIGHT
+    ).unwrap();
+    for (i, (day, secs)) in days.iter().enumerate() {
+        let height = if max > 0.0 { secs / max * HEIGHT } else { 0.0 };
+        let x = i as f64 * slot + (slot - bar_width) / 2.0;
+        writeln!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"3\"><title>{}: {}</title></rect>",
+            x,
+            HEIGHT - height,
+            bar_width,
+            height,
+            escape_html(&i18n::day(*day)),
+            humanize::duration(*secs)
+        ).unwrap();
+        if days.len() <= 31 {
+            writeln!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                x + bar_width / 2.0,
+                HEIGHT + LABEL_HEIGHT - 4.0,
+                day.format(if days.len() <= 7 { "%a" } else { "%d" })
+            ).unwrap();
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// Standard base64 with padding
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, byte)| n | u32::from(*byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = "<style>
+body { margin: 0; background: #f4f5f7; color: #1f2328; font: 15px/1.5 -apple-system, 'Segoe UI', Roboto, sans-serif; }
+main { max-width: 720px; margin: 2em auto; padding: 2em; background: #fff; border-radius: 8px; box-shadow: 0 1px 4px rgba(0,0,0,.08); }
+h1 { margin-top: 0; font-size: 1.6em; }
+h2 { margin-top: 1.6em; font-size: 1.15em; border-bottom: 1px solid #e5e7eb; padding-bottom: .3em; }
+.meta { color: #6b7280; }
+.chart { width: 100%; height: auto; }
+.chart rect { fill: #4f7cff; }
+.chart text { fill: #6b7280; font-size: 11px; }
+table { width: 100%; border-collapse: collapse; }
+th, td { padding: .35em .5em; text-align: left; border-bottom: 1px solid #f0f1f3; vertical-align: middle; }
+td:first-child { word-break: break-word; }
+.num { text-align: right; white-space: nowrap; font-variant-numeric: tabular-nums; }
+.share { width: 30%; }
+.bar { height: .7em; background: #4f7cff; border-radius: 3px; }
+.icon { width: 1.1em; height: 1.1em; margin-right: .4em; vertical-align: -.2em; }
+.swatch { display: inline-block; width: .75em; height: .75em; margin-right: .4em; border-radius: 2px; }
+.anomalies li { color: #b45309; }
+</style>
+";
+
+// A zone changing to and from daylight saving time like Europe/Berlin in 2024, for tests that
+// can't count on the zone of the machine they run on
+#[cfg(test)]
+pub(crate) mod testing {
+    use chrono::{FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeZone};
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct Berlin;
+
+    fn winter() -> FixedOffset {
+        FixedOffset::east_opt(3600).unwrap()
+    }
+
+    fn summer() -> FixedOffset {
+        FixedOffset::east_opt(2 * 3600).unwrap()
+    }
+
+    // At 01:00 UTC the clocks went from 02:00 to 03:00 on 2024-03-31 and back on 2024-10-27
+    fn utc(month: u32, day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap().and_hms_opt(1, 0, 0).unwrap()
+    }
+
+    impl TimeZone for Berlin {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Berlin
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let fits = |offset: FixedOffset| self.offset_from_utc_datetime(&(*local - offset)) == offset;
+            match (fits(summer()), fits(winter())) {
+                (true, true) => LocalResult::Ambiguous(summer(), winter()),
+                (true, false) => LocalResult::Single(summer()),
+                (false, true) => LocalResult::Single(winter()),
+                (false, false) => LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, time: &NaiveDateTime) -> FixedOffset {
+            if (utc(3, 31)..utc(10, 27)).contains(time) { summer() } else { winter() }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use chrono::FixedOffset;
+
+    use super::testing::Berlin;
+    use super::*;
+
+    fn day(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    // From one wall-clock time in the zone to another
+    fn interval_in<Tz: TimeZone>(zone: &Tz, from: NaiveDateTime, to: NaiveDateTime) -> Interval {
+        let (start, end) = (wall_clock(zone, from), wall_clock(zone, to));
+        let mut interval = Interval::new("", "", SystemTime::from(start.with_timezone(&Utc)), SystemTime::from(end.with_timezone(&Utc)));
+        interval.utc_offset = Some(start.offset().fix().local_minus_utc());
+        interval
+    }
+
+    fn secs(chunks: &[(NaiveDate, f64)]) -> f64 {
+        chunks.iter().map(|(_, secs)| secs).sum()
+    }
+
+    #[test]
+    fn wall_clock_times_a_change_skips_or_repeats() {
+        // 02:30 didn't happen on 2024-03-31, it is the moment the clocks jumped to 03:00
+        let skipped = wall_clock(&Berlin, day(3, 31).and_hms_opt(2, 30, 0).unwrap());
+        assert_eq!(skipped.naive_utc(), day(3, 31).and_hms_opt(1, 0, 0).unwrap());
+        // 02:30 happened twice on 2024-10-27, the first time is taken
+        let repeated = wall_clock(&Berlin, day(10, 27).and_hms_opt(2, 30, 0).unwrap());
+        assert_eq!(repeated.naive_utc(), day(10, 27).and_hms_opt(0, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn a_night_the_clocks_jump_forward_is_an_hour_shorter() {
+        let interval = interval_in(&Berlin, day(3, 30).and_hms_opt(22, 0, 0).unwrap(), day(3, 31).and_hms_opt(4, 0, 0).unwrap());
+        assert_eq!(interval.duration_secs(), 5.0 * 3600.0);
+        let chunks = chunks_in(&Berlin, &interval);
+        assert_eq!(chunks, [(day(3, 30), 2.0 * 3600.0), (day(3, 31), 3.0 * 3600.0)]);
+        assert_eq!(secs(&chunks), interval.duration_secs());
+    }
+
+    #[test]
+    fn a_night_the_clocks_fall_back_is_an_hour_longer() {
+        let interval = interval_in(&Berlin, day(10, 26).and_hms_opt(22, 0, 0).unwrap(), day(10, 27).and_hms_opt(4, 0, 0).unwrap());
+        assert_eq!(interval.duration_secs(), 7.0 * 3600.0);
+        let chunks = chunks_in(&Berlin, &interval);
+        assert_eq!(chunks, [(day(10, 26), 2.0 * 3600.0), (day(10, 27), 5.0 * 3600.0)]);
+        assert_eq!(secs(&chunks), interval.duration_secs());
+
+        // A whole day of it has 25 hours
+        let whole = interval_in(&Berlin, day(10, 27).and_hms_opt(0, 0, 0).unwrap(), day(10, 28).and_hms_opt(0, 0, 0).unwrap());
+        assert_eq!(chunks_in(&Berlin, &whole), [(day(10, 27), 25.0 * 3600.0)]);
+    }
+
+    #[test]
+    fn days_are_on_the_clock_the_interval_was_recorded_on() {
+        // Recorded in Kathmandu, 20:00 to 02:00 there, whatever the zone here is
+        let kathmandu = FixedOffset::east_opt(5 * 3600 + 45 * 60).unwrap();
+        let interval = interval_in(&kathmandu, day(6, 1).and_hms_opt(20, 0, 0).unwrap(), day(6, 2).and_hms_opt(2, 0, 0).unwrap());
+        let chunks = daily_chunks(&interval);
+        assert_eq!(chunks, [(day(6, 1), 4.0 * 3600.0), (day(6, 2), 2.0 * 3600.0)]);
+        assert_eq!(secs(&chunks), interval.duration_secs());
+
+        // Without an offset, on the clock here
+        let unknown = Interval { utc_offset: None, ..interval.clone() };
+        assert_eq!(secs(&daily_chunks(&unknown)), interval.duration_secs());
+        assert_eq!(daily_chunks(&unknown), chunks_in(&Local, &unknown));
+    }
+}
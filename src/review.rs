@@ -0,0 +1,207 @@
+// `review`: the titles tracked recently, marked interactively as never to be tracked or tracked
+// without their title. The choices become [[ignore]] rules appended to config.toml.
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use chrono::Utc;
+
+use crate::cli::Args;
+use crate::config::{self, Config};
+use crate::humanize;
+use crate::ignore::{Decision, WindowFilter, REDACTED};
+use crate::report::last_days;
+use crate::storage;
+
+const REVIEW_LIMIT: usize = 30;
+
+struct Entry {
+    app: String,
+    title: String,
+    secs: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Choice {
+    Never,
+    Redact,
+    NeverApp,
+}
+
+impl Choice {
+    fn parse(letter: char) -> Option<Self> {
+        match letter {
+            'n' => Some(Choice::Never),
+            'r' => Some(Choice::Redact),
+            'a' => Some(Choice::NeverApp),
+            _ => None,
+        }
+    }
+
+    fn describe(&self, entry: &Entry) -> String {
+        match self {
+            Choice::Never => format!("never track '{}'", entry.title),
+            Choice::Redact => format!("track '{}' without its title", entry.title),
+            Choice::NeverApp => format!("never track {}", app_label(&entry.app)),
+        }
+    }
+
+    fn rule(&self, entry: &Entry) -> String {
+        let string = |text: &str| toml::Value::String(text.to_string()).to_string();
+        let title = string(&format!("^{}$", regex::escape(&entry.title)));
+        match self {
+            Choice::Never if entry.app.is_empty() => format!("[[ignore]]\ntitle = {}\n", title),
+            Choice::Never => format!("[[ignore]]\napp = {}\ntitle = {}\n", string(&entry.app), title),
+            Choice::Redact if entry.app.is_empty() => format!("[[ignore]]\ntitle = {}\nredact = true\n", title),
+            Choice::Redact => format!("[[ignore]]\napp = {}\ntitle = {}\nredact = true\n", string(&entry.app), title),
+            Choice::NeverApp => format!("[[ignore]]\napp = {}\n", string(&entry.app)),
+        }
+    }
+}
+
+fn app_label(app: &str) -> &str {
+    if app.is_empty() { "unknown" } else { app }
+}
+
+// The most tracked titles the current rules still record as they are
+fn entries(days: u32, config: &Config) -> Result<Vec<Entry>, String> {
+    let filter = WindowFilter::from_config(config)?;
+    let (from, to) = last_days(days);
+    let intervals = storage::load_intervals(from.with_timezone(&Utc), to.with_timezone(&Utc))
+        .map_err(|e| format!("failed to read history: {}", e))?;
+
+    let mut totals: HashMap<(&str, &str), f64> = HashMap::new();
+    for interval in &intervals {
+        if interval.title == REDACTED || !matches!(filter.decide(&interval.title, &interval.app), Decision::Track) {
+            continue;
+        }
+        *totals.entry((&interval.app, &interval.title)).or_insert(0.0) += interval.duration_secs();
+    }
+    let mut entries: Vec<Entry> = totals.into_iter()
+        .map(|((app, title), secs)| Entry { app: app.to_string(), title: title.to_string(), secs })
+        .collect();
+    entries.sort_by(|a, b| b.secs.total_cmp(&a.secs).then_with(|| a.title.cmp(&b.title)));
+    entries.truncate(REVIEW_LIMIT);
+    Ok(entries)
+}
+
+// "n3 r5 a7" marks entry 3 as never tracked, 5 as redacted and 7's app as never tracked
+fn parse_choices(line: &str, count: usize) -> Result<Vec<(usize, Choice)>, String> {
+    line.split_whitespace()
+        .map(|word| {
+            let mut chars = word.chars();
+            let choice = chars.next().and_then(Choice::parse);
+            let index = chars.as_str().parse::<usize>().ok().filter(|index| (1..=count).contains(index));
+            match (choice, index) {
+                (Some(choice), Some(index)) => Ok((index - 1, choice)),
+                _ => Err(format!("'{}' is not n, r or a followed by a number from 1 to {}", word, count)),
+            }
+        })
+        .collect()
+}
+
+pub fn prompt(text: &str) -> Result<Option<String>, String> {
+    eprint!("{}", text);
+    io::stderr().flush().map_err(|e| e.to_string())?;
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) => Ok(None),
+        Ok(_) => Ok(Some(line.trim().to_string())),
+        Err(e) => Err(format!("failed to read input: {}", e)),
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    if !io::stdin().is_terminal() {
+        return Err("review is interactive, run it in a terminal".to_string());
+    }
+    let days: u32 = args.parsed("--days", 7)?;
+    if days == 0 {
+        return Err("--days must be at least 1".to_string());
+    }
+
+    let config = config::load()?;
+    let entries = entries(days, &config)?;
+    if entries.is_empty() {
+        println!("Nothing tracked in the last {} days that isn't already ignored", days);
+        return Ok(());
+    }
+
+    println!("Most tracked titles in the last {} days:", days);
+    for (i, entry) in entries.iter().enumerate() {
+        println!("{:>3}  {:>8}  {:<16} {}", i + 1, humanize::duration(entry.secs), app_label(&entry.app), entry.title);
+    }
+    println!("\nMark entries with n<number> to never track the title, r<number> to track it without the title,");
+    println!("a<number> to never track the app at all. An empty line finishes.");
+
+    let mut chosen: Vec<(usize, Choice)> = Vec::new();
+    while let Some(line) = prompt("> ")? {
+        if line.is_empty() {
+            break;
+        }
+        match parse_choices(&line, entries.len()) {
+            Ok(choices) => {
+                for (index, choice) in choices {
+                    chosen.retain(|(other, _)| *other != index);
+                    chosen.push((index, choice));
+                    println!("  {}", choice.describe(&entries[index]));
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    if chosen.is_empty() {
+        println!("No rules added");
+        return Ok(());
+    }
+
+    let mut rules: Vec<String> = Vec::new();
+    for (index, choice) in &chosen {
+        let rule = choice.rule(&entries[*index]);
+        if !rules.contains(&rule) {
+            rules.push(rule);
+        }
+    }
+    confirm_and_append(&rules, |updated| WindowFilter::from_config(updated).map(|_| ()))
+}
+
+// Asks before appending the rules to config.toml
+pub fn confirm_and_append(rules: &[String], check: impl Fn(&Config) -> Result<(), String>) -> Result<(), String> {
+    let path = config::config_path();
+    let answer = prompt(&format!("Add {} rules to {}? [y/N] ", rules.len(), path.display()))?;
+    if !answer.is_some_and(|answer| answer.eq_ignore_ascii_case("y")) {
+        println!("No rules added");
+        return Ok(());
+    }
+
+    append_to_config(rules, check)?;
+    println!("Added {} rules to {}, a running tracker picks them up right away", rules.len(), path.display());
+    Ok(())
+}
+
+// Appends the snippets to config.toml, and only writes a config that loads and that `check` accepts
+pub fn append_to_config(snippets: &[String], check: impl Fn(&Config) -> Result<(), String>) -> Result<PathBuf, String> {
+    let path = config::config_path();
+    let mut text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+    };
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    for snippet in snippets {
+        text.push('\n');
+        text.push_str(snippet);
+    }
+    // Nothing is written that the tracker would refuse to load
+    let updated: Config = toml::from_str(&text).map_err(|e| format!("this would make the config invalid: {}", e))?;
+    check(&updated)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(&path, text).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
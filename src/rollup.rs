@@ -0,0 +1,248 @@
+// Pivots computed from the history rather than by whoever reads it: `report --group-by app,day
+// --rollup sum,count,max_streak` and GET /api/intervals?group_by=app,day&rollup=sum gives a row per
+// combination of the keys with the rollups as columns. Keys: app, title, category, class, window,
+// event, day, weekday and field:<name>; with day or weekday an interval crossing midnight counts on
+// both days. Rollups: sum, avg and max seconds of the intervals, count of them, and max_streak, the
+// longest stretch in the group where it was left for under STREAK_GAP at a time.
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Local, Utc};
+use serde_json::Value;
+
+use crate::cli::Args;
+use crate::config;
+use crate::filter::Filter;
+use crate::merge::Merges;
+use crate::report::{clip, last_days, local_midnight};
+use crate::storage::{self, Interval};
+use crate::table::{Format, Table};
+
+// Going elsewhere for less than this doesn't end a streak
+const STREAK_GAP: i64 = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Key {
+    App,
+    Title,
+    Category,
+    Class,
+    Window,
+    Event,
+    Day,
+    Weekday,
+    Field(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rollup {
+    Sum,
+    Count,
+    Avg,
+    Max,
+    MaxStreak,
+}
+
+impl Key {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "app" => Ok(Key::App),
+            "title" => Ok(Key::Title),
+            "category" => Ok(Key::Category),
+            "class" => Ok(Key::Class),
+            "window" => Ok(Key::Window),
+            "event" => Ok(Key::Event),
+            "day" => Ok(Key::Day),
+            "weekday" => Ok(Key::Weekday),
+            other => match other.strip_prefix("field:") {
+                Some(field) if !field.is_empty() => Ok(Key::Field(field.to_string())),
+                _ => Err(format!("unknown group-by key '{}', expected app, title, category, class, window, event, day, weekday or field:<name>", other)),
+            },
+        }
+    }
+
+    fn column(&self) -> String {
+        match self {
+            Key::App => "app".to_string(),
+            Key::Title => "title".to_string(),
+            Key::Category => "category".to_string(),
+            Key::Class => "class".to_string(),
+            Key::Window => "window".to_string(),
+            Key::Event => "event".to_string(),
+            Key::Day => "day".to_string(),
+            Key::Weekday => "weekday".to_string(),
+            Key::Field(field) => field.clone(),
+        }
+    }
+
+    fn value(&self, interval: &Interval) -> String {
+        let local = || interval.start.with_timezone(&Local);
+        match self {
+            Key::App => interval.app_name().to_string(),
+            Key::Title => interval.title.clone(),
+            Key::Category => interval.category_name().to_string(),
+            Key::Class => interval.class_name().to_string(),
+            Key::Window => interval.window_id.clone(),
+            Key::Event => interval.event.as_str().to_string(),
+            Key::Day => local().date_naive().to_string(),
+            Key::Weekday => local().format("%a").to_string(),
+            Key::Field(field) => interval.attributes.get(field).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+impl Rollup {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "sum" => Ok(Rollup::Sum),
+            "count" => Ok(Rollup::Count),
+            "avg" => Ok(Rollup::Avg),
+            "max" => Ok(Rollup::Max),
+            "max_streak" => Ok(Rollup::MaxStreak),
+            other => Err(format!("unknown rollup '{}', expected sum, count, avg, max or max_streak", other)),
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            Rollup::Sum => "sum_secs",
+            Rollup::Count => "count",
+            Rollup::Avg => "avg_secs",
+            Rollup::Max => "max_secs",
+            Rollup::MaxStreak => "max_streak_secs",
+        }
+    }
+
+    // Over the group's intervals in time order
+    fn value(&self, intervals: &[&Interval]) -> Value {
+        let secs = intervals.iter().map(|interval| interval.duration_secs());
+        match self {
+            Rollup::Sum => rounded(secs.sum()),
+            Rollup::Count => intervals.len().into(),
+            Rollup::Avg => rounded(secs.sum::<f64>() / intervals.len().max(1) as f64),
+            Rollup::Max => rounded(secs.fold(0.0, f64::max)),
+            Rollup::MaxStreak => {
+                let Some(first) = intervals.first() else { return 0.into() };
+                let (mut start, mut end) = (first.start, first.end);
+                let mut longest = end - start;
+                for interval in &intervals[1..] {
+                    if interval.start - end < Duration::seconds(STREAK_GAP) {
+                        end = end.max(interval.end);
+                    } else {
+                        (start, end) = (interval.start, interval.end);
+                    }
+                    longest = longest.max(end - start);
+                }
+                rounded(longest.num_milliseconds() as f64 / 1000.0)
+            }
+        }
+    }
+}
+
+// Whole seconds, like the secs column of the other tables
+pub fn rounded(secs: f64) -> Value {
+    (secs.round() as u64).into()
+}
+
+// Splits intervals at local midnight, so day keys count each day's own part
+fn by_day(intervals: &[Interval]) -> Vec<Interval> {
+    let mut pieces = Vec::with_capacity(intervals.len());
+    for interval in intervals {
+        let mut start = interval.start;
+        while start < interval.end {
+            let day = start.with_timezone(&Local).date_naive();
+            let end = local_midnight(day + Duration::days(1)).with_timezone(&Utc).min(interval.end);
+            pieces.push(Interval { start, end, ..interval.clone() });
+            start = end;
+        }
+    }
+    pieces
+}
+
+pub struct Pivot {
+    keys: Vec<Key>,
+    rollups: Vec<Rollup>,
+}
+
+impl Pivot {
+    // Comma-separated keys and rollups, sum when no rollup is given
+    pub fn parse(group_by: &str, rollups: Option<&str>) -> Result<Self, String> {
+        let keys = group_by.split(',').map(str::trim).filter(|key| !key.is_empty()).map(Key::parse).collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err("--group-by needs at least one key, e.g. app,day".to_string());
+        }
+        let rollups = rollups.unwrap_or("sum").split(',').map(str::trim).filter(|rollup| !rollup.is_empty()).map(Rollup::parse).collect::<Result<Vec<_>, _>>()?;
+        if rollups.is_empty() {
+            return Err("--rollup needs at least one of sum, count, avg, max or max_streak".to_string());
+        }
+        Ok(Pivot { keys, rollups })
+    }
+
+    // Totals by title are by the names [[merge]] gives them, as in the report
+    pub fn by_title(&self) -> bool {
+        self.keys.contains(&Key::Title)
+    }
+
+    // A row per combination of key values, the biggest first by the first rollup
+    pub fn table(&self, intervals: &[Interval]) -> Table {
+        let split;
+        let intervals = if self.keys.iter().any(|key| matches!(key, Key::Day | Key::Weekday)) {
+            split = by_day(intervals);
+            &split
+        } else {
+            intervals
+        };
+        let mut groups: HashMap<Vec<String>, Vec<&Interval>> = HashMap::new();
+        for interval in intervals {
+            groups.entry(self.keys.iter().map(|key| key.value(interval)).collect()).or_default().push(interval);
+        }
+
+        let mut rows: Vec<(Vec<String>, Vec<Value>)> = groups.into_iter()
+            .map(|(keys, mut intervals)| {
+                intervals.sort_by_key(|interval| interval.start);
+                let values = self.rollups.iter().map(|rollup| rollup.value(&intervals)).collect();
+                (keys, values)
+            })
+            .collect();
+        rows.sort_by(|(a_keys, a), (b_keys, b)| {
+            let (a_first, b_first) = (a[0].as_f64().unwrap_or(0.0), b[0].as_f64().unwrap_or(0.0));
+            b_first.total_cmp(&a_first).then_with(|| a_keys.cmp(b_keys))
+        });
+
+        let columns = self.keys.iter().map(Key::column).chain(self.rollups.iter().map(|rollup| rollup.column().to_string())).collect();
+        let mut table = Table::with_columns(columns);
+        for (keys, values) in rows {
+            table.push(keys.into_iter().map(Value::from).chain(values).collect());
+        }
+        table
+    }
+}
+
+// `report --group-by <keys> [--rollup <rollups>]`
+pub fn run(args: &Args) -> Result<(), String> {
+    print(args, args.value("--group-by").unwrap_or_default(), |from, to, filter| {
+        storage::load_matching(from, to, filter).map_err(|e| format!("failed to read history: {}", e))
+    })
+}
+
+// The pivot of what `load` gives for the --days and --filter of the command line
+pub fn print(
+    args: &Args,
+    group_by: &str,
+    load: impl FnOnce(DateTime<Utc>, DateTime<Utc>, &Filter) -> Result<Vec<Interval>, String>,
+) -> Result<(), String> {
+    let pivot = Pivot::parse(group_by, args.value("--rollup"))?;
+    let days: u32 = args.parsed("--days", 7)?;
+    if days == 0 {
+        return Err("--days must be at least 1".to_string());
+    }
+    let format = match Format::from_args(args)? {
+        Some(format) => format,
+        None if args.json_output()? => Format::Json,
+        None => Format::Table,
+    };
+    let (from, to) = last_days(days);
+    let history = load(from.with_timezone(&Utc), to.with_timezone(&Utc), &Filter::from_args(args)?)?;
+    let history = if pivot.by_title() { Merges::from_config(&config::load()?)?.apply(&history) } else { history };
+    pivot.table(&clip(&history, from, to)).print(format);
+    Ok(())
+}
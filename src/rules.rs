@@ -0,0 +1,276 @@
+// The config's [[ignore]], [[title_templates]], [[categories]] and [[enrichers]] run again over
+// stored intervals. `rules test` shows which categories would change without touching the history,
+// with WT_CONFIG pointing at a draft it tries rules out before the running tracker picks them up.
+// `recategorize` stores the new categories and fields, the title and app stay as they were
+// recorded, and keeps what it changed so `recategorize --undo` can put the last run back.
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::categories::Categorizer;
+use crate::cli::Args;
+use crate::config::{self, Config};
+use crate::enrichers::Enrichers;
+use crate::humanize;
+use crate::ignore::{Decision, WindowFilter, REDACTED};
+use crate::report::local_midnight;
+use crate::storage::{self, Interval};
+use crate::table::{Format, Table};
+use crate::templates::TitleParser;
+
+// The category of intervals the ignore rules would leave out
+const NOT_TRACKED: &str = "(not tracked)";
+// Titles listed under each change
+const TOP_TITLES: usize = 5;
+// Intervals between progress lines
+const PROGRESS_EVERY: usize = 1000;
+
+pub struct Rules {
+    filter: WindowFilter,
+    parser: TitleParser,
+    categorizer: Categorizer,
+    enrichers: Enrichers,
+}
+
+impl Rules {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        Enrichers::check(config)?;
+        let mut enrichers = Enrichers::default();
+        enrichers.configure(config);
+        Ok(Rules {
+            filter: WindowFilter::from_config(config)?,
+            parser: TitleParser::from_config(config)?,
+            categorizer: Categorizer::from_config(config)?,
+            enrichers,
+        })
+    }
+
+    // The interval as the tracker would record it now with the title and app it was recorded with,
+    // None when the ignore rules would leave it out
+    pub fn apply(&mut self, interval: &Interval) -> Option<Interval> {
+        let mut applied = interval.clone();
+        match self.filter.decide(&interval.title, &interval.app) {
+            Decision::Track => {}
+            Decision::Redact => applied.title = REDACTED.to_string(),
+            Decision::Skip => return None,
+        }
+        // Fields from templates since removed stay, as they did on the interval
+        applied.attributes.extend(self.parser.parse(&applied.title, &applied.app));
+        applied.category = self.categorizer.categorize(&applied.title, &applied.app, &applied.attributes).to_string();
+        self.enrichers.enrich(&mut applied);
+        applied.title = interval.title.clone();
+        applied.app = interval.app.clone();
+        Some(applied)
+    }
+}
+
+// today, yesterday, <n>d for n days ago, or yyyy-mm-dd
+pub fn parse_day(text: &str, option: &str) -> Result<NaiveDate, String> {
+    let today = Local::now().date_naive();
+    match text {
+        "today" => return Ok(today),
+        "yesterday" => return Ok(today - Duration::days(1)),
+        _ => {}
+    }
+    if let Some(days) = text.strip_suffix('d').and_then(|days| days.parse::<i64>().ok()) {
+        return Ok(today - Duration::days(days));
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map_err(|_| format!("invalid day '{}' for {}, expected today, yesterday, <n>d or yyyy-mm-dd", text, option))
+}
+
+// <from>..<to> including both days, either left out for no limit, or a single day
+fn parse_range(text: &str) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    let (from, to) = text.split_once("..").unwrap_or((text, text));
+    let from = match from {
+        "" => DateTime::<Utc>::MIN_UTC,
+        day => local_midnight(parse_day(day, "--range")?).with_timezone(&Utc),
+    };
+    let to = match to {
+        "" => DateTime::<Utc>::MAX_UTC,
+        day => local_midnight(parse_day(day, "--range")? + Duration::days(1)).with_timezone(&Utc),
+    };
+    if to <= from {
+        return Err(format!("the range '{}' ends before it starts", text));
+    }
+    Ok((from, to))
+}
+
+#[derive(Default)]
+struct Change {
+    intervals: usize,
+    secs: f64,
+    // (title, app) to time
+    titles: HashMap<(String, String), f64>,
+}
+
+// The windows that spent the most time in the change
+fn top_titles(change: &Change) -> Vec<(&(String, String), &f64)> {
+    let mut titles: Vec<(&(String, String), &f64)> = change.titles.iter().collect();
+    titles.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    titles.truncate(TOP_TITLES);
+    titles
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    if args.arg(1) != Some("test") {
+        return Err("usage: rules test [--since <today|yesterday|<n>d|yyyy-mm-dd>]".to_string());
+    }
+    let json_output = args.json_output()?;
+    let format = Format::from_args(args)?;
+    let since = parse_day(args.value("--since").unwrap_or("yesterday"), "--since")?;
+
+    let mut rules = Rules::from_config(&config::load()?)?;
+    let intervals = storage::load_intervals(local_midnight(since).with_timezone(&Utc), Utc::now())
+        .map_err(|e| format!("failed to read history: {}", e))?;
+
+    let mut changes: HashMap<(String, String), Change> = HashMap::new();
+    for interval in &intervals {
+        let category = match rules.apply(interval) {
+            Some(applied) => applied.category_name().to_string(),
+            None => NOT_TRACKED.to_string(),
+        };
+        if category == interval.category_name() {
+            continue;
+        }
+        let change = changes.entry((interval.category_name().to_string(), category)).or_default();
+        change.intervals += 1;
+        change.secs += interval.duration_secs();
+        *change.titles.entry((interval.title.clone(), interval.app_name().to_string())).or_default() += interval.duration_secs();
+    }
+
+    let mut changes: Vec<((String, String), Change)> = changes.into_iter().collect();
+    changes.sort_by(|(_, a), (_, b)| b.secs.total_cmp(&a.secs));
+    let changed: usize = changes.iter().map(|(_, change)| change.intervals).sum();
+
+    if let Some(format) = format {
+        let mut table = Table::new(&["from", "to", "intervals", "secs"]);
+        for ((from, to), change) in &changes {
+            table.push(vec![json!(from), json!(to), json!(change.intervals), json!(change.secs.round() as u64)]);
+        }
+        table.print(format);
+        return Ok(());
+    }
+
+    if json_output {
+        let listed: Vec<serde_json::Value> = changes.iter()
+            .map(|((from, to), change)| json!({
+                "from": from,
+                "to": to,
+                "intervals": change.intervals,
+                "secs": change.secs,
+                "titles": top_titles(change).into_iter()
+                    .map(|((title, app), secs)| json!({ "title": title, "app": app, "secs": secs }))
+                    .collect::<Vec<_>>(),
+            }))
+            .collect();
+        println!("{}", json!({ "since": since.to_string(), "intervals": intervals.len(), "changed": changed, "changes": listed }));
+        return Ok(());
+    }
+
+    println!("The rules in {} over {} intervals since {}: {} would change", config::config_path().display(), intervals.len(), since, changed);
+    for ((from, to), change) in &changes {
+        println!("{} -> {}: {} intervals, {}", from, to, change.intervals, humanize::duration(change.secs));
+        for ((title, app), secs) in top_titles(change) {
+            println!("  {} '{}' ({})", humanize::duration(*secs), title, app);
+        }
+    }
+    Ok(())
+}
+
+// One interval the last recategorize changed, as it was and as it was stored
+#[derive(Serialize, Deserialize)]
+struct Undo {
+    before: Interval,
+    after: Interval,
+}
+
+fn undo_path() -> PathBuf {
+    storage::data_dir().join("recategorize-undo.jsonl")
+}
+
+// Passes the stored intervals through rewrite_intervals, those matching a key take its
+// replacements in turn, so identical copies of one interval each get theirs
+fn replace(replacements: HashMap<String, VecDeque<Interval>>) -> Result<storage::Rewritten, String> {
+    let replacements = RefCell::new(replacements);
+    storage::rewrite_intervals(|interval| {
+        let Ok(key) = serde_json::to_string(interval) else { return Some(interval.clone()) };
+        let replacement = replacements.borrow_mut().get_mut(&key).and_then(VecDeque::pop_front);
+        Some(replacement.unwrap_or_else(|| interval.clone()))
+    }).map_err(|e| format!("failed to rewrite history: {}", e))
+}
+
+fn undo() -> Result<(), String> {
+    let path = undo_path();
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err("there is no recategorize to undo".to_string()),
+        Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+    };
+    let mut replacements: HashMap<String, VecDeque<Interval>> = HashMap::new();
+    let mut total = 0;
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let undo: Undo = serde_json::from_str(line).map_err(|e| format!("unreadable {}: {}", path.display(), e))?;
+        let key = serde_json::to_string(&undo.after).map_err(|e| e.to_string())?;
+        replacements.entry(key).or_default().push_back(undo.before);
+        total += 1;
+    }
+    let rewritten = replace(replacements)?;
+    fs::remove_file(&path).map_err(|e| format!("failed to remove {}: {}", path.display(), e))?;
+    println!("Restored {} intervals", rewritten.changed);
+    if rewritten.changed < total {
+        // Deleted or edited again since
+        println!("{} had changed since and were left as they are", total - rewritten.changed);
+    }
+    Ok(())
+}
+
+pub fn run_recategorize(args: &Args) -> Result<(), String> {
+    if args.flag("--undo") {
+        return undo();
+    }
+    let range = args.value("--range")
+        .ok_or("usage: recategorize --range <from>..<to> (days as today, yesterday, <n>d or yyyy-mm-dd, either may be left out)")?;
+    let (from, to) = parse_range(range)?;
+
+    let mut rules = Rules::from_config(&config::load()?)?;
+    let intervals = storage::load_intervals(from, to.min(Utc::now()))
+        .map_err(|e| format!("failed to read history: {}", e))?;
+
+    let mut undos = Vec::new();
+    for (done, interval) in intervals.iter().enumerate() {
+        if done > 0 && done % PROGRESS_EVERY == 0 {
+            eprintln!("Checked {} of {} intervals", done, intervals.len());
+        }
+        // Recorded before the current ignore rules, deleting it is up to purge
+        let Some(applied) = rules.apply(interval) else { continue };
+        if applied.category != interval.category || applied.attributes != interval.attributes {
+            undos.push(Undo { before: interval.clone(), after: applied });
+        }
+    }
+    println!("Checked {} intervals, {} get another category or fields", intervals.len(), undos.len());
+    if undos.is_empty() {
+        return Ok(());
+    }
+
+    // Kept before anything changes, so a run cut short can still be undone
+    let mut snapshot = String::new();
+    let mut replacements: HashMap<String, VecDeque<Interval>> = HashMap::new();
+    for undo in &undos {
+        snapshot.push_str(&serde_json::to_string(undo).map_err(|e| e.to_string())?);
+        snapshot.push('\n');
+        let key = serde_json::to_string(&undo.before).map_err(|e| e.to_string())?;
+        replacements.entry(key).or_default().push_back(undo.after.clone());
+    }
+    let path = undo_path();
+    fs::write(&path, snapshot).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+
+    let rewritten = replace(replacements)?;
+    println!("Recategorized {} intervals, recategorize --undo puts them back", rewritten.changed);
+    Ok(())
+}
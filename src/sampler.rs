@@ -0,0 +1,128 @@
+// Platform queries run on a worker thread. GetWindowTextW and friends block while the target
+// window's thread is hung, and XGetWindowProperty on a wedged X server; waiting with a timeout
+// turns that into a skipped sample instead of freezing the tracking loop, flushes and the API.
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long a caller waits for one query
+const TIMEOUT: Duration = Duration::from_secs(1);
+// A worker stuck this long is left to finish on its own and a fresh one takes over, so focus
+// moving on from the hung window is seen again
+const REPLACE_AFTER: Duration = Duration::from_secs(5);
+// Abandoned workers still blocked in a call, beyond this samples are skipped until one returns
+const MAX_ABANDONED: usize = 4;
+pub const THREAD_NAME: &str = "wt-sampler";
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Worker {
+    jobs: Sender<Job>,
+    // When the job it is running started, None while idle
+    busy_since: Arc<Mutex<Option<Instant>>>,
+    // Set once a hang has been logged, so a long one is reported once
+    reported: Arc<AtomicBool>,
+}
+
+static WORKER: Mutex<Option<Worker>> = Mutex::new(None);
+static ABANDONED: AtomicUsize = AtomicUsize::new(0);
+// Workers parked for good, see park
+static PARKED: AtomicUsize = AtomicUsize::new(0);
+
+impl Worker {
+    fn spawn() -> Option<Worker> {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name(THREAD_NAME.to_string())
+            .spawn(move || {
+                for job in queue {
+                    job();
+                }
+                // The sender only goes away when this worker was abandoned
+                ABANDONED.fetch_sub(1, Ordering::SeqCst);
+            })
+            .map_err(|e| eprintln!("Failed to start the sampler thread: {}", e))
+            .ok()?;
+        Some(Worker { jobs, busy_since: Arc::new(Mutex::new(None)), reported: Arc::new(AtomicBool::new(false)) })
+    }
+
+    fn stuck_for(&self) -> Option<Duration> {
+        self.busy_since.lock().unwrap().map(|since| since.elapsed())
+    }
+}
+
+// `query` on the worker, None when it doesn't return within the timeout or the worker is still
+// stuck in an earlier one. `name` is for the log line.
+pub fn run<T: Send + 'static>(name: &str, query: impl FnOnce() -> T + Send + 'static) -> Option<T> {
+    let (jobs, busy_since, reported) = {
+        let mut worker = WORKER.lock().unwrap();
+        if let Some(stuck_for) = worker.as_ref().and_then(Worker::stuck_for) {
+            let blocked = ABANDONED.load(Ordering::SeqCst).saturating_sub(PARKED.load(Ordering::SeqCst));
+            if stuck_for < REPLACE_AFTER || blocked >= MAX_ABANDONED {
+                return None;
+            }
+            eprintln!("The sampler has been stuck for {}s, starting another one", stuck_for.as_secs());
+            ABANDONED.fetch_add(1, Ordering::SeqCst);
+            *worker = None;
+        }
+        if worker.is_none() {
+            *worker = Some(Worker::spawn()?);
+        }
+        let worker = worker.as_ref().unwrap();
+        (worker.jobs.clone(), worker.busy_since.clone(), worker.reported.clone())
+    };
+
+    let (reply, result) = mpsc::sync_channel(1);
+    let job_busy_since = busy_since.clone();
+    let job_reported = reported.clone();
+    let job: Job = Box::new(move || {
+        *job_busy_since.lock().unwrap() = Some(Instant::now());
+        let value = query();
+        *job_busy_since.lock().unwrap() = None;
+        // Nobody is waiting any more after a timeout
+        let _ = reply.send(value);
+        if job_reported.swap(false, Ordering::SeqCst) {
+            eprintln!("The sampler is responding again");
+        }
+    });
+    jobs.send(job).ok()?;
+
+    match result.recv_timeout(TIMEOUT) {
+        Ok(value) => Some(value),
+        Err(RecvTimeoutError::Timeout) => {
+            if !reported.swap(true, Ordering::SeqCst) {
+                eprintln!("{} did not return within {}ms, skipping samples until it does", name, TIMEOUT.as_millis());
+            }
+            None
+        }
+        // The worker thread died with the query, likely a panic in the platform code
+        Err(RecvTimeoutError::Disconnected) => {
+            let mut worker = WORKER.lock().unwrap();
+            if worker.as_ref().is_some_and(|worker| Arc::ptr_eq(&worker.busy_since, &busy_since)) {
+                *worker = None;
+            }
+            None
+        }
+    }
+}
+
+// Drops the worker so the next query starts on a fresh thread, for when samples stopped coming
+// back without the worker looking stuck
+pub fn restart() {
+    // Its thread counts itself out once it finishes, like a replaced stuck worker
+    if WORKER.lock().unwrap().take().is_some() {
+        ABANDONED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// For a query on the worker that must never return, like Xlib's after its connection died where
+// libX11 is too old to let it return. The worker is replaced as if it hung, but doesn't count
+// against MAX_ABANDONED.
+pub fn park() -> ! {
+    PARKED.fetch_add(1, Ordering::SeqCst);
+    loop {
+        thread::park();
+    }
+}
@@ -0,0 +1,324 @@
+// [[schedule]]: reports the running tracker makes by itself, so an end-of-day summary shows up
+// without anyone running `report`. `at` is cron's minute hour day-of-month month day-of-week,
+// with *, lists, ranges, /steps and jan or mon style names, or one of @hourly, @daily, @weekly
+// and @monthly. Times are on the wall clock: a job at a time a change to daylight saving time skips
+// runs right after the jump, one at a time the change back repeats runs once.
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+use chrono::{Datelike, Duration, Local, NaiveDateTime, Timelike};
+
+use crate::config::{Config, ReportFormat, ScheduledJob};
+#[cfg(feature = "server")]
+use crate::i18n::tr;
+#[cfg(feature = "server")]
+use crate::mail;
+use crate::report::{self, GroupBy};
+
+// Jobs missed while the machine slept this long or less still run once it wakes up
+const CATCH_UP_MINS: i64 = 24 * 60;
+
+const MONTHS: &[&str] = &["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+// One bit per allowed value of each field
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // As in cron, a day matching either field is enough when both are restricted
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("'{}' needs 5 fields: minute hour day-of-month month day-of-week", expression));
+        };
+        Ok(Cron {
+            minutes: field(minutes, 0, 59, &[], "minute")?,
+            hours: field(hours, 0, 23, &[], "hour")?,
+            days: field(days, 1, 31, &[], "day of month")?,
+            months: field(months, 1, 12, MONTHS, "month")?,
+            // 7 is Sunday too
+            weekdays: field(weekdays, 0, 7, WEEKDAYS, "day of week").map(|bits| (bits | bits >> 7) & 0x7f)?,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        let has = |bits: u64, value: u32| bits & (1 << value) != 0;
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        has(self.minutes, time.minute()) && has(self.hours, time.hour()) && has(self.months, time.month()) && day_matches
+    }
+
+    // Whether a wall-clock minute after `last` up to `now` matches, the ones a change to daylight
+    // saving time skipped among them; none after a change back, until the clock is past `last` again
+    pub fn due(&self, last: NaiveDateTime, now: NaiveDateTime) -> bool {
+        let first = (last + Duration::minutes(1)).max(now - Duration::minutes(CATCH_UP_MINS));
+        (0..=(now - first).num_minutes()).any(|offset| self.matches(&(first + Duration::minutes(offset))))
+    }
+}
+
+// "1-5", "*/15", "mon,wed" and the like as bits min..=max; names count from min
+fn field(text: &str, min: u32, max: u32, names: &[&str], name: &str) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let lower = text.to_lowercase();
+        let number = match names.iter().position(|candidate| *candidate == lower) {
+            Some(index) => index as u32 + min,
+            None => text.parse().map_err(|_| format!("'{}' is not a {}", text, name))?,
+        };
+        if number < min || number > max {
+            return Err(format!("{} {} is outside {}-{}", name, number, min, max));
+        }
+        Ok(number)
+    };
+
+    let mut bits = 0;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0).ok_or_else(|| format!("'{}' is not a step", step))?),
+            None => (part, 1),
+        };
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (value(first)?, value(last)?),
+            // "5/10" runs from 5 to the end like in cron
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        if first > last {
+            return Err(format!("'{}' runs backwards", range));
+        }
+        for number in (first..=last).step_by(step as usize) {
+            bits |= 1 << number;
+        }
+    }
+    Ok(bits)
+}
+
+fn job_name(job: &ScheduledJob) -> &str {
+    job.name.as_deref().unwrap_or(&job.at)
+}
+
+pub struct Schedule {
+    jobs: Vec<(Cron, ScheduledJob)>,
+}
+
+impl Schedule {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let jobs = config.schedule.iter()
+            .map(|job| {
+                let cron = Cron::parse(&job.at).map_err(|e| format!("invalid schedule '{}': {}", job_name(job), e))?;
+                if job.path.is_none() && job.webhook.is_none() && job.mail_to.is_none() {
+                    return Err(format!("schedule '{}' has nothing to do, set path, webhook or mail_to", job_name(job)));
+                }
+                if job.days == 0 {
+                    return Err(format!("schedule '{}': days must be at least 1", job_name(job)));
+                }
+                #[cfg(not(feature = "webhook"))]
+                if job.webhook.is_some() {
+                    return Err(format!("schedule '{}' posts to a webhook, which needs --features webhook", job_name(job)));
+                }
+                #[cfg(not(feature = "server"))]
+                if job.mail_to.is_some() {
+                    return Err(format!("schedule '{}' mails the report, which needs --features server", job_name(job)));
+                }
+                Ok((cron, job.clone()))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Schedule { jobs })
+    }
+}
+
+#[derive(Default)]
+pub struct ScheduleWatch {
+    // The latest wall-clock minute jobs were checked for, nothing runs for the one tracking starts in
+    last_minute: Option<NaiveDateTime>,
+}
+
+impl ScheduleWatch {
+    pub fn check(&mut self, config: &Config) {
+        let now = Local::now().naive_local();
+        let Some(minute) = now.with_second(0).and_then(|time| time.with_nanosecond(0)) else { return };
+        let last = *self.last_minute.get_or_insert(minute);
+        if minute <= last {
+            return;
+        }
+        self.last_minute = Some(minute);
+        // Checked with the rest of the config when it was loaded
+        let Ok(schedule) = Schedule::from_config(config) else { return };
+
+        for (cron, job) in schedule.jobs {
+            if cron.due(last, minute) {
+                // Mail and webhooks can take a while, sampling goes on meanwhile
+                thread::spawn(move || {
+                    if let Err(e) = run(&job) {
+                        eprintln!("Scheduled job '{}' failed: {}", job_name(&job), e);
+                    }
+                });
+            }
+        }
+    }
+}
+
+pub fn run(job: &ScheduledJob) -> Result<(), String> {
+    let summary = report::load(job.days, GroupBy::configured()?)?;
+    if let Some(path) = &job.path {
+        let text = match job.format {
+            ReportFormat::Html => report::render_html(&summary),
+            ReportFormat::Json => report::render_json(&summary),
+            ReportFormat::Text => report::render_text(&summary),
+        };
+        let path = path.replace("{date}", &Local::now().format("%Y-%m-%d").to_string());
+        if let Some(parent) = Path::new(&path).parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&path, text).map_err(|e| format!("failed to write {}: {}", path, e))?;
+    }
+    #[cfg(feature = "webhook")]
+    if let Some(url) = &job.webhook {
+        ureq::post(url)
+            .timeout(std::time::Duration::from_secs(30))
+            .set("Content-Type", "application/json")
+            .send_string(&report::render_json(&summary))
+            .map_err(|e| format!("failed to post to {}: {}", url, e))?;
+    }
+    #[cfg(feature = "server")]
+    if let Some(to) = &job.mail_to {
+        let subject = tr("html-title", &[("period", &report::period_label(&summary))]);
+        mail::send_html(&job.smtp, &job.mail_from, to, &subject, &report::render_html(&summary))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    // The minutes of the day the expression matches, as HH:MM
+    fn times_on(cron: &str, day: &str) -> Vec<String> {
+        let cron = Cron::parse(cron).unwrap();
+        let midnight = at(&format!("{} 00:00", day));
+        (0..24 * 60).map(|minute| midnight + Duration::minutes(minute))
+            .filter(|time| cron.matches(time))
+            .map(|time| time.format("%H:%M").to_string())
+            .collect()
+    }
+
+    // The days of 2024 the expression matches at midnight
+    fn days_in_2024(cron: &str) -> Vec<NaiveDate> {
+        let cron = Cron::parse(cron).unwrap();
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().iter_days()
+            .take_while(|day| day.year() == 2024)
+            .filter(|day| cron.matches(&day.and_hms_opt(0, 0, 0).unwrap()))
+            .collect()
+    }
+
+    fn day(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn ranges_lists_and_steps() {
+        assert_eq!(times_on("0 9-11 * * *", "2024-05-06"), ["09:00", "10:00", "11:00"]);
+        assert_eq!(times_on("15,45 9 * * *", "2024-05-06"), ["09:15", "09:45"]);
+        assert_eq!(times_on("*/20 9 * * *", "2024-05-06"), ["09:00", "09:20", "09:40"]);
+        assert_eq!(times_on("10-30/10 9 * * *", "2024-05-06"), ["09:10", "09:20", "09:30"]);
+        // A start with a step runs to the end like in cron
+        assert_eq!(times_on("50/5 9 * * *", "2024-05-06"), ["09:50", "09:55"]);
+        assert_eq!(times_on("0 */8 * * *", "2024-05-06"), ["00:00", "08:00", "16:00"]);
+        assert_eq!(times_on("@hourly", "2024-05-06").len(), 24);
+        assert_eq!(times_on("@daily", "2024-05-06"), ["00:00"]);
+    }
+
+    #[test]
+    fn days_of_the_week_and_of_the_month() {
+        // 2024-05-06 is a Monday
+        assert_eq!(times_on("0 18 * * mon-fri", "2024-05-06"), ["18:00"]);
+        assert!(times_on("0 18 * * mon-fri", "2024-05-05").is_empty());
+        // Sunday is 0 and 7
+        assert_eq!(times_on("0 18 * * 7", "2024-05-05"), ["18:00"]);
+        assert_eq!(times_on("0 18 * * SUN", "2024-05-05"), ["18:00"]);
+        assert_eq!(days_in_2024("@weekly").len(), 52);
+        // Either field restricted is enough, as in cron: the 13th or a Friday
+        let days = days_in_2024("0 0 13 * fri");
+        assert!(days.contains(&day(9, 13)) && days.contains(&day(9, 6)) && days.contains(&day(8, 13)));
+        assert_eq!(days.len(), 12 + 52 - 2);
+    }
+
+    #[test]
+    fn month_boundaries() {
+        assert_eq!(days_in_2024("@monthly").len(), 12);
+        // The 31st only comes in the months that have one, a leap day only in a leap year
+        assert_eq!(days_in_2024("0 0 31 * *").len(), 7);
+        assert_eq!(days_in_2024("0 0 29 feb *"), [day(2, 29)]);
+        assert_eq!(days_in_2024("0 0 30 jan-mar *"), [day(1, 30), day(3, 30)]);
+        assert_eq!(days_in_2024("0 0 1 */3 *"), [day(1, 1), day(4, 1), day(7, 1), day(10, 1)]);
+        // Due across midnight at the end of a month and of the year
+        let monthly = Cron::parse("@monthly").unwrap();
+        assert!(monthly.due(at("2024-01-31 23:59"), at("2024-02-01 00:00")));
+        assert!(monthly.due(at("2024-12-31 23:59"), at("2025-01-01 00:00")));
+        assert!(!monthly.due(at("2024-02-01 00:00"), at("2024-02-01 00:01")));
+    }
+
+    #[test]
+    fn daylight_saving_time_changes() {
+        let cron = Cron::parse("30 2 * * *").unwrap();
+        // Clocks jumping from 02:00 to 03:00: due right after the jump
+        assert!(cron.due(at("2024-03-31 01:59"), at("2024-03-31 03:00")));
+        // Jumping back from 03:00 to 02:00, the repeated 02:30 is before the latest minute
+        // checked and doesn't run again, the minutes after it do
+        assert!(cron.due(at("2024-10-27 02:29"), at("2024-10-27 02:30")));
+        assert!(!cron.due(at("2024-10-27 02:59"), at("2024-10-27 02:30")));
+        assert!(!cron.due(at("2024-10-27 02:59"), at("2024-10-27 03:00")));
+        assert!(Cron::parse("0 3 * * *").unwrap().due(at("2024-10-27 02:59"), at("2024-10-27 03:00")));
+        // Missed while asleep for a day at most, then not any more
+        assert!(cron.due(at("2024-05-05 23:00"), at("2024-05-06 12:00")));
+        let fourth = Cron::parse("30 2 4 may *").unwrap();
+        assert!(fourth.due(at("2024-05-03 12:00"), at("2024-05-05 01:00")));
+        assert!(!fourth.due(at("2024-05-03 12:00"), at("2024-05-05 03:00")));
+    }
+
+    #[test]
+    fn malformed_expressions_are_errors() {
+        for (expression, error) in [
+            ("* * * *", "needs 5 fields"),
+            ("60 * * * *", "minute 60 is outside 0-59"),
+            ("* 24 * * *", "hour 24 is outside 0-23"),
+            ("* * 0 * *", "day of month 0 is outside 1-31"),
+            ("* * * foo *", "'foo' is not a month"),
+            ("*/0 * * * *", "'0' is not a step"),
+            ("30-10 * * * *", "'30-10' runs backwards"),
+            ("* * * * 8", "day of week 8 is outside 0-7"),
+        ] {
+            let e = Cron::parse(expression).unwrap_err();
+            assert!(e.contains(error), "{}: {}", expression, e);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+// [[enrichers]] with script = "<file>.rhai" instead of a command: classification and redaction
+// written as a small rhai script that runs inside the tracker. It sees the finished interval as
+// `interval`, the map a plugin reads on stdin, and `secs`, how long it was; its value is what a
+// plugin answers, like #{ category: "Client work", attributes: #{ client: "acme" } } or
+// #{ title: "(private)" }, and () keeps the interval as it is. matches(text, pattern) tests a regex.
+//
+// Scripts are sandboxed: there are no modules, no eval and no print, and nothing in the engine
+// reaches files, the network or other processes. A run is stopped past timeout_ms or
+// max_operations, strings, arrays and maps are capped in size, and calls and expressions in depth.
+// The file is read again when it changes; a version that doesn't compile keeps the previous one.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use regex::{Regex, RegexBuilder};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use crate::config::{self, EnricherConfig};
+use crate::enrichers::{Changes, Enricher};
+use crate::storage::Interval;
+
+const MAX_STRING_BYTES: usize = 64 * 1024;
+const MAX_ITEMS: usize = 10_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPRESSION_DEPTH: usize = 64;
+const MAX_VARIABLES: usize = 256;
+const MAX_PATTERN_BYTES: usize = 1 << 20;
+const MAX_PATTERNS: usize = 256;
+
+// Compiled patterns for matches(), shared by every script
+static PATTERNS: Mutex<BTreeMap<String, Regex>> = Mutex::new(BTreeMap::new());
+
+fn matches(text: &str, pattern: &str) -> Result<bool, Box<EvalAltResult>> {
+    let mut patterns = PATTERNS.lock().unwrap();
+    if !patterns.contains_key(pattern) {
+        let regex = RegexBuilder::new(pattern).size_limit(MAX_PATTERN_BYTES).build()
+            .map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?;
+        if patterns.len() >= MAX_PATTERNS {
+            patterns.clear();
+        }
+        patterns.insert(pattern.to_string(), regex);
+    }
+    Ok(patterns[pattern].is_match(text))
+}
+
+// The engine with nothing from outside the script in reach, stopped once `deadline` passes
+fn sandbox(max_operations: u64, deadline: Arc<Mutex<Instant>>) -> Engine {
+    let mut engine = Engine::new();
+    // The default resolver would load any file an import names
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine.set_max_operations(max_operations);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_expr_depths(MAX_EXPRESSION_DEPTH, MAX_EXPRESSION_DEPTH);
+    engine.set_max_variables(MAX_VARIABLES);
+    engine.set_max_string_size(MAX_STRING_BYTES);
+    engine.set_max_array_size(MAX_ITEMS);
+    engine.set_max_map_size(MAX_ITEMS);
+    engine.on_progress(move |_| (Instant::now() >= *deadline.lock().unwrap()).then(|| Dynamic::from("timeout")));
+    engine.register_fn("matches", matches);
+    engine
+}
+
+pub struct Script {
+    name: String,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    engine: Engine,
+    ast: AST,
+    timeout: Duration,
+    max_operations: u64,
+    deadline: Arc<Mutex<Instant>>,
+}
+
+// A script's path as config.toml gives it, next to config.toml when relative
+fn script_path(config: &EnricherConfig) -> PathBuf {
+    let script = config.script.clone().unwrap_or_default();
+    match config::base_config_path().parent() {
+        Some(directory) if script.is_relative() => directory.join(script),
+        _ => script,
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl Script {
+    pub fn load(config: &EnricherConfig) -> Result<Self, String> {
+        let path = script_path(config);
+        let source = fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let mut script = Script::compile(config, &source).map_err(|e| format!("{}: {}", path.display(), e))?;
+        script.modified = modified(&path);
+        script.path = path;
+        Ok(script)
+    }
+
+    fn compile(config: &EnricherConfig, source: &str) -> Result<Self, String> {
+        let deadline = Arc::new(Mutex::new(Instant::now()));
+        let engine = sandbox(config.max_operations, deadline.clone());
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+        let path = config.script.clone().unwrap_or_default();
+        Ok(Script {
+            name: config.name.clone().unwrap_or_else(|| path.display().to_string()),
+            path,
+            modified: None,
+            engine,
+            ast,
+            timeout: Duration::from_millis(config.timeout_ms),
+            max_operations: config.max_operations,
+            deadline,
+        })
+    }
+
+    // Picks up an edited script, keeping the one that ran so far when it doesn't compile
+    fn reload(&mut self) {
+        let modified = modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        let compiled = fs::read_to_string(&self.path).map_err(|e| e.to_string())
+            .and_then(|source| self.engine.compile(source).map_err(|e| e.to_string()));
+        match compiled {
+            Ok(ast) => self.ast = ast,
+            Err(e) => eprintln!("Script {} doesn't compile, keeping the previous version: {}", self.path.display(), e),
+        }
+    }
+
+    fn run(&self, interval: &Interval) -> Result<Option<Changes>, String> {
+        let mut scope = Scope::new();
+        scope.push("interval", rhai::serde::to_dynamic(interval).map_err(|e| e.to_string())?);
+        scope.push("secs", interval.duration_secs());
+        *self.deadline.lock().unwrap() = Instant::now() + self.timeout;
+        let value: Dynamic = self.engine.eval_ast_with_scope(&mut scope, &self.ast).map_err(|e| match *e {
+            EvalAltResult::ErrorTerminated(..) => format!("no answer within {}ms", self.timeout.as_millis()),
+            EvalAltResult::ErrorTooManyOperations(_) => format!("stopped after max_operations ({})", self.max_operations),
+            other => other.to_string(),
+        })?;
+        if value.is_unit() {
+            return Ok(None);
+        }
+        rhai::serde::from_dynamic(&value).map(Some).map_err(|e| format!("unreadable answer {}: {}", value, e))
+    }
+}
+
+impl Enricher for Script {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn enrich(&mut self, interval: &mut Interval) -> Result<(), String> {
+        self.reload();
+        if let Some(changes) = self.run(interval)? {
+            changes.apply(interval);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_operations: u64) -> EnricherConfig {
+        EnricherConfig {
+            name: None,
+            command: Vec::new(),
+            script: Some(PathBuf::from("test.rhai")),
+            timeout_ms: 200,
+            max_operations,
+        }
+    }
+
+    fn script(source: &str) -> Result<Script, String> {
+        Script::compile(&config(100_000), source)
+    }
+
+    fn interval(title: &str, app: &str) -> Interval {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        Interval::new(title, app, start, start + Duration::from_secs(90))
+    }
+
+    #[test]
+    fn a_script_classifies_and_redacts() {
+        let mut script = script(r#"
+            if interval.app == "slack" && matches(interval.title, "(?i)acme") {
+                #{ category: "Client work", title: "(private)", attributes: #{ client: "acme", secs: `${secs}` } }
+            }
+        "#).unwrap();
+        let mut acme = interval("ACME standup", "slack");
+        script.enrich(&mut acme).unwrap();
+        assert_eq!((acme.category.as_str(), acme.title.as_str()), ("Client work", "(private)"));
+        assert_eq!(acme.attributes["client"], "acme");
+        assert_eq!(acme.attributes["secs"], "90.0");
+
+        let mut other = interval("general", "slack");
+        script.enrich(&mut other).unwrap();
+        assert_eq!(other, interval("general", "slack"));
+    }
+
+    #[test]
+    fn a_script_is_stopped_past_its_limits() {
+        let endless = script("loop {}").unwrap();
+        assert!(endless.run(&interval("a", "b")).unwrap_err().contains("max_operations"));
+        let slow = Script::compile(&config(u64::MAX), "loop {}").unwrap();
+        let started = Instant::now();
+        assert!(slow.run(&interval("a", "b")).unwrap_err().contains("no answer within 200ms"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        let growing = script(r#"let text = "x"; loop { text += text; }"#).unwrap();
+        assert!(growing.run(&interval("a", "b")).is_err());
+    }
+
+    #[test]
+    fn a_script_reaches_nothing_outside() {
+        assert!(script(r#"eval("1")"#).is_err());
+        let module = std::env::temp_dir().join(format!("wt-script-{}.rhai", std::process::id()));
+        fs::write(&module, "export const SECRET = 42;").unwrap();
+        let import = script(&format!("import {:?} as other; #{{ title: `${{other::SECRET}}` }}", module.with_extension("").display().to_string()));
+        let imported = import.unwrap().run(&interval("a", "b"));
+        let _ = fs::remove_file(&module);
+        assert!(imported.is_err());
+        assert!(script(r#"#{ owner: "me" }"#).unwrap().run(&interval("a", "b")).unwrap_err().contains("unreadable answer"));
+        assert!(script("print(interval.title); ()").unwrap().run(&interval("a", "b")).unwrap().is_none());
+    }
+}
@@ -0,0 +1,124 @@
+// [statsd]: focus seconds per category and window switches sent every interval_secs while tracking,
+// for monitoring stacks older than Prometheus. StatsD gets counters over UDP:
+//   window_tracker.focus_secs.Coding:10|c
+// and Graphite the seconds of each interval over TCP in the plaintext protocol:
+//   window_tracker.focus_secs.Coding 10 1700000000
+// The open interval counts as it goes, so a long stretch in one window doesn't arrive in one piece.
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::categories::UNCATEGORIZED;
+use crate::config::{MetricsProtocol, StatsdConfig};
+use crate::hot;
+
+// Below the usual 1500 byte MTU with the UDP and IP headers taken off
+const MAX_PACKET: usize = 1432;
+
+pub fn check(config: &StatsdConfig) -> Result<(), String> {
+    config.address.to_socket_addrs()
+        .map_err(|e| format!("invalid [statsd] address '{}': {}", config.address, e))?;
+    if config.interval_secs == 0 {
+        return Err("the [statsd] interval_secs must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+// A category as one dot-separated path segment
+fn segment(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+fn send_statsd(config: &StatsdConfig, metrics: &[(String, u64)]) -> Result<(), String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("failed to open a UDP socket: {}", e))?;
+    let mut packet = String::new();
+    for (name, value) in metrics {
+        let line = format!("{}.{}:{}|c", config.prefix, name, value);
+        if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET {
+            socket.send_to(packet.as_bytes(), &config.address).map_err(|e| format!("failed to send to {}: {}", config.address, e))?;
+            packet.clear();
+        }
+        if !packet.is_empty() {
+            packet.push('\n');
+        }
+        packet.push_str(&line);
+    }
+    if !packet.is_empty() {
+        socket.send_to(packet.as_bytes(), &config.address).map_err(|e| format!("failed to send to {}: {}", config.address, e))?;
+    }
+    Ok(())
+}
+
+fn send_graphite(config: &StatsdConfig, metrics: &[(String, u64)]) -> Result<(), String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let lines: String = metrics.iter().map(|(name, value)| format!("{}.{} {} {}\n", config.prefix, name, value, now)).collect();
+    let address = config.address.to_socket_addrs().ok().and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| format!("failed to resolve {}", config.address))?;
+    let mut stream = TcpStream::connect_timeout(&address, Duration::from_secs(5))
+        .map_err(|e| format!("failed to connect to {}: {}", config.address, e))?;
+    stream.set_write_timeout(Some(Duration::from_secs(5))).ok();
+    stream.write_all(lines.as_bytes()).map_err(|e| format!("failed to send to {}: {}", config.address, e))
+}
+
+// Sends from a background thread so an unreachable daemon never delays sampling
+pub fn start(config: &StatsdConfig) -> Result<(), String> {
+    check(config)?;
+    // Focus time by category of the intervals stored so far this session
+    let stored: Arc<Mutex<HashMap<String, f64>>> = Arc::default();
+    let listener_stored = Arc::clone(&stored);
+    crate::wt_add_interval_listener(Box::new(move |interval| {
+        *listener_stored.lock().unwrap().entry(interval.category_name().to_string()).or_default() += interval.duration_secs();
+    }));
+
+    let config = config.clone();
+    thread::spawn(move || {
+        // Whole seconds sent per category and the switches sent, each total only ever goes up. When
+        // an interval is stored just as the open one is read it counts twice for a moment, and the
+        // time after it is held back until the total catches up.
+        let mut sent: HashMap<String, u64> = HashMap::new();
+        let (mut sent_switches, mut sent_title_changes) = hot::event_counts();
+        loop {
+            thread::sleep(Duration::from_secs(config.interval_secs));
+            let mut totals = stored.lock().unwrap().clone();
+            if let Some(window) = hot::current() {
+                let category = crate::wt_categorize(&window);
+                let category = if category.is_empty() { UNCATEGORIZED.to_string() } else { category };
+                *totals.entry(category).or_default() += hot::focus_duration().as_secs_f64();
+            }
+
+            let mut metrics: Vec<(String, u64)> = Vec::new();
+            for (category, secs) in totals {
+                let sent = sent.entry(category.clone()).or_default();
+                let whole = secs as u64;
+                if whole > *sent {
+                    metrics.push((format!("focus_secs.{}", segment(&category)), whole - *sent));
+                    *sent = whole;
+                }
+            }
+            let (switches, title_changes) = hot::event_counts();
+            if switches > sent_switches {
+                metrics.push(("switches".to_string(), switches - sent_switches));
+            }
+            if title_changes > sent_title_changes {
+                metrics.push(("title_changes".to_string(), title_changes - sent_title_changes));
+            }
+            (sent_switches, sent_title_changes) = (switches, title_changes);
+            if metrics.is_empty() {
+                continue;
+            }
+            metrics.sort();
+
+            let result = match config.protocol {
+                MetricsProtocol::Statsd => send_statsd(&config, &metrics),
+                MetricsProtocol::Graphite => send_graphite(&config, &metrics),
+            };
+            if let Err(e) = result {
+                eprintln!("{}", e);
+            }
+        }
+    });
+    Ok(())
+}
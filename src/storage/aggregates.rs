@@ -0,0 +1,357 @@
+// Daily totals of the stored history in aggregates.json, so reports over months don't parse every
+// interval again: per day and hour the seconds of each (title, app, class, category, call), and the
+// day's switch counts, with how far into the history they go. Each load first adds whatever was appended since,
+// from the tracker, an import or another profile's flush alike, and anything that rewrites the
+// history drops the file, to be built again from all of it.
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Offset, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{backend, data_dir, pending, EventKind, Interval};
+use crate::report::wall_clock;
+
+const AGGREGATES_FILE: &str = "aggregates.json";
+// Bumped when what a day holds changes, older files are rebuilt
+const VERSION: u32 = 1;
+
+// What a row is told apart by
+type RowKey = (u32, String, String, String, String, bool);
+type Totals = HashMap<NaiveDate, HashMap<RowKey, f64>>;
+type Counts = HashMap<NaiveDate, (usize, usize)>;
+
+#[derive(Serialize, Deserialize)]
+struct Cache {
+    version: u32,
+    // Which backend position is a byte offset or rowid of
+    backend: String,
+    position: u64,
+    days: BTreeMap<NaiveDate, Day>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Day {
+    rows: Vec<Row>,
+    switches: usize,
+    title_changes: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Row {
+    // Local hour of the day
+    hour: u32,
+    title: String,
+    app: String,
+    class: String,
+    category: String,
+    call: bool,
+    secs: f64,
+}
+
+// The days asked for: each row as an interval starting at its hour and lasting its seconds, which
+// is all the totals and the anomalies' day parts need, and the switch counts per day
+pub struct Days {
+    pub intervals: Vec<Interval>,
+    pub counts: BTreeMap<NaiveDate, (usize, usize)>,
+}
+
+impl Cache {
+    fn empty() -> Self {
+        Cache { version: VERSION, backend: backend::NAME.to_string(), position: 0, days: BTreeMap::new() }
+    }
+
+    fn add(&mut self, intervals: &[Interval]) {
+        let (mut totals, mut counts) = (Totals::new(), Counts::new());
+        for interval in intervals {
+            add(&mut totals, &mut counts, interval);
+        }
+        merge(&mut self.days, totals, counts);
+    }
+}
+
+fn path() -> PathBuf {
+    data_dir().join(AGGREGATES_FILE)
+}
+
+fn read() -> Cache {
+    let cache: Option<Cache> = fs::read(path()).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok());
+    cache.filter(|cache| cache.version == VERSION && cache.backend == backend::NAME).unwrap_or_else(Cache::empty)
+}
+
+fn write(cache: &Cache) -> io::Result<()> {
+    let path = path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, serde_json::to_vec(cache)?)?;
+    fs::rename(temporary, path)
+}
+
+pub fn clear() -> io::Result<()> {
+    match fs::remove_file(path()) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+// The interval's seconds in each hour it falls on, by the clock it was recorded on as daily_chunks
+fn hourly_chunks(interval: &Interval) -> Vec<(NaiveDate, u32, f64)> {
+    match interval.recorded_offset() {
+        Some(offset) if offset != interval.start.with_timezone(&Local).offset().fix() => chunks_in(&offset, interval),
+        _ => chunks_in(&Local, interval),
+    }
+}
+
+fn chunks_in<Tz: TimeZone>(zone: &Tz, interval: &Interval) -> Vec<(NaiveDate, u32, f64)> {
+    let mut chunks = Vec::new();
+    let mut start = interval.start.with_timezone(zone);
+    let end = interval.end.with_timezone(zone);
+    while start < end {
+        let (day, hour) = (start.date_naive(), start.hour());
+        let next_hour = match hour {
+            23 => (day + Duration::days(1)).and_hms_opt(0, 0, 0),
+            _ => day.and_hms_opt(hour + 1, 0, 0),
+        };
+        let chunk_end = end.clone().min(wall_clock(zone, next_hour.unwrap()));
+        chunks.push((day, hour, (chunk_end.clone() - start.clone()).num_milliseconds() as f64 / 1000.0));
+        start = chunk_end;
+    }
+    chunks
+}
+
+// Adds the interval's seconds to each hour it falls on, and its switch to the day it started
+fn add(totals: &mut Totals, counts: &mut Counts, interval: &Interval) {
+    let chunks = hourly_chunks(interval);
+    for (day, hour, secs) in &chunks {
+        let key = (*hour, interval.title.clone(), interval.app.clone(), interval.class.clone(), interval.category.clone(), interval.call);
+        *totals.entry(*day).or_default().entry(key).or_default() += secs;
+    }
+    if let Some((day, _, _)) = chunks.first() {
+        let counts = counts.entry(*day).or_default();
+        match interval.event {
+            EventKind::FocusChanged => counts.0 += 1,
+            EventKind::TitleChanged => counts.1 += 1,
+            EventKind::CallChanged => {}
+        }
+    }
+}
+
+fn merge(days: &mut BTreeMap<NaiveDate, Day>, totals: Totals, counts: Counts) {
+    for (day, added) in totals {
+        let rows = &mut days.entry(day).or_default().rows;
+        let mut index: HashMap<RowKey, usize> = rows.iter().enumerate()
+            .map(|(i, row)| ((row.hour, row.title.clone(), row.app.clone(), row.class.clone(), row.category.clone(), row.call), i))
+            .collect();
+        for (key, secs) in added {
+            match index.get(&key) {
+                Some(&i) => rows[i].secs += secs,
+                None => {
+                    index.insert(key.clone(), rows.len());
+                    let (hour, title, app, class, category, call) = key;
+                    rows.push(Row { hour, title, app, class, category, call, secs });
+                }
+            }
+        }
+    }
+    for (day, (switches, title_changes)) in counts {
+        let day = days.entry(day).or_default();
+        day.switches += switches;
+        day.title_changes += title_changes;
+    }
+}
+
+fn at(day: NaiveDate, hour: u32) -> DateTime<Utc> {
+    wall_clock(&Local, day.and_hms_opt(hour, 0, 0).unwrap()).with_timezone(&Utc)
+}
+
+// Brings the file up to what the history holds now
+fn update() -> io::Result<Cache> {
+    let mut cache = read();
+    let mut appended = Vec::new();
+    let mut collect = |interval| {
+        appended.push(interval);
+        Ok(())
+    };
+    let position = match backend::scan_appended(cache.position, &mut collect)? {
+        Some(position) => position,
+        // Replaced without going through the storage layer, like by hand
+        None => {
+            cache = Cache::empty();
+            backend::scan_appended(0, &mut collect)?.unwrap_or_default()
+        }
+    };
+    if appended.is_empty() && position == cache.position {
+        return Ok(cache);
+    }
+    cache.add(&appended);
+    cache.position = position;
+    if write(&cache).is_err() {
+        // Not being able to keep it only makes the next report slower
+        let _ = clear();
+    }
+    Ok(cache)
+}
+
+pub fn load(first: NaiveDate, last: NaiveDate) -> io::Result<Days> {
+    let cache = update()?;
+    let mut days: BTreeMap<NaiveDate, Day> = cache.days.range(first..=last).map(|(day, totals)| (*day, totals.clone())).collect();
+    // Intervals still queued for the history are added to the result but not kept, they come in
+    // with the tail once flushed; a day either side for those running past midnight
+    let queued = pending::load(at(first - Duration::days(1), 0), at(last + Duration::days(2), 0))?;
+    if !queued.is_empty() {
+        let (mut totals, mut counts) = (Totals::new(), Counts::new());
+        for interval in &queued {
+            add(&mut totals, &mut counts, interval);
+        }
+        totals.retain(|day, _| (first..=last).contains(day));
+        counts.retain(|day, _| (first..=last).contains(day));
+        merge(&mut days, totals, counts);
+    }
+
+    let mut intervals = Vec::new();
+    let mut counts = BTreeMap::new();
+    for (day, totals) in days {
+        for row in totals.rows {
+            let start = at(day, row.hour);
+            intervals.push(Interval {
+                title: row.title,
+                app: row.app,
+                category: row.category,
+                window_id: String::new(),
+                class: row.class,
+                attributes: BTreeMap::new(),
+                event: EventKind::FocusChanged,
+                start,
+                end: start + Duration::milliseconds((row.secs * 1000.0) as i64),
+                utc_offset: None,
+                call: row.call,
+            });
+        }
+        counts.insert(day, (totals.switches, totals.title_changes));
+    }
+    Ok(Days { intervals, counts })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use chrono::{FixedOffset, NaiveDateTime};
+
+    use super::*;
+    use crate::report::testing::Berlin;
+    use crate::storage::{self, testing};
+
+    fn day(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn interval_in<Tz: TimeZone>(zone: &Tz, from: NaiveDateTime, to: NaiveDateTime) -> Interval {
+        let (start, end) = (wall_clock(zone, from), wall_clock(zone, to));
+        let mut interval = Interval::new("", "", SystemTime::from(start.with_timezone(&Utc)), SystemTime::from(end.with_timezone(&Utc)));
+        interval.utc_offset = Some(start.offset().fix().local_minus_utc());
+        interval
+    }
+
+    fn secs(chunks: &[(NaiveDate, u32, f64)]) -> f64 {
+        chunks.iter().map(|(_, _, secs)| secs).sum()
+    }
+
+    #[test]
+    fn the_hour_the_clocks_skip_has_nothing() {
+        let interval = interval_in(&Berlin, day(3, 31).and_hms_opt(0, 30, 0).unwrap(), day(3, 31).and_hms_opt(3, 30, 0).unwrap());
+        let chunks = chunks_in(&Berlin, &interval);
+        assert_eq!(chunks, [(day(3, 31), 0, 1800.0), (day(3, 31), 1, 3600.0), (day(3, 31), 3, 1800.0)]);
+        assert_eq!(secs(&chunks), interval.duration_secs());
+    }
+
+    #[test]
+    fn the_hour_the_clocks_repeat_has_both() {
+        let interval = interval_in(&Berlin, day(10, 26).and_hms_opt(23, 30, 0).unwrap(), day(10, 27).and_hms_opt(3, 30, 0).unwrap());
+        let chunks = chunks_in(&Berlin, &interval);
+        assert_eq!(chunks, [
+            (day(10, 26), 23, 1800.0),
+            (day(10, 27), 0, 3600.0),
+            (day(10, 27), 1, 3600.0),
+            (day(10, 27), 2, 7200.0),
+            (day(10, 27), 3, 1800.0),
+        ]);
+        assert_eq!(secs(&chunks), interval.duration_secs());
+    }
+
+    #[test]
+    fn hours_are_on_the_clock_the_interval_was_recorded_on() {
+        let kathmandu = FixedOffset::east_opt(5 * 3600 + 45 * 60).unwrap();
+        let interval = interval_in(&kathmandu, day(6, 1).and_hms_opt(23, 15, 0).unwrap(), day(6, 2).and_hms_opt(0, 45, 0).unwrap());
+        let chunks = hourly_chunks(&interval);
+        assert_eq!(chunks, [(day(6, 1), 23, 2700.0), (day(6, 2), 0, 2700.0)]);
+        assert_eq!(secs(&chunks), interval.duration_secs());
+    }
+
+    // An interval of the given minutes starting at a wall-clock time here
+    fn local(title: &str, day: NaiveDate, hour: u32, mins: i64) -> Interval {
+        let start = at(day, hour);
+        Interval::new(title, "app", SystemTime::from(start), SystemTime::from(start + Duration::minutes(mins)))
+    }
+
+    type Sums = BTreeMap<(NaiveDate, String), i64>;
+
+    // Seconds per day and title, and switches per day, of the days loaded
+    fn loaded(first: NaiveDate, last: NaiveDate) -> (Sums, BTreeMap<NaiveDate, usize>) {
+        let days = load(first, last).unwrap();
+        let mut sums = Sums::new();
+        for interval in days.intervals {
+            *sums.entry((interval.start.with_timezone(&Local).date_naive(), interval.title.clone())).or_default() += (interval.duration_secs() * 1000.0).round() as i64;
+        }
+        (sums, days.counts.into_iter().filter(|(_, (switches, _))| *switches > 0).map(|(day, (switches, _))| (day, switches)).collect())
+    }
+
+    // The same from every stored and queued interval
+    fn scanned(first: NaiveDate, last: NaiveDate) -> (Sums, BTreeMap<NaiveDate, usize>) {
+        let (mut sums, mut counts) = (Sums::new(), BTreeMap::new());
+        for interval in storage::load_intervals(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC).unwrap() {
+            let chunks = hourly_chunks(&interval);
+            for (day, _, secs) in chunks.iter().filter(|(day, _, _)| (first..=last).contains(day)) {
+                *sums.entry((*day, interval.title.clone())).or_default() += (secs * 1000.0).round() as i64;
+            }
+            if let Some((day, _, _)) = chunks.first().filter(|(day, _, _)| (first..=last).contains(day)) {
+                *counts.entry(*day).or_default() += 1;
+            }
+        }
+        (sums, counts)
+    }
+
+    #[test]
+    fn the_totals_are_those_of_a_full_scan() {
+        let (_turn, _base) = testing::data_dir();
+        let (first, last) = (day(6, 10), day(6, 12));
+        storage::append_intervals(&[local("Docs", day(6, 10), 9, 90), local("Mail", day(6, 11), 23, 120)]).unwrap();
+        assert_eq!(loaded(first, last), scanned(first, last));
+        assert!(!loaded(first, last).0.is_empty());
+
+        // Appended after the file was built, only the tail is added
+        storage::append_intervals(&[local("Docs", day(6, 12), 14, 30), local("Before", day(6, 9), 22, 180)]).unwrap();
+        assert_eq!(loaded(first, last), scanned(first, last));
+
+        // Rewritten, it is built again from what is left
+        storage::rewrite_intervals(|interval| (interval.title != "Mail").then(|| interval.clone())).unwrap();
+        assert!(!path().exists());
+        assert_eq!(loaded(first, last), scanned(first, last));
+
+        // Queued ones count once, only on the days asked for, and still once when flushed
+        for interval in [local("Queued", day(6, 12), 23, 120), local("Queued", day(6, 11), 10, 15), local("Queued", day(6, 20), 10, 15)] {
+            pending::queue(&interval).unwrap();
+        }
+        let with_queued = loaded(first, last);
+        assert_eq!(with_queued, scanned(first, last));
+        assert_eq!(with_queued.0[&(day(6, 12), "Queued".to_string())], 3_600_000);
+        assert!(!with_queued.0.keys().any(|(day, _)| !(first..=last).contains(day)));
+        assert_eq!(loaded(first, last), with_queued);
+        pending::flush().unwrap();
+        assert_eq!(loaded(first, last), with_queued);
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use super::{data_dir, decode, encode, Interval, Rewritten};
+use crate::config::SyncPolicy;
+
+const HISTORY_FILE: &str = "intervals.jsonl";
+// Tells apart positions of this backend from the other's
+#[cfg_attr(feature = "sqlite", allow(dead_code))]
+pub const NAME: &str = "jsonl";
+
+pub fn history_path() -> PathBuf {
+    data_dir().join(HISTORY_FILE)
+}
+
+#[cfg_attr(feature = "sqlite", allow(dead_code))]
+pub fn append_intervals(intervals: &[Interval], sync: SyncPolicy) -> io::Result<()> {
+    append_to(&history_path(), intervals, sync)
+}
+
+// One write for all of them, so a batch can't interleave with another writer's lines
+pub fn append_to(path: &Path, intervals: &[Interval], sync: SyncPolicy) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut lines = String::new();
+    for interval in intervals {
+        lines.push_str(&encode(interval)?);
+        lines.push('\n');
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(lines.as_bytes())?;
+    if sync == SyncPolicy::Safe {
+        file.sync_data()?;
+    }
+    Ok(())
+}
+
+pub fn load_intervals(from: DateTime<Utc>, to: DateTime<Utc>) -> io::Result<Vec<Interval>> {
+    load_file(&history_path(), from, to)
+}
+
+pub fn load_file(path: &Path, from: DateTime<Utc>, to: DateTime<Utc>) -> io::Result<Vec<Interval>> {
+    let mut intervals = Vec::new();
+    scan_file(path, from, to, |interval| {
+        intervals.push(interval);
+        Ok(())
+    })?;
+    Ok(intervals)
+}
+
+#[cfg_attr(feature = "sqlite", allow(dead_code))]
+pub fn scan_intervals(from: DateTime<Utc>, to: DateTime<Utc>, each: impl FnMut(Interval) -> io::Result<()>) -> io::Result<()> {
+    scan_file(&history_path(), from, to, each)
+}
+
+// Hands over the intervals overlapping [from, to) one at a time as they are read, in file order
+pub fn scan_file(path: &Path, from: DateTime<Utc>, to: DateTime<Utc>, mut each: impl FnMut(Interval) -> io::Result<()>) -> io::Result<()> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let interval = match decode(&line) {
+            Ok(interval) => interval,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                eprintln!("Skipping unreadable history entry: {}", e);
+                continue;
+            }
+            Err(e) => return Err(newer(path, e)),
+        };
+
+        if interval.end > from && interval.start < to {
+            each(interval)?;
+        }
+    }
+    Ok(())
+}
+
+fn newer(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
+
+// Hands over the intervals appended since `position`, the byte offset an earlier call returned (0
+// for all of them), and returns the one to pass next time; None when the file got shorter, so it
+// was replaced
+#[cfg_attr(feature = "sqlite", allow(dead_code))]
+pub fn scan_appended(position: u64, mut each: impl FnMut(Interval) -> io::Result<()>) -> io::Result<Option<u64>> {
+    let mut file = match File::open(history_path()) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((position == 0).then_some(0)),
+        Err(e) => return Err(e),
+    };
+    if file.metadata()?.len() < position {
+        return Ok(None);
+    }
+    file.seek(SeekFrom::Start(position))?;
+
+    let mut reader = BufReader::new(file);
+    let mut position = position;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        // A line still being written is left for the next call
+        if read == 0 || line.last() != Some(&b'\n') {
+            break;
+        }
+        position += read as u64;
+        let text = String::from_utf8_lossy(&line);
+        if text.trim().is_empty() {
+            continue;
+        }
+        match decode(&text) {
+            Ok(interval) => each(interval)?,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => eprintln!("Skipping unreadable history entry: {}", e),
+            Err(e) => return Err(newer(&history_path(), e)),
+        }
+    }
+    Ok(Some(position))
+}
+
+pub fn rewrite_intervals(keep: impl Fn(&Interval) -> Option<Interval>) -> io::Result<Rewritten> {
+    rewrite_file(&history_path(), keep)
+}
+
+pub fn rewrite_file(path: &Path, keep: impl Fn(&Interval) -> Option<Interval>) -> io::Result<Rewritten> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Rewritten::default()),
+        Err(e) => return Err(e),
+    };
+
+    let mut rewritten = Rewritten::default();
+    let mut kept = String::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let interval = match decode(line) {
+            Ok(interval) => interval,
+            // Unreadable entries are left as they are, they may still be recovered by hand
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                kept.push_str(line);
+                kept.push('\n');
+                continue;
+            }
+            // Newer ones may hold what `keep` should change but can't see
+            Err(e) => return Err(newer(path, e)),
+        };
+        let after = keep(&interval);
+        rewritten.count(&interval, after.as_ref());
+        if let Some(after) = after {
+            kept.push_str(&encode(&after)?);
+            kept.push('\n');
+        }
+    }
+
+    let temporary = path.with_extension("jsonl.tmp");
+    fs::write(&temporary, kept)?;
+    fs::rename(temporary, path)?;
+    Ok(rewritten)
+}
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS intervals (
+    id INTEGER PRIMARY KEY,
+    title TEXT NOT NULL,
+    app TEXT NOT NULL DEFAULT '',
+    category TEXT NOT NULL DEFAULT '',
+    started_at TEXT NOT NULL,
+    ended_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS intervals_started_at ON intervals (started_at);
@@ -0,0 +1 @@
+ALTER TABLE intervals ADD COLUMN window_id TEXT NOT NULL DEFAULT '';
@@ -0,0 +1 @@
+ALTER TABLE intervals ADD COLUMN event TEXT NOT NULL DEFAULT 'focus_changed';
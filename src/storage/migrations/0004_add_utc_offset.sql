@@ -0,0 +1 @@
+ALTER TABLE intervals ADD COLUMN utc_offset INTEGER;
@@ -0,0 +1 @@
+ALTER TABLE intervals ADD COLUMN call INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE intervals ADD COLUMN class TEXT NOT NULL DEFAULT '';
@@ -0,0 +1 @@
+ALTER TABLE intervals ADD COLUMN attributes TEXT NOT NULL DEFAULT '{}';
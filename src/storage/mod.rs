@@ -0,0 +1,438 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Offset, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::categories::UNCATEGORIZED;
+use crate::config::{self, StorageConfig};
+use crate::filter::Filter;
+use crate::profile;
+
+pub use aggregates::Days;
+
+mod aggregates;
+mod jsonl;
+mod pending;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(not(feature = "sqlite"))]
+use jsonl as backend;
+#[cfg(feature = "sqlite")]
+use sqlite as backend;
+
+// How many intervals scan_matching hands over at a time
+const SCAN_CHUNK: usize = 10_000;
+// Holds the number of rewrites of the history, see Mark
+const GENERATION_FILE: &str = "generation";
+
+// What opened an interval: moving focus to another window, the focused window changing its title
+// (a new browser tab, another document), or a call starting or ending in the same window. Only the
+// first counts as a window switch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    #[default]
+    FocusChanged,
+    TitleChanged,
+    CallChanged,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::FocusChanged => "focus_changed",
+            EventKind::TitleChanged => "title_changed",
+            EventKind::CallChanged => "call_changed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "title_changed" => EventKind::TitleChanged,
+            "call_changed" => EventKind::CallChanged,
+            _ => EventKind::FocusChanged,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interval {
+    pub title: String,
+    #[serde(default)]
+    pub app: String,
+    #[serde(default)]
+    pub category: String,
+    // Identity of the native window, see ActiveWindow::key
+    #[serde(default)]
+    pub window_id: String,
+    // The native window class, see ActiveWindow::class
+    #[serde(default)]
+    pub class: String,
+    // Fields the title templates extracted, like page or ticket
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub event: EventKind,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    // Seconds east of UTC on the local clock when the interval started, missing in older history
+    #[serde(default)]
+    pub utc_offset: Option<i32>,
+    // The camera or microphone was in use, see calls
+    #[serde(default)]
+    pub call: bool,
+}
+
+impl Interval {
+    pub fn new(title: &str, app: &str, start: SystemTime, end: SystemTime) -> Self {
+        let start: DateTime<Utc> = start.into();
+        Interval {
+            title: title.to_string(),
+            app: app.to_string(),
+            category: String::new(),
+            window_id: String::new(),
+            class: String::new(),
+            attributes: BTreeMap::new(),
+            event: EventKind::FocusChanged,
+            start,
+            end: end.into(),
+            utc_offset: Some(Local.offset_from_utc_datetime(&start.naive_utc()).fix().local_minus_utc()),
+            call: false,
+        }
+    }
+
+    pub fn app_name(&self) -> &str {
+        if self.app.is_empty() { "unknown" } else { &self.app }
+    }
+
+    // Intervals from before classes were recorded, and platforms without them, fall back to the app
+    pub fn class_name(&self) -> &str {
+        if self.class.is_empty() { self.app_name() } else { &self.class }
+    }
+
+    pub fn category_name(&self) -> &str {
+        if self.category.is_empty() { UNCATEGORIZED } else { &self.category }
+    }
+
+    pub fn recorded_offset(&self) -> Option<FixedOffset> {
+        self.utc_offset.and_then(FixedOffset::east_opt)
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        (self.end - self.start).num_milliseconds().max(0) as f64 / 1000.0
+    }
+}
+
+// The version stamped as "v" on intervals written as JSON: the JSONL history, the journal, pending
+// writes and exports. Lines without one are version 1, from before it was stamped. Older versions
+// are read through UPGRADES, newer ones, written by a later build on another machine, are refused
+// rather than read wrong.
+pub const SCHEMA_VERSION: u32 = 2;
+// The versions whose lines need more than serde's defaults to read as the next one, and what does
+// it; version 2 only added the stamp
+const UPGRADES: &[(u32, Upgrade)] = &[];
+
+type Upgrade = fn(&mut serde_json::Value);
+
+#[derive(Serialize)]
+struct Stamped<'a> {
+    v: u32,
+    #[serde(flatten)]
+    interval: &'a Interval,
+}
+
+#[derive(Deserialize)]
+struct Stamp {
+    #[serde(default = "unstamped")]
+    v: u32,
+}
+
+fn unstamped() -> u32 {
+    1
+}
+
+pub fn encode(interval: &Interval) -> serde_json::Result<String> {
+    serde_json::to_string(&Stamped { v: SCHEMA_VERSION, interval })
+}
+
+// An error of kind InvalidData for what isn't an interval, Unsupported for one of a newer version
+pub fn decode(text: &str) -> io::Result<Interval> {
+    // A line cut short is as unreadable as any other, not the end of the history
+    let invalid = |e: serde_json::Error| io::Error::new(io::ErrorKind::InvalidData, e);
+    let version = serde_json::from_str::<Stamp>(text).map_err(invalid)?.v;
+    if version > SCHEMA_VERSION {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, format!(
+            "format version {} is newer than this build supports ({}), upgrade window_tracker",
+            version, SCHEMA_VERSION
+        )));
+    }
+    if !UPGRADES.iter().any(|(from, _)| *from >= version) {
+        return serde_json::from_str(text).map_err(invalid);
+    }
+    let mut value: serde_json::Value = serde_json::from_str(text).map_err(invalid)?;
+    for (_, upgrade) in UPGRADES.iter().filter(|(from, _)| *from >= version) {
+        upgrade(&mut value);
+    }
+    serde_json::from_value(value).map_err(invalid)
+}
+
+// WT_DATA_DIR, then data_dir in config.toml, override the per-platform default location.
+// The active profile's data
+pub fn data_dir() -> PathBuf {
+    profile::scoped_dir(base_data_dir())
+}
+
+// Shared by all profiles, where the running tracker is controlled from
+pub fn base_data_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("WT_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    // Read once, moving the history while tracking would split it
+    static CONFIGURED: OnceLock<Option<PathBuf>> = OnceLock::new();
+    if let Some(dir) = CONFIGURED.get_or_init(|| config::load_base().ok().and_then(|config| config.data_dir)) {
+        return dir.clone();
+    }
+
+    default_data_dir()
+}
+
+// Where the history is kept unless configured otherwise
+pub fn default_data_dir() -> PathBuf {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+
+    base.unwrap_or_else(|| PathBuf::from(".")).join("window_tracker")
+}
+
+// Stores the interval right away, after everything still pending
+pub fn append_interval(interval: &Interval) -> io::Result<()> {
+    append_intervals(std::slice::from_ref(interval))
+}
+
+pub fn append_intervals(intervals: &[Interval]) -> io::Result<()> {
+    pending::flush()?;
+    backend::append_intervals(intervals, pending::sync())
+}
+
+// Stores the interval with the next batch, see [storage] in the config
+pub fn queue_interval(interval: &Interval) -> io::Result<()> {
+    pending::queue(interval)
+}
+
+// Moves the pending intervals into the history when the oldest has waited flush_secs
+pub fn flush_if_due() -> io::Result<()> {
+    pending::flush_if_due()
+}
+
+pub fn flush() -> io::Result<()> {
+    pending::flush()
+}
+
+// Why intervals can't be stored right now, None while writes work
+pub fn last_error() -> Option<String> {
+    pending::last_error()
+}
+
+pub fn configure_writes(config: &StorageConfig) {
+    pending::configure(config);
+}
+
+// Returns every recorded interval overlapping [from, to), clipped to that range.
+pub fn load_intervals(from: DateTime<Utc>, to: DateTime<Utc>) -> io::Result<Vec<Interval>> {
+    let mut intervals = backend::load_intervals(from, to)?;
+    intervals.extend(pending::load(from, to)?);
+    for interval in &mut intervals {
+        interval.start = interval.start.max(from);
+        interval.end = interval.end.min(to);
+    }
+    Ok(intervals)
+}
+
+// load_intervals with only what the --filter expression picks, durations as clipped to the range
+pub fn load_matching(from: DateTime<Utc>, to: DateTime<Utc>, filter: &Filter) -> io::Result<Vec<Interval>> {
+    let mut intervals = load_intervals(from, to)?;
+    if !filter.is_empty() {
+        intervals.retain(|interval| filter.matches(interval));
+    }
+    Ok(intervals)
+}
+
+// load_matching a chunk of at most SCAN_CHUNK at a time, for exports of histories too big to hold
+// at once: the stored intervals in the backend's order (by start with SQLite, as written with JSON
+// lines), then those still queued
+pub fn scan_matching(from: DateTime<Utc>, to: DateTime<Utc>, filter: &Filter, mut each_chunk: impl FnMut(&[Interval]) -> io::Result<()>) -> io::Result<()> {
+    let mut chunk = Vec::with_capacity(SCAN_CHUNK);
+    let mut take = |mut interval: Interval| {
+        interval.start = interval.start.max(from);
+        interval.end = interval.end.min(to);
+        if filter.matches(&interval) {
+            chunk.push(interval);
+        }
+        if chunk.len() == SCAN_CHUNK {
+            each_chunk(&chunk)?;
+            chunk.clear();
+        }
+        Ok(())
+    };
+    backend::scan_intervals(from, to, &mut take)?;
+    pending::load(from, to)?.into_iter().try_for_each(&mut take)?;
+    if !chunk.is_empty() {
+        each_chunk(&chunk)?;
+    }
+    Ok(())
+}
+
+// first..=last as daily totals, from aggregates.json where it has them, for reports over long periods
+pub fn load_days(first: NaiveDate, last: NaiveDate) -> io::Result<Days> {
+    aggregates::load(first, last)
+}
+
+// Where an incremental export got to: how far into the history, as a byte offset or rowid of the
+// backend's, and which version of it, so one made before a rewrite isn't taken for a position in
+// the new one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mark {
+    backend: String,
+    generation: u64,
+    position: u64,
+}
+
+// Counts the rewrites of the history
+pub fn generation() -> u64 {
+    fs::read_to_string(data_dir().join(GENERATION_FILE)).ok().and_then(|text| text.trim().parse().ok()).unwrap_or(0)
+}
+
+// Drops what was derived from the history as it was; rewrite_intervals does this, whatever
+// changes the history behind the storage layer's back, like restore, has to as well
+pub fn history_replaced() -> io::Result<()> {
+    aggregates::clear()?;
+    let path = data_dir().join(GENERATION_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, (generation() + 1).to_string())
+}
+
+// scan_matching over only what was stored after `mark`, all of it without one, in the order it was
+// stored; intervals still queued come once they are stored. Returns the mark to pass next time, or
+// None, having handed over nothing, when the history was rewritten since the mark.
+pub fn scan_since(
+    mark: Option<&Mark>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    filter: &Filter,
+    mut each_chunk: impl FnMut(&[Interval]) -> io::Result<()>,
+) -> io::Result<Option<Mark>> {
+    let generation = generation();
+    let position = match mark {
+        Some(mark) if mark.backend != backend::NAME || mark.generation != generation => return Ok(None),
+        Some(mark) => mark.position,
+        None => 0,
+    };
+
+    let mut chunk = Vec::with_capacity(SCAN_CHUNK);
+    let take = |mut interval: Interval| {
+        if interval.end <= from || interval.start >= to {
+            return Ok(());
+        }
+        interval.start = interval.start.max(from);
+        interval.end = interval.end.min(to);
+        if filter.matches(&interval) {
+            chunk.push(interval);
+        }
+        if chunk.len() == SCAN_CHUNK {
+            each_chunk(&chunk)?;
+            chunk.clear();
+        }
+        Ok(())
+    };
+    let Some(position) = backend::scan_appended(position, take)? else { return Ok(None) };
+    if !chunk.is_empty() {
+        each_chunk(&chunk)?;
+    }
+    Ok(Some(Mark { backend: backend::NAME.to_string(), generation, position }))
+}
+
+// What rewrite_intervals did to the stored history
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rewritten {
+    pub removed: usize,
+    pub changed: usize,
+}
+
+impl Rewritten {
+    pub fn count(&mut self, before: &Interval, after: Option<&Interval>) {
+        match after {
+            None => self.removed += 1,
+            Some(after) if after != before => self.changed += 1,
+            Some(_) => {}
+        }
+    }
+}
+
+// Passes every stored interval through `keep`, which returns it as it should be stored or None to
+// delete it. Nothing deleted stays behind on disk, not even in free database pages.
+pub fn rewrite_intervals(keep: impl Fn(&Interval) -> Option<Interval>) -> io::Result<Rewritten> {
+    // The daily totals are rebuilt from what it leaves and export marks start over; before, so a
+    // crash halfway can't leave them behind the history, and after, for a reader of the old one
+    history_replaced()?;
+    // History from before the SQLite backend was enabled is still in the JSON lines file
+    #[cfg(feature = "sqlite")]
+    jsonl::rewrite_intervals(&keep)?;
+    let pending = pending::rewrite(&keep)?;
+    let mut rewritten = backend::rewrite_intervals(&keep)?;
+    rewritten.removed += pending.removed;
+    rewritten.changed += pending.changed;
+    history_replaced()?;
+    Ok(rewritten)
+}
+
+// The same for another file of intervals as JSON lines
+pub fn rewrite_interval_file(path: &Path, keep: impl Fn(&Interval) -> Option<Interval>) -> io::Result<Rewritten> {
+    jsonl::rewrite_file(path, keep)
+}
+
+// Intervals as JSON lines in a file of their own, as the hub keeps each device's
+pub fn append_interval_file(path: &Path, intervals: &[Interval]) -> io::Result<()> {
+    jsonl::append_to(path, intervals, pending::sync())
+}
+
+// The intervals of such a file overlapping [from, to), unclipped
+pub fn load_interval_file(path: &Path, from: DateTime<Utc>, to: DateTime<Utc>) -> io::Result<Vec<Interval>> {
+    jsonl::load_file(path, from, to)
+}
+
+// Tests that go through the storage layer share WT_DATA_DIR and WT_CONFIG, so they take turns,
+// each with directories of its own
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Mutex, MutexGuard};
+
+    static TURN: Mutex<()> = Mutex::new(());
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    // The data directory for this test, empty; the config next to it doesn't exist yet
+    pub fn data_dir() -> (MutexGuard<'static, ()>, PathBuf) {
+        let turn = TURN.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let base = std::env::temp_dir().join(format!("wt-test-{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)));
+        let _ = std::fs::remove_dir_all(&base);
+        std::env::set_var("WT_DATA_DIR", base.join("data"));
+        std::env::set_var("WT_CONFIG", base.join("config").join("config.toml"));
+        (turn, base)
+    }
+}
@@ -0,0 +1,107 @@
+// Finished intervals are appended to pending.jsonl without an fsync and moved into the history in
+// batches, so a focus change costs one small write instead of a synced insert. Reports read the
+// file as well, and whatever a crash leaves in it goes over with the next flush.
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::slice;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use super::{backend, data_dir, jsonl, Interval};
+use crate::config::{StorageConfig, SyncPolicy};
+
+const PENDING_FILE: &str = "pending.jsonl";
+
+#[derive(Default)]
+struct Pending {
+    config: StorageConfig,
+    // Queued by this process since the last flush
+    count: usize,
+    // When the first of them was, or the last failed flush
+    since: Option<Instant>,
+    // Why the last write to pending.jsonl or the last flush failed, until the next one works
+    write_error: Option<String>,
+    flush_error: Option<String>,
+}
+
+lazy_static::lazy_static! {
+    static ref PENDING: Mutex<Pending> = Mutex::new(Pending::default());
+}
+
+fn path() -> PathBuf {
+    data_dir().join(PENDING_FILE)
+}
+
+pub fn configure(config: &StorageConfig) {
+    PENDING.lock().unwrap().config = config.clone();
+}
+
+pub fn sync() -> SyncPolicy {
+    PENDING.lock().unwrap().config.sync
+}
+
+pub fn queue(interval: &Interval) -> io::Result<()> {
+    let mut pending = PENDING.lock().unwrap();
+    let written = jsonl::append_to(&path(), slice::from_ref(interval), SyncPolicy::Fast);
+    pending.write_error = written.as_ref().err().map(|e| e.to_string());
+    written?;
+    pending.count += 1;
+    pending.since.get_or_insert_with(Instant::now);
+    if pending.count >= pending.config.flush_intervals {
+        flush_locked(&mut pending)?;
+    }
+    Ok(())
+}
+
+pub fn flush_if_due() -> io::Result<()> {
+    let mut pending = PENDING.lock().unwrap();
+    let flush_after = Duration::from_secs(pending.config.flush_secs);
+    if pending.since.is_some_and(|since| since.elapsed() >= flush_after) {
+        flush_locked(&mut pending)?;
+    }
+    Ok(())
+}
+
+pub fn flush() -> io::Result<()> {
+    flush_locked(&mut PENDING.lock().unwrap())
+}
+
+fn flush_locked(pending: &mut Pending) -> io::Result<()> {
+    let path = path();
+    let intervals = jsonl::load_file(&path, DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC)?;
+    if !intervals.is_empty() {
+        if let Err(e) = backend::append_intervals(&intervals, pending.config.sync) {
+            // Retried a full flush_secs later rather than on every sample
+            pending.since = Some(Instant::now());
+            pending.flush_error = Some(e.to_string());
+            return Err(e);
+        }
+    }
+    // A crash right here stores the batch twice, as with the journal
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    pending.count = 0;
+    pending.since = None;
+    pending.flush_error = None;
+    Ok(())
+}
+
+pub fn last_error() -> Option<String> {
+    let pending = PENDING.lock().unwrap();
+    pending.write_error.clone().or_else(|| pending.flush_error.clone())
+}
+
+pub fn load(from: DateTime<Utc>, to: DateTime<Utc>) -> io::Result<Vec<Interval>> {
+    let _pending = PENDING.lock().unwrap();
+    jsonl::load_file(&path(), from, to)
+}
+
+pub fn rewrite(keep: impl Fn(&Interval) -> Option<Interval>) -> io::Result<super::Rewritten> {
+    let _pending = PENDING.lock().unwrap();
+    jsonl::rewrite_file(&path(), keep)
+}
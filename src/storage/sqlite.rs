@@ -0,0 +1,274 @@
+use std::io;
+use std::path::PathBuf;
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use rusqlite::{params, Connection, OpenFlags};
+
+use super::{data_dir, jsonl, EventKind, Interval, Rewritten};
+use crate::config::SyncPolicy;
+
+const DATABASE_FILE: &str = "intervals.db";
+// Tells apart positions of this backend from the other's
+pub const NAME: &str = "sqlite";
+
+// Applied in order, each exactly once; the number applied is tracked in PRAGMA user_version.
+// Never edit a released migration, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    include_str!("migrations/0001_create_intervals.sql"),
+    include_str!("migrations/0002_add_window_id.sql"),
+    include_str!("migrations/0003_add_event.sql"),
+    include_str!("migrations/0004_add_utc_offset.sql"),
+    include_str!("migrations/0005_add_call.sql"),
+    include_str!("migrations/0006_add_class.sql"),
+    include_str!("migrations/0007_add_attributes.sql"),
+];
+
+pub fn database_path() -> PathBuf {
+    data_dir().join(DATABASE_FILE)
+}
+
+fn to_io(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+// Timestamps are stored as fixed-width UTC RFC 3339 text, so they sort and compare as strings
+// and SQLite's date functions understand them.
+fn timestamp(time: DateTime<Utc>) -> String {
+    // Past four-digit years RFC 3339 gains a sign and stops sorting as text, e.g. for MAX_UTC
+    let earliest = Utc.with_ymd_and_hms(0, 1, 1, 0, 0, 0).unwrap();
+    let latest = Utc.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap();
+    time.clamp(earliest, latest).to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+pub fn open() -> io::Result<Connection> {
+    let path = database_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let is_new = !path.exists();
+    let mut connection = Connection::open(&path).map_err(to_io)?;
+    migrate(&mut connection)?;
+    if is_new {
+        import_jsonl_history(&mut connection)?;
+    }
+    Ok(connection)
+}
+
+fn migrate(connection: &mut Connection) -> io::Result<()> {
+    let version: usize = connection.pragma_query_value(None, "user_version", |row| row.get(0)).map_err(to_io)?;
+    if version > MIGRATIONS.len() {
+        return Err(io::Error::other(format!(
+            "database schema version {} is newer than this build supports ({}), upgrade window_tracker",
+            version,
+            MIGRATIONS.len()
+        )));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction().map_err(to_io)?;
+        transaction.execute_batch(migration).map_err(to_io)?;
+        transaction.pragma_update(None, "user_version", index + 1).map_err(to_io)?;
+        transaction.commit().map_err(to_io)?;
+    }
+    Ok(())
+}
+
+// Opens the database so that nothing run through it can modify the history.
+pub fn open_read_only() -> io::Result<Connection> {
+    // Creates or upgrades the schema first, which needs write access
+    drop(open()?);
+
+    let connection = Connection::open_with_flags(database_path(), OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(to_io)?;
+    connection.pragma_update(None, "query_only", true).map_err(to_io)?;
+    Ok(connection)
+}
+
+// Carries over history recorded before the SQLite backend was enabled
+fn import_jsonl_history(connection: &mut Connection) -> io::Result<()> {
+    let intervals = jsonl::load_intervals(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC)?;
+    if intervals.is_empty() {
+        return Ok(());
+    }
+
+    let transaction = connection.transaction().map_err(to_io)?;
+    for interval in &intervals {
+        insert(&transaction, interval)?;
+    }
+    transaction.commit().map_err(to_io)?;
+    eprintln!("Imported {} intervals from {}", intervals.len(), jsonl::history_path().display());
+    Ok(())
+}
+
+fn insert(connection: &Connection, interval: &Interval) -> io::Result<()> {
+    connection.execute(
+        "INSERT INTO intervals (title, app, category, window_id, event, started_at, ended_at, utc_offset, call, class, attributes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            interval.title,
+            interval.app,
+            interval.category,
+            interval.window_id,
+            interval.event.as_str(),
+            timestamp(interval.start),
+            timestamp(interval.end),
+            interval.utc_offset,
+            interval.call,
+            interval.class,
+            attributes_json(interval)
+        ],
+    ).map_err(to_io)?;
+    Ok(())
+}
+
+fn attributes_json(interval: &Interval) -> String {
+    serde_json::to_string(&interval.attributes).unwrap()
+}
+
+pub fn append_intervals(intervals: &[Interval], sync: SyncPolicy) -> io::Result<()> {
+    let mut connection = open()?;
+    let synchronous = match sync {
+        SyncPolicy::Safe => "FULL",
+        SyncPolicy::Fast => "OFF",
+    };
+    connection.pragma_update(None, "synchronous", synchronous).map_err(to_io)?;
+    let transaction = connection.transaction().map_err(to_io)?;
+    for interval in intervals {
+        insert(&transaction, interval)?;
+    }
+    transaction.commit().map_err(to_io)
+}
+
+pub fn load_intervals(from: DateTime<Utc>, to: DateTime<Utc>) -> io::Result<Vec<Interval>> {
+    let rows = select(&open()?, from, to)?;
+    Ok(rows.into_iter().map(|(_, interval)| interval).collect())
+}
+
+// Hands over the intervals overlapping [from, to) one at a time as SQLite steps through them, by start
+pub fn scan_intervals(from: DateTime<Utc>, to: DateTime<Utc>, mut each: impl FnMut(Interval) -> io::Result<()>) -> io::Result<()> {
+    scan_where(&open()?, "ended_at > ?1 AND started_at < ?2", params![timestamp(from), timestamp(to)], |_, interval| each(interval))
+}
+
+// Hands over the intervals inserted after rowid `position` (0 for all of them) and returns the last
+// rowid, to pass next time; None when rows past it are gone, so the history was replaced
+pub fn scan_appended(position: u64, mut each: impl FnMut(Interval) -> io::Result<()>) -> io::Result<Option<u64>> {
+    let connection = open()?;
+    let last: i64 = connection.query_row("SELECT COALESCE(MAX(rowid), 0) FROM intervals", [], |row| row.get(0)).map_err(to_io)?;
+    if (last as u64) < position {
+        return Ok(None);
+    }
+    scan_where(&connection, "rowid > ?1 AND rowid <= ?2", params![position as i64, last], |_, interval| each(interval))?;
+    Ok(Some(last as u64))
+}
+
+// The intervals overlapping [from, to) with their rowids
+fn select(connection: &Connection, from: DateTime<Utc>, to: DateTime<Utc>) -> io::Result<Vec<(i64, Interval)>> {
+    select_where(connection, "ended_at > ?1 AND started_at < ?2", params![timestamp(from), timestamp(to)])
+}
+
+fn select_where(connection: &Connection, condition: &str, parameters: impl rusqlite::Params) -> io::Result<Vec<(i64, Interval)>> {
+    let mut rows = Vec::new();
+    scan_where(connection, condition, parameters, |rowid, interval| {
+        rows.push((rowid, interval));
+        Ok(())
+    })?;
+    Ok(rows)
+}
+
+fn scan_where(
+    connection: &Connection,
+    condition: &str,
+    parameters: impl rusqlite::Params,
+    mut each: impl FnMut(i64, Interval) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT rowid, title, app, category, window_id, event, started_at, ended_at, utc_offset, call, class, attributes FROM intervals
+         WHERE {} ORDER BY started_at",
+        condition,
+    )).map_err(to_io)?;
+
+    let rows = statement.query_map(parameters, |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get::<_, String>(5)?,
+            row.get::<_, String>(6)?,
+            row.get::<_, String>(7)?,
+            row.get(8)?,
+            row.get(9)?,
+            row.get(10)?,
+            row.get::<_, String>(11)?,
+        ))
+    }).map_err(to_io)?;
+
+    for row in rows {
+        let (rowid, title, app, category, window_id, event, start, end, utc_offset, call, class, attributes) = row.map_err(to_io)?;
+        let (Ok(start), Ok(end)) = (DateTime::parse_from_rfc3339(&start), DateTime::parse_from_rfc3339(&end)) else {
+            eprintln!("Skipping interval with unreadable timestamps: {} - {}", start, end);
+            continue;
+        };
+        each(rowid, Interval {
+            title,
+            app,
+            category,
+            window_id,
+            class,
+            // Written by attributes_json, so only a hand-edited database can make this fall back
+            attributes: serde_json::from_str(&attributes).unwrap_or_default(),
+            event: EventKind::parse(&event),
+            start: start.with_timezone(&Utc),
+            end: end.with_timezone(&Utc),
+            utc_offset,
+            call,
+        })?;
+    }
+    Ok(())
+}
+
+pub fn rewrite_intervals(keep: impl Fn(&Interval) -> Option<Interval>) -> io::Result<Rewritten> {
+    let mut connection = open()?;
+    let rows = select(&connection, DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC)?;
+
+    let mut rewritten = Rewritten::default();
+    let transaction = connection.transaction().map_err(to_io)?;
+    for (rowid, interval) in rows {
+        let after = keep(&interval);
+        rewritten.count(&interval, after.as_ref());
+        match after {
+            None => {
+                transaction.execute("DELETE FROM intervals WHERE rowid = ?1", [rowid]).map_err(to_io)?;
+            }
+            Some(after) if after != interval => {
+                transaction.execute(
+                    "UPDATE intervals SET title = ?1, app = ?2, category = ?3, window_id = ?4, event = ?5,
+                     started_at = ?6, ended_at = ?7, utc_offset = ?8, call = ?9, class = ?10, attributes = ?11 WHERE rowid = ?12",
+                    params![
+                        after.title,
+                        after.app,
+                        after.category,
+                        after.window_id,
+                        after.event.as_str(),
+                        timestamp(after.start),
+                        timestamp(after.end),
+                        after.utc_offset,
+                        after.call,
+                        after.class,
+                        attributes_json(&after),
+                        rowid
+                    ],
+                ).map_err(to_io)?;
+            }
+            Some(_) => {}
+        }
+    }
+    transaction.commit().map_err(to_io)?;
+
+    // Deleted rows linger in free pages until the file is rebuilt
+    if rewritten.removed > 0 || rewritten.changed > 0 {
+        connection.execute_batch("VACUUM").map_err(to_io)?;
+    }
+    Ok(rewritten)
+}
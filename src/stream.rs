@@ -0,0 +1,87 @@
+// `track --stream json`: one JSON object per line on stdout, each with an "event" field, so
+// pipelines and supervisors can follow tracking without parsing the status display. WebSocket
+// clients subscribe to the same lines, each one of the events in events serialized.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::events::{FocusEvent, IdleEvent, SessionEvent};
+use crate::storage::Interval;
+
+static STDOUT: AtomicBool = AtomicBool::new(false);
+static SUBSCRIBERS: Mutex<Vec<Sender<String>>> = Mutex::new(Vec::new());
+static LISTENERS: Once = Once::new();
+
+fn emit(event: &impl Serialize) {
+    let stdout = STDOUT.load(Ordering::Relaxed);
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    if !stdout && subscribers.is_empty() {
+        return;
+    }
+    let line = serde_json::to_string(event).unwrap();
+    if stdout {
+        println!("{}", line);
+    }
+    // A receiver is gone once its client disconnected
+    subscribers.retain(|subscriber| subscriber.send(line.clone()).is_ok());
+}
+
+pub fn is_active() -> bool {
+    STDOUT.load(Ordering::Relaxed) || !SUBSCRIBERS.lock().unwrap().is_empty()
+}
+
+pub fn interval_finalized(interval: &Interval) {
+    emit(&SessionEvent::IntervalFinalized { time: Utc::now(), interval: interval.clone(), duration_secs: interval.duration_secs() });
+}
+
+fn listen() {
+    LISTENERS.call_once(|| {
+        crate::wt_add_focus_listener(Box::new(|event, window| emit(&FocusEvent::new(event, window))));
+        crate::wt_add_interval_listener(Box::new(interval_finalized));
+    });
+}
+
+// Events go to stdout from now on
+pub fn install() {
+    listen();
+    STDOUT.store(true, Ordering::Relaxed);
+}
+
+// Every event line from now on, until the receiver is dropped
+pub fn subscribe() -> Receiver<String> {
+    listen();
+    let (sender, receiver) = mpsc::channel();
+    SUBSCRIBERS.lock().unwrap().push(sender);
+    receiver
+}
+
+pub fn profile_switched(profile: &str) {
+    emit(&SessionEvent::ProfileSwitched { time: Utc::now(), profile: profile.to_string() });
+}
+
+pub fn config_reloaded(changes: &[String]) {
+    emit(&SessionEvent::ConfigReloaded { time: Utc::now(), changes: changes.to_vec() });
+}
+
+// Turns the idle time into idle_start and idle_end events around the configured threshold
+#[derive(Default)]
+pub struct IdleWatch {
+    idle: bool,
+}
+
+impl IdleWatch {
+    pub fn check(&mut self, idle_for: Option<Duration>, threshold: Duration) {
+        let Some(idle_for) = idle_for else { return };
+        if !self.idle && idle_for >= threshold {
+            self.idle = true;
+            emit(&IdleEvent::IdleStart { time: Utc::now(), idle_secs: idle_for.as_secs_f64() });
+        } else if self.idle && idle_for < threshold {
+            self.idle = false;
+            emit(&IdleEvent::IdleEnd { time: Utc::now() });
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// Rows printed by the read commands with --format: an aligned table, JSON, CSV, TSV or a Markdown
+// table, so the same report goes into a spreadsheet or a GitHub comment as it is.
+use serde_json::Value;
+
+use crate::cli::Args;
+use crate::export::csv_field;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+    Tsv,
+    Markdown,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "markdown" | "md" => Ok(Format::Markdown),
+            other => Err(format!("unknown format '{}', expected 'table', 'json', 'csv', 'tsv' or 'markdown'", other)),
+        }
+    }
+
+    // --format when given, None leaves the command's own text (or -o json) output
+    pub fn from_args(args: &Args) -> Result<Option<Self>, String> {
+        args.value("--format").map(Format::parse).transpose()
+    }
+}
+
+pub struct Table {
+    columns: Vec<String>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(columns: &[&str]) -> Self {
+        Table { columns: columns.iter().map(|column| column.to_string()).collect(), rows: Vec::new() }
+    }
+
+    pub fn with_columns(columns: Vec<String>) -> Self {
+        Table { columns, rows: Vec::new() }
+    }
+
+    // One value per column, numbers stay numbers in JSON
+    pub fn push(&mut self, row: Vec<Value>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Table => self.aligned(),
+            Format::Json => format!("{}\n", serde_json::to_string_pretty(&self.objects()).unwrap()),
+            Format::Csv => self.separated(",", csv_field),
+            // Tabs and line breaks inside a value would start another column or row
+            Format::Tsv => self.separated("\t", |text| text.replace(['\t', '\n', '\r'], " ")),
+            Format::Markdown => self.markdown(),
+        }
+    }
+
+    // A JSON object per row, keyed by column
+    pub fn objects(&self) -> Vec<Value> {
+        self.rows.iter()
+            .map(|row| Value::Object(self.columns.iter().cloned().zip(row.iter().cloned()).collect()))
+            .collect()
+    }
+
+    pub fn print(&self, format: Format) {
+        print!("{}", self.render(format));
+    }
+
+    fn cells(&self) -> Vec<Vec<String>> {
+        self.rows.iter().map(|row| row.iter().map(plain).collect()).collect()
+    }
+
+    fn separated(&self, separator: &str, field: impl Fn(&str) -> String) -> String {
+        let line = |row: &[String]| row.iter().map(|cell| field(cell)).collect::<Vec<_>>().join(separator);
+        let mut out = format!("{}\n", line(&self.columns));
+        for row in self.cells() {
+            out.push_str(&line(&row));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn aligned(&self) -> String {
+        let cells = self.cells();
+        let widths: Vec<usize> = self.columns.iter().enumerate()
+            .map(|(i, name)| cells.iter().map(|row| row[i].chars().count()).fold(name.chars().count(), usize::max))
+            .collect();
+        let line = |row: &[String]| {
+            let line = row.iter().zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            format!("{}\n", line.trim_end())
+        };
+        let mut out = line(&self.columns);
+        out.push_str(&widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>().join("-+-"));
+        out.push('\n');
+        for row in &cells {
+            out.push_str(&line(row));
+        }
+        out
+    }
+
+    // GitHub-flavoured, with pipes escaped and line breaks as <br>
+    fn markdown(&self) -> String {
+        let field = |text: &str| text.replace('|', "\\|").replace("\r\n", "<br>").replace(['\n', '\r'], "<br>");
+        let line = |row: &[String]| format!("| {} |\n", row.iter().map(|cell| field(cell)).collect::<Vec<_>>().join(" | "));
+        let mut out = line(&self.columns);
+        out.push_str(&format!("|{}|\n", self.columns.iter().map(|_| " --- ").collect::<Vec<_>>().join("|")));
+        for row in self.cells() {
+            out.push_str(&line(&row));
+        }
+        out
+    }
+}
+
+// A value as text, empty for null
+pub fn plain(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,89 @@
+// [[title_templates]]: per-app patterns like "{page} — Mozilla Firefox" that pull fields out of
+// window titles. The fields are stored with each interval for category rules and report --by field:<name>.
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::config::Config;
+
+struct Template {
+    app: Option<String>,
+    pattern: Regex,
+}
+
+#[derive(Default)]
+pub struct TitleParser {
+    templates: Vec<Template>,
+}
+
+impl TitleParser {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let templates = config.title_templates.iter()
+            .map(|template| {
+                let pattern = compile(&template.template)
+                    .map_err(|e| format!("invalid title template '{}': {}", template.template, e))?;
+                Ok(Template { app: template.app.as_ref().map(|app| app.to_lowercase()), pattern })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(TitleParser { templates })
+    }
+
+    // The fields of the first template for this app that matches the whole title
+    pub fn parse(&self, title: &str, app: &str) -> BTreeMap<String, String> {
+        let app = app.to_lowercase();
+        self.templates.iter()
+            .filter(|template| template.app.as_ref().is_none_or(|wanted| *wanted == app))
+            .find_map(|template| {
+                let captures = template.pattern.captures(title)?;
+                Some(template.pattern.capture_names()
+                    .flatten()
+                    .filter_map(|name| captures.name(name).map(|value| (name.to_string(), value.as_str().trim().to_string())))
+                    .collect())
+            })
+            .unwrap_or_default()
+    }
+}
+
+// "{page} — Mozilla Firefox" as ^(?P<page>.+?) — Mozilla Firefox$, with {{ and }} for literal braces
+fn compile(template: &str) -> Result<Regex, String> {
+    let mut pattern = String::from("^");
+    let mut names = Vec::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                pattern.push_str(&regex::escape("{"));
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                pattern.push_str(&regex::escape("}"));
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("'{{{}' is not closed", name)),
+                    }
+                }
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') || name.starts_with(|c: char| c.is_ascii_digit()) {
+                    return Err(format!("'{{{}}}' is not a field, use letters, digits and _", name));
+                }
+                if names.contains(&name) {
+                    return Err(format!("field '{}' appears twice", name));
+                }
+                pattern.push_str(&format!("(?P<{}>.+?)", name));
+                names.push(name);
+            }
+            '}' => return Err("unmatched '}', write }} for a literal brace".to_string()),
+            other => pattern.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    if names.is_empty() {
+        return Err("no {field} to extract".to_string());
+    }
+    pattern.push('$');
+    Regex::new(&pattern).map_err(|e| e.to_string())
+}
@@ -0,0 +1,56 @@
+// title_debounce_ms: a title that keeps changing, like a download percentage or a blinking unread
+// counter, reaches focus listeners at most once per period with the latest title. The history is
+// unaffected, every title is still recorded.
+use std::time::{Duration, Instant};
+
+use crate::storage::EventKind;
+use crate::ActiveWindow;
+
+#[derive(Default)]
+pub struct TitleThrottle {
+    period: Duration,
+    // Window key and title listeners last heard about, and when
+    sent: Option<(String, String, Instant)>,
+    held: Option<ActiveWindow>,
+}
+
+impl TitleThrottle {
+    pub fn set_period(&mut self, period: Duration) {
+        self.period = period;
+    }
+
+    // What to pass on for an event that just happened, in order
+    pub fn event(&mut self, event: EventKind, window: &ActiveWindow, now: Instant) -> Vec<(EventKind, ActiveWindow)> {
+        if self.period.is_zero() {
+            return vec![(event, window.clone())];
+        }
+        let key = window.key();
+        let recent = self.sent.as_ref()
+            .is_some_and(|(sent_key, _, at)| *sent_key == key && now.duration_since(*at) < self.period);
+        if matches!(event, EventKind::TitleChanged) && (recent || self.held.is_some()) {
+            self.held = Some(window.clone());
+            return Vec::new();
+        }
+        // Leaving the window settles its title first
+        let mut events: Vec<(EventKind, ActiveWindow)> = self.take_held(now).into_iter().collect();
+        self.sent = Some((key, window.title.clone(), now));
+        events.push((event, window.clone()));
+        events
+    }
+
+    // A held title change once the period since the last one is over
+    pub fn due(&mut self, now: Instant) -> Option<(EventKind, ActiveWindow)> {
+        let ready = self.sent.as_ref().is_none_or(|(_, _, at)| now.duration_since(*at) >= self.period);
+        if ready { self.take_held(now) } else { None }
+    }
+
+    fn take_held(&mut self, now: Instant) -> Option<(EventKind, ActiveWindow)> {
+        let window = self.held.take()?;
+        // A title that flickered back to what listeners already have is no change
+        if self.sent.as_ref().is_some_and(|(key, title, _)| *key == window.key() && *title == window.title) {
+            return None;
+        }
+        self.sent = Some((window.key(), window.title.clone(), now));
+        Some((EventKind::TitleChanged, window))
+    }
+}
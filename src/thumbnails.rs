@@ -0,0 +1,233 @@
+// [thumbnails]: a small picture of the screen at every switch to another window, so `recall --at
+// 15:34` can show what was on screen then. Off without the section. The PNGs stay in thumbnails/
+// next to the history, named by the time they were taken, and are deleted after retention_days.
+// Windows the ignore rules skip or redact aren't captured, and nothing is with privacy = "apps" or
+// in an ephemeral session.
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+#[cfg(feature = "thumbnails")]
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde_json::json;
+
+use crate::cli::Args;
+use crate::config::{Config, Privacy, ThumbnailConfig};
+use crate::storage;
+
+const NAME_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+#[cfg(feature = "thumbnails")]
+const PRUNE_EVERY: Duration = Duration::from_secs(3600);
+// A thumbnail this long before the time asked about, with no switch since, still shows it
+const MAX_AGE: chrono::Duration = chrono::Duration::hours(12);
+
+#[cfg(feature = "thumbnails")]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    // Row by row, three bytes per pixel
+    pub rgb: Vec<u8>,
+}
+
+static CONFIG: Mutex<Option<ThumbnailConfig>> = Mutex::new(None);
+#[cfg(feature = "thumbnails")]
+static LAST_PRUNE: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub fn check(config: &Config) -> Result<(), String> {
+    let Some(thumbnails) = &config.thumbnails else { return Ok(()) };
+    if config.privacy == Privacy::Apps {
+        return Err("[thumbnails] show window contents, they can't be combined with privacy = \"apps\"".to_string());
+    }
+    if !(16..=1024).contains(&thumbnails.width) {
+        return Err(format!("thumbnails width must be 16 to 1024 pixels, not {}", thumbnails.width));
+    }
+    Ok(())
+}
+
+pub fn configure(config: &Config) {
+    *CONFIG.lock().unwrap() = config.thumbnails.clone();
+}
+
+// The size a screen is shrunk to, keeping its aspect ratio
+#[cfg(feature = "thumbnails")]
+pub fn scaled_size(width: u32, height: u32, max_width: u32) -> (u32, u32) {
+    let scaled_width = width.min(max_width).max(1);
+    let scaled_height = (u64::from(height) * u64::from(scaled_width) / u64::from(width.max(1))).max(1) as u32;
+    (scaled_width, scaled_height)
+}
+
+// Shrinks a width x height screen read through `pixel`, averaging a few samples per thumbnail
+// pixel so text shrinks to grey rather than noise
+#[cfg(feature = "thumbnails")]
+pub fn downscale(width: u32, height: u32, max_width: u32, pixel: impl Fn(u32, u32) -> [u8; 3]) -> Option<Thumbnail> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let (scaled_width, scaled_height) = scaled_size(width, height, max_width);
+    let mut rgb = Vec::with_capacity(scaled_width as usize * scaled_height as usize * 3);
+    for y in 0..scaled_height {
+        for x in 0..scaled_width {
+            let mut sum = [0u32; 3];
+            for (dx, dy) in [(1, 1), (3, 1), (1, 3), (3, 3)] {
+                let source_x = ((u64::from(x) * 4 + dx) * u64::from(width) / (u64::from(scaled_width) * 4)) as u32;
+                let source_y = ((u64::from(y) * 4 + dy) * u64::from(height) / (u64::from(scaled_height) * 4)) as u32;
+                let sample = pixel(source_x.min(width - 1), source_y.min(height - 1));
+                for (total, value) in sum.iter_mut().zip(sample) {
+                    *total += u32::from(value);
+                }
+            }
+            rgb.extend(sum.map(|total| (total / 4) as u8));
+        }
+    }
+    Some(Thumbnail { width: scaled_width, height: scaled_height, rgb })
+}
+
+#[cfg(feature = "thumbnails")]
+fn encode_png(thumbnail: &Thumbnail) -> std::io::Result<Vec<u8>> {
+    use flate2::write::ZlibEncoder;
+    use flate2::{Compression, Crc};
+    use std::io::Write;
+
+    let chunk = |png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]| {
+        png.extend((data.len() as u32).to_be_bytes());
+        png.extend(kind);
+        png.extend(data);
+        let mut crc = Crc::new();
+        crc.update(kind);
+        crc.update(data);
+        png.extend(crc.sum().to_be_bytes());
+    };
+
+    let mut header = Vec::with_capacity(13);
+    header.extend(thumbnail.width.to_be_bytes());
+    header.extend(thumbnail.height.to_be_bytes());
+    // 8 bits per channel, RGB, deflate, adaptive filtering, no interlace
+    header.extend([8, 2, 0, 0, 0]);
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in thumbnail.rgb.chunks(thumbnail.width as usize * 3) {
+        // Each row unfiltered
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    let pixels = encoder.finish()?;
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &pixels);
+    chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+fn directory() -> PathBuf {
+    storage::data_dir().join("thumbnails")
+}
+
+// Every stored thumbnail with when it was taken, oldest first
+fn list() -> Vec<(DateTime<Utc>, PathBuf)> {
+    let Ok(entries) = fs::read_dir(directory()) else { return Vec::new() };
+    let mut thumbnails: Vec<(DateTime<Utc>, PathBuf)> = entries.filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let taken = NaiveDateTime::parse_from_str(name.strip_suffix(".png")?, NAME_FORMAT).ok()?;
+            Some((taken.and_utc(), entry.path()))
+        })
+        .collect();
+    thumbnails.sort();
+    thumbnails
+}
+
+// Deletes the thumbnails `selected` picks by when they were taken, returns their paths
+pub fn delete(force: bool, selected: impl Fn(DateTime<Utc>) -> bool) -> Result<Vec<PathBuf>, String> {
+    let mut deleted = Vec::new();
+    for (taken, path) in list() {
+        if !selected(taken) {
+            continue;
+        }
+        if force {
+            fs::remove_file(&path).map_err(|e| format!("failed to delete {}: {}", path.display(), e))?;
+        }
+        deleted.push(path);
+    }
+    Ok(deleted)
+}
+
+#[cfg(feature = "thumbnails")]
+fn prune(retention_days: u64) {
+    let cutoff = Utc::now() - chrono::Duration::days(retention_days.min(36500) as i64);
+    if let Err(e) = delete(true, |taken| taken < cutoff) {
+        eprintln!("Failed to delete old thumbnails: {}", e);
+    }
+}
+
+// After focus moved to another window, `redacted` when the ignore rules hide its title
+#[cfg(feature = "thumbnails")]
+pub fn focus_changed(redacted: bool, time: std::time::SystemTime) {
+    let Some(config) = CONFIG.lock().unwrap().clone() else { return };
+    if crate::wt_is_ephemeral() || crate::privacy::apps_only() {
+        return;
+    }
+    let mut last_prune = LAST_PRUNE.lock().unwrap();
+    if last_prune.is_none_or(|at| at.elapsed() >= PRUNE_EVERY) {
+        *last_prune = Some(Instant::now());
+        prune(config.retention_days);
+    }
+    drop(last_prune);
+    if redacted {
+        return;
+    }
+
+    let width = config.width;
+    let Some(thumbnail) = crate::sampler::run("The screenshot", move || crate::platform::capture_screen(width)).flatten() else { return };
+    let stored = encode_png(&thumbnail).and_then(|png| {
+        let directory = directory();
+        fs::create_dir_all(&directory)?;
+        let name = format!("{}.png", DateTime::<Utc>::from(time).format(NAME_FORMAT));
+        fs::write(directory.join(name), png)
+    });
+    if let Err(e) = stored {
+        eprintln!("Failed to store a thumbnail: {}", e);
+    }
+}
+
+// hh:mm today, or after a day: yesterday 15:34, 2024-03-01 15:34
+fn parse_when(text: &str) -> Result<DateTime<Utc>, String> {
+    let invalid = || format!("invalid time '{}' for --at, expected hh:mm, yesterday hh:mm or yyyy-mm-dd hh:mm", text);
+    let (day, time) = match text.trim().rsplit_once(' ') {
+        Some((day, time)) => (crate::rules::parse_day(day.trim(), "--at")?, time),
+        None => (Local::now().date_naive(), text.trim()),
+    };
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .map_err(|_| invalid())?;
+    Local.from_local_datetime(&day.and_time(time)).earliest().map(|at| at.with_timezone(&Utc)).ok_or_else(invalid)
+}
+
+pub fn run_recall(args: &Args) -> Result<(), String> {
+    let at = parse_when(args.value("--at").ok_or("usage: recall --at <hh:mm|yesterday hh:mm|yyyy-mm-dd hh:mm>")?)?;
+    let json_output = args.json_output()?;
+    let intervals = storage::load_intervals(at - chrono::Duration::days(1), at + chrono::Duration::days(1))
+        .map_err(|e| format!("failed to read history: {}", e))?;
+    let interval = intervals.iter().find(|interval| interval.start <= at && at < interval.end);
+    let thumbnail = list().into_iter().rev().find(|(taken, _)| *taken <= at).filter(|(taken, _)| at - *taken <= MAX_AGE);
+    let local = |time: &DateTime<Utc>| time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string();
+
+    if json_output {
+        println!("{}", json!({
+            "at": at.to_rfc3339(),
+            "interval": interval,
+            "thumbnail": thumbnail.as_ref().map(|(taken, path)| json!({ "taken": taken.to_rfc3339(), "path": path })),
+        }));
+        return Ok(());
+    }
+    match interval {
+        Some(interval) => println!("At {}: '{}' ({}), {} to {}", local(&at), interval.title, interval.app_name(), local(&interval.start), local(&interval.end)),
+        None => println!("Nothing was tracked at {}", local(&at)),
+    }
+    match thumbnail {
+        Some((taken, path)) => println!("Screen at {}: {}", local(&taken), path.display()),
+        None => println!("No thumbnail from before then, enable [thumbnails] in config.toml to take them"),
+    }
+    Ok(())
+}
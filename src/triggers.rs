@@ -0,0 +1,178 @@
+// Automatic profile switching from [[profile_triggers]] in the top-level config.toml. The
+// surroundings are probed every CHECK_INTERVAL, and only what some trigger asks about.
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::config::{self, ProfileTrigger};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default)]
+pub struct Surroundings {
+    pub ssid: Option<String>,
+    pub vpn: Option<bool>,
+    pub monitors: Option<usize>,
+}
+
+impl Surroundings {
+    pub fn probe(triggers: &[ProfileTrigger]) -> Self {
+        Surroundings {
+            ssid: triggers.iter().any(|trigger| trigger.ssid.is_some()).then(wifi_ssid).flatten(),
+            vpn: triggers.iter().any(|trigger| trigger.vpn.is_some()).then(vpn_connected).flatten(),
+            monitors: triggers.iter().any(|trigger| trigger.monitors.is_some()).then(monitor_count).flatten(),
+        }
+    }
+
+    // Something that couldn't be detected never matches
+    fn matches(&self, trigger: &ProfileTrigger) -> bool {
+        trigger.ssid.as_ref().is_none_or(|ssid| self.ssid.as_ref() == Some(ssid))
+            && trigger.vpn.is_none_or(|vpn| self.vpn == Some(vpn))
+            && trigger.monitors.is_none_or(|monitors| self.monitors == Some(monitors))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ssid) = &self.ssid {
+            parts.push(format!("wi-fi '{}'", ssid));
+        }
+        if let Some(vpn) = self.vpn {
+            parts.push(if vpn { "vpn connected" } else { "no vpn" }.to_string());
+        }
+        if let Some(monitors) = self.monitors {
+            parts.push(format!("{} monitors", monitors));
+        }
+        if parts.is_empty() { "nothing detected".to_string() } else { parts.join(", ") }
+    }
+}
+
+// The first trigger whose conditions all hold
+pub fn matching<'a>(triggers: &'a [ProfileTrigger], surroundings: &Surroundings) -> Option<&'a ProfileTrigger> {
+    triggers.iter().find(|trigger| surroundings.matches(trigger))
+}
+
+pub struct ProfileTriggers {
+    last_check: Option<Instant>,
+    // Switching only happens when the match changes, so a manual switch-profile sticks until then
+    last_match: Option<String>,
+}
+
+impl ProfileTriggers {
+    // The profile picked at startup counts as the current match
+    pub fn start() -> Self {
+        let mut triggers = ProfileTriggers { last_check: None, last_match: None };
+        triggers.last_match = triggers.evaluate();
+        triggers
+    }
+
+    fn evaluate(&mut self) -> Option<String> {
+        self.last_check = Some(Instant::now());
+        let triggers = match config::load_base() {
+            Ok(config) => config.profile_triggers,
+            Err(e) => {
+                eprintln!("Ignoring profile triggers: {}", e);
+                return None;
+            }
+        };
+        if triggers.is_empty() {
+            return None;
+        }
+        matching(&triggers, &Surroundings::probe(&triggers)).map(|trigger| trigger.profile.clone())
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.last_match.as_deref()
+    }
+
+    // The profile to switch to, when a different trigger started matching
+    pub fn check(&mut self) -> Option<String> {
+        if self.last_check.is_some_and(|last| last.elapsed() < CHECK_INTERVAL) {
+            return None;
+        }
+        let matched = self.evaluate();
+        if matched == self.last_match {
+            return None;
+        }
+        self.last_match = matched.clone();
+        matched.filter(|profile| *profile != crate::profile::name())
+    }
+}
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// "  Name : value" lines printed by netsh and ipconfig
+#[cfg(any(windows, target_os = "macos"))]
+fn field<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == name).then(|| value.trim()).filter(|value| !value.is_empty())
+    })
+}
+
+#[cfg(windows)]
+fn wifi_ssid() -> Option<String> {
+    field(&output("netsh", &["wlan", "show", "interfaces"])?, "SSID").map(str::to_string)
+}
+
+#[cfg(target_os = "macos")]
+fn wifi_ssid() -> Option<String> {
+    field(&output("ipconfig", &["getsummary", "en0"])?, "SSID").map(str::to_string)
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn wifi_ssid() -> Option<String> {
+    if let Some(text) = output("nmcli", &["-t", "-f", "active,ssid", "dev", "wifi"]) {
+        return text.lines().find_map(|line| line.strip_prefix("yes:")).map(|ssid| ssid.replace("\\:", ":"));
+    }
+    output("iwgetid", &["-r"]).map(|ssid| ssid.trim().to_string()).filter(|ssid| !ssid.is_empty())
+}
+
+#[cfg(windows)]
+fn vpn_connected() -> Option<bool> {
+    let count = output("powershell", &[
+        "-NoProfile",
+        "-Command",
+        "@(Get-NetAdapter | Where-Object { $_.Status -eq 'Up' -and $_.InterfaceDescription -match 'VPN|TAP-|WireGuard|Wintun|Tunnel' }).Count",
+    ])?;
+    count.trim().parse::<usize>().ok().map(|count| count > 0)
+}
+
+// Network Extension VPNs, including WireGuard and most corporate clients, show up here
+#[cfg(target_os = "macos")]
+fn vpn_connected() -> Option<bool> {
+    Some(output("scutil", &["--nc", "list"])?.lines().any(|line| line.contains("(Connected)")))
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn vpn_connected() -> Option<bool> {
+    const TUNNELS: [&str; 6] = ["tun", "tap", "wg", "ppp", "tailscale", "nordlynx"];
+    let interfaces = std::fs::read_dir("/sys/class/net").ok()?;
+    Some(interfaces.filter_map(Result::ok).any(|interface| {
+        let name = interface.file_name().to_string_lossy().into_owned();
+        let state = std::fs::read_to_string(interface.path().join("operstate")).unwrap_or_default();
+        TUNNELS.iter().any(|prefix| name.starts_with(prefix)) && state.trim() != "down"
+    }))
+}
+
+#[cfg(windows)]
+fn monitor_count() -> Option<usize> {
+    use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_CMONITORS};
+    let count = unsafe { GetSystemMetrics(SM_CMONITORS) };
+    (count > 0).then_some(count as usize)
+}
+
+#[cfg(target_os = "macos")]
+fn monitor_count() -> Option<usize> {
+    core_graphics::display::CGDisplay::active_displays().ok().map(|displays| displays.len())
+}
+
+// Connected outputs as the kernel sees them, which works without X
+#[cfg(not(any(windows, target_os = "macos")))]
+fn monitor_count() -> Option<usize> {
+    let outputs = std::fs::read_dir("/sys/class/drm").ok()?;
+    Some(outputs.filter_map(Result::ok)
+        .filter(|output| std::fs::read_to_string(output.path().join("status")).is_ok_and(|status| status.trim() == "connected"))
+        .count())
+}
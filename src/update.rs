@@ -0,0 +1,221 @@
+// `self-update`: installs the latest GitHub release of this repo over the running executable. The
+// release carries a binary per platform, named like window_tracker_concept-linux-x86_64, and
+// SHA256SUMS listing their checksums, signed in SHA256SUMS.sig (a base64 Ed25519 signature) with
+// the key whose public half release builds bake in through WT_UPDATE_PUBLIC_KEY. The new binary is
+// written next to the old one and renamed over it, a failed download or check leaves the old one as
+// it was, then a running tracker is asked to restart into it.
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::Engine;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::cli::Args;
+use crate::ipc;
+
+const REPO: &str = "RPDevJesco/window_tracker_concept";
+const CHECKSUMS: &str = "SHA256SUMS";
+const SIGNATURE: &str = "SHA256SUMS.sig";
+// Base64 of the 32-byte Ed25519 public key release signatures are checked with
+const PUBLIC_KEY: Option<&str> = option_env!("WT_UPDATE_PUBLIC_KEY");
+const MAX_BINARY: u64 = 256 * 1024 * 1024;
+const MAX_TEXT: u64 = 1024 * 1024;
+
+struct Release {
+    tag: String,
+    // (name, download url)
+    assets: Vec<(String, String)>,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Result<&str, String> {
+        self.assets.iter().find(|(asset, _)| asset == name).map(|(_, url)| url.as_str())
+            .ok_or_else(|| format!("release {} has no {}", self.tag, name))
+    }
+}
+
+fn get(url: &str, limit: u64) -> Result<Vec<u8>, String> {
+    let response = ureq::get(url)
+        .set("User-Agent", "window_tracker_concept")
+        .timeout(Duration::from_secs(300))
+        .call()
+        .map_err(|e| match e {
+            ureq::Error::Status(code, response) => format!("{} {} from {}", code, response.into_string().unwrap_or_default().trim(), url),
+            other => format!("failed to fetch {}: {}", url, other),
+        })?;
+    let mut body = Vec::new();
+    response.into_reader().take(limit + 1).read_to_end(&mut body).map_err(|e| format!("failed to download {}: {}", url, e))?;
+    if body.len() as u64 > limit {
+        return Err(format!("{} is larger than expected", url));
+    }
+    Ok(body)
+}
+
+fn latest_release() -> Result<Release, String> {
+    let body = get(&format!("https://api.github.com/repos/{}/releases/latest", REPO), MAX_TEXT)?;
+    let release: Value = serde_json::from_slice(&body).map_err(|e| format!("unreadable release: {}", e))?;
+    let tag = release["tag_name"].as_str().ok_or("the release has no tag")?.to_string();
+    let assets = release["assets"].as_array().map(Vec::as_slice).unwrap_or_default().iter()
+        .filter_map(|asset| Some((asset["name"].as_str()?.to_string(), asset["browser_download_url"].as_str()?.to_string())))
+        .collect();
+    Ok(Release { tag, assets })
+}
+
+// The numbers of v1.2.3, anything after a - left out
+fn version(tag: &str) -> Vec<u64> {
+    let tag = tag.trim_start_matches('v');
+    tag.split('-').next().unwrap_or(tag).split('.').map(|part| part.parse().unwrap_or(0)).collect()
+}
+
+fn asset_name() -> String {
+    format!("{}-{}-{}{}", env!("CARGO_PKG_NAME"), std::env::consts::OS, std::env::consts::ARCH, std::env::consts::EXE_SUFFIX)
+}
+
+fn verify_signature(checksums: &[u8], signature: &[u8], public_key: &str) -> Result<(), String> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let key = engine.decode(public_key.trim()).map_err(|e| format!("invalid WT_UPDATE_PUBLIC_KEY: {}", e))?;
+    let signature = engine.decode(String::from_utf8_lossy(signature).trim()).map_err(|e| format!("invalid {}: {}", SIGNATURE, e))?;
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, key)
+        .verify(checksums, &signature)
+        .map_err(|_| format!("{} doesn't match its signature, not updating", CHECKSUMS))
+}
+
+// The hex checksum SHA256SUMS lists for the asset, as sha256sum writes them
+fn listed_checksum(checksums: &str, name: &str) -> Option<String> {
+    checksums.lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, file)| file.trim().trim_start_matches('*') == name)
+        .map(|(checksum, _)| checksum.to_lowercase())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn sibling(executable: &Path, suffix: &str) -> PathBuf {
+    let mut name = executable.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    executable.with_file_name(name)
+}
+
+// Renames the new binary over the old one; Windows won't replace a running executable but lets it
+// be renamed, so it is moved aside first and removed by the next update
+fn replace(executable: &Path, binary: &[u8]) -> Result<(), String> {
+    let new = sibling(executable, ".new");
+    fs::write(&new, binary).map_err(|e| format!("failed to write {}: {}", new.display(), e))?;
+    let permissions = fs::metadata(executable).map_err(|e| format!("failed to read {}: {}", executable.display(), e))?.permissions();
+    fs::set_permissions(&new, permissions).map_err(|e| format!("failed to make {} executable: {}", new.display(), e))?;
+    if cfg!(windows) {
+        let old = sibling(executable, ".old");
+        fs::rename(executable, &old).map_err(|e| format!("failed to move {} aside: {}", executable.display(), e))?;
+        if let Err(e) = fs::rename(&new, executable) {
+            let _ = fs::rename(&old, executable);
+            return Err(format!("failed to install {}: {}", executable.display(), e));
+        }
+        return Ok(());
+    }
+    fs::rename(&new, executable).map_err(|e| {
+        let _ = fs::remove_file(&new);
+        format!("failed to install {}: {}", executable.display(), e)
+    })
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let json_output = args.json_output()?;
+    let executable = std::env::current_exe().map_err(|e| format!("failed to locate the executable: {}", e))?;
+    let _ = fs::remove_file(sibling(&executable, ".old"));
+    let current = env!("CARGO_PKG_VERSION");
+    let release = latest_release()?;
+    let available = version(&release.tag) > version(current);
+    if !available || args.flag("--check") {
+        if json_output {
+            println!("{}", json!({ "current": current, "latest": release.tag, "available": available, "updated": false }));
+        } else if available {
+            println!("{} is available, this is {}; `self-update` installs it", release.tag, current);
+        } else {
+            println!("Up to date ({}), the latest release is {}", current, release.tag);
+        }
+        return Ok(());
+    }
+
+    let name = asset_name();
+    let checksums = get(release.asset(CHECKSUMS)?, MAX_TEXT)?;
+    match PUBLIC_KEY {
+        Some(key) => verify_signature(&checksums, &get(release.asset(SIGNATURE)?, MAX_TEXT)?, key)?,
+        None if args.flag("--unsigned") => eprintln!("This build has no signing key, checking {} against {} alone", name, CHECKSUMS),
+        None => return Err("this build has no signing key to check the release with (WT_UPDATE_PUBLIC_KEY wasn't set when it was built), --unsigned updates on the checksum alone".to_string()),
+    }
+    let expected = listed_checksum(&String::from_utf8_lossy(&checksums), &name)
+        .ok_or_else(|| format!("{} doesn't list {}", CHECKSUMS, name))?;
+    let binary = get(release.asset(&name)?, MAX_BINARY)?;
+    if hex(&Sha256::digest(&binary)) != expected {
+        return Err(format!("{} doesn't match its checksum, not updating", name));
+    }
+    replace(&executable, &binary)?;
+    let restarted = ipc::request("restart", json!({ "executable": executable }));
+    if json_output {
+        let (pid, error) = match &restarted {
+            Ok(response) => (response.as_ref().map(|response| response["pid"].clone()), None),
+            Err(e) => (None, Some(e.as_str())),
+        };
+        println!("{}", json!({
+            "current": current,
+            "latest": release.tag,
+            "available": true,
+            "updated": true,
+            "executable": executable,
+            "restarted_pid": pid,
+            "restart_error": error,
+        }));
+        return Ok(());
+    }
+    println!("Updated {} from {} to {}", executable.display(), current, release.tag);
+    match restarted {
+        Ok(Some(response)) => println!("Restarted the running tracker (pid {})", response["pid"]),
+        Ok(None) => println!("No tracker is running, the next one started runs {}", release.tag),
+        Err(e) => println!("The running tracker couldn't be restarted ({}), restart it to run {}", e, release.tag),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    fn key_pair() -> (Ed25519KeyPair, String) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let public = base64::engine::general_purpose::STANDARD.encode(pair.public_key().as_ref());
+        (pair, public)
+    }
+
+    fn sign(pair: &Ed25519KeyPair, checksums: &[u8]) -> Vec<u8> {
+        base64::engine::general_purpose::STANDARD.encode(pair.sign(checksums).as_ref()).into_bytes()
+    }
+
+    #[test]
+    fn only_checksums_signed_with_the_key_are_accepted() {
+        let (pair, public) = key_pair();
+        let checksums = b"0123abcd  window_tracker_concept-linux-x86_64\n";
+        let signature = sign(&pair, checksums);
+        assert_eq!(verify_signature(checksums, &signature, &public), Ok(()));
+
+        let tampered = b"ffffffff  window_tracker_concept-linux-x86_64\n";
+        assert!(verify_signature(tampered, &signature, &public).unwrap_err().contains("doesn't match its signature"));
+        let (other, _) = key_pair();
+        assert!(verify_signature(checksums, &sign(&other, checksums), &public).is_err());
+        assert!(verify_signature(checksums, b"not base64!", &public).unwrap_err().contains(SIGNATURE));
+        assert!(verify_signature(checksums, b"", &public).is_err());
+    }
+
+    #[test]
+    fn the_checksum_is_the_one_listed_for_the_asset() {
+        let checksums = "AAAA  window_tracker_concept-linux-x86_64.old\nBBBB *window_tracker_concept-linux-x86_64\n";
+        assert_eq!(listed_checksum(checksums, "window_tracker_concept-linux-x86_64").as_deref(), Some("bbbb"));
+        assert_eq!(listed_checksum(checksums, "window_tracker_concept-macos-aarch64"), None);
+    }
+}
@@ -0,0 +1,251 @@
+// `verify`: checks that the stored history still adds up, with no interval ending before it starts
+// or after now, none overlapping another and no day holding more time than it has, and with
+// --repair fixes what doesn't. Visible and floating windows overlap by nature and aren't checked.
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde_json::json;
+
+use crate::cli::Args;
+use crate::humanize;
+use crate::report::{daily_chunks, local_midnight};
+use crate::storage::{self, Interval, Rewritten};
+
+// Listed per check in text output, the rest is counted
+const SHOWN: usize = 10;
+
+struct Violation {
+    check: &'static str,
+    detail: String,
+}
+
+// How the interval is described in violations
+fn describe(interval: &Interval) -> String {
+    format!("'{}' ({}) from {} to {}", interval.title, interval.app_name(), interval.start.to_rfc3339(), interval.end.to_rfc3339())
+}
+
+fn secs(duration: Duration) -> f64 {
+    duration.num_milliseconds() as f64 / 1000.0
+}
+
+// Each interval as --repair would store it, None to delete it, in the order they were loaded
+fn repair(intervals: &[Interval], now: DateTime<Utc>, violations: &mut Vec<Violation>) -> Vec<Option<Interval>> {
+    let mut order: Vec<usize> = (0..intervals.len()).collect();
+    // The longest of intervals starting together is kept whole
+    order.sort_by_key(|&index| (intervals[index].start, Reverse(intervals[index].end)));
+
+    let mut repaired = vec![None; intervals.len()];
+    let mut covered_until = DateTime::<Utc>::MIN_UTC;
+    for index in order {
+        let interval = &intervals[index];
+        if interval.end < interval.start {
+            violations.push(Violation { check: "backwards", detail: format!("{} ends before it starts", describe(interval)) });
+            continue;
+        }
+        let mut fixed = interval.clone();
+        if fixed.end > now {
+            violations.push(Violation { check: "future", detail: format!("{} ends in the future", describe(interval)) });
+            fixed.end = now;
+        }
+        if fixed.start < covered_until && fixed.end > fixed.start {
+            let twice = fixed.end.min(covered_until) - fixed.start;
+            violations.push(Violation {
+                check: "overlap",
+                detail: format!("{} overlaps earlier intervals by {}", describe(interval), humanize::duration(secs(twice))),
+            });
+            fixed.start = covered_until;
+        }
+        if fixed.end <= fixed.start && interval.end > interval.start {
+            // Nothing left of it
+            continue;
+        }
+        covered_until = covered_until.max(fixed.end);
+        repaired[index] = Some(fixed);
+    }
+    repaired
+}
+
+// Days with more time recorded than they have, which overlaps or intervals from a clock gone
+// wrong lead to. A day changing to or from daylight saving time has 23 or 25 hours.
+fn long_days(intervals: &[Interval], violations: &mut Vec<Violation>) {
+    let mut days: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    for interval in intervals.iter().filter(|interval| interval.end > interval.start) {
+        for (day, secs) in daily_chunks(interval) {
+            *days.entry(day).or_default() += secs;
+        }
+    }
+    for (day, total) in days {
+        let length = day.succ_opt().map_or(Duration::days(1), |next| local_midnight(next) - local_midnight(day)).max(Duration::days(1));
+        if total > secs(length) + 1.0 {
+            violations.push(Violation {
+                check: "long_day",
+                detail: format!("{} has {} recorded in {}", day, humanize::duration(total), humanize::duration(secs(length))),
+            });
+        }
+    }
+}
+
+// Replaces the stored intervals with their repaired versions, leaving the rest as they are
+fn store_repaired(intervals: &[Interval], repaired: Vec<Option<Interval>>) -> Result<Rewritten, String> {
+    // The stored intervals come back through rewrite_intervals one by one, identical copies
+    // of one interval take their fixed versions in turn
+    let mut fixes: HashMap<String, VecDeque<Option<Interval>>> = HashMap::new();
+    for (interval, fixed) in intervals.iter().zip(repaired) {
+        let key = serde_json::to_string(interval).map_err(|e| e.to_string())?;
+        fixes.entry(key).or_default().push_back(fixed);
+    }
+    let fixes = RefCell::new(fixes);
+    storage::rewrite_intervals(|interval| {
+        let Ok(key) = serde_json::to_string(interval) else { return Some(interval.clone()) };
+        // Stored since the history was read, by a running tracker
+        match fixes.borrow_mut().get_mut(&key).and_then(VecDeque::pop_front) {
+            Some(fixed) => fixed,
+            None => Some(interval.clone()),
+        }
+    }).map_err(|e| format!("failed to repair history: {}", e))
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    let json_output = args.json_output()?;
+    let repair_requested = args.flag("--repair");
+    let now = Utc::now();
+    let intervals = storage::load_intervals(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC)
+        .map_err(|e| format!("failed to read history: {}", e))?;
+
+    let mut violations = Vec::new();
+    let repaired = repair(&intervals, now, &mut violations);
+    long_days(&intervals, &mut violations);
+
+    let rewritten = if repair_requested && !violations.is_empty() {
+        Some(store_repaired(&intervals, repaired)?)
+    } else {
+        None
+    };
+
+    if json_output {
+        let listed: Vec<serde_json::Value> = violations.iter()
+            .map(|violation| json!({ "check": violation.check, "detail": violation.detail }))
+            .collect();
+        println!("{}", json!({
+            "intervals": intervals.len(),
+            "violations": listed,
+            "repaired": rewritten.is_some(),
+            "removed": rewritten.map_or(0, |rewritten| rewritten.removed),
+            "changed": rewritten.map_or(0, |rewritten| rewritten.changed),
+        }));
+    } else {
+        println!("Checked {} intervals", intervals.len());
+        for (check, description) in [
+            ("backwards", "intervals ending before they start"),
+            ("future", "intervals ending in the future"),
+            ("overlap", "overlapping intervals"),
+            ("long_day", "days with more time than they have"),
+        ] {
+            let found: Vec<&Violation> = violations.iter().filter(|violation| violation.check == check).collect();
+            if found.is_empty() {
+                println!("[ok] no {}", description);
+                continue;
+            }
+            println!("[{}] {}", found.len(), description);
+            for violation in found.iter().take(SHOWN) {
+                println!("  {}", violation.detail);
+            }
+            if found.len() > SHOWN {
+                println!("  and {} more", found.len() - SHOWN);
+            }
+        }
+        if let Some(rewritten) = rewritten {
+            println!("Deleted {} intervals and shortened {}", rewritten.removed, rewritten.changed);
+        }
+    }
+
+    if !violations.is_empty() && !repair_requested {
+        return Err(format!("found {} problems, pass --repair to fix them", violations.len()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs)
+    }
+
+    fn checks(violations: &[Violation]) -> Vec<&str> {
+        violations.iter().map(|violation| violation.check).collect()
+    }
+
+    #[test]
+    fn overlaps_are_cut_and_backwards_intervals_deleted() {
+        let intervals = vec![
+            Interval::new("Valid", "code", at(0), at(600)),
+            // Starts inside Valid: what is left starts where Valid ends
+            Interval::new("Overlapping", "code", at(300), at(900)),
+            // Inside what is already covered, nothing is left
+            Interval::new("Inside", "code", at(100), at(200)),
+            Interval::new("Backwards", "code", at(2000), at(1500)),
+            Interval::new("Later", "code", at(3000), at(3600)),
+        ];
+        let now = DateTime::<Utc>::from(at(10_000));
+        let mut violations = Vec::new();
+        let repaired = repair(&intervals, now, &mut violations);
+        assert_eq!(checks(&violations), ["overlap", "overlap", "backwards"]);
+        let spans: Vec<Option<(DateTime<Utc>, DateTime<Utc>)>> = repaired.iter().map(|fixed| fixed.as_ref().map(|fixed| (fixed.start, fixed.end))).collect();
+        let span = |from, to| Some((DateTime::<Utc>::from(at(from)), DateTime::<Utc>::from(at(to))));
+        assert_eq!(spans, [span(0, 600), span(600, 900), None, None, span(3000, 3600)]);
+        // The valid ones are untouched
+        assert_eq!(repaired[0].as_ref(), Some(&intervals[0]));
+        assert_eq!(repaired[4].as_ref(), Some(&intervals[4]));
+    }
+
+    #[test]
+    fn intervals_ending_after_now_end_now() {
+        let intervals = vec![Interval::new("Clock ahead", "code", at(0), at(7200))];
+        let mut violations = Vec::new();
+        let repaired = repair(&intervals, DateTime::<Utc>::from(at(3600)), &mut violations);
+        assert_eq!(checks(&violations), ["future"]);
+        assert_eq!(repaired[0].as_ref().map(|fixed| fixed.end), Some(DateTime::<Utc>::from(at(3600))));
+    }
+
+    // The JSON lines file is where lines can be cut short
+    #[cfg(not(feature = "sqlite"))]
+    #[test]
+    fn repair_leaves_valid_and_unreadable_lines_as_they_were() {
+        let (_turn, _base) = storage::testing::data_dir();
+        let valid = Interval::new("Valid", "code", at(0), at(600));
+        storage::append_intervals(&[
+            valid.clone(),
+            Interval::new("Overlapping", "code", at(300), at(900)),
+            Interval::new("Backwards", "code", at(2000), at(1500)),
+        ]).unwrap();
+        let history = storage::data_dir().join("intervals.jsonl");
+        let truncated = r#"{"title":"Cut short","app":"code","start":"#;
+        let mut text = std::fs::read_to_string(&history).unwrap();
+        let valid_line = text.lines().next().unwrap().to_string();
+        text.push_str(truncated);
+        text.push('\n');
+        std::fs::write(&history, text).unwrap();
+
+        let intervals = storage::load_intervals(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC).unwrap();
+        assert_eq!(intervals.len(), 3);
+        let mut violations = Vec::new();
+        let repaired = repair(&intervals, DateTime::<Utc>::from(at(10_000)), &mut violations);
+        let rewritten = store_repaired(&intervals, repaired).unwrap();
+        assert_eq!((rewritten.removed, rewritten.changed), (1, 1));
+
+        let text = std::fs::read_to_string(&history).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], valid_line);
+        assert_eq!(lines[2], truncated);
+        let stored = storage::load_intervals(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC).unwrap();
+        assert_eq!(stored[0], valid);
+        assert_eq!((stored[1].title.as_str(), stored[1].start, stored[1].end), ("Overlapping", DateTime::<Utc>::from(at(600)), DateTime::<Utc>::from(at(900))));
+    }
+}
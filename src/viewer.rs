@@ -0,0 +1,243 @@
+// `serve --read-only --public <folder>`: shares reports made beforehand, like a `report --html`
+// year in review, over HTTP without the tracker behind them. Only GET and HEAD of the files in the
+// folder are answered, and only of the kinds reports are shared as (HTML, CSS, images, PDF): there
+// is no API, no control and nothing read from the history, so a history file or a JSON or CSV
+// export dropped in the folder by mistake is still not served. Hidden files and anything outside the folder, symlinks
+// included, are not found; a folder without index.html lists what it serves.
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::api::decoded;
+use crate::cli::Args;
+use crate::config;
+use crate::storage;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9480";
+const MAX_REQUEST_BYTES: usize = 8192;
+const INDEX: &str = "index.html";
+// What is served, by extension
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("ico", "image/x-icon"),
+    ("pdf", "application/pdf"),
+];
+
+fn content_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    CONTENT_TYPES.iter().find(|(known, _)| *known == extension).map(|(_, content_type)| *content_type)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Where a folder is found from the outside: its path under root, each segment percent-encoded, so
+// the redirect to it can't send the browser to another host
+fn location(root: &Path, directory: &Path) -> String {
+    let mut location = String::from("/");
+    for segment in directory.strip_prefix(root).unwrap_or(Path::new("")).iter() {
+        for byte in segment.to_string_lossy().bytes() {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+                location.push(byte as char);
+            } else {
+                location.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        location.push('/');
+    }
+    location
+}
+
+// The file a request path names inside root, None for anything hidden, outside it or not served
+fn resolve(root: &Path, target: &str) -> Option<PathBuf> {
+    // A + in a path is itself, unlike in a query
+    let path = decoded(&target.replace('+', "%2B"));
+    let mut resolved = root.to_path_buf();
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        if segment.starts_with('.') || segment.contains('\\') || segment.contains('\0') {
+            return None;
+        }
+        resolved.push(segment);
+    }
+    let resolved = resolved.canonicalize().ok()?;
+    if !resolved.starts_with(root) {
+        return None;
+    }
+    if resolved.is_dir() {
+        return Some(resolved);
+    }
+    content_type(&resolved).map(|_| resolved)
+}
+
+// The served files and folders of a folder without index.html
+fn listing(root: &Path, directory: &Path) -> String {
+    let mut names: Vec<String> = fs::read_dir(directory).map(|entries| {
+        entries.filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let path = entry.path();
+                if name.starts_with('.') || !path.canonicalize().is_ok_and(|path| path.starts_with(root)) {
+                    None
+                } else if path.is_dir() {
+                    Some(format!("{}/", name))
+                } else {
+                    content_type(&path).map(|_| name)
+                }
+            })
+            .collect()
+    }).unwrap_or_default();
+    names.sort();
+    let title = format!("/{}", directory.strip_prefix(root).unwrap_or(directory).to_string_lossy());
+    let items: String = names.iter()
+        .map(|name| format!("<li><a href=\"{}\">{}</a></li>\n", escape_html(name), escape_html(name)))
+        .collect();
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head>\n<body><h1>{0}</h1>\n<ul>\n{1}</ul></body></html>\n",
+        escape_html(&title),
+        items
+    )
+}
+
+fn answer(connection: &TcpStream, root: &Path) -> io::Result<()> {
+    let mut reader = BufReader::new(connection);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Headers are read past, nothing in them changes the answer
+    let mut total = request.len();
+    loop {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line)?;
+        total += read;
+        if read == 0 || line.trim_end().is_empty() || total > MAX_REQUEST_BYTES {
+            break;
+        }
+    }
+
+    let mut parts = request.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    let mut headers = String::new();
+    let (status, content_type, body) = match (method, resolve(root, path)) {
+        ("GET" | "HEAD", Some(file)) if file.is_dir() && !path.ends_with('/') => {
+            headers.push_str(&format!("Location: {}\r\n", location(root, &file)));
+            ("301 Moved Permanently", "text/plain; charset=utf-8", Vec::new())
+        }
+        ("GET" | "HEAD", Some(file)) if file.is_dir() => match fs::read(file.join(INDEX)) {
+            Ok(bytes) => ("200 OK", "text/html; charset=utf-8", bytes),
+            Err(_) => ("200 OK", "text/html; charset=utf-8", listing(root, &file).into_bytes()),
+        },
+        ("GET" | "HEAD", Some(file)) => match fs::read(&file) {
+            Ok(bytes) => ("200 OK", content_type(&file).unwrap_or("application/octet-stream"), bytes),
+            Err(_) => ("404 Not Found", "text/plain; charset=utf-8", b"Not found\n".to_vec()),
+        },
+        ("GET" | "HEAD", None) => ("404 Not Found", "text/plain; charset=utf-8", b"Not found\n".to_vec()),
+        _ => {
+            headers.push_str("Allow: GET, HEAD\r\n");
+            ("405 Method Not Allowed", "text/plain; charset=utf-8", b"This server is read-only\n".to_vec())
+        }
+    };
+    let mut stream = connection;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n{}Content-Type: {}\r\nContent-Length: {}\r\nX-Content-Type-Options: nosniff\r\nReferrer-Policy: no-referrer\r\nConnection: close\r\n\r\n",
+        status, headers, content_type, body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(&body)?;
+    }
+    stream.flush()
+}
+
+// A folder holding the history or the config would share it
+fn check_root(root: &Path) -> Result<(), String> {
+    let private = [storage::base_data_dir(), config::base_config_path().parent().map(Path::to_path_buf).unwrap_or_default()];
+    for private in private.iter().filter_map(|path| path.canonicalize().ok()) {
+        if root.starts_with(&private) || private.starts_with(root) {
+            return Err(format!("serving {} would share the history or the config in {}", root.display(), private.display()));
+        }
+    }
+    Ok(())
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    if !args.flag("--read-only") {
+        return Err("serve only shares reports made beforehand, pass --read-only --public <folder>; the live API is the [health] address".to_string());
+    }
+    let folder = args.value("--public").ok_or("serve --read-only needs --public <folder> with the reports to share")?;
+    let root = Path::new(folder).canonicalize().map_err(|e| format!("failed to open {}: {}", folder, e))?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a folder", folder));
+    }
+    check_root(&root)?;
+    let address = args.value("--address").unwrap_or(DEFAULT_ADDRESS);
+    let listener = TcpListener::bind(address).map_err(|e| format!("failed to listen on {}: {}", address, e))?;
+    println!("Sharing {} read-only on http://{}/", root.display(), address);
+    for connection in listener.incoming().filter_map(Result::ok) {
+        let root = root.clone();
+        thread::spawn(move || {
+            let _ = connection.set_read_timeout(Some(Duration::from_secs(10)));
+            let _ = answer(&connection, &root);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_outside_the_folder_is_resolved() {
+        let base = std::env::temp_dir().join(format!("wt-viewer-{}", std::process::id()));
+        let root = base.join("shared");
+        fs::create_dir_all(root.join("2024")).unwrap();
+        fs::create_dir_all(root.join("evil.example")).unwrap();
+        fs::create_dir_all(root.join("year in review")).unwrap();
+        for (path, text) in [("2024/review.html", "<p>"), (".private.html", "<p>"), ("intervals.jsonl", "{}"), ("totals.csv", "a,b"), ("totals.json", "{}")] {
+            fs::write(root.join(path), text).unwrap();
+        }
+        fs::write(base.join("secret.html"), "<p>").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(base.join("secret.html"), root.join("link.html")).unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let resolved = |target: &str| resolve(&root, target);
+        assert_eq!(resolved("/"), Some(root.clone()));
+        assert_eq!(resolved("/2024/review.html"), Some(root.join("2024/review.html")));
+        for target in [
+            "/../secret.html",
+            "/2024/../../secret.html",
+            "/%2e%2e/secret.html",
+            "/2024/..%2f..%2fsecret.html",
+            "/..%5csecret.html",
+            "/2024%5c..%5c..%5csecret.html",
+            "/2024/review.html%00.png",
+            "/.private.html",
+            "/intervals.jsonl",
+            "/totals.csv",
+            "/totals.json",
+            "/link.html",
+        ] {
+            assert_eq!(resolved(target), None, "{}", target);
+        }
+
+        // A folder asked for without its slash is redirected on this host, never to //evil.example/
+        let redirect = |target: &str| resolved(target).map(|folder| location(&root, &folder));
+        assert_eq!(redirect("//evil.example").as_deref(), Some("/evil.example/"));
+        assert_eq!(redirect("/2024").as_deref(), Some("/2024/"));
+        assert_eq!(redirect("/year%20in%20review").as_deref(), Some("/year%20in%20review/"));
+        let _ = fs::remove_dir_all(&base);
+    }
+}
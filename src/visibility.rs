@@ -0,0 +1,207 @@
+// `track --visible`: every window on screen, not just the focused one, kept in visible.jsonl as
+// intervals of its own. Reports compare that against focus time to show apps left open in the background.
+// Always-on-top windows, like picture-in-picture video, are recorded the same way into floating.jsonl
+// on every track, reported as media running alongside the focused window.
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+
+use crate::storage::{self, Interval, Rewritten};
+use crate::ActiveWindow;
+
+#[derive(Clone, Copy)]
+pub enum Layer {
+    Visible,
+    Floating,
+}
+
+impl Layer {
+    fn path(self) -> PathBuf {
+        storage::data_dir().join(match self {
+            Layer::Visible => "visible.jsonl",
+            Layer::Floating => "floating.jsonl",
+        })
+    }
+
+    fn windows(self) -> Vec<ActiveWindow> {
+        let query = match self {
+            Layer::Visible => crate::platform::get_visible_windows,
+            Layer::Floating => crate::platform::get_floating_windows,
+        };
+        crate::sampler::run("The visible windows query", move || query().into_iter().map(crate::privacy::strip).collect())
+            .unwrap_or_default()
+    }
+}
+
+fn append(layer: Layer, interval: &Interval) -> io::Result<()> {
+    let path = layer.path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", storage::encode(interval)?)
+}
+
+// The layer's intervals overlapping [from, to), clipped to it
+pub fn load(layer: Layer, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Interval>, String> {
+    let text = match fs::read_to_string(layer.path()) {
+        Ok(text) => text,
+        Err(_) => return Ok(Vec::new()),
+    };
+    Ok(text.lines()
+        .filter_map(|line| storage::decode(line).ok())
+        .filter(|interval| interval.end > from && interval.start < to)
+        .map(|interval| Interval { start: interval.start.max(from), end: interval.end.min(to), ..interval })
+        .collect())
+}
+
+pub fn rewrite(layer: Layer, keep: impl Fn(&Interval) -> Option<Interval>) -> io::Result<Rewritten> {
+    storage::rewrite_interval_file(&layer.path(), keep)
+}
+
+// The layer's windows on screen since the last sample, keyed like the tracker's window records
+pub struct VisibilityWatch {
+    layer: Layer,
+    open: HashMap<(String, String), (ActiveWindow, SystemTime)>,
+}
+
+impl VisibilityWatch {
+    pub fn new(layer: Layer) -> Self {
+        VisibilityWatch { layer, open: HashMap::new() }
+    }
+
+    pub fn check(&mut self, now: SystemTime) {
+        if crate::wt_is_paused() {
+            self.finish(now);
+            return;
+        }
+
+        let visible: HashMap<(String, String), ActiveWindow> = self.layer.windows().into_iter()
+            .filter_map(crate::wt_filter_window)
+            .map(|window| ((window.key(), window.title.clone()), window))
+            .collect();
+
+        let gone: Vec<(String, String)> = self.open.keys().filter(|key| !visible.contains_key(*key)).cloned().collect();
+        for key in gone {
+            if let Some((window, since)) = self.open.remove(&key) {
+                store(self.layer, &window, since, now);
+            }
+        }
+        for (key, window) in visible {
+            self.open.entry(key).or_insert((window, now));
+        }
+    }
+
+    // Stores whatever is still on screen, at the end of a session or on pause
+    pub fn finish(&mut self, now: SystemTime) {
+        for (_, (window, since)) in self.open.drain() {
+            store(self.layer, &window, since, now);
+        }
+    }
+}
+
+fn store(layer: Layer, window: &ActiveWindow, since: SystemTime, until: SystemTime) {
+    let mut interval = Interval::new(&window.title, &window.app, since, until);
+    interval.window_id = window.key();
+    interval.attributes = crate::wt_title_fields(window);
+    interval.category = crate::wt_categorize(window);
+    if interval.duration_secs() > 0.0 {
+        if let Err(e) = append(layer, &interval) {
+            eprintln!("Failed to record visible window: {}", e);
+        }
+    }
+}
+
+type Span = (DateTime<Utc>, DateTime<Utc>);
+
+pub struct BackgroundApp {
+    pub app: String,
+    // On screen at all, counting overlapping windows of the app once
+    pub visible_secs: f64,
+    pub active_secs: f64,
+}
+
+impl BackgroundApp {
+    pub fn background_secs(&self) -> f64 {
+        (self.visible_secs - self.active_secs).max(0.0)
+    }
+}
+
+// Per app, how long it was on screen against how long it had focus, most background time first
+pub fn background_apps(visible: &[Interval], focused: &[Interval]) -> Vec<BackgroundApp> {
+    let mut spans: HashMap<&str, Vec<Span>> = HashMap::new();
+    for interval in visible {
+        spans.entry(interval.app_name()).or_default().push((interval.start, interval.end));
+    }
+    let mut active: HashMap<&str, f64> = HashMap::new();
+    for interval in focused {
+        *active.entry(interval.app_name()).or_insert(0.0) += interval.duration_secs();
+    }
+
+    let mut apps: Vec<BackgroundApp> = spans.into_iter()
+        .map(|(app, spans)| BackgroundApp {
+            app: app.to_string(),
+            visible_secs: union_secs(spans),
+            active_secs: active.get(app).copied().unwrap_or(0.0),
+        })
+        .collect();
+    apps.sort_by(|a, b| b.background_secs().total_cmp(&a.background_secs()).then_with(|| a.app.cmp(&b.app)));
+    apps
+}
+
+fn union_secs(mut spans: Vec<Span>) -> f64 {
+    spans.sort();
+    let mut total = 0.0;
+    let mut current: Option<Span> = None;
+    for (start, end) in spans {
+        match &mut current {
+            Some((_, current_end)) if start <= *current_end => *current_end = (*current_end).max(end),
+            _ => {
+                if let Some((from, to)) = current {
+                    total += (to - from).num_milliseconds() as f64 / 1000.0;
+                }
+                current = Some((start, end));
+            }
+        }
+    }
+    if let Some((from, to)) = current {
+        total += (to - from).num_milliseconds() as f64 / 1000.0;
+    }
+    total
+}
+
+pub struct ConcurrentMedia {
+    pub app: String,
+    pub title: String,
+    // Floating while another window had focus
+    pub secs: f64,
+}
+
+// Per floating window, the time it played on top of something else: the time it had focus itself
+// doesn't count. Longest first.
+pub fn concurrent_media(floating: &[Interval], focused: &[Interval]) -> Vec<ConcurrentMedia> {
+    let mut focus_spans: HashMap<&str, Vec<Span>> = HashMap::new();
+    for interval in focused.iter().filter(|interval| !interval.window_id.is_empty()) {
+        focus_spans.entry(&interval.window_id).or_default().push((interval.start, interval.end));
+    }
+
+    let mut totals: HashMap<(&str, &str), f64> = HashMap::new();
+    for interval in floating {
+        let focused_secs: f64 = focus_spans.get(interval.window_id.as_str()).into_iter().flatten()
+            .map(|(start, end)| (*end.min(&interval.end) - *start.max(&interval.start)).num_milliseconds().max(0) as f64 / 1000.0)
+            .sum();
+        let secs = (interval.duration_secs() - focused_secs).max(0.0);
+        *totals.entry((interval.app_name(), &interval.title)).or_insert(0.0) += secs;
+    }
+
+    let mut media: Vec<ConcurrentMedia> = totals.into_iter()
+        .filter(|(_, secs)| *secs > 0.0)
+        .map(|((app, title), secs)| ConcurrentMedia { app: app.to_string(), title: title.to_string(), secs })
+        .collect();
+    media.sort_by(|a, b| b.secs.total_cmp(&a.secs).then_with(|| a.title.cmp(&b.title)));
+    media
+}
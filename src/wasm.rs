@@ -0,0 +1,66 @@
+// Report queries for the browser: the same rollups as the report and export commands, run over
+// history exported as JSON (an array of intervals, or intervals.jsonl as is).
+use chrono::{DateTime, Local, NaiveDate};
+use wasm_bindgen::prelude::*;
+
+use crate::heatmap;
+use crate::report::{self, GroupBy};
+use crate::storage::{self, Interval};
+
+// Each interval goes through storage::decode, so older versions are upgraded and newer ones refused
+fn parse_intervals(history: &str) -> Result<Vec<Interval>, JsError> {
+    if history.trim_start().starts_with('[') {
+        let values: Vec<serde_json::Value> = serde_json::from_str(history).map_err(|e| JsError::new(&format!("invalid history: {}", e)))?;
+        return values.iter()
+            .enumerate()
+            .map(|(index, value)| {
+                storage::decode(&value.to_string()).map_err(|e| JsError::new(&format!("invalid history at interval {}: {}", index + 1, e)))
+            })
+            .collect();
+    }
+
+    history.lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            storage::decode(line).map_err(|e| JsError::new(&format!("invalid history on line {}: {}", index + 1, e)))
+        })
+        .collect()
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Local>, JsError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Local))
+        .map_err(|_| JsError::new(&format!("invalid time '{}', expected RFC 3339", value)))
+}
+
+fn parse_day(value: &str) -> Result<NaiveDate, JsError> {
+    value.parse().map_err(|_| JsError::new(&format!("invalid day '{}', expected YYYY-MM-DD", value)))
+}
+
+// The report for `from`..`to` as JSON. Include earlier history to get anomalies against a baseline.
+#[wasm_bindgen]
+pub fn summarize(history: &str, from: &str, to: &str, group_by: &str) -> Result<String, JsError> {
+    let history = parse_intervals(history)?;
+    let group_by = GroupBy::parse(group_by).map_err(|e| JsError::new(&e))?;
+    let summary = report::build(&history, parse_time(from)?, parse_time(to)?, group_by);
+    Ok(report::render_json(&summary))
+}
+
+// The category heatmap as JSON or SVG; `categories` is a comma separated filter, empty for all.
+#[wasm_bindgen]
+pub fn heatmap(history: &str, first_day: &str, last_day: &str, categories: &str, format: &str) -> Result<String, JsError> {
+    let history = parse_intervals(history)?;
+    let (first_day, last_day) = (parse_day(first_day)?, parse_day(last_day)?);
+    if first_day > last_day {
+        return Err(JsError::new("the first day is after the last day"));
+    }
+
+    let filter: Vec<&str> = categories.split(',').map(str::trim).filter(|name| !name.is_empty()).collect();
+    let heatmap = heatmap::from_intervals(&history, first_day, last_day, (!filter.is_empty()).then_some(filter.as_slice()));
+    match format {
+        "json" => Ok(heatmap::render_json(&heatmap)),
+        "svg" => Ok(heatmap::render_svg(&heatmap)),
+        other => Err(JsError::new(&format!("unknown heatmap format '{}', expected 'svg' or 'json'", other))),
+    }
+}
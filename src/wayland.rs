@@ -0,0 +1,196 @@
+// The focused and visible windows of a Wayland session. Wayland lets no client see another's
+// windows, so they come from the compositor's own IPC: swaymsg on sway, hyprctl on Hyprland, asked
+// once per sample. Other compositors answer None and the X11 backend, where it is built, sees the
+// XWayland windows. Idle time isn't asked here, without X11 there is none.
+use std::process::Command;
+
+use serde_json::Value;
+
+use crate::doctor::{Check, Status};
+use crate::ActiveWindow;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compositor {
+    Sway,
+    Hyprland,
+}
+
+// The compositor of this session, if it is one with IPC this knows
+fn compositor() -> Option<Compositor> {
+    std::env::var_os("WAYLAND_DISPLAY")?;
+    if std::env::var_os("SWAYSOCK").is_some() {
+        Some(Compositor::Sway)
+    } else if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        Some(Compositor::Hyprland)
+    } else {
+        None
+    }
+}
+
+fn query(program: &str, args: &[&str]) -> Option<Value> {
+    let output = Command::new(program).args(args).output().ok().filter(|output| output.status.success())?;
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+fn process_name(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok().map(|name| name.trim_end().to_string())
+}
+
+fn window(title: &str, class: &str, id: u64, pid: u32) -> ActiveWindow {
+    ActiveWindow {
+        title: title.to_string(),
+        app: process_name(pid).unwrap_or_default(),
+        class: class.to_string(),
+        id,
+        pid,
+    }
+}
+
+// Every node of sway's tree, tiled and floating
+fn sway_nodes(node: &Value) -> Vec<&Value> {
+    let children = ["nodes", "floating_nodes"].into_iter().filter_map(|key| node[key].as_array()).flatten();
+    std::iter::once(node).chain(children.flat_map(sway_nodes)).collect()
+}
+
+// Containers holding a window rather than other containers
+fn is_sway_window(node: &Value) -> bool {
+    matches!(node["type"].as_str(), Some("con" | "floating_con")) && node["pid"].as_u64().is_some()
+}
+
+fn sway_window(node: &Value) -> ActiveWindow {
+    // Native Wayland windows have an app_id, XWayland ones the X11 class
+    let class = node["app_id"].as_str().or(node["window_properties"]["class"].as_str()).unwrap_or_default();
+    window(node["name"].as_str().unwrap_or_default(), class, node["id"].as_u64().unwrap_or(0), node["pid"].as_u64().unwrap_or(0) as u32)
+}
+
+// The focused node is the workspace itself when no window on it has focus
+fn sway_focused(tree: &Value) -> ActiveWindow {
+    sway_nodes(tree).into_iter()
+        .find(|node| node["focused"].as_bool() == Some(true) && is_sway_window(node))
+        .map_or_else(ActiveWindow::none, sway_window)
+}
+
+// Sticky windows stay on screen across workspaces, like pinned video
+fn sway_visible(tree: &Value, sticky_only: bool) -> Vec<ActiveWindow> {
+    sway_nodes(tree).into_iter()
+        .filter(|node| is_sway_window(node) && node["visible"].as_bool() == Some(true))
+        .filter(|node| !sticky_only || node["sticky"].as_bool() == Some(true))
+        .map(sway_window)
+        .collect()
+}
+
+fn hyprland_window(client: &Value) -> Option<ActiveWindow> {
+    let address = client["address"].as_str()?;
+    let id = u64::from_str_radix(address.trim_start_matches("0x"), 16).ok()?;
+    Some(window(client["title"].as_str().unwrap_or_default(), client["class"].as_str().unwrap_or_default(), id, client["pid"].as_u64().unwrap_or(0) as u32))
+}
+
+// hyprctl answers {} when nothing has focus
+fn hyprland_focused(active: &Value) -> ActiveWindow {
+    hyprland_window(active).unwrap_or_else(ActiveWindow::none)
+}
+
+// The clients on a workspace some monitor shows, only pinned ones with `pinned_only`
+fn hyprland_visible(clients: &Value, monitors: &Value, pinned_only: bool) -> Vec<ActiveWindow> {
+    let shown: Vec<i64> = monitors.as_array().into_iter().flatten().filter_map(|monitor| monitor["activeWorkspace"]["id"].as_i64()).collect();
+    clients.as_array().into_iter().flatten()
+        .filter(|client| client["mapped"].as_bool() != Some(false) && client["hidden"].as_bool() != Some(true))
+        .filter(|client| client["workspace"]["id"].as_i64().is_some_and(|id| shown.contains(&id)) || client["pinned"].as_bool() == Some(true))
+        .filter(|client| !pinned_only || client["pinned"].as_bool() == Some(true))
+        .filter_map(hyprland_window)
+        .collect()
+}
+
+// None outside a compositor this knows, or when its IPC doesn't answer
+pub fn get_active_window() -> Option<ActiveWindow> {
+    match compositor()? {
+        Compositor::Sway => query("swaymsg", &["-t", "get_tree"]).map(|tree| sway_focused(&tree)),
+        Compositor::Hyprland => query("hyprctl", &["activewindow", "-j"]).map(|active| hyprland_focused(&active)),
+    }
+}
+
+fn windows(floating_only: bool) -> Option<Vec<ActiveWindow>> {
+    match compositor()? {
+        Compositor::Sway => query("swaymsg", &["-t", "get_tree"]).map(|tree| sway_visible(&tree, floating_only)),
+        Compositor::Hyprland => {
+            let monitors = query("hyprctl", &["monitors", "-j"])?;
+            query("hyprctl", &["clients", "-j"]).map(|clients| hyprland_visible(&clients, &monitors, floating_only))
+        }
+    }
+}
+
+pub fn get_visible_windows() -> Option<Vec<ActiveWindow>> {
+    windows(false)
+}
+
+// Sticky (sway) or pinned (Hyprland) windows, what comes closest to always on top there
+pub fn get_floating_windows() -> Option<Vec<ActiveWindow>> {
+    windows(true)
+}
+
+pub fn checks() -> Vec<Check> {
+    if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return Vec::new();
+    }
+    let (name, program) = match compositor() {
+        Some(Compositor::Sway) => ("sway", "swaymsg"),
+        Some(Compositor::Hyprland) => ("Hyprland", "hyprctl"),
+        None => return vec![Check::new("wayland", Status::Warning, "Wayland session of a compositor without supported IPC (sway or Hyprland), only XWayland windows can be seen through X11")],
+    };
+    vec![match get_active_window() {
+        Some(_) => Check::new("wayland", Status::Ok, format!("{} session, windows are asked with {}", name, program)),
+        None => Check::new("wayland", Status::Failed, format!("{} session but {} didn't answer, is it on PATH?", name, program)),
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn titles(windows: &[ActiveWindow]) -> Vec<&str> {
+        windows.iter().map(|window| window.title.as_str()).collect()
+    }
+
+    #[test]
+    fn sway_tree_gives_the_focused_and_visible_windows() {
+        let tree = json!({ "type": "root", "nodes": [{ "type": "output", "nodes": [
+            { "type": "workspace", "focused": false, "nodes": [
+                { "type": "con", "id": 7, "pid": 1, "name": "README.md - code", "app_id": "code", "focused": true, "visible": true },
+                { "type": "con", "id": 8, "pid": 1, "name": "Terminal", "window_properties": { "class": "XTerm" }, "visible": true, "nodes": [] },
+            ], "floating_nodes": [
+                { "type": "floating_con", "id": 9, "pid": 1, "name": "Picture-in-Picture", "app_id": "firefox", "visible": true, "sticky": true },
+            ] },
+            { "type": "workspace", "nodes": [{ "type": "con", "id": 10, "pid": 1, "name": "Elsewhere", "visible": false }] },
+        ] }] });
+        let focused = sway_focused(&tree);
+        assert_eq!((focused.title.as_str(), focused.class.as_str(), focused.id), ("README.md - code", "code", 7));
+        assert_eq!(titles(&sway_visible(&tree, false)), ["README.md - code", "Terminal", "Picture-in-Picture"]);
+        assert_eq!(sway_visible(&tree, false)[1].class, "XTerm");
+        assert_eq!(titles(&sway_visible(&tree, true)), ["Picture-in-Picture"]);
+
+        // An empty workspace focused is nothing focused
+        let empty = json!({ "type": "root", "nodes": [{ "type": "workspace", "focused": true, "nodes": [] }] });
+        assert!(sway_focused(&empty).is_placeholder());
+    }
+
+    #[test]
+    fn hyprland_clients_on_shown_workspaces_are_visible() {
+        let active = json!({ "address": "0x5d2a", "pid": 1, "class": "firefox", "title": "News" });
+        let focused = hyprland_focused(&active);
+        assert_eq!((focused.title.as_str(), focused.class.as_str(), focused.id), ("News", "firefox", 0x5d2a));
+        assert!(hyprland_focused(&json!({})).is_placeholder());
+
+        let monitors = json!([{ "activeWorkspace": { "id": 1 } }, { "activeWorkspace": { "id": 4 } }]);
+        let clients = json!([
+            { "address": "0x1", "pid": 1, "title": "News", "workspace": { "id": 1 } },
+            { "address": "0x2", "pid": 1, "title": "Chat", "workspace": { "id": 2 } },
+            { "address": "0x3", "pid": 1, "title": "Video", "workspace": { "id": 3 }, "pinned": true },
+            { "address": "0x4", "pid": 1, "title": "Hidden", "workspace": { "id": 4 }, "hidden": true },
+            { "address": "0x5", "pid": 1, "title": "Unmapped", "workspace": { "id": 4 }, "mapped": false },
+        ]);
+        assert_eq!(titles(&hyprland_visible(&clients, &monitors, false)), ["News", "Video"]);
+        assert_eq!(titles(&hyprland_visible(&clients, &monitors, true)), ["Video"]);
+    }
+}
@@ -0,0 +1,176 @@
+// [websocket]: the --stream events pushed to WebSocket clients as text messages the moment they
+// happen, for dashboards and overlays that would otherwise poll. Each connection gets its own
+// thread; clients only listen, anything they send besides ping and close is ignored.
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use base64::Engine;
+use sha1::{Digest, Sha1};
+
+use crate::config::WebSocketConfig;
+use crate::events::FocusEvent;
+use crate::storage::EventKind;
+use crate::stream;
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const MAX_HEADER_BYTES: usize = 8192;
+// Pings find clients that went away without closing
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+const TEXT: u8 = 0x1;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+
+pub fn start(config: &WebSocketConfig) -> Result<(), String> {
+    let address: SocketAddr = config.address.parse()
+        .map_err(|_| format!("invalid websocket address '{}', expected e.g. 127.0.0.1:7878", config.address))?;
+    let listener = TcpListener::bind(address).map_err(|e| format!("failed to listen on {}: {}", address, e))?;
+    let origins = config.origins.clone();
+    thread::spawn(move || {
+        for connection in listener.incoming().filter_map(Result::ok) {
+            let origins = origins.clone();
+            thread::spawn(move || {
+                // Clients vanishing is normal, there is no one to report it to
+                let _ = serve(connection, &origins);
+            });
+        }
+    });
+    Ok(())
+}
+
+fn serve(connection: TcpStream, origins: &[String]) -> io::Result<()> {
+    let mut reader = BufReader::new(connection.try_clone()?);
+    let mut writer = connection;
+    let headers = read_headers(&mut reader)?;
+    let header = |name: &str| headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str());
+
+    if header("Origin").is_some_and(|origin| !origins.iter().any(|allowed| allowed == origin)) {
+        return writer.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    }
+    let Some(key) = header("Sec-WebSocket-Key").filter(|_| header("Upgrade").is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket")))
+    else {
+        return writer.write_all(b"HTTP/1.1 426 Upgrade Required\r\nUpgrade: websocket\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    };
+    let accept = base64::engine::general_purpose::STANDARD.encode(Sha1::digest(format!("{}{}", key.trim(), ACCEPT_GUID)));
+    write!(writer, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept)?;
+
+    let events = stream::subscribe();
+    let writer = Arc::new(Mutex::new(writer));
+    // What has focus right now, so a client doesn't start out blank
+    if let Some(window) = crate::wt_get_current_window() {
+        let mut current = serde_json::to_value(FocusEvent::new(EventKind::FocusChanged, &window)).unwrap();
+        current["event"] = "current".into();
+        current["paused"] = crate::wt_is_paused().into();
+        write_frame(&writer, TEXT, current.to_string().as_bytes())?;
+    }
+
+    let reader_writer = Arc::clone(&writer);
+    let client = thread::spawn(move || read_frames(&mut reader, &reader_writer));
+    loop {
+        if client.is_finished() {
+            return Ok(());
+        }
+        let (opcode, payload) = match events.recv_timeout(PING_INTERVAL) {
+            Ok(line) => (TEXT, line.into_bytes()),
+            Err(RecvTimeoutError::Timeout) => (PING, Vec::new()),
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        write_frame(&writer, opcode, &payload)?;
+    }
+}
+
+// The request line and headers up to the blank line
+fn read_headers(reader: &mut impl BufRead) -> io::Result<Vec<(String, String)>> {
+    let mut headers = Vec::new();
+    let mut total = 0;
+    loop {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line)?;
+        total += read;
+        if read == 0 || total > MAX_HEADER_BYTES {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "incomplete or oversized request"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(headers);
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+}
+
+// Answers pings and close frames until the client closes or the connection drops
+fn read_frames(reader: &mut impl Read, writer: &Mutex<TcpStream>) -> io::Result<()> {
+    loop {
+        let mut head = [0u8; 2];
+        reader.read_exact(&mut head)?;
+        let opcode = head[0] & 0x0F;
+        let masked = head[1] & 0x80 != 0;
+        let length = match head[1] & 0x7F {
+            126 => {
+                let mut bytes = [0u8; 2];
+                reader.read_exact(&mut bytes)?;
+                u64::from(u16::from_be_bytes(bytes))
+            }
+            127 => {
+                let mut bytes = [0u8; 8];
+                reader.read_exact(&mut bytes)?;
+                u64::from_be_bytes(bytes)
+            }
+            length => u64::from(length),
+        };
+        // Control frames are small, large data frames from a client are skipped unread
+        if length > 125 && opcode >= CLOSE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "oversized control frame"));
+        }
+        let mut mask = [0u8; 4];
+        if masked {
+            reader.read_exact(&mut mask)?;
+        }
+        if opcode < CLOSE {
+            io::copy(&mut reader.take(length), &mut io::sink())?;
+            continue;
+        }
+        let mut payload = vec![0u8; length as usize];
+        reader.read_exact(&mut payload)?;
+        if masked {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+        match opcode {
+            CLOSE => {
+                write_frame(writer, CLOSE, &payload)?;
+                return writer.lock().unwrap().shutdown(Shutdown::Both);
+            }
+            PING => write_frame(writer, PONG, &payload)?,
+            _ => {}
+        }
+    }
+}
+
+// Server frames are never masked or fragmented
+fn write_frame(writer: &Mutex<TcpStream>, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        length if length < 126 => frame.push(length as u8),
+        length if length <= usize::from(u16::MAX) => {
+            frame.push(126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    let mut writer = writer.lock().unwrap();
+    writer.write_all(&frame)?;
+    writer.flush()
+}